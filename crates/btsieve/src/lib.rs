@@ -2,6 +2,7 @@ use bitcoin::Script;
 use bitcoin::Txid;
 use std::collections::hash_map::Entry;
 use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::fmt;
 
@@ -26,15 +27,42 @@ impl<E> State<E> {
     }
 
     /// Returns all scripts that we are currently monitoring.
+    ///
+    /// Each script is only returned once, even if several transactions are monitored on it.
     pub fn monitoring_scripts(&self) -> impl Iterator<Item = &Script> + Clone {
-        self.awaiting_status.keys().map(|(_, script)| script)
+        let scripts = self
+            .awaiting_status
+            .keys()
+            .map(|(_, script)| script)
+            .collect::<BTreeSet<_>>();
+
+        scripts.into_iter().collect::<Vec<_>>().into_iter()
+    }
+
+    /// Monitor a transaction for the given status.
+    ///
+    /// Registering the same status and event for a transaction more than once has no effect.
+    pub fn monitor(&mut self, txid: Txid, script: Script, script_status: ScriptStatus, event: E)
+    where
+        E: PartialEq,
+    {
+        let awaiting = self.awaiting_status.entry((txid, script)).or_default();
+
+        if awaiting
+            .iter()
+            .any(|(status, e)| *status == script_status && *e == event)
+        {
+            return;
+        }
+
+        awaiting.push((script_status, event));
     }
 
-    pub fn monitor(&mut self, txid: Txid, script: Script, script_status: ScriptStatus, event: E) {
-        self.awaiting_status
-            .entry((txid, script))
-            .or_default()
-            .push((script_status, event));
+    /// Returns the status of a monitored transaction as of the last update.
+    ///
+    /// `None` if the transaction is not monitored (anymore) or was not updated yet.
+    pub fn status(&self, txid: Txid, script: &Script) -> Option<ScriptStatus> {
+        self.current_status.get(&(txid, script.clone())).copied()
     }
 }
 
@@ -210,7 +238,7 @@ impl Confirmed {
         Self { depth }
     }
 
-    fn confirmations(&self) -> u32 {
+    pub fn confirmations(&self) -> u32 {
         self.depth + 1
     }
 }
@@ -257,6 +285,12 @@ impl From<usize> for BlockHeight {
     }
 }
 
+impl From<BlockHeight> for u32 {
+    fn from(height: BlockHeight) -> Self {
+        height.0
+    }
+}
+
 impl fmt::Display for BlockHeight {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.0)
@@ -315,6 +349,54 @@ mod tests {
         assert_eq!(ready_events, vec![baz_expired]);
     }
 
+    #[test]
+    fn scripts_monitored_for_several_transactions_are_returned_once() {
+        let mut state = State::new(BlockHeight(0));
+        state.monitor(
+            txid1(),
+            script1(),
+            ScriptStatus::InMempool,
+            Event::FooFinality,
+        );
+        state.monitor(
+            txid2(),
+            script1(),
+            ScriptStatus::InMempool,
+            Event::BarFinality,
+        );
+
+        assert_eq!(state.monitoring_scripts().count(), 1);
+    }
+
+    #[test]
+    fn monitoring_the_same_event_twice_only_emits_it_once() {
+        let foo_finality = Event::FooFinality;
+
+        let mut state = State::new(BlockHeight(0));
+        state.monitor(
+            txid1(),
+            script1(),
+            ScriptStatus::with_confirmations(1),
+            foo_finality,
+        );
+        state.monitor(
+            txid1(),
+            script1(),
+            ScriptStatus::with_confirmations(1),
+            foo_finality,
+        );
+
+        let ready_events = state.update(
+            BlockHeight(10),
+            vec![vec![TxStatus {
+                height: 5,
+                tx_hash: txid1(),
+            }]],
+        );
+
+        assert_eq!(ready_events, vec![foo_finality]);
+    }
+
     #[test]
     fn update_for_a_script_only_results_in_event_for_corresponding_transaction() {
         let _guard = tracing_subscriber::fmt()
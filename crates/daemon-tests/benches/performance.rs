@@ -0,0 +1,214 @@
+//! Performance benchmarks of the protocols between maker and taker.
+//!
+//! Every scenario starts fresh maker and taker actor systems with in-memory databases and mocked
+//! wallet, oracle and monitor, connected through the in-memory transport of libp2p. The timings
+//! therefore do not depend on the network stack of the host. Only the protocol under test is
+//! timed, starting the actor systems and preparing the CFDs is not.
+//!
+//! Run with `cargo bench -p daemon-tests`, optionally followed by a filter on the scenario name,
+//! e.g. `cargo bench -p daemon-tests -- rollover`.
+
+use daemon::N_PAYOUTS;
+use daemon_tests::flow::next_with;
+use daemon_tests::initial_price_for;
+use daemon_tests::maia::olivia::btc_example_0;
+use daemon_tests::open_cfd;
+use daemon_tests::rollover::rollover;
+use daemon_tests::Maker;
+use daemon_tests::MakerConfig;
+use daemon_tests::OfferParamsBuilder;
+use daemon_tests::OpenCfdArgs;
+use daemon_tests::Taker;
+use daemon_tests::TakerConfig;
+use futures::future::join_all;
+use model::ContractSymbol;
+use model::Price;
+use rust_decimal::Decimal;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Number of offers published in the offer publication scenario
+const OFFER_SAMPLES: usize = 100;
+
+/// Number of times each contract setup and rollover scenario is repeated
+const PROTOCOL_SAMPLES: usize = 3;
+
+/// Numbers of CETs per DLC the protocols are benchmarked with
+const N_PAYOUTS_CHOICES: [usize; 3] = [N_PAYOUTS / 4, N_PAYOUTS / 2, N_PAYOUTS];
+
+/// Numbers of maker and taker pairs running the protocol at the same time
+const CONCURRENT_CFDS_CHOICES: [usize; 3] = [1, 4, 8];
+
+#[tokio::main]
+async fn main() {
+    let filter = std::env::args().skip(1).find(|arg| !arg.starts_with("--"));
+    let is_selected = |scenario: &str| {
+        filter
+            .as_ref()
+            .map_or(true, |filter| scenario.contains(filter.as_str()))
+    };
+
+    if is_selected("offer_publication") {
+        offer_publication().await.report();
+    }
+
+    for n_payouts in N_PAYOUTS_CHOICES {
+        for n_cfds in CONCURRENT_CFDS_CHOICES {
+            let scenario = format!("contract_setup/n_payouts={n_payouts}/cfds={n_cfds}");
+            if is_selected(&scenario) {
+                contract_setup(scenario, n_payouts, n_cfds).await.report();
+            }
+
+            let scenario = format!("rollover/n_payouts={n_payouts}/cfds={n_cfds}");
+            if is_selected(&scenario) {
+                rollover_cfds(scenario, n_payouts, n_cfds).await.report();
+            }
+        }
+    }
+}
+
+/// Time from setting the offer parameters in the maker until the taker received the offer.
+async fn offer_publication() -> Samples {
+    let symbol = ContractSymbol::BtcUsd;
+    let mut samples = Samples::new("offer_publication", 1);
+
+    let (mut maker, mut taker) = start_pairs(N_PAYOUTS, 1).await.remove(0);
+
+    for i in 0..OFFER_SAMPLES {
+        // Change the price with every offer to be able to tell the offers apart on the taker side
+        let price = Price::new(initial_price_for(symbol).into_decimal() + Decimal::from(i + 1))
+            .expect("valid price");
+
+        let start = Instant::now();
+
+        maker
+            .set_offer_params(OfferParamsBuilder::new(symbol).price(price).build())
+            .await;
+        next_with(taker.offers_feed(), |offers| {
+            offers.btcusd_short.filter(|offer| offer.price == price)
+        })
+        .await
+        .expect("taker to receive offer");
+
+        samples.push(start.elapsed());
+    }
+
+    samples
+}
+
+/// Time to set up `n_cfds` CFDs at the same time, each between a different pair.
+async fn contract_setup(scenario: String, n_payouts: usize, n_cfds: usize) -> Samples {
+    let mut samples = Samples::new(scenario, n_cfds);
+
+    for _ in 0..PROTOCOL_SAMPLES {
+        let mut pairs = start_pairs(n_payouts, n_cfds).await;
+
+        let start = Instant::now();
+
+        join_all(
+            pairs
+                .iter_mut()
+                .map(|(maker, taker)| open_cfd(taker, maker, OpenCfdArgs::default())),
+        )
+        .await;
+
+        samples.push(start.elapsed());
+    }
+
+    samples
+}
+
+/// Time to roll over `n_cfds` CFDs at the same time, each between a different pair.
+async fn rollover_cfds(scenario: String, n_payouts: usize, n_cfds: usize) -> Samples {
+    let mut samples = Samples::new(scenario, n_cfds);
+
+    for _ in 0..PROTOCOL_SAMPLES {
+        let mut pairs = start_pairs(n_payouts, n_cfds).await;
+
+        let order_ids = join_all(
+            pairs
+                .iter_mut()
+                .map(|(maker, taker)| open_cfd(taker, maker, OpenCfdArgs::default())),
+        )
+        .await;
+
+        let start = Instant::now();
+
+        join_all(
+            pairs
+                .iter_mut()
+                .zip(order_ids)
+                .map(|((maker, taker), order_id)| {
+                    rollover(maker, taker, order_id, btc_example_0())
+                }),
+        )
+        .await;
+
+        samples.push(start.elapsed());
+    }
+
+    samples
+}
+
+async fn start_pairs(n_payouts: usize, n_pairs: usize) -> Vec<(Maker, Taker)> {
+    let mut pairs = Vec::with_capacity(n_pairs);
+
+    for _ in 0..n_pairs {
+        let maker = Maker::start(
+            &MakerConfig::default()
+                .with_n_payouts(n_payouts)
+                .with_memory_transport(),
+        )
+        .await;
+        let taker = Taker::start(
+            &TakerConfig::default().with_n_payouts(n_payouts),
+            maker.identity,
+            maker.connect_addr.clone(),
+        )
+        .await;
+
+        pairs.push((maker, taker));
+    }
+
+    pairs
+}
+
+/// Durations measured for a scenario
+struct Samples {
+    scenario: String,
+    /// Number of operations completed within each sample
+    operations_per_sample: usize,
+    durations: Vec<Duration>,
+}
+
+impl Samples {
+    fn new(scenario: impl Into<String>, operations_per_sample: usize) -> Self {
+        Self {
+            scenario: scenario.into(),
+            operations_per_sample,
+            durations: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, duration: Duration) {
+        self.durations.push(duration);
+    }
+
+    fn report(mut self) {
+        self.durations.sort();
+
+        let percentile = |p: usize| self.durations[(self.durations.len() - 1) * p / 100];
+        let total = self.durations.iter().sum::<Duration>();
+        let throughput =
+            (self.durations.len() * self.operations_per_sample) as f64 / total.as_secs_f64();
+
+        println!(
+            "{:<40} median {:>10.3?}  p90 {:>10.3?}  max {:>10.3?}  {:>8.2} ops/s",
+            self.scenario,
+            percentile(50),
+            percentile(90),
+            percentile(100),
+            throughput,
+        );
+    }
+}
@@ -24,6 +24,7 @@ use daemon::projection::FeedReceivers;
 use daemon::projection::MakerOffers;
 use daemon::seed::RandomSeed;
 use daemon::seed::Seed;
+use daemon::service_status::MakerServiceStatus;
 use daemon::Environment;
 use daemon::N_PAYOUTS;
 use maia::olivia::btc_example_0;
@@ -32,6 +33,7 @@ use maker::cfd::OfferParams;
 use model::libp2p::PeerId;
 use model::olivia::Announcement;
 use model::olivia::BitMexPriceEventId;
+use model::trading_calendar::TradingCalendar;
 use model::CfdEvent;
 use model::CompleteFee;
 use model::ContractSymbol;
@@ -40,11 +42,13 @@ use model::Dlc;
 use model::EventKind;
 use model::FeeAccount;
 use model::FundingFee;
+use model::FundingPeriod;
 use model::FundingRate;
 use model::Identity;
 use model::Leverage;
 use model::LotSize;
 use model::OpeningFee;
+use model::OpeningFeeTiers;
 use model::OrderId;
 use model::Position;
 use model::Price;
@@ -56,7 +60,6 @@ use rust_decimal_macros::dec;
 use std::collections::HashSet;
 use std::net::IpAddr;
 use std::net::Ipv4Addr;
-use std::net::SocketAddr;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -563,17 +566,43 @@ pub struct MakerConfig {
     oracle_pk: XOnlyPublicKey,
     seed: RandomSeed,
     n_payouts: usize,
-    libp2p_port: u16,
+    libp2p_listen: Multiaddr,
     blocked_peers: HashSet<xtra_libp2p::libp2p::PeerId>,
 }
 
+impl MakerConfig {
+    pub fn with_n_payouts(self, n_payouts: usize) -> Self {
+        Self { n_payouts, ..self }
+    }
+
+    /// Listen on the in-memory transport of libp2p instead of the loopback interface.
+    ///
+    /// Only takers in the same process can connect. Requires the `memory-transport` feature of
+    /// the daemon, which the benchmarks enable.
+    pub fn with_memory_transport(self) -> Self {
+        // On port 0 the transport would pick a port the taker does not know about
+        let port = rand::random::<u64>().max(1);
+
+        Self {
+            libp2p_listen: format!("/memory/{port}")
+                .parse()
+                .expect("to parse properly"),
+            ..self
+        }
+    }
+}
+
 impl Default for MakerConfig {
     fn default() -> Self {
         Self {
             oracle_pk: oracle_pk(),
             seed: RandomSeed::default(),
             n_payouts: N_PAYOUTS,
-            libp2p_port: portpicker::pick_unused_port().expect("to be able to find a free port"),
+            libp2p_listen: daemon::libp2p_utils::create_listen_tcp_multiaddr(
+                &IpAddr::V4(Ipv4Addr::LOCALHOST),
+                portpicker::pick_unused_port().expect("to be able to find a free port"),
+            )
+            .expect("to parse properly"),
             blocked_peers: HashSet::new(),
         }
     }
@@ -586,6 +615,12 @@ pub struct TakerConfig {
     n_payouts: usize,
 }
 
+impl TakerConfig {
+    pub fn with_n_payouts(self, n_payouts: usize) -> Self {
+        Self { n_payouts, ..self }
+    }
+}
+
 impl Default for TakerConfig {
     fn default() -> Self {
         Self {
@@ -601,7 +636,7 @@ pub struct Maker {
     pub system: maker::ActorSystem<OracleActor, WalletActor>,
     pub mocks: mocks::Mocks,
     pub feeds: FeedReceivers,
-    pub listen_addr: SocketAddr,
+    pub listen_addr: Multiaddr,
     pub identity: Identity,
     /// The address on which taker can dial in with libp2p protocols (includes
     /// maker's PeerId)
@@ -658,8 +693,6 @@ impl Maker {
 
     #[instrument(name = "Start maker", skip_all)]
     pub async fn start(config: &MakerConfig) -> Self {
-        let address = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), config.libp2p_port);
-
         let db = sqlite_db::memory().await.unwrap();
 
         let (wallet, wallet_mock) = WalletActor::new();
@@ -678,14 +711,13 @@ impl Maker {
 
         let identities = config.seed.derive_identities();
 
-        let (projection_actor, projection_context) = xtra::Context::new(None);
+        let (projection_actor, projection_context) =
+            xtra::Context::new(Some(projection::MAILBOX_CAPACITY));
 
         let mut monitor_mock = None;
         let mut oracle_mock = None;
 
-        let endpoint_listen =
-            daemon::libp2p_utils::create_listen_tcp_multiaddr(&address.ip(), address.port())
-                .expect("to parse properly");
+        let endpoint_listen = config.libp2p_listen.clone();
 
         let maker = maker::ActorSystem::new(
             db.clone(),
@@ -709,6 +741,22 @@ impl Maker {
             identities.clone(),
             endpoint_listen.clone(),
             config.blocked_peers.clone(),
+            Vec::new(),
+            None,
+            None,
+            None,
+            vec![],
+            None,
+            None,
+            None,
+            daemon::order::setup_queue::DEFAULT_MAX_CONCURRENT_SETUPS,
+            Default::default(),
+            None,
+            None,
+            // The balance of the mocked wallet is unrelated to the offers of the tests
+            true,
+            None,
+            None,
         )
         .unwrap();
 
@@ -734,7 +782,7 @@ impl Maker {
             system: maker,
             feeds: feed_receivers,
             identity: model::Identity::new(identities.identity_pk),
-            listen_addr: address,
+            listen_addr: endpoint_listen.clone(),
             mocks,
             _tasks: tasks,
             connect_addr: create_connect_multiaddr(&endpoint_listen, &identities.peer_id().inner())
@@ -748,13 +796,17 @@ impl Maker {
             price_short,
             min_quantity,
             max_quantity,
+            max_contracts_per_order,
             tx_fee_rate,
             funding_rate_long,
             funding_rate_short,
+            funding_period,
             opening_fee,
+            opening_fee_tiers,
             leverage_choices,
             contract_symbol,
             lot_size,
+            trading_calendar,
         } = offer_params;
         self.system
             .set_offer_params(
@@ -762,13 +814,17 @@ impl Maker {
                 price_short,
                 min_quantity,
                 max_quantity,
+                max_contracts_per_order,
                 tx_fee_rate,
                 funding_rate_long,
                 funding_rate_short,
+                funding_period,
                 opening_fee,
+                opening_fee_tiers,
                 leverage_choices,
                 contract_symbol,
                 lot_size,
+                trading_calendar,
             )
             .await
             .unwrap();
@@ -856,6 +912,12 @@ impl Taker {
         &mut self.system.maker_online_status_feed_receiver
     }
 
+    pub fn maker_service_status_feed(
+        &mut self,
+    ) -> &mut watch::Receiver<Option<MakerServiceStatus>> {
+        &mut self.system.maker_service_status_feed_receiver
+    }
+
     #[instrument(name = "Start taker", skip_all)]
     pub async fn start(
         config: &TakerConfig,
@@ -877,7 +939,8 @@ impl Taker {
             let _ = price_feed_fut.await;
         });
 
-        let (projection_actor, projection_context) = xtra::Context::new(None);
+        let (projection_actor, projection_context) =
+            xtra::Context::new(Some(projection::MAILBOX_CAPACITY));
 
         let mut oracle_mock = None;
         let mut monitor_mock = None;
@@ -906,7 +969,17 @@ impl Taker {
             projection_actor,
             maker_identity,
             maker_multiaddr.clone(),
+            vec![],
+            daemon::maker_selection::Preferences::default(),
             Environment::new("test"),
+            false,
+            vec![],
+            None,
+            None,
+            None,
+            None,
+            daemon::intents::DEFAULT_VALIDITY,
+            None,
         )
         .unwrap();
 
@@ -1038,14 +1111,18 @@ impl OfferParamsBuilder {
             price_short: Some(dummy_price),
             min_quantity: Contracts::new(100),
             max_quantity: Contracts::new(1000),
+            max_contracts_per_order: None,
             tx_fee_rate: TxFeeRate::default(),
             // 8.76% annualized = rate of 0.0876 annualized = rate of 0.00024 daily
             funding_rate_long: FundingRate::new(dec!(0.00024)).unwrap(),
             funding_rate_short: FundingRate::new(dec!(0.00024)).unwrap(),
+            funding_period: FundingPeriod::Hourly,
             opening_fee: OpeningFee::new(Amount::from_sat(2)),
+            opening_fee_tiers: OpeningFeeTiers::default(),
             leverage_choices: vec![Leverage::TWO],
             contract_symbol: symbol,
             lot_size: lot_size_for(symbol),
+            trading_calendar: None,
         })
     }
 
@@ -1062,6 +1139,25 @@ impl OfferParamsBuilder {
         self
     }
 
+    pub fn funding_rate(mut self, funding_rate: FundingRate) -> Self {
+        self.0.funding_rate_long = funding_rate;
+        self.0.funding_rate_short = funding_rate;
+
+        self
+    }
+
+    pub fn funding_period(mut self, funding_period: FundingPeriod) -> Self {
+        self.0.funding_period = funding_period;
+
+        self
+    }
+
+    pub fn trading_calendar(mut self, trading_calendar: TradingCalendar) -> Self {
+        self.0.trading_calendar = Some(trading_calendar);
+
+        self
+    }
+
     pub fn build(self) -> OfferParams {
         self.0
     }
@@ -55,7 +55,7 @@ impl OliviaData {
         let ids = model::olivia::hourly_events(
             id.timestamp(),
             id.timestamp() + 24.hours(),
-            id.index_price(),
+            id.contract_symbol(),
         )
         .unwrap();
 
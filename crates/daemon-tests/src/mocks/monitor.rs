@@ -41,6 +41,8 @@ impl MonitorActor {
 
     async fn handle(&mut self, _: monitor::MonitorCollaborativeSettlement) {}
 
+    async fn handle(&mut self, _: monitor::MonitorPendingCollaborativeSettlement) {}
+
     async fn handle(&mut self, _: monitor::TryBroadcastTransaction) -> Result<()> {
         Ok(())
     }
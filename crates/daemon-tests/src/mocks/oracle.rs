@@ -1,6 +1,7 @@
 use crate::maia::olivia::btc_example_0;
 use async_trait::async_trait;
 use daemon::command;
+use daemon::janitor;
 use daemon::oracle;
 use model::olivia;
 use model::olivia::BitMexPriceEventId;
@@ -56,6 +57,10 @@ impl OracleActor {
     async fn handle(&mut self, _msg: oracle::SyncAnnouncements) {}
 
     async fn handle(&mut self, _msg: oracle::SyncAttestations) {}
+
+    async fn handle(&mut self, _msg: janitor::CollectOrphans) -> usize {
+        0
+    }
 }
 
 pub struct MockOracle {
@@ -8,6 +8,7 @@ use daemon::bdk::bitcoin::Txid;
 use daemon::bdk::wallet::tx_builder::TxOrdering;
 use daemon::bdk::wallet::AddressIndex;
 use daemon::bdk::FeeRate;
+use daemon::janitor;
 use daemon::maia_core::PartyParams;
 use daemon::maia_core::TxBuilderExt;
 use daemon::wallet;
@@ -25,7 +26,13 @@ pub struct WalletActor {
 
 impl WalletActor {
     pub fn new() -> (WalletActor, Arc<Mutex<MockWallet>>) {
-        let mock = Arc::new(Mutex::new(MockWallet::new()));
+        let mut wallet = MockWallet::new();
+        // The maker checks its liquidity when takers reserve it for orders
+        wallet
+            .expect_get_unlocked_balance()
+            .returning(|_| Ok(Amount::from_btc(1.4).unwrap()));
+
+        let mock = Arc::new(Mutex::new(wallet));
         let actor = Self { mock: mock.clone() };
 
         (actor, mock)
@@ -56,6 +63,15 @@ impl WalletActor {
     async fn handle(&mut self, msg: wallet::ImportSeed) -> Result<bdk::wallet::AddressInfo> {
         self.mock.lock().await.import_seed(msg)
     }
+    async fn handle(&mut self, msg: wallet::GetUnlockedBalance) -> Result<Amount> {
+        self.mock.lock().await.get_unlocked_balance(msg)
+    }
+    async fn handle(&mut self, msg: wallet::ConsolidateUtxos) -> Result<Option<Txid>> {
+        self.mock.lock().await.consolidate_utxos(msg)
+    }
+    async fn handle(&mut self, _msg: janitor::CollectOrphans) -> usize {
+        0
+    }
 }
 
 #[automock]
@@ -79,6 +95,14 @@ pub trait Wallet {
     fn import_seed(&mut self, _msg: wallet::ImportSeed) -> Result<bdk::wallet::AddressInfo> {
         unreachable!("mockall will reimplement this method")
     }
+
+    fn get_unlocked_balance(&mut self, _msg: wallet::GetUnlockedBalance) -> Result<Amount> {
+        unreachable!("mockall will reimplement this method")
+    }
+
+    fn consolidate_utxos(&mut self, _msg: wallet::ConsolidateUtxos) -> Result<Option<Txid>> {
+        unreachable!("mockall will reimplement this method")
+    }
 }
 
 pub fn build_party_params(msg: wallet::BuildPartyParams) -> Result<PartyParams> {
@@ -0,0 +1,149 @@
+//! Protocols under injected faults.
+//!
+//! Faults are configured globally, hence these tests live in their own test binary and all
+//! scenarios are run sequentially within a single test. Run with `--features chaos`.
+#![cfg(feature = "chaos")]
+
+use daemon::projection::CfdState;
+use daemon_tests::confirm;
+use daemon_tests::flow::cfd_with_state;
+use daemon_tests::flow::next_with;
+use daemon_tests::flow::one_cfd_with_state;
+use daemon_tests::maia::olivia::btc_example_0;
+use daemon_tests::mock_oracle_announcements;
+use daemon_tests::mock_quotes;
+use daemon_tests::open_cfd;
+use daemon_tests::rollover::rollover;
+use daemon_tests::start_both;
+use daemon_tests::wait_next_state;
+use daemon_tests::OpenCfdArgs;
+use daemon_tests::Taker;
+use model::ContractSymbol;
+use model::OrderId;
+use model::Position;
+use otel_tests::otel_test;
+use std::time::Duration;
+use xtra_libp2p::chaos;
+use xtra_libp2p::chaos::Faults;
+
+#[otel_test]
+async fn protocols_recover_from_injected_faults() {
+    // Contract setup succeeds despite delayed substreams and duplicated messages
+    chaos::set_faults(Faults {
+        max_delay: Duration::from_millis(200),
+        duplicate_probability: 1.0,
+        ..Faults::default()
+    });
+
+    let (mut maker, mut taker) = start_both().await;
+    let cfd_args = OpenCfdArgs::default();
+    let order_id = open_cfd(&mut taker, &mut maker, cfd_args.clone()).await;
+
+    // A rollover whose substream is dropped fails and leaves the CFD open
+    chaos::set_faults(Faults {
+        drop_probability: 1.0,
+        protocols: vec!["/itchysats/rollover".to_owned()],
+        ..Faults::default()
+    });
+
+    let commit_txid_before_failed_rollover = taker.latest_commit_txid();
+    mock_oracle_announcements(&mut maker, &mut taker, btc_example_0().announcements()).await;
+    taker.cfd_feed().borrow_and_update();
+    taker
+        .trigger_rollover_with_latest_dlc_params(order_id)
+        .await;
+
+    wait_taker_state(&mut taker, order_id, CfdState::Open).await;
+    assert_eq!(
+        commit_txid_before_failed_rollover,
+        taker.latest_commit_txid(),
+        "Failed rollover must not change the DLC"
+    );
+
+    // The rollover can be retried and succeeds despite duplicated messages
+    chaos::set_faults(Faults {
+        duplicate_probability: 1.0,
+        protocols: vec!["/itchysats/rollover".to_owned()],
+        ..Faults::default()
+    });
+    rollover(&mut maker, &mut taker, order_id, btc_example_0()).await;
+
+    // A collaborative settlement whose substream is dropped fails and leaves the CFD open
+    chaos::set_faults(Faults {
+        drop_probability: 1.0,
+        protocols: vec!["/itchysats/collab-settlement".to_owned()],
+        ..Faults::default()
+    });
+
+    mock_quotes(&mut maker, &mut taker, cfd_args.contract_symbol).await;
+    taker.cfd_feed().borrow_and_update();
+    taker.system.propose_settlement(order_id).await.unwrap();
+
+    wait_taker_state(&mut taker, order_id, CfdState::Open).await;
+
+    // The collaborative settlement can be retried and succeeds despite delayed substreams and
+    // duplicated messages
+    chaos::set_faults(Faults {
+        max_delay: Duration::from_millis(200),
+        duplicate_probability: 1.0,
+        protocols: vec!["/itchysats/collab-settlement".to_owned()],
+        ..Faults::default()
+    });
+
+    taker.system.propose_settlement(order_id).await.unwrap();
+
+    wait_next_state!(
+        order_id,
+        maker,
+        taker,
+        CfdState::IncomingSettlementProposal,
+        CfdState::OutgoingSettlementProposal
+    );
+
+    maker.system.accept_settlement(order_id).await.unwrap();
+    wait_next_state!(order_id, maker, taker, CfdState::PendingClose);
+
+    confirm!(close transaction, order_id, maker, taker);
+    wait_next_state!(order_id, maker, taker, CfdState::Closed);
+
+    // A contract setup whose substreams are dropped fails the order.
+    //
+    // This scenario comes last because the failed order adds a second CFD on the taker, which
+    // the helpers above do not expect.
+    chaos::set_faults(Faults {
+        drop_probability: 1.0,
+        protocols: vec!["/itchysats/order".to_owned()],
+        ..Faults::default()
+    });
+
+    let offers = taker.offers_feed().borrow().clone();
+    let offer = match (cfd_args.contract_symbol, cfd_args.position_maker) {
+        (ContractSymbol::BtcUsd, Position::Long) => offers.btcusd_long,
+        (ContractSymbol::BtcUsd, Position::Short) => offers.btcusd_short,
+        (ContractSymbol::EthUsd, Position::Long) => offers.ethusd_long,
+        (ContractSymbol::EthUsd, Position::Short) => offers.ethusd_short,
+    }
+    .expect("offer to be still available");
+
+    let failed_order_id = taker
+        .system
+        .place_order(offer.id, cfd_args.quantity, cfd_args.taker_leverage)
+        .await
+        .unwrap();
+
+    wait_taker_state(&mut taker, failed_order_id, CfdState::SetupFailed).await;
+
+    chaos::set_faults(Faults::default());
+}
+
+/// Waits until the taker's CFD is in the given state.
+///
+/// Dropped substreams can keep the maker from learning about a protocol, hence only the taker's
+/// state changes.
+async fn wait_taker_state(taker: &mut Taker, order_id: OrderId, state: CfdState) {
+    next_with(taker.cfd_feed(), |cfds| {
+        cfds.and_then(cfd_with_state(order_id, state))
+    })
+    .await
+    .unwrap();
+}
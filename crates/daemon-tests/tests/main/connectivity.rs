@@ -1,4 +1,5 @@
 use daemon::online_status::ConnectionStatus;
+use daemon::service_status::ServiceStatus;
 use daemon_tests::flow::next_with;
 use daemon_tests::Maker;
 use daemon_tests::MakerConfig;
@@ -25,6 +26,37 @@ async fn taker_notices_lack_of_maker() {
     wait_next_connection_status_to_maker(&mut taker, ConnectionStatus::Online).await;
 }
 
+#[otel_test]
+async fn taker_receives_service_status_of_maker() {
+    let maker_config = MakerConfig::default();
+    let maker = Maker::start(&maker_config).await;
+
+    let taker_config = TakerConfig::default();
+    let mut taker = Taker::start(&taker_config, maker.identity, maker.connect_addr.clone()).await;
+
+    wait_next_connection_status_to_maker(&mut taker, ConnectionStatus::Online).await;
+
+    let status = ServiceStatus {
+        accepting_orders: false,
+        accepting_rollovers: true,
+        maintenance_message: Some("Upgrading the maker".to_owned()),
+        min_taker_version: Some("99.0.0".to_owned()),
+    };
+    maker
+        .system
+        .update_service_status(status.clone())
+        .await
+        .unwrap();
+
+    let received = next_with(taker.maker_service_status_feed(), |received| {
+        received.filter(|received| received.status == status)
+    })
+    .await
+    .unwrap();
+
+    assert!(received.taker_outdated);
+}
+
 /// Wait indefinitely until the `taker`'s connection status to the maker is the one `expected` by
 /// the caller.
 async fn wait_next_connection_status_to_maker(taker: &mut Taker, expected: ConnectionStatus) {
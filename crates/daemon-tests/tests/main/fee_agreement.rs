@@ -0,0 +1,134 @@
+//! Maker and taker compute the fees of a CFD independently, hence the implementations can drift
+//! apart between releases. These properties drive both daemons through randomized sequences of
+//! rollovers and ensure that the accumulated fees of maker and taker mirror each other after every
+//! step.
+
+use daemon_tests::initial_price_for;
+use daemon_tests::maia::olivia::btc_example_0;
+use daemon_tests::maia::olivia::eth_example_0;
+use daemon_tests::maia::OliviaData;
+use daemon_tests::open_cfd;
+use daemon_tests::rollover::rollover;
+use daemon_tests::start_both;
+use daemon_tests::Maker;
+use daemon_tests::OfferParamsBuilder;
+use daemon_tests::OpenCfdArgs;
+use daemon_tests::Taker;
+use model::ContractSymbol;
+use model::Contracts;
+use model::FundingRate;
+use model::Position;
+use proptest::prelude::*;
+use rust_decimal::Decimal;
+
+/// Every case starts both daemons, hence only a few cases are run
+const CASES: u32 = 8;
+
+/// Upper bound of the funding rate in millionths, ten times the default rate of the offers
+const MAX_FUNDING_RATE_MILLIONTHS: i64 = 2_400;
+
+#[derive(Debug, Clone)]
+struct Scenario {
+    contract_symbol: ContractSymbol,
+    position_maker: Position,
+    quantity: Contracts,
+    /// Funding rate of the maker's offer for each rollover
+    funding_rates: Vec<FundingRate>,
+}
+
+impl Scenario {
+    fn oracle_data(&self) -> OliviaData {
+        match self.contract_symbol {
+            ContractSymbol::BtcUsd => btc_example_0(),
+            ContractSymbol::EthUsd => eth_example_0(),
+        }
+    }
+}
+
+fn scenario() -> impl Strategy<Value = Scenario> {
+    (
+        prop_oneof![Just(ContractSymbol::BtcUsd), Just(ContractSymbol::EthUsd)],
+        prop_oneof![Just(Position::Long), Just(Position::Short)],
+        // Multiples of the largest lot size within the quantity bounds of the offers
+        1..=10u64,
+        prop::collection::vec(
+            -MAX_FUNDING_RATE_MILLIONTHS..=MAX_FUNDING_RATE_MILLIONTHS,
+            1..=3,
+        ),
+    )
+        .prop_map(
+            |(contract_symbol, position_maker, lots, funding_rates)| Scenario {
+                contract_symbol,
+                position_maker,
+                quantity: Contracts::new(lots * 100),
+                funding_rates: funding_rates
+                    .into_iter()
+                    .map(|rate| FundingRate::new(Decimal::new(rate, 6)).unwrap())
+                    .collect(),
+            },
+        )
+}
+
+fn assert_fees_mirror(maker: &mut Maker, taker: &mut Taker, step: &str) {
+    let maker_fees = maker.latest_accumulated_fees();
+    let taker_fees = taker.latest_accumulated_fees();
+
+    assert_eq!(
+        maker_fees,
+        taker_fees * -1,
+        "Accumulated fees of maker and taker diverged after {step}"
+    );
+}
+
+async fn run(scenario: Scenario) {
+    let (mut maker, mut taker) = start_both().await;
+
+    let order_id = open_cfd(
+        &mut taker,
+        &mut maker,
+        OpenCfdArgs {
+            contract_symbol: scenario.contract_symbol,
+            position_maker: scenario.position_maker,
+            initial_price: initial_price_for(scenario.contract_symbol),
+            quantity: scenario.quantity,
+            oracle_data: scenario.oracle_data(),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    assert_fees_mirror(&mut maker, &mut taker, "contract setup");
+
+    for (i, funding_rate) in scenario.funding_rates.iter().enumerate() {
+        // The maker charges the funding rate of its current offer upon rollover
+        maker
+            .set_offer_params(
+                OfferParamsBuilder::new(scenario.contract_symbol)
+                    .funding_rate(*funding_rate)
+                    .build(),
+            )
+            .await;
+
+        rollover(&mut maker, &mut taker, order_id, scenario.oracle_data()).await;
+
+        assert_fees_mirror(
+            &mut maker,
+            &mut taker,
+            &format!("rollover {} at funding rate {funding_rate}", i + 1),
+        );
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(CASES))]
+
+    #[test]
+    fn maker_and_taker_fees_mirror_each_other_across_rollovers(scenario in scenario()) {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        runtime.block_on(run(scenario));
+    }
+}
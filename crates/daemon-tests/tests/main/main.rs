@@ -1,5 +1,6 @@
 mod collaborative_settlement;
 mod connectivity;
+mod fee_agreement;
 mod import_seed;
 mod liquidation;
 mod non_collaborative_settlement;
@@ -12,6 +12,7 @@ use model::Position;
 use otel_tests::otel_test;
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
+use std::time::Duration;
 
 #[otel_test]
 async fn taker_receives_btc_usd_offer_from_maker_on_publication() {
@@ -32,6 +33,31 @@ async fn taker_can_receive_offers_with_different_symbols_from_maker() {
     test_offer(&mut maker, &mut taker, ContractSymbol::EthUsd).await;
 }
 
+#[otel_test]
+async fn taker_receives_funding_rate_history_of_published_offers() {
+    let (mut maker, mut taker) = start_both().await;
+    ensure_null_next_offers(taker.offers_feed()).await.unwrap();
+
+    test_offer(&mut maker, &mut taker, ContractSymbol::BtcUsd).await;
+
+    // Funding rates are recorded asynchronously, hence we might have to ask more than once
+    let mut history = Vec::new();
+    for _ in 0..10 {
+        history = taker.system.funding_rate_history(1).await.unwrap();
+
+        if history.len() == 2 {
+            break;
+        }
+
+        tokio_extras::time::sleep(Duration::from_millis(200)).await;
+    }
+
+    assert_eq!(history.len(), 2, "Expected one entry per maker position");
+    assert!(history
+        .iter()
+        .all(|entry| entry.contract_symbol == ContractSymbol::BtcUsd));
+}
+
 async fn publish_offer(maker: &mut Maker, contract_symbol: ContractSymbol) {
     let leverage = Leverage::TWO;
     maker
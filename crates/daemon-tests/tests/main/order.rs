@@ -11,11 +11,15 @@ use daemon_tests::wait_next_state_multi_cfd;
 use daemon_tests::Maker;
 use daemon_tests::OfferParamsBuilder;
 use daemon_tests::Taker;
+use model::trading_calendar::Session;
+use model::trading_calendar::TradingCalendar;
 use model::ContractSymbol;
 use model::Contracts;
 use model::Leverage;
 use model::OrderId;
 use otel_tests::otel_test;
+use time::Duration;
+use time::OffsetDateTime;
 
 #[otel_test]
 async fn taker_places_order_and_maker_rejects() {
@@ -49,6 +53,51 @@ async fn taker_places_order_and_maker_rejects() {
     wait_next_state!(order_id, maker, taker, CfdState::Rejected);
 }
 
+#[otel_test]
+async fn given_market_closed_then_maker_rejects_order() {
+    let (mut maker, mut taker) = start_both().await;
+
+    ensure_null_next_offers(taker.offers_feed()).await.unwrap();
+
+    // The market only opens the day after tomorrow
+    let calendar = TradingCalendar::new(vec![Session {
+        weekday: (OffsetDateTime::now_utc() + Duration::days(2)).weekday(),
+        open_minute: 0,
+        close_minute: 24 * 60,
+    }])
+    .unwrap();
+
+    let symbol = ContractSymbol::BtcUsd;
+    maker
+        .set_offer_params(
+            OfferParamsBuilder::new(symbol)
+                .trading_calendar(calendar.clone())
+                .build(),
+        )
+        .await;
+
+    let (_, received) = next_maker_offers(maker.offers_feed(), taker.offers_feed(), &symbol)
+        .await
+        .unwrap();
+
+    let offer = received.btcusd_short.unwrap();
+    assert_eq!(offer.trading_calendar, Some(calendar));
+
+    taker.mocks.mock_oracle_announcement(symbol).await;
+    maker.mocks.mock_oracle_announcement(symbol).await;
+    taker
+        .system
+        .place_order(offer.id, Contracts::new(100), Leverage::TWO)
+        .await
+        .unwrap();
+
+    next_with(taker.cfd_feed(), |maybe_cfds| {
+        maybe_cfds.and_then(one_cfd_with_state(CfdState::Rejected))
+    })
+    .await
+    .unwrap();
+}
+
 #[otel_test]
 async fn taker_places_btc_usd_order_and_maker_accepts_and_contract_setup() {
     taker_places_order_and_maker_accepts_and_contract_setup(ContractSymbol::BtcUsd).await;
@@ -0,0 +1,170 @@
+//! Issuing of API tokens scoped to individual CFDs.
+//!
+//! A token is 32 random bytes, hex-encoded and prefixed with [`PREFIX`] so that it can be told
+//! apart from other secrets. Tokens are shown once when they are issued and only their SHA-256 hash
+//! is stored: unlike passwords they are random, so a salted and slow hash is not needed.
+
+use anyhow::ensure;
+use anyhow::Result;
+use model::OrderId;
+use model::Timestamp;
+use rand::Rng;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+use sqlite_db::api_tokens::ApiToken;
+use sqlite_db::api_tokens::TokenAction;
+use std::time::Duration;
+
+pub const PREFIX: &str = "hermes_";
+
+/// Tokens cannot be issued for longer than this
+pub const MAX_VALIDITY: Duration = Duration::from_secs(90 * 24 * 60 * 60);
+
+pub struct NewApiToken {
+    pub label: Option<String>,
+    pub order_ids: Vec<OrderId>,
+    pub actions: Vec<TokenAction>,
+    pub validity: Duration,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssuedApiToken {
+    /// The token itself, it cannot be retrieved again
+    pub token: String,
+    #[serde(flatten)]
+    pub api_token: ApiToken,
+}
+
+#[derive(Clone)]
+pub struct ApiTokens {
+    db: sqlite_db::Connection,
+}
+
+impl ApiTokens {
+    pub fn new(db: sqlite_db::Connection) -> Self {
+        Self { db }
+    }
+
+    pub async fn issue(&self, new: NewApiToken) -> Result<IssuedApiToken> {
+        let NewApiToken {
+            label,
+            order_ids,
+            actions,
+            validity,
+        } = new;
+
+        ensure!(!order_ids.is_empty(), "A token needs at least one order id");
+        ensure!(!actions.is_empty(), "A token needs at least one action");
+        ensure!(
+            validity > Duration::ZERO && validity <= MAX_VALIDITY,
+            "A token has to be valid for at most {} days",
+            MAX_VALIDITY.as_secs() / (24 * 60 * 60)
+        );
+
+        let token = format!(
+            "{PREFIX}{}",
+            hex::encode(rand::thread_rng().gen::<[u8; 32]>())
+        );
+        let created_at = Timestamp::now();
+        let expires_at = Timestamp::new(created_at.seconds() + validity.as_secs() as i64);
+
+        let id = self
+            .db
+            .insert_api_token(
+                &hash(&token),
+                label.as_deref(),
+                &order_ids,
+                &actions,
+                created_at,
+                expires_at,
+            )
+            .await?;
+
+        tracing::info!(%id, ?order_ids, ?actions, %expires_at, "Issued API token");
+
+        Ok(IssuedApiToken {
+            token,
+            api_token: ApiToken {
+                id,
+                label,
+                order_ids,
+                actions,
+                created_at,
+                expires_at,
+                revoked_at: None,
+            },
+        })
+    }
+
+    /// All tokens that were issued, including expired and revoked ones.
+    pub async fn list(&self) -> Result<Vec<ApiToken>> {
+        self.db.load_api_tokens().await
+    }
+
+    pub async fn revoke(&self, id: i64) -> Result<()> {
+        self.db.revoke_api_token(id, Timestamp::now()).await?;
+
+        tracing::info!(%id, "Revoked API token");
+
+        Ok(())
+    }
+
+    /// Look up the token presented by a client, `None` if it was never issued.
+    ///
+    /// Expired and revoked tokens are returned as well, [`ApiToken::allows`] rejects them.
+    pub async fn authenticate(&self, token: &str) -> Result<Option<ApiToken>> {
+        if !token.starts_with(PREFIX) {
+            return Ok(None);
+        }
+
+        self.db.load_api_token_by_hash(&hash(token)).await
+    }
+}
+
+fn hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn issued_token_authenticates_until_revoked() {
+        let api_tokens = ApiTokens::new(sqlite_db::memory().await.unwrap());
+        let order_id = OrderId::default();
+
+        let issued = api_tokens
+            .issue(NewApiToken {
+                label: None,
+                order_ids: vec![order_id],
+                actions: vec![TokenAction::Settle],
+                validity: Duration::from_secs(60 * 60),
+            })
+            .await
+            .unwrap();
+
+        let token = api_tokens
+            .authenticate(&issued.token)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(token, issued.api_token);
+        assert!(token.allows(order_id, TokenAction::Settle, Timestamp::now()));
+        assert!(api_tokens
+            .authenticate(&format!("{PREFIX}00"))
+            .await
+            .unwrap()
+            .is_none());
+
+        api_tokens.revoke(token.id).await.unwrap();
+
+        let token = api_tokens
+            .authenticate(&issued.token)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!token.allows(order_id, TokenAction::Settle, Timestamp::now()));
+    }
+}
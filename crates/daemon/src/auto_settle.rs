@@ -0,0 +1,316 @@
+//! Automatic settlement of CFDs once their take-profit or stop-loss price level is reached.
+//!
+//! The taker compares the closing price of every CFD with price levels against the latest quote.
+//! To not settle upon a single outlier, a price level has to be reached on several consecutive
+//! quotes. The CFD is then settled collaboratively, or committed if the maker is offline.
+
+use crate::command;
+use crate::into_price_feed_symbol;
+use crate::online_status::ConnectionStatus;
+use crate::taker_cfd;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::StreamExt;
+use model::market_closing_price;
+use model::OrderId;
+use model::Price;
+use model::PriceLevel;
+use model::Role;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::time::Duration;
+use time::ext::NumericalDuration;
+use time::OffsetDateTime;
+use tokio::sync::watch;
+use xtra::prelude::MessageChannel;
+use xtra::Address;
+use xtra_bitmex_price_feed::GetLatestQuotes;
+use xtra_bitmex_price_feed::LatestQuotes;
+use xtra_bitmex_price_feed::Quote;
+use xtra_bitmex_price_feed::QUOTE_INTERVAL_MINUTES;
+use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+/// Interval at which the price levels are checked against the latest quotes
+const CHECK_INTERVAL: Duration = Duration::from_secs(20);
+
+/// Number of consecutive quotes that have to reach a price level before the CFD is settled
+const REQUIRED_QUOTES: u32 = 3;
+
+pub struct Actor {
+    db: sqlite_db::Connection,
+    price_feed: MessageChannel<GetLatestQuotes, LatestQuotes>,
+    cfd_actor: Address<taker_cfd::Actor>,
+    executor: command::Executor,
+    maker_online_status: watch::Receiver<ConnectionStatus>,
+    triggers: HashMap<OrderId, Trigger>,
+}
+
+impl Actor {
+    pub fn new(
+        db: sqlite_db::Connection,
+        price_feed: MessageChannel<GetLatestQuotes, LatestQuotes>,
+        cfd_actor: Address<taker_cfd::Actor>,
+        executor: command::Executor,
+        maker_online_status: watch::Receiver<ConnectionStatus>,
+    ) -> Self {
+        Self {
+            db,
+            price_feed,
+            cfd_actor,
+            executor,
+            maker_online_status,
+            triggers: HashMap::new(),
+        }
+    }
+}
+
+/// Consecutive quotes at which a CFD reached the same price level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Trigger {
+    level: PriceLevel,
+    quotes: u32,
+    last_quote_at: Option<OffsetDateTime>,
+}
+
+impl Trigger {
+    fn new(level: PriceLevel) -> Self {
+        Self {
+            level,
+            quotes: 0,
+            last_quote_at: None,
+        }
+    }
+}
+
+/// Check the price levels of all CFDs against the latest quotes.
+#[derive(Clone, Copy)]
+struct CheckPriceLevels;
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: CheckPriceLevels) {
+        if let Err(e) = self.check_price_levels().await {
+            tracing::warn!("Failed to check price levels: {e:#}");
+        }
+    }
+}
+
+impl Actor {
+    async fn check_price_levels(&mut self) -> Result<()> {
+        let quotes = self
+            .price_feed
+            .send(GetLatestQuotes)
+            .await
+            .context("Price feed not available")?;
+
+        let mut watched = HashSet::new();
+        let mut reached = Vec::new();
+
+        let mut stream = self.db.load_all_open_cfds::<model::Cfd>(());
+        while let Some(cfd) = stream.next().await {
+            let cfd = match cfd {
+                Ok(cfd) => cfd,
+                Err(e) => {
+                    tracing::warn!("Failed to load CFD from database: {e:#}");
+                    continue;
+                }
+            };
+
+            if cfd.take_profit().is_none() && cfd.stop_loss().is_none() {
+                continue;
+            }
+
+            // Wait for the outcome of an ongoing settlement
+            if cfd.is_in_collaborative_settlement() {
+                continue;
+            }
+
+            let order_id = cfd.id();
+
+            let quote = match quotes.get(&into_price_feed_symbol(cfd.contract_symbol())) {
+                Some(quote) if !quote.is_older_than(QUOTE_INTERVAL_MINUTES.minutes() * 2) => quote,
+                _ => {
+                    tracing::debug!(%order_id, "No recent quote to check price levels against");
+                    continue;
+                }
+            };
+
+            watched.insert(order_id);
+
+            let closing_price = market_closing_price(
+                Price::new(quote.bid())?,
+                Price::new(quote.ask())?,
+                Role::Taker,
+                cfd.position(),
+            );
+
+            match cfd.price_level_reached(closing_price) {
+                Some(level) => {
+                    if count_quote(&mut self.triggers, order_id, level, quote.timestamp) {
+                        reached.push((order_id, level, *quote));
+                    }
+                }
+                None => {
+                    self.triggers.remove(&order_id);
+                }
+            }
+        }
+
+        self.triggers
+            .retain(|order_id, _| watched.contains(order_id));
+
+        for (order_id, level, quote) in reached {
+            self.triggers.remove(&order_id);
+
+            if let Err(e) = self.settle(order_id, level, quote).await {
+                tracing::warn!(%order_id, ?level, "Failed to settle CFD automatically: {e:#}");
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn settle(&self, order_id: OrderId, level: PriceLevel, quote: Quote) -> Result<()> {
+        let maker_status = *self.maker_online_status.borrow();
+
+        match maker_status {
+            ConnectionStatus::Online => {
+                tracing::info!(%order_id, ?level, "Price level reached, settling collaboratively");
+
+                self.cfd_actor
+                    .send(taker_cfd::ProposeSettlement {
+                        order_id,
+                        bid: Price::new(quote.bid())?,
+                        ask: Price::new(quote.ask())?,
+                        quote_timestamp: quote
+                            .timestamp
+                            .format(&time::format_description::well_known::Rfc3339)
+                            .context("Failed to format timestamp")?,
+                        payout_address: None,
+                    })
+                    .await
+                    .context("CFD actor not available")??;
+            }
+            ConnectionStatus::Offline => {
+                tracing::info!(%order_id, ?level, "Price level reached while maker is offline, committing");
+
+                self.executor
+                    .execute(order_id, |cfd| cfd.manual_commit_to_blockchain())
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Count a quote at which the CFD reached `level`.
+///
+/// Returns whether the level was reached on enough consecutive quotes to settle the CFD. The same
+/// quote is only counted once, reaching a different level starts counting anew.
+fn count_quote(
+    triggers: &mut HashMap<OrderId, Trigger>,
+    order_id: OrderId,
+    level: PriceLevel,
+    quote_at: OffsetDateTime,
+) -> bool {
+    let trigger = triggers
+        .entry(order_id)
+        .or_insert_with(|| Trigger::new(level));
+
+    if trigger.level != level {
+        *trigger = Trigger::new(level);
+    }
+
+    if trigger.last_quote_at != Some(quote_at) {
+        trigger.quotes += 1;
+        trigger.last_quote_at = Some(quote_at);
+    }
+
+    trigger.quotes >= REQUIRED_QUOTES
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we just started");
+
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(
+                CHECK_INTERVAL,
+                || CheckPriceLevels,
+                xtras::IncludeSpan::Never,
+            ),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_same_quote_then_counted_once() {
+        let mut triggers = HashMap::new();
+        let order_id = OrderId::default();
+        let quote_at = OffsetDateTime::UNIX_EPOCH;
+
+        for _ in 0..REQUIRED_QUOTES {
+            assert!(!count_quote(
+                &mut triggers,
+                order_id,
+                PriceLevel::StopLoss,
+                quote_at
+            ));
+        }
+    }
+
+    #[test]
+    fn given_consecutive_quotes_at_level_then_settles() {
+        let mut triggers = HashMap::new();
+        let order_id = OrderId::default();
+
+        let settles = (0..REQUIRED_QUOTES)
+            .map(|i| {
+                count_quote(
+                    &mut triggers,
+                    order_id,
+                    PriceLevel::TakeProfit,
+                    OffsetDateTime::UNIX_EPOCH + (i as i64).minutes(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(settles.last(), Some(&true));
+        assert!(settles[..settles.len() - 1].iter().all(|settles| !settles));
+    }
+
+    #[test]
+    fn given_other_level_reached_then_counting_starts_anew() {
+        let mut triggers = HashMap::new();
+        let order_id = OrderId::default();
+
+        for i in 0..REQUIRED_QUOTES - 1 {
+            count_quote(
+                &mut triggers,
+                order_id,
+                PriceLevel::TakeProfit,
+                OffsetDateTime::UNIX_EPOCH + (i as i64).minutes(),
+            );
+        }
+
+        assert!(!count_quote(
+            &mut triggers,
+            order_id,
+            PriceLevel::StopLoss,
+            OffsetDateTime::UNIX_EPOCH + 1.hours(),
+        ));
+    }
+}
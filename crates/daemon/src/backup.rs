@@ -0,0 +1,179 @@
+//! Encrypted backups of individual CFDs.
+//!
+//! A backup contains everything needed to enforce a contract without the daemon: the DLC with all
+//! transactions, the adaptor signatures of the counterparty and the keys derived for the order, as
+//! well as the oracle events the CETs are locked to. Backups are encrypted with a key derived from
+//! the identity seed, so they can be stored anywhere; the seed file is needed to restore them with
+//! `hermes-recover`.
+
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use chacha20poly1305::aead::Aead;
+use chacha20poly1305::aead::NewAead;
+use chacha20poly1305::ChaCha20Poly1305;
+use chacha20poly1305::Nonce;
+use model::olivia::BitMexPriceEventId;
+use model::Cfd;
+use model::ContractSymbol;
+use model::Dlc;
+use model::OrderId;
+use model::Position;
+use model::Role;
+use model::Timestamp;
+use rand::Rng;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Version of the encrypted blob layout: `version || nonce || ciphertext`
+const VERSION: u8 = 1;
+const NONCE_SIZE: usize = 12;
+
+/// Key to encrypt and decrypt backups with.
+#[derive(Clone)]
+pub struct Key([u8; 32]);
+
+impl From<[u8; 32]> for Key {
+    fn from(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl Key {
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(chacha20poly1305::Key::from_slice(&self.0))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Backup {
+    pub order_id: OrderId,
+    pub role: Role,
+    pub position: Position,
+    pub contract_symbol: ContractSymbol,
+    pub created_at: Timestamp,
+    /// The oracle events for which the DLC contains CETs
+    pub oracle_event_ids: Vec<BitMexPriceEventId>,
+    pub settlement_event_id: BitMexPriceEventId,
+    pub dlc: Dlc,
+}
+
+impl Backup {
+    pub fn new(cfd: &Cfd) -> Result<Self> {
+        let dlc = cfd
+            .dlc()
+            .with_context(|| format!("CFD {} has no DLC to back up", cfd.id()))?
+            .clone();
+
+        Ok(Self {
+            order_id: cfd.id(),
+            role: cfd.role(),
+            position: cfd.position(),
+            contract_symbol: cfd.contract_symbol(),
+            created_at: Timestamp::now(),
+            oracle_event_ids: dlc.event_ids(),
+            settlement_event_id: dlc.settlement_event_id,
+            dlc,
+        })
+    }
+
+    pub fn encrypt(&self, key: &Key) -> Result<Vec<u8>> {
+        let plaintext = serde_json::to_vec(self).context("Failed to encode backup")?;
+
+        seal(&plaintext, key)
+    }
+
+    pub fn decrypt(blob: &[u8], key: &Key) -> Result<Self> {
+        let plaintext = open(blob, key)?;
+
+        serde_json::from_slice(&plaintext).context("Failed to decode backup")
+    }
+}
+
+fn seal(plaintext: &[u8], key: &Key) -> Result<Vec<u8>> {
+    let nonce = rand::thread_rng().gen::<[u8; NONCE_SIZE]>();
+
+    let ciphertext = key
+        .cipher()
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| anyhow!("Failed to encrypt backup"))?;
+
+    let mut blob = Vec::with_capacity(1 + NONCE_SIZE + ciphertext.len());
+    blob.push(VERSION);
+    blob.extend_from_slice(&nonce);
+    blob.extend_from_slice(&ciphertext);
+
+    Ok(blob)
+}
+
+fn open(blob: &[u8], key: &Key) -> Result<Vec<u8>> {
+    let (version, rest) = blob.split_first().context("Backup is empty")?;
+    if *version != VERSION {
+        bail!("Unsupported backup version {version}");
+    }
+    if rest.len() < NONCE_SIZE {
+        bail!("Backup is truncated");
+    }
+    let (nonce, ciphertext) = rest.split_at(NONCE_SIZE);
+
+    key.cipher()
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("Failed to decrypt backup, was it created with another seed?"))
+}
+
+/// Creates encrypted backups of the open CFDs in the database.
+#[derive(Clone)]
+pub struct Exporter {
+    db: sqlite_db::Connection,
+    key: Key,
+}
+
+impl Exporter {
+    pub fn new(db: sqlite_db::Connection, key: Key) -> Self {
+        Self { db, key }
+    }
+
+    pub async fn export(&self, order_id: OrderId) -> Result<Vec<u8>> {
+        let cfd = self.db.load_open_cfd::<Cfd>(order_id, ()).await?;
+
+        Backup::new(&cfd)?.encrypt(&self.key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seed::RandomSeed;
+    use crate::seed::Seed;
+
+    #[test]
+    fn sealed_backup_can_be_opened_with_same_seed() {
+        let key = RandomSeed::default().derive_backup_key();
+
+        let blob = seal(b"backup", &key).unwrap();
+
+        assert_eq!(blob[0], VERSION);
+        assert_eq!(open(&blob, &key).unwrap(), b"backup");
+    }
+
+    #[test]
+    fn given_other_seed_then_backup_cannot_be_opened() {
+        let key = RandomSeed::default().derive_backup_key();
+        let other_key = RandomSeed::default().derive_backup_key();
+
+        let blob = seal(b"backup", &key).unwrap();
+
+        assert!(open(&blob, &other_key).is_err());
+    }
+
+    #[test]
+    fn given_tampered_backup_then_it_cannot_be_opened() {
+        let key = RandomSeed::default().derive_backup_key();
+
+        let mut blob = seal(b"backup", &key).unwrap();
+        *blob.last_mut().unwrap() ^= 1;
+
+        assert!(open(&blob, &key).is_err());
+    }
+}
@@ -0,0 +1,148 @@
+//! When to publish the CET once the oracle attested and the CET timelock expired.
+//!
+//! Both parties hold the same CET and usually both publish it. Publishing is redundant if the
+//! counterparty publishes anyway, hence the [`Strategy`] allows to leave it to the counterparty.
+//! Regardless of the strategy, the CET is published once the refund timelock is less than
+//! [`DEADLINE_BLOCKS`] away: the refund transaction must never be able to compete with the CET.
+
+use anyhow::bail;
+use sqlite_db::cet_publications::PublishedBy;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Number of blocks before the refund timelock expires at which a deferred CET is published (~1
+/// day)
+pub const DEADLINE_BLOCKS: u32 = 144;
+
+/// Number of recently deferred CETs considered to decide whether the counterparty usually publishes
+pub const RECENT_PUBLICATIONS: u32 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Publish the CET as soon as possible
+    Immediate,
+    /// Wait for the configured delay before publishing the CET if the counterparty published most
+    /// of the recently deferred CETs
+    Delayed,
+    /// Only publish the CET if the counterparty did not publish it before the deadline
+    Deadline,
+}
+
+impl FromStr for Strategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "immediate" => Ok(Strategy::Immediate),
+            "delayed" => Ok(Strategy::Delayed),
+            "deadline" => Ok(Strategy::Deadline),
+            other => {
+                bail!("Unknown strategy {other}, expected `immediate`, `delayed` or `deadline`")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Strategy::Immediate => write!(f, "immediate"),
+            Strategy::Delayed => write!(f, "delayed"),
+            Strategy::Deadline => write!(f, "deadline"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    pub strategy: Strategy,
+    /// How long to wait for the counterparty with [`Strategy::Delayed`]
+    pub delay: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            strategy: Strategy::Immediate,
+            delay: Duration::ZERO,
+        }
+    }
+}
+
+impl Config {
+    /// Whether to publish a deferred CET now.
+    ///
+    /// `blocks_until_refund` is `None` if the refund timelock cannot be estimated, in which case
+    /// the CET is published to be on the safe side.
+    pub fn should_publish(
+        &self,
+        deferred_for: Duration,
+        blocks_until_refund: Option<u32>,
+        counterparty_usually_publishes: bool,
+    ) -> bool {
+        match blocks_until_refund {
+            Some(blocks) if blocks > DEADLINE_BLOCKS => {}
+            _ => return true,
+        }
+
+        match self.strategy {
+            Strategy::Immediate => true,
+            Strategy::Delayed => !counterparty_usually_publishes || deferred_for >= self.delay,
+            Strategy::Deadline => false,
+        }
+    }
+}
+
+/// Whether the counterparty published at least half of the recently deferred CETs.
+///
+/// Without any deferred CETs yet, we give the counterparty the chance to publish.
+pub fn counterparty_usually_publishes(recent: &[PublishedBy]) -> bool {
+    let by_counterparty = recent
+        .iter()
+        .filter(|published_by| **published_by == PublishedBy::Counterparty)
+        .count();
+
+    recent.is_empty() || by_counterparty * 2 >= recent.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn deadline_overrides_strategy() {
+        let config = Config {
+            strategy: Strategy::Deadline,
+            delay: Duration::ZERO,
+        };
+
+        assert!(!config.should_publish(MINUTE, Some(DEADLINE_BLOCKS + 1), true));
+        assert!(config.should_publish(MINUTE, Some(DEADLINE_BLOCKS), true));
+        assert!(config.should_publish(MINUTE, None, true));
+    }
+
+    #[test]
+    fn delay_only_applies_if_counterparty_usually_publishes() {
+        let config = Config {
+            strategy: Strategy::Delayed,
+            delay: 30 * MINUTE,
+        };
+        let far = Some(DEADLINE_BLOCKS * 2);
+
+        assert!(!config.should_publish(MINUTE, far, true));
+        assert!(config.should_publish(30 * MINUTE, far, true));
+        assert!(config.should_publish(MINUTE, far, false));
+    }
+
+    #[test]
+    fn counterparty_usually_publishes_with_half_of_recent_cets() {
+        use PublishedBy::*;
+
+        assert!(counterparty_usually_publishes(&[]));
+        assert!(counterparty_usually_publishes(&[Us, Counterparty]));
+        assert!(!counterparty_usually_publishes(&[Us, Us, Counterparty]));
+    }
+}
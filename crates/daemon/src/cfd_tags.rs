@@ -0,0 +1,112 @@
+//! User-defined tags attached to CFDs, e.g. to group positions by strategy or client.
+//!
+//! Tags are stored separately from the event log and included in the CFD feed, where CFDs can be
+//! filtered by them.
+
+use crate::projection;
+use anyhow::ensure;
+use anyhow::Result;
+use async_trait::async_trait;
+use model::OrderId;
+use xtra::Address;
+use xtra_productivity::xtra_productivity;
+
+/// Maximum number of characters of a tag
+pub const MAX_TAG_LENGTH: usize = 32;
+
+pub struct Actor {
+    db: sqlite_db::Connection,
+    projection: Address<projection::Actor>,
+}
+
+impl Actor {
+    pub fn new(db: sqlite_db::Connection, projection: Address<projection::Actor>) -> Self {
+        Self { db, projection }
+    }
+
+    /// Make the tags visible in the CFD feed.
+    async fn publish(&self) {
+        let tags = match self.db.load_cfd_tags().await {
+            Ok(tags) => tags,
+            Err(e) => {
+                tracing::warn!("Failed to load CFD tags: {e:#}");
+                return;
+            }
+        };
+
+        if let Err(e) = self.projection.send(projection::Update(tags)).await {
+            tracing::warn!("Failed to update projection with CFD tags: {e:#}");
+        }
+    }
+}
+
+/// Check that a tag is non-empty, not too long and only consists of alphanumeric characters, `-`
+/// and `_`.
+pub fn validate(tag: &str) -> Result<()> {
+    ensure!(!tag.is_empty(), "Tag must not be empty");
+    ensure!(
+        tag.chars().count() <= MAX_TAG_LENGTH,
+        "Tag must not be longer than {MAX_TAG_LENGTH} characters"
+    );
+    ensure!(
+        tag.chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_'),
+        "Tag may only contain alphanumeric characters, '-' and '_'"
+    );
+
+    Ok(())
+}
+
+pub struct AddTag {
+    pub order_id: OrderId,
+    pub tag: String,
+}
+
+pub struct RemoveTag {
+    pub order_id: OrderId,
+    pub tag: String,
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: AddTag) -> Result<()> {
+        validate(&msg.tag)?;
+
+        self.db.insert_cfd_tag(msg.order_id, &msg.tag).await?;
+        self.publish().await;
+
+        Ok(())
+    }
+
+    async fn handle(&mut self, msg: RemoveTag) -> Result<()> {
+        self.db.delete_cfd_tag(msg.order_id, &msg.tag).await?;
+        self.publish().await;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, _: &mut xtra::Context<Self>) {
+        self.publish().await;
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_and_rejects_tags() {
+        assert!(validate("client-a_2").is_ok());
+
+        assert!(validate("").is_err());
+        assert!(validate("with space").is_err());
+        assert!(validate(&"a".repeat(MAX_TAG_LENGTH + 1)).is_err());
+    }
+}
@@ -0,0 +1,238 @@
+//! Detection of diverging views of the chain between the wallet and the monitor.
+//!
+//! The wallet and the monitor sync independently and may be connected to different Electrum
+//! servers. If one of them ends up on a stale or partitioned view of the chain, acting on the
+//! monitor's view risks publishing transactions prematurely, e.g. a commit transaction because a
+//! timelock seemingly expired. While the views diverge, the monitor holds back timelock-dependent
+//! broadcasts until they agree again.
+
+use crate::projection::ChainTip;
+use async_trait::async_trait;
+use model::WalletInfo;
+use serde::Serialize;
+use std::time::Duration;
+use tokio::sync::watch;
+use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+/// Number of blocks the chain tips of wallet and monitor may differ by
+///
+/// Both sync at different intervals, hence their tips are rarely exactly the same.
+pub const MAX_HEIGHT_DIFFERENCE: u32 = 2;
+
+/// Interval at which the views of wallet and monitor are compared
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+static CHAIN_VIEWS_DIVERGED_GAUGE: conquer_once::Lazy<prometheus::IntGauge> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_int_gauge!(
+            "chain_views_diverged",
+            "Whether the chain views of wallet and monitor diverge, 1 if they do."
+        )
+        .unwrap()
+    });
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Status {
+    /// Either wallet or monitor did not sync yet
+    Unknown,
+    Consistent,
+    Diverged {
+        wallet_height: u32,
+        monitor_height: u32,
+    },
+}
+
+impl Status {
+    /// Whether transactions depending on the state of the chain may be broadcast.
+    pub fn allows_broadcasts(&self) -> bool {
+        !matches!(self, Status::Diverged { .. })
+    }
+}
+
+/// Compare the chain views of wallet and monitor.
+///
+/// Besides the chain tips, the views also diverge if the wallet saw one of its transactions
+/// confirm in a block the monitor does not know about.
+pub fn compare(wallet: &WalletInfo, monitor: &ChainTip) -> Status {
+    let wallet_height = wallet.block_height;
+    let monitor_height = monitor.height;
+
+    let highest_confirmation = wallet
+        .transactions
+        .iter()
+        .filter_map(|tx| tx.confirmation_time.as_ref())
+        .map(|confirmation| confirmation.height)
+        .max()
+        .unwrap_or_default();
+
+    if wallet_height.abs_diff(monitor_height) > MAX_HEIGHT_DIFFERENCE
+        || highest_confirmation > monitor_height + MAX_HEIGHT_DIFFERENCE
+    {
+        return Status::Diverged {
+            wallet_height,
+            monitor_height,
+        };
+    }
+
+    Status::Consistent
+}
+
+pub struct Actor {
+    wallet: watch::Receiver<Option<WalletInfo>>,
+    chain_tip: watch::Receiver<Option<ChainTip>>,
+    sender: watch::Sender<Status>,
+}
+
+impl Actor {
+    pub fn new(
+        wallet: watch::Receiver<Option<WalletInfo>>,
+        chain_tip: watch::Receiver<Option<ChainTip>>,
+    ) -> (Self, watch::Receiver<Status>) {
+        let (sender, receiver) = watch::channel(Status::Unknown);
+
+        (
+            Self {
+                wallet,
+                chain_tip,
+                sender,
+            },
+            receiver,
+        )
+    }
+
+    fn check(&self) {
+        let status = match (&*self.wallet.borrow(), &*self.chain_tip.borrow()) {
+            (Some(wallet), Some(chain_tip)) => compare(wallet, chain_tip),
+            _ => Status::Unknown,
+        };
+
+        let previous = *self.sender.borrow();
+        if status == previous {
+            return;
+        }
+
+        match status {
+            Status::Diverged {
+                wallet_height,
+                monitor_height,
+            } => {
+                tracing::error!(
+                    %wallet_height,
+                    %monitor_height,
+                    "Chain views of wallet and monitor diverged, holding back broadcasts"
+                );
+            }
+            Status::Consistent if !previous.allows_broadcasts() => {
+                tracing::info!("Chain views of wallet and monitor agree again");
+            }
+            Status::Consistent | Status::Unknown => {}
+        }
+
+        CHAIN_VIEWS_DIVERGED_GAUGE.set((!status.allows_broadcasts()).into());
+        let _ = self.sender.send(status);
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we just started");
+
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(CHECK_INTERVAL, || Check, xtras::IncludeSpan::Never),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[derive(Clone, Copy)]
+struct Check;
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: Check) {
+        self.check();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bdk::bitcoin::Address;
+    use bdk::bitcoin::Amount;
+    use bdk::bitcoin::Network;
+    use bdk::BlockTime;
+    use bdk::TransactionDetails;
+    use model::Timestamp;
+
+    #[test]
+    fn given_tips_within_threshold_then_consistent() {
+        let status = compare(&wallet(700_001, vec![]), &chain_tip(700_000));
+
+        assert_eq!(status, Status::Consistent);
+    }
+
+    #[test]
+    fn given_tips_too_far_apart_then_diverged() {
+        let status = compare(&wallet(700_000, vec![]), &chain_tip(700_010));
+
+        assert_eq!(
+            status,
+            Status::Diverged {
+                wallet_height: 700_000,
+                monitor_height: 700_010
+            }
+        );
+        assert!(!status.allows_broadcasts());
+    }
+
+    #[test]
+    fn given_wallet_confirmation_unknown_to_monitor_then_diverged() {
+        let status = compare(&wallet(700_000, vec![700_005]), &chain_tip(700_000));
+
+        assert!(!status.allows_broadcasts());
+    }
+
+    fn wallet(block_height: u32, confirmations: Vec<u32>) -> WalletInfo {
+        WalletInfo {
+            network: Network::Regtest,
+            balance: Amount::ZERO,
+            address: "bcrt1qxw9v2aes9dx3dlan3p8ggts0jl2fpvcwrnyn0z"
+                .parse::<Address>()
+                .unwrap(),
+            last_updated_at: Timestamp::now(),
+            block_height,
+            transactions: confirmations
+                .into_iter()
+                .map(|height| TransactionDetails {
+                    transaction: None,
+                    txid: Default::default(),
+                    received: 0,
+                    sent: 0,
+                    fee: None,
+                    confirmation_time: Some(BlockTime {
+                        height,
+                        timestamp: 0,
+                    }),
+                })
+                .collect(),
+            pending_deposits: vec![],
+            managed_wallet: false,
+        }
+    }
+
+    fn chain_tip(height: u32) -> ChainTip {
+        ChainTip {
+            height,
+            last_synced_at: Timestamp::now(),
+            electrum_healthy: true,
+            soonest_refund: None,
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Cooperative close of all CFDs between a maker and a taker.
+//!
+//! Either side can ask to settle every open CFD it has with a counterparty in one go. The taker
+//! proposes collaborative settlement for each of them. The maker cannot initiate collaborative
+//! settlement, instead it asks the taker to settle each CFD through a settlement proposal and
+//! accepts the resulting proposals of the taker.
+
+use crate::projection;
+use crate::projection::CfdState;
+use model::libp2p::PeerId;
+use model::OrderId;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Failure {
+    pub order_id: OrderId,
+    pub reason: String,
+}
+
+/// Outcome of closing all CFDs with a counterparty
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Summary {
+    /// CFDs for which collaborative settlement is still in progress
+    pub pending: Vec<OrderId>,
+    /// CFDs that were settled collaboratively
+    pub settled: Vec<OrderId>,
+    pub failures: Vec<Failure>,
+}
+
+impl Summary {
+    pub fn new(pending: Vec<OrderId>) -> Self {
+        Self {
+            pending,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn record_settled(&mut self, order_id: OrderId) {
+        self.pending.retain(|pending| *pending != order_id);
+        self.failures.retain(|failure| failure.order_id != order_id);
+        self.settled.push(order_id);
+    }
+
+    pub fn record_failure(&mut self, order_id: OrderId, e: &anyhow::Error) {
+        tracing::warn!(%order_id, "Failed to close CFD with counterparty: {e:#}");
+
+        self.pending.retain(|pending| *pending != order_id);
+        self.failures.retain(|failure| failure.order_id != order_id);
+        self.failures.push(Failure {
+            order_id,
+            reason: format!("{e:#}"),
+        });
+    }
+}
+
+/// The open CFDs with `counterparty` that can be settled collaboratively.
+pub fn open_cfds_with(cfds: &[projection::Cfd], counterparty: PeerId) -> Vec<OrderId> {
+    cfds.iter()
+        .filter(|cfd| cfd.counterparty == counterparty && cfd.state == CfdState::Open)
+        .map(|cfd| cfd.order_id)
+        .collect()
+}
+
+/// Whether a CFD that was asked to be closed is settled collaboratively.
+///
+/// Returns `None` while the settlement is still in progress and an error if the CFD can no
+/// longer be settled collaboratively.
+pub fn settlement_outcome(state: CfdState) -> Option<anyhow::Result<()>> {
+    match state {
+        CfdState::PendingClose | CfdState::Closed => Some(Ok(())),
+        CfdState::Open
+        | CfdState::IncomingSettlementProposal
+        | CfdState::OutgoingSettlementProposal
+        | CfdState::RolloverSetup => None,
+        state => Some(Err(anyhow::anyhow!(
+            "CFD can no longer be settled collaboratively in state {state:?}"
+        ))),
+    }
+}
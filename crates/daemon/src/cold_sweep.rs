@@ -0,0 +1,211 @@
+//! Forwarding of settlement payouts of the taker to an external cold wallet.
+//!
+//! Keeps the balance of the hot wallet small: once the payout of a settled CFD confirmed, the
+//! unlocked balance above the threshold of the policy is sent to the cold wallet. Payouts that
+//! confirm within [`BATCH_WINDOW`] are forwarded in a single transaction, and nothing is sent while
+//! the estimated fee rate exceeds the maximum of the policy.
+
+use crate::projection::Cfd;
+use crate::projection::CfdState;
+use crate::wallet;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use bdk::FeeRate;
+use model::OrderId;
+use model::Timestamp;
+use sqlite_db::cold_sweeps::ColdSweep;
+use sqlite_db::cold_sweeps::ColdSweepPolicy;
+use std::collections::HashSet;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::sync::watch;
+use xtra::prelude::MessageChannel;
+use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+/// Interval at which newly settled CFDs are checked for
+const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Time to wait for further payouts after a payout confirmed, to forward them together
+const BATCH_WINDOW: Duration = Duration::from_secs(30 * 60);
+
+pub struct Actor {
+    db: sqlite_db::Connection,
+    wallet: MessageChannel<wallet::ForwardExcess, Result<wallet::ForwardOutcome>>,
+    cfds: watch::Receiver<Option<Vec<Cfd>>>,
+    /// CFDs whose payout confirmed, `None` until the CFDs were loaded
+    settled: Option<HashSet<OrderId>>,
+    /// When the first payout that was not forwarded yet was noticed
+    pending_since: Option<Instant>,
+}
+
+impl Actor {
+    pub fn new(
+        db: sqlite_db::Connection,
+        wallet: MessageChannel<wallet::ForwardExcess, Result<wallet::ForwardOutcome>>,
+        cfds: watch::Receiver<Option<Vec<Cfd>>>,
+    ) -> Self {
+        Self {
+            db,
+            wallet,
+            cfds,
+            settled: None,
+            pending_since: None,
+        }
+    }
+
+    /// Remember the payouts that confirmed since the last check.
+    fn track_payouts(&mut self, now: Instant) {
+        let settled = match &*self.cfds.borrow() {
+            Some(cfds) => cfds
+                .iter()
+                .filter(|cfd| matches!(cfd.state, CfdState::Closed | CfdState::Refunded))
+                .map(|cfd| cfd.order_id)
+                .collect::<HashSet<_>>(),
+            None => return,
+        };
+
+        match &self.settled {
+            // Payouts that confirmed before the daemon started are not forwarded automatically
+            None => {}
+            Some(known) => {
+                if settled.difference(known).next().is_some() && self.pending_since.is_none() {
+                    self.pending_since = Some(now);
+                }
+            }
+        }
+
+        self.settled = Some(settled);
+    }
+
+    async fn forward(&mut self, now: Instant) -> Result<()> {
+        match self.pending_since {
+            Some(pending_since) if now.duration_since(pending_since) >= BATCH_WINDOW => {}
+            _ => return Ok(()),
+        }
+
+        let policy = match self.db.load_cold_sweep_policy().await? {
+            Some(policy) if policy.enabled => policy,
+            _ => {
+                self.pending_since = None;
+                return Ok(());
+            }
+        };
+
+        let outcome = self
+            .wallet
+            .send(wallet::ForwardExcess {
+                address: policy.address.clone(),
+                threshold: policy.threshold,
+                max_fee_rate: FeeRate::from_sat_per_vb(policy.max_fee_rate),
+            })
+            .await
+            .context("Wallet actor not available")??;
+
+        match outcome {
+            wallet::ForwardOutcome::Forwarded {
+                txid,
+                amount,
+                fee_rate,
+            } => {
+                self.db
+                    .insert_cold_sweep(&ColdSweep {
+                        txid,
+                        address: policy.address,
+                        amount,
+                        fee_rate: fee_rate.as_sat_per_vb(),
+                        created_at: Timestamp::now(),
+                    })
+                    .await?;
+            }
+            wallet::ForwardOutcome::NothingToForward => {
+                tracing::debug!(threshold = %policy.threshold, "Balance does not exceed cold sweep threshold");
+            }
+            wallet::ForwardOutcome::FeeRateTooHigh { estimated } => {
+                tracing::info!(
+                    estimated = %estimated.as_sat_per_vb(),
+                    max = %policy.max_fee_rate,
+                    "Postponing cold sweep because the fee rate is too high"
+                );
+                return Ok(());
+            }
+        }
+
+        self.pending_since = None;
+
+        Ok(())
+    }
+}
+
+/// Get the cold sweep policy, `None` if none was configured.
+#[derive(Clone, Copy)]
+pub struct GetPolicy;
+
+/// Replace the cold sweep policy, e.g. to turn forwarding on or off.
+pub struct SetPolicy(pub ColdSweepPolicy);
+
+/// Get all transactions that forwarded funds to the cold wallet, newest first.
+#[derive(Clone, Copy)]
+pub struct GetHistory;
+
+#[derive(Clone, Copy)]
+struct Check;
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: GetPolicy) -> Result<Option<ColdSweepPolicy>> {
+        self.db.load_cold_sweep_policy().await
+    }
+
+    async fn handle(&mut self, msg: SetPolicy) -> Result<()> {
+        let policy = msg.0;
+
+        anyhow::ensure!(
+            policy.max_fee_rate > 0.0,
+            "Maximum fee rate has to be positive"
+        );
+
+        self.db.upsert_cold_sweep_policy(&policy).await?;
+
+        tracing::info!(
+            enabled = %policy.enabled,
+            address = %policy.address,
+            threshold = %policy.threshold,
+            max_fee_rate = %policy.max_fee_rate,
+            "Updated cold sweep policy"
+        );
+
+        Ok(())
+    }
+
+    async fn handle(&mut self, _: GetHistory) -> Result<Vec<ColdSweep>> {
+        self.db.load_cold_sweeps().await
+    }
+
+    async fn handle(&mut self, _: Check) {
+        let now = Instant::now();
+
+        self.track_payouts(now);
+
+        if let Err(e) = self.forward(now).await {
+            tracing::warn!("Failed to forward funds to cold wallet: {e:#}");
+        }
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we just started");
+
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(CHECK_INTERVAL, || Check, xtras::IncludeSpan::Never),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
@@ -1,5 +0,0 @@
-pub mod maker;
-pub mod protocol;
-pub mod taker;
-
-pub const PROTOCOL: &str = "/itchysats/collab-settlement/2.0.0";
@@ -0,0 +1,279 @@
+//! End-of-day mark-to-market snapshots.
+//!
+//! Every day at the configured UTC time each open CFD is marked at the price it would be closed at
+//! according to the latest quote, together with its unrealized profit, the fees accumulated so far
+//! and the health of its margin. The marks are stored in the database and kept after the CFD was
+//! closed, so the value of the portfolio can be reconstructed for any past day.
+//!
+//! The job checks periodically whether the marks of the day are due. Marks missed while the
+//! daemon was offline are therefore taken once it runs again on the same day, and CFDs that could
+//! not be marked, e.g. because there was no recent quote, are retried. A CFD that opens after the
+//! mark time is marked on its first day as well.
+
+use crate::projection;
+use crate::projection::CfdState;
+use crate::scheduler::Job;
+use anyhow::ensure;
+use anyhow::Context;
+use anyhow::Result;
+use bdk::bitcoin::Amount;
+use bdk::bitcoin::SignedAmount;
+use model::market_closing_price;
+use model::Price;
+use model::Timestamp;
+use rust_decimal::Decimal;
+use sqlite_db::daily_marks::DailyMark;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use time::format_description::FormatItem;
+use time::macros::format_description;
+use time::Date;
+use time::OffsetDateTime;
+use time::Time;
+use tokio::sync::watch;
+
+pub const JOB: &str = "daily_marks";
+
+/// Interval at which the job checks whether the marks of the day are due
+const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Quotes older than this are not used to mark CFDs
+const MAX_QUOTE_AGE_SECS: i64 = 10 * 60;
+
+/// Number of decimal places of the margin health
+const MARGIN_HEALTH_DECIMAL_PLACES: u32 = 4;
+
+/// Format of the date of the marks, e.g. `2022-11-04`
+const DATE_FORMAT: &[FormatItem] = format_description!("[year]-[month]-[day]");
+
+/// Parse a date of the format `YYYY-MM-DD`.
+pub fn parse_date(s: &str) -> Result<Date> {
+    Date::parse(s, DATE_FORMAT)
+        .with_context(|| format!("Expected date in the format YYYY-MM-DD, got {s}"))
+}
+
+/// Time of the day at which CFDs are marked, in UTC
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarkTime(Time);
+
+impl MarkTime {
+    pub fn time(&self) -> Time {
+        self.0
+    }
+}
+
+impl Default for MarkTime {
+    fn default() -> Self {
+        Self(Time::MIDNIGHT)
+    }
+}
+
+impl FromStr for MarkTime {
+    type Err = anyhow::Error;
+
+    /// Parse a time of the format `HH:MM`, e.g. `16:00`.
+    fn from_str(s: &str) -> Result<Self> {
+        let (hour, minute) = s
+            .split_once(':')
+            .context("Expected mark time in the format HH:MM")?;
+        let hour = hour
+            .parse()
+            .with_context(|| format!("Invalid hour: {hour}"))?;
+        let minute = minute
+            .parse()
+            .with_context(|| format!("Invalid minute: {minute}"))?;
+
+        let time = Time::from_hms(hour, minute, 0).context("Invalid mark time")?;
+
+        Ok(Self(time))
+    }
+}
+
+impl fmt::Display for MarkTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.0.hour(), self.0.minute())
+    }
+}
+
+#[derive(Clone)]
+pub struct DailyMarks {
+    db: sqlite_db::Connection,
+    cfds: watch::Receiver<Option<Vec<projection::Cfd>>>,
+    quotes: watch::Receiver<projection::LatestQuotes>,
+    mark_time: MarkTime,
+}
+
+impl DailyMarks {
+    pub fn new(
+        db: sqlite_db::Connection,
+        feeds: &projection::FeedReceivers,
+        mark_time: MarkTime,
+    ) -> Self {
+        Self {
+            db,
+            cfds: feeds.cfds.clone(),
+            quotes: feeds.quote.clone(),
+            mark_time,
+        }
+    }
+
+    /// The marks of all CFDs that were open on `date`.
+    pub async fn load(&self, date: Date) -> Result<Vec<DailyMark>> {
+        self.db.load_daily_marks(date).await
+    }
+
+    pub fn job(&self) -> Job {
+        let daily_marks = self.clone();
+
+        Job::new(JOB, CHECK_INTERVAL, move || {
+            let daily_marks = daily_marks.clone();
+            async move { daily_marks.mark_if_due().await }
+        })
+    }
+
+    async fn mark_if_due(&self) -> Result<()> {
+        let now = OffsetDateTime::now_utc();
+        if now.time() < self.mark_time.time() {
+            return Ok(());
+        }
+
+        let cfds = self
+            .cfds
+            .borrow()
+            .clone()
+            .context("CFDs are not loaded yet")?;
+        let quotes = self.quotes.borrow().clone();
+
+        let mut failed = 0;
+        for cfd in cfds.iter().filter(|cfd| is_open(cfd.state)) {
+            let quote = quotes.get(&cfd.contract_symbol);
+
+            let mark = match mark(cfd, quote, Timestamp::new(now.unix_timestamp())) {
+                Ok(mark) => mark,
+                Err(e) => {
+                    tracing::warn!(order_id = %cfd.order_id, "Failed to mark CFD: {e:#}");
+                    failed += 1;
+                    continue;
+                }
+            };
+
+            if self.db.insert_daily_mark(now.date(), &mark).await? {
+                tracing::debug!(order_id = %cfd.order_id, mark_price = %mark.mark_price, "Marked CFD");
+            }
+        }
+
+        ensure!(failed == 0, "Failed to mark {failed} CFDs");
+
+        Ok(())
+    }
+}
+
+/// Whether the margin of the CFD is locked, i.e. the CFD has to be marked.
+fn is_open(state: CfdState) -> bool {
+    use CfdState::*;
+
+    match state {
+        PendingOpen
+        | Open
+        | PendingCommit
+        | PendingCet
+        | PendingClose
+        | OpenCommitted
+        | IncomingSettlementProposal
+        | OutgoingSettlementProposal
+        | RolloverSetup
+        | PendingRefund => true,
+        PendingSetup | ContractSetup | Rejected | Closed | Refunded | SetupFailed => false,
+    }
+}
+
+fn mark(
+    cfd: &projection::Cfd,
+    quote: Option<&projection::Quote>,
+    now: Timestamp,
+) -> Result<DailyMark> {
+    let quote = quote.with_context(|| format!("No quote for {}", cfd.contract_symbol))?;
+    ensure!(
+        now.seconds() - quote.last_updated_at.seconds() <= MAX_QUOTE_AGE_SECS,
+        "Latest quote for {} is too old",
+        cfd.contract_symbol
+    );
+
+    let mark_price = market_closing_price(
+        Price::new(quote.bid)?,
+        Price::new(quote.ask)?,
+        cfd.role,
+        cfd.position,
+    );
+    let unrealized_pnl = cfd.profit_btc.context("Profit of CFD is not known")?;
+
+    Ok(DailyMark {
+        order_id: cfd.order_id,
+        contract_symbol: cfd.contract_symbol,
+        position: cfd.position,
+        role: cfd.role,
+        quantity: cfd.quantity,
+        mark_price,
+        unrealized_pnl,
+        accumulated_fees: cfd.accumulated_fees,
+        margin: cfd.margin,
+        margin_health: margin_health(cfd.margin, unrealized_pnl)?,
+        marked_at: now,
+    })
+}
+
+/// The share of the margin that is left given the unrealized profit.
+fn margin_health(margin: Amount, unrealized_pnl: SignedAmount) -> Result<Decimal> {
+    ensure!(margin > Amount::ZERO, "CFD without margin");
+
+    let margin = Decimal::from(margin.to_sat());
+    let equity = margin + Decimal::from(unrealized_pnl.to_sat());
+
+    Ok((equity / margin).round_dp(MARGIN_HEALTH_DECIMAL_PLACES))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn mark_time_is_parsed_from_hours_and_minutes() {
+        assert_eq!(
+            "16:30".parse::<MarkTime>().unwrap().time(),
+            Time::from_hms(16, 30, 0).unwrap()
+        );
+        assert_eq!("16:30".parse::<MarkTime>().unwrap().to_string(), "16:30");
+        assert!("24:00".parse::<MarkTime>().is_err());
+        assert!("1630".parse::<MarkTime>().is_err());
+    }
+
+    #[test]
+    fn date_is_parsed_from_year_month_and_day() {
+        assert_eq!(
+            parse_date("2022-11-04").unwrap(),
+            Date::from_calendar_date(2022, time::Month::November, 4).unwrap()
+        );
+        assert!(parse_date("2022-11-31").is_err());
+        assert!(parse_date("04.11.2022").is_err());
+    }
+
+    #[test]
+    fn margin_health_is_share_of_margin_left() {
+        let margin = Amount::from_sat(100_000);
+
+        assert_eq!(
+            margin_health(margin, SignedAmount::from_sat(0)).unwrap(),
+            dec!(1)
+        );
+        assert_eq!(
+            margin_health(margin, SignedAmount::from_sat(-25_000)).unwrap(),
+            dec!(0.75)
+        );
+        assert_eq!(
+            margin_health(margin, SignedAmount::from_sat(50_000)).unwrap(),
+            dec!(1.5)
+        );
+    }
+}
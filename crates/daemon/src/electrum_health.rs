@@ -0,0 +1,343 @@
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use bdk::electrum_client;
+use bdk::electrum_client::ElectrumApi;
+use serde::Serialize;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::sync::watch;
+use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+/// Interval at which the health of the active Electrum endpoint is checked
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Timeout for connecting to and pinging an Electrum endpoint during a health check
+const HEALTH_CHECK_TIMEOUT_SECS: u8 = 10;
+
+/// Health checks that take longer than this are treated as failed
+const MAX_LATENCY: Duration = Duration::from_secs(5);
+
+/// Number of consecutive failed health checks after which we switch to the next endpoint
+const MAX_CONSECUTIVE_FAILURES: u32 = 3;
+
+/// Status of the Electrum endpoint used by the wallet and the monitor
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ElectrumStatus {
+    pub active_endpoint: String,
+    /// Whether the last health check of the active endpoint succeeded
+    pub healthy: bool,
+    /// Round-trip time of the last successful health check in milliseconds
+    pub latency_ms: Option<u64>,
+    /// Total number of configured endpoints, including the active one
+    pub num_endpoints: usize,
+}
+
+#[derive(Clone, Copy)]
+struct CheckHealth;
+
+/// Actor that monitors the health of the active Electrum endpoint
+///
+/// If the active endpoint fails [`MAX_CONSECUTIVE_FAILURES`] health checks in a row, the actor
+/// switches over to the next configured endpoint. Components that hold a connection to Electrum
+/// learn about the switch through an [`EndpointWatcher`].
+pub struct Actor {
+    endpoints: Vec<String>,
+    active: usize,
+    consecutive_failures: u32,
+    sender: watch::Sender<ElectrumStatus>,
+}
+
+impl Actor {
+    /// Creates the actor for the given endpoints, the first one being used initially.
+    pub fn new(endpoints: Vec<String>) -> Result<(Self, watch::Receiver<ElectrumStatus>)> {
+        Self::with_active(endpoints, 0)
+    }
+
+    /// Creates the actor for the given endpoints, starting with the first one that is reachable.
+    ///
+    /// The wallet and the monitor connect to the initial endpoint right away, hence an unreachable
+    /// primary endpoint would prevent the daemon from starting although backups are configured.
+    /// If no endpoint is reachable, the first one is used.
+    pub async fn start(endpoints: Vec<String>) -> Result<(Self, watch::Receiver<ElectrumStatus>)> {
+        let active = tokio::task::spawn_blocking({
+            let endpoints = endpoints.clone();
+            move || first_reachable(&endpoints, ping)
+        })
+        .await
+        .context("Probing Electrum endpoints panicked")?;
+
+        let active = match active {
+            Some(active) => active,
+            None => {
+                tracing::warn!("None of the configured Electrum endpoints is reachable");
+                0
+            }
+        };
+
+        Self::with_active(endpoints, active)
+    }
+
+    fn with_active(
+        endpoints: Vec<String>,
+        active: usize,
+    ) -> Result<(Self, watch::Receiver<ElectrumStatus>)> {
+        let active_endpoint = match endpoints.get(active) {
+            Some(endpoint) => endpoint.clone(),
+            None => bail!("At least one Electrum endpoint has to be configured"),
+        };
+
+        let (sender, receiver) = watch::channel(ElectrumStatus {
+            active_endpoint,
+            healthy: true,
+            latency_ms: None,
+            num_endpoints: endpoints.len(),
+        });
+
+        let actor = Self {
+            endpoints,
+            active,
+            consecutive_failures: 0,
+            sender,
+        };
+
+        Ok((actor, receiver))
+    }
+
+    fn active_endpoint(&self) -> &str {
+        &self.endpoints[self.active]
+    }
+
+    fn switch_to_next_endpoint(&mut self) {
+        let previous = self.active_endpoint().to_owned();
+
+        self.active = (self.active + 1) % self.endpoints.len();
+        self.consecutive_failures = 0;
+
+        let next = self.active_endpoint();
+        tracing::warn!(%previous, %next, "Switching to backup Electrum endpoint");
+
+        ENDPOINT_SWITCH_COUNTER.inc();
+    }
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: CheckHealth) {
+        let endpoint = self.active_endpoint().to_owned();
+
+        let latency = tokio::task::spawn_blocking({
+            let endpoint = endpoint.clone();
+            move || ping(&endpoint)
+        })
+        .await
+        .context("Health check task panicked")
+        .and_then(|result| result);
+
+        let latency = match latency {
+            Ok(latency) if latency <= MAX_LATENCY => {
+                self.consecutive_failures = 0;
+                LATENCY_HISTOGRAM.observe(latency.as_secs_f64());
+
+                Some(latency)
+            }
+            Ok(latency) => {
+                self.consecutive_failures += 1;
+                tracing::warn!(%endpoint, "Electrum endpoint responded slowly: {latency:?}");
+
+                None
+            }
+            Err(e) => {
+                self.consecutive_failures += 1;
+                tracing::warn!(%endpoint, "Electrum health check failed: {e:#}");
+
+                None
+            }
+        };
+
+        if self.consecutive_failures >= MAX_CONSECUTIVE_FAILURES {
+            if self.endpoints.len() > 1 {
+                self.switch_to_next_endpoint();
+            } else {
+                tracing::error!(%endpoint, "Electrum endpoint unavailable and no backup endpoint configured");
+            }
+        }
+
+        let _ = self.sender.send(ElectrumStatus {
+            active_endpoint: self.active_endpoint().to_owned(),
+            healthy: latency.is_some(),
+            latency_ms: latency.map(|latency| latency.as_millis() as u64),
+            num_endpoints: self.endpoints.len(),
+        });
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we just started");
+
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(
+                HEALTH_CHECK_INTERVAL,
+                || CheckHealth,
+                xtras::IncludeSpan::Always,
+            ),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+/// Keeps track of the Electrum endpoint a component is connected to
+///
+/// Components holding a connection to Electrum are expected to check for a changed endpoint
+/// before using their connection and to reconnect if needed.
+pub struct EndpointWatcher {
+    current: String,
+    receiver: watch::Receiver<ElectrumStatus>,
+}
+
+impl EndpointWatcher {
+    pub fn new(receiver: watch::Receiver<ElectrumStatus>) -> Self {
+        let current = receiver.borrow().active_endpoint.clone();
+
+        Self { current, receiver }
+    }
+
+    /// The endpoint the component is currently connected to
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+
+    /// Returns the active endpoint if it differs from the one the component is connected to
+    pub fn changed(&self) -> Option<String> {
+        let active = &self.receiver.borrow().active_endpoint;
+
+        (*active != self.current).then(|| active.clone())
+    }
+
+    /// Record that the component successfully connected to the given endpoint
+    pub fn connected_to(&mut self, endpoint: String) {
+        self.current = endpoint;
+    }
+}
+
+/// Index of the first endpoint that responds to `ping` within [`MAX_LATENCY`].
+fn first_reachable(endpoints: &[String], ping: impl Fn(&str) -> Result<Duration>) -> Option<usize> {
+    endpoints.iter().position(|endpoint| match ping(endpoint) {
+        Ok(latency) if latency <= MAX_LATENCY => true,
+        Ok(latency) => {
+            tracing::warn!(%endpoint, "Electrum endpoint responded slowly on startup: {latency:?}");
+            false
+        }
+        Err(e) => {
+            tracing::warn!(%endpoint, "Electrum endpoint unreachable on startup: {e:#}");
+            false
+        }
+    })
+}
+
+fn ping(endpoint: &str) -> Result<Duration> {
+    let start = Instant::now();
+
+    let client = electrum_client::Client::from_config(
+        endpoint,
+        electrum_client::ConfigBuilder::new()
+            .timeout(Some(HEALTH_CHECK_TIMEOUT_SECS))?
+            .build(),
+    )
+    .context("Failed to connect to Electrum endpoint")?;
+    client.ping().context("Failed to ping Electrum endpoint")?;
+
+    Ok(start.elapsed())
+}
+
+static ENDPOINT_SWITCH_COUNTER: conquer_once::Lazy<prometheus::IntCounter> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_int_counter!(
+            "electrum_endpoint_switches_total",
+            "The number of times we switched to a backup Electrum endpoint."
+        )
+        .unwrap()
+    });
+
+static LATENCY_HISTOGRAM: conquer_once::Lazy<prometheus::Histogram> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_histogram!(
+            "electrum_health_check_latency_seconds",
+            "The round-trip time of health checks against the active Electrum endpoint.",
+            vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.0, 5.0]
+        )
+        .unwrap()
+    });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_no_endpoints_then_error() {
+        assert!(Actor::new(vec![]).is_err());
+    }
+
+    #[test]
+    fn switching_endpoints_wraps_around() {
+        let (mut actor, _) = Actor::new(vec![
+            "tcp://primary:50001".to_owned(),
+            "tcp://backup:50001".to_owned(),
+        ])
+        .unwrap();
+
+        actor.switch_to_next_endpoint();
+        assert_eq!(actor.active_endpoint(), "tcp://backup:50001");
+
+        actor.switch_to_next_endpoint();
+        assert_eq!(actor.active_endpoint(), "tcp://primary:50001");
+    }
+
+    #[test]
+    fn given_unreachable_primary_then_start_with_first_reachable_backup() {
+        let endpoints = vec![
+            "tcp://primary:50001".to_owned(),
+            "tcp://backup-1:50001".to_owned(),
+            "tcp://backup-2:50001".to_owned(),
+        ];
+
+        let active = first_reachable(&endpoints, |endpoint| match endpoint {
+            "tcp://primary:50001" => bail!("connection refused"),
+            _ => Ok(Duration::from_millis(10)),
+        });
+        assert_eq!(active, Some(1));
+
+        let none = first_reachable(&endpoints, |_| bail!("connection refused"));
+        assert_eq!(none, None);
+    }
+
+    #[test]
+    fn endpoint_watcher_reports_changed_endpoint_until_connected() {
+        let (sender, receiver) = watch::channel(ElectrumStatus {
+            active_endpoint: "tcp://primary:50001".to_owned(),
+            healthy: true,
+            latency_ms: None,
+            num_endpoints: 2,
+        });
+        let mut watcher = EndpointWatcher::new(receiver);
+
+        assert_eq!(watcher.changed(), None);
+
+        sender.send_modify(|status| status.active_endpoint = "tcp://backup:50001".to_owned());
+
+        let changed = watcher.changed();
+        assert_eq!(changed, Some("tcp://backup:50001".to_owned()));
+
+        watcher.connected_to(changed.unwrap());
+        assert_eq!(watcher.changed(), None);
+        assert_eq!(watcher.current(), "tcp://backup:50001");
+    }
+}
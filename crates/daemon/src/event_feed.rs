@@ -0,0 +1,82 @@
+//! Paging through all CFD events, e.g. for accounting pipelines.
+//!
+//! See [`sqlite_db::event_feed`] for the guarantees of the feed.
+
+use crate::projection;
+use crate::projection::TimelineEntry;
+use anyhow::Context;
+use anyhow::Result;
+use model::CfdEvent;
+use model::EventKind;
+use model::OrderId;
+use model::Role;
+use serde::Serialize;
+use sqlite_db::event_feed::FeedEvent;
+
+/// Number of events per page if the consumer did not ask for a specific number
+pub const DEFAULT_PAGE_SIZE: u32 = 100;
+
+/// Highest number of events per page
+pub const MAX_PAGE_SIZE: u32 = 1000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Page {
+    pub events: Vec<FeedEvent>,
+    /// Cursor to request the next page with, the sequence number of the last event of this page
+    pub next_after_seq: u64,
+}
+
+/// Reads pages of the event feed.
+#[derive(Clone)]
+pub struct EventFeed {
+    db: sqlite_db::Connection,
+    role: Role,
+}
+
+impl EventFeed {
+    pub fn new(db: sqlite_db::Connection, role: Role) -> Self {
+        Self { db, role }
+    }
+
+    /// Load the events following the event with sequence number `after_seq`.
+    ///
+    /// `limit` is capped at [`MAX_PAGE_SIZE`].
+    pub async fn page(&self, after_seq: u64, limit: Option<u32>) -> Result<Page> {
+        let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+
+        let events = self.db.load_event_feed(after_seq, limit).await?;
+        let next_after_seq = events.last().map_or(after_seq, |event| event.seq);
+
+        Ok(Page {
+            events,
+            next_after_seq,
+        })
+    }
+
+    /// The history of the state of a CFD, see [`projection::timeline`].
+    ///
+    /// Returns `None` if the feed contains no events of the CFD.
+    pub async fn timeline(&self, order_id: OrderId) -> Result<Option<Vec<TimelineEntry>>> {
+        let events = self.db.load_event_feed_of_cfd(order_id).await?;
+        if events.is_empty() {
+            return Ok(None);
+        }
+
+        let events = events
+            .into_iter()
+            .map(|event| {
+                let seq = event.seq;
+                let kind = EventKind::from_json(event.name, event.data.to_string())
+                    .with_context(|| format!("Failed to decode event {seq}"))?;
+
+                Ok(CfdEvent {
+                    timestamp: event.created_at,
+                    id: event.order_id,
+                    event: kind,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Some(projection::timeline(self.role, events)))
+    }
+}
@@ -0,0 +1,137 @@
+//! Opt-in reports of failed protocols to the counterparty.
+//!
+//! If contract setup or rollover fails on the taker side, the maker usually only sees a dropped
+//! substream. Takers that opted in send an anonymized report about the failure to the maker: the
+//! failed step, a coarse category of the error and their daemon version. No error messages,
+//! amounts or addresses are included. The maker archives the reports and aggregates them in
+//! metrics to speed up support triage.
+
+use anyhow::Error;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fmt;
+
+pub mod maker;
+mod protocol;
+pub mod taker;
+
+pub const PROTOCOL: &str = "/itchysats/failure-report/1.0.0";
+
+/// Maximum size of an encoded failure report in bytes
+pub const MAX_FRAME_SIZE: usize = 256 * 1024;
+
+/// The step of the CFD lifecycle that failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    ContractSetup,
+    Rollover,
+}
+
+/// Coarse category of the error that made the protocol fail
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    /// The counterparty did not respond in time
+    Timeout,
+    /// The connection to the counterparty failed
+    Network,
+    /// A message of the counterparty could not be decoded
+    Protocol,
+    /// Any other error, e.g. in the wallet or the database
+    Internal,
+}
+
+impl Category {
+    /// Categorize the error by the first error in its chain that hints at the cause.
+    pub fn of(error: &Error) -> Self {
+        error
+            .chain()
+            .find_map(|cause| {
+                if cause.is::<tokio::time::error::Elapsed>() {
+                    return Some(Category::Timeout);
+                }
+
+                if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+                    return Some(match e.kind() {
+                        std::io::ErrorKind::TimedOut => Category::Timeout,
+                        std::io::ErrorKind::InvalidData => Category::Protocol,
+                        _ => Category::Network,
+                    });
+                }
+
+                if cause.is::<xtra_libp2p::Error>() {
+                    return Some(Category::Network);
+                }
+
+                if cause.is::<asynchronous_codec::JsonCodecError>()
+                    || cause.is::<serde_json::Error>()
+                {
+                    return Some(Category::Protocol);
+                }
+
+                None
+            })
+            .unwrap_or(Category::Internal)
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Step::ContractSetup => "contract_setup",
+            Step::Rollover => "rollover",
+        };
+
+        s.fmt(f)
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Category::Timeout => "timeout",
+            Category::Network => "network",
+            Category::Protocol => "protocol",
+            Category::Internal => "internal",
+        };
+
+        s.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use anyhow::Context;
+
+    #[tokio::test]
+    async fn elapsed_timeout_is_categorized_as_timeout() {
+        #[allow(clippy::disallowed_methods)]
+        let elapsed =
+            tokio::time::timeout(std::time::Duration::ZERO, futures::future::pending::<()>())
+                .await
+                .unwrap_err();
+
+        let error = Error::new(elapsed).context("The maker did not respond");
+
+        assert_eq!(Category::of(&error), Category::Timeout);
+    }
+
+    #[test]
+    fn decoding_error_is_categorized_as_protocol() {
+        let error = serde_json::from_str::<u64>("not a number")
+            .context("Failed to decode message")
+            .unwrap_err();
+
+        assert_eq!(Category::of(&error), Category::Protocol);
+    }
+
+    #[test]
+    fn unknown_error_is_categorized_as_internal() {
+        let error = anyhow!("Insufficient funds").context("Failed to build party params");
+
+        assert_eq!(Category::of(&error), Category::Internal);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::failure_report::protocol::Report;
+use crate::failure_report::MAX_FRAME_SIZE;
+use anyhow::Context;
+use async_trait::async_trait;
+use asynchronous_codec::FramedRead;
+use futures::StreamExt;
+use model::Timestamp;
+use sqlite_db::protocol_failures::ProtocolFailure;
+use std::time::Duration;
+use tokio_extras::FutureExt;
+use xtra_libp2p::limited::LimitedJsonCodec;
+use xtra_libp2p::NewInboundSubstream;
+use xtra_productivity::xtra_productivity;
+
+const REPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Archives failure reports sent by takers
+pub struct Actor {
+    db: sqlite_db::Connection,
+}
+
+impl Actor {
+    pub fn new(db: sqlite_db::Connection) -> Self {
+        Self { db }
+    }
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: NewInboundSubstream, ctx: &mut xtra::Context<Self>) {
+        let NewInboundSubstream { peer_id, stream } = msg;
+        let db = self.db.clone();
+
+        let task = async move {
+            let mut framed =
+                FramedRead::new(stream, LimitedJsonCodec::<(), Report>::new(MAX_FRAME_SIZE));
+
+            let Report {
+                order_id,
+                step,
+                category,
+                daemon_version,
+            } = framed
+                .next()
+                .timeout(REPORT_TIMEOUT, || {
+                    tracing::debug_span!("receive failure report")
+                })
+                .await
+                .context("Taker did not send report in time")?
+                .context("Stream terminated")?
+                .context("Failed to decode report")?;
+
+            let daemon_version = sanitize_version(daemon_version);
+
+            tracing::info!(%peer_id, %order_id, %step, %category, %daemon_version, "Taker reported protocol failure");
+
+            PROTOCOL_FAILURES_REPORTED
+                .with_label_values(&[&step.to_string(), &category.to_string(), &daemon_version])
+                .inc();
+
+            db.insert_protocol_failure(&ProtocolFailure {
+                peer_id: peer_id.into(),
+                order_id,
+                step: step.to_string(),
+                category: category.to_string(),
+                daemon_version,
+                timestamp: Timestamp::now(),
+            })
+            .await?;
+
+            anyhow::Ok(())
+        };
+
+        let err_handler = move |e: anyhow::Error| async move {
+            tracing::debug!(%peer_id, "Failed to archive failure report: {e:#}")
+        };
+
+        let this = ctx.address().expect("self to be alive");
+        tokio_extras::spawn_fallible(&this, task, err_handler);
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+/// Maximum length of the reported daemon version
+const MAX_VERSION_LEN: usize = 32;
+
+/// The version is reported by the taker and used as metrics label, hence has to be restricted to
+/// prevent flooding the metrics with arbitrary labels.
+fn sanitize_version(version: String) -> String {
+    let is_valid = version.len() <= MAX_VERSION_LEN
+        && version
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+'));
+
+    if is_valid {
+        version
+    } else {
+        "invalid".to_owned()
+    }
+}
+
+const STEP_LABEL: &str = "step";
+const CATEGORY_LABEL: &str = "category";
+const VERSION_LABEL: &str = "version";
+
+static PROTOCOL_FAILURES_REPORTED: conquer_once::Lazy<prometheus::IntCounterVec> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_int_counter_vec!(
+            "protocol_failures_reported_total",
+            "The number of protocol failures reported by takers.",
+            &[STEP_LABEL, CATEGORY_LABEL, VERSION_LABEL]
+        )
+        .unwrap()
+    });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_valid_version_then_unchanged() {
+        assert_eq!(sanitize_version("0.7.0-rc.1".to_owned()), "0.7.0-rc.1");
+    }
+
+    #[test]
+    fn given_arbitrary_version_then_replaced() {
+        assert_eq!(sanitize_version("0.7.0\"}".to_owned()), "invalid");
+        assert_eq!(sanitize_version("1".repeat(MAX_VERSION_LEN + 1)), "invalid");
+    }
+}
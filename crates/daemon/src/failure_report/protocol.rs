@@ -0,0 +1,13 @@
+use crate::failure_report::Category;
+use crate::failure_report::Step;
+use model::OrderId;
+use serde::Deserialize;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Report {
+    pub order_id: OrderId,
+    pub step: Step,
+    pub category: Category,
+    pub daemon_version: String,
+}
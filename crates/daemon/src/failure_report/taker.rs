@@ -0,0 +1,109 @@
+use crate::failure_report::protocol::Report;
+use crate::failure_report::Category;
+use crate::failure_report::Step;
+use crate::failure_report::MAX_FRAME_SIZE;
+use crate::failure_report::PROTOCOL;
+use anyhow::Context;
+use async_trait::async_trait;
+use asynchronous_codec::FramedWrite;
+use futures::SinkExt;
+use libp2p_core::PeerId;
+use model::OrderId;
+use xtra_libp2p::limited::LimitedJsonCodec;
+use xtra_libp2p::Endpoint;
+use xtra_libp2p::OpenSubstream;
+use xtra_productivity::xtra_productivity;
+
+/// Sends anonymized reports of failed protocols to the maker
+pub struct Actor {
+    endpoint: xtra::Address<Endpoint>,
+}
+
+impl Actor {
+    pub fn new(endpoint: xtra::Address<Endpoint>) -> Self {
+        Self { endpoint }
+    }
+
+    fn report(&self, peer_id: PeerId, report: Report, ctx: &mut xtra::Context<Self>) {
+        let endpoint = self.endpoint.clone();
+        let order_id = report.order_id;
+
+        let task = async move {
+            let stream = endpoint
+                .send(OpenSubstream::single_protocol(peer_id, PROTOCOL))
+                .await
+                .context("Endpoint is disconnected")?
+                .context("No connection to peer")?
+                .await
+                .context("Failed to open substream")?;
+
+            let mut framed =
+                FramedWrite::new(stream, LimitedJsonCodec::<Report, ()>::new(MAX_FRAME_SIZE));
+            framed.send(report).await?;
+
+            anyhow::Ok(())
+        };
+
+        let err_handler = move |e: anyhow::Error| async move {
+            tracing::debug!(%order_id, %peer_id, "Failed to send failure report: {e:#}")
+        };
+
+        let this = ctx.address().expect("self to be alive");
+        tokio_extras::spawn_fallible(&this, task, err_handler);
+    }
+}
+
+/// Emitted if contract setup failed on the taker side
+pub struct ContractSetupFailed {
+    pub order_id: OrderId,
+    pub maker_peer_id: PeerId,
+    pub error: anyhow::Error,
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: ContractSetupFailed, ctx: &mut xtra::Context<Self>) {
+        let ContractSetupFailed {
+            order_id,
+            maker_peer_id,
+            error,
+        } = msg;
+
+        let report = Report {
+            order_id,
+            step: Step::ContractSetup,
+            category: Category::of(&error),
+            daemon_version: crate::version(),
+        };
+
+        self.report(maker_peer_id, report, ctx);
+    }
+
+    async fn handle(
+        &mut self,
+        msg: rollover::taker::RolloverFailed,
+        ctx: &mut xtra::Context<Self>,
+    ) {
+        let rollover::taker::RolloverFailed {
+            order_id,
+            maker_peer_id,
+            error,
+        } = msg;
+
+        let report = Report {
+            order_id,
+            step: Step::Rollover,
+            category: Category::of(&error),
+            daemon_version: crate::version(),
+        };
+
+        self.report(maker_peer_id.inner(), report, ctx);
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn stopped(self) -> Self::Stop {}
+}
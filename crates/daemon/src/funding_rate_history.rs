@@ -0,0 +1,18 @@
+//! Protocol through which takers query the funding rates the maker offered in the past.
+//!
+//! The maker records its funding rates whenever they change. Takers can request the history of
+//! the past days to evaluate the cost trend of holding positions with this maker.
+
+pub mod maker;
+mod protocol;
+pub mod taker;
+
+pub use sqlite_db::funding_rate_history::FundingRateEntry;
+
+pub const PROTOCOL: &str = "/itchysats/funding-rate-history/1.0.0";
+
+/// Maximum size of an encoded request or response in bytes
+pub const MAX_FRAME_SIZE: usize = 1024 * 1024;
+
+/// Maximum number of days of funding rate history a taker can request
+pub const MAX_DAYS: u32 = 90;
@@ -0,0 +1,113 @@
+use crate::funding_rate_history::protocol::Request;
+use crate::funding_rate_history::protocol::Response;
+use crate::funding_rate_history::FundingRateEntry;
+use crate::funding_rate_history::MAX_DAYS;
+use crate::funding_rate_history::MAX_FRAME_SIZE;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use asynchronous_codec::Framed;
+use futures::SinkExt;
+use futures::StreamExt;
+use model::Timestamp;
+use std::time::Duration;
+use tokio_extras::FutureExt;
+use xtra_libp2p::limited::LimitedJsonCodec;
+use xtra_libp2p::NewInboundSubstream;
+use xtra_productivity::xtra_productivity;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Records the maker's funding rates and answers requests for the funding rate history
+pub struct Actor {
+    db: sqlite_db::Connection,
+}
+
+impl Actor {
+    pub fn new(db: sqlite_db::Connection) -> Self {
+        Self { db }
+    }
+}
+
+/// Record the funding rates of newly published offers.
+pub struct RecordFundingRates(pub Vec<model::Offer>);
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: RecordFundingRates) -> Result<()> {
+        let timestamp = Timestamp::now();
+
+        for offer in msg.0 {
+            let entry = FundingRateEntry {
+                contract_symbol: offer.contract_symbol,
+                position_maker: offer.position_maker,
+                funding_rate: offer.funding_rate,
+                timestamp,
+            };
+
+            if self.db.record_funding_rate(entry).await? {
+                tracing::debug!(
+                    contract_symbol = %entry.contract_symbol,
+                    position_maker = ?entry.position_maker,
+                    funding_rate = %entry.funding_rate,
+                    "Recorded changed funding rate"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn handle(&mut self, msg: NewInboundSubstream, ctx: &mut xtra::Context<Self>) {
+        let NewInboundSubstream { peer_id, stream } = msg;
+        let db = self.db.clone();
+
+        let task = async move {
+            let mut framed = Framed::new(
+                stream,
+                LimitedJsonCodec::<Response, Request>::new(MAX_FRAME_SIZE),
+            );
+
+            let Request { days } = framed
+                .next()
+                .timeout(REQUEST_TIMEOUT, || {
+                    tracing::debug_span!("receive funding rate history request")
+                })
+                .await
+                .context("Taker did not send request in time")?
+                .context("Stream terminated")?
+                .context("Failed to decode request")?;
+
+            let days = days.min(MAX_DAYS);
+            let since =
+                Timestamp::new(Timestamp::now().seconds() - i64::from(days) * SECONDS_PER_DAY);
+
+            let entries = db
+                .load_funding_rate_history(since)
+                .await?
+                .into_iter()
+                .map(Into::into)
+                .collect();
+
+            framed.send(Response { entries }).await?;
+
+            anyhow::Ok(())
+        };
+
+        let err_handler = move |e: anyhow::Error| async move {
+            tracing::debug!(%peer_id, "Failed to answer funding rate history request: {e:#}")
+        };
+
+        let this = ctx.address().expect("self to be alive");
+        tokio_extras::spawn_fallible(&this, task, err_handler);
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn stopped(self) -> Self::Stop {}
+}
@@ -0,0 +1,47 @@
+use crate::funding_rate_history::FundingRateEntry;
+use model::ContractSymbol;
+use model::FundingRate;
+use model::Position;
+use model::Timestamp;
+use serde::Deserialize;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct Request {
+    pub days: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Response {
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct Entry {
+    contract_symbol: ContractSymbol,
+    position_maker: Position,
+    funding_rate: FundingRate,
+    timestamp: Timestamp,
+}
+
+impl From<FundingRateEntry> for Entry {
+    fn from(entry: FundingRateEntry) -> Self {
+        Self {
+            contract_symbol: entry.contract_symbol,
+            position_maker: entry.position_maker,
+            funding_rate: entry.funding_rate,
+            timestamp: entry.timestamp,
+        }
+    }
+}
+
+impl From<Entry> for FundingRateEntry {
+    fn from(entry: Entry) -> Self {
+        Self {
+            contract_symbol: entry.contract_symbol,
+            position_maker: entry.position_maker,
+            funding_rate: entry.funding_rate,
+            timestamp: entry.timestamp,
+        }
+    }
+}
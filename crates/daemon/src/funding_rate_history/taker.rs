@@ -0,0 +1,90 @@
+use crate::funding_rate_history::protocol::Request;
+use crate::funding_rate_history::protocol::Response;
+use crate::funding_rate_history::FundingRateEntry;
+use crate::funding_rate_history::MAX_FRAME_SIZE;
+use crate::funding_rate_history::PROTOCOL;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use asynchronous_codec::Framed;
+use futures::SinkExt;
+use futures::StreamExt;
+use libp2p_core::PeerId;
+use std::time::Duration;
+use tokio_extras::FutureExt;
+use xtra_libp2p::limited::LimitedJsonCodec;
+use xtra_libp2p::Endpoint;
+use xtra_libp2p::OpenSubstream;
+use xtra_productivity::xtra_productivity;
+
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Queries the funding rate history of a maker
+pub struct Actor {
+    endpoint: xtra::Address<Endpoint>,
+}
+
+impl Actor {
+    pub fn new(endpoint: xtra::Address<Endpoint>) -> Self {
+        Self { endpoint }
+    }
+}
+
+/// Request the funding rates the maker offered during the past `days`.
+///
+/// The maker caps `days` at [`MAX_DAYS`](crate::funding_rate_history::MAX_DAYS).
+#[derive(Debug, Clone, Copy)]
+pub struct GetFundingRateHistory {
+    pub maker_peer_id: PeerId,
+    pub days: u32,
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: GetFundingRateHistory) -> Result<Vec<FundingRateEntry>> {
+        let GetFundingRateHistory {
+            maker_peer_id,
+            days,
+        } = msg;
+
+        let stream = self
+            .endpoint
+            .send(OpenSubstream::single_protocol(maker_peer_id, PROTOCOL))
+            .await
+            .context("Endpoint is disconnected")?
+            .context("No connection to peer")?
+            .await
+            .context("Failed to open substream")?;
+
+        let mut framed = Framed::new(
+            stream,
+            LimitedJsonCodec::<Request, Response>::new(MAX_FRAME_SIZE),
+        );
+
+        framed.send(Request { days }).await?;
+
+        let Response { entries } = framed
+            .next()
+            .timeout(RESPONSE_TIMEOUT, || {
+                tracing::debug_span!("receive funding rate history")
+            })
+            .await
+            .with_context(|| {
+                format!(
+                    "The maker did not respond within {} seconds",
+                    RESPONSE_TIMEOUT.as_secs()
+                )
+            })?
+            .context("Stream terminated")?
+            .context("Failed to decode funding rate history")?;
+
+        Ok(entries.into_iter().map(Into::into).collect())
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn stopped(self) -> Self::Stop {}
+}
@@ -0,0 +1,341 @@
+//! Health of the components the daemon depends on.
+//!
+//! All components are checked upon request, so that load balancers probing the health check
+//! endpoint always get a recent answer. The daemon is only healthy if all components are healthy.
+
+use crate::electrum_health::ElectrumStatus;
+use crate::oracle;
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use model::olivia;
+use model::ContractSymbol;
+use model::Role;
+use model::Timestamp;
+use model::WalletInfo;
+use model::SETTLEMENT_INTERVAL;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+use std::time::Instant;
+use strum::IntoEnumIterator;
+use time::OffsetDateTime;
+use tokio::sync::watch;
+use tokio_extras::FutureExt;
+use xtra::prelude::MessageChannel;
+use xtra_bitmex_price_feed::GetLatestQuotes;
+use xtra_bitmex_price_feed::LatestQuotes;
+use xtra_libp2p::endpoint;
+use xtra_libp2p::Endpoint;
+use xtra_productivity::xtra_productivity;
+
+/// Timeout for checking a single component
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Quotes older than this are considered stale
+///
+/// BitMex publishes a quote every minute.
+const MAX_QUOTE_AGE: time::Duration = time::Duration::minutes(5);
+
+/// The wallet is considered out of sync if it was not synced for this long
+///
+/// The wallet is synced every three minutes, this allows for a few failed syncs.
+const MAX_WALLET_SYNC_AGE: time::Duration = time::Duration::minutes(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Healthy,
+    Unhealthy,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ComponentHealth {
+    pub status: Status,
+    /// When the component was last found to be healthy
+    pub last_healthy_at: Option<Timestamp>,
+    /// Round-trip time of the check in milliseconds, only reported for remote components
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// Why the component is unhealthy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Health {
+    /// Healthy if all components are healthy
+    pub status: Status,
+    pub checked_at: Timestamp,
+    /// The database can be queried
+    pub database: ComponentHealth,
+    /// The active Electrum endpoint responds
+    pub electrum: ComponentHealth,
+    /// Quotes for all contract symbols are recent
+    pub price_feed: ComponentHealth,
+    /// The oracle announcements for new positions have been fetched
+    pub oracle: ComponentHealth,
+    /// The maker listens for connections, the taker is connected to the maker
+    pub libp2p: ComponentHealth,
+    /// The wallet was synced recently
+    pub wallet: ComponentHealth,
+}
+
+impl Health {
+    pub fn is_healthy(&self) -> bool {
+        self.status == Status::Healthy
+    }
+}
+
+/// Check the health of all components.
+#[derive(Clone, Copy)]
+pub struct GetHealth;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Component {
+    Database,
+    Electrum,
+    PriceFeed,
+    Oracle,
+    Libp2p,
+    Wallet,
+}
+
+/// Outcome of checking a single component
+struct Check {
+    result: Result<()>,
+    latency_ms: Option<u64>,
+}
+
+impl Check {
+    fn new(result: Result<()>) -> Self {
+        Self {
+            result,
+            latency_ms: None,
+        }
+    }
+
+    /// Check a remote component, measuring the round-trip time of the check.
+    async fn timed(check: impl Future<Output = Result<()>>) -> Self {
+        let start = Instant::now();
+
+        let result = check
+            .timeout(CHECK_TIMEOUT, || tracing::debug_span!("health check"))
+            .await
+            .with_context(|| {
+                format!(
+                    "Check did not complete within {} seconds",
+                    CHECK_TIMEOUT.as_secs()
+                )
+            })
+            .and_then(|result| result);
+
+        Self {
+            result,
+            latency_ms: Some(start.elapsed().as_millis() as u64),
+        }
+    }
+}
+
+pub struct Actor {
+    db: sqlite_db::Connection,
+    electrum_status: watch::Receiver<ElectrumStatus>,
+    wallet_info: watch::Receiver<Option<WalletInfo>>,
+    price_feed: MessageChannel<GetLatestQuotes, LatestQuotes>,
+    oracle: MessageChannel<
+        oracle::GetAnnouncements,
+        Result<Vec<olivia::Announcement>, oracle::NoAnnouncement>,
+    >,
+    endpoint: xtra::Address<Endpoint>,
+    role: Role,
+    last_healthy_at: HashMap<Component, Timestamp>,
+}
+
+impl Actor {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        db: sqlite_db::Connection,
+        electrum_status: watch::Receiver<ElectrumStatus>,
+        wallet_info: watch::Receiver<Option<WalletInfo>>,
+        price_feed: MessageChannel<GetLatestQuotes, LatestQuotes>,
+        oracle: MessageChannel<
+            oracle::GetAnnouncements,
+            Result<Vec<olivia::Announcement>, oracle::NoAnnouncement>,
+        >,
+        endpoint: xtra::Address<Endpoint>,
+        role: Role,
+    ) -> Self {
+        Self {
+            db,
+            electrum_status,
+            wallet_info,
+            price_feed,
+            oracle,
+            endpoint,
+            role,
+            last_healthy_at: HashMap::new(),
+        }
+    }
+
+    async fn check_database(&self) -> Check {
+        Check::timed(self.db.ping()).await
+    }
+
+    fn check_electrum(&self) -> Check {
+        let status = self.electrum_status.borrow();
+
+        let result = if status.healthy {
+            Ok(())
+        } else {
+            Err(anyhow!("Active Electrum endpoint is not responding"))
+        };
+
+        Check {
+            result,
+            latency_ms: status.latency_ms,
+        }
+    }
+
+    async fn check_price_feed(&self) -> Result<()> {
+        let quotes = self
+            .price_feed
+            .send(GetLatestQuotes)
+            .await
+            .context("Price feed actor not available")?;
+
+        let oldest = quotes
+            .values()
+            .map(|quote| quote.timestamp)
+            .min()
+            .context("No quotes received yet")?;
+
+        let age = OffsetDateTime::now_utc() - oldest;
+        if age > MAX_QUOTE_AGE {
+            bail!("Oldest quote is {} seconds old", age.whole_seconds());
+        }
+
+        Ok(())
+    }
+
+    async fn check_oracle(&self) -> Result<()> {
+        let event_ids = ContractSymbol::iter()
+            .map(|symbol| {
+                olivia::next_announcement_after(
+                    OffsetDateTime::now_utc() + SETTLEMENT_INTERVAL,
+                    symbol,
+                )
+            })
+            .collect();
+
+        self.oracle
+            .send(oracle::GetAnnouncements(event_ids))
+            .await
+            .context("Oracle actor not available")??;
+
+        Ok(())
+    }
+
+    async fn check_libp2p(&self) -> Result<()> {
+        let stats = self
+            .endpoint
+            .send(endpoint::GetConnectionStats)
+            .await
+            .context("Endpoint not available")?;
+
+        match self.role {
+            Role::Maker if stats.listen_addresses.is_empty() => {
+                bail!("Not listening on any address")
+            }
+            Role::Taker if stats.connected_peers.is_empty() => bail!("Not connected to the maker"),
+            Role::Maker | Role::Taker => Ok(()),
+        }
+    }
+
+    fn check_wallet(&self) -> Result<()> {
+        let last_updated_at = match &*self.wallet_info.borrow() {
+            Some(wallet_info) => wallet_info.last_updated_at,
+            None => bail!("Wallet was not synced yet"),
+        };
+
+        let age = Timestamp::now().seconds() - last_updated_at.seconds();
+        if age > MAX_WALLET_SYNC_AGE.whole_seconds() {
+            bail!("Wallet was last synced {age} seconds ago");
+        }
+
+        Ok(())
+    }
+
+    fn record(&mut self, component: Component, check: Check, now: Timestamp) -> ComponentHealth {
+        let (status, error) = match check.result {
+            Ok(()) => {
+                self.last_healthy_at.insert(component, now);
+                (Status::Healthy, None)
+            }
+            Err(e) => {
+                tracing::debug!(?component, "Component is unhealthy: {e:#}");
+                (Status::Unhealthy, Some(format!("{e:#}")))
+            }
+        };
+
+        ComponentHealth {
+            status,
+            last_healthy_at: self.last_healthy_at.get(&component).copied(),
+            latency_ms: check.latency_ms,
+            error,
+        }
+    }
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: GetHealth) -> Health {
+        let (database, price_feed, oracle, libp2p) = futures::join!(
+            self.check_database(),
+            self.check_price_feed(),
+            self.check_oracle(),
+            self.check_libp2p()
+        );
+        let price_feed = Check::new(price_feed);
+        let oracle = Check::new(oracle);
+        let libp2p = Check::new(libp2p);
+        let electrum = self.check_electrum();
+        let wallet = Check::new(self.check_wallet());
+
+        let now = Timestamp::now();
+        let database = self.record(Component::Database, database, now);
+        let electrum = self.record(Component::Electrum, electrum, now);
+        let price_feed = self.record(Component::PriceFeed, price_feed, now);
+        let oracle = self.record(Component::Oracle, oracle, now);
+        let libp2p = self.record(Component::Libp2p, libp2p, now);
+        let wallet = self.record(Component::Wallet, wallet, now);
+
+        let all_healthy = [&database, &electrum, &price_feed, &oracle, &libp2p, &wallet]
+            .iter()
+            .all(|component| component.status == Status::Healthy);
+
+        Health {
+            status: if all_healthy {
+                Status::Healthy
+            } else {
+                Status::Unhealthy
+            },
+            checked_at: now,
+            database,
+            electrum,
+            price_feed,
+            oracle,
+            libp2p,
+            wallet,
+        }
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn stopped(self) -> Self::Stop {}
+}
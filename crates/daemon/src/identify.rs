@@ -1,5 +1,6 @@
 use crate::Environment;
 use std::collections::HashSet;
+use std::time::Duration;
 
 pub mod dialer;
 pub mod listener;
@@ -7,12 +8,17 @@ pub mod protocol;
 
 pub const PROTOCOL: &str = "/itchysats/id/1.0.0";
 
+/// Maximum size of an encoded identify message in bytes
+pub const MAX_FRAME_SIZE: usize = 64 * 1024;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PeerInfo {
     pub wire_version: String,
     pub daemon_version: String,
     pub environment: Environment,
     pub protocols: HashSet<String>,
+    /// Longest interval between pings the peer tolerates before considering the connection dead
+    pub max_ping_interval: Option<Duration>,
 }
 
 impl TryFrom<protocol::IdentifyMsg> for PeerInfo {
@@ -24,6 +30,7 @@ impl TryFrom<protocol::IdentifyMsg> for PeerInfo {
             daemon_version: identity_msg.daemon_version()?,
             environment: identity_msg.environment().into(),
             protocols: identity_msg.protocols(),
+            max_ping_interval: identity_msg.max_ping_interval(),
         };
 
         Ok(identity_info)
@@ -103,6 +110,7 @@ mod tests {
             daemon_version: "0.4.22".to_string(),
             environment: Environment::unknown(),
             protocols: HashSet::from(["some_maker_protocol".to_string()]),
+            max_ping_interval: None,
         };
 
         let expected_taker_peer_info = PeerInfo {
@@ -110,6 +118,7 @@ mod tests {
             daemon_version: "0.4.22".to_string(),
             environment: Environment::new("umbrel"),
             protocols: HashSet::from(["some_taker_protocol".to_string()]),
+            max_ping_interval: None,
         };
 
         assert_eq!(maker_peer_info, expected_maker_peer_info);
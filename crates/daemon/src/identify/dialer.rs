@@ -5,6 +5,7 @@ use async_trait::async_trait;
 use std::collections::HashMap;
 use tokio::sync::watch;
 use tokio_extras::spawn_fallible;
+use xtra::prelude::MessageChannel;
 use xtra::Address;
 use xtra::Context;
 use xtra_libp2p::endpoint;
@@ -18,6 +19,14 @@ pub struct Actor {
     endpoint: Address<Endpoint>,
     peer_infos: HashMap<PeerId, PeerInfo>,
     peer_info_channel: Option<watch::Sender<Option<PeerInfo>>>,
+    peer_identified_subscriber: Option<MessageChannel<PeerIdentified, ()>>,
+}
+
+/// Notifies a subscriber about the peer info a connected peer sent us.
+#[derive(Debug, Clone)]
+pub struct PeerIdentified {
+    pub peer_id: PeerId,
+    pub peer_info: PeerInfo,
 }
 
 impl Actor {
@@ -28,6 +37,15 @@ impl Actor {
             endpoint,
             peer_infos: HashMap::default(),
             peer_info_channel: None,
+            peer_identified_subscriber: None,
+        }
+    }
+
+    /// Notify `subscriber` about the peer info of every connected peer.
+    pub fn with_subscriber(self, subscriber: MessageChannel<PeerIdentified, ()>) -> Self {
+        Self {
+            peer_identified_subscriber: Some(subscriber),
+            ..self
         }
     }
 
@@ -43,6 +61,7 @@ impl Actor {
                 endpoint,
                 peer_infos: HashMap::default(),
                 peer_info_channel: Some(sender),
+                peer_identified_subscriber: None,
             },
             receiver,
         )
@@ -95,6 +114,15 @@ impl Actor {
                 .inc();
         }
 
+        if let Some(subscriber) = &self.peer_identified_subscriber {
+            subscriber
+                .send_async_next(PeerIdentified {
+                    peer_id,
+                    peer_info: peer_info.clone(),
+                })
+                .await;
+        }
+
         if let Some(peer_info_channel) = &self.peer_info_channel {
             if let Err(e) = peer_info_channel.send(Some(peer_info)) {
                 tracing::warn!("Failed to send identity info to notify channel: {e:#}");
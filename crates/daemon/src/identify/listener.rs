@@ -4,6 +4,7 @@ use async_trait::async_trait;
 use libp2p_core::Multiaddr;
 use libp2p_core::PublicKey;
 use std::collections::HashSet;
+use std::time::Duration;
 use tokio_extras::spawn_fallible;
 use xtra::Context;
 use xtra_libp2p::NewInboundSubstream;
@@ -15,6 +16,7 @@ pub struct Actor {
     identity: PublicKey,
     listen_addrs: HashSet<Multiaddr>,
     protocols: HashSet<String>,
+    max_ping_interval: Option<Duration>,
 }
 
 impl Actor {
@@ -31,6 +33,15 @@ impl Actor {
             identity,
             listen_addrs,
             protocols,
+            max_ping_interval: None,
+        }
+    }
+
+    /// Announce the longest interval between pings we tolerate to connecting peers.
+    pub fn with_max_ping_interval(self, max_ping_interval: Duration) -> Self {
+        Self {
+            max_ping_interval: Some(max_ping_interval),
+            ..self
         }
     }
 }
@@ -49,7 +60,8 @@ impl Actor {
             self.listen_addrs.clone(),
             Multiaddr::empty(),
             self.protocols.clone(),
-        );
+        )
+        .with_max_ping_interval(self.max_ping_interval);
 
         let send_identify_msg_fut = protocol::send(stream, identify_msg);
 
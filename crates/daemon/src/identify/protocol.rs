@@ -1,9 +1,9 @@
+use crate::identify::MAX_FRAME_SIZE;
 use crate::Multiaddr;
 use anyhow::Context;
 use anyhow::Result;
 use asynchronous_codec::FramedRead;
 use asynchronous_codec::FramedWrite;
-use asynchronous_codec::JsonCodec;
 use futures::AsyncReadExt;
 use futures::AsyncWriteExt;
 use futures::SinkExt;
@@ -15,6 +15,7 @@ use std::collections::HashSet;
 use std::string::ToString;
 use std::time::Duration;
 use tokio_extras::FutureExt;
+use xtra_libp2p::limited::LimitedJsonCodec;
 
 // Start libp2p based protocols from 0.3.0 since the last wire version was 0.2.1
 const PROTOCOL_VERSION: &str = "0.3.0";
@@ -32,6 +33,11 @@ pub struct IdentifyMsg {
 
     /// Optional environment field that is not part of the identify spec
     environment: Option<Environment>,
+
+    /// Optional longest interval in seconds between pings the peer tolerates, not part of the
+    /// identify spec
+    #[serde(default)]
+    max_ping_interval_secs: Option<u64>,
 }
 
 impl IdentifyMsg {
@@ -53,6 +59,14 @@ impl IdentifyMsg {
             observed_addr,
             protocols,
             environment: Some(environment),
+            max_ping_interval_secs: None,
+        }
+    }
+
+    pub fn with_max_ping_interval(self, max_ping_interval: Option<Duration>) -> Self {
+        Self {
+            max_ping_interval_secs: max_ping_interval.map(|interval| interval.as_secs()),
+            ..self
         }
     }
 
@@ -78,13 +92,20 @@ impl IdentifyMsg {
     pub fn protocols(&self) -> HashSet<String> {
         self.protocols.clone()
     }
+
+    pub fn max_ping_interval(&self) -> Option<Duration> {
+        self.max_ping_interval_secs.map(Duration::from_secs)
+    }
 }
 
 pub(crate) async fn recv<S>(stream: S) -> Result<IdentifyMsg>
 where
     S: AsyncReadExt + Unpin,
 {
-    let mut framed = FramedRead::new(stream, JsonCodec::<(), IdentifyMsg>::new());
+    let mut framed = FramedRead::new(
+        stream,
+        LimitedJsonCodec::<(), IdentifyMsg>::new(MAX_FRAME_SIZE),
+    );
 
     let identify_msg = framed
         .next()
@@ -101,7 +122,10 @@ pub(crate) async fn send<S>(stream: S, identify_msg: IdentifyMsg) -> Result<()>
 where
     S: AsyncWriteExt + Unpin,
 {
-    let mut framed = FramedWrite::new(stream, JsonCodec::<IdentifyMsg, ()>::new());
+    let mut framed = FramedWrite::new(
+        stream,
+        LimitedJsonCodec::<IdentifyMsg, ()>::new(MAX_FRAME_SIZE),
+    );
     framed
         .send(identify_msg)
         .await
@@ -155,4 +179,27 @@ mod tests {
 
         assert_eq!(daemon_version, "0.4.3".to_string());
     }
+
+    #[test]
+    fn max_ping_interval_is_optional() {
+        let msg = IdentifyMsg::new(
+            "0.4.3".to_string(),
+            Environment::unknown(),
+            Keypair::generate_ed25519().public(),
+            HashSet::new(),
+            Multiaddr::empty(),
+            HashSet::new(),
+        )
+        .with_max_ping_interval(Some(Duration::from_secs(120)));
+
+        let mut json = serde_json::to_value(&msg).unwrap();
+        let decoded = serde_json::from_value::<IdentifyMsg>(json.clone()).unwrap();
+        assert_eq!(decoded.max_ping_interval(), Some(Duration::from_secs(120)));
+
+        json.as_object_mut()
+            .unwrap()
+            .remove("max_ping_interval_secs");
+        let decoded = serde_json::from_value::<IdentifyMsg>(json).unwrap();
+        assert_eq!(decoded.max_ping_interval(), None);
+    }
 }
@@ -0,0 +1,65 @@
+//! Fencing of taker instances that share the same identity.
+//!
+//! Two takers started with the same seed connect to the maker with the same peer id. The maker
+//! only keeps one connection per peer, hence the takers keep replacing each other's connection and
+//! their protocols conflict.
+//!
+//! Upon connecting, the taker announces its [`Instance`] to the maker. The maker remembers the
+//! instances of every peer in the order it saw them: the instance announced last fences the ones
+//! before. The taker's clock does not matter, a taker that restarted with its clock behind its
+//! previous run is still a new instance. An older instance is rejected with [`Status::Fenced`],
+//! stops dialing the maker and reports why until it is restarted.
+
+use model::Timestamp;
+use serde::Deserialize;
+use serde::Serialize;
+use uuid::Uuid;
+
+pub mod maker;
+mod protocol;
+pub mod taker;
+
+pub const PROTOCOL: &str = "/itchysats/instance-fence/1.0.0";
+
+/// Maximum size of an encoded announcement or decision in bytes
+pub const MAX_FRAME_SIZE: usize = 1024;
+
+/// A running taker process
+///
+/// `started_at` is reported by the taker and only informs the user, instances are told apart by
+/// their id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Instance {
+    pub started_at: Timestamp,
+    pub id: Uuid,
+}
+
+impl Instance {
+    pub fn new() -> Self {
+        Self {
+            started_at: Timestamp::now(),
+            id: Uuid::new_v4(),
+        }
+    }
+}
+
+impl Default for Instance {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Status {
+    /// No newer instance with the same identity is known to the maker
+    Active,
+    /// A newer instance with the same identity connected to the maker
+    Fenced { by: Instance },
+}
+
+impl Status {
+    pub fn is_fenced(&self) -> bool {
+        matches!(self, Status::Fenced { .. })
+    }
+}
@@ -0,0 +1,258 @@
+use crate::instance_fence::protocol::Announcement;
+use crate::instance_fence::protocol::Decision;
+use crate::instance_fence::Instance;
+use crate::instance_fence::MAX_FRAME_SIZE;
+use anyhow::Context;
+use async_trait::async_trait;
+use asynchronous_codec::Framed;
+use futures::SinkExt;
+use futures::StreamExt;
+use libp2p_core::PeerId;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio_extras::FutureExt;
+use uuid::Uuid;
+use xtra::Address;
+use xtra_libp2p::limited::LimitedJsonCodec;
+use xtra_libp2p::Disconnect;
+use xtra_libp2p::Endpoint;
+use xtra_libp2p::NewInboundSubstream;
+use xtra_productivity::xtra_productivity;
+
+const ANNOUNCEMENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Number of superseded instances remembered per peer
+///
+/// Only remembered instances are fenced. An instance that was superseded by more than this many
+/// newer instances is taken for a new instance if it announces itself again.
+const MAX_FENCED_INSTANCES: usize = 16;
+
+/// Remembers the latest instance of every taker and fences older ones
+pub struct Actor {
+    endpoint: Address<Endpoint>,
+    instances: Instances,
+}
+
+impl Actor {
+    pub fn new(endpoint: Address<Endpoint>) -> Self {
+        Self {
+            endpoint,
+            instances: Instances::default(),
+        }
+    }
+}
+
+/// Instances announced per peer
+#[derive(Debug, Default)]
+struct Instances(HashMap<PeerId, Seen>);
+
+/// The instances of one peer, by when the maker first saw them
+#[derive(Debug)]
+struct Seen {
+    latest: Instance,
+    /// Ids of the instances the latest one superseded, most recent last
+    fenced: VecDeque<Uuid>,
+}
+
+impl Instances {
+    fn announce(&mut self, peer_id: PeerId, instance: Instance) -> Decision {
+        let seen = match self.0.entry(peer_id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                entry.insert(Seen {
+                    latest: instance,
+                    fenced: VecDeque::with_capacity(MAX_FENCED_INSTANCES),
+                });
+                return Decision::Accepted;
+            }
+        };
+
+        if seen.latest.id == instance.id {
+            return Decision::Accepted;
+        }
+
+        if seen.fenced.contains(&instance.id) {
+            return Decision::Fenced { by: seen.latest };
+        }
+
+        if seen.fenced.len() == MAX_FENCED_INSTANCES {
+            seen.fenced.pop_front();
+        }
+        seen.fenced.push_back(seen.latest.id);
+        seen.latest = instance;
+
+        Decision::Accepted
+    }
+}
+
+struct Announce {
+    peer_id: PeerId,
+    instance: Instance,
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: Announce) -> Decision {
+        let Announce { peer_id, instance } = msg;
+
+        let decision = self.instances.announce(peer_id, instance);
+        match decision {
+            Decision::Accepted => {
+                tracing::debug!(%peer_id, id = %instance.id, "Taker instance announced")
+            }
+            Decision::Fenced { by } => tracing::warn!(
+                %peer_id,
+                id = %instance.id,
+                newer_id = %by.id,
+                "Rejecting taker instance because a newer instance with the same identity connected"
+            ),
+        }
+
+        decision
+    }
+
+    async fn handle(&mut self, msg: NewInboundSubstream, ctx: &mut xtra::Context<Self>) {
+        let NewInboundSubstream { peer_id, stream } = msg;
+        let this = ctx.address().expect("self to be alive");
+        let endpoint = self.endpoint.clone();
+
+        let task = {
+            let this = this.clone();
+            async move {
+                let mut framed = Framed::new(
+                    stream,
+                    LimitedJsonCodec::<Decision, Announcement>::new(MAX_FRAME_SIZE),
+                );
+
+                let Announcement { instance } = framed
+                    .next()
+                    .timeout(ANNOUNCEMENT_TIMEOUT, || {
+                        tracing::debug_span!("receive instance announcement")
+                    })
+                    .await
+                    .context("Taker did not announce its instance in time")?
+                    .context("Stream terminated")?
+                    .context("Failed to decode announcement")?;
+
+                let decision = this.send(Announce { peer_id, instance }).await?;
+
+                framed.send(decision).await?;
+
+                if let Decision::Fenced { .. } = decision {
+                    endpoint.send(Disconnect(peer_id)).await?;
+                }
+
+                anyhow::Ok(())
+            }
+        };
+
+        let err_handler = move |e: anyhow::Error| async move {
+            tracing::debug!(%peer_id, "Failed to handle instance announcement: {e:#}")
+        };
+
+        tokio_extras::spawn_fallible(&this, task, err_handler);
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::Timestamp;
+    use uuid::Uuid;
+
+    #[test]
+    fn newer_instance_fences_older_one() {
+        let peer_id = PeerId::random();
+        let older = instance(100);
+        let newer = instance(200);
+
+        let mut instances = Instances::default();
+
+        assert_eq!(instances.announce(peer_id, older), Decision::Accepted);
+        assert_eq!(instances.announce(peer_id, newer), Decision::Accepted);
+        assert_eq!(
+            instances.announce(peer_id, older),
+            Decision::Fenced { by: newer }
+        );
+        assert_eq!(instances.announce(peer_id, newer), Decision::Accepted);
+    }
+
+    #[test]
+    fn restarted_taker_with_clock_behind_fences_previous_run() {
+        let peer_id = PeerId::random();
+        let previous_run = instance(200);
+        let restarted = instance(100);
+
+        let mut instances = Instances::default();
+
+        assert_eq!(
+            instances.announce(peer_id, previous_run),
+            Decision::Accepted
+        );
+        assert_eq!(instances.announce(peer_id, restarted), Decision::Accepted);
+        assert_eq!(instances.announce(peer_id, restarted), Decision::Accepted);
+        assert_eq!(
+            instances.announce(peer_id, previous_run),
+            Decision::Fenced { by: restarted }
+        );
+    }
+
+    #[test]
+    fn only_the_most_recently_superseded_instances_are_remembered() {
+        let peer_id = PeerId::random();
+        let first = instance(100);
+        let second = instance(200);
+
+        let mut instances = Instances::default();
+
+        assert_eq!(instances.announce(peer_id, first), Decision::Accepted);
+        assert_eq!(instances.announce(peer_id, second), Decision::Accepted);
+        for started_at in 0..MAX_FENCED_INSTANCES as i64 {
+            assert_eq!(
+                instances.announce(peer_id, instance(300 + started_at)),
+                Decision::Accepted
+            );
+        }
+
+        let seen = &instances.0[&peer_id];
+        assert_eq!(seen.fenced.len(), MAX_FENCED_INSTANCES);
+        assert!(!seen.fenced.contains(&first.id));
+        let latest = seen.latest;
+
+        assert_eq!(
+            instances.announce(peer_id, second),
+            Decision::Fenced { by: latest }
+        );
+        assert_eq!(instances.announce(peer_id, first), Decision::Accepted);
+    }
+
+    #[test]
+    fn instances_of_different_peers_do_not_fence_each_other() {
+        let mut instances = Instances::default();
+
+        assert_eq!(
+            instances.announce(PeerId::random(), instance(200)),
+            Decision::Accepted
+        );
+        assert_eq!(
+            instances.announce(PeerId::random(), instance(100)),
+            Decision::Accepted
+        );
+    }
+
+    fn instance(started_at: i64) -> Instance {
+        Instance {
+            started_at: Timestamp::new(started_at),
+            id: Uuid::new_v4(),
+        }
+    }
+}
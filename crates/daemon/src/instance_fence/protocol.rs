@@ -0,0 +1,17 @@
+use crate::instance_fence::Instance;
+use serde::Deserialize;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct Announcement {
+    pub instance: Instance,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum Decision {
+    Accepted,
+    /// A newer instance with the same identity is connected
+    Fenced {
+        by: Instance,
+    },
+}
@@ -0,0 +1,143 @@
+use crate::instance_fence::protocol::Announcement;
+use crate::instance_fence::protocol::Decision;
+use crate::instance_fence::Instance;
+use crate::instance_fence::Status;
+use crate::instance_fence::MAX_FRAME_SIZE;
+use crate::instance_fence::PROTOCOL;
+use anyhow::Context;
+use async_trait::async_trait;
+use asynchronous_codec::Framed;
+use futures::SinkExt;
+use futures::StreamExt;
+use libp2p_core::PeerId;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio_extras::FutureExt;
+use xtra::Address;
+use xtra_libp2p::endpoint;
+use xtra_libp2p::limited::LimitedJsonCodec;
+use xtra_libp2p::Disconnect;
+use xtra_libp2p::Endpoint;
+use xtra_libp2p::OpenSubstream;
+use xtra_productivity::xtra_productivity;
+
+const DECISION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Announces this instance to the maker whenever we connect to it
+pub struct Actor {
+    endpoint: Address<Endpoint>,
+    maker_peer_id: PeerId,
+    instance: Instance,
+    sender: watch::Sender<Status>,
+}
+
+impl Actor {
+    pub fn new(
+        endpoint: Address<Endpoint>,
+        maker_peer_id: PeerId,
+    ) -> (Self, watch::Receiver<Status>) {
+        let (sender, receiver) = watch::channel(Status::Active);
+
+        (
+            Self {
+                endpoint,
+                maker_peer_id,
+                instance: Instance::new(),
+                sender,
+            },
+            receiver,
+        )
+    }
+}
+
+struct DecisionReceived(Decision);
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle_connection_established(
+        &mut self,
+        msg: endpoint::ConnectionEstablished,
+        ctx: &mut xtra::Context<Self>,
+    ) {
+        let peer_id = msg.peer_id;
+        if peer_id != self.maker_peer_id {
+            return;
+        }
+
+        let endpoint = self.endpoint.clone();
+        let instance = self.instance;
+        let this = ctx.address().expect("self to be alive");
+
+        let task = {
+            let this = this.clone();
+            async move {
+                let stream = endpoint
+                    .send(OpenSubstream::single_protocol(peer_id, PROTOCOL))
+                    .await
+                    .context("Endpoint is disconnected")?
+                    .context("No connection to peer")?
+                    .await
+                    .context("Failed to open substream")?;
+
+                let mut framed = Framed::new(
+                    stream,
+                    LimitedJsonCodec::<Announcement, Decision>::new(MAX_FRAME_SIZE),
+                );
+
+                framed.send(Announcement { instance }).await?;
+
+                let decision = framed
+                    .next()
+                    .timeout(DECISION_TIMEOUT, || {
+                        tracing::debug_span!("receive instance decision")
+                    })
+                    .await
+                    .context("The maker did not respond in time")?
+                    .context("Stream terminated")?
+                    .context("Failed to decode decision")?;
+
+                this.send(DecisionReceived(decision)).await?;
+
+                anyhow::Ok(())
+            }
+        };
+
+        // Makers that do not support the protocol yet cannot fence us
+        let err_handler = move |e: anyhow::Error| async move {
+            tracing::debug!(%peer_id, "Failed to announce instance: {e:#}")
+        };
+
+        tokio_extras::spawn_fallible(&this, task, err_handler);
+    }
+
+    async fn handle(&mut self, msg: DecisionReceived) {
+        match msg.0 {
+            Decision::Accepted => {
+                tracing::debug!(id = %self.instance.id, "Maker accepted instance");
+            }
+            Decision::Fenced { by } => {
+                tracing::error!(
+                    id = %self.instance.id,
+                    newer_id = %by.id,
+                    newer_started_at = %by.started_at.seconds(),
+                    "Another taker with the same seed connected to the maker. Not reconnecting to \
+                     the maker until this taker is restarted. Make sure only one taker per seed is \
+                     running."
+                );
+
+                let _ = self.sender.send(Status::Fenced { by });
+
+                if let Err(e) = self.endpoint.send(Disconnect(self.maker_peer_id)).await {
+                    tracing::warn!("Failed to disconnect from maker: {e:#}");
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn stopped(self) -> Self::Stop {}
+}
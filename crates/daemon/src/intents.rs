@@ -0,0 +1,236 @@
+//! Queue of actions the taker requested while the maker was offline.
+//!
+//! Settling a CFD collaboratively or rolling it over requires the maker to be online. Instead of
+//! failing, such actions are persisted as intents and executed as soon as the maker is online
+//! again. Intents that could not be executed within their validity window are dropped. Every
+//! intent is executed at most once; if the execution fails, the action has to be requested again.
+
+use crate::into_price_feed_symbol;
+use crate::online_status::ConnectionStatus;
+use crate::projection;
+use crate::taker_cfd;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use model::OrderId;
+use model::Price;
+use model::Timestamp;
+use rollover::taker::ProposeRollover;
+use sqlite_db::intents::Intent;
+use sqlite_db::intents::IntentAction;
+use std::time::Duration;
+use time::ext::NumericalDuration;
+use tokio::sync::watch;
+use xtra::prelude::MessageChannel;
+use xtra::Address;
+use xtra_bitmex_price_feed::GetLatestQuotes;
+use xtra_bitmex_price_feed::LatestQuotes;
+use xtra_bitmex_price_feed::QUOTE_INTERVAL_MINUTES;
+use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+/// How long an intent waits for the maker to come online by default
+pub const DEFAULT_VALIDITY: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// Interval at which the queued intents are executed if the maker is online
+const EXECUTE_INTERVAL: Duration = Duration::from_secs(10);
+
+pub struct Actor {
+    db: sqlite_db::Connection,
+    price_feed: MessageChannel<GetLatestQuotes, LatestQuotes>,
+    cfd_actor: Address<taker_cfd::Actor>,
+    rollover: MessageChannel<ProposeRollover, ()>,
+    projection: Address<projection::Actor>,
+    maker_online_status: watch::Receiver<ConnectionStatus>,
+    validity: Duration,
+}
+
+impl Actor {
+    pub fn new(
+        db: sqlite_db::Connection,
+        price_feed: MessageChannel<GetLatestQuotes, LatestQuotes>,
+        cfd_actor: Address<taker_cfd::Actor>,
+        rollover: MessageChannel<ProposeRollover, ()>,
+        projection: Address<projection::Actor>,
+        maker_online_status: watch::Receiver<ConnectionStatus>,
+        validity: Duration,
+    ) -> Self {
+        Self {
+            db,
+            price_feed,
+            cfd_actor,
+            rollover,
+            projection,
+            maker_online_status,
+            validity,
+        }
+    }
+}
+
+/// Queue an action until the maker is online, replacing an action queued earlier for the same CFD.
+pub struct QueueIntent {
+    pub order_id: OrderId,
+    pub action: IntentAction,
+}
+
+#[derive(Clone, Copy)]
+struct ExecuteIntents;
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: QueueIntent) -> Result<()> {
+        let QueueIntent { order_id, action } = msg;
+
+        let created_at = Timestamp::now();
+        let intent = Intent {
+            order_id,
+            action,
+            created_at,
+            expires_at: Timestamp::new(created_at.seconds() + self.validity.as_secs() as i64),
+        };
+
+        self.db.insert_intent(&intent).await?;
+
+        tracing::info!(%order_id, action = %intent.action, "Maker is offline, queued action until it is back online");
+
+        self.publish().await;
+
+        Ok(())
+    }
+
+    async fn handle(&mut self, _: ExecuteIntents) {
+        if let Err(e) = self.execute_intents().await {
+            tracing::warn!("Failed to execute queued intents: {e:#}");
+        }
+    }
+}
+
+impl Actor {
+    async fn execute_intents(&mut self) -> Result<()> {
+        let intents = self.db.load_intents().await?;
+        if intents.is_empty() {
+            return Ok(());
+        }
+
+        let now = Timestamp::now();
+        let maker_online = *self.maker_online_status.borrow() == ConnectionStatus::Online;
+
+        for intent in intents {
+            let order_id = intent.order_id;
+
+            if intent.expires_at <= now {
+                tracing::info!(%order_id, action = %intent.action, "Dropping queued action, the maker did not come online in time");
+            } else if maker_online {
+                match self.execute(&intent).await {
+                    Ok(()) => {
+                        tracing::info!(%order_id, action = %intent.action, "Executed queued action")
+                    }
+                    Err(e) => {
+                        tracing::warn!(%order_id, action = %intent.action, "Failed to execute queued action: {e:#}")
+                    }
+                }
+            } else {
+                continue;
+            }
+
+            self.db.delete_intent(order_id).await?;
+        }
+
+        self.publish().await;
+
+        Ok(())
+    }
+
+    async fn execute(&self, intent: &Intent) -> Result<()> {
+        let order_id = intent.order_id;
+
+        match &intent.action {
+            IntentAction::Settle { payout_address } => {
+                let cfd = self.db.load_open_cfd::<model::Cfd>(order_id, ()).await?;
+
+                let quotes = self
+                    .price_feed
+                    .send(GetLatestQuotes)
+                    .await
+                    .context("Price feed not available")?;
+                let quote = quotes
+                    .get(&into_price_feed_symbol(cfd.contract_symbol()))
+                    .context("No quote available")?;
+
+                if quote.is_older_than(QUOTE_INTERVAL_MINUTES.minutes() * 2) {
+                    bail!("Latest quote is too old to settle at market");
+                }
+
+                self.cfd_actor
+                    .send(taker_cfd::ProposeSettlement {
+                        order_id,
+                        bid: Price::new(quote.bid())?,
+                        ask: Price::new(quote.ask())?,
+                        quote_timestamp: quote
+                            .timestamp
+                            .format(&time::format_description::well_known::Rfc3339)
+                            .context("Failed to format timestamp")?,
+                        payout_address: payout_address.clone(),
+                    })
+                    .await
+                    .context("CFD actor not available")??;
+            }
+            IntentAction::Rollover => {
+                let cfd = self.db.load_open_cfd::<model::Cfd>(order_id, ()).await?;
+
+                let (from_commit_txid, from_settlement_event_id) = cfd.can_rollover_taker()?;
+
+                self.rollover
+                    .send(ProposeRollover {
+                        order_id,
+                        maker_peer_id: cfd
+                            .counterparty_peer_id()
+                            .context("No counterparty peer id found")?,
+                        from_commit_txid,
+                        from_settlement_event_id,
+                    })
+                    .await
+                    .context("Rollover actor not available")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Make the queued intents visible in the CFD feed.
+    async fn publish(&self) {
+        let intents = match self.db.load_intents().await {
+            Ok(intents) => intents,
+            Err(e) => {
+                tracing::warn!("Failed to load queued intents: {e:#}");
+                return;
+            }
+        };
+
+        if let Err(e) = self.projection.send(projection::Update(intents)).await {
+            tracing::warn!("Failed to update projection with queued intents: {e:#}");
+        }
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        self.publish().await;
+
+        let this = ctx.address().expect("we just started");
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(
+                EXECUTE_INTERVAL,
+                || ExecuteIntents,
+                xtras::IncludeSpan::Never,
+            ),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
@@ -0,0 +1,216 @@
+//! Garbage collection of orphaned protocol artifacts.
+//!
+//! Failed contract setups and ended sessions can leave artifacts behind that are never cleaned up
+//! by the protocol itself: UTXOs locked for the lock transaction of a setup that failed, decisions
+//! waiting for a taker that went away, announcements of past events and cached aggregates of CFDs
+//! that are closed. The janitor periodically asks the owner of each kind of artifact to release
+//! the ones that are not referenced by any live CFD or active session.
+//!
+//! In dry-run mode the artifacts are only counted, which allows to check what would be released
+//! before letting the janitor release anything.
+
+use crate::projection;
+use crate::projection::CfdState;
+use crate::scheduler::Job;
+use anyhow::Context;
+use anyhow::Result;
+use model::OrderId;
+use model::Timestamp;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use xtra::prelude::MessageChannel;
+
+pub const JOB: &str = "janitor";
+
+const INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Artifacts younger than this are never released
+///
+/// The CFD an artifact was created for might not be projected yet.
+pub const MIN_AGE: Duration = Duration::from_secs(60);
+
+const ARTIFACT_LABEL: &str = "artifact";
+
+/// Count the orphaned artifacts of an actor and release them unless `dry_run`.
+///
+/// Returns the number of orphaned artifacts.
+#[derive(Clone)]
+pub struct CollectOrphans {
+    /// Orders of CFDs whose contract setup did not fail
+    pub live_orders: Arc<HashSet<OrderId>>,
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Artifact {
+    /// UTXO locked for the lock transaction of a contract setup
+    ReservedUtxo,
+    /// Decision on an order waiting for a taker that went away
+    PendingDecision,
+    OracleAnnouncement,
+    CachedAggregate,
+}
+
+impl Artifact {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Artifact::ReservedUtxo => "reserved_utxo",
+            Artifact::PendingDecision => "pending_decision",
+            Artifact::OracleAnnouncement => "oracle_announcement",
+            Artifact::CachedAggregate => "cached_aggregate",
+        }
+    }
+}
+
+/// Outcome of the last run of the janitor.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub ran_at: Timestamp,
+    /// Whether the orphaned artifacts were only counted
+    pub dry_run: bool,
+    /// Number of orphaned artifacts per kind of artifact
+    pub orphaned: BTreeMap<&'static str, usize>,
+}
+
+#[derive(Clone)]
+pub struct Janitor {
+    db: sqlite_db::Connection,
+    cfds: watch::Receiver<Option<Vec<projection::Cfd>>>,
+    collectors: Vec<(Artifact, MessageChannel<CollectOrphans, usize>)>,
+    dry_run: bool,
+    report: Arc<watch::Sender<Option<Report>>>,
+}
+
+impl Janitor {
+    pub fn new(
+        db: sqlite_db::Connection,
+        cfds: watch::Receiver<Option<Vec<projection::Cfd>>>,
+        dry_run: bool,
+    ) -> Self {
+        let (report, _) = watch::channel(None);
+
+        Self {
+            db,
+            cfds,
+            collectors: Vec::new(),
+            dry_run,
+            report: Arc::new(report),
+        }
+    }
+
+    /// Let the janitor collect an artifact owned by an actor.
+    pub fn with_collector(
+        mut self,
+        artifact: Artifact,
+        collector: MessageChannel<CollectOrphans, usize>,
+    ) -> Self {
+        self.collectors.push((artifact, collector));
+        self
+    }
+
+    /// The outcome of the last run, `None` if the janitor did not run yet.
+    pub fn report(&self) -> Option<Report> {
+        self.report.borrow().clone()
+    }
+
+    pub fn job(&self) -> Job {
+        let janitor = self.clone();
+
+        Job::new(JOB, INTERVAL, move || {
+            let janitor = janitor.clone();
+            async move { janitor.collect().await }
+        })
+    }
+
+    async fn collect(&self) -> Result<()> {
+        let live_orders = self
+            .cfds
+            .borrow()
+            .as_ref()
+            .context("CFDs are not loaded yet")?
+            .iter()
+            .filter(|cfd| !is_failed(cfd.state))
+            .map(|cfd| cfd.order_id)
+            .collect::<HashSet<_>>();
+
+        let msg = CollectOrphans {
+            live_orders: Arc::new(live_orders),
+            dry_run: self.dry_run,
+        };
+
+        let mut orphaned = BTreeMap::new();
+
+        for (artifact, collector) in self.collectors.iter() {
+            let count = collector
+                .send(msg.clone())
+                .await
+                .with_context(|| format!("Collector of {} disconnected", artifact.as_str()))?;
+
+            *orphaned.entry(*artifact).or_default() += count;
+        }
+
+        let count = self.db.prune_aggregate_cache(self.dry_run).await?;
+        *orphaned.entry(Artifact::CachedAggregate).or_default() += count;
+
+        for (artifact, count) in orphaned.iter() {
+            ORPHANED_ARTIFACTS
+                .with_label_values(&[artifact.as_str()])
+                .set(*count as i64);
+
+            if self.dry_run || *count == 0 {
+                continue;
+            }
+
+            RELEASED_ARTIFACTS
+                .with_label_values(&[artifact.as_str()])
+                .inc_by(*count as u64);
+            tracing::info!(artifact = %artifact.as_str(), %count, "Released orphaned artifacts");
+        }
+
+        let report = Report {
+            ran_at: Timestamp::now(),
+            dry_run: self.dry_run,
+            orphaned: orphaned
+                .into_iter()
+                .map(|(artifact, count)| (artifact.as_str(), count))
+                .collect(),
+        };
+
+        if self.dry_run {
+            tracing::info!(orphaned = ?report.orphaned, "Janitor dry run");
+        }
+
+        self.report.send_replace(Some(report));
+
+        Ok(())
+    }
+}
+
+/// Whether the contract setup of the CFD is over without the CFD being opened.
+fn is_failed(state: CfdState) -> bool {
+    matches!(state, CfdState::Rejected | CfdState::SetupFailed)
+}
+
+static ORPHANED_ARTIFACTS: conquer_once::Lazy<prometheus::IntGaugeVec> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_int_gauge_vec!(
+            "janitor_orphaned_artifacts",
+            "The number of orphaned artifacts found in the last run of the janitor.",
+            &[ARTIFACT_LABEL]
+        )
+        .unwrap()
+    });
+
+static RELEASED_ARTIFACTS: conquer_once::Lazy<prometheus::IntCounterVec> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_int_counter_vec!(
+            "janitor_released_artifacts_total",
+            "The number of orphaned artifacts released by the janitor.",
+            &[ARTIFACT_LABEL]
+        )
+        .unwrap()
+    });
@@ -0,0 +1,132 @@
+//! Adapt the interval at which the taker pings makers to the activity on the connection.
+//!
+//! Idle takers only ping at the idle interval to save bandwidth and battery. The connection to a
+//! maker is considered active, and pinged at the regular interval, while a CFD with that maker is
+//! being set up, settled or rolled over, or while its settlement approaches. The idle interval is
+//! bounded by the longest interval the maker announces through the identify protocol.
+
+use crate::identify::dialer::PeerIdentified;
+use async_trait::async_trait;
+use futures::StreamExt;
+use libp2p_core::PeerId;
+use ping_pong::ping;
+use std::collections::HashSet;
+use std::time::Duration;
+use time::OffsetDateTime;
+use xtra::Address;
+use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+/// Interval at which the activity on the connections is checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long before the settlement of a CFD the connection to the maker is considered active
+const SETTLEMENT_WINDOW: time::Duration = time::Duration::HOUR;
+
+pub struct Actor {
+    db: sqlite_db::Connection,
+    ping: Address<ping::Actor>,
+}
+
+impl Actor {
+    pub fn new(db: sqlite_db::Connection, ping: Address<ping::Actor>) -> Self {
+        Self { db, ping }
+    }
+
+    /// The makers with which a protocol is ongoing or expected soon.
+    async fn active_peers(&self, now: OffsetDateTime) -> HashSet<PeerId> {
+        let mut active_peers = HashSet::new();
+
+        let mut stream = self.db.load_all_open_cfds::<model::Cfd>(());
+        while let Some(cfd) = stream.next().await {
+            let cfd = match cfd {
+                Ok(cfd) => cfd,
+                Err(e) => {
+                    tracing::warn!("Failed to load CFD from database: {e:#}");
+                    continue;
+                }
+            };
+
+            let peer_id = match cfd.counterparty_peer_id() {
+                Some(peer_id) => peer_id.inner(),
+                None => continue,
+            };
+
+            if is_active(&cfd, now) {
+                active_peers.insert(peer_id);
+            }
+        }
+
+        active_peers
+    }
+}
+
+fn is_active(cfd: &model::Cfd, now: OffsetDateTime) -> bool {
+    let dlc = match cfd.dlc() {
+        Some(dlc) => dlc,
+        // Contract setup did not finish yet
+        None => return true,
+    };
+
+    cfd.is_in_collaborative_settlement()
+        || cfd.can_auto_rollover_taker(now).is_ok()
+        || dlc.settlement_event_id.timestamp() - now <= SETTLEMENT_WINDOW
+}
+
+#[derive(Clone, Copy)]
+struct CheckActivity;
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: CheckActivity) {
+        let active_peers = self.active_peers(OffsetDateTime::now_utc()).await;
+
+        for peer_id in active_peers {
+            // Stay active at least until the next check
+            if let Err(e) = self
+                .ping
+                .send(ping::RecordActivity {
+                    peer_id,
+                    duration: CHECK_INTERVAL * 2,
+                })
+                .await
+            {
+                tracing::warn!(%peer_id, "Failed to record activity: {e:#}");
+            }
+        }
+    }
+
+    async fn handle(&mut self, msg: PeerIdentified) {
+        let max_interval = match msg.peer_info.max_ping_interval {
+            Some(max_interval) => max_interval,
+            None => return,
+        };
+
+        if let Err(e) = self
+            .ping
+            .send(ping::SetMaxInterval {
+                peer_id: msg.peer_id,
+                max_interval,
+            })
+            .await
+        {
+            tracing::warn!(peer_id = %msg.peer_id, "Failed to bound ping interval: {e:#}");
+        }
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we just started");
+
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(CHECK_INTERVAL, || CheckActivity, xtras::IncludeSpan::Never),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
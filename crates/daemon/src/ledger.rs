@@ -0,0 +1,241 @@
+//! Ledger of all changes to the wallet balance.
+//!
+//! Every confirmed wallet transaction is recorded with the reason for the change of the balance.
+//! Transactions of CFDs are linked to their CFD by the process manager and recorded as lock-up or
+//! settlement payout. All other incoming transactions are recorded as deposits, all other
+//! outgoing transactions as withdrawal plus chain fee. The sum of all entries therefore equals
+//! the confirmed balance of the wallet, which allows reconciling the ledger against the wallet's
+//! on-chain history.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bdk::bitcoin::SignedAmount;
+use bdk::TransactionDetails;
+use model::LedgerEntry;
+use model::LedgerReason;
+use model::OrderId;
+use model::Timestamp;
+use model::WalletInfo;
+use serde::Serialize;
+use std::time::Duration;
+use tokio::sync::watch;
+use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+/// Interval at which newly confirmed wallet transactions are recorded
+const RECORD_INTERVAL: Duration = Duration::from_secs(60);
+
+pub struct Actor {
+    db: sqlite_db::Connection,
+    wallet_info: watch::Receiver<Option<WalletInfo>>,
+}
+
+impl Actor {
+    pub fn new(
+        db: sqlite_db::Connection,
+        wallet_info: watch::Receiver<Option<WalletInfo>>,
+    ) -> Self {
+        Self { db, wallet_info }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Ledger {
+    /// All entries, oldest first
+    pub entries: Vec<LedgerEntry>,
+    /// Sum of all entries, equal to the confirmed balance of the wallet
+    #[serde(with = "::bdk::bitcoin::util::amount::serde::as_sat")]
+    pub balance: SignedAmount,
+}
+
+/// Get all entries of the ledger.
+#[derive(Clone, Copy)]
+pub struct GetLedger;
+
+/// Record the wallet transactions confirmed since the last time.
+#[derive(Clone, Copy)]
+struct RecordTransactions;
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: GetLedger) -> Result<Ledger> {
+        let entries = self.db.load_ledger_entries().await?;
+        let balance = entries
+            .iter()
+            .fold(SignedAmount::ZERO, |acc, entry| acc + entry.amount);
+
+        Ok(Ledger { entries, balance })
+    }
+
+    async fn handle(&mut self, _: RecordTransactions) {
+        if let Err(e) = self.record_transactions().await {
+            tracing::warn!("Failed to record wallet transactions in ledger: {e:#}");
+        }
+    }
+}
+
+impl Actor {
+    async fn record_transactions(&mut self) -> Result<()> {
+        let transactions = match &*self.wallet_info.borrow() {
+            Some(wallet_info) => wallet_info.transactions.clone(),
+            None => return Ok(()),
+        };
+
+        let recorded = self.db.load_ledger_txids().await?;
+
+        for tx in transactions {
+            let confirmed_at = match &tx.confirmation_time {
+                Some(block_time) => Timestamp::new(block_time.timestamp as i64),
+                None => continue,
+            };
+
+            if recorded.contains(&tx.txid) {
+                continue;
+            }
+
+            let cfd = self.db.load_cfd_transaction(tx.txid).await?;
+            let entries = entries(&tx, cfd, confirmed_at);
+
+            tracing::debug!(txid = %tx.txid, ?entries, "Recording wallet transaction in ledger");
+
+            self.db.insert_ledger_entries(&entries).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we just started");
+
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(
+                RECORD_INTERVAL,
+                || RecordTransactions,
+                xtras::IncludeSpan::Never,
+            ),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+/// The ledger entries of a confirmed wallet transaction.
+///
+/// The amounts of the entries add up to the net change of the wallet balance.
+fn entries(
+    tx: &TransactionDetails,
+    cfd: Option<(OrderId, LedgerReason)>,
+    timestamp: Timestamp,
+) -> Vec<LedgerEntry> {
+    let net = SignedAmount::from_sat(tx.received as i64 - tx.sent as i64);
+    let entry = |reason, order_id, amount| LedgerEntry {
+        txid: tx.txid,
+        reason,
+        order_id,
+        amount,
+        timestamp,
+        label: None,
+    };
+
+    if let Some((order_id, reason)) = cfd {
+        // The fee of CFD transactions is shared with the counterparty and already part of the
+        // lock-up or deducted from the payout
+        return vec![entry(reason, Some(order_id), net)];
+    }
+
+    if !net.is_negative() {
+        return vec![entry(LedgerReason::Deposit, None, net)];
+    }
+
+    let fee = SignedAmount::from_sat(tx.fee.unwrap_or_default() as i64);
+    let withdrawal = net + fee;
+
+    let mut entries = Vec::new();
+    if withdrawal != SignedAmount::ZERO {
+        entries.push(entry(LedgerReason::Withdrawal, None, withdrawal));
+    }
+    if fee != SignedAmount::ZERO {
+        entries.push(entry(LedgerReason::ChainFee, None, -fee));
+    }
+
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bdk::bitcoin::Txid;
+    use std::str::FromStr;
+
+    #[test]
+    fn given_outgoing_transaction_then_withdrawal_and_chain_fee() {
+        let tx = transaction(0, 1_000_000, Some(500));
+
+        let entries = entries(&tx, None, Timestamp::new(0));
+
+        assert_eq!(
+            amounts(&entries),
+            vec![
+                (LedgerReason::Withdrawal, SignedAmount::from_sat(-999_500)),
+                (LedgerReason::ChainFee, SignedAmount::from_sat(-500)),
+            ]
+        );
+    }
+
+    #[test]
+    fn given_incoming_transaction_then_deposit() {
+        let tx = transaction(250_000, 0, None);
+
+        let entries = entries(&tx, None, Timestamp::new(0));
+
+        assert_eq!(
+            amounts(&entries),
+            vec![(LedgerReason::Deposit, SignedAmount::from_sat(250_000))]
+        );
+    }
+
+    #[test]
+    fn given_lock_transaction_then_lock_up_of_net_amount() {
+        let order_id = OrderId::default();
+        let tx = transaction(400_000, 1_000_000, None);
+
+        let entries = entries(
+            &tx,
+            Some((order_id, LedgerReason::LockUp)),
+            Timestamp::new(0),
+        );
+
+        assert_eq!(
+            amounts(&entries),
+            vec![(LedgerReason::LockUp, SignedAmount::from_sat(-600_000))]
+        );
+        assert_eq!(entries[0].order_id, Some(order_id));
+    }
+
+    fn transaction(received: u64, sent: u64, fee: Option<u64>) -> TransactionDetails {
+        TransactionDetails {
+            transaction: None,
+            txid: Txid::from_str(
+                "4b8b3a9e8a2e8b0e5c6f5e4d3c2b1a09f8e7d6c5b4a392817161514131211100",
+            )
+            .unwrap(),
+            received,
+            sent,
+            fee,
+            confirmation_time: None,
+        }
+    }
+
+    fn amounts(entries: &[LedgerEntry]) -> Vec<(LedgerReason, SignedAmount)> {
+        entries
+            .iter()
+            .map(|entry| (entry.reason, entry.amount))
+            .collect()
+    }
+}
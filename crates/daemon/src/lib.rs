@@ -13,15 +13,16 @@ use bdk::bitcoin::Amount;
 use bdk::FeeRate;
 use identify::PeerInfo;
 use libp2p_core::Multiaddr;
-use libp2p_tcp::TokioTcpConfig;
 pub use maia;
 pub use maia_core;
 use maia_core::secp256k1_zkp::XOnlyPublicKey;
 use model::libp2p::PeerId;
 use model::olivia;
+use model::Cfd;
 use model::Contracts;
 use model::Identity;
 use model::Leverage;
+use model::Offer;
 use model::OfferId;
 use model::OrderId;
 use model::Price;
@@ -30,7 +31,10 @@ use online_status::ConnectionStatus;
 use parse_display::Display;
 use ping_pong::ping;
 use ping_pong::pong;
+use rust_decimal::Decimal;
 use seed::Identities;
+use sqlite_db::intents::IntentAction;
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -48,23 +52,51 @@ use xtra_libp2p::Endpoint;
 use xtras::supervisor::always_restart_after;
 use xtras::supervisor::Supervisor;
 
-pub mod archive_closed_cfds;
-pub mod archive_failed_cfds;
+pub mod api_tokens;
 pub mod auto_rollover;
-pub mod collab_settlement;
+pub mod auto_settle;
+pub mod backup;
+pub mod cet_broadcast;
+pub mod cfd_tags;
+pub mod chain_consistency;
+pub mod close_all;
+pub mod cold_sweep;
 pub mod command;
+pub mod daily_marks;
+pub mod electrum_health;
+pub mod event_feed;
+pub mod failure_report;
+pub mod funding_rate_history;
+pub mod health;
 pub mod identify;
+pub mod instance_fence;
+pub mod intents;
+pub mod janitor;
+pub mod keep_alive;
+pub mod ledger;
 pub mod libp2p_utils;
 pub mod listen_protocols;
+pub mod loss_limit;
+pub mod maker_selection;
+pub mod metrics_persistence;
+pub mod missing_attestation;
 pub mod monitor;
+pub mod notifications;
 pub mod online_status;
 pub mod oracle;
 pub mod order;
 pub mod position_metrics;
+pub mod preflight;
 pub mod process_manager;
 pub mod projection;
+pub mod scheduler;
 pub mod seed;
+pub mod service_status;
+pub mod settlement_proposal;
+pub mod supervision;
 pub mod taker_cfd;
+pub mod telemetry;
+pub mod transcript;
 pub mod wallet;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -75,24 +107,34 @@ pub const RESTART_INTERVAL: Duration = Duration::from_secs(5);
 
 pub const ENDPOINT_CONNECTION_TIMEOUT: Duration = Duration::from_secs(20);
 pub const PING_INTERVAL: Duration = Duration::from_secs(30);
+/// Interval at which the taker pings makers it has no ongoing or upcoming protocols with
+pub const IDLE_PING_INTERVAL: Duration = Duration::from_secs(5 * 60);
 
 pub const N_PAYOUTS: usize = 200;
 
 pub struct TakerActorSystem<O, W, P> {
     pub cfd_actor: Address<taker_cfd::Actor>,
     pub wallet_actor: Address<W>,
-    _oracle_actor: Address<O>,
+    pub oracle_actor: Address<O>,
+    pub endpoint: Address<Endpoint>,
     pub auto_rollover_actor: Address<auto_rollover::Actor>,
     pub price_feed_actor: Address<P>,
-    executor: command::Executor,
-    _close_cfds_actor: Address<archive_closed_cfds::Actor>,
-    _archive_failed_cfds_actor: Address<archive_failed_cfds::Actor>,
+    _auto_settle_actor: Address<auto_settle::Actor>,
+    intents_actor: Address<intents::Actor>,
+    cfd_tags_actor: Address<cfd_tags::Actor>,
+    pub executor: command::Executor,
+    pub scheduler_actor: Address<scheduler::Actor>,
     _pong_actor: Address<pong::Actor>,
     _online_status_actor: Address<online_status::Actor>,
     _identify_dialer_actor: Address<identify::dialer::Actor>,
+    funding_rate_history_actor: Address<funding_rate_history::taker::Actor>,
+    maker_peer_id: libp2p_core::PeerId,
 
     pub maker_online_status_feed_receiver: watch::Receiver<ConnectionStatus>,
     pub identify_info_feed_receiver: watch::Receiver<Option<PeerInfo>>,
+    pub instance_status_feed_receiver: watch::Receiver<instance_fence::Status>,
+    pub maker_service_status_feed_receiver:
+        watch::Receiver<Option<service_status::MakerServiceStatus>>,
 
     _tasks: Tasks,
 
@@ -105,12 +147,16 @@ where
         + Handler<
             oracle::GetAnnouncements,
             Return = Result<Vec<olivia::Announcement>, oracle::NoAnnouncement>,
-        > + Actor<Stop = ()>,
+        > + Handler<janitor::CollectOrphans, Return = usize>
+        + Actor<Stop = ()>,
     W: Handler<wallet::BuildPartyParams, Return = Result<maia_core::PartyParams>>
         + Handler<wallet::Sign, Return = Result<PartiallySignedTransaction>>
         + Handler<wallet::Withdraw, Return = Result<Txid>>
         + Handler<wallet::ImportSeed, Return = Result<bdk::wallet::AddressInfo>>
         + Handler<wallet::Sync, Return = ()>
+        + Handler<wallet::GetUnlockedBalance, Return = Result<Amount>>
+        + Handler<wallet::ConsolidateUtxos, Return = Result<Option<Txid>>>
+        + Handler<janitor::CollectOrphans, Return = usize>
         + Actor<Stop = ()>,
     P: Handler<
             xtra_bitmex_price_feed::GetLatestQuotes,
@@ -141,13 +187,24 @@ where
         projection_actor: Address<projection::Actor>,
         maker_identity: Identity,
         maker_multiaddr: Multiaddr,
+        additional_makers: Vec<(Identity, Multiaddr)>,
+        maker_preferences: maker_selection::Preferences,
         environment: Environment,
+        report_protocol_failures: bool,
+        job_intervals: Vec<scheduler::JobInterval>,
+        telemetry: Option<telemetry::Telemetry>,
+        daily_marks: Option<daily_marks::DailyMarks>,
+        janitor: Option<janitor::Janitor>,
+        rollover_funding_rate_tolerance: Option<Decimal>,
+        intent_validity: Duration,
+        settlement_proposal_tolerance: Option<Decimal>,
     ) -> Result<Self>
     where
         M: Handler<monitor::MonitorAfterContractSetup, Return = ()>
             + Handler<monitor::MonitorAfterRollover, Return = ()>
             + Handler<monitor::Sync, Return = ()>
             + Handler<monitor::MonitorCollaborativeSettlement, Return = ()>
+            + Handler<monitor::MonitorPendingCollaborativeSettlement, Return = ()>
             + Handler<monitor::MonitorCetFinality, Return = Result<()>>
             + Handler<monitor::TryBroadcastTransaction, Return = Result<()>>
             + Actor<Stop = ()>,
@@ -167,7 +224,7 @@ where
             .create(None)
             .spawn(&mut tasks);
 
-        tasks.add(process_manager_ctx.run(process_manager::Actor::new(
+        let process_manager = process_manager::Actor::new(
             db.clone(),
             Role::Taker,
             projection_actor.clone().into(),
@@ -176,11 +233,22 @@ where
             monitor_addr.clone().into(),
             monitor_addr.clone().into(),
             monitor_addr.clone().into(),
-            monitor_addr.into(),
+            monitor_addr.clone().into(),
             oracle_addr.clone().into(),
-        )));
+        );
+        tasks.add(process_manager_ctx.run(match &telemetry {
+            Some(telemetry) => process_manager.with_telemetry(telemetry.clone()),
+            None => process_manager,
+        }));
 
         let (endpoint_addr, endpoint_context) = Context::new(None);
+        let (offer_discovery_addr, offer_discovery_context) = Context::new(None);
+
+        let failure_reporter = report_protocol_failures.then(|| {
+            failure_report::taker::Actor::new(endpoint_addr.clone())
+                .create(None)
+                .spawn(&mut tasks)
+        });
 
         let (order_supervisor, order) = Supervisor::new({
             let oracle = oracle_addr.clone();
@@ -189,8 +257,9 @@ where
             let wallet = wallet_actor_addr.clone();
             let projection = projection_actor.clone();
             let endpoint = endpoint_addr.clone();
+            let failure_reporter = failure_reporter.clone();
             move || {
-                order::taker::Actor::new(
+                let actor = order::taker::Actor::new(
                     n_payouts,
                     oracle_pk,
                     oracle.clone().into(),
@@ -198,65 +267,157 @@ where
                     (wallet.clone().into(), wallet.clone().into()),
                     projection.clone(),
                     endpoint.clone(),
-                )
+                );
+
+                match &failure_reporter {
+                    Some(failure_reporter) => {
+                        actor.with_failure_reports(failure_reporter.clone().into())
+                    }
+                    None => actor,
+                }
             }
         });
         tasks.add(order_supervisor.run_log_summary());
         let (collab_settlement_supervisor, collab_settlement_addr) = Supervisor::new({
             let endpoint_addr = endpoint_addr.clone();
             let executor = executor.clone();
+            let db = db.clone();
+            let monitor_addr = monitor_addr.clone();
             move || {
                 collab_settlement::taker::Actor::new(
                     endpoint_addr.clone(),
                     executor.clone(),
                     n_payouts,
+                    db.clone(),
+                    monitor_addr.clone().into(),
                 )
             }
         });
         tasks.add(collab_settlement_supervisor.run_log_summary());
 
+        let maker_peer_id = maker_multiaddr
+            .clone()
+            .extract_peer_id()
+            .context("Unable to extract peer id from maker address")?;
+        let additional_maker_peer_ids = additional_makers
+            .iter()
+            .map(|(_, multiaddr)| {
+                multiaddr.clone().extract_peer_id().with_context(|| {
+                    format!("Unable to extract peer id from maker address {multiaddr}")
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
         let cfd_actor_addr = taker_cfd::Actor::new(
             db.clone(),
-            projection_actor,
-            collab_settlement_addr,
+            projection_actor.clone(),
+            collab_settlement_addr.clone(),
             order,
             maker_identity,
-            PeerId::from(
-                maker_multiaddr
-                    .clone()
-                    .extract_peer_id()
-                    .context("Unable to extract peer id from maker address")?,
-            ),
+            maker_peer_id,
+        )
+        .with_additional_makers(
+            additional_makers
+                .iter()
+                .map(|(identity, _)| *identity)
+                .zip(additional_maker_peer_ids.iter().copied())
+                .collect(),
         )
+        .with_maker_preferences(maker_preferences)
+        .with_discovery(offer_discovery_addr.clone(), endpoint_addr.clone())
         .create(None)
         .spawn(&mut tasks);
 
+        // Offers of makers the taker is configured with are received from the makers directly
+        tasks.add(
+            offer_discovery_context.run(
+                offer::relay::Discovery::new().with_subscriber(
+                    cfd_actor_addr.clone().into(),
+                    [maker_peer_id]
+                        .into_iter()
+                        .chain(additional_maker_peer_ids.iter().copied())
+                        .collect(),
+                ),
+            ),
+        );
+
+        let (gossip_supervisor, gossip_addr) = Supervisor::new({
+            let endpoint_addr = endpoint_addr.clone();
+            let offer_discovery_addr = offer_discovery_addr.clone();
+            move || {
+                let subscriptions = offer::relay::topics()
+                    .map(|topic| (topic, offer_discovery_addr.clone().into()))
+                    .collect();
+
+                gossip::Actor::new(endpoint_addr.clone(), subscriptions)
+            }
+        });
+        tasks.add(gossip_supervisor.run_log_summary());
+
         let (rollover_supervisor, rollover_addr) = Supervisor::new({
             let endpoint_addr = endpoint_addr.clone();
             let executor = executor.clone();
             let oracle_addr = oracle_addr.clone();
+            let cfd_actor_addr = cfd_actor_addr.clone();
             move || {
-                rollover::taker::Actor::new(
+                let actor = rollover::taker::Actor::new(
                     endpoint_addr.clone(),
                     executor.clone(),
                     oracle_pk,
                     oracle::AnnouncementsChannel::new(oracle_addr.clone().into()),
                     n_payouts,
-                )
+                );
+
+                let actor = match rollover_funding_rate_tolerance {
+                    Some(tolerance) => {
+                        actor.with_funding_rate_band(cfd_actor_addr.clone().into(), tolerance)
+                    }
+                    None => actor,
+                };
+
+                match &failure_reporter {
+                    Some(failure_reporter) => {
+                        actor.with_failure_reports(failure_reporter.clone().into())
+                    }
+                    None => actor,
+                }
             }
         });
         tasks.add(rollover_supervisor.run_log_summary());
 
+        let intents_addr = intents::Actor::new(
+            db.clone(),
+            price_feed_actor.clone().into(),
+            cfd_actor_addr.clone(),
+            rollover_addr.clone().into(),
+            projection_actor.clone(),
+            maker_online_status_feed_receiver.clone(),
+            intent_validity,
+        )
+        .create(None)
+        .spawn(&mut tasks);
+
+        let cfd_tags_addr = cfd_tags::Actor::new(db.clone(), projection_actor.clone())
+            .create(None)
+            .spawn(&mut tasks);
+
         let auto_rollover_addr = auto_rollover::Actor::new(db.clone(), rollover_addr)
             .create(None)
             .spawn(&mut tasks);
 
+        let auto_settle_addr = auto_settle::Actor::new(
+            db.clone(),
+            price_feed_actor.clone().into(),
+            cfd_actor_addr.clone(),
+            executor.clone(),
+            maker_online_status_feed_receiver.clone(),
+        )
+        .create(None)
+        .spawn(&mut tasks);
+
         let online_status_actor = online_status::Actor::new(
             endpoint_addr.clone(),
-            maker_multiaddr
-                .clone()
-                .extract_peer_id()
-                .expect("to be able to extract peer id"),
+            maker_peer_id,
             maker_online_status_feed_sender,
         )
         .create(None)
@@ -265,18 +426,68 @@ where
         tasks.add(monitor_ctx.run(monitor_constructor(executor.clone())?));
         tasks.add(oracle_ctx.run(oracle_constructor(executor.clone())));
 
+        let (instance_fence_actor, instance_status_feed_receiver) =
+            instance_fence::taker::Actor::new(endpoint_addr.clone(), maker_peer_id);
+        let instance_fence_actor = instance_fence_actor.create(None).spawn(&mut tasks);
+
+        let (service_status_actor, maker_service_status_feed_receiver) =
+            service_status::taker::Actor::new(maker_peer_id);
+        let service_status_actor = service_status_actor.create(None).spawn(&mut tasks);
+
         let dialer_constructor = {
             let endpoint_addr = endpoint_addr.clone();
             move || dialer::Actor::new(endpoint_addr.clone(), maker_multiaddr.clone())
         };
-        let (dialer_supervisor, dialer_actor) = Supervisor::<_, dialer::Error>::with_policy(
-            dialer_constructor,
-            always_restart_after(RESTART_INTERVAL),
-        );
+        let (dialer_supervisor, dialer_actor) =
+            Supervisor::<_, dialer::Error>::with_policy(dialer_constructor, {
+                let instance_status = instance_status_feed_receiver.clone();
+                Box::new(move |_: &dialer::Error| {
+                    let instance_status = instance_status.clone();
+                    Box::pin(async move {
+                        tokio_extras::time::sleep(RESTART_INTERVAL).await;
+
+                        // A fenced instance must not take the connection back from the newer one
+                        !instance_status.borrow().is_fenced()
+                    })
+                })
+            });
+
+        // The online status, identify information and funding rate history are only tracked for
+        // the main maker, additional makers are only dialed to receive their offers
+        let additional_dialer_actors = additional_makers
+            .into_iter()
+            .map(|(_, multiaddr)| {
+                let endpoint_addr = endpoint_addr.clone();
+                let (supervisor, dialer_actor) = Supervisor::<_, dialer::Error>::with_policy(
+                    move || dialer::Actor::new(endpoint_addr.clone(), multiaddr.clone()),
+                    always_restart_after(RESTART_INTERVAL),
+                );
+                tasks.add(supervisor.run_log_summary());
+
+                dialer_actor
+            })
+            .collect::<Vec<_>>();
 
         let (offer_supervisor, offer_addr) = Supervisor::new({
             let cfd_actor_addr = cfd_actor_addr.clone();
-            move || offer::taker::Actor::new(cfd_actor_addr.clone().into())
+            move || {
+                offer::taker::Actor::new(cfd_actor_addr.clone().into(), maker_peer_id)
+                    .with_additional_makers(additional_maker_peer_ids.clone())
+            }
+        });
+
+        let (settlement_proposal_supervisor, settlement_proposal_addr) = Supervisor::new({
+            let executor = executor.clone();
+            let price_feed_actor = price_feed_actor.clone();
+            let cfd_actor_addr = cfd_actor_addr.clone();
+            move || {
+                settlement_proposal::taker::Actor::new(
+                    executor.clone(),
+                    price_feed_actor.clone().into(),
+                    cfd_actor_addr.clone(),
+                    settlement_proposal_tolerance,
+                )
+            }
         });
 
         let (identify_listener_supervisor, identify_listener_actor) = Supervisor::new({
@@ -292,37 +503,64 @@ where
             }
         });
 
-        let (identify_dialer_actor, identify_info_feed_receiver) =
-            identify::dialer::Actor::new_with_subscriber(endpoint_addr.clone());
-        let identify_dialer_actor = identify_dialer_actor.create(None).spawn(&mut tasks);
-
         let pong_address = pong::Actor.create(None).spawn(&mut tasks);
 
-        let (supervisor, ping_actor) =
-            Supervisor::new(move || ping::Actor::new(endpoint_addr.clone(), PING_INTERVAL));
+        let funding_rate_history_actor =
+            funding_rate_history::taker::Actor::new(endpoint_addr.clone())
+                .create(None)
+                .spawn(&mut tasks);
+
+        let (supervisor, ping_actor) = Supervisor::new({
+            let endpoint_addr = endpoint_addr.clone();
+            move || {
+                ping::Actor::new(endpoint_addr.clone(), PING_INTERVAL)
+                    .with_idle_interval(IDLE_PING_INTERVAL)
+            }
+        });
         tasks.add(supervisor.run_log_summary());
 
+        let keep_alive_actor = keep_alive::Actor::new(db.clone(), ping_actor.clone())
+            .create(None)
+            .spawn(&mut tasks);
+
+        let (identify_dialer_actor, identify_info_feed_receiver) =
+            identify::dialer::Actor::new_with_subscriber(endpoint_addr.clone());
+        let identify_dialer_actor = identify_dialer_actor
+            .with_subscriber(keep_alive_actor.into())
+            .create(None)
+            .spawn(&mut tasks);
+
         let endpoint = Endpoint::new(
-            Box::new(TokioTcpConfig::new),
+            Box::new(libp2p_utils::transport),
             identity.libp2p,
             ENDPOINT_CONNECTION_TIMEOUT,
             TAKER_LISTEN_PROTOCOLS.inbound_substream_handlers(
                 pong_address.clone(),
                 identify_listener_actor,
                 offer_addr,
+                settlement_proposal_addr,
+                service_status_actor,
+                gossip_addr.clone(),
             ),
             endpoint::Subscribers::new(
                 vec![
                     online_status_actor.clone().into(),
                     ping_actor.clone().into(),
                     identify_dialer_actor.clone().into(),
+                    collab_settlement_addr.into(),
+                    instance_fence_actor.into(),
+                    gossip_addr.clone().into(),
                 ],
-                vec![
+                [
                     dialer_actor.into(),
                     ping_actor.into(),
                     online_status_actor.clone().into(),
                     identify_dialer_actor.clone().into(),
-                ],
+                    gossip_addr.into(),
+                ]
+                .into_iter()
+                .chain(additional_dialer_actors.into_iter().map(Into::into))
+                .collect(),
                 vec![],
                 vec![],
             ),
@@ -333,12 +571,30 @@ where
 
         tasks.add(dialer_supervisor.run_log_summary());
         tasks.add(offer_supervisor.run_log_summary());
+        tasks.add(settlement_proposal_supervisor.run_log_summary());
         tasks.add(identify_listener_supervisor.run_log_summary());
 
-        let close_cfds_actor = archive_closed_cfds::Actor::new(db.clone())
-            .create(None)
-            .spawn(&mut tasks);
-        let archive_failed_cfds_actor = archive_failed_cfds::Actor::new(db.clone())
+        let mut jobs = scheduler::maintenance_jobs(db.clone(), wallet_actor_addr.clone().into());
+        if let Some(telemetry) = &telemetry {
+            jobs.extend(telemetry.jobs());
+        }
+        if let Some(daily_marks) = &daily_marks {
+            jobs.push(daily_marks.job());
+        }
+        if let Some(janitor) = janitor {
+            let janitor = janitor
+                .with_collector(
+                    janitor::Artifact::ReservedUtxo,
+                    wallet_actor_addr.clone().into(),
+                )
+                .with_collector(
+                    janitor::Artifact::OracleAnnouncement,
+                    oracle_addr.clone().into(),
+                );
+            jobs.push(janitor.job());
+        }
+
+        let scheduler_actor = scheduler::Actor::new(jobs, &job_intervals)?
             .create(None)
             .spawn(&mut tasks);
 
@@ -347,18 +603,25 @@ where
         Ok(Self {
             cfd_actor: cfd_actor_addr,
             wallet_actor: wallet_actor_addr,
-            _oracle_actor: oracle_addr,
+            oracle_actor: oracle_addr,
+            endpoint: endpoint_addr,
             auto_rollover_actor: auto_rollover_addr,
             price_feed_actor,
+            _auto_settle_actor: auto_settle_addr,
+            intents_actor: intents_addr,
+            cfd_tags_actor: cfd_tags_addr,
             executor,
-            _close_cfds_actor: close_cfds_actor,
-            _archive_failed_cfds_actor: archive_failed_cfds_actor,
+            scheduler_actor,
             _tasks: tasks,
             maker_online_status_feed_receiver,
             identify_info_feed_receiver,
+            instance_status_feed_receiver,
+            maker_service_status_feed_receiver,
             _online_status_actor: online_status_actor,
             _pong_actor: pong_address,
             _identify_dialer_actor: identify_dialer_actor,
+            funding_rate_history_actor,
+            maker_peer_id,
             db,
         })
     }
@@ -369,6 +632,22 @@ where
         offer_id: OfferId,
         quantity: Contracts,
         leverage: Leverage,
+    ) -> Result<OrderId> {
+        self.place_order_with_maker(offer_id, quantity, leverage, None)
+            .await
+    }
+
+    /// Place an order for the given offer, or the equivalent offer of `maker` if given.
+    ///
+    /// Without `maker`, the offer of the maker that is preferred according to the configured
+    /// [`maker_selection::Preferences`] is taken among the offers equivalent to `offer_id`.
+    #[instrument(skip(self), err)]
+    pub async fn place_order_with_maker(
+        &self,
+        offer_id: OfferId,
+        quantity: Contracts,
+        leverage: Leverage,
+        maker: Option<libp2p_core::PeerId>,
     ) -> Result<OrderId> {
         let order_id = self
             .cfd_actor
@@ -376,12 +655,134 @@ where
                 offer_id,
                 quantity,
                 leverage,
+                maker,
             })
             .await??;
 
         Ok(order_id)
     }
 
+    /// Query the funding rates the maker offered during the past `days`.
+    #[instrument(skip(self), err)]
+    pub async fn funding_rate_history(
+        &self,
+        days: u32,
+    ) -> Result<Vec<funding_rate_history::FundingRateEntry>> {
+        let history = self
+            .funding_rate_history_actor
+            .send(funding_rate_history::taker::GetFundingRateHistory {
+                maker_peer_id: self.maker_peer_id,
+                days,
+            })
+            .await
+            .context("Funding rate history actor not available")??;
+
+        Ok(history)
+    }
+
+    /// Margin and opening fee of taking the offer with the given quantity and leverage.
+    #[instrument(skip(self), err)]
+    pub async fn initial_costs(
+        &self,
+        offer_id: OfferId,
+        quantity: Contracts,
+        leverage: Leverage,
+    ) -> Result<preflight::InitialCosts> {
+        let offer = self
+            .cfd_actor
+            .send(taker_cfd::GetOffer { offer_id })
+            .await
+            .context("CFD actor not available")?
+            .with_context(|| format!("Offer {offer_id} not found"))?;
+
+        preflight::check_order_parameters(&offer, quantity, leverage)?;
+
+        Ok(preflight::InitialCosts::new(&offer, quantity, leverage))
+    }
+
+    /// The latest offers of every maker that are still safe to take.
+    #[instrument(skip(self), err)]
+    pub async fn offers_by_maker(&self) -> Result<HashMap<libp2p_core::PeerId, Vec<Offer>>> {
+        let offers = self
+            .cfd_actor
+            .send(taker_cfd::GetOffersByMaker)
+            .await
+            .context("CFD actor not available")?;
+
+        Ok(offers)
+    }
+
+    /// Check whether a contract setup for the given order parameters is expected to succeed,
+    /// without placing an order.
+    #[instrument(skip(self), err)]
+    pub async fn preflight(
+        &self,
+        offer_id: OfferId,
+        quantity: Contracts,
+        leverage: Leverage,
+    ) -> Result<preflight::Report> {
+        let mut report = preflight::Report::default();
+
+        let offer = self
+            .cfd_actor
+            .send(taker_cfd::GetOffer { offer_id })
+            .await
+            .context("CFD actor not available")?;
+
+        report.record(
+            preflight::Check::Offer,
+            preflight::check_offer(offer.as_ref(), time::OffsetDateTime::now_utc()),
+        );
+
+        let maker_connectivity = match *self.maker_online_status_feed_receiver.borrow() {
+            ConnectionStatus::Online => Ok(()),
+            ConnectionStatus::Offline => Err(anyhow::anyhow!("Maker is offline")),
+        };
+        report.record(preflight::Check::MakerConnectivity, maker_connectivity);
+
+        let offer = match offer {
+            Some(offer) => offer,
+            None => {
+                // All remaining checks depend on the offer
+                return Ok(report);
+            }
+        };
+
+        report.record(
+            preflight::Check::OrderParameters,
+            preflight::check_order_parameters(&offer, quantity, leverage),
+        );
+
+        let wallet_balance = async {
+            let unlocked_balance = self
+                .wallet_actor
+                .send(wallet::GetUnlockedBalance)
+                .await
+                .context("Wallet actor not available")??;
+
+            preflight::check_wallet_balance(
+                unlocked_balance,
+                preflight::required_funds(&offer, quantity, leverage),
+            )
+        };
+        report.record(preflight::Check::WalletBalance, wallet_balance.await);
+
+        let oracle_announcement = async {
+            self.oracle_actor
+                .send(oracle::GetAnnouncements(vec![offer.oracle_event_id]))
+                .await
+                .context("Oracle actor not available")??;
+
+            anyhow::Ok(())
+        };
+        report.record(
+            preflight::Check::OracleAnnouncement,
+            oracle_announcement.await,
+        );
+
+        Ok(report)
+    }
+
     #[instrument(skip(self), err)]
     pub async fn commit(&self, order_id: OrderId) -> Result<()> {
         self.executor
@@ -391,13 +792,69 @@ where
         Ok(())
     }
 
+    /// Set the price levels at which the CFD is settled automatically, `None` removes a level.
+    #[instrument(skip(self), err)]
+    pub async fn set_price_levels(
+        &self,
+        order_id: OrderId,
+        take_profit: Option<Price>,
+        stop_loss: Option<Price>,
+    ) -> Result<()> {
+        self.executor
+            .execute(order_id, |cfd| cfd.set_price_levels(take_profit, stop_loss))
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self), err)]
+    pub async fn add_cfd_tag(&self, order_id: OrderId, tag: String) -> Result<()> {
+        self.cfd_tags_actor
+            .send(cfd_tags::AddTag { order_id, tag })
+            .await
+            .context("CFD tags actor not available")?
+    }
+
+    #[instrument(skip(self), err)]
+    pub async fn remove_cfd_tag(&self, order_id: OrderId, tag: String) -> Result<()> {
+        self.cfd_tags_actor
+            .send(cfd_tags::RemoveTag { order_id, tag })
+            .await
+            .context("CFD tags actor not available")?
+    }
+
     #[instrument(skip(self), err)]
     pub async fn propose_settlement(&self, order_id: OrderId) -> Result<()> {
+        self.settle(order_id, None).await
+    }
+
+    /// Propose a collaborative settlement that pays out to an external address instead of the
+    /// wallet.
+    #[instrument(skip(self), err)]
+    pub async fn propose_settlement_to(
+        &self,
+        order_id: OrderId,
+        payout_address: bitcoin::Address,
+    ) -> Result<()> {
+        self.settle(order_id, Some(payout_address)).await
+    }
+
+    async fn settle(
+        &self,
+        order_id: OrderId,
+        payout_address: Option<bitcoin::Address>,
+    ) -> Result<()> {
         let contract_symbol = self
             .executor
             .query(order_id, |cfd| Ok(cfd.contract_symbol()))
             .await?;
 
+        if *self.maker_online_status_feed_receiver.borrow() == ConnectionStatus::Offline {
+            return self
+                .queue_intent(order_id, IntentAction::Settle { payout_address })
+                .await;
+        }
+
         let latest_quote = *self
             .price_feed_actor
             .send(xtra_bitmex_price_feed::GetLatestQuotes)
@@ -426,10 +883,70 @@ where
                 bid: Price::new(latest_quote.bid())?,
                 ask: Price::new(latest_quote.ask())?,
                 quote_timestamp,
+                payout_address,
             })
             .await?
     }
 
+    /// Propose collaborative settlement of all open CFDs with `maker`.
+    ///
+    /// Failing to propose settlement of one CFD does not stop the others from being proposed, the
+    /// failures are reported in the summary.
+    #[instrument(skip(self), err)]
+    pub async fn close_all(&self, maker: PeerId) -> Result<close_all::Summary> {
+        let mut order_ids = Vec::new();
+        for order_id in self.db.load_open_cfd_ids().await? {
+            let cfd = self.db.load_open_cfd::<Cfd>(order_id, ()).await?;
+            if cfd.counterparty_peer_id() == Some(maker) {
+                order_ids.push(order_id);
+            }
+        }
+
+        if order_ids.is_empty() {
+            bail!("No open CFDs with maker {maker}");
+        }
+
+        let mut summary = close_all::Summary::new(order_ids.clone());
+        for order_id in order_ids {
+            if let Err(e) = self.propose_settlement(order_id).await {
+                summary.record_failure(order_id, &e);
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Propose a rollover to the maker, regardless of when the CFD expires.
+    #[instrument(skip(self), err)]
+    pub async fn propose_rollover(&self, order_id: OrderId) -> Result<()> {
+        let cfd = self.db.load_open_cfd::<Cfd>(order_id, ()).await?;
+        let (from_commit_txid, from_settlement_event_id) = cfd.can_rollover_taker()?;
+
+        if *self.maker_online_status_feed_receiver.borrow() == ConnectionStatus::Offline {
+            return self.queue_intent(order_id, IntentAction::Rollover).await;
+        }
+
+        self.auto_rollover_actor
+            .send(auto_rollover::Rollover {
+                order_id,
+                maker_peer_id: cfd.counterparty_peer_id(),
+                from_commit_txid,
+                from_settlement_event_id,
+            })
+            .await
+            .context("Auto-rollover actor not available")?;
+
+        Ok(())
+    }
+
+    /// Queue an action that requires the maker to be online until the maker is back online.
+    async fn queue_intent(&self, order_id: OrderId, action: IntentAction) -> Result<()> {
+        self.intents_actor
+            .send(intents::QueueIntent { order_id, action })
+            .await
+            .context("Intents actor not available")?
+    }
+
     #[instrument(skip(self), err)]
     pub async fn withdraw(
         &self,
@@ -512,6 +1029,32 @@ impl Environment {
     }
 }
 
+/// Verify the cached state of all open CFDs against the event log.
+///
+/// Meant to run once upon startup, after the actors loaded the open CFDs. Mismatching cache
+/// entries are reported and invalidated, so that they are rebuilt from the event log.
+pub async fn verify_state(db: &sqlite_db::Connection) -> Result<()> {
+    let report = db
+        .verify_cached_aggregates::<model::Cfd>(())
+        .await
+        .context("Failed to verify cached CFD state")?;
+
+    if report.is_consistent() {
+        tracing::info!(
+            verified = %report.verified,
+            uncached = %report.uncached,
+            "Cached CFD state matches event log"
+        );
+    } else {
+        tracing::error!(
+            mismatched = ?report.mismatched,
+            "Cached CFD state does not match event log, invalidated cache entries"
+        );
+    }
+
+    Ok(())
+}
+
 /// The version of the `daemon` crate, as specified in its `Cargo.toml` file.
 pub fn version() -> String {
     VERSION.to_string()
@@ -4,8 +4,29 @@ use anyhow::Result;
 use std::net::IpAddr;
 use std::net::SocketAddr;
 
+#[cfg(feature = "memory-transport")]
+use libp2p_core::transport::MemoryTransport;
+#[cfg(feature = "memory-transport")]
+use libp2p_core::transport::OrTransport;
 use libp2p_core::Multiaddr;
 use libp2p_core::PeerId;
+#[cfg(feature = "memory-transport")]
+use libp2p_core::Transport;
+use libp2p_tcp::TokioTcpConfig;
+
+/// The transport of the libp2p endpoint.
+#[cfg(not(feature = "memory-transport"))]
+pub fn transport() -> TokioTcpConfig {
+    TokioTcpConfig::new()
+}
+
+/// The transport of the libp2p endpoint.
+///
+/// In addition to TCP, peers in the same process can connect through `/memory/<port>` addresses.
+#[cfg(feature = "memory-transport")]
+pub fn transport() -> OrTransport<TokioTcpConfig, MemoryTransport> {
+    TokioTcpConfig::new().or_transport(MemoryTransport::default())
+}
 
 /// Creates MultiAddr from SocketAddr and PeerId
 pub fn create_connect_tcp_multiaddr(
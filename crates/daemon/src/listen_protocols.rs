@@ -1,8 +1,12 @@
-use crate::collab_settlement;
 use crate::command;
+use crate::failure_report;
+use crate::funding_rate_history;
 use crate::identify;
+use crate::instance_fence;
 use crate::oracle;
 use crate::order;
+use crate::service_status;
+use crate::settlement_proposal;
 use ping_pong::pong;
 use std::collections::HashSet;
 use xtra::message_channel::MessageChannel;
@@ -12,24 +16,45 @@ use xtra_libp2p::NewInboundSubstream;
 pub const MAKER_LISTEN_PROTOCOLS: MakerListenProtocols = MakerListenProtocols::new(
     ping_pong::PROTOCOL,
     identify::PROTOCOL,
-    (order::PROTOCOL, order::deprecated::PROTOCOL),
-    (rollover::PROTOCOL, rollover::deprecated::PROTOCOL),
+    (
+        order::PROTOCOL,
+        order::UNSEQUENCED_PROTOCOL,
+        order::deprecated::PROTOCOL,
+    ),
+    (
+        rollover::PROTOCOL,
+        rollover::UNSEQUENCED_PROTOCOL,
+        rollover::deprecated::PROTOCOL,
+    ),
     (
         collab_settlement::PROTOCOL,
+        collab_settlement::UNSEQUENCED_PROTOCOL,
         collab_settlement::deprecated::PROTOCOL,
     ),
+    collab_settlement::RESUME_PROTOCOL,
+    funding_rate_history::PROTOCOL,
+    gossip::PROTOCOL,
+    failure_report::PROTOCOL,
+    instance_fence::PROTOCOL,
 );
 
-pub const TAKER_LISTEN_PROTOCOLS: TakerListenProtocols =
-    TakerListenProtocols::new(ping_pong::PROTOCOL, identify::PROTOCOL, offer::PROTOCOL);
+pub const TAKER_LISTEN_PROTOCOLS: TakerListenProtocols = TakerListenProtocols::new(
+    ping_pong::PROTOCOL,
+    identify::PROTOCOL,
+    offer::PROTOCOL,
+    settlement_proposal::PROTOCOL,
+    service_status::PROTOCOL,
+    gossip::PROTOCOL,
+);
 
+/// The taker falls back to the versions without sequence numbers, which are therefore sufficient.
 pub const REQUIRED_MAKER_LISTEN_PROTOCOLS: RequiredMakerListenProtocols =
     RequiredMakerListenProtocols::new(
         ping_pong::PROTOCOL,
         identify::PROTOCOL,
-        order::PROTOCOL,
-        rollover::PROTOCOL,
-        collab_settlement::PROTOCOL,
+        order::UNSEQUENCED_PROTOCOL,
+        rollover::UNSEQUENCED_PROTOCOL,
+        collab_settlement::UNSEQUENCED_PROTOCOL,
     );
 
 /// Verify if the listen protocols that the `maker` supports are
@@ -57,11 +82,19 @@ pub struct MakerListenProtocols {
     ping: &'static str,
     identify: &'static str,
     order: &'static str,
+    order_unsequenced: &'static str,
     order_deprecated: &'static str,
     rollover: &'static str,
+    rollover_unsequenced: &'static str,
     rollover_deprecated: &'static str,
     collaborative_settlement: &'static str,
+    collaborative_settlement_unsequenced: &'static str,
     collaborative_settlement_deprecated: &'static str,
+    collaborative_settlement_resume: &'static str,
+    funding_rate_history: &'static str,
+    gossip: &'static str,
+    failure_report: &'static str,
+    instance_fence: &'static str,
 }
 
 type RolloverAddress<R> =
@@ -72,27 +105,45 @@ type RolloverDeprecatedAddress<RD> = Address<
 >;
 
 impl MakerListenProtocols {
-    pub const NR_OF_SUPPORTED_PROTOCOLS: usize = 8;
+    pub const NR_OF_SUPPORTED_PROTOCOLS: usize = 16;
 
     pub const fn new(
         ping: &'static str,
         identify: &'static str,
-        (order, order_deprecated): (&'static str, &'static str),
-        (rollover, rollover_deprecated): (&'static str, &'static str),
-        (collaborative_settlement, collaborative_settlement_deprecated): (
+        (order, order_unsequenced, order_deprecated): (&'static str, &'static str, &'static str),
+        (rollover, rollover_unsequenced, rollover_deprecated): (
+            &'static str,
             &'static str,
             &'static str,
         ),
+        (
+            collaborative_settlement,
+            collaborative_settlement_unsequenced,
+            collaborative_settlement_deprecated,
+        ): (&'static str, &'static str, &'static str),
+        collaborative_settlement_resume: &'static str,
+        funding_rate_history: &'static str,
+        gossip: &'static str,
+        failure_report: &'static str,
+        instance_fence: &'static str,
     ) -> Self {
         Self {
             ping,
             identify,
             order,
+            order_unsequenced,
             order_deprecated,
             rollover,
+            rollover_unsequenced,
             rollover_deprecated,
             collaborative_settlement,
+            collaborative_settlement_unsequenced,
             collaborative_settlement_deprecated,
+            collaborative_settlement_resume,
+            funding_rate_history,
+            gossip,
+            failure_report,
+            instance_fence,
         }
     }
 
@@ -113,9 +164,14 @@ impl MakerListenProtocols {
             RolloverDeprecatedAddress<RD>,
         ),
         (collaborative_settlement_handler, collaborative_settlement_deprecated_handler): (
-            Address<collab_settlement::maker::Actor>,
-            Address<collab_settlement::deprecated::maker::Actor>,
+            Address<collab_settlement::maker::Actor<command::Executor>>,
+            Address<collab_settlement::deprecated::maker::Actor<command::Executor>>,
         ),
+        collaborative_settlement_resume_handler: Address<collab_settlement::resume::Actor>,
+        funding_rate_history_handler: Address<funding_rate_history::maker::Actor>,
+        gossip_handler: Address<gossip::Actor>,
+        failure_report_handler: Address<failure_report::maker::Actor>,
+        instance_fence_handler: Address<instance_fence::maker::Actor>,
     ) -> [(&'static str, MessageChannel<NewInboundSubstream, ()>); Self::NR_OF_SUPPORTED_PROTOCOLS]
     where
         R: rollover::protocol::GetRates + Send + Sync + Clone + 'static,
@@ -126,28 +182,52 @@ impl MakerListenProtocols {
             ping,
             identify,
             order,
+            order_unsequenced,
             order_deprecated,
             rollover,
+            rollover_unsequenced,
             rollover_deprecated,
             collaborative_settlement,
+            collaborative_settlement_unsequenced,
             collaborative_settlement_deprecated,
+            collaborative_settlement_resume,
+            funding_rate_history,
+            gossip,
+            failure_report,
+            instance_fence,
         } = self;
 
         [
             (ping, ping_handler.into()),
             (identify, identify_handler.into()),
-            (order, order_handler.into()),
+            // The listeners of the current versions also serve the versions without sequence
+            // numbers
+            (order, order_handler.clone().into()),
+            (order_unsequenced, order_handler.into()),
             (order_deprecated, order_deprecated_handler.into()),
-            (rollover, rollover_handler.into()),
+            (rollover, rollover_handler.clone().into()),
+            (rollover_unsequenced, rollover_handler.into()),
             (rollover_deprecated, rollover_deprecated_handler.into()),
             (
                 collaborative_settlement,
+                collaborative_settlement_handler.clone().into(),
+            ),
+            (
+                collaborative_settlement_unsequenced,
                 collaborative_settlement_handler.into(),
             ),
             (
                 collaborative_settlement_deprecated,
                 collaborative_settlement_deprecated_handler.into(),
             ),
+            (
+                collaborative_settlement_resume,
+                collaborative_settlement_resume_handler.into(),
+            ),
+            (funding_rate_history, funding_rate_history_handler.into()),
+            (gossip, gossip_handler.into()),
+            (failure_report, failure_report_handler.into()),
+            (instance_fence, instance_fence_handler.into()),
         ]
     }
 }
@@ -159,22 +239,38 @@ impl From<MakerListenProtocols> for HashSet<String> {
             ping,
             identify,
             order,
+            order_unsequenced,
             order_deprecated,
             rollover,
+            rollover_unsequenced,
             rollover_deprecated,
             collaborative_settlement,
+            collaborative_settlement_unsequenced,
             collaborative_settlement_deprecated,
+            collaborative_settlement_resume,
+            funding_rate_history,
+            gossip,
+            failure_report,
+            instance_fence,
         } = maker;
 
         HashSet::from([
             ping.to_string(),
             identify.to_string(),
             order.to_string(),
+            order_unsequenced.to_string(),
             order_deprecated.to_string(),
             rollover.to_string(),
+            rollover_unsequenced.to_string(),
             rollover_deprecated.to_string(),
             collaborative_settlement.to_string(),
+            collaborative_settlement_unsequenced.to_string(),
             collaborative_settlement_deprecated.to_string(),
+            collaborative_settlement_resume.to_string(),
+            funding_rate_history.to_string(),
+            gossip.to_string(),
+            failure_report.to_string(),
+            instance_fence.to_string(),
         ])
     }
 }
@@ -235,16 +331,29 @@ pub struct TakerListenProtocols {
     ping: &'static str,
     identify: &'static str,
     offer: &'static str,
+    settlement_proposal: &'static str,
+    service_status: &'static str,
+    gossip: &'static str,
 }
 
 impl TakerListenProtocols {
-    const NR_OF_SUPPORTED_PROTOCOLS: usize = 3;
+    const NR_OF_SUPPORTED_PROTOCOLS: usize = 6;
 
-    pub const fn new(ping: &'static str, identify: &'static str, offer: &'static str) -> Self {
+    pub const fn new(
+        ping: &'static str,
+        identify: &'static str,
+        offer: &'static str,
+        settlement_proposal: &'static str,
+        service_status: &'static str,
+        gossip: &'static str,
+    ) -> Self {
         Self {
             ping,
             identify,
             offer,
+            settlement_proposal,
+            service_status,
+            gossip,
         }
     }
 
@@ -257,6 +366,9 @@ impl TakerListenProtocols {
         ping_handler: Address<pong::Actor>,
         identify_handler: Address<identify::listener::Actor>,
         offer_handler: Address<offer::taker::Actor>,
+        settlement_proposal_handler: Address<settlement_proposal::taker::Actor>,
+        service_status_handler: Address<service_status::taker::Actor>,
+        gossip_handler: Address<gossip::Actor>,
     ) -> [(&'static str, MessageChannel<NewInboundSubstream, ()>); Self::NR_OF_SUPPORTED_PROTOCOLS]
     {
         // We deconstruct to ensure that all protocols are being used
@@ -264,12 +376,18 @@ impl TakerListenProtocols {
             ping,
             identify,
             offer,
+            settlement_proposal,
+            service_status,
+            gossip,
         } = self;
 
         [
             (ping, ping_handler.into()),
             (identify, identify_handler.into()),
             (offer, offer_handler.into()),
+            (settlement_proposal, settlement_proposal_handler.into()),
+            (service_status, service_status_handler.into()),
+            (gossip, gossip_handler.into()),
         ]
     }
 }
@@ -281,9 +399,19 @@ impl From<TakerListenProtocols> for HashSet<String> {
             ping,
             identify,
             offer,
+            settlement_proposal,
+            service_status,
+            gossip,
         } = protocols;
 
-        HashSet::from_iter([ping.to_string(), identify.to_string(), offer.to_string()])
+        HashSet::from_iter([
+            ping.to_string(),
+            identify.to_string(),
+            offer.to_string(),
+            settlement_proposal.to_string(),
+            service_status.to_string(),
+            gossip.to_string(),
+        ])
     }
 }
 
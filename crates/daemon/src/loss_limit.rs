@@ -0,0 +1,153 @@
+//! Daily limit on the realized losses of the taker.
+//!
+//! The realized profit and loss of a day is the sum of the profits and losses of all CFDs whose
+//! settlement was confirmed on that (UTC) day. Once the net loss reaches the configured limit,
+//! new orders are refused until the next UTC day, unless the limit is overridden for the day.
+
+use crate::projection::Cfd;
+use anyhow::Result;
+use async_trait::async_trait;
+use bdk::bitcoin::Amount;
+use bdk::bitcoin::SignedAmount;
+use model::Timestamp;
+use serde::Serialize;
+use time::Date;
+use time::OffsetDateTime;
+use tokio::sync::watch;
+use xtra_productivity::xtra_productivity;
+
+pub struct Actor {
+    db: sqlite_db::Connection,
+    cfds: watch::Receiver<Option<Vec<Cfd>>>,
+    limit: Amount,
+    overridden_on: Option<Date>,
+}
+
+impl Actor {
+    pub fn new(
+        db: sqlite_db::Connection,
+        cfds: watch::Receiver<Option<Vec<Cfd>>>,
+        limit: Amount,
+    ) -> Self {
+        Self {
+            db,
+            cfds,
+            limit,
+            overridden_on: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Status {
+    #[serde(with = "::bdk::bitcoin::util::amount::serde::as_btc")]
+    pub limit: Amount,
+    /// Net loss of all CFDs closed today, zero if today's CFDs made a profit
+    #[serde(with = "::bdk::bitcoin::util::amount::serde::as_btc")]
+    pub realized_loss_today: Amount,
+    pub limit_reached: bool,
+    /// Whether the limit was overridden for today
+    pub overridden: bool,
+}
+
+impl Status {
+    /// Whether new orders have to be refused
+    pub fn refuses_orders(&self) -> bool {
+        self.limit_reached && !self.overridden
+    }
+}
+
+/// Get the realized loss of today and whether the limit was reached.
+#[derive(Clone, Copy)]
+pub struct GetStatus;
+
+/// Accept new orders for the rest of the current UTC day even if the limit was reached.
+#[derive(Clone, Copy)]
+pub struct Override;
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: GetStatus) -> Result<Status> {
+        let now = OffsetDateTime::now_utc();
+        let start_of_day = now.date().midnight().assume_utc();
+
+        let closed_today = self
+            .db
+            .load_cfd_ids_closed_since(Timestamp::new(start_of_day.unix_timestamp()))
+            .await?;
+
+        let profits = self
+            .cfds
+            .borrow()
+            .iter()
+            .flatten()
+            .filter(|cfd| closed_today.contains(&cfd.order_id))
+            .filter_map(|cfd| cfd.profit_btc)
+            .collect::<Vec<_>>();
+
+        let realized_loss_today = realized_loss(profits);
+
+        Ok(Status {
+            limit: self.limit,
+            realized_loss_today,
+            limit_reached: realized_loss_today >= self.limit,
+            overridden: self.overridden_on == Some(now.date()),
+        })
+    }
+
+    async fn handle(&mut self, _: Override) {
+        let today = OffsetDateTime::now_utc().date();
+
+        tracing::info!(%today, "Daily loss limit overridden");
+
+        self.overridden_on = Some(today);
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+/// The net loss of the given profits and losses, zero if they add up to a profit.
+fn realized_loss(profits: impl IntoIterator<Item = SignedAmount>) -> Amount {
+    let net = profits
+        .into_iter()
+        .fold(SignedAmount::ZERO, |acc, profit| acc + profit);
+
+    if net.is_negative() {
+        net.abs()
+            .to_unsigned()
+            .expect("absolute amount to be positive")
+    } else {
+        Amount::ZERO
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_net_loss_then_realized_loss_is_net_loss() {
+        let profits = [
+            SignedAmount::from_sat(-50_000),
+            SignedAmount::from_sat(20_000),
+            SignedAmount::from_sat(-10_000),
+        ];
+
+        assert_eq!(realized_loss(profits), Amount::from_sat(40_000));
+    }
+
+    #[test]
+    fn given_net_profit_then_no_realized_loss() {
+        let profits = [
+            SignedAmount::from_sat(-50_000),
+            SignedAmount::from_sat(60_000),
+        ];
+
+        assert_eq!(realized_loss(profits), Amount::ZERO);
+    }
+}
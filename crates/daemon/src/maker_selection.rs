@@ -0,0 +1,170 @@
+//! Selection of the maker to trade with if several makers offer equivalent terms.
+//!
+//! Offers are equivalent if they are for the same contract symbol and position and accept the
+//! quantity and leverage of the order. Among equivalent offers the one with the best price for the
+//! taker wins, unless an offer of a more preferred maker is within the price tolerance.
+
+use anyhow::ensure;
+use anyhow::Result;
+use libp2p_core::PeerId;
+use model::Contracts;
+use model::Leverage;
+use model::Offer;
+use model::Position;
+use rust_decimal::Decimal;
+use std::cmp::Ordering;
+
+/// Policy for choosing between equivalent offers of several makers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Preferences {
+    /// Makers in order of preference, makers not listed are least preferred
+    preferred_makers: Vec<PeerId>,
+    /// Relative price difference to the best price that is accepted in favour of a more
+    /// preferred maker, e.g. `0.001` for 0.1%
+    price_tolerance: Decimal,
+}
+
+impl Preferences {
+    pub fn new(preferred_makers: Vec<PeerId>, price_tolerance: Decimal) -> Result<Self> {
+        ensure!(
+            price_tolerance >= Decimal::ZERO && price_tolerance < Decimal::ONE,
+            "Price tolerance must be in [0, 1), got {price_tolerance}"
+        );
+
+        Ok(Self {
+            preferred_makers,
+            price_tolerance,
+        })
+    }
+
+    /// Select the offer to take among offers that are equivalent for the taker.
+    ///
+    /// Returns `None` if there are no candidates.
+    pub fn select<'a>(&self, candidates: &'a [(PeerId, Offer)]) -> Option<&'a (PeerId, Offer)> {
+        let best = candidates
+            .iter()
+            .min_by(|(_, a), (_, b)| compare_prices(a, b))?;
+
+        candidates
+            .iter()
+            .filter(|(_, offer)| self.is_within_tolerance(offer, &best.1))
+            .min_by(|(maker_a, a), (maker_b, b)| {
+                self.rank(maker_a)
+                    .cmp(&self.rank(maker_b))
+                    .then_with(|| compare_prices(a, b))
+            })
+    }
+
+    fn rank(&self, maker: &PeerId) -> usize {
+        self.preferred_makers
+            .iter()
+            .position(|preferred| preferred == maker)
+            .unwrap_or(self.preferred_makers.len())
+    }
+
+    fn is_within_tolerance(&self, offer: &Offer, best: &Offer) -> bool {
+        let best_price = best.price.into_decimal();
+        let difference = (offer.price.into_decimal() - best_price).abs();
+
+        difference <= best_price * self.price_tolerance
+    }
+}
+
+/// Whether `offer` is an alternative to `reference` for an order of the given quantity and
+/// leverage.
+pub fn is_equivalent(
+    offer: &Offer,
+    reference: &Offer,
+    quantity: Contracts,
+    leverage: Leverage,
+) -> bool {
+    offer.contract_symbol == reference.contract_symbol
+        && offer.position_maker == reference.position_maker
+        && crate::preflight::check_order_parameters(offer, quantity, leverage).is_ok()
+}
+
+/// Order offers by how good their price is for the taker, best first.
+///
+/// The taker takes the opposite position of the maker: a taker going long wants to buy low, a
+/// taker going short wants to sell high.
+fn compare_prices(a: &Offer, b: &Offer) -> Ordering {
+    match a.position_maker {
+        Position::Short => a.price.into_decimal().cmp(&b.price.into_decimal()),
+        Position::Long => b.price.into_decimal().cmp(&a.price.into_decimal()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::Price;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn given_no_preferences_then_best_price_selected() {
+        let (a, b) = (PeerId::random(), PeerId::random());
+        let candidates = vec![
+            (a, dummy_offer(Position::Short, dec!(1001))),
+            (b, dummy_offer(Position::Short, dec!(1000))),
+        ];
+
+        let (maker, _) = Preferences::default().select(&candidates).unwrap();
+
+        assert_eq!(*maker, b);
+    }
+
+    #[test]
+    fn given_maker_long_then_highest_price_is_best_for_taker() {
+        let (a, b) = (PeerId::random(), PeerId::random());
+        let candidates = vec![
+            (a, dummy_offer(Position::Long, dec!(1001))),
+            (b, dummy_offer(Position::Long, dec!(1000))),
+        ];
+
+        let (maker, _) = Preferences::default().select(&candidates).unwrap();
+
+        assert_eq!(*maker, a);
+    }
+
+    #[test]
+    fn given_preferred_maker_within_tolerance_then_preferred_maker_selected() {
+        let (a, b) = (PeerId::random(), PeerId::random());
+        let candidates = vec![
+            (a, dummy_offer(Position::Short, dec!(1001))),
+            (b, dummy_offer(Position::Short, dec!(1000))),
+        ];
+        let preferences = Preferences::new(vec![a], dec!(0.001)).unwrap();
+
+        let (maker, _) = preferences.select(&candidates).unwrap();
+
+        assert_eq!(*maker, a);
+    }
+
+    #[test]
+    fn given_preferred_maker_outside_tolerance_then_best_price_selected() {
+        let (a, b) = (PeerId::random(), PeerId::random());
+        let candidates = vec![
+            (a, dummy_offer(Position::Short, dec!(1002))),
+            (b, dummy_offer(Position::Short, dec!(1000))),
+        ];
+        let preferences = Preferences::new(vec![a], dec!(0.001)).unwrap();
+
+        let (maker, _) = preferences.select(&candidates).unwrap();
+
+        assert_eq!(*maker, b);
+    }
+
+    #[test]
+    fn given_tolerance_outside_range_then_rejected() {
+        assert!(Preferences::new(vec![], dec!(-0.1)).is_err());
+        assert!(Preferences::new(vec![], dec!(1)).is_err());
+    }
+
+    fn dummy_offer(position_maker: Position, price: Decimal) -> Offer {
+        Offer {
+            position_maker,
+            price: Price::new(price).unwrap(),
+            ..Offer::dummy()
+        }
+    }
+}
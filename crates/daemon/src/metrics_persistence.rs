@@ -0,0 +1,128 @@
+//! Persistence of business-level counters across restarts.
+//!
+//! Prometheus counters start from zero whenever the daemon restarts, which makes long-horizon
+//! figures like the total volume traded awkward to track. The persisted counters are snapshotted to
+//! the database periodically and the snapshots are added back onto the counters at startup.
+
+use crate::position_metrics;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use model::Timestamp;
+use prometheus::core::Collector;
+use sqlite_db::metric_snapshots::MetricSnapshot;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::time::Duration;
+use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+pub struct Actor {
+    db: sqlite_db::Connection,
+    interval: Duration,
+}
+
+impl Actor {
+    pub fn new(db: sqlite_db::Connection, interval: Duration) -> Self {
+        Self { db, interval }
+    }
+
+    /// Add the snapshotted values onto the counters.
+    ///
+    /// Counters may have been incremented since startup already, adding rather than setting the
+    /// values keeps these increments.
+    async fn restore(&self) -> Result<()> {
+        let counters = position_metrics::persisted_counters();
+
+        for snapshot in self.db.load_metric_snapshots().await? {
+            let counter = match counters.iter().find(|counter| {
+                counter
+                    .desc()
+                    .iter()
+                    .any(|desc| desc.fq_name == snapshot.name)
+            }) {
+                Some(counter) => counter,
+                None => {
+                    tracing::debug!(name = %snapshot.name, "Ignoring snapshot of unknown counter");
+                    continue;
+                }
+            };
+
+            let labels = serde_json::from_str::<BTreeMap<String, String>>(&snapshot.labels)
+                .with_context(|| format!("Invalid labels of counter {}", snapshot.name))?;
+            let labels = labels
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect::<HashMap<_, _>>();
+
+            counter
+                .get_metric_with(&labels)
+                .with_context(|| format!("Labels do not match counter {}", snapshot.name))?
+                .inc_by(snapshot.value);
+        }
+
+        Ok(())
+    }
+
+    async fn snapshot(&self) -> Result<()> {
+        let mut snapshots = Vec::new();
+
+        for family in position_metrics::persisted_counters()
+            .into_iter()
+            .flat_map(|counter| counter.collect())
+        {
+            for metric in family.get_metric() {
+                let labels = metric
+                    .get_label()
+                    .iter()
+                    .map(|label| (label.get_name(), label.get_value()))
+                    .collect::<BTreeMap<_, _>>();
+
+                snapshots.push(MetricSnapshot {
+                    name: family.get_name().to_owned(),
+                    labels: serde_json::to_string(&labels)?,
+                    value: metric.get_counter().get_value(),
+                });
+            }
+        }
+
+        self.db
+            .upsert_metric_snapshots(&snapshots, Timestamp::now())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        // Snapshotting without the restored values would overwrite the persisted ones
+        if let Err(e) = self.restore().await {
+            tracing::error!("Failed to restore persisted metrics, not persisting metrics: {e:#}");
+            return;
+        }
+
+        let this = ctx.address().expect("we just started");
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(self.interval, || Snapshot, xtras::IncludeSpan::Never),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[derive(Clone, Copy)]
+struct Snapshot;
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: Snapshot) {
+        if let Err(e) = self.snapshot().await {
+            tracing::warn!("Failed to persist metrics: {e:#}");
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! Handling of CFDs whose oracle attestation is missing past the settlement time.
+//!
+//! Without the attestation, no CET can be decrypted and the CFD can only be closed through the
+//! refund transaction. The refund timelock is relative to the commit transaction, hence the refund
+//! path only becomes reachable after the commit transaction was published. The [`Policy`] decides
+//! whether the daemon keeps waiting for the attestation, alerts the operator or publishes the
+//! commit transaction to start the refund timelock. If the attestation arrives before the refund
+//! timelock expires, the CFD is still settled through the CET.
+
+use crate::command;
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::StreamExt;
+use model::OrderId;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use time::OffsetDateTime;
+use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+/// Time after the settlement event after which the attestation is considered missing
+///
+/// Olivia usually attests within seconds, this leaves plenty of room for delays.
+pub const GRACE_PERIOD: time::Duration = time::Duration::hours(1);
+
+/// Interval at which the CFDs are checked for missing attestations
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+static MISSING_ATTESTATIONS_GAUGE: conquer_once::Lazy<prometheus::IntGauge> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_int_gauge!(
+            "cfds_missing_attestation_total",
+            "The number of open CFDs whose oracle attestation is overdue."
+        )
+        .unwrap()
+    });
+
+/// What to do about CFDs whose attestation is missing past the grace period.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Policy {
+    /// Keep waiting for the attestation
+    Wait,
+    /// Keep waiting for the attestation, but report every affected CFD as an error
+    Alert,
+    /// Publish the commit transaction so that the refund transaction can be published once the
+    /// refund timelock expired
+    Refund,
+}
+
+impl FromStr for Policy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wait" => Ok(Policy::Wait),
+            "alert" => Ok(Policy::Alert),
+            "refund" => Ok(Policy::Refund),
+            other => bail!("Unknown policy {other}, expected `wait`, `alert` or `refund`"),
+        }
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Policy::Wait => write!(f, "wait"),
+            Policy::Alert => write!(f, "alert"),
+            Policy::Refund => write!(f, "refund"),
+        }
+    }
+}
+
+pub struct Actor {
+    db: sqlite_db::Connection,
+    executor: command::Executor,
+    policy: Policy,
+    /// CFDs the policy was already applied to
+    handled: HashSet<OrderId>,
+}
+
+impl Actor {
+    pub fn new(db: sqlite_db::Connection, executor: command::Executor, policy: Policy) -> Self {
+        Self {
+            db,
+            executor,
+            policy,
+            handled: HashSet::new(),
+        }
+    }
+
+    async fn check(&mut self) {
+        let now = OffsetDateTime::now_utc();
+        let mut missing = Vec::new();
+
+        let mut stream = self.db.load_all_open_cfds::<model::Cfd>(());
+        while let Some(cfd) = stream.next().await {
+            let cfd = match cfd {
+                Ok(cfd) => cfd,
+                Err(e) => {
+                    tracing::warn!("Failed to load CFD from database: {e:#}");
+                    continue;
+                }
+            };
+
+            if let Some(missing_since) = cfd.attestation_missing_since(now, GRACE_PERIOD) {
+                missing.push((cfd.id(), missing_since, cfd.is_in_force_close()));
+            }
+        }
+
+        MISSING_ATTESTATIONS_GAUGE.set(missing.len() as i64);
+        self.handled
+            .retain(|order_id| missing.iter().any(|(id, ..)| id == order_id));
+
+        for (order_id, missing_since, committed) in missing {
+            if !self.handled.insert(order_id) {
+                continue;
+            }
+
+            match self.policy {
+                Policy::Wait => {
+                    tracing::info!(%order_id, %missing_since, "Attestation missing, waiting for it");
+                }
+                Policy::Alert => {
+                    tracing::error!(%order_id, %missing_since, "Attestation missing, CFD cannot be settled through a CET");
+                }
+                Policy::Refund if committed => {
+                    tracing::info!(%order_id, %missing_since, "Attestation missing, waiting for refund timelock of published commit transaction");
+                }
+                Policy::Refund => {
+                    tracing::warn!(%order_id, %missing_since, "Attestation missing, committing to start refund timelock");
+
+                    if let Err(e) = self
+                        .executor
+                        .execute(order_id, |cfd| cfd.manual_commit_to_blockchain())
+                        .await
+                    {
+                        tracing::warn!(%order_id, "Failed to commit CFD: {e:#}");
+                        self.handled.remove(&order_id);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Check all open CFDs for missing attestations.
+#[derive(Clone, Copy)]
+struct Check;
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: Check) {
+        self.check().await;
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we just started");
+
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(CHECK_INTERVAL, || Check, xtras::IncludeSpan::Never),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
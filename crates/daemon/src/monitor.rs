@@ -1,6 +1,13 @@
 use crate::bitcoin::consensus::encode::serialize_hex;
 use crate::bitcoin::Transaction;
+use crate::cet_broadcast;
+use crate::chain_consistency;
 use crate::command;
+use crate::electrum_health::ElectrumStatus;
+use crate::electrum_health::EndpointWatcher;
+use crate::projection;
+use crate::projection::ChainTip;
+use crate::projection::RefundEstimate;
 use crate::wallet::RpcErrorCode;
 use anyhow::Context;
 use anyhow::Result;
@@ -12,22 +19,29 @@ use bdk::descriptor::Descriptor;
 use bdk::electrum_client;
 use bdk::electrum_client::ElectrumApi;
 use bdk::miniscript::DescriptorTrait;
+use btsieve::BlockHeight;
 use btsieve::ScriptStatus;
 use btsieve::State;
 use btsieve::TxStatus;
+pub use collab_settlement::taker::MonitorPendingCollaborativeSettlement;
 use futures::StreamExt;
 use model::CfdEvent;
 use model::Dlc;
 use model::EventKind;
 use model::OrderId;
+use model::Timestamp;
 use model::CET_TIMELOCK;
 use serde_json::Value;
 use sqlite_db;
+use sqlite_db::cet_publications::PublishedBy;
 use std::collections::HashMap;
 use std::time::Duration;
 use std::time::Instant;
+use tokio::sync::watch;
 use tracing::Instrument;
+use xtra::prelude::MessageChannel;
 use xtra_productivity::xtra_productivity;
+use xtras::SendAsyncSafe;
 use xtras::SendInterval;
 
 const LOCK_FINALITY_CONFIRMATIONS: u32 = 1;
@@ -43,6 +57,15 @@ const REFUND_FINALITY_CONFIRMATIONS: u32 = 3;
 /// used which is hard to be predicted.
 const ELECTRUM_CLIENT_TIMEOUT_SECS: u8 = 120;
 
+/// Number of blocks the monitor may fall behind the chain before we warn about it
+///
+/// Since we cannot learn the actual chain tip while the Electrum backend is unreachable, the
+/// number of missed blocks is estimated from the time passed since the last successful sync.
+const MAX_BLOCKS_BEHIND: i64 = 3;
+
+/// Average time between two blocks in seconds
+const BLOCK_INTERVAL_SECS: i64 = 600;
+
 pub struct MonitorAfterContractSetup {
     order_id: OrderId,
     transactions: TransactionsAfterContractSetup,
@@ -78,6 +101,15 @@ pub enum TransactionKind {
 }
 
 impl TransactionKind {
+    /// Whether publishing the transaction relies on the monitor's view of the chain, e.g. because
+    /// a timelock expired.
+    fn depends_on_chain_view(&self) -> bool {
+        match self {
+            TransactionKind::Commit | TransactionKind::Cet | TransactionKind::Refund => true,
+            TransactionKind::Lock | TransactionKind::CollaborativeClose => false,
+        }
+    }
+
     fn name(&self) -> &'static str {
         match self {
             TransactionKind::Lock => "lock",
@@ -116,8 +148,20 @@ pub struct Sync;
 pub struct Actor {
     executor: command::Executor,
     client: bdk::electrum_client::Client,
+    electrum_endpoint: EndpointWatcher,
     state: State<Event>,
     db: sqlite_db::Connection,
+    chain_tip: ChainTip,
+    chain_tip_feed: MessageChannel<projection::Update<ChainTip>, ()>,
+    refund_estimates_feed: MessageChannel<projection::Update<Vec<RefundEstimate>>, ()>,
+    chain_consistency: Option<watch::Receiver<chain_consistency::Status>>,
+    /// Timelock-dependent transactions not broadcast because the chain views diverged
+    held_back_broadcasts: Vec<TryBroadcastTransaction>,
+    /// Refund timelocks of the CFDs that are neither closed nor refund-eligible yet
+    refund_timelocks: HashMap<OrderId, RefundTimelock>,
+    cet_broadcast: cet_broadcast::Config,
+    /// CETs we leave to the counterparty to publish for now
+    deferred_cets: Vec<DeferredCet>,
 }
 
 /// Read-model of the CFD for the monitoring actor.
@@ -138,6 +182,8 @@ struct Cfd {
 
     cet: Option<(Txid, Script)>,
     monitor_cet_finality: bool,
+    /// Watch for CETs spending the commit transaction that we did not publish
+    monitor_counterparty_cets: bool,
 
     refund: Option<Refund>,
     monitor_refund_finality: bool,
@@ -168,6 +214,7 @@ impl sqlite_db::CfdAggregate for Cfd {
             monitor_refund_timelock: false,
             cet: None,
             monitor_cet_finality: false,
+            monitor_counterparty_cets: false,
             refund: None,
             monitor_refund_finality: false,
             monitor_revoked_commit_transactions: Vec::new(),
@@ -215,6 +262,7 @@ impl Cfd {
                     monitor_commit_finality: true,
                     monitor_cet_timelock: true,
                     monitor_refund_timelock: true,
+                    monitor_counterparty_cets: true,
                     refund: Some(refund),
                     monitor_refund_finality: true,
                     monitor_revoked_commit_transactions: Vec::new(),
@@ -235,6 +283,7 @@ impl Cfd {
                     monitor_commit_finality: true,
                     monitor_cet_timelock: true,
                     monitor_refund_timelock: true,
+                    monitor_counterparty_cets: true,
                     refund: Some(refund),
                     monitor_refund_finality: true,
                     monitor_revoked_commit_transactions: revoked_commits,
@@ -277,6 +326,7 @@ impl Cfd {
                 monitor_revoked_commit_transactions: Vec::new(),
                 monitor_collaborative_settlement_finality: false,
                 monitor_cet_finality: false,
+                monitor_counterparty_cets: false,
                 broadcast_lock: None,
                 broadcast_cet: None,
                 broadcast_commit: None,
@@ -292,6 +342,14 @@ impl Cfd {
                 cet: cet_txid_and_script(cet),
                 monitor_cet_finality: true,
                 monitor_cet_timelock: false,
+                monitor_counterparty_cets: false,
+                ..self
+            },
+            CetPublishedByCounterparty { cet, .. } => Self {
+                cet: cet_txid_and_script(cet),
+                monitor_cet_finality: true,
+                monitor_cet_timelock: false,
+                monitor_counterparty_cets: false,
                 ..self
             },
             RefundTimelockExpired { .. } => Self {
@@ -303,6 +361,8 @@ impl Cfd {
             | RolloverStarted { .. }
             | RolloverAccepted
             | RolloverFailed
+            | RolloverFundingRateRejected { .. }
+            | SettlementProposedByMaker { .. }
             | OracleAttestedPriorCetTimelock { .. }
             | CollaborativeSettlementStarted { .. }
             | CollaborativeSettlementRejected
@@ -311,7 +371,8 @@ impl Cfd {
             | ContractSetupStarted
             | ContractSetupFailed
             | OfferRejected
-            | RolloverRejected => self,
+            | RolloverRejected
+            | PriceLevelsSet { .. } => self,
             RevokeConfirmed => {
                 // TODO: Implement revoked logic
                 self
@@ -333,20 +394,17 @@ fn cet_txid_and_script(cet: Transaction) -> Option<(Txid, Script)> {
 impl Actor {
     pub fn new(
         db: sqlite_db::Connection,
-        electrum_rpc_url: String,
+        electrum: watch::Receiver<ElectrumStatus>,
         executor: command::Executor,
+        chain_tip_feed: MessageChannel<projection::Update<ChainTip>, ()>,
+        refund_estimates_feed: MessageChannel<projection::Update<Vec<RefundEstimate>>, ()>,
     ) -> Result<Self> {
-        let client = bdk::electrum_client::Client::from_config(
-            &electrum_rpc_url,
-            electrum_client::ConfigBuilder::new()
-                .timeout(Some(ELECTRUM_CLIENT_TIMEOUT_SECS))?
-                .build(),
-        )
-        .context("Failed to initialize Electrum RPC client")?;
+        let electrum_endpoint = EndpointWatcher::new(electrum);
+        let client = connect(electrum_endpoint.current())?;
 
         // Initially fetch the latest block for storing the height.
         // We do not act on this subscription after this call.
-        let latest_block = client
+        let latest_block: BlockHeight = client
             .block_headers_subscribe()
             .context("Failed to subscribe to header notifications")?
             .height
@@ -354,11 +412,45 @@ impl Actor {
 
         Ok(Self {
             client,
+            electrum_endpoint,
             executor,
             state: State::new(latest_block),
             db,
+            chain_tip: ChainTip {
+                height: latest_block.into(),
+                last_synced_at: Timestamp::now(),
+                electrum_healthy: true,
+                soonest_refund: None,
+            },
+            chain_tip_feed,
+            refund_estimates_feed,
+            chain_consistency: None,
+            held_back_broadcasts: Vec::new(),
+            refund_timelocks: HashMap::new(),
+            cet_broadcast: cet_broadcast::Config::default(),
+            deferred_cets: Vec::new(),
         })
     }
+
+    /// Hold back timelock-dependent broadcasts while the chain views of wallet and monitor
+    /// diverge.
+    pub fn with_chain_consistency(
+        self,
+        status: watch::Receiver<chain_consistency::Status>,
+    ) -> Self {
+        Self {
+            chain_consistency: Some(status),
+            ..self
+        }
+    }
+
+    /// Decide when to publish CETs according to `config` instead of publishing them immediately.
+    pub fn with_cet_broadcast(self, config: cet_broadcast::Config) -> Self {
+        Self {
+            cet_broadcast: config,
+            ..self
+        }
+    }
 }
 
 impl Actor {
@@ -371,7 +463,13 @@ impl Actor {
         )
     }
 
-    fn monitor_commit_finality(&mut self, order_id: OrderId, Commit { txid, descriptor }: Commit) {
+    fn monitor_commit_finality(
+        &mut self,
+        order_id: OrderId,
+        Commit {
+            txid, descriptor, ..
+        }: Commit,
+    ) {
         self.state.monitor(
             txid,
             descriptor.script_pubkey(),
@@ -389,6 +487,15 @@ impl Actor {
         );
     }
 
+    fn monitor_pending_close(&mut self, order_id: OrderId, (txid, script): (Txid, Script)) {
+        self.state.monitor(
+            txid,
+            script,
+            ScriptStatus::InMempool,
+            Event::CounterpartyCloseFound(order_id, txid),
+        );
+    }
+
     fn monitor_cet_finality(&mut self, order_id: OrderId, close_params: (Txid, Script)) {
         self.state.monitor(
             close_params.0,
@@ -401,7 +508,9 @@ impl Actor {
     fn monitor_commit_cet_timelock(
         &mut self,
         order_id: OrderId,
-        Commit { txid, descriptor }: Commit,
+        Commit {
+            txid, descriptor, ..
+        }: Commit,
     ) {
         self.state.monitor(
             txid,
@@ -414,17 +523,53 @@ impl Actor {
     fn monitor_commit_refund_timelock(
         &mut self,
         order_id: OrderId,
-        Commit { txid, descriptor }: Commit,
+        Commit {
+            txid, descriptor, ..
+        }: Commit,
         refund_timelock: u32,
     ) {
+        let script_pubkey = descriptor.script_pubkey();
+
+        // A rollover replaces the commit transaction and thus the refund timelock
+        self.refund_timelocks.insert(
+            order_id,
+            RefundTimelock {
+                commit_txid: txid,
+                commit_script_pubkey: script_pubkey.clone(),
+                blocks: refund_timelock,
+            },
+        );
+
         self.state.monitor(
             txid,
-            descriptor.script_pubkey(),
+            script_pubkey,
             ScriptStatus::with_confirmations(refund_timelock),
             Event::RefundTimelockExpired(order_id),
         );
     }
 
+    /// Watch the commit output for being spent by any of the CETs.
+    ///
+    /// The CETs spend the commit output, hence they show up in the history of its script.
+    fn monitor_counterparty_cets(
+        &mut self,
+        order_id: OrderId,
+        Commit {
+            descriptor, cets, ..
+        }: Commit,
+    ) {
+        let script = descriptor.script_pubkey();
+
+        for txid in cets {
+            self.state.monitor(
+                txid,
+                script.clone(),
+                ScriptStatus::InMempool,
+                Event::CounterpartyCetFound(order_id, txid),
+            );
+        }
+    }
+
     fn monitor_refund_finality(
         &mut self,
         order_id: OrderId,
@@ -461,16 +606,33 @@ impl Actor {
         }
     }
 
+    /// Reconnect to the active Electrum endpoint if it changed since the last sync.
+    fn reconnect_if_endpoint_changed(&mut self) -> Result<()> {
+        let endpoint = match self.electrum_endpoint.changed() {
+            Some(endpoint) => endpoint,
+            None => return Ok(()),
+        };
+
+        self.client = connect(&endpoint)?;
+
+        tracing::info!(%endpoint, "Monitor switched Electrum endpoint");
+        self.electrum_endpoint.connected_to(endpoint);
+
+        Ok(())
+    }
+
     #[tracing::instrument("Sync monitor", skip_all, err)]
     async fn sync(&mut self) -> Result<()> {
         let start_time = Instant::now();
 
+        self.reconnect_if_endpoint_changed()?;
+
         // Fetch the latest block for storing the height.
         // We do not act on this subscription after this call, as we cannot rely on
         // subscription push notifications because eventually the Electrum server will
         // close the connection and subscriptions are not automatically renewed
         // upon renewing the connection.
-        let latest_block_height = self
+        let latest_block_height: BlockHeight = self
             .client
             .block_headers_subscribe()
             .context("Failed to subscribe to header notifications")?
@@ -488,6 +650,13 @@ impl Actor {
 
         tracing::trace!("Sync Update: Fetching histories finished, updating state");
 
+        self.chain_tip = ChainTip {
+            height: latest_block_height.into(),
+            last_synced_at: Timestamp::now(),
+            electrum_healthy: true,
+            soonest_refund: self.chain_tip.soonest_refund,
+        };
+
         let mut ready_events = self.state.update(
             latest_block_height,
             histories
@@ -516,6 +685,7 @@ impl Actor {
                         .await
                 }
                 Event::CloseFinality(id) => {
+                    self.refund_timelocks.remove(&id);
                     self.invoke_cfd_command(id, |cfd| {
                         Ok(Some(cfd.handle_collaborative_settlement_confirmed()))
                     })
@@ -526,10 +696,12 @@ impl Actor {
                         .await
                 }
                 Event::CetFinality(id) => {
+                    self.refund_timelocks.remove(&id);
                     self.invoke_cfd_command(id, |cfd| Ok(Some(cfd.handle_cet_confirmed())))
                         .await
                 }
                 Event::RefundFinality(id) => {
+                    self.refund_timelocks.remove(&id);
                     self.invoke_cfd_command(id, |cfd| Ok(Some(cfd.handle_refund_confirmed())))
                         .await
                 }
@@ -537,13 +709,42 @@ impl Actor {
                     self.invoke_cfd_command(id, |cfd| Ok(Some(cfd.handle_revoke_confirmed())))
                         .await
                 }
+                Event::CounterpartyCloseFound(id, txid) => match self.client.transaction_get(&txid)
+                {
+                    Ok(spend_tx) => {
+                        self.invoke_cfd_command(id, |cfd| {
+                            cfd.complete_collaborative_settlement_by_counterparty(spend_tx)
+                        })
+                        .await
+                    }
+                    Err(e) => {
+                        tracing::warn!(order_id = %id, %txid, "Failed to fetch collaborative settlement transaction: {e:#}");
+                    }
+                },
                 Event::RefundTimelockExpired(id) => {
+                    self.refund_timelocks.remove(&id);
                     self.invoke_cfd_command(id, |cfd| cfd.handle_refund_timelock_expired())
                         .await
                 }
+                Event::CounterpartyCetFound(id, txid) => match self.client.transaction_get(&txid) {
+                    Ok(cet) => {
+                        self.invoke_cfd_command(id, |cfd| cfd.handle_counterparty_cet(cet))
+                            .await
+                    }
+                    Err(e) => {
+                        tracing::warn!(order_id = %id, %txid, "Failed to fetch CET: {e:#}");
+                    }
+                },
             }
         }
 
+        let refund_estimates = self.estimate_refunds();
+        self.chain_tip.soonest_refund = refund_estimates
+            .iter()
+            .min_by_key(|estimate| estimate.blocks_remaining)
+            .copied();
+        self.report_refund_estimates(refund_estimates).await;
+
         let execution_time = start_time.elapsed().as_secs_f64();
         SYNC_DURATION_HISTOGRAM.observe(execution_time);
         tracing::debug!("Sync Finished: Execution time {execution_time:?}");
@@ -551,6 +752,45 @@ impl Actor {
         Ok(())
     }
 
+    /// Estimate when the committed CFDs become refund-eligible.
+    ///
+    /// CFDs whose commit transaction is not confirmed are left out, their refund timelock has not
+    /// started yet.
+    fn estimate_refunds(&self) -> Vec<RefundEstimate> {
+        self.refund_timelocks
+            .iter()
+            .filter_map(|(order_id, timelock)| {
+                let blocks_remaining = self.blocks_until_refund(timelock)?;
+
+                Some(RefundEstimate::new(*order_id, blocks_remaining))
+            })
+            .collect()
+    }
+
+    /// Number of blocks until the refund timelock expires, `None` unless the commit transaction
+    /// is confirmed.
+    fn blocks_until_refund(&self, timelock: &RefundTimelock) -> Option<u32> {
+        let confirmations = match self
+            .state
+            .status(timelock.commit_txid, &timelock.commit_script_pubkey)?
+        {
+            ScriptStatus::Confirmed(confirmed) => confirmed.confirmations(),
+            ScriptStatus::Unseen | ScriptStatus::InMempool => return None,
+        };
+
+        Some(timelock.blocks.saturating_sub(confirmations))
+    }
+
+    async fn report_refund_estimates(&self, refund_estimates: Vec<RefundEstimate>) {
+        if let Err(e) = self
+            .refund_estimates_feed
+            .send_async_safe(projection::Update(refund_estimates))
+            .await
+        {
+            tracing::warn!("Failed to report refund estimates to projection: {e:#}");
+        }
+    }
+
     async fn invoke_cfd_command(
         &self,
         order_id: OrderId,
@@ -565,6 +805,18 @@ impl Actor {
     }
 }
 
+fn connect(electrum_rpc_url: &str) -> Result<electrum_client::Client> {
+    let client = electrum_client::Client::from_config(
+        electrum_rpc_url,
+        electrum_client::ConfigBuilder::new()
+            .timeout(Some(ELECTRUM_CLIENT_TIMEOUT_SECS))?
+            .build(),
+    )
+    .context("Failed to initialize Electrum RPC client")?;
+
+    Ok(client)
+}
+
 #[derive(Debug, Clone, PartialEq, Copy)]
 enum Event {
     LockFinality(OrderId),
@@ -575,6 +827,8 @@ enum Event {
     RefundTimelockExpired(OrderId),
     RefundFinality(OrderId),
     RevokedTransactionFound(OrderId),
+    CounterpartyCloseFound(OrderId, Txid),
+    CounterpartyCetFound(OrderId, Txid),
 }
 
 #[async_trait]
@@ -612,6 +866,7 @@ impl xtra::Actor for Actor {
                             monitor_refund_timelock,
                             cet,
                             monitor_cet_finality,
+                            monitor_counterparty_cets,
                             refund,
                             monitor_refund_finality,
                             monitor_revoked_commit_transactions,
@@ -680,6 +935,7 @@ impl xtra::Actor for Actor {
                             monitor_refund_timelock,
                             cet,
                             monitor_cet_finality,
+                            monitor_counterparty_cets,
                             refund,
                             monitor_refund_finality,
                             monitor_revoked_commit_transactions,
@@ -715,6 +971,7 @@ impl Actor {
         self.monitor_lock_finality(order_id, lock);
         self.monitor_commit_finality(order_id, commit.clone());
         self.monitor_commit_cet_timelock(order_id, commit.clone());
+        self.monitor_counterparty_cets(order_id, commit.clone());
         self.monitor_commit_refund_timelock(order_id, commit, refund.timelock);
         self.monitor_refund_finality(order_id, refund);
     }
@@ -732,6 +989,7 @@ impl Actor {
 
         self.monitor_commit_finality(order_id, commit.clone());
         self.monitor_commit_cet_timelock(order_id, commit.clone());
+        self.monitor_counterparty_cets(order_id, commit.clone());
         self.monitor_commit_refund_timelock(order_id, commit, refund.timelock);
         self.monitor_refund_finality(order_id, refund);
         self.monitor_revoked_commit_transactions(order_id, revoked_commits)
@@ -747,53 +1005,29 @@ impl Actor {
         );
     }
 
-    async fn handle_try_broadcast_transaction(&self, msg: TryBroadcastTransaction) -> Result<()> {
-        let TryBroadcastTransaction { tx, kind } = msg;
-
-        let result = self.client.transaction_broadcast(&tx);
-
-        if let Err(electrum_client::Error::Protocol(ref value)) = result {
-            let rpc_error = parse_rpc_protocol_error(value)
-                .with_context(|| format!("Failed to parse electrum error response '{value:?}'"))?;
+    fn handle_pending_collaborative_settlement(
+        &mut self,
+        pending_settlement: MonitorPendingCollaborativeSettlement,
+    ) {
+        self.monitor_pending_close(pending_settlement.order_id, pending_settlement.tx);
+    }
 
-            if rpc_error.code == i64::from(RpcErrorCode::RpcVerifyAlreadyInChain) {
-                let txid = tx.txid();
-                tracing::trace!(
-                    %txid, kind = %kind.name(), "Attempted to broadcast transaction that was already on-chain",
-                );
+    async fn handle_try_broadcast_transaction(
+        &mut self,
+        msg: TryBroadcastTransaction,
+    ) -> Result<()> {
+        let TryBroadcastTransaction { tx, kind } = msg;
 
-                return Ok(());
-            }
+        if matches!(kind, TransactionKind::Cet)
+            && self.cet_broadcast.strategy != cet_broadcast::Strategy::Immediate
+        {
+            self.defer_cet(tx).await;
+            self.publish_deferred_cets().await;
 
-            // We do this check because electrum sometimes returns an RpcVerifyError when it should
-            // be returning a RpcVerifyAlreadyInChain error,
-            if rpc_error.code == i64::from(RpcErrorCode::RpcVerifyError)
-                && rpc_error.message == "bad-txns-inputs-missingorspent"
-            {
-                if let Ok(tx) = self.client.transaction_get(&tx.txid()) {
-                    let txid = tx.txid();
-                    tracing::trace!(
-                        %txid, kind = %kind.name(), "Attempted to broadcast transaction that was already on-chain",
-                    );
-                    return Ok(());
-                }
-            }
+            return Ok(());
         }
-        let txid = tx.txid();
-
-        result.with_context(|| {
-            let tx_hex = serialize_hex(&tx);
-
-            format!("Failed to broadcast transaction. Txid: {txid}. Kind: {}. Raw transaction: {tx_hex}", kind.name())
-        })?;
 
-        tracing::info!(%txid, kind = %kind.name(), "Transaction published on chain");
-
-        TRANSACTION_BROADCAST_COUNTER
-            .with(&HashMap::from([(KIND_LABEL, kind.name())]))
-            .inc();
-
-        Ok(())
+        self.broadcast_unless_held_back(tx, kind)
     }
 
     async fn handle_reinit_monitoring(&mut self, msg: ReinitMonitoring) {
@@ -809,6 +1043,7 @@ impl Actor {
             monitor_refund_timelock,
             cet,
             monitor_cet_finality,
+            monitor_counterparty_cets,
             refund,
             monitor_refund_finality,
             monitor_revoked_commit_transactions,
@@ -827,6 +1062,10 @@ impl Actor {
                 self.monitor_commit_cet_timelock(id, commit.clone());
             }
 
+            if monitor_counterparty_cets {
+                self.monitor_counterparty_cets(id, commit.clone());
+            }
+
             if let (Some(refund), true) = (&refund, monitor_refund_timelock) {
                 self.monitor_commit_refund_timelock(id, commit, refund.timelock);
             }
@@ -910,6 +1149,7 @@ impl TransactionsAfterContractSetup {
             commit: Commit {
                 txid: commit_tx.txid(),
                 descriptor: commit_descriptor.clone(),
+                cets: cet_txids(dlc),
             },
             refund: Refund {
                 txid: refund_txid,
@@ -955,6 +1195,7 @@ impl TransactionsAfterRollover {
             commit: Commit {
                 txid: commit_tx.txid(),
                 descriptor: commit_descriptor.clone(),
+                cets: cet_txids(dlc),
             },
             refund: Refund {
                 txid: refund_txid,
@@ -976,6 +1217,12 @@ struct Lock {
 struct Commit {
     txid: Txid,
     descriptor: Descriptor<PublicKey>,
+    /// The CETs spending the commit transaction
+    cets: Vec<Txid>,
+}
+
+fn cet_txids(dlc: &Dlc) -> Vec<Txid> {
+    dlc.cets.values().flatten().map(|cet| cet.txid).collect()
 }
 
 #[derive(Clone)]
@@ -985,6 +1232,21 @@ struct Refund {
     timelock: u32,
 }
 
+struct DeferredCet {
+    tx: Transaction,
+    deferred_at: Instant,
+    counterparty_usually_publishes: bool,
+    /// Whether the counterparty had the chance to publish the CET before us
+    waited: bool,
+}
+
+/// The refund timelock of a CFD, it starts with the confirmation of the commit transaction
+struct RefundTimelock {
+    commit_txid: Txid,
+    commit_script_pubkey: Script,
+    blocks: u32,
+}
+
 #[derive(Clone)]
 struct RevokedCommit {
     txid: Txid,
@@ -1007,6 +1269,7 @@ struct ReinitMonitoring {
 
     cet: Option<(Txid, Script)>,
     monitor_cet_finality: bool,
+    monitor_counterparty_cets: bool,
 
     refund: Option<Refund>,
     monitor_refund_finality: bool,
@@ -1019,6 +1282,225 @@ impl Actor {
     async fn handle(&mut self, _: Sync) {
         if let Err(e) = self.sync().await {
             tracing::warn!("Sync failed: {:#}", e);
+            self.chain_tip.electrum_healthy = false;
+        }
+
+        self.report_chain_tip().await;
+        self.broadcast_held_back();
+        self.publish_deferred_cets().await;
+    }
+}
+
+impl Actor {
+    fn broadcast_unless_held_back(&mut self, tx: Transaction, kind: TransactionKind) -> Result<()> {
+        if kind.depends_on_chain_view() && !self.broadcasts_allowed() {
+            tracing::warn!(txid = %tx.txid(), kind = %kind.name(), "Holding back broadcast until the chain views of wallet and monitor agree");
+
+            self.held_back_broadcasts
+                .push(TryBroadcastTransaction { tx, kind });
+            return Ok(());
+        }
+
+        self.broadcast(tx, kind)
+    }
+
+    async fn defer_cet(&mut self, tx: Transaction) {
+        let txid = tx.txid();
+
+        if self
+            .deferred_cets
+            .iter()
+            .any(|deferred| deferred.tx.txid() == txid)
+        {
+            return;
+        }
+
+        let counterparty_usually_publishes = match self
+            .db
+            .load_recent_cet_publications(cet_broadcast::RECENT_PUBLICATIONS)
+            .await
+        {
+            Ok(recent) => cet_broadcast::counterparty_usually_publishes(&recent),
+            Err(e) => {
+                tracing::warn!("Failed to load recent CET publications: {e:#}");
+                false
+            }
+        };
+
+        self.deferred_cets.push(DeferredCet {
+            tx,
+            deferred_at: Instant::now(),
+            counterparty_usually_publishes,
+            waited: false,
+        });
+    }
+
+    /// Publish the deferred CETs that are due, forget the ones the counterparty published.
+    async fn publish_deferred_cets(&mut self) {
+        for mut deferred in std::mem::take(&mut self.deferred_cets) {
+            let txid = deferred.tx.txid();
+
+            let published_by = if self.is_published(&deferred.tx) {
+                tracing::info!(%txid, "Counterparty published deferred CET");
+
+                PublishedBy::Counterparty
+            } else {
+                let blocks_until_refund = self.cet_blocks_until_refund(&deferred.tx);
+
+                if !self.cet_broadcast.should_publish(
+                    deferred.deferred_at.elapsed(),
+                    blocks_until_refund,
+                    deferred.counterparty_usually_publishes,
+                ) {
+                    deferred.waited = true;
+                    self.deferred_cets.push(deferred);
+                    continue;
+                }
+
+                if let Err(e) =
+                    self.broadcast_unless_held_back(deferred.tx.clone(), TransactionKind::Cet)
+                {
+                    tracing::warn!("{e:#}");
+
+                    // Retry upon the next sync
+                    self.deferred_cets.push(deferred);
+                    continue;
+                }
+
+                PublishedBy::Us
+            };
+
+            // If we did not wait, the counterparty had no chance to publish
+            if !deferred.waited {
+                continue;
+            }
+
+            if let Err(e) = self
+                .db
+                .insert_cet_publication(txid, published_by, Timestamp::now())
+                .await
+            {
+                tracing::warn!(%txid, "Failed to record publication of CET: {e:#}");
+            }
+        }
+    }
+
+    /// Whether the CET was seen in the mempool or on chain.
+    fn is_published(&self, cet: &Transaction) -> bool {
+        let script = match cet.output.first() {
+            Some(output) => &output.script_pubkey,
+            None => return false,
+        };
+
+        matches!(
+            self.state.status(cet.txid(), script),
+            Some(ScriptStatus::InMempool | ScriptStatus::Confirmed(_))
+        )
+    }
+
+    /// Number of blocks until the refund timelock of the commit transaction the CET spends
+    /// expires.
+    fn cet_blocks_until_refund(&self, cet: &Transaction) -> Option<u32> {
+        let commit_txid = cet.input.first()?.previous_output.txid;
+
+        let timelock = self
+            .refund_timelocks
+            .values()
+            .find(|timelock| timelock.commit_txid == commit_txid)?;
+
+        self.blocks_until_refund(timelock)
+    }
+
+    fn broadcast(&self, tx: Transaction, kind: TransactionKind) -> Result<()> {
+        let result = self.client.transaction_broadcast(&tx);
+
+        if let Err(electrum_client::Error::Protocol(ref value)) = result {
+            let rpc_error = parse_rpc_protocol_error(value)
+                .with_context(|| format!("Failed to parse electrum error response '{value:?}'"))?;
+
+            if rpc_error.code == i64::from(RpcErrorCode::RpcVerifyAlreadyInChain) {
+                let txid = tx.txid();
+                tracing::trace!(
+                    %txid, kind = %kind.name(), "Attempted to broadcast transaction that was already on-chain",
+                );
+
+                return Ok(());
+            }
+
+            // We do this check because electrum sometimes returns an RpcVerifyError when it should
+            // be returning a RpcVerifyAlreadyInChain error,
+            if rpc_error.code == i64::from(RpcErrorCode::RpcVerifyError)
+                && rpc_error.message == "bad-txns-inputs-missingorspent"
+            {
+                if let Ok(tx) = self.client.transaction_get(&tx.txid()) {
+                    let txid = tx.txid();
+                    tracing::trace!(
+                        %txid, kind = %kind.name(), "Attempted to broadcast transaction that was already on-chain",
+                    );
+                    return Ok(());
+                }
+            }
+        }
+        let txid = tx.txid();
+
+        result.with_context(|| {
+            let tx_hex = serialize_hex(&tx);
+
+            format!("Failed to broadcast transaction. Txid: {txid}. Kind: {}. Raw transaction: {tx_hex}", kind.name())
+        })?;
+
+        tracing::info!(%txid, kind = %kind.name(), "Transaction published on chain");
+
+        TRANSACTION_BROADCAST_COUNTER
+            .with(&HashMap::from([(KIND_LABEL, kind.name())]))
+            .inc();
+
+        Ok(())
+    }
+
+    fn broadcasts_allowed(&self) -> bool {
+        self.chain_consistency
+            .as_ref()
+            .map_or(true, |status| status.borrow().allows_broadcasts())
+    }
+
+    /// Broadcast the transactions held back while the chain views diverged, once they agree again.
+    fn broadcast_held_back(&mut self) {
+        if self.held_back_broadcasts.is_empty() || !self.broadcasts_allowed() {
+            return;
+        }
+
+        for TryBroadcastTransaction { tx, kind } in std::mem::take(&mut self.held_back_broadcasts) {
+            if let Err(e) = self.broadcast(tx, kind) {
+                tracing::warn!("{e:#}");
+            }
+        }
+    }
+
+    async fn report_chain_tip(&self) {
+        let chain_tip = self.chain_tip;
+
+        let seconds_since_last_sync =
+            Timestamp::now().seconds() - chain_tip.last_synced_at.seconds();
+        let estimated_blocks_behind = seconds_since_last_sync / BLOCK_INTERVAL_SECS;
+
+        CHAIN_TIP_HEIGHT_GAUGE.set(chain_tip.height.into());
+        SECONDS_SINCE_LAST_SYNC_GAUGE.set(seconds_since_last_sync);
+        ELECTRUM_HEALTHY_GAUGE.set(chain_tip.electrum_healthy.into());
+
+        if estimated_blocks_behind > MAX_BLOCKS_BEHIND {
+            tracing::warn!(
+                height = %chain_tip.height,
+                "Blockchain monitoring fell behind by ~{estimated_blocks_behind} blocks, last successful sync {seconds_since_last_sync}s ago"
+            );
+        }
+
+        if let Err(e) = self
+            .chain_tip_feed
+            .send_async_safe(projection::Update(chain_tip))
+            .await
+        {
+            tracing::warn!("Failed to report chain tip to projection: {e:#}");
         }
     }
 }
@@ -1048,3 +1530,30 @@ static SYNC_DURATION_HISTOGRAM: conquer_once::Lazy<prometheus::Histogram> =
         )
         .unwrap()
     });
+
+static CHAIN_TIP_HEIGHT_GAUGE: conquer_once::Lazy<prometheus::IntGauge> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_int_gauge!(
+            "monitor_chain_tip_height",
+            "The height of the latest block the monitor synced to."
+        )
+        .unwrap()
+    });
+
+static SECONDS_SINCE_LAST_SYNC_GAUGE: conquer_once::Lazy<prometheus::IntGauge> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_int_gauge!(
+            "monitor_seconds_since_last_sync",
+            "The number of seconds since the monitor last synced successfully."
+        )
+        .unwrap()
+    });
+
+static ELECTRUM_HEALTHY_GAUGE: conquer_once::Lazy<prometheus::IntGauge> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_int_gauge!(
+            "monitor_electrum_healthy",
+            "Whether the last sync attempt reached the Electrum backend (1) or not (0)."
+        )
+        .unwrap()
+    });
@@ -0,0 +1,623 @@
+use crate::online_status::ConnectionStatus;
+use crate::projection::Cfd;
+use crate::projection::CfdState;
+use crate::projection::TxLabel;
+use async_trait::async_trait;
+use bdk::bitcoin::Amount;
+use bdk::bitcoin::SignedAmount;
+use model::OrderId;
+use model::Timestamp;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use time::OffsetDateTime;
+use tokio::sync::watch;
+use xtra_productivity::xtra_productivity;
+
+pub mod email;
+
+/// Number of notifications kept around for clients that (re-)connect
+const MAX_RECENT_NOTIFICATIONS: usize = 100;
+
+/// Share of the margin that has to be lost before we emit a margin warning
+const MARGIN_WARNING_THRESHOLD: f64 = 0.8;
+
+/// Number of blocks before the refund timelock expires at which we warn about it (~3 days)
+const REFUND_WARNING_BLOCKS: u32 = 3 * 144;
+
+/// Number of blocks before the refund timelock expires at which we alert about it (~1 day)
+const REFUND_ALERT_BLOCKS: u32 = 144;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    OrderAccepted,
+    OrderRejected,
+    ContractSetupFailed,
+    RolloverCompleted,
+    SettlementConfirmed,
+    CommitPublished,
+    MarginWarning,
+    MakerOffline,
+    MakerOnline,
+    /// The wallet balance exceeds the target working balance of the operator
+    BalanceAboveTarget,
+    /// The wallet balance above the target working balance was withdrawn to the cold wallet
+    ExcessWithdrawn,
+    /// A committed CFD is getting close to its refund timelock, e.g. because the oracle did not
+    /// attest
+    RefundApproaching,
+}
+
+impl NotificationKind {
+    /// Whether the user has to be informed about the notification immediately, even if they are
+    /// not looking at the UI.
+    pub fn is_critical(&self) -> bool {
+        matches!(
+            self,
+            NotificationKind::CommitPublished
+                | NotificationKind::MarginWarning
+                | NotificationKind::BalanceAboveTarget
+                | NotificationKind::ExcessWithdrawn
+                | NotificationKind::RefundApproaching
+        )
+    }
+}
+
+/// A discrete, user-facing event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Notification {
+    /// Identifies the notification, it stays the same across reconnects of a client
+    pub id: String,
+    /// Strictly increasing sequence number
+    pub sequence: u64,
+    pub kind: NotificationKind,
+    pub severity: Severity,
+    pub order_id: Option<OrderId>,
+    pub message: String,
+    /// Link to the transaction the notification is about on the block explorer
+    pub tx_url: Option<String>,
+    pub timestamp: Timestamp,
+}
+
+/// Actor that derives user-facing notifications from the CFD feed and the maker's online status
+///
+/// The most recent notifications are published through a watch channel, each with a sequence
+/// number that allows consumers to only forward notifications they have not seen yet.
+pub struct Actor {
+    cfds: watch::Receiver<Option<Vec<Cfd>>>,
+    maker_status: watch::Receiver<ConnectionStatus>,
+    tracker: Tracker,
+    recent: VecDeque<Notification>,
+    sender: watch::Sender<Vec<Notification>>,
+}
+
+impl Actor {
+    pub fn new(
+        cfds: watch::Receiver<Option<Vec<Cfd>>>,
+        maker_status: watch::Receiver<ConnectionStatus>,
+    ) -> (Self, watch::Receiver<Vec<Notification>>) {
+        let (sender, receiver) = watch::channel(Vec::new());
+
+        let actor = Self {
+            cfds,
+            maker_status,
+            tracker: Tracker::default(),
+            recent: VecDeque::with_capacity(MAX_RECENT_NOTIFICATIONS),
+            sender,
+        };
+
+        (actor, receiver)
+    }
+
+    fn publish(&mut self, notifications: Vec<Notification>) {
+        if notifications.is_empty() {
+            return;
+        }
+
+        for notification in notifications {
+            tracing::debug!(id = %notification.id, "New notification");
+
+            if self.recent.len() == MAX_RECENT_NOTIFICATIONS {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(notification);
+        }
+
+        let _ = self.sender.send(self.recent.iter().cloned().collect());
+    }
+}
+
+struct CfdsChanged(Vec<CfdSnapshot>);
+
+struct MakerStatusChanged(ConnectionStatus);
+
+#[xtra_productivity]
+impl Actor {
+    fn handle(&mut self, msg: CfdsChanged) {
+        let notifications = self.tracker.on_cfds(msg.0, Timestamp::now());
+        self.publish(notifications);
+    }
+
+    fn handle(&mut self, msg: MakerStatusChanged) {
+        let notifications = self.tracker.on_maker_status(msg.0, Timestamp::now());
+        self.publish(notifications);
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we just started");
+
+        tokio_extras::spawn(&this.clone(), {
+            let mut cfds = self.cfds.clone();
+            let mut maker_status = self.maker_status.clone();
+
+            async move {
+                loop {
+                    let result = tokio::select! {
+                        Ok(()) = cfds.changed() => {
+                            let snapshots = cfds
+                                .borrow()
+                                .iter()
+                                .flatten()
+                                .map(CfdSnapshot::from)
+                                .collect();
+
+                            this.send(CfdsChanged(snapshots)).await
+                        }
+                        Ok(()) = maker_status.changed() => {
+                            let status = *maker_status.borrow();
+
+                            this.send(MakerStatusChanged(status)).await
+                        }
+                        else => return,
+                    };
+
+                    if result.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+/// The parts of a CFD that are relevant for notifications
+#[derive(Debug, Clone, PartialEq)]
+struct CfdSnapshot {
+    order_id: OrderId,
+    state: CfdState,
+    expiry_timestamp: Option<OffsetDateTime>,
+    profit_btc: Option<SignedAmount>,
+    margin: Amount,
+    /// Number of blocks until the refund timelock expires, if it is running
+    refund_blocks_remaining: Option<u32>,
+    commit_tx_url: Option<String>,
+    /// URL of the collaborative settlement transaction or the CET
+    settlement_tx_url: Option<String>,
+}
+
+impl From<&Cfd> for CfdSnapshot {
+    fn from(cfd: &Cfd) -> Self {
+        Self {
+            order_id: cfd.order_id,
+            state: cfd.state,
+            expiry_timestamp: cfd.expiry_timestamp,
+            profit_btc: cfd.profit_btc,
+            margin: cfd.margin,
+            refund_blocks_remaining: cfd
+                .refund_estimate
+                .map(|estimate| estimate.blocks_remaining),
+            commit_tx_url: cfd.details.tx_url(TxLabel::Commit).map(str::to_owned),
+            settlement_tx_url: cfd
+                .details
+                .tx_url(TxLabel::Collaborative)
+                .or_else(|| cfd.details.tx_url(TxLabel::Cet))
+                .map(str::to_owned),
+        }
+    }
+}
+
+impl CfdSnapshot {
+    fn share_of_margin_lost(&self) -> f64 {
+        let profit = match self.profit_btc {
+            Some(profit) => profit.as_sat(),
+            None => return 0.0,
+        };
+
+        if profit >= 0 || self.margin == Amount::ZERO {
+            return 0.0;
+        }
+
+        -profit as f64 / self.margin.as_sat() as f64
+    }
+
+    /// How urgent it is to tell the user about the approaching refund timelock, `None` if it is
+    /// not running or still far away.
+    fn refund_severity(&self) -> Option<Severity> {
+        match self.refund_blocks_remaining? {
+            blocks if blocks <= REFUND_ALERT_BLOCKS => Some(Severity::Error),
+            blocks if blocks <= REFUND_WARNING_BLOCKS => Some(Severity::Warning),
+            _ => None,
+        }
+    }
+}
+
+/// Derives notifications by comparing consecutive snapshots of the CFDs
+#[derive(Default)]
+struct Tracker {
+    cfds: HashMap<OrderId, TrackedCfd>,
+    maker_status: Option<ConnectionStatus>,
+    sequence: u64,
+}
+
+struct TrackedCfd {
+    snapshot: CfdSnapshot,
+    margin_warning_active: bool,
+    num_margin_warnings: u32,
+    /// Severity of the last refund notification, it only escalates
+    refund_severity: Option<Severity>,
+}
+
+impl Tracker {
+    fn on_cfds(&mut self, cfds: Vec<CfdSnapshot>, now: Timestamp) -> Vec<Notification> {
+        let mut notifications = Vec::new();
+
+        for cfd in cfds {
+            let tracked = match self.cfds.get_mut(&cfd.order_id) {
+                Some(tracked) => tracked,
+                None => {
+                    // We only notify about changes, not about CFDs we learn about for the first
+                    // time (e.g. upon startup).
+                    self.cfds.insert(
+                        cfd.order_id,
+                        TrackedCfd {
+                            margin_warning_active: cfd.share_of_margin_lost()
+                                >= MARGIN_WARNING_THRESHOLD,
+                            num_margin_warnings: 0,
+                            refund_severity: cfd.refund_severity(),
+                            snapshot: cfd,
+                        },
+                    );
+                    continue;
+                }
+            };
+
+            let previous = std::mem::replace(&mut tracked.snapshot, cfd.clone());
+
+            let order_id = cfd.order_id;
+
+            let transition = match (previous.state, cfd.state) {
+                (CfdState::PendingSetup, CfdState::ContractSetup | CfdState::PendingOpen) => {
+                    Some((
+                        NotificationKind::OrderAccepted,
+                        Severity::Success,
+                        format!("{order_id}"),
+                        "Your order was accepted by the maker".to_owned(),
+                        None,
+                    ))
+                }
+                (from, CfdState::Rejected) if from != CfdState::Rejected => Some((
+                    NotificationKind::OrderRejected,
+                    Severity::Error,
+                    format!("{order_id}"),
+                    "Your order was rejected by the maker".to_owned(),
+                    None,
+                )),
+                (from, CfdState::SetupFailed) if from != CfdState::SetupFailed => Some((
+                    NotificationKind::ContractSetupFailed,
+                    Severity::Error,
+                    format!("{order_id}"),
+                    "Contract setup failed".to_owned(),
+                    None,
+                )),
+                (CfdState::RolloverSetup, CfdState::Open)
+                    if previous.expiry_timestamp != cfd.expiry_timestamp =>
+                {
+                    let expiry = cfd
+                        .expiry_timestamp
+                        .map(|expiry| expiry.unix_timestamp())
+                        .unwrap_or_default();
+
+                    Some((
+                        NotificationKind::RolloverCompleted,
+                        Severity::Info,
+                        format!("{order_id}-{expiry}"),
+                        "Your position was rolled over".to_owned(),
+                        None,
+                    ))
+                }
+                (from, CfdState::PendingCommit) if from != CfdState::PendingCommit => Some((
+                    NotificationKind::CommitPublished,
+                    Severity::Error,
+                    format!("{order_id}"),
+                    "The commit transaction of your position was published".to_owned(),
+                    cfd.commit_tx_url.clone(),
+                )),
+                (from, CfdState::Closed) if from != CfdState::Closed => Some((
+                    NotificationKind::SettlementConfirmed,
+                    Severity::Success,
+                    format!("{order_id}"),
+                    "Your position was settled".to_owned(),
+                    cfd.settlement_tx_url.clone(),
+                )),
+                _ => None,
+            };
+
+            if let Some((kind, severity, discriminator, message, tx_url)) = transition {
+                self.sequence += 1;
+                notifications.push(Notification {
+                    id: format!("{}-{discriminator}", kind_name(kind)),
+                    sequence: self.sequence,
+                    kind,
+                    severity,
+                    order_id: Some(order_id),
+                    message,
+                    tx_url,
+                    timestamp: now,
+                });
+            }
+
+            let margin_lost = cfd.share_of_margin_lost();
+            let is_open = cfd.state == CfdState::Open;
+
+            if is_open && margin_lost >= MARGIN_WARNING_THRESHOLD && !tracked.margin_warning_active
+            {
+                tracked.margin_warning_active = true;
+                tracked.num_margin_warnings += 1;
+
+                let percent = (margin_lost * 100.0).round();
+                let num_warnings = tracked.num_margin_warnings;
+
+                self.sequence += 1;
+                notifications.push(Notification {
+                    id: format!(
+                        "{}-{order_id}-{num_warnings}",
+                        kind_name(NotificationKind::MarginWarning)
+                    ),
+                    sequence: self.sequence,
+                    kind: NotificationKind::MarginWarning,
+                    severity: Severity::Warning,
+                    order_id: Some(order_id),
+                    message: format!("Your position lost {percent}% of its margin"),
+                    tx_url: None,
+                    timestamp: now,
+                });
+            } else if margin_lost < MARGIN_WARNING_THRESHOLD {
+                tracked.margin_warning_active = false;
+            }
+
+            let refund_severity = cfd.refund_severity();
+            let escalated = match (tracked.refund_severity, refund_severity) {
+                (_, None) => false,
+                (None, Some(_)) => true,
+                (Some(previous), Some(current)) => {
+                    previous == Severity::Warning && current == Severity::Error
+                }
+            };
+
+            if let (true, Some(severity), Some(blocks)) =
+                (escalated, refund_severity, cfd.refund_blocks_remaining)
+            {
+                tracked.refund_severity = Some(severity);
+
+                let hours = blocks / 6;
+                let level = match severity {
+                    Severity::Error => "alert",
+                    _ => "warning",
+                };
+
+                self.sequence += 1;
+                notifications.push(Notification {
+                    id: format!(
+                        "{}-{order_id}-{level}",
+                        kind_name(NotificationKind::RefundApproaching)
+                    ),
+                    sequence: self.sequence,
+                    kind: NotificationKind::RefundApproaching,
+                    severity,
+                    order_id: Some(order_id),
+                    message: format!("The refund transaction of your position can be published in about {hours} hours ({blocks} blocks)"),
+                    tx_url: None,
+                    timestamp: now,
+                });
+            }
+        }
+
+        notifications
+    }
+
+    fn on_maker_status(&mut self, status: ConnectionStatus, now: Timestamp) -> Vec<Notification> {
+        let previous = self.maker_status.replace(status);
+
+        let (kind, severity, message) = match (previous, status) {
+            (Some(ConnectionStatus::Online), ConnectionStatus::Offline) => (
+                NotificationKind::MakerOffline,
+                Severity::Warning,
+                "Lost connection to the maker",
+            ),
+            (Some(ConnectionStatus::Offline), ConnectionStatus::Online) => (
+                NotificationKind::MakerOnline,
+                Severity::Info,
+                "Connection to the maker re-established",
+            ),
+            _ => return Vec::new(),
+        };
+
+        self.sequence += 1;
+
+        vec![Notification {
+            id: format!("{}-{}", kind_name(kind), now.seconds()),
+            sequence: self.sequence,
+            kind,
+            severity,
+            order_id: None,
+            message: message.to_owned(),
+            tx_url: None,
+            timestamp: now,
+        }]
+    }
+}
+
+/// Name of the kind of notification, notification ids start with it.
+pub fn kind_name(kind: NotificationKind) -> &'static str {
+    match kind {
+        NotificationKind::OrderAccepted => "order-accepted",
+        NotificationKind::OrderRejected => "order-rejected",
+        NotificationKind::ContractSetupFailed => "contract-setup-failed",
+        NotificationKind::RolloverCompleted => "rollover-completed",
+        NotificationKind::SettlementConfirmed => "settlement-confirmed",
+        NotificationKind::CommitPublished => "commit-published",
+        NotificationKind::MarginWarning => "margin-warning",
+        NotificationKind::MakerOffline => "maker-offline",
+        NotificationKind::MakerOnline => "maker-online",
+        NotificationKind::BalanceAboveTarget => "balance-above-target",
+        NotificationKind::ExcessWithdrawn => "excess-withdrawn",
+        NotificationKind::RefundApproaching => "refund-approaching",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(state: CfdState) -> CfdSnapshot {
+        CfdSnapshot {
+            order_id: OrderId::default(),
+            state,
+            expiry_timestamp: None,
+            profit_btc: None,
+            margin: Amount::from_sat(100_000),
+            refund_blocks_remaining: None,
+            commit_tx_url: None,
+            settlement_tx_url: None,
+        }
+    }
+
+    #[test]
+    fn first_snapshot_does_not_notify() {
+        let mut tracker = Tracker::default();
+
+        let notifications = tracker.on_cfds(vec![snapshot(CfdState::Open)], Timestamp::now());
+
+        assert!(notifications.is_empty());
+    }
+
+    #[test]
+    fn accepted_order_notifies_once() {
+        let mut tracker = Tracker::default();
+        let now = Timestamp::now();
+
+        tracker.on_cfds(vec![snapshot(CfdState::PendingSetup)], now);
+        let notifications = tracker.on_cfds(vec![snapshot(CfdState::ContractSetup)], now);
+        let repeated = tracker.on_cfds(vec![snapshot(CfdState::ContractSetup)], now);
+
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].kind, NotificationKind::OrderAccepted);
+        assert_eq!(notifications[0].sequence, 1);
+        assert!(repeated.is_empty());
+    }
+
+    #[test]
+    fn margin_warning_is_emitted_when_crossing_threshold() {
+        let mut tracker = Tracker::default();
+        let now = Timestamp::now();
+
+        let healthy = snapshot(CfdState::Open);
+        let losing = CfdSnapshot {
+            profit_btc: Some(SignedAmount::from_sat(-90_000)),
+            ..healthy.clone()
+        };
+
+        tracker.on_cfds(vec![healthy.clone()], now);
+        let first = tracker.on_cfds(vec![losing.clone()], now);
+        let still_losing = tracker.on_cfds(vec![losing.clone()], now);
+        tracker.on_cfds(vec![healthy], now);
+        let second = tracker.on_cfds(vec![losing], now);
+
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].kind, NotificationKind::MarginWarning);
+        assert!(still_losing.is_empty());
+        assert_eq!(second.len(), 1);
+        assert_ne!(first[0].id, second[0].id);
+    }
+
+    #[test]
+    fn published_commit_is_critical() {
+        let mut tracker = Tracker::default();
+        let now = Timestamp::now();
+
+        let committed = CfdSnapshot {
+            commit_tx_url: Some("https://mempool.space/tx/commit".to_owned()),
+            ..snapshot(CfdState::PendingCommit)
+        };
+
+        tracker.on_cfds(vec![snapshot(CfdState::Open)], now);
+        let notifications = tracker.on_cfds(vec![committed], now);
+
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].kind, NotificationKind::CommitPublished);
+        assert!(notifications[0].kind.is_critical());
+        assert_eq!(
+            notifications[0].tx_url.as_deref(),
+            Some("https://mempool.space/tx/commit")
+        );
+    }
+
+    #[test]
+    fn approaching_refund_escalates_once_per_severity() {
+        let mut tracker = Tracker::default();
+        let now = Timestamp::now();
+
+        let committed = |refund_blocks_remaining| CfdSnapshot {
+            refund_blocks_remaining,
+            ..snapshot(CfdState::OpenCommitted)
+        };
+
+        tracker.on_cfds(vec![committed(None)], now);
+        let far = tracker.on_cfds(vec![committed(Some(REFUND_WARNING_BLOCKS + 1))], now);
+        let warning = tracker.on_cfds(vec![committed(Some(REFUND_WARNING_BLOCKS))], now);
+        let repeated = tracker.on_cfds(vec![committed(Some(REFUND_ALERT_BLOCKS + 1))], now);
+        let alert = tracker.on_cfds(vec![committed(Some(REFUND_ALERT_BLOCKS))], now);
+        let eligible = tracker.on_cfds(vec![committed(Some(0))], now);
+
+        assert!(far.is_empty());
+        assert_eq!(warning.len(), 1);
+        assert_eq!(warning[0].kind, NotificationKind::RefundApproaching);
+        assert_eq!(warning[0].severity, Severity::Warning);
+        assert!(repeated.is_empty());
+        assert_eq!(alert.len(), 1);
+        assert_eq!(alert[0].severity, Severity::Error);
+        assert_ne!(warning[0].id, alert[0].id);
+        assert!(eligible.is_empty());
+    }
+
+    #[test]
+    fn maker_going_offline_notifies() {
+        let mut tracker = Tracker::default();
+        let now = Timestamp::now();
+
+        let initial = tracker.on_maker_status(ConnectionStatus::Online, now);
+        let offline = tracker.on_maker_status(ConnectionStatus::Offline, now);
+
+        assert!(initial.is_empty());
+        assert_eq!(offline.len(), 1);
+        assert_eq!(offline[0].kind, NotificationKind::MakerOffline);
+        assert_eq!(offline[0].severity, Severity::Warning);
+    }
+}
@@ -0,0 +1,509 @@
+//! Delivery of notifications by email.
+//!
+//! If a `notifications.toml` exists in the data directory, a daily digest with the open
+//! positions, accrued fees, pending actions and the wallet balance is sent at the configured hour
+//! of the UTC day. Critical notifications, e.g. a published commit transaction or a margin warning,
+//! are sent immediately. Subjects and bodies are rendered from templates which can be overridden
+//! in the configuration file.
+
+use crate::notifications::Notification;
+use crate::projection::rounding;
+use crate::projection::Cfd;
+use crate::projection::CfdState;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use bdk::bitcoin::Amount;
+use bdk::bitcoin::SignedAmount;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::AsyncSmtpTransport;
+use lettre::AsyncTransport;
+use lettre::Message;
+use lettre::Tokio1Executor;
+use model::WalletInfo;
+use serde::Deserialize;
+use std::path::Path;
+use std::time::Duration;
+use time::Date;
+use time::OffsetDateTime;
+use tokio::sync::watch;
+use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+/// Name of the configuration file in the data directory
+pub const CONFIG_FILE: &str = "notifications.toml";
+
+/// Interval at which we check whether the digest is due
+const DIGEST_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub smtp: SmtpConfig,
+    /// Hour of the UTC day at which the daily digest is sent
+    #[serde(default = "default_digest_hour")]
+    pub digest_hour: u8,
+    #[serde(default)]
+    pub templates: Templates,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SmtpConfig {
+    pub host: String,
+    /// Defaults to the port of the `security` mode
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub security: Security,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Security {
+    /// Upgrade the connection with `STARTTLS`, port 587 by default
+    #[default]
+    Starttls,
+    /// Connect through TLS, port 465 by default
+    Tls,
+    /// Send unencrypted, only meant for a mail server on the same host
+    None,
+}
+
+/// Templates of the emails, `{{name}}` is replaced with the value of `name`.
+///
+/// The digest templates can use `open_positions`, `fees_accrued`, `pending_actions` and
+/// `wallet_balance`, the templates of critical notifications can use `kind`, `order_id`, `message`,
+/// `tx_url` and `timestamp`.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Templates {
+    pub digest_subject: String,
+    pub digest_body: String,
+    pub critical_subject: String,
+    pub critical_body: String,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self {
+            digest_subject: "ItchySats daily digest".to_owned(),
+            digest_body: "Open positions: {{open_positions}}\n\
+                          Fees accrued: {{fees_accrued}}\n\
+                          Positions with pending actions: {{pending_actions}}\n\
+                          Wallet balance: {{wallet_balance}}\n"
+                .to_owned(),
+            critical_subject: "ItchySats: {{message}}".to_owned(),
+            critical_body: "{{message}}\n\nOrder: {{order_id}}\nTime: {{timestamp}}\n".to_owned(),
+        }
+    }
+}
+
+fn default_digest_hour() -> u8 {
+    8
+}
+
+impl Config {
+    /// Load the configuration from the data directory, if it exists.
+    pub async fn load(data_dir: &Path) -> Result<Option<Self>> {
+        let path = data_dir.join(CONFIG_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = tokio::fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let config = Self::from_toml(&content)
+            .with_context(|| format!("Invalid configuration in {}", path.display()))?;
+
+        Ok(Some(config))
+    }
+
+    pub fn from_toml(s: &str) -> Result<Self> {
+        let config = toml::from_str::<Self>(s)?;
+
+        anyhow::ensure!(
+            config.digest_hour < 24,
+            "Digest hour must be below 24, got {}",
+            config.digest_hour
+        );
+        anyhow::ensure!(
+            !config.smtp.to.is_empty(),
+            "At least one recipient is required"
+        );
+
+        Ok(config)
+    }
+}
+
+/// The figures reported in the daily digest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Digest {
+    open_positions: usize,
+    fees_accrued: SignedAmount,
+    pending_actions: usize,
+    wallet_balance: Option<Amount>,
+}
+
+impl Digest {
+    fn new(cfds: &[Cfd], wallet: Option<&WalletInfo>) -> Self {
+        let open = cfds
+            .iter()
+            .filter(|cfd| is_open(cfd.state))
+            .collect::<Vec<_>>();
+
+        Self {
+            open_positions: open.len(),
+            fees_accrued: open
+                .iter()
+                .map(|cfd| cfd.accumulated_fees)
+                .fold(SignedAmount::ZERO, |sum, fees| sum + fees),
+            pending_actions: cfds.iter().filter(|cfd| is_pending(cfd.state)).count(),
+            wallet_balance: wallet.map(|wallet| wallet.balance),
+        }
+    }
+
+    fn render(&self, templates: &Templates) -> (String, String) {
+        let values = [
+            ("open_positions", self.open_positions.to_string()),
+            ("fees_accrued", rounding::format_fee(self.fees_accrued)),
+            ("pending_actions", self.pending_actions.to_string()),
+            (
+                "wallet_balance",
+                self.wallet_balance
+                    .map(|balance| balance.to_string())
+                    .unwrap_or_else(|| "unknown".to_owned()),
+            ),
+        ];
+
+        (
+            render(&templates.digest_subject, &values),
+            render(&templates.digest_body, &values),
+        )
+    }
+}
+
+fn render_critical(notification: &Notification, templates: &Templates) -> (String, String) {
+    let values = [
+        ("kind", format!("{:?}", notification.kind)),
+        (
+            "order_id",
+            notification
+                .order_id
+                .map(|order_id| order_id.to_string())
+                .unwrap_or_default(),
+        ),
+        ("message", notification.message.clone()),
+        ("tx_url", notification.tx_url.clone().unwrap_or_default()),
+        ("timestamp", notification.timestamp.seconds().to_string()),
+    ];
+
+    (
+        render(&templates.critical_subject, &values),
+        render(&templates.critical_body, &values),
+    )
+}
+
+/// Replace every `{{name}}` in `template` with the value of `name`.
+///
+/// Placeholders without value are kept as they are.
+fn render(template: &str, values: &[(&str, String)]) -> String {
+    values
+        .iter()
+        .fold(template.to_owned(), |rendered, (name, value)| {
+            rendered.replace(&format!("{{{{{name}}}}}"), value)
+        })
+}
+
+fn is_open(state: CfdState) -> bool {
+    matches!(
+        state,
+        CfdState::Open
+            | CfdState::OpenCommitted
+            | CfdState::IncomingSettlementProposal
+            | CfdState::OutgoingSettlementProposal
+            | CfdState::RolloverSetup
+            | CfdState::PendingCommit
+            | CfdState::PendingCet
+            | CfdState::PendingClose
+            | CfdState::PendingRefund
+    )
+}
+
+/// Whether the CFD is in the middle of a protocol or waiting for a transaction to confirm
+fn is_pending(state: CfdState) -> bool {
+    matches!(
+        state,
+        CfdState::PendingSetup
+            | CfdState::ContractSetup
+            | CfdState::PendingOpen
+            | CfdState::IncomingSettlementProposal
+            | CfdState::OutgoingSettlementProposal
+            | CfdState::RolloverSetup
+            | CfdState::PendingCommit
+            | CfdState::PendingCet
+            | CfdState::PendingClose
+            | CfdState::PendingRefund
+    )
+}
+
+/// Actor that sends the daily digest and critical notifications by email
+pub struct Actor {
+    config: Config,
+    mailer: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    cfds: watch::Receiver<Option<Vec<Cfd>>>,
+    wallet: watch::Receiver<Option<WalletInfo>>,
+    notifications: watch::Receiver<Vec<Notification>>,
+    /// Sequence number of the latest notification we have seen
+    last_sequence: u64,
+    /// The UTC day the digest was last sent on
+    last_digest: Option<Date>,
+}
+
+impl Actor {
+    pub fn new(
+        config: Config,
+        cfds: watch::Receiver<Option<Vec<Cfd>>>,
+        wallet: watch::Receiver<Option<WalletInfo>>,
+        notifications: watch::Receiver<Vec<Notification>>,
+    ) -> Result<Self> {
+        let smtp = &config.smtp;
+
+        let mut builder = match smtp.security {
+            Security::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&smtp.host)?,
+            Security::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&smtp.host)?,
+            Security::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp.host),
+        };
+        if let Some(port) = smtp.port {
+            builder = builder.port(port);
+        }
+        if let (Some(username), Some(password)) = (&smtp.username, &smtp.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        let from = smtp
+            .from
+            .parse()
+            .with_context(|| format!("Invalid sender address {}", smtp.from))?;
+        let to = smtp
+            .to
+            .iter()
+            .map(|to| {
+                to.parse()
+                    .with_context(|| format!("Invalid recipient address {to}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Only notifications that arrive after startup are sent
+        let last_sequence = notifications
+            .borrow()
+            .iter()
+            .map(|notification| notification.sequence)
+            .max()
+            .unwrap_or_default();
+
+        // Don't send another digest if we are restarted after the digest hour
+        let now = OffsetDateTime::now_utc();
+        let last_digest = (now.hour() >= config.digest_hour).then(|| now.date());
+
+        Ok(Self {
+            mailer: builder.build(),
+            config,
+            from,
+            to,
+            cfds,
+            wallet,
+            notifications,
+            last_sequence,
+            last_digest,
+        })
+    }
+
+    async fn send(&self, subject: String, body: String) -> Result<()> {
+        let mut message = Message::builder().from(self.from.clone()).subject(subject);
+        for to in self.to.iter() {
+            message = message.to(to.clone());
+        }
+
+        self.mailer
+            .send(message.body(body)?)
+            .await
+            .context("Failed to send email")?;
+
+        Ok(())
+    }
+}
+
+/// Send the digest if it is due.
+#[derive(Clone, Copy)]
+struct CheckDigest;
+
+struct NotificationsChanged(Vec<Notification>);
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: CheckDigest) {
+        let now = OffsetDateTime::now_utc();
+        if now.hour() < self.config.digest_hour || self.last_digest == Some(now.date()) {
+            return;
+        }
+
+        let digest = {
+            let cfds = self.cfds.borrow();
+            let wallet = self.wallet.borrow();
+
+            Digest::new(cfds.as_deref().unwrap_or_default(), wallet.as_ref())
+        };
+        let (subject, body) = digest.render(&self.config.templates);
+
+        match self.send(subject, body).await {
+            Ok(()) => {
+                tracing::info!("Sent daily digest by email");
+                self.last_digest = Some(now.date());
+            }
+            Err(e) => tracing::warn!("Failed to send daily digest: {e:#}"),
+        }
+    }
+
+    async fn handle(&mut self, msg: NotificationsChanged) {
+        let new = msg
+            .0
+            .into_iter()
+            .filter(|notification| notification.sequence > self.last_sequence)
+            .collect::<Vec<_>>();
+
+        for notification in new {
+            self.last_sequence = notification.sequence;
+
+            if !notification.kind.is_critical() {
+                continue;
+            }
+
+            let (subject, body) = render_critical(&notification, &self.config.templates);
+            if let Err(e) = self.send(subject, body).await {
+                tracing::warn!(id = %notification.id, "Failed to send notification by email: {e:#}");
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we just started");
+
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(
+                DIGEST_CHECK_INTERVAL,
+                || CheckDigest,
+                xtras::IncludeSpan::Never,
+            ),
+        );
+
+        tokio_extras::spawn(&this.clone(), {
+            let mut notifications = self.notifications.clone();
+
+            async move {
+                while notifications.changed().await.is_ok() {
+                    let notifications = notifications.borrow().clone();
+
+                    if this
+                        .send(NotificationsChanged(notifications))
+                        .await
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+            }
+        });
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_are_replaced() {
+        let rendered = render(
+            "{{open_positions}} open, {{unknown}} kept",
+            &[("open_positions", "2".to_owned())],
+        );
+
+        assert_eq!(rendered, "2 open, {{unknown}} kept");
+    }
+
+    #[test]
+    fn given_partial_templates_then_defaults_for_the_rest() {
+        let config = Config::from_toml(
+            r#"
+            digest_hour = 18
+
+            [smtp]
+            host = "smtp.example.com"
+            from = "taker@example.com"
+            to = ["me@example.com"]
+
+            [templates]
+            digest_subject = "Positions: {{open_positions}}"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.digest_hour, 18);
+        assert_eq!(config.smtp.security, Security::Starttls);
+        assert_eq!(
+            config.templates.digest_subject,
+            "Positions: {{open_positions}}"
+        );
+        assert_eq!(
+            config.templates.critical_body,
+            Templates::default().critical_body
+        );
+    }
+
+    #[test]
+    fn given_no_recipient_then_config_rejected() {
+        let result = Config::from_toml(
+            r#"
+            [smtp]
+            host = "smtp.example.com"
+            from = "taker@example.com"
+            to = []
+            "#,
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn digest_renders_all_figures() {
+        let digest = Digest {
+            open_positions: 2,
+            fees_accrued: SignedAmount::from_sat(-1_500),
+            pending_actions: 1,
+            wallet_balance: None,
+        };
+
+        let (subject, body) = digest.render(&Templates::default());
+
+        assert_eq!(subject, "ItchySats daily digest");
+        assert!(body.contains("Open positions: 2"));
+        assert!(body.contains("Fees accrued: -0.00001500 BTC"));
+        assert!(body.contains("Positions with pending actions: 1"));
+        assert!(body.contains("Wallet balance: unknown"));
+    }
+}
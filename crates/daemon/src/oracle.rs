@@ -1,4 +1,5 @@
 use crate::command;
+use crate::janitor;
 use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
@@ -305,6 +306,28 @@ impl Actor {
         Ok(announcements)
     }
 
+    /// Announcements of past events are only needed by the CFDs whose attestation is pending.
+    fn handle_collect_orphans(&mut self, msg: janitor::CollectOrphans) -> usize {
+        let now = OffsetDateTime::now_utc();
+
+        let orphaned = self
+            .announcements
+            .iter()
+            .filter(|(id, (expected_outcome_time, _))| {
+                *expected_outcome_time < now && !self.pending_attestations.contains(*id)
+            })
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+
+        if !msg.dry_run {
+            for id in orphaned.iter() {
+                self.announcements.remove(id);
+            }
+        }
+
+        orphaned.len()
+    }
+
     fn handle_new_announcement_fetched(&mut self, msg: NewAnnouncementFetched) {
         self.announcements
             .insert(msg.id, (msg.expected_outcome_time, msg.nonce_pks));
@@ -1,4 +1,8 @@
 mod current;
 pub mod deprecated;
+mod exposure;
+pub mod pending_timeout;
+mod reservation;
+pub mod setup_queue;
 
 pub use current::*;
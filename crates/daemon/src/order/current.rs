@@ -3,4 +3,13 @@ pub mod maker;
 mod protocol;
 pub mod taker;
 
-pub const PROTOCOL: &str = "/itchysats/order/2.0.0";
+pub const PROTOCOL: &str = "/itchysats/order/3.0.0";
+
+/// Version of [`PROTOCOL`] without sequence numbers, served until all takers upgraded
+pub const UNSEQUENCED_PROTOCOL: &str = "/itchysats/order/2.0.0";
+
+/// Maximum size of an encoded message in bytes
+///
+/// Contract setup messages carry the CETs of all payouts, which makes them by far the largest
+/// messages of all protocols.
+pub const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
@@ -28,14 +28,15 @@ use maia_core::PartyParams;
 use maia_core::PunishParams;
 use model::olivia;
 use model::olivia::BitMexPriceEventId;
-use model::payout_curve::ETHUSD_MULTIPLIER;
 use model::shared_protocol::verify_adaptor_signature;
 use model::shared_protocol::verify_cets;
+use model::shared_protocol::verify_lock_inputs;
 use model::shared_protocol::verify_signature;
+use model::symbols::PayoutCurve;
 use model::Cet;
-use model::ContractSymbol;
 use model::Dlc;
 use model::OraclePayouts;
+use model::OrderId;
 use model::Payouts;
 use model::Position;
 use model::Role;
@@ -52,13 +53,6 @@ use xtra::prelude::MessageChannel;
 
 use super::protocol::SetupMsg;
 
-/// How long contract setup protocol waits for the next message before giving up
-///
-/// 120s are currently needed to ensure that we can outlive times when the maker/taker are under
-/// heavy message load. Failed contract setups are annoying compared to failed rollovers so we allow
-/// more time to see them less often.
-const CONTRACT_SETUP_MSG_TIMEOUT: Duration = Duration::from_secs(120);
-
 /// Given an initial set of parameters, sets up the CFD contract with
 /// the counterparty.
 #[allow(clippy::too_many_arguments)]
@@ -73,17 +67,25 @@ pub async fn new(
     mut stream: impl Stream<Item = SetupMsg> + Unpin,
     (oracle_pk, announcements): (XOnlyPublicKey, Vec<olivia::Announcement>),
     setup_params: SetupParams,
+    order_id: OrderId,
     build_party_params_channel: MessageChannel<wallet::BuildPartyParams, Result<PartyParams>>,
     sign_channel: MessageChannel<wallet::Sign, Result<PartiallySignedTransaction>>,
     own_role: Role,
     position: Position,
     n_payouts: usize,
+    msg_timeout: Duration,
 ) -> Result<Dlc> {
-    tracing::debug!(?setup_params, ?own_role, ?position, ?n_payouts);
+    tracing::debug!(
+        ?setup_params,
+        ?own_role,
+        ?position,
+        ?n_payouts,
+        ?msg_timeout
+    );
     tracing::trace!(?oracle_pk, ?announcements);
 
     let (own, own_punish, key_pairs) =
-        own_setup_params(build_party_params_channel, setup_params).await?;
+        own_setup_params(build_party_params_channel, setup_params, order_id).await?;
 
     sink.send(SetupMsg::Msg0(Msg0::from((own.clone(), own_punish))))
         .instrument(tracing::debug_span!("Send Msg0"))
@@ -91,18 +93,14 @@ pub async fn new(
         .context("Failed to send Msg0")?;
     let msg0 = stream
         .next()
-        .timeout(CONTRACT_SETUP_MSG_TIMEOUT, stream_next_span)
+        .timeout(msg_timeout, stream_next_span)
         .await
-        .with_context(|| {
-            format!(
-                "Expected Msg0 within {} seconds",
-                CONTRACT_SETUP_MSG_TIMEOUT.as_secs()
-            )
-        })?
+        .with_context(|| format!("Expected Msg0 within {} seconds", msg_timeout.as_secs()))?
         .context("Empty stream instead of Msg0")?
         .try_into_msg0()?;
 
     let (counterparty, counterparty_punish) = msg0.into();
+    verify_lock_inputs(&counterparty).context("Counterparty cannot fund its margin")?;
 
     let params = AllParams {
         own,
@@ -130,14 +128,9 @@ pub async fn new(
 
     let msg1 = stream
         .next()
-        .timeout(CONTRACT_SETUP_MSG_TIMEOUT, stream_next_span)
+        .timeout(msg_timeout, stream_next_span)
         .await
-        .with_context(|| {
-            format!(
-                "Expected Msg1 within {} seconds",
-                CONTRACT_SETUP_MSG_TIMEOUT.as_secs()
-            )
-        })?
+        .with_context(|| format!("Expected Msg1 within {} seconds", msg_timeout.as_secs()))?
         .context("Empty stream instead of Msg1")?
         .try_into_msg1()?;
 
@@ -169,14 +162,9 @@ pub async fn new(
 
     let msg2 = stream
         .next()
-        .timeout(CONTRACT_SETUP_MSG_TIMEOUT, stream_next_span)
+        .timeout(msg_timeout, stream_next_span)
         .await
-        .with_context(|| {
-            format!(
-                "Expected Msg2 within {} seconds",
-                CONTRACT_SETUP_MSG_TIMEOUT.as_secs()
-            )
-        })?
+        .with_context(|| format!("Expected Msg2 within {} seconds", msg_timeout.as_secs()))?
         .context("Empty stream instead of Msg2")?
         .try_into_msg2()?;
 
@@ -203,14 +191,9 @@ pub async fn new(
         .context("Failed to send Msg3")?;
     let _ = stream
         .next()
-        .timeout(CONTRACT_SETUP_MSG_TIMEOUT, stream_next_span)
+        .timeout(msg_timeout, stream_next_span)
         .await
-        .with_context(|| {
-            format!(
-                "Expected Msg3 within {} seconds",
-                CONTRACT_SETUP_MSG_TIMEOUT.as_secs()
-            )
-        })?
+        .with_context(|| format!("Expected Msg3 within {} seconds", msg_timeout.as_secs()))?
         .context("Empty stream instead of Msg3")?
         .try_into_msg3()?;
 
@@ -262,6 +245,7 @@ struct KeyPairs {
 async fn own_setup_params(
     build_party_params_channel: MessageChannel<wallet::BuildPartyParams, Result<PartyParams>>,
     setup_params: SetupParams,
+    order_id: OrderId,
 ) -> Result<(PartyParams, PunishParams, KeyPairs)> {
     let key_pairs = KeyPairs {
         identity: keypair::new(&mut rand::thread_rng()).into(),
@@ -274,6 +258,7 @@ async fn own_setup_params(
             amount: setup_params.margin,
             identity_pk: key_pairs.identity.public,
             fee_rate: setup_params.tx_fee_rate,
+            order_id,
         })
         .instrument(tracing::debug_span!(
             "Send BuildPartyParams to wallet actor"
@@ -311,8 +296,8 @@ async fn create_cfd_transactions(
 
     let settlement_event_id = announcements.last().context("Empty announcements")?.id;
 
-    let payouts = match setup_params.contract_symbol {
-        ContractSymbol::BtcUsd => Payouts::new_inverse_olivia_max(
+    let payouts = match setup_params.payout_curve {
+        PayoutCurve::Inverse => Payouts::new_inverse_olivia_max(
             (position, role),
             setup_params.price,
             setup_params.quantity,
@@ -320,13 +305,13 @@ async fn create_cfd_transactions(
             n_payouts,
             setup_params.fee_account.settle(),
         )?,
-        ContractSymbol::EthUsd => Payouts::new_quanto(
+        PayoutCurve::Quanto { multiplier } => Payouts::new_quanto(
             (position, role),
             setup_params.price.to_u64(),
             setup_params.quantity.to_u64(),
             (setup_params.long_leverage, setup_params.short_leverage),
             n_payouts,
-            ETHUSD_MULTIPLIER,
+            multiplier,
             setup_params.fee_account.settle(),
         )?,
     };
@@ -1,21 +1,33 @@
 use crate::command;
+use crate::janitor;
 use crate::oracle;
 use crate::oracle::NoAnnouncement;
 use crate::order::current::contract_setup;
 use crate::order::current::protocol;
 use crate::order::current::protocol::MakerMessage;
+use crate::order::current::protocol::RejectReason;
 use crate::order::current::protocol::SetupMsg;
 use crate::order::current::protocol::TakerMessage;
+use crate::order::current::MAX_FRAME_SIZE;
+use crate::order::current::PROTOCOL;
+use crate::order::exposure;
+use crate::order::pending_timeout::PendingOrderTimeouts;
+use crate::order::reservation::Claim;
+use crate::order::reservation::ReservationId;
+use crate::order::reservation::Reservations;
+use crate::order::reservation::RESERVATION_TTL;
+use crate::order::setup_queue::SetupQueue;
 use crate::process_manager;
 use crate::projection;
 use crate::wallet;
 use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::ensure;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
-use asynchronous_codec::Framed;
-use asynchronous_codec::JsonCodec;
 use bdk::bitcoin::psbt::PartiallySignedTransaction;
+use bdk::bitcoin::Amount;
 use bdk::bitcoin::XOnlyPublicKey;
 use futures::channel::oneshot;
 use futures::future;
@@ -23,17 +35,27 @@ use futures::SinkExt;
 use futures::StreamExt;
 use maia_core::PartyParams;
 use model::olivia;
+use model::protocol_timeout::TimeoutPolicy;
 use model::Cfd;
+use model::Contracts;
 use model::Identity;
+use model::Leverage;
 use model::OfferId;
+use model::OpeningFee;
 use model::OrderId;
 use model::Role;
+use model::Timestamp;
+use rust_decimal::Decimal;
 use std::collections::HashMap;
 use std::fmt;
 use std::time::Duration;
+use time::OffsetDateTime;
 use tokio_extras::FutureExt;
 use tracing::instrument;
 use xtra::prelude::MessageChannel;
+use xtra_libp2p::libp2p::PeerId;
+use xtra_libp2p::sequenced::Session;
+use xtra_libp2p::sequenced::Sessions;
 use xtra_libp2p::NewInboundSubstream;
 use xtra_libp2p::Substream;
 use xtra_productivity::xtra_productivity;
@@ -41,6 +63,11 @@ use xtras::SendAsyncSafe;
 
 const ORDER_TIMEOUT: Duration = Duration::from_secs(5);
 
+/// Interval at which queued takers are informed about their position in the setup queue
+///
+/// Has to be shorter than the time the taker waits for the next message after placing the order.
+const QUEUE_POSITION_INTERVAL: Duration = Duration::from_secs(10);
+
 pub struct Actor {
     executor: command::Executor,
     oracle_pk: XOnlyPublicKey,
@@ -48,11 +75,18 @@ pub struct Actor {
         MessageChannel<oracle::GetAnnouncements, Result<Vec<olivia::Announcement>, NoAnnouncement>>,
     build_party_params: MessageChannel<wallet::BuildPartyParams, Result<PartyParams>>,
     sign: MessageChannel<wallet::Sign, Result<PartiallySignedTransaction>>,
+    get_unlocked_balance: MessageChannel<wallet::GetUnlockedBalance, Result<Amount>>,
     projection: xtra::Address<projection::Actor>,
     n_payouts: usize,
     decision_senders: HashMap<OrderId, oneshot::Sender<protocol::Decision>>,
     db: sqlite_db::Connection,
     latest_offers: MessageChannel<offer::maker::GetLatestOffers, Vec<model::Offer>>,
+    max_contracts_per_taker: Option<Contracts>,
+    setup_queue: SetupQueue,
+    pending_order_timeouts: PendingOrderTimeouts,
+    reservations: Reservations,
+    is_accepting_orders: bool,
+    sessions: Sessions,
 }
 
 impl Actor {
@@ -64,12 +98,15 @@ impl Actor {
             Result<Vec<olivia::Announcement>, NoAnnouncement>,
         >,
         (db, process_manager): (sqlite_db::Connection, xtra::Address<process_manager::Actor>),
-        (build_party_params, sign): (
+        (build_party_params, sign, get_unlocked_balance): (
             MessageChannel<wallet::BuildPartyParams, Result<PartyParams>>,
             MessageChannel<wallet::Sign, Result<PartiallySignedTransaction>>,
+            MessageChannel<wallet::GetUnlockedBalance, Result<Amount>>,
         ),
         projection: xtra::Address<projection::Actor>,
         latest_offers: MessageChannel<offer::maker::GetLatestOffers, Vec<model::Offer>>,
+        max_contracts_per_taker: Option<Contracts>,
+        setup_queue: SetupQueue,
     ) -> Self {
         Self {
             executor: command::Executor::new(db.clone(), process_manager),
@@ -77,28 +114,60 @@ impl Actor {
             get_announcement,
             build_party_params,
             sign,
+            get_unlocked_balance,
             projection,
             n_payouts,
             decision_senders: HashMap::default(),
             db,
             latest_offers,
+            max_contracts_per_taker,
+            setup_queue,
+            pending_order_timeouts: PendingOrderTimeouts::default(),
+            reservations: Reservations::default(),
+            is_accepting_orders: true,
+            sessions: Sessions::default(),
         }
     }
 
-    #[instrument(skip(self), err)]
-    async fn receive_order(
-        &mut self,
-        framed: &mut Framed<Substream, JsonCodec<MakerMessage, TakerMessage>>,
-    ) -> Result<TakerMessage> {
-        let order = framed
-            .next()
-            .timeout(ORDER_TIMEOUT, || tracing::debug_span!("receive order"))
-            .await
-            .context("Timeout when waiting for order")?
-            .context("Stream terminated")?
-            .context("Unable to decode order")?;
+    /// Reject orders the operator did not decide on within the grace period of their contract
+    /// symbol.
+    pub fn with_pending_order_timeouts(mut self, timeouts: PendingOrderTimeouts) -> Self {
+        self.pending_order_timeouts = timeouts;
+        self
+    }
 
-        Ok(order)
+    /// Receive the order on a substream that the taker opened.
+    ///
+    /// Returns `None` if the taker resumed the session of an order on the substream.
+    #[instrument(skip(self, stream), err)]
+    async fn receive_order(
+        &self,
+        peer_id: PeerId,
+        stream: Substream,
+    ) -> Result<Option<(Session<MakerMessage, TakerMessage>, TakerMessage)>> {
+        let sessions = self.sessions.clone();
+
+        async move {
+            let mut session = match sessions
+                .accept::<MakerMessage, TakerMessage>(peer_id, stream, PROTOCOL, MAX_FRAME_SIZE)
+                .await
+                .context("Unable to decode order")?
+            {
+                Some(session) => session,
+                None => return Ok(None),
+            };
+
+            let order = session
+                .next()
+                .await
+                .context("Stream terminated")?
+                .context("Unable to decode order")?;
+
+            anyhow::Ok(Some((session, order)))
+        }
+        .timeout(ORDER_TIMEOUT, || tracing::debug_span!("receive order"))
+        .await
+        .context("Timeout when waiting for order")?
     }
 
     #[instrument(skip(self))]
@@ -117,6 +186,203 @@ impl Actor {
 
         Ok(offer)
     }
+
+    /// Pick the offer the order refers to and ensure that the order can be accepted.
+    ///
+    /// Violations of the limits of the maker are reported as [`RejectReason`].
+    async fn check_order(
+        &self,
+        peer_id: PeerId,
+        offer_id: OfferId,
+        revision: u32,
+        quanto_multiplier: Option<Decimal>,
+        quantity: Contracts,
+        opening_fee: Option<OpeningFee>,
+    ) -> Result<model::Offer> {
+        ensure!(
+            self.is_accepting_orders,
+            "Maker is not accepting new orders"
+        );
+
+        let offer = self.pick_offer(offer_id).await?;
+
+        check_revision(&offer, revision)?;
+
+        // Only new orders are subject to the trading hours, rollovers and settlements are not
+        if let Some(reopens_at) = offer
+            .trading_calendar
+            .as_ref()
+            .and_then(|calendar| calendar.reopens_at(OffsetDateTime::now_utc()))
+        {
+            return Err(RejectReason::MarketClosed {
+                reopens_at: Timestamp::new(reopens_at.unix_timestamp()),
+            }
+            .into());
+        }
+
+        check_opening_fee(&offer, quantity, opening_fee)?;
+        check_quanto_multiplier(&offer, quanto_multiplier)?;
+
+        if let Some(max) = offer.max_contracts_per_order {
+            if quantity > max {
+                return Err(RejectReason::MaxContractsPerOrderExceeded { quantity, max }.into());
+            }
+        }
+
+        if let Some(max) = self.max_contracts_per_taker {
+            let open =
+                exposure::open_contracts(&self.db, peer_id.into(), offer.contract_symbol).await?;
+
+            if open + quantity > max {
+                return Err(RejectReason::MaxContractsPerTakerExceeded {
+                    quantity,
+                    open,
+                    max,
+                }
+                .into());
+            }
+        }
+
+        Ok(offer)
+    }
+
+    async fn unlocked_balance(&self) -> Result<Amount> {
+        self.get_unlocked_balance
+            .send(wallet::GetUnlockedBalance)
+            .await
+            .context("Wallet actor not available")?
+    }
+
+    /// Redeem the reservation of the order or ensure that the order does not use liquidity
+    /// reserved for other orders.
+    ///
+    /// The returned claim keeps the liquidity reserved until it is dropped.
+    async fn claim_liquidity(
+        &self,
+        peer_id: PeerId,
+        reservation: Option<ReservationId>,
+        offer: &model::Offer,
+        quantity: Contracts,
+    ) -> Result<Option<Claim>> {
+        if let Some(id) = reservation {
+            match self.reservations.redeem(id, peer_id, offer.id, quantity) {
+                Some(claim) => return Ok(Some(claim)),
+                None => {
+                    tracing::debug!(%peer_id, reservation = %id, "Reservation expired or does not cover order")
+                }
+            }
+        }
+
+        let reserved = self.reservations.reserved();
+        if reserved == Amount::ZERO {
+            return Ok(None);
+        }
+
+        let margin = maker_margin(offer, quantity);
+        let unlocked_balance = self.unlocked_balance().await?;
+
+        ensure!(
+            reserved + margin <= unlocked_balance,
+            "Order of {quantity} contracts would use liquidity reserved for other orders"
+        );
+
+        Ok(None)
+    }
+
+    async fn reserve(
+        &self,
+        peer_id: PeerId,
+        offer: protocol::Offer,
+        quantity: Contracts,
+        opening_fee: OpeningFee,
+    ) -> Result<ReservationId> {
+        let offer = self
+            .check_order(
+                peer_id,
+                offer.id,
+                offer.revision,
+                offer.quanto_multiplier,
+                quantity,
+                Some(opening_fee),
+            )
+            .await?;
+
+        let margin = maker_margin(&offer, quantity);
+        let unlocked_balance = self.unlocked_balance().await?;
+
+        let id = self
+            .reservations
+            .reserve(peer_id, offer.id, quantity, margin, unlocked_balance)
+            .ok_or(RejectReason::InsufficientLiquidity { quantity })?;
+
+        Ok(id)
+    }
+}
+
+/// The margin the maker locks for an order of `quantity` contracts.
+fn maker_margin(offer: &model::Offer, quantity: Contracts) -> Amount {
+    model::calculate_margin(offer.payout_curve(), offer.price, quantity, Leverage::ONE)
+}
+
+/// Ensure that the taker agrees with the opening fee of the offer for the ordered quantity.
+/// Ensure the order refers to the current revision of the offer.
+///
+/// Besides explicit updates, the maker moves an offer to the next revision when re-issuing it for
+/// a new oracle event, see [`model::Offer::reissue`]. Orders for an earlier revision would
+/// otherwise set up the CFD against an oracle event the taker does not expect.
+fn check_revision(offer: &model::Offer, revision: u32) -> Result<(), RejectReason> {
+    if offer.revision != revision {
+        return Err(RejectReason::OfferUpdated {
+            offer_id: offer.id,
+            revision: offer.revision,
+        });
+    }
+
+    Ok(())
+}
+
+fn check_opening_fee(
+    offer: &model::Offer,
+    quantity: Contracts,
+    taker_opening_fee: Option<OpeningFee>,
+) -> Result<()> {
+    let opening_fee = offer.opening_fee_for(quantity);
+
+    match taker_opening_fee {
+        Some(taker_opening_fee) if taker_opening_fee != opening_fee => bail!(
+            "Taker expects opening fee of {} but offer {} charges {} for {quantity} contracts",
+            taker_opening_fee.to_inner(),
+            offer.id,
+            opening_fee.to_inner()
+        ),
+        // Old takers cannot interpret opening fee tiers
+        None if !offer.opening_fee_tiers.is_empty() => {
+            bail!(
+                "Taker does not support opening fee tiers of offer {}",
+                offer.id
+            )
+        }
+        _ => Ok(()),
+    }
+}
+
+fn check_quanto_multiplier(
+    offer: &model::Offer,
+    taker_quanto_multiplier: Option<Decimal>,
+) -> Result<()> {
+    let quanto_multiplier = offer.payout_curve().quanto_multiplier();
+
+    match taker_quanto_multiplier {
+        Some(taker_quanto_multiplier) if Some(taker_quanto_multiplier) != quanto_multiplier => {
+            bail!(
+                "Taker expects quanto multiplier of {taker_quanto_multiplier} but offer {} uses \
+                 {quanto_multiplier:?}",
+                offer.id,
+            )
+        }
+        // Old takers use the multiplier from their symbol registry
+        _ => Ok(()),
+    }
 }
 
 #[xtra_productivity]
@@ -124,23 +390,87 @@ impl Actor {
     async fn handle(&mut self, msg: NewInboundSubstream, ctx: &mut xtra::Context<Self>) {
         let NewInboundSubstream { peer_id, stream } = msg;
 
-        let mut framed = Framed::new(stream, JsonCodec::<MakerMessage, TakerMessage>::new());
-
-        let order = match self.receive_order(&mut framed).await {
-            Ok(order) => order,
+        let (mut framed, order) = match self.receive_order(peer_id, stream).await {
+            Ok(Some(order)) => order,
+            Ok(None) => return,
             Err(e) => {
                 tracing::error!("Failed to receive order from taker: {e:#}");
                 return;
             }
         };
 
-        let (order_id, offer_id, quantity, leverage) = match order {
+        let (
+            order_id,
+            offer_id,
+            revision,
+            quanto_multiplier,
+            quantity,
+            leverage,
+            opening_fee,
+            supports_queue_position,
+            reservation,
+            setup_msg_timeout_secs,
+        ) = match order {
             TakerMessage::PlaceOrder {
                 id,
                 offer,
                 quantity,
                 leverage,
-            } => (id, offer.id, quantity, leverage),
+                opening_fee,
+                supports_queue_position,
+                reservation,
+                setup_msg_timeout_secs,
+            } => (
+                id,
+                offer.id,
+                offer.revision,
+                offer.quanto_multiplier,
+                quantity,
+                leverage,
+                opening_fee,
+                supports_queue_position,
+                reservation,
+                setup_msg_timeout_secs,
+            ),
+            TakerMessage::RequestReservation {
+                offer,
+                quantity,
+                opening_fee,
+            } => {
+                let decision = match self.reserve(peer_id, offer, quantity, opening_fee).await {
+                    Ok(id) => {
+                        tracing::info!(%peer_id, %quantity, reservation = %id, "Granted reservation");
+
+                        protocol::ReservationDecision::Granted {
+                            id,
+                            valid_for_secs: RESERVATION_TTL.as_secs(),
+                        }
+                    }
+                    Err(e) => {
+                        tracing::info!(%peer_id, %quantity, "Denying reservation: {e:#}");
+
+                        protocol::ReservationDecision::Denied(
+                            e.downcast_ref::<RejectReason>().copied(),
+                        )
+                    }
+                };
+
+                let future = async move {
+                    framed.send(MakerMessage::Reservation(decision)).await?;
+
+                    anyhow::Ok(())
+                };
+
+                tokio_extras::spawn_fallible(
+                    &ctx.address().expect("self to be alive"),
+                    future,
+                    move |e| async move {
+                        tracing::debug!(%peer_id, "Failed to send reservation decision: {e}");
+                    },
+                );
+
+                return;
+            }
             TakerMessage::ContractSetupMsg(_) => {
                 tracing::error!("Unexpected message");
                 return;
@@ -149,19 +479,41 @@ impl Actor {
 
         tracing::info!(%peer_id, %quantity, %order_id, %offer_id, "Taker wants to place an order");
 
-        // Reject the order if the offer cannot be found in the latest offers
-        let offer = match self.pick_offer(offer_id).await {
-            Ok(offer) => offer,
+        // Reject the order if the offer cannot be found in the latest offers, was revised since
+        // the taker saw it, the taker expects a different opening fee or quanto multiplier, the
+        // order exceeds the limits of the maker or uses liquidity reserved for other orders
+        let checked = async {
+            let offer = self
+                .check_order(
+                    peer_id,
+                    offer_id,
+                    revision,
+                    quanto_multiplier,
+                    quantity,
+                    opening_fee,
+                )
+                .await?;
+            let claim = self
+                .claim_liquidity(peer_id, reservation, &offer, quantity)
+                .await?;
+
+            anyhow::Ok((offer, claim))
+        };
+
+        let (offer, claim) = match checked.await {
+            Ok(checked) => checked,
             Err(e) => {
-                tracing::warn!(
-                    %peer_id,
-                    "Rejecting taker order because unable to pick offer: {e:#}"
-                );
+                tracing::warn!(%peer_id, "Rejecting taker order: {e:#}");
+
+                let decision = match e.downcast_ref::<RejectReason>() {
+                    Some(reason) if opening_fee.is_some() => {
+                        protocol::Decision::RejectWithReason(*reason)
+                    }
+                    _ => protocol::Decision::Reject,
+                };
 
                 let future = async move {
-                    framed
-                        .send(MakerMessage::Decision(protocol::Decision::Reject))
-                        .await?;
+                    framed.send(MakerMessage::Decision(decision)).await?;
 
                     anyhow::Ok(())
                 };
@@ -179,6 +531,9 @@ impl Actor {
         };
 
         let oracle_event_id = offer.oracle_event_id;
+        let pending_timeout = self.pending_order_timeouts.get(offer.contract_symbol);
+        let supports_reject_reason = opening_fee.is_some();
+        let setup_msg_timeout = TimeoutPolicy::CONTRACT_SETUP.negotiate(setup_msg_timeout_secs);
 
         let cfd = Cfd::from_order(
             order_id,
@@ -208,6 +563,10 @@ impl Actor {
             return;
         }
 
+        // Forget about orders that were rejected because they timed out
+        self.decision_senders
+            .retain(|_, sender| !sender.is_canceled());
+
         let (sender, receiver) = oneshot::channel();
         self.decision_senders.insert(order_id, sender);
 
@@ -218,26 +577,89 @@ impl Actor {
             let executor = self.executor.clone();
             let oracle_pk = self.oracle_pk;
             let n_payouts = self.n_payouts;
+            let setup_queue = self.setup_queue.clone();
             async move {
-                match receiver.await? {
-                    protocol::Decision::Accept => {
-                        framed
-                            .send(MakerMessage::Decision(protocol::Decision::Accept))
-                            .await?;
+                // Hold the reserved liquidity until the contract setup ended
+                let _claim = claim;
+
+                let decision = match pending_timeout {
+                    Some(timeout) => match receiver
+                        .timeout(timeout, || {
+                            tracing::debug_span!("wait for decision on order")
+                        })
+                        .await
+                    {
+                        Ok(decision) => decision?,
+                        Err(_) => {
+                            tracing::info!(%peer_id, %order_id, "Rejecting order that was not decided on in time");
+
+                            protocol::Decision::RejectWithReason(RejectReason::TimedOut {
+                                minutes: timeout.as_secs() / 60,
+                            })
+                        }
+                    },
+                    None => receiver.await?,
+                };
 
-                        tracing::info!(%peer_id, %quantity, %order_id, "Order accepted");
+                match decision {
+                    protocol::Decision::Accept | protocol::Decision::AcceptWithTimeout { .. } => {
+                        let mut acquire = Box::pin(setup_queue.acquire(order_id));
+                        let _permit = loop {
+                            match (&mut acquire)
+                                .timeout(QUEUE_POSITION_INTERVAL, || {
+                                    tracing::debug_span!("wait for contract setup slot")
+                                })
+                                .await
+                            {
+                                Ok(permit) => break permit?,
+                                Err(_) => {
+                                    let position = match setup_queue.position(order_id) {
+                                        Some(position) => position,
+                                        None => continue,
+                                    };
+
+                                    tracing::debug!(%order_id, %position, "Contract setup is queued");
+
+                                    if supports_queue_position {
+                                        framed.send(MakerMessage::Queued { position }).await?;
+                                    }
+                                }
+                            }
+                        };
+
+                        let decision = match setup_msg_timeout_secs {
+                            Some(_) => protocol::Decision::AcceptWithTimeout {
+                                setup_msg_timeout_secs: setup_msg_timeout.as_secs(),
+                            },
+                            None => protocol::Decision::Accept,
+                        };
+                        framed.send(MakerMessage::Decision(decision)).await?;
+
+                        tracing::info!(%peer_id, %quantity, %order_id, ?setup_msg_timeout, "Order accepted");
                     }
-                    protocol::Decision::Reject => {
-                        framed
-                            .send(MakerMessage::Decision(protocol::Decision::Reject))
-                            .await?;
+                    decision @ (protocol::Decision::Reject
+                    | protocol::Decision::RejectWithReason(_)) => {
+                        let (decision, reason) = match decision {
+                            protocol::Decision::RejectWithReason(reason)
+                                if !supports_reject_reason =>
+                            {
+                                (protocol::Decision::Reject, anyhow::Error::new(reason))
+                            }
+                            protocol::Decision::RejectWithReason(reason) => {
+                                (decision, anyhow::Error::new(reason))
+                            }
+                            _ => (decision, anyhow!("Unknown")),
+                        };
+
+                        // The taker may have given up on the order already
+                        if let Err(e) = framed.send(MakerMessage::Decision(decision)).await {
+                            tracing::debug!(%peer_id, %order_id, "Failed to send reject order message: {e:#}");
+                        }
 
                         tracing::info!(%peer_id, %quantity, %order_id, "Order rejected");
 
                         executor
-                            .execute(order_id, |cfd| {
-                                cfd.reject_contract_setup(anyhow::anyhow!("Unknown"))
-                            })
+                            .execute(order_id, |cfd| cfd.reject_contract_setup(reason))
                             .await?;
 
                         return anyhow::Ok(());
@@ -276,11 +698,13 @@ impl Actor {
                     .fuse(),
                     (oracle_pk, announcement),
                     setup_params,
+                    order_id,
                     build_party_params,
                     sign,
                     Role::Maker,
                     position,
                     n_payouts,
+                    setup_msg_timeout,
                 )
                 .await?;
 
@@ -311,6 +735,26 @@ impl Actor {
         tokio_extras::spawn_fallible(&address, task, err_handler);
     }
 
+    async fn handle(&mut self, msg: UpdateConfiguration) {
+        self.is_accepting_orders = msg.is_accepting_orders;
+    }
+
+    /// Decisions can no longer be delivered to orders whose session ended.
+    async fn handle(&mut self, msg: janitor::CollectOrphans) -> usize {
+        let orphaned = self
+            .decision_senders
+            .values()
+            .filter(|sender| sender.is_canceled())
+            .count();
+
+        if !msg.dry_run {
+            self.decision_senders
+                .retain(|_, sender| !sender.is_canceled());
+        }
+
+        orphaned
+    }
+
     async fn handle(&mut self, msg: Decision) -> Result<()> {
         let id = msg.id();
 
@@ -329,6 +773,19 @@ impl Actor {
     }
 }
 
+#[derive(Clone, Copy)]
+pub struct UpdateConfiguration {
+    is_accepting_orders: bool,
+}
+
+impl UpdateConfiguration {
+    pub fn new(is_accepting_orders: bool) -> Self {
+        Self {
+            is_accepting_orders,
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub enum Decision {
     Accept(OrderId),
@@ -369,3 +826,29 @@ impl xtra::Actor for Actor {
 
     async fn stopped(self) -> Self::Stop {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn given_oracle_event_rolled_over_when_taking_pre_reissue_copy_then_order_is_rejected() {
+        let created_at = datetime!(2021-11-19 10:05:00).assume_utc();
+        let offer = model::Offer::dummy()
+            .with_time_to_live(Some(time::Duration::minutes(2)))
+            .reissue(created_at);
+        let taker_copy = offer.clone();
+
+        let offer = offer.reissue(created_at + time::Duration::minutes(1));
+        assert_eq!(offer.oracle_event_id, taker_copy.oracle_event_id);
+        assert!(check_revision(&offer, taker_copy.revision).is_ok());
+
+        let offer = offer.reissue(created_at + time::Duration::hours(1));
+        assert_ne!(offer.oracle_event_id, taker_copy.oracle_event_id);
+        assert!(matches!(
+            check_revision(&offer, taker_copy.revision),
+            Err(RejectReason::OfferUpdated { revision, .. }) if revision == offer.revision
+        ));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::order::reservation::ReservationId;
 use anyhow::bail;
 use anyhow::Result;
 use bdk::bitcoin::psbt::PartiallySignedTransaction;
@@ -12,7 +13,10 @@ use maia_core::PunishParams;
 use model::Contracts;
 use model::Leverage;
 use model::OfferId;
+use model::OpeningFee;
 use model::OrderId;
+use model::Timestamp;
+use rust_decimal::Decimal;
 use serde::Deserialize;
 use serde::Serialize;
 use std::collections::HashMap;
@@ -25,8 +29,35 @@ pub(crate) enum TakerMessage {
         offer: Offer,
         quantity: Contracts,
         leverage: Leverage,
+        /// The opening fee the taker expects to pay for the quantity
+        ///
+        /// Old takers do not send the opening fee, they only take offers with a flat fee.
+        #[serde(default)]
+        opening_fee: Option<OpeningFee>,
+        /// Whether the taker understands [`MakerMessage::Queued`]
+        #[serde(default)]
+        supports_queue_position: bool,
+        /// The reservation the maker granted for the order
+        ///
+        /// Takers do not send a reservation if the maker does not support reservations.
+        #[serde(default)]
+        reservation: Option<ReservationId>,
+        /// The taker's proposal of the contract setup message timeout in seconds, see
+        /// [`model::protocol_timeout`]
+        ///
+        /// Old takers do not propose a timeout, they use the legacy timeout.
+        #[serde(default)]
+        setup_msg_timeout_secs: Option<u64>,
     },
     ContractSetupMsg(Box<SetupMsg>),
+    /// Reserve the maker's liquidity for a subsequent order, see [`crate::order::reservation`]
+    ///
+    /// Sent on its own substream, the maker replies with [`MakerMessage::Reservation`].
+    RequestReservation {
+        offer: Offer,
+        quantity: Contracts,
+        opening_fee: OpeningFee,
+    },
 }
 
 /// Identifies the offer which the taker used as a source to place the order.
@@ -36,18 +67,83 @@ pub(crate) enum TakerMessage {
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct Offer {
     pub id: OfferId,
+    /// The revision of the offer the taker saw, see [`model::Offer::revision`]
+    ///
+    /// Old takers do not send the revision, they only know offers that were never revised.
+    #[serde(default)]
+    pub revision: u32,
+    /// The quanto multiplier of the offer the taker saw, see [`model::Offer::quanto_multiplier`]
+    ///
+    /// Old takers do not send the multiplier, they use the one from their symbol registry.
+    #[serde(default)]
+    pub quanto_multiplier: Option<Decimal>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) enum MakerMessage {
     Decision(Decision),
     ContractSetupMsg(Box<SetupMsg>),
+    /// The order waits for a free contract setup slot of the maker
+    ///
+    /// Sent periodically until the decision is sent, starting at position 1 for the next order to
+    /// be set up. Only sent to takers that set `supports_queue_position`.
+    Queued {
+        position: usize,
+    },
+    Reservation(ReservationDecision),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) enum ReservationDecision {
+    /// The order will not be rejected because of concurrent orders if it is placed within
+    /// `valid_for_secs` seconds
+    Granted {
+        id: ReservationId,
+        valid_for_secs: u64,
+    },
+    /// `None` if the reservation was denied for a reason the maker does not disclose
+    Denied(Option<RejectReason>),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) enum Decision {
     Accept,
+    /// Acceptance with the negotiated contract setup message timeout in seconds
+    ///
+    /// Only sent to takers that propose a timeout with their order, older takers are not able to
+    /// decode it.
+    AcceptWithTimeout {
+        setup_msg_timeout_secs: u64,
+    },
     Reject,
+    /// Rejection because the order violates a limit of the maker
+    ///
+    /// Only sent to takers that send the opening fee with their order, older takers are not able
+    /// to decode it.
+    RejectWithReason(RejectReason),
+}
+
+/// Why the maker rejected an order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+pub(crate) enum RejectReason {
+    #[error("Order of {quantity} contracts exceeds the maximum of {max} contracts per order")]
+    MaxContractsPerOrderExceeded { quantity: Contracts, max: Contracts },
+    #[error("Order of {quantity} contracts exceeds the maximum of {max} open contracts per taker, {open} contracts are already open")]
+    MaxContractsPerTakerExceeded {
+        quantity: Contracts,
+        open: Contracts,
+        max: Contracts,
+    },
+    #[error("Offer {offer_id} was updated to revision {revision}")]
+    OfferUpdated { offer_id: OfferId, revision: u32 },
+    #[error("Order timed out after waiting {minutes} minutes for the maker's decision")]
+    TimedOut { minutes: u64 },
+    /// Only sent in [`ReservationDecision::Denied`], older takers are not able to decode it
+    #[error("Maker has insufficient liquidity for {quantity} contracts")]
+    InsufficientLiquidity { quantity: Contracts },
+    /// Only sent for offers with a trading calendar, which older takers are not able to verify
+    #[error("Market closed, it reopens at {reopens_at}")]
+    MarketClosed { reopens_at: Timestamp },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -219,6 +315,8 @@ impl TryFrom<MakerMessage> for SetupMsg {
     fn try_from(value: MakerMessage) -> Result<Self> {
         match value {
             MakerMessage::Decision(_) => bail!("Expected SetupMsg, got decision"),
+            MakerMessage::Queued { .. } => bail!("Expected SetupMsg, got queue position"),
+            MakerMessage::Reservation(_) => bail!("Expected SetupMsg, got reservation"),
             MakerMessage::ContractSetupMsg(msg) => Ok(*msg),
         }
     }
@@ -230,7 +328,143 @@ impl TryFrom<TakerMessage> for SetupMsg {
     fn try_from(value: TakerMessage) -> Result<Self> {
         match value {
             TakerMessage::PlaceOrder { .. } => bail!("Expected SetupMsg, got order placement"),
+            TakerMessage::RequestReservation { .. } => {
+                bail!("Expected SetupMsg, got reservation request")
+            }
             TakerMessage::ContractSetupMsg(msg) => Ok(*msg),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xtra_libp2p::wire_fixtures::Fixtures;
+
+    const FIXTURES: Fixtures = Fixtures::new("./src/order/wire_fixtures/2.0.0");
+    const SEQUENCED_FIXTURES: Fixtures = Fixtures::sequenced("./src/order/wire_fixtures/3.0.0");
+
+    #[test]
+    fn place_order_matches_recording() {
+        let msg = FIXTURES.round_trip::<TakerMessage>("place_order.json");
+
+        assert!(matches!(
+            msg,
+            TakerMessage::PlaceOrder {
+                offer: Offer { revision: 2, .. },
+                supports_queue_position: true,
+                reservation: Some(_),
+                setup_msg_timeout_secs: Some(120),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn sequenced_place_order_matches_recording() {
+        let msg = SEQUENCED_FIXTURES.round_trip::<TakerMessage>("place_order.json");
+
+        assert!(matches!(msg, TakerMessage::PlaceOrder { .. }));
+    }
+
+    #[test]
+    fn legacy_place_order_defaults_new_fields() {
+        let msg = FIXTURES.decode::<TakerMessage>("place_order_legacy.json");
+
+        assert!(matches!(
+            msg,
+            TakerMessage::PlaceOrder {
+                offer: Offer {
+                    revision: 0,
+                    quanto_multiplier: None,
+                    ..
+                },
+                opening_fee: None,
+                supports_queue_position: false,
+                reservation: None,
+                setup_msg_timeout_secs: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn request_reservation_matches_recording() {
+        let msg = FIXTURES.round_trip::<TakerMessage>("request_reservation.json");
+
+        assert!(matches!(msg, TakerMessage::RequestReservation { .. }));
+    }
+
+    #[test]
+    fn decisions_match_recording() {
+        let accept = FIXTURES.round_trip::<MakerMessage>("decision_accept.json");
+        let accept_with_timeout =
+            FIXTURES.round_trip::<MakerMessage>("decision_accept_with_timeout.json");
+        let reject = FIXTURES.round_trip::<MakerMessage>("decision_reject_with_reason.json");
+
+        assert!(matches!(accept, MakerMessage::Decision(Decision::Accept)));
+        assert!(matches!(
+            accept_with_timeout,
+            MakerMessage::Decision(Decision::AcceptWithTimeout {
+                setup_msg_timeout_secs: 120
+            })
+        ));
+        assert!(matches!(
+            reject,
+            MakerMessage::Decision(Decision::RejectWithReason(
+                RejectReason::MaxContractsPerTakerExceeded { open, .. }
+            )) if open == Contracts::new(900)
+        ));
+    }
+
+    #[test]
+    fn queue_position_matches_recording() {
+        let msg = FIXTURES.round_trip::<MakerMessage>("queued.json");
+
+        assert!(matches!(msg, MakerMessage::Queued { position: 3 }));
+    }
+
+    #[test]
+    fn reservation_decisions_match_recording() {
+        let granted = FIXTURES.round_trip::<MakerMessage>("reservation_granted.json");
+        let denied = FIXTURES.round_trip::<MakerMessage>("reservation_denied.json");
+
+        assert!(matches!(
+            granted,
+            MakerMessage::Reservation(ReservationDecision::Granted {
+                valid_for_secs: 60,
+                ..
+            })
+        ));
+        assert!(matches!(
+            denied,
+            MakerMessage::Reservation(ReservationDecision::Denied(Some(
+                RejectReason::InsufficientLiquidity { .. }
+            )))
+        ));
+    }
+
+    #[test]
+    fn setup_messages_match_recording() {
+        let msg0 = FIXTURES.round_trip::<TakerMessage>("setup_msg0.json");
+        let msg1 = FIXTURES.round_trip::<MakerMessage>("setup_msg1.json");
+        let msg2 = FIXTURES.round_trip::<MakerMessage>("setup_msg2.json");
+        let msg3 = FIXTURES.round_trip::<TakerMessage>("setup_msg3.json");
+
+        let msg0 = SetupMsg::try_from(msg0).unwrap().try_into_msg0().unwrap();
+        assert_eq!(
+            msg0.lock_psbt.unsigned_tx.txid().to_string(),
+            "6807432760ab06cb821064b72d536d738b8d5bb652283c68ae4c40407d9b8c88"
+        );
+        assert_eq!(msg0.lock_amount, Amount::from_sat(500_000));
+        let msg1 = SetupMsg::try_from(msg1).unwrap().try_into_msg1().unwrap();
+        assert_eq!(msg1.cets.values().map(Vec::len).sum::<usize>(), 1);
+        let msg2 = SetupMsg::try_from(msg2).unwrap().try_into_msg2().unwrap();
+        assert_eq!(
+            msg2.signed_lock.unsigned_tx.txid(),
+            msg0.lock_psbt.unsigned_tx.txid()
+        );
+        assert!(msg2.signed_lock.inputs[0].final_script_witness.is_some());
+        SetupMsg::try_from(msg3).unwrap().try_into_msg3().unwrap();
+    }
+}
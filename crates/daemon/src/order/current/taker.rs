@@ -1,13 +1,17 @@
 use crate::command;
+use crate::failure_report;
 use crate::oracle;
 use crate::oracle::NoAnnouncement;
 use crate::order::current::contract_setup;
 use crate::order::current::protocol;
 use crate::order::current::protocol::Decision;
 use crate::order::current::protocol::MakerMessage;
+use crate::order::current::protocol::ReservationDecision;
 use crate::order::current::protocol::SetupMsg;
 use crate::order::current::protocol::TakerMessage;
+use crate::order::current::MAX_FRAME_SIZE;
 use crate::order::current::PROTOCOL;
+use crate::order::current::UNSEQUENCED_PROTOCOL;
 use crate::process_manager;
 use crate::projection;
 use crate::wallet;
@@ -15,8 +19,6 @@ use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
-use asynchronous_codec::Framed;
-use asynchronous_codec::JsonCodec;
 use bdk::bitcoin::psbt::PartiallySignedTransaction;
 use bdk::bitcoin::XOnlyPublicKey;
 use futures::future;
@@ -25,23 +27,29 @@ use futures::StreamExt;
 use libp2p_core::PeerId;
 use maia_core::PartyParams;
 use model::olivia;
+use model::protocol_timeout::TimeoutPolicy;
 use model::Cfd;
 use model::Contracts;
 use model::Identity;
 use model::Leverage;
 use model::Offer;
+use model::OpeningFee;
 use model::OrderId;
 use model::Role;
 use std::time::Duration;
 use tokio_extras::FutureExt;
 use xtra::prelude::MessageChannel;
+use xtra_libp2p::sequenced::Session;
+use xtra_libp2p::sequenced::SessionId;
 use xtra_libp2p::Endpoint;
-use xtra_libp2p::OpenSubstream;
 use xtra_productivity::xtra_productivity;
 
 /// Timeout for awaiting a response to an order request from the maker
 const PLACE_ORDER_RESPONSE_TIMEOUT: Duration = Duration::from_secs(60);
 
+/// Timeout for awaiting a response to a reservation request from the maker
+const RESERVATION_RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub struct Actor {
     endpoint: xtra::Address<Endpoint>,
     executor: command::Executor,
@@ -53,6 +61,7 @@ pub struct Actor {
     projection: xtra::Address<projection::Actor>,
     n_payouts: usize,
     db: sqlite_db::Connection,
+    failure_reports: Option<MessageChannel<failure_report::taker::ContractSetupFailed, ()>>,
 }
 
 impl Actor {
@@ -81,6 +90,18 @@ impl Actor {
             projection,
             n_payouts,
             db,
+            failure_reports: None,
+        }
+    }
+
+    /// Report failed contract setups to the given channel.
+    pub fn with_failure_reports(
+        self,
+        failure_reports: MessageChannel<failure_report::taker::ContractSetupFailed, ()>,
+    ) -> Self {
+        Self {
+            failure_reports: Some(failure_reports),
+            ..self
         }
     }
 }
@@ -89,6 +110,7 @@ impl Actor {
 impl Actor {
     pub async fn handle(&mut self, msg: PlaceOrder, ctx: &mut xtra::Context<Self>) {
         let id = msg.order_id;
+        let maker_peer_id = msg.maker_peer_id;
 
         let task = {
             let build_party_params = self.build_party_params.clone();
@@ -132,42 +154,104 @@ impl Actor {
 
                 projection.send(projection::CfdChanged(cfd.id())).await?;
 
-                let stream = endpoint
-                    .send(OpenSubstream::single_protocol(maker_peer_id, PROTOCOL))
-                    .await
-                    .context("Endpoint is disconnected")?
-                    .context("No connection to peer")?
-                    .await
-                    .context("Failed to open substream")?;
+                let offer_ref = || protocol::Offer {
+                    id: offer.id,
+                    revision: offer.revision,
+                    quanto_multiplier: offer.quanto_multiplier,
+                };
+                let opening_fee = offer.opening_fee_for(quantity);
+
+                // Makers that do not support reservations close the substream, the order is then
+                // placed without a reservation
+                let reservation = match request_reservation(
+                    &endpoint,
+                    maker_peer_id,
+                    order_id,
+                    offer_ref(),
+                    quantity,
+                    opening_fee,
+                )
+                .await
+                {
+                    Ok(ReservationDecision::Granted { id, valid_for_secs }) => {
+                        tracing::debug!(%order_id, reservation = %id, %valid_for_secs, "Maker granted reservation");
+                        Some(id)
+                    }
+                    Ok(ReservationDecision::Denied(reason)) => {
+                        let reason = match reason {
+                            Some(reason) => anyhow::Error::new(reason),
+                            None => anyhow::anyhow!("Unknown"),
+                        };
+                        tracing::info!(%order_id, %maker_peer_id, "Maker denied reservation: {reason:#}");
 
-                let mut framed =
-                    Framed::new(stream, JsonCodec::<TakerMessage, MakerMessage>::new());
+                        executor
+                            .execute(order_id, |cfd| cfd.reject_contract_setup(reason))
+                            .await?;
+
+                        return anyhow::Ok(());
+                    }
+                    Err(e) => {
+                        tracing::debug!(%order_id, %maker_peer_id, "Placing order without reservation: {e:#}");
+                        None
+                    }
+                };
+
+                let mut framed = dial(&endpoint, maker_peer_id, order_id).await?;
 
                 framed
                     .send(TakerMessage::PlaceOrder {
                         id: order_id,
-                        offer: protocol::Offer { id: offer.id },
+                        offer: offer_ref(),
                         quantity,
                         leverage,
+                        opening_fee: Some(opening_fee),
+                        supports_queue_position: true,
+                        reservation,
+                        setup_msg_timeout_secs: Some(TimeoutPolicy::CONTRACT_SETUP.proposal_secs()),
                     })
                     .await?;
 
-                match framed
-                    .next()
-                    .timeout(PLACE_ORDER_RESPONSE_TIMEOUT, || {
-                        tracing::debug_span!("receive make response")
-                    })
-                    .await
-                    .with_context(|| {
-                        format!(
-                            "The maker did not respond within {} seconds",
-                            PLACE_ORDER_RESPONSE_TIMEOUT.as_secs()
-                        )
-                    })?
-                    .context("Stream terminated")??
-                {
+                // The maker keeps sending its position in the setup queue while the order waits
+                // for a free contract setup slot
+                let decision = loop {
+                    match framed
+                        .next()
+                        .timeout(PLACE_ORDER_RESPONSE_TIMEOUT, || {
+                            tracing::debug_span!("receive make response")
+                        })
+                        .await
+                        .with_context(|| {
+                            format!(
+                                "The maker did not respond within {} seconds",
+                                PLACE_ORDER_RESPONSE_TIMEOUT.as_secs()
+                            )
+                        })?
+                        .context("Stream terminated")??
+                    {
+                        MakerMessage::Queued { position } => {
+                            tracing::info!(%order_id, %maker_peer_id, %position, "Order is queued for contract setup");
+                        }
+                        message => break message,
+                    }
+                };
+
+                let setup_msg_timeout = match decision {
                     MakerMessage::Decision(Decision::Accept) => {
                         tracing::info!(order_id = %msg.order_id, %maker_peer_id, "Order accepted");
+
+                        // Makers that do not negotiate use the legacy timeout
+                        TimeoutPolicy::CONTRACT_SETUP.verify(None)?
+                    }
+                    MakerMessage::Decision(Decision::AcceptWithTimeout {
+                        setup_msg_timeout_secs,
+                    }) => {
+                        let setup_msg_timeout = TimeoutPolicy::CONTRACT_SETUP
+                            .verify(Some(setup_msg_timeout_secs))
+                            .context("Maker negotiated an invalid contract setup timeout")?;
+
+                        tracing::info!(order_id = %msg.order_id, %maker_peer_id, ?setup_msg_timeout, "Order accepted");
+
+                        setup_msg_timeout
                     }
                     MakerMessage::Decision(Decision::Reject) => {
                         tracing::info!(order_id = %msg.order_id, %maker_peer_id, "Order rejected");
@@ -180,7 +264,22 @@ impl Actor {
 
                         return anyhow::Ok(());
                     }
-                    MakerMessage::ContractSetupMsg(_) => bail!("Unexpected message"),
+                    MakerMessage::Decision(Decision::RejectWithReason(reason)) => {
+                        tracing::info!(order_id = %msg.order_id, %maker_peer_id, %reason, "Order rejected");
+
+                        executor
+                            .execute(order_id, |cfd| {
+                                cfd.reject_contract_setup(anyhow::Error::new(reason))
+                            })
+                            .await?;
+
+                        return anyhow::Ok(());
+                    }
+                    MakerMessage::ContractSetupMsg(_)
+                    | MakerMessage::Queued { .. }
+                    | MakerMessage::Reservation(_) => {
+                        bail!("Unexpected message")
+                    }
                 };
 
                 let (setup_params, position) = executor
@@ -215,11 +314,13 @@ impl Actor {
                     .fuse(),
                     (oracle_pk, announcement),
                     setup_params,
+                    order_id,
                     build_party_params,
                     sign,
                     Role::Taker,
                     position,
                     n_payouts,
+                    setup_msg_timeout,
                 )
                 .await?;
 
@@ -236,7 +337,28 @@ impl Actor {
 
         let err_handler = {
             let executor = self.executor.clone();
-            move |e| async move {
+            let failure_reports = self.failure_reports.clone();
+            move |e: anyhow::Error| async move {
+                let e = match failure_reports {
+                    Some(failure_reports) => {
+                        let message = anyhow::anyhow!("{e:#}");
+
+                        if let Err(e) = failure_reports
+                            .send(failure_report::taker::ContractSetupFailed {
+                                order_id: id,
+                                maker_peer_id,
+                                error: e,
+                            })
+                            .await
+                        {
+                            tracing::debug!(%id, "Failed to report contract setup failure: {e:#}");
+                        }
+
+                        message
+                    }
+                    None => e,
+                };
+
                 if let Err(e) = executor
                     .execute(id, |cfd| Ok(cfd.fail_contract_setup(e)))
                     .await
@@ -251,6 +373,59 @@ impl Actor {
     }
 }
 
+/// Reserve the maker's liquidity for an order on a dedicated substream.
+async fn request_reservation(
+    endpoint: &xtra::Address<Endpoint>,
+    maker_peer_id: PeerId,
+    order_id: OrderId,
+    offer: protocol::Offer,
+    quantity: Contracts,
+    opening_fee: OpeningFee,
+) -> Result<ReservationDecision> {
+    let mut framed = dial(endpoint, maker_peer_id, order_id).await?;
+
+    framed
+        .send(TakerMessage::RequestReservation {
+            offer,
+            quantity,
+            opening_fee,
+        })
+        .await?;
+
+    let response = framed
+        .next()
+        .timeout(RESERVATION_RESPONSE_TIMEOUT, || {
+            tracing::debug_span!("receive reservation decision")
+        })
+        .await
+        .context("The maker did not respond to the reservation request in time")?
+        .context("Stream terminated")??;
+
+    match response {
+        MakerMessage::Reservation(decision) => Ok(decision),
+        _ => bail!("Unexpected message"),
+    }
+}
+
+/// Open a session with the maker, on the protocol version with sequence numbers if the maker
+/// supports it.
+///
+/// A session with sequence numbers is resumed on a new substream if its substream breaks.
+async fn dial(
+    endpoint: &xtra::Address<Endpoint>,
+    maker_peer_id: PeerId,
+    order_id: OrderId,
+) -> Result<Session<TakerMessage, MakerMessage>> {
+    Session::dial(
+        endpoint,
+        maker_peer_id,
+        (PROTOCOL, UNSEQUENCED_PROTOCOL),
+        SessionId::new(order_id),
+        MAX_FRAME_SIZE,
+    )
+    .await
+}
+
 #[derive(Debug)]
 pub(crate) struct PlaceOrder {
     order_id: OrderId,
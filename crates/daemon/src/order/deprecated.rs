@@ -3,3 +3,6 @@ pub mod maker;
 mod protocol;
 
 pub const PROTOCOL: &str = "/itchysats/order/1.0.0";
+
+/// Maximum size of an encoded message in bytes, see [`crate::order::current::MAX_FRAME_SIZE`]
+pub const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
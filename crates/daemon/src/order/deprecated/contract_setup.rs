@@ -28,14 +28,15 @@ use maia_core::PartyParams;
 use maia_core::PunishParams;
 use model::olivia;
 use model::olivia::BitMexPriceEventId;
-use model::payout_curve::ETHUSD_MULTIPLIER;
 use model::shared_protocol::verify_adaptor_signature;
 use model::shared_protocol::verify_cets;
+use model::shared_protocol::verify_lock_inputs;
 use model::shared_protocol::verify_signature;
+use model::symbols::PayoutCurve;
 use model::Cet;
-use model::ContractSymbol;
 use model::Dlc;
 use model::OraclePayouts;
+use model::OrderId;
 use model::Payouts;
 use model::Position;
 use model::Role;
@@ -73,6 +74,7 @@ pub async fn new(
     mut stream: impl Stream<Item = SetupMsg> + Unpin,
     (oracle_pk, announcements): (XOnlyPublicKey, Vec<olivia::Announcement>),
     setup_params: SetupParams,
+    order_id: OrderId,
     build_party_params_channel: MessageChannel<wallet::BuildPartyParams, Result<PartyParams>>,
     sign_channel: MessageChannel<wallet::Sign, Result<PartiallySignedTransaction>>,
     own_role: Role,
@@ -83,7 +85,7 @@ pub async fn new(
     tracing::trace!(?oracle_pk, ?announcements);
 
     let (own, own_punish, key_pairs) =
-        own_setup_params(build_party_params_channel, setup_params).await?;
+        own_setup_params(build_party_params_channel, setup_params, order_id).await?;
 
     sink.send(SetupMsg::Msg0(Msg0::from((own.clone(), own_punish))))
         .instrument(tracing::debug_span!("Send Msg0"))
@@ -103,6 +105,7 @@ pub async fn new(
         .try_into_msg0()?;
 
     let (counterparty, counterparty_punish) = msg0.into();
+    verify_lock_inputs(&counterparty).context("Counterparty cannot fund its margin")?;
 
     let params = AllParams {
         own,
@@ -262,6 +265,7 @@ struct KeyPairs {
 async fn own_setup_params(
     build_party_params_channel: MessageChannel<wallet::BuildPartyParams, Result<PartyParams>>,
     setup_params: SetupParams,
+    order_id: OrderId,
 ) -> Result<(PartyParams, PunishParams, KeyPairs)> {
     let key_pairs = KeyPairs {
         identity: keypair::new(&mut rand::thread_rng()).into(),
@@ -274,6 +278,7 @@ async fn own_setup_params(
             amount: setup_params.margin,
             identity_pk: key_pairs.identity.public,
             fee_rate: setup_params.tx_fee_rate,
+            order_id,
         })
         .instrument(tracing::debug_span!(
             "Send BuildPartyParams to wallet actor"
@@ -311,8 +316,8 @@ async fn create_cfd_transactions(
 
     let settlement_event_id = announcements.last().context("Empty announcements")?.id;
 
-    let payouts = match setup_params.contract_symbol {
-        ContractSymbol::BtcUsd => Payouts::new_inverse_double_initial(
+    let payouts = match setup_params.payout_curve {
+        PayoutCurve::Inverse => Payouts::new_inverse_double_initial(
             (position, role),
             setup_params.price,
             setup_params.quantity,
@@ -320,13 +325,13 @@ async fn create_cfd_transactions(
             n_payouts,
             setup_params.fee_account.settle(),
         )?,
-        ContractSymbol::EthUsd => Payouts::new_quanto(
+        PayoutCurve::Quanto { multiplier } => Payouts::new_quanto(
             (position, role),
             setup_params.price.to_u64(),
             setup_params.quantity.to_u64(),
             (setup_params.long_leverage, setup_params.short_leverage),
             n_payouts,
-            ETHUSD_MULTIPLIER,
+            multiplier,
             setup_params.fee_account.settle(),
         )?,
     };
@@ -1,4 +1,5 @@
 use crate::command;
+use crate::janitor;
 use crate::oracle;
 use crate::oracle::NoAnnouncement;
 use crate::order::deprecated::contract_setup;
@@ -6,15 +7,17 @@ use crate::order::deprecated::protocol;
 use crate::order::deprecated::protocol::MakerMessage;
 use crate::order::deprecated::protocol::SetupMsg;
 use crate::order::deprecated::protocol::TakerMessage;
+use crate::order::deprecated::MAX_FRAME_SIZE;
+use crate::order::exposure;
 use crate::process_manager;
 use crate::projection;
 use crate::wallet;
 use anyhow::anyhow;
+use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
 use asynchronous_codec::Framed;
-use asynchronous_codec::JsonCodec;
 use bdk::bitcoin::psbt::PartiallySignedTransaction;
 use bdk::bitcoin::XOnlyPublicKey;
 use futures::channel::oneshot;
@@ -24,6 +27,7 @@ use futures::StreamExt;
 use maia_core::PartyParams;
 use model::olivia;
 use model::Cfd;
+use model::Contracts;
 use model::Identity;
 use model::OfferId;
 use model::OrderId;
@@ -34,6 +38,8 @@ use std::time::Duration;
 use tokio_extras::FutureExt;
 use tracing::instrument;
 use xtra::prelude::MessageChannel;
+use xtra_libp2p::libp2p::PeerId;
+use xtra_libp2p::limited::LimitedJsonCodec;
 use xtra_libp2p::NewInboundSubstream;
 use xtra_libp2p::Substream;
 use xtra_productivity::xtra_productivity;
@@ -53,6 +59,8 @@ pub struct Actor {
     decision_senders: HashMap<OrderId, oneshot::Sender<protocol::Decision>>,
     db: sqlite_db::Connection,
     latest_offers: MessageChannel<offer::maker::GetLatestOffers, Vec<model::Offer>>,
+    max_contracts_per_taker: Option<Contracts>,
+    is_accepting_orders: bool,
 }
 
 impl Actor {
@@ -70,6 +78,7 @@ impl Actor {
         ),
         projection: xtra::Address<projection::Actor>,
         latest_offers: MessageChannel<offer::maker::GetLatestOffers, Vec<model::Offer>>,
+        max_contracts_per_taker: Option<Contracts>,
     ) -> Self {
         Self {
             executor: command::Executor::new(db.clone(), process_manager),
@@ -82,13 +91,15 @@ impl Actor {
             decision_senders: HashMap::default(),
             db,
             latest_offers,
+            max_contracts_per_taker,
+            is_accepting_orders: true,
         }
     }
 
     #[instrument(skip(self), err)]
     async fn receive_order(
         &mut self,
-        framed: &mut Framed<Substream, JsonCodec<MakerMessage, TakerMessage>>,
+        framed: &mut Framed<Substream, LimitedJsonCodec<MakerMessage, TakerMessage>>,
     ) -> Result<TakerMessage> {
         let order = framed
             .next()
@@ -115,6 +126,42 @@ impl Actor {
             .with_context(|| format!("Offer with id {offer_id} not found in current offers"))?
             .clone();
 
+        // Offers with opening fee tiers, a cap per order, revisions or trading hours are not
+        // announced through the deprecated protocol
+        if !offer.opening_fee_tiers.is_empty()
+            || offer.max_contracts_per_order.is_some()
+            || offer.revision > 0
+            || offer.trading_calendar.is_some()
+        {
+            bail!("Offer with id {offer_id} is not available to deprecated takers");
+        }
+
+        Ok(offer)
+    }
+
+    /// Pick the offer the order refers to and ensure that the order does not exceed the maximum
+    /// of open contracts per taker.
+    async fn check_order(
+        &self,
+        peer_id: PeerId,
+        offer_id: OfferId,
+        quantity: Contracts,
+    ) -> Result<model::Offer> {
+        if !self.is_accepting_orders {
+            bail!("Maker is not accepting new orders");
+        }
+
+        let offer = self.pick_offer(offer_id).await?;
+
+        if let Some(max) = self.max_contracts_per_taker {
+            let open =
+                exposure::open_contracts(&self.db, peer_id.into(), offer.contract_symbol).await?;
+
+            if open + quantity > max {
+                bail!("Order of {quantity} contracts exceeds the maximum of {max} open contracts per taker, {open} contracts are already open");
+            }
+        }
+
         Ok(offer)
     }
 }
@@ -124,7 +171,10 @@ impl Actor {
     async fn handle(&mut self, msg: NewInboundSubstream, ctx: &mut xtra::Context<Self>) {
         let NewInboundSubstream { peer_id, stream } = msg;
 
-        let mut framed = Framed::new(stream, JsonCodec::<MakerMessage, TakerMessage>::new());
+        let mut framed = Framed::new(
+            stream,
+            LimitedJsonCodec::<MakerMessage, TakerMessage>::new(MAX_FRAME_SIZE),
+        );
 
         let order = match self.receive_order(&mut framed).await {
             Ok(order) => order,
@@ -149,11 +199,12 @@ impl Actor {
 
         tracing::info!(%peer_id, %quantity, %order_id, "Taker wants to place an order");
 
-        // Reject the order if the offer cannot be found in the latest offers
-        let offer = match self.pick_offer(offer_id).await {
+        // Reject the order if the offer cannot be found in the latest offers or the order exceeds
+        // the maximum of open contracts per taker
+        let offer = match self.check_order(peer_id, offer_id, quantity).await {
             Ok(offer) => offer,
             Err(e) => {
-                tracing::warn!("Rejecting taker order because unable to pick offer: {e:#}");
+                tracing::warn!("Rejecting taker order: {e:#}");
 
                 let future = async move {
                     framed
@@ -273,6 +324,7 @@ impl Actor {
                     .fuse(),
                     (oracle_pk, announcement),
                     setup_params,
+                    order_id,
                     build_party_params,
                     sign,
                     Role::Maker,
@@ -308,6 +360,26 @@ impl Actor {
         tokio_extras::spawn_fallible(&address, task, err_handler);
     }
 
+    async fn handle(&mut self, msg: UpdateConfiguration) {
+        self.is_accepting_orders = msg.is_accepting_orders;
+    }
+
+    /// Decisions can no longer be delivered to orders whose session ended.
+    async fn handle(&mut self, msg: janitor::CollectOrphans) -> usize {
+        let orphaned = self
+            .decision_senders
+            .values()
+            .filter(|sender| sender.is_canceled())
+            .count();
+
+        if !msg.dry_run {
+            self.decision_senders
+                .retain(|_, sender| !sender.is_canceled());
+        }
+
+        orphaned
+    }
+
     async fn handle(&mut self, msg: Decision) -> Result<()> {
         let id = msg.id();
 
@@ -326,6 +398,19 @@ impl Actor {
     }
 }
 
+#[derive(Clone, Copy)]
+pub struct UpdateConfiguration {
+    is_accepting_orders: bool,
+}
+
+impl UpdateConfiguration {
+    pub fn new(is_accepting_orders: bool) -> Self {
+        Self {
+            is_accepting_orders,
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub enum Decision {
     Accept(OrderId),
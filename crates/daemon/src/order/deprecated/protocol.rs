@@ -234,3 +234,36 @@ impl TryFrom<TakerMessage> for SetupMsg {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xtra_libp2p::wire_fixtures::Fixtures;
+
+    const FIXTURES: Fixtures = Fixtures::new("./src/order/wire_fixtures/1.0.0");
+
+    #[test]
+    fn place_order_matches_recording() {
+        let msg = FIXTURES.round_trip::<TakerMessage>("place_order.json");
+
+        assert!(matches!(msg, TakerMessage::PlaceOrder { .. }));
+    }
+
+    #[test]
+    fn decisions_match_recording() {
+        let accept = FIXTURES.round_trip::<MakerMessage>("decision_accept.json");
+        let reject = FIXTURES.round_trip::<MakerMessage>("decision_reject.json");
+
+        assert!(matches!(accept, MakerMessage::Decision(Decision::Accept)));
+        assert!(matches!(reject, MakerMessage::Decision(Decision::Reject)));
+    }
+
+    #[test]
+    fn setup_messages_match_recording() {
+        let msg1 = FIXTURES.round_trip::<MakerMessage>("setup_msg1.json");
+        let msg3 = FIXTURES.round_trip::<TakerMessage>("setup_msg3.json");
+
+        SetupMsg::try_from(msg1).unwrap().try_into_msg1().unwrap();
+        SetupMsg::try_from(msg3).unwrap().try_into_msg3().unwrap();
+    }
+}
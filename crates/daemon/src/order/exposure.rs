@@ -0,0 +1,122 @@
+//! Open exposure of the maker towards a single taker.
+//!
+//! The maker can limit how many contracts a single taker may hold open at the same time. The
+//! exposure is the sum of the quantities of all CFDs with the taker that are either in contract
+//! setup or open, per contract symbol because contracts of different symbols are not comparable.
+
+use anyhow::Result;
+use futures::StreamExt;
+use model::libp2p::PeerId;
+use model::CfdEvent;
+use model::ContractSymbol;
+use model::Contracts;
+use model::EventKind;
+
+/// Sum of the quantities of all CFDs with the taker in `contract_symbol` that did not fail or
+/// close.
+pub(crate) async fn open_contracts(
+    db: &sqlite_db::Connection,
+    peer_id: PeerId,
+    contract_symbol: ContractSymbol,
+) -> Result<Contracts> {
+    let mut stream = db.load_all_open_cfds::<Cfd>(());
+
+    let mut open_contracts = Contracts::ZERO;
+    while let Some(cfd) = stream.next().await {
+        let cfd = cfd?;
+
+        if cfd.counterparty_peer_id == Some(peer_id)
+            && cfd.contract_symbol == contract_symbol
+            && !cfd.is_done
+        {
+            open_contracts = open_contracts + cfd.quantity;
+        }
+    }
+
+    Ok(open_contracts)
+}
+
+/// Read-model of the CFD for computing the exposure towards a taker.
+#[derive(Clone, Copy)]
+struct Cfd {
+    quantity: Contracts,
+    contract_symbol: ContractSymbol,
+    counterparty_peer_id: Option<PeerId>,
+    /// Whether the CFD failed or was settled, but was not archived yet
+    is_done: bool,
+    version: u32,
+}
+
+impl sqlite_db::CfdAggregate for Cfd {
+    type CtorArgs = ();
+
+    fn new(_: Self::CtorArgs, cfd: sqlite_db::Cfd) -> Self {
+        Self {
+            quantity: cfd.quantity,
+            contract_symbol: cfd.contract_symbol,
+            counterparty_peer_id: cfd.counterparty_peer_id,
+            is_done: false,
+            version: 0,
+        }
+    }
+
+    fn apply(self, event: CfdEvent) -> Self {
+        self.apply(event)
+    }
+
+    fn version(&self) -> u32 {
+        self.version
+    }
+}
+
+impl Cfd {
+    fn apply(mut self, event: CfdEvent) -> Self {
+        self.version += 1;
+
+        use EventKind::*;
+        match event.event {
+            ContractSetupFailed
+            | OfferRejected
+            | CollaborativeSettlementConfirmed
+            | CetConfirmed
+            | RefundConfirmed => Self {
+                is_done: true,
+                ..self
+            },
+            _ => self,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::OrderId;
+
+    #[test]
+    fn given_rejected_order_then_cfd_is_done() {
+        let cfd = dummy_cfd().apply(CfdEvent::new(OrderId::default(), EventKind::OfferRejected));
+
+        assert!(cfd.is_done);
+    }
+
+    #[test]
+    fn given_contract_setup_started_then_cfd_is_not_done() {
+        let cfd = dummy_cfd().apply(CfdEvent::new(
+            OrderId::default(),
+            EventKind::ContractSetupStarted,
+        ));
+
+        assert!(!cfd.is_done);
+    }
+
+    fn dummy_cfd() -> Cfd {
+        Cfd {
+            quantity: Contracts::new(100),
+            contract_symbol: ContractSymbol::BtcUsd,
+            counterparty_peer_id: None,
+            is_done: false,
+            version: 0,
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! Grace period after which the maker rejects orders it did not decide on.
+//!
+//! Orders are neither accepted nor rejected automatically; they wait for the operator. If a grace
+//! period is configured for the contract symbol of an order, the order is rejected as timed out
+//! once it was pending for longer than that.
+
+use anyhow::Context;
+use anyhow::Result;
+use model::ContractSymbol;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+use strum::IntoEnumIterator;
+
+/// Grace period of pending orders, parsed from `<minutes>` for all contract symbols or
+/// `<symbol>=<minutes>` for a single one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingOrderTimeout {
+    pub contract_symbol: Option<ContractSymbol>,
+    pub timeout: Duration,
+}
+
+impl FromStr for PendingOrderTimeout {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (contract_symbol, minutes) = match s.split_once('=') {
+            Some((symbol, minutes)) => {
+                let contract_symbol = ContractSymbol::iter()
+                    .find(|candidate| candidate.to_string().eq_ignore_ascii_case(symbol))
+                    .with_context(|| format!("Unknown contract symbol: {symbol}"))?;
+
+                (Some(contract_symbol), minutes)
+            }
+            None => (None, s),
+        };
+
+        let minutes = minutes
+            .parse::<u64>()
+            .with_context(|| format!("Invalid number of minutes: {minutes}"))?;
+
+        Ok(Self {
+            contract_symbol,
+            timeout: Duration::from_secs(minutes * 60),
+        })
+    }
+}
+
+/// The grace periods of pending orders per contract symbol.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PendingOrderTimeouts {
+    default: Option<Duration>,
+    per_symbol: HashMap<ContractSymbol, Duration>,
+}
+
+impl PendingOrderTimeouts {
+    /// Grace periods given for a single contract symbol take precedence over the ones given for all
+    /// contract symbols; later ones take precedence over earlier ones.
+    pub fn new(timeouts: &[PendingOrderTimeout]) -> Self {
+        let mut pending_order_timeouts = Self::default();
+
+        for timeout in timeouts {
+            match timeout.contract_symbol {
+                Some(contract_symbol) => {
+                    pending_order_timeouts
+                        .per_symbol
+                        .insert(contract_symbol, timeout.timeout);
+                }
+                None => pending_order_timeouts.default = Some(timeout.timeout),
+            }
+        }
+
+        pending_order_timeouts
+    }
+
+    /// The grace period of orders of the given contract symbol, `None` if they wait for the
+    /// operator indefinitely.
+    pub fn get(&self, contract_symbol: ContractSymbol) -> Option<Duration> {
+        self.per_symbol
+            .get(&contract_symbol)
+            .copied()
+            .or(self.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_pending_order_timeout() {
+        assert_eq!(
+            "5".parse::<PendingOrderTimeout>().unwrap(),
+            PendingOrderTimeout {
+                contract_symbol: None,
+                timeout: Duration::from_secs(300),
+            }
+        );
+        assert_eq!(
+            "ethusd=2".parse::<PendingOrderTimeout>().unwrap(),
+            PendingOrderTimeout {
+                contract_symbol: Some(ContractSymbol::EthUsd),
+                timeout: Duration::from_secs(120),
+            }
+        );
+        assert!("DOGEUSD=2".parse::<PendingOrderTimeout>().is_err());
+        assert!("BTCUSD=2m".parse::<PendingOrderTimeout>().is_err());
+    }
+
+    #[test]
+    fn timeout_of_symbol_takes_precedence() {
+        let timeouts =
+            PendingOrderTimeouts::new(&["BTCUSD=2".parse().unwrap(), "10".parse().unwrap()]);
+
+        assert_eq!(
+            timeouts.get(ContractSymbol::BtcUsd),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            timeouts.get(ContractSymbol::EthUsd),
+            Some(Duration::from_secs(600))
+        );
+    }
+
+    #[test]
+    fn given_no_timeouts_then_orders_wait_indefinitely() {
+        let timeouts = PendingOrderTimeouts::new(&[]);
+
+        assert_eq!(timeouts.get(ContractSymbol::BtcUsd), None);
+    }
+}
@@ -0,0 +1,325 @@
+//! Short-lived reservations of the maker's liquidity for orders.
+//!
+//! When several takers take offers at the same time, the unlocked balance of the maker may not
+//! cover the margin of all contract setups and the late ones fail when building the lock
+//! transaction. A taker therefore first reserves the quantity it wants to order. The maker grants
+//! the reservation if its unlocked balance covers the margin on top of the margin of all other
+//! reservations and holds it for [`RESERVATION_TTL`]. An order placed with a granted reservation is
+//! not rejected because of concurrent orders; orders without a reservation are only accepted if
+//! they do not eat into reserved liquidity.
+
+use bdk::bitcoin::Amount;
+use model::Contracts;
+use model::OfferId;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+use uuid::Uuid;
+use xtra_libp2p::libp2p::PeerId;
+
+/// How long a granted reservation is held for the order of the taker
+pub const RESERVATION_TTL: Duration = Duration::from_secs(30);
+
+const OUTCOME_LABEL: &str = "outcome";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ReservationId(Uuid);
+
+impl ReservationId {
+    fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl fmt::Display for ReservationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.hyphenated().fmt(f)
+    }
+}
+
+/// Reservations of the maker's liquidity, shared between the order protocol handlers.
+#[derive(Clone, Default)]
+pub(crate) struct Reservations {
+    inner: Arc<Mutex<HashMap<ReservationId, Reservation>>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Reservation {
+    peer_id: PeerId,
+    offer_id: OfferId,
+    quantity: Contracts,
+    margin: Amount,
+    /// `None` once the order was placed, the margin stays reserved until the contract setup ended
+    expires_at: Option<Instant>,
+}
+
+impl Reservations {
+    /// Reserve `margin` for an order of `quantity` contracts of the given offer.
+    ///
+    /// Returns `None` if `unlocked_balance` does not cover the margin on top of the margin of all
+    /// other reservations.
+    pub(crate) fn reserve(
+        &self,
+        peer_id: PeerId,
+        offer_id: OfferId,
+        quantity: Contracts,
+        margin: Amount,
+        unlocked_balance: Amount,
+    ) -> Option<ReservationId> {
+        self.reserve_at(
+            Instant::now(),
+            peer_id,
+            (offer_id, quantity, margin),
+            unlocked_balance,
+        )
+    }
+
+    fn reserve_at(
+        &self,
+        now: Instant,
+        peer_id: PeerId,
+        (offer_id, quantity, margin): (OfferId, Contracts, Amount),
+        unlocked_balance: Amount,
+    ) -> Option<ReservationId> {
+        let mut reservations = self.lock(now);
+
+        if reserved(&reservations) + margin > unlocked_balance {
+            RESERVATIONS_COUNTER.with_label_values(&["denied"]).inc();
+            return None;
+        }
+
+        let id = ReservationId::new();
+        reservations.insert(
+            id,
+            Reservation {
+                peer_id,
+                offer_id,
+                quantity,
+                margin,
+                expires_at: Some(now + RESERVATION_TTL),
+            },
+        );
+
+        RESERVATIONS_COUNTER.with_label_values(&["granted"]).inc();
+        RESERVED_MARGIN_GAUGE.set(reserved(&reservations).as_sat() as i64);
+
+        Some(id)
+    }
+
+    /// Redeem the reservation with an order of `quantity` contracts of the given offer.
+    ///
+    /// Returns `None` if the taker holds no valid reservation covering the order. The margin stays
+    /// reserved for as long as the returned [`Claim`] is alive.
+    pub(crate) fn redeem(
+        &self,
+        id: ReservationId,
+        peer_id: PeerId,
+        offer_id: OfferId,
+        quantity: Contracts,
+    ) -> Option<Claim> {
+        self.redeem_at(Instant::now(), id, peer_id, offer_id, quantity)
+    }
+
+    fn redeem_at(
+        &self,
+        now: Instant,
+        id: ReservationId,
+        peer_id: PeerId,
+        offer_id: OfferId,
+        quantity: Contracts,
+    ) -> Option<Claim> {
+        let mut reservations = self.lock(now);
+
+        let reservation = reservations.get_mut(&id)?;
+        if reservation.peer_id != peer_id
+            || reservation.offer_id != offer_id
+            || reservation.expires_at.is_none()
+            || quantity > reservation.quantity
+        {
+            return None;
+        }
+
+        reservation.expires_at = None;
+        RESERVATIONS_COUNTER.with_label_values(&["redeemed"]).inc();
+
+        Some(Claim {
+            reservations: self.clone(),
+            id,
+        })
+    }
+
+    /// The margin reserved for orders.
+    pub(crate) fn reserved(&self) -> Amount {
+        reserved(&self.lock(Instant::now()))
+    }
+
+    /// Lock the reservations after dropping the expired ones.
+    fn lock(&self, now: Instant) -> std::sync::MutexGuard<'_, HashMap<ReservationId, Reservation>> {
+        let mut reservations = self.inner.lock().expect("lock not to be poisoned");
+
+        let before = reservations.len();
+        reservations.retain(|_, reservation| match reservation.expires_at {
+            Some(expires_at) => now < expires_at,
+            None => true,
+        });
+        let expired = before - reservations.len();
+
+        if expired > 0 {
+            RESERVATIONS_COUNTER
+                .with_label_values(&["expired"])
+                .inc_by(expired as u64);
+            RESERVED_MARGIN_GAUGE.set(reserved(&reservations).as_sat() as i64);
+        }
+
+        reservations
+    }
+}
+
+fn reserved(reservations: &HashMap<ReservationId, Reservation>) -> Amount {
+    reservations
+        .values()
+        .fold(Amount::ZERO, |sum, reservation| sum + reservation.margin)
+}
+
+/// Keeps the margin of a redeemed reservation reserved until dropped.
+pub(crate) struct Claim {
+    reservations: Reservations,
+    id: ReservationId,
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        let mut reservations = self
+            .reservations
+            .inner
+            .lock()
+            .expect("lock not to be poisoned");
+
+        reservations.remove(&self.id);
+        RESERVED_MARGIN_GAUGE.set(reserved(&reservations).as_sat() as i64);
+    }
+}
+
+static RESERVATIONS_COUNTER: conquer_once::Lazy<prometheus::IntCounterVec> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_int_counter_vec!(
+            "order_reservations_total",
+            "The number of order reservations by outcome.",
+            &[OUTCOME_LABEL]
+        )
+        .unwrap()
+    });
+
+static RESERVED_MARGIN_GAUGE: conquer_once::Lazy<prometheus::IntGauge> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_int_gauge!(
+            "order_reserved_margin_sats",
+            "The margin of the maker reserved for orders in satoshis."
+        )
+        .unwrap()
+    });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_reserved_liquidity_then_concurrent_reservation_is_denied() {
+        let reservations = Reservations::default();
+        let now = Instant::now();
+        let offer_id = OfferId::default();
+        let unlocked_balance = Amount::from_sat(150_000);
+
+        let first = reservations.reserve_at(
+            now,
+            PeerId::random(),
+            (offer_id, Contracts::new(100), Amount::from_sat(100_000)),
+            unlocked_balance,
+        );
+        let second = reservations.reserve_at(
+            now,
+            PeerId::random(),
+            (offer_id, Contracts::new(100), Amount::from_sat(100_000)),
+            unlocked_balance,
+        );
+
+        assert!(first.is_some());
+        assert!(second.is_none());
+        assert_eq!(reservations.reserved(), Amount::from_sat(100_000));
+    }
+
+    #[test]
+    fn given_expired_reservation_then_it_cannot_be_redeemed_and_frees_liquidity() {
+        let reservations = Reservations::default();
+        let now = Instant::now();
+        let peer_id = PeerId::random();
+        let offer_id = OfferId::default();
+        let unlocked_balance = Amount::from_sat(100_000);
+
+        let id = reservations
+            .reserve_at(
+                now,
+                peer_id,
+                (offer_id, Contracts::new(100), Amount::from_sat(100_000)),
+                unlocked_balance,
+            )
+            .unwrap();
+
+        let later = now + RESERVATION_TTL;
+        assert!(reservations
+            .redeem_at(later, id, peer_id, offer_id, Contracts::new(100))
+            .is_none());
+        assert!(reservations
+            .reserve_at(
+                later,
+                PeerId::random(),
+                (offer_id, Contracts::new(100), Amount::from_sat(100_000)),
+                unlocked_balance,
+            )
+            .is_some());
+    }
+
+    #[test]
+    fn redeemed_reservation_is_held_until_claim_is_dropped() {
+        let reservations = Reservations::default();
+        let now = Instant::now();
+        let peer_id = PeerId::random();
+        let offer_id = OfferId::default();
+
+        let id = reservations
+            .reserve_at(
+                now,
+                peer_id,
+                (offer_id, Contracts::new(100), Amount::from_sat(100_000)),
+                Amount::from_sat(100_000),
+            )
+            .unwrap();
+
+        assert!(reservations
+            .redeem_at(now, id, PeerId::random(), offer_id, Contracts::new(100))
+            .is_none());
+        assert!(reservations
+            .redeem_at(now, id, peer_id, offer_id, Contracts::new(200))
+            .is_none());
+
+        let claim = reservations
+            .redeem_at(now, id, peer_id, offer_id, Contracts::new(50))
+            .unwrap();
+        assert!(reservations
+            .redeem_at(now, id, peer_id, offer_id, Contracts::new(50))
+            .is_none());
+        assert_eq!(
+            reserved(&reservations.lock(now + RESERVATION_TTL)),
+            Amount::from_sat(100_000)
+        );
+
+        drop(claim);
+
+        assert_eq!(reservations.reserved(), Amount::ZERO);
+    }
+}
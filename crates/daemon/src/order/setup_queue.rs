@@ -0,0 +1,152 @@
+//! Limit on the number of contract setups the maker runs concurrently.
+//!
+//! Building and signing the CETs of a contract setup is CPU-intensive; too many simultaneous
+//! setups starve the rest of the daemon. Setups beyond the limit wait in a FIFO queue until a
+//! running setup finishes.
+
+use anyhow::Context;
+use anyhow::Result;
+use model::OrderId;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Instant;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+
+pub const DEFAULT_MAX_CONCURRENT_SETUPS: usize = 10;
+
+#[derive(Clone)]
+pub struct SetupQueue {
+    semaphore: Arc<Semaphore>,
+    waiting: Arc<Mutex<VecDeque<OrderId>>>,
+}
+
+impl SetupQueue {
+    pub fn new(max_concurrent_setups: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrent_setups)),
+            waiting: Arc::default(),
+        }
+    }
+
+    /// Wait until the contract setup of the given order may run.
+    ///
+    /// The setup may run for as long as the returned permit is alive.
+    pub async fn acquire(&self, order_id: OrderId) -> Result<OwnedSemaphorePermit> {
+        let _waiting = Waiting::new(self.waiting.clone(), order_id);
+        let queued_at = Instant::now();
+
+        let permit = self
+            .semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .context("Setup queue closed")?;
+
+        WAIT_TIME_HISTOGRAM.observe(queued_at.elapsed().as_secs_f64());
+
+        Ok(permit)
+    }
+
+    /// Position of the order in the queue, starting at 1 for the setup that runs next.
+    ///
+    /// Returns `None` if the order is not waiting.
+    pub fn position(&self, order_id: OrderId) -> Option<usize> {
+        self.waiting
+            .lock()
+            .expect("lock not to be poisoned")
+            .iter()
+            .position(|id| *id == order_id)
+            .map(|index| index + 1)
+    }
+}
+
+/// Keeps the order in the queue until dropped.
+struct Waiting {
+    waiting: Arc<Mutex<VecDeque<OrderId>>>,
+    order_id: OrderId,
+}
+
+impl Waiting {
+    fn new(waiting: Arc<Mutex<VecDeque<OrderId>>>, order_id: OrderId) -> Self {
+        {
+            let mut queue = waiting.lock().expect("lock not to be poisoned");
+            queue.push_back(order_id);
+            QUEUE_DEPTH_GAUGE.set(queue.len() as i64);
+        }
+
+        Self { waiting, order_id }
+    }
+}
+
+impl Drop for Waiting {
+    fn drop(&mut self) {
+        let mut queue = self.waiting.lock().expect("lock not to be poisoned");
+        queue.retain(|id| *id != self.order_id);
+        QUEUE_DEPTH_GAUGE.set(queue.len() as i64);
+    }
+}
+
+static QUEUE_DEPTH_GAUGE: conquer_once::Lazy<prometheus::IntGauge> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_int_gauge!(
+            "contract_setup_queue_depth",
+            "The number of contract setups waiting for a free slot."
+        )
+        .unwrap()
+    });
+
+static WAIT_TIME_HISTOGRAM: conquer_once::Lazy<prometheus::Histogram> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_histogram!(
+            "contract_setup_queue_wait_seconds",
+            "The time contract setups waited for a free slot.",
+            vec![0.1, 1.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0]
+        )
+        .unwrap()
+    });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+
+    #[tokio::test]
+    async fn given_no_free_slot_then_setup_waits_in_queue() {
+        let queue = SetupQueue::new(1);
+        let running = OrderId::default();
+        let queued = OrderId::default();
+
+        let permit = queue.acquire(running).await.unwrap();
+        let mut acquire = Box::pin(queue.acquire(queued));
+
+        assert!((&mut acquire).now_or_never().is_none());
+        assert_eq!(queue.position(running), None);
+        assert_eq!(queue.position(queued), Some(1));
+
+        drop(permit);
+        let _permit = acquire.await.unwrap();
+
+        assert_eq!(queue.position(queued), None);
+    }
+
+    #[tokio::test]
+    async fn given_setup_gives_up_then_it_leaves_queue() {
+        let queue = SetupQueue::new(1);
+        let first = OrderId::default();
+        let second = OrderId::default();
+
+        let _permit = queue.acquire(OrderId::default()).await.unwrap();
+        let mut first_acquire = Box::pin(queue.acquire(first));
+        let mut second_acquire = Box::pin(queue.acquire(second));
+        assert!((&mut first_acquire).now_or_never().is_none());
+        assert!((&mut second_acquire).now_or_never().is_none());
+        assert_eq!(queue.position(second), Some(2));
+
+        drop(first_acquire);
+
+        assert_eq!(queue.position(first), None);
+        assert_eq!(queue.position(second), Some(1));
+    }
+}
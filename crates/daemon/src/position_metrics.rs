@@ -3,6 +3,7 @@ use async_trait::async_trait;
 use bdk::bitcoin::Amount;
 use futures::StreamExt;
 use model::calculate_margin;
+use model::symbols;
 use model::CfdEvent;
 use model::ClosedCfd;
 use model::ContractSymbol;
@@ -16,9 +17,13 @@ use model::OrderId;
 use model::Position;
 use model::Role;
 use model::Settlement;
+use model::Timestamp;
+use serde::Serialize;
 use sqlite_db;
 use std::collections::HashMap;
 use strum::IntoEnumIterator;
+use time::Date;
+use time::OffsetDateTime;
 use xtra_productivity::xtra_productivity;
 use xtras::SendAsyncNext;
 
@@ -31,6 +36,7 @@ pub struct Actor {
 #[derive(Default)]
 struct State {
     cfds: HashMap<OrderId, Cfd>,
+    market: MarketStats,
 }
 
 impl Actor {
@@ -71,11 +77,13 @@ impl Actor {
             cfds.insert(cfd.id, cfd);
         }
 
+        self.state.market = MarketStats::from_cfds(cfds.values(), OffsetDateTime::now_utc().date());
         self.state.cfds = cfds;
 
         for symbol in ContractSymbol::iter() {
             metrics::update_position_metrics(&self.state.cfds, symbol);
         }
+        metrics::update_market_metrics(&self.state.market);
     }
 
     async fn handle(&mut self, msg: CfdChanged) {
@@ -87,18 +95,146 @@ impl Actor {
         for symbol in ContractSymbol::iter() {
             metrics::update_position_metrics(&self.state.cfds, symbol)
         }
+        metrics::update_market_metrics(&self.state.market);
+    }
+
+    async fn handle(&mut self, _: GetMarketStats) -> MarketStats {
+        let mut market = self.state.market.clone();
+        market.roll_over(OffsetDateTime::now_utc().date());
+
+        market
     }
 }
 
 impl State {
     async fn update_cfd(&mut self, db: &sqlite_db::Connection, id: OrderId) -> Result<()> {
         let cfd = db.load_open_cfd(id, ()).await?;
-        self.cfds.insert(id, cfd);
+        let previous = self.cfds.insert(id, cfd);
+
+        let was_open = previous
+            .as_ref()
+            .map_or(false, |previous| previous.state == AggregatedState::Open);
+        if !was_open && cfd.state == AggregatedState::Open {
+            metrics::record_opened_cfd(&cfd);
+        }
+
+        self.market
+            .record(previous.as_ref(), &cfd, OffsetDateTime::now_utc().date());
 
         Ok(())
     }
 }
 
+/// Get the aggregate open interest and the volume traded today.
+#[derive(Clone, Copy)]
+pub struct GetMarketStats;
+
+/// Aggregate market statistics across all takers.
+///
+/// Updated incrementally as CFDs open and close instead of being recomputed from all CFDs.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MarketStats {
+    /// The UTC day the volume refers to
+    #[serde(with = "date")]
+    pub day: Date,
+    pub symbols: HashMap<ContractSymbol, SymbolStats>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SymbolStats {
+    /// Number of contracts in open CFDs
+    pub open_interest: Contracts,
+    /// Number of contracts of CFDs opened during the day
+    pub daily_volume: Contracts,
+}
+
+impl Default for SymbolStats {
+    fn default() -> Self {
+        Self {
+            open_interest: Contracts::ZERO,
+            daily_volume: Contracts::ZERO,
+        }
+    }
+}
+
+impl Default for MarketStats {
+    fn default() -> Self {
+        Self::new(OffsetDateTime::now_utc().date())
+    }
+}
+
+impl MarketStats {
+    fn new(day: Date) -> Self {
+        Self {
+            day,
+            symbols: ContractSymbol::iter()
+                .map(|symbol| (symbol, SymbolStats::default()))
+                .collect(),
+        }
+    }
+
+    fn from_cfds<'a>(cfds: impl Iterator<Item = &'a Cfd>, today: Date) -> Self {
+        let mut stats = Self::new(today);
+
+        for cfd in cfds {
+            let symbol = stats.symbols.entry(cfd.contract_symbol).or_default();
+
+            if cfd.state == AggregatedState::Open {
+                symbol.open_interest = symbol.open_interest + cfd.quantity;
+            }
+            if cfd.opened_on() == Some(today) {
+                symbol.daily_volume = symbol.daily_volume + cfd.quantity;
+            }
+        }
+
+        stats
+    }
+
+    /// Account for the transition of a CFD from its `previous` state.
+    fn record(&mut self, previous: Option<&Cfd>, cfd: &Cfd, today: Date) {
+        self.roll_over(today);
+
+        let was_open = previous.map_or(false, |previous| previous.state == AggregatedState::Open);
+        let is_open = cfd.state == AggregatedState::Open;
+        let symbol = self.symbols.entry(cfd.contract_symbol).or_default();
+
+        match (was_open, is_open) {
+            (false, true) => {
+                symbol.open_interest = symbol.open_interest + cfd.quantity;
+                symbol.daily_volume = symbol.daily_volume + cfd.quantity;
+            }
+            (true, false) => {
+                symbol.open_interest = symbol.open_interest - cfd.quantity;
+            }
+            (true, true) | (false, false) => {}
+        }
+    }
+
+    /// Start counting the volume from zero if a new day began.
+    fn roll_over(&mut self, today: Date) {
+        if self.day == today {
+            return;
+        }
+
+        self.day = today;
+        for symbol in self.symbols.values_mut() {
+            symbol.daily_volume = Contracts::ZERO;
+        }
+    }
+}
+
+mod date {
+    use serde::Serializer;
+    use time::Date;
+
+    pub fn serialize<S>(date: &Date, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(date)
+    }
+}
+
 #[derive(Debug)]
 struct Initialize;
 
@@ -114,14 +250,28 @@ pub struct Cfd {
     quantity: Contracts,
     margin: Amount,
     margin_counterparty: Amount,
+    /// The opening fee paid to us, zero if we are the taker
+    opening_fee_earned: Amount,
 
     state: AggregatedState,
     counterparty_network_identity: Identity,
 
     contract_symbol: ContractSymbol,
+    /// When the contract setup completed
+    opened_at: Option<Timestamp>,
     version: u32,
 }
 
+impl Cfd {
+    fn opened_on(&self) -> Option<Date> {
+        let opened_at = self.opened_at?;
+
+        OffsetDateTime::from_unix_timestamp(opened_at.seconds())
+            .ok()
+            .map(|opened_at| opened_at.date())
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum AggregatedState {
     /// Used when the CFD is new or in contract setup
@@ -142,18 +292,19 @@ impl sqlite_db::CfdAggregate for Cfd {
             Role::Taker => (cfd.taker_leverage, Leverage::ONE),
         };
 
-        let margin = calculate_margin(
-            cfd.contract_symbol,
-            cfd.initial_price,
-            cfd.quantity,
-            our_leverage,
-        );
+        let payout_curve = symbols::payout_curve(cfd.contract_symbol, cfd.quanto_multiplier);
+
+        let margin = calculate_margin(payout_curve, cfd.initial_price, cfd.quantity, our_leverage);
         let margin_counterparty = calculate_margin(
-            cfd.contract_symbol,
+            payout_curve,
             cfd.initial_price,
             cfd.quantity,
             counterparty_leverage,
         );
+        let opening_fee_earned = match cfd.role {
+            Role::Maker => cfd.opening_fee.to_inner(),
+            Role::Taker => Amount::ZERO,
+        };
 
         Self {
             id: cfd.id,
@@ -161,9 +312,11 @@ impl sqlite_db::CfdAggregate for Cfd {
             quantity: cfd.quantity,
             margin,
             margin_counterparty,
+            opening_fee_earned,
             state: AggregatedState::New,
             counterparty_network_identity: cfd.counterparty_network_identity,
             contract_symbol: cfd.contract_symbol,
+            opened_at: None,
             version: 0,
         }
     }
@@ -180,6 +333,7 @@ impl sqlite_db::CfdAggregate for Cfd {
 impl Cfd {
     fn apply(mut self, event: CfdEvent) -> Self {
         self.version += 1;
+        let timestamp = event.timestamp;
         use EventKind::*;
         match event.event {
             ContractSetupStarted => Self {
@@ -188,6 +342,7 @@ impl Cfd {
             },
             ContractSetupCompleted { .. } | LockConfirmed => Self {
                 state: AggregatedState::Open,
+                opened_at: self.opened_at.or(Some(timestamp)),
                 ..self
             },
             ContractSetupFailed => Self {
@@ -202,14 +357,17 @@ impl Cfd {
             | RolloverAccepted
             | RolloverRejected
             | RolloverCompleted { .. }
-            | RolloverFailed => Self {
+            | RolloverFailed
+            | RolloverFundingRateRejected { .. }
+            | SettlementProposedByMaker { .. } => Self {
                 // should still be open
                 ..self
             },
             CollaborativeSettlementStarted { .. }
             | CollaborativeSettlementProposalAccepted
             | CollaborativeSettlementRejected
-            | CollaborativeSettlementFailed => Self {
+            | CollaborativeSettlementFailed
+            | PriceLevelsSet { .. } => Self {
                 // should still be open
                 ..self
             },
@@ -257,7 +415,9 @@ impl Cfd {
                 state: AggregatedState::Closed,
                 ..self
             },
-            OracleAttestedPriorCetTimelock { .. } | OracleAttestedPostCetTimelock { .. } => Self {
+            OracleAttestedPriorCetTimelock { .. }
+            | OracleAttestedPostCetTimelock { .. }
+            | CetPublishedByCounterparty { .. } => Self {
                 // we know the closing price already and can assume that the cfd will be closed
                 // accordingly
                 state: AggregatedState::Closed,
@@ -279,6 +439,7 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
             taker_leverage,
             initial_price,
             contract_symbol,
+            creation_timestamp,
             ..
         } = closed_cfd;
 
@@ -306,9 +467,13 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
             quantity,
             margin,
             margin_counterparty,
+            // Closed and failed CFDs do not open anymore, the fee is only accounted for when
+            // opening
+            opening_fee_earned: Amount::ZERO,
             state,
             counterparty_network_identity,
             contract_symbol,
+            opened_at: Some(creation_timestamp),
             version: 0,
         }
     }
@@ -353,17 +518,24 @@ impl sqlite_db::FailedCfdAggregate for Cfd {
             quantity,
             margin,
             margin_counterparty,
+            // Closed and failed CFDs do not open anymore, the fee is only accounted for when
+            // opening
+            opening_fee_earned: Amount::ZERO,
             state,
             counterparty_network_identity,
             contract_symbol: cfd.contract_symbol,
+            opened_at: None,
             version: 0,
         }
     }
 }
 
+pub(crate) use metrics::persisted_counters;
+
 mod metrics {
     use crate::position_metrics::AggregatedState;
     use crate::position_metrics::Cfd;
+    use crate::position_metrics::MarketStats;
     use bdk::bitcoin::Amount;
     use itertools::Itertools;
     use model::ContractSymbol;
@@ -438,6 +610,92 @@ mod metrics {
             .unwrap()
         });
 
+    static OPEN_INTEREST_GAUGE: conquer_once::Lazy<prometheus::GaugeVec> =
+        conquer_once::Lazy::new(|| {
+            prometheus::register_gauge_vec!(
+                "market_open_interest_contracts",
+                "Total number of contracts in open positions on ItchySats.",
+                &[SYMBOL_LABEL]
+            )
+            .unwrap()
+        });
+
+    static DAILY_VOLUME_GAUGE: conquer_once::Lazy<prometheus::GaugeVec> =
+        conquer_once::Lazy::new(|| {
+            prometheus::register_gauge_vec!(
+                "market_daily_volume_contracts",
+                "Number of contracts of positions opened on ItchySats during the current UTC day.",
+                &[SYMBOL_LABEL]
+            )
+            .unwrap()
+        });
+
+    static VOLUME_COUNTER: conquer_once::Lazy<prometheus::CounterVec> =
+        conquer_once::Lazy::new(|| {
+            prometheus::register_counter_vec!(
+                "cfd_volume_contracts_total",
+                "Number of contracts of all positions ever opened on ItchySats.",
+                &[SYMBOL_LABEL]
+            )
+            .unwrap()
+        });
+
+    static OPENING_FEES_EARNED_COUNTER: conquer_once::Lazy<prometheus::CounterVec> =
+        conquer_once::Lazy::new(|| {
+            prometheus::register_counter_vec!(
+                "cfd_opening_fees_earned_satoshis_total",
+                "Opening fees earned from all positions ever opened on ItchySats.",
+                &[SYMBOL_LABEL]
+            )
+            .unwrap()
+        });
+
+    /// The counters which are carried over across restarts.
+    pub fn persisted_counters() -> [&'static prometheus::CounterVec; 2] {
+        [&VOLUME_COUNTER, &OPENING_FEES_EARNED_COUNTER]
+    }
+
+    pub fn record_opened_cfd(cfd: &Cfd) {
+        let symbol = cfd.contract_symbol.to_string();
+        let labels = HashMap::from([(SYMBOL_LABEL, symbol.as_str())]);
+
+        VOLUME_COUNTER
+            .with(&labels)
+            .inc_by(cfd.quantity.into_decimal().to_f64().unwrap_or_default());
+        OPENING_FEES_EARNED_COUNTER
+            .with(&labels)
+            .inc_by(cfd.opening_fee_earned.to_sat() as f64);
+    }
+
+    pub fn update_market_metrics(market: &MarketStats) {
+        for (symbol, stats) in market.symbols.iter() {
+            OPEN_INTEREST_GAUGE
+                .with(&HashMap::from([(
+                    SYMBOL_LABEL,
+                    symbol.to_string().as_str(),
+                )]))
+                .set(
+                    stats
+                        .open_interest
+                        .into_decimal()
+                        .to_f64()
+                        .unwrap_or_default(),
+                );
+            DAILY_VOLUME_GAUGE
+                .with(&HashMap::from([(
+                    SYMBOL_LABEL,
+                    symbol.to_string().as_str(),
+                )]))
+                .set(
+                    stats
+                        .daily_volume
+                        .into_decimal()
+                        .to_f64()
+                        .unwrap_or_default(),
+                );
+        }
+    }
+
     pub fn update_position_metrics(cfds: &HashMap<OrderId, Cfd>, symbol: ContractSymbol) {
         let cfds = cfds
             .iter()
@@ -582,3 +840,74 @@ mod metrics {
             .fold(Contracts::ZERO, |sum, cfd| cfd.quantity + sum)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    #[test]
+    fn given_cfd_opens_and_closes_then_open_interest_returns_and_volume_remains() {
+        let today = date!(2022 - 10 - 16);
+        let mut market = MarketStats::new(today);
+
+        let new = dummy_cfd(AggregatedState::New);
+        let open = dummy_cfd(AggregatedState::Open);
+        let closed = dummy_cfd(AggregatedState::Closed);
+
+        market.record(None, &new, today);
+        market.record(Some(&new), &open, today);
+        market.record(Some(&open), &open, today);
+        assert_eq!(
+            market.symbols[&ContractSymbol::BtcUsd],
+            SymbolStats {
+                open_interest: Contracts::new(100),
+                daily_volume: Contracts::new(100),
+            }
+        );
+
+        market.record(Some(&open), &closed, today);
+        assert_eq!(
+            market.symbols[&ContractSymbol::BtcUsd],
+            SymbolStats {
+                open_interest: Contracts::ZERO,
+                daily_volume: Contracts::new(100),
+            }
+        );
+    }
+
+    #[test]
+    fn given_new_day_then_volume_starts_from_zero() {
+        let mut market = MarketStats::new(date!(2022 - 10 - 16));
+        market.record(
+            Some(&dummy_cfd(AggregatedState::New)),
+            &dummy_cfd(AggregatedState::Open),
+            date!(2022 - 10 - 16),
+        );
+
+        market.roll_over(date!(2022 - 10 - 17));
+
+        assert_eq!(
+            market.symbols[&ContractSymbol::BtcUsd],
+            SymbolStats {
+                open_interest: Contracts::new(100),
+                daily_volume: Contracts::ZERO,
+            }
+        );
+    }
+
+    fn dummy_cfd(state: AggregatedState) -> Cfd {
+        Cfd {
+            id: OrderId::default(),
+            position: Position::Long,
+            quantity: Contracts::new(100),
+            margin: Amount::ZERO,
+            margin_counterparty: Amount::ZERO,
+            state,
+            counterparty_network_identity: Identity::new(x25519_dalek::PublicKey::from([0; 32])),
+            contract_symbol: ContractSymbol::BtcUsd,
+            opened_at: None,
+            version: 0,
+        }
+    }
+}
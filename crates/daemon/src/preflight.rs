@@ -0,0 +1,201 @@
+//! Pre-flight checks for contract setup.
+//!
+//! Running these checks before placing an order allows the taker to find out about problems that
+//! would otherwise only surface in the middle of the contract setup protocol.
+
+use anyhow::bail;
+use anyhow::Result;
+use bdk::bitcoin::Amount;
+use model::calculate_margin;
+use model::Contracts;
+use model::Leverage;
+use model::Offer;
+use model::OpeningFee;
+use serde::Serialize;
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Check {
+    /// The offer is known and still safe to take
+    Offer,
+    /// Quantity and leverage are within what the offer allows
+    OrderParameters,
+    /// The maker is connected
+    MakerConnectivity,
+    /// The wallet holds enough unlocked funds to cover margin and opening fee
+    WalletBalance,
+    /// The oracle announcement referenced by the offer can be fetched
+    OracleAnnouncement,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedCheck {
+    pub check: Check,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Report {
+    pub passed: Vec<Check>,
+    pub failed: Vec<FailedCheck>,
+}
+
+impl Report {
+    /// Whether all checks passed, i.e. a contract setup is expected to succeed
+    pub fn is_ok(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    pub fn record(&mut self, check: Check, result: Result<()>) {
+        match result {
+            Ok(()) => self.passed.push(check),
+            Err(e) => self.failed.push(FailedCheck {
+                check,
+                reason: format!("{e:#}"),
+            }),
+        }
+    }
+}
+
+pub fn check_offer(offer: Option<&Offer>, now: OffsetDateTime) -> Result<()> {
+    let offer = match offer {
+        Some(offer) => offer,
+        None => bail!("Offer not found in current maker offers"),
+    };
+
+    if !offer.is_safe_to_take(now) {
+        bail!("Offer is outdated");
+    }
+
+    if let Some(reopens_at) = offer
+        .trading_calendar
+        .as_ref()
+        .and_then(|calendar| calendar.reopens_at(now))
+    {
+        bail!("Market closed, it reopens at {reopens_at}");
+    }
+
+    Ok(())
+}
+
+pub fn check_order_parameters(
+    offer: &Offer,
+    quantity: Contracts,
+    leverage: Leverage,
+) -> Result<()> {
+    if quantity < offer.min_quantity || quantity > offer.max_quantity {
+        bail!(
+            "Quantity {quantity} is outside of the offered range [{}, {}]",
+            offer.min_quantity,
+            offer.max_quantity
+        );
+    }
+
+    if let Some(max) = offer.max_contracts_per_order {
+        if quantity > max {
+            bail!("Quantity {quantity} exceeds the maximum of {max} contracts per order");
+        }
+    }
+
+    if !offer.leverage_choices.contains(&leverage) {
+        bail!("Leverage {leverage} is not offered");
+    }
+
+    Ok(())
+}
+
+/// Costs of the taker upon taking an offer, excluding transaction fees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InitialCosts {
+    pub margin: Amount,
+    pub opening_fee: OpeningFee,
+}
+
+impl InitialCosts {
+    pub fn new(offer: &Offer, quantity: Contracts, leverage: Leverage) -> Self {
+        Self {
+            margin: calculate_margin(offer.contract_symbol, offer.price, quantity, leverage),
+            opening_fee: offer.opening_fee_for(quantity),
+        }
+    }
+
+    pub fn total(&self) -> Amount {
+        self.margin + self.opening_fee.to_inner()
+    }
+}
+
+/// Funds the taker has to contribute to the lock transaction, excluding transaction fees.
+pub fn required_funds(offer: &Offer, quantity: Contracts, leverage: Leverage) -> Amount {
+    InitialCosts::new(offer, quantity, leverage).total()
+}
+
+pub fn check_wallet_balance(unlocked_balance: Amount, required: Amount) -> Result<()> {
+    if unlocked_balance < required {
+        bail!("Unlocked wallet balance of {unlocked_balance} is less than the required {required}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::OpeningFeeTier;
+    use model::OpeningFeeTiers;
+
+    #[test]
+    fn given_quantity_outside_offered_range_then_order_parameters_check_fails() {
+        let offer = Offer::dummy();
+
+        assert!(check_order_parameters(&offer, Contracts::new(100), Leverage::TWO).is_ok());
+        assert!(check_order_parameters(&offer, Contracts::new(50), Leverage::TWO).is_err());
+        assert!(check_order_parameters(&offer, Contracts::new(1100), Leverage::TWO).is_err());
+    }
+
+    #[test]
+    fn given_leverage_not_offered_then_order_parameters_check_fails() {
+        let offer = Offer::dummy();
+
+        assert!(check_order_parameters(&offer, Contracts::new(100), Leverage::ONE).is_err());
+    }
+
+    #[test]
+    fn report_is_only_ok_if_no_check_failed() {
+        let mut report = Report::default();
+        report.record(Check::Offer, Ok(()));
+        assert!(report.is_ok());
+
+        report.record(
+            Check::WalletBalance,
+            check_wallet_balance(Amount::ZERO, Amount::ONE_SAT),
+        );
+        assert!(!report.is_ok());
+        assert_eq!(report.passed, vec![Check::Offer]);
+        assert_eq!(report.failed[0].check, Check::WalletBalance);
+    }
+
+    #[test]
+    fn given_opening_fee_tiers_then_required_funds_include_fee_of_quantity() {
+        let tiers = OpeningFeeTiers::new(vec![OpeningFeeTier {
+            min_quantity: Contracts::new(500),
+            flat: Amount::from_sat(1_000),
+            bps: 0,
+        }])
+        .unwrap();
+        let offer = Offer::dummy().with_opening_fee_tiers(tiers);
+
+        let below_tier = InitialCosts::new(&offer, Contracts::new(100), Leverage::TWO);
+        let in_tier = InitialCosts::new(&offer, Contracts::new(500), Leverage::TWO);
+
+        assert_eq!(below_tier.opening_fee, OpeningFee::default());
+        assert_eq!(
+            in_tier.opening_fee,
+            OpeningFee::new(Amount::from_sat(1_000))
+        );
+        assert_eq!(
+            required_funds(&offer, Contracts::new(500), Leverage::TWO),
+            in_tier.margin + Amount::from_sat(1_000)
+        );
+    }
+}
@@ -7,16 +7,46 @@ use crate::monitor::TryBroadcastTransaction;
 use crate::oracle;
 use crate::position_metrics;
 use crate::projection;
+use crate::telemetry::Telemetry;
 use anyhow::Result;
 use async_trait::async_trait;
+use bdk::bitcoin::Txid;
+use conquer_once::Lazy;
 use model::CfdEvent;
 use model::EventKind;
+use model::LedgerReason;
 use model::Role;
+use model::Timestamp;
+use prometheus::IntCounter;
 use sqlite_db;
+use std::time::Duration;
 use tracing::Instrument;
 use xtra::prelude::MessageChannel;
 use xtra_productivity::xtra_productivity;
+use xtras::SendAsyncNext;
 use xtras::SendAsyncSafe;
+use xtras::SendInterval;
+
+/// Interval at which we retry performing side effects that have not been acknowledged.
+const OUTBOX_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Delay before retrying side effects after the first failed attempt, doubled with every
+/// further failed attempt.
+const OUTBOX_INITIAL_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Upper bound of the delay between two attempts to perform side effects.
+const OUTBOX_MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// Number of failed attempts after which we give up performing the side effects of an event.
+const MAX_OUTBOX_ATTEMPTS: u32 = 10;
+
+static OUTBOX_GIVEN_UP_COUNTER: Lazy<IntCounter> = Lazy::new(|| {
+    prometheus::register_int_counter!(
+        "process_manager_outbox_entries_given_up_total",
+        "Number of events whose side effects were given up after repeated failures."
+    )
+    .unwrap()
+});
 
 pub struct Actor {
     db: sqlite_db::Connection,
@@ -29,10 +59,15 @@ pub struct Actor {
     monitor_cet_finality: MessageChannel<MonitorCetFinality, Result<()>>,
     monitor_collaborative_settlement: MessageChannel<MonitorCollaborativeSettlement, ()>,
     monitor_attestation: MessageChannel<oracle::MonitorAttestations, ()>,
+    telemetry: Option<Telemetry>,
 }
 
 pub struct Event(CfdEvent);
 
+/// Perform the side effects of all events that have not been acknowledged yet.
+#[derive(Clone, Copy)]
+struct DispatchOutbox;
+
 impl Event {
     pub fn new(event: CfdEvent) -> Self {
         Self(event)
@@ -64,6 +99,15 @@ impl Actor {
             monitor_cet_finality,
             monitor_collaborative_settlement,
             monitor_attestation,
+            telemetry: None,
+        }
+    }
+
+    /// Count the events in the telemetry of a daemon that opted in.
+    pub fn with_telemetry(self, telemetry: Telemetry) -> Self {
+        Self {
+            telemetry: Some(telemetry),
+            ..self
         }
     }
 }
@@ -73,10 +117,107 @@ impl Actor {
     fn handle(&mut self, msg: Event) -> Result<()> {
         let event = msg.0;
 
-        // 1. Safe in DB
-        self.db.append_event(event.clone()).await?;
+        // 1. Safe in DB, together with an outbox entry for the side effects
+        let outbox_id = self.db.append_event_with_outbox(event.clone()).await?;
+
+        // Counted once per event, unlike side effects which are performed again on failure
+        if let Some(telemetry) = &self.telemetry {
+            if let Err(e) = telemetry.record(&event.event).await {
+                tracing::warn!(order_id = %event.id, "Failed to record telemetry: {e:#}");
+            }
+        }
+
+        // 2. Perform side effects and acknowledge them
+        let order_id = event.id;
+        let event_name = event.event.to_string();
+
+        if let Err(e) = self.dispatch(outbox_id, event).await {
+            self.record_failure(outbox_id, 1, order_id, &event_name, &e)
+                .await;
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    async fn handle(&mut self, _: DispatchOutbox) {
+        let entries = match self.db.load_pending_outbox_entries(Timestamp::now()).await {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::error!("Failed to load pending outbox entries: {e:#}");
+                return;
+            }
+        };
+
+        for sqlite_db::OutboxEntry {
+            id,
+            event,
+            attempts,
+        } in entries
+        {
+            let order_id = event.id;
+            let event_name = event.event.to_string();
+
+            tracing::info!(%order_id, event = %event_name, %attempts, "Performing side effects of unacknowledged event");
+
+            if let Err(e) = self.dispatch(id, event).await {
+                self.record_failure(id, attempts + 1, order_id, &event_name, &e)
+                    .await;
+            }
+        }
+    }
+}
+
+impl Actor {
+    /// Performs the side effects of an event and acknowledges the corresponding outbox entry.
+    ///
+    /// All side effects must be idempotent because they are performed again if we fail to
+    /// acknowledge the outbox entry.
+    async fn dispatch(&mut self, outbox_id: i64, event: CfdEvent) -> Result<()> {
+        self.post_process(event).await?;
+        self.db.acknowledge_outbox_entry(outbox_id).await?;
+
+        Ok(())
+    }
+
+    /// Postpones the next attempt to perform the side effects of an outbox entry, or gives up on
+    /// them after [`MAX_OUTBOX_ATTEMPTS`] failed attempts.
+    async fn record_failure(
+        &self,
+        outbox_id: i64,
+        attempts: u32,
+        order_id: model::OrderId,
+        event_name: &str,
+        error: &anyhow::Error,
+    ) {
+        let now = Timestamp::now();
+        let next_attempt_at = next_attempt_at(attempts, now);
+
+        match next_attempt_at {
+            Some(next_attempt_at) => {
+                tracing::warn!(%order_id, event = %event_name, %attempts, next_attempt_at = %next_attempt_at.seconds(), "Failed to perform side effects: {error:#}");
+            }
+            None => {
+                OUTBOX_GIVEN_UP_COUNTER.inc();
+                tracing::error!(%order_id, event = %event_name, %attempts, "Giving up on side effects after repeated failures, manual intervention required: {error:#}");
+            }
+        }
+
+        if let Err(e) = self
+            .db
+            .record_outbox_failure(outbox_id, next_attempt_at, now)
+            .await
+        {
+            tracing::error!(%order_id, %outbox_id, "Failed to record failed side effects: {e:#}");
+        }
+    }
+
+    async fn post_process(&mut self, event: CfdEvent) -> Result<()> {
+        // Allows the ledger to attribute the transaction to the CFD once it is confirmed
+        if let Some((txid, reason)) = wallet_transaction(&event.event) {
+            self.db.link_cfd_transaction(txid, event.id, reason).await?;
+        }
 
-        // 2. Post process event
         use EventKind::*;
         match event.event {
             ContractSetupCompleted { dlc: Some(dlc), .. } => {
@@ -133,6 +274,15 @@ impl Actor {
                     })
                     .await?;
             }
+            CetPublishedByCounterparty { cet, .. } => {
+                // Already published, we only have to wait for finality
+                self.monitor_cet_finality
+                    .send_async_safe(MonitorCetFinality {
+                        order_id: event.id,
+                        cet,
+                    })
+                    .await?;
+            }
             CetTimelockExpiredPostOracleAttestation { cet }
             | OracleAttestedPostCetTimelock { cet, .. } => {
                 let _ = self
@@ -210,6 +360,8 @@ impl Actor {
             | RolloverAccepted
             | RolloverRejected
             | RolloverFailed
+            | RolloverFundingRateRejected { .. }
+            | SettlementProposedByMaker { .. }
             | CollaborativeSettlementProposalAccepted
             | LockConfirmed
             | LockConfirmedAfterFinality
@@ -219,15 +371,16 @@ impl Actor {
             | CollaborativeSettlementConfirmed
             | CollaborativeSettlementRejected
             | CollaborativeSettlementFailed
-            | CetTimelockExpiredPriorOracleAttestation => {}
+            | CetTimelockExpiredPriorOracleAttestation
+            | PriceLevelsSet { .. } => {}
         }
 
-        // 3. Update UI
+        // Update UI
         self.cfds_changed
             .send_async_safe(projection::CfdChanged(event.id))
             .await?;
 
-        // 4. Update metrics
+        // Update metrics
         self.cfd_changed_metrics
             .send_async_safe(position_metrics::CfdChanged(event.id))
             .await?;
@@ -236,9 +389,104 @@ impl Actor {
     }
 }
 
+/// The transaction of the event that changes the balance of our wallet once confirmed, if any.
+fn wallet_transaction(event: &EventKind) -> Option<(Txid, LedgerReason)> {
+    use EventKind::*;
+    match event {
+        ContractSetupCompleted { dlc: Some(dlc) } => {
+            Some((dlc.lock.0.txid(), LedgerReason::LockUp))
+        }
+        CollaborativeSettlementCompleted { spend_tx, .. } => {
+            Some((spend_tx.txid(), LedgerReason::SettlementPayout))
+        }
+        CetTimelockExpiredPostOracleAttestation { cet }
+        | OracleAttestedPostCetTimelock { cet, .. }
+        | CetPublishedByCounterparty { cet, .. }
+        | OracleAttestedPriorCetTimelock {
+            timelocked_cet: cet,
+            ..
+        } => Some((cet.txid(), LedgerReason::SettlementPayout)),
+        RefundTimelockExpired { refund_tx } => {
+            Some((refund_tx.txid(), LedgerReason::SettlementPayout))
+        }
+        ContractSetupCompleted { dlc: None }
+        | ManualCommit { .. }
+        | RolloverCompleted { .. }
+        | RefundConfirmed
+        | CollaborativeSettlementStarted { .. }
+        | ContractSetupStarted
+        | ContractSetupFailed
+        | OfferRejected
+        | RolloverStarted
+        | RolloverAccepted
+        | RolloverRejected
+        | RolloverFailed
+        | RolloverFundingRateRejected { .. }
+        | SettlementProposedByMaker { .. }
+        | CollaborativeSettlementProposalAccepted
+        | LockConfirmed
+        | LockConfirmedAfterFinality
+        | CommitConfirmed
+        | CetConfirmed
+        | RevokeConfirmed
+        | CollaborativeSettlementConfirmed
+        | CollaborativeSettlementRejected
+        | CollaborativeSettlementFailed
+        | CetTimelockExpiredPriorOracleAttestation
+        | PriceLevelsSet { .. } => None,
+    }
+}
+
+/// When to attempt to perform side effects again after `attempts` failed attempts, `None` if we
+/// give up.
+fn next_attempt_at(attempts: u32, now: Timestamp) -> Option<Timestamp> {
+    if attempts >= MAX_OUTBOX_ATTEMPTS {
+        return None;
+    }
+
+    let backoff = OUTBOX_INITIAL_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempts.saturating_sub(1)))
+        .min(OUTBOX_MAX_BACKOFF);
+
+    Some(Timestamp::new(now.seconds() + backoff.as_secs() as i64))
+}
+
 #[async_trait]
 impl xtra::Actor for Actor {
     type Stop = ();
 
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we just started");
+
+        // Catch up on side effects that were not performed before the last shutdown
+        this.send_async_next(DispatchOutbox).await;
+
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(
+                OUTBOX_RETRY_INTERVAL,
+                || DispatchOutbox,
+                xtras::IncludeSpan::Always,
+            ),
+        );
+    }
+
     async fn stopped(self) -> Self::Stop {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_side_effects_are_retried_with_backoff_until_given_up() {
+        let now = Timestamp::new(1_000);
+
+        let delays = (1..MAX_OUTBOX_ATTEMPTS)
+            .map(|attempts| next_attempt_at(attempts, now).unwrap().seconds() - now.seconds())
+            .collect::<Vec<_>>();
+
+        assert_eq!(delays, vec![60, 120, 240, 480, 960, 1920, 3600, 3600, 3600]);
+        assert_eq!(next_attempt_at(MAX_OUTBOX_ATTEMPTS, now), None);
+    }
+}
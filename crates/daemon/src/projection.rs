@@ -1,8 +1,10 @@
+use crate::missing_attestation;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
 use bdk::bitcoin::Amount;
 use bdk::bitcoin::Network;
+use bdk::bitcoin::OutPoint;
 use bdk::bitcoin::Script;
 use bdk::bitcoin::SignedAmount;
 use bdk::bitcoin::Transaction;
@@ -10,6 +12,7 @@ use bdk::bitcoin::Txid;
 use bdk::miniscript::DescriptorTrait;
 use core::fmt;
 use derivative::Derivative;
+use futures::Future;
 use futures::StreamExt;
 use itertools::Itertools;
 use maia_core::TransactionExt;
@@ -21,6 +24,9 @@ use model::calculate_short_liquidation_price;
 use model::libp2p::PeerId;
 use model::long_and_short_leverage;
 use model::market_closing_price;
+use model::symbols;
+use model::symbols::PayoutCurve;
+use model::trading_calendar::TradingCalendar;
 use model::CfdEvent;
 use model::ClosedCfd;
 use model::ContractSymbol;
@@ -31,15 +37,19 @@ use model::FailedCfd;
 use model::FailedKind;
 use model::FeeAccount;
 use model::FundingFee;
+use model::FundingPeriod;
 use model::FundingRate;
+use model::FundingRateCheck;
 use model::Leverage;
 use model::LotSize;
 use model::OfferId;
+use model::OpeningFeeTiers;
 use model::OrderId;
 use model::Position;
 use model::Price;
 use model::Role;
 use model::Settlement;
+use model::SettlementPriceCheck;
 use model::Timestamp;
 use model::SETTLEMENT_INTERVAL;
 use parse_display::Display;
@@ -49,9 +59,11 @@ use rust_decimal_macros::dec;
 use serde::Deserialize;
 use serde::Serialize;
 use sqlite_db;
+use sqlite_db::cfd_tags::CfdTag;
+use sqlite_db::intents::Intent;
+use sqlite_db::intents::IntentAction;
 use std::collections::HashMap;
 use std::collections::HashSet;
-use std::fmt::Write;
 use std::sync::Arc;
 use std::time::Duration;
 use time::OffsetDateTime;
@@ -62,11 +74,27 @@ use xtra::prelude::MessageChannel;
 use xtra_bitmex_price_feed::GetLatestQuotes;
 use xtra_productivity::xtra_productivity;
 use xtras::SendAsyncNext;
+use xtras::SendAsyncSafe;
+
+mod cfd_feed;
+pub mod explorer;
+mod offload;
+mod rehydration;
+pub mod rounding;
+
+/// Number of messages the mailbox of the projection holds
+///
+/// Once the mailbox is full, senders of e.g. [`CfdChanged`] wait until the projection caught up.
+pub const MAILBOX_CAPACITY: usize = 1000;
 
 /// Store the latest state of `T` for display purposes
 /// (replaces previously stored values)
 pub struct Update<T>(pub T);
 
+/// Indicates that the maker withdrew all offers.
+#[derive(Clone, Copy)]
+pub struct OffersWithdrawn;
+
 /// Indicates that the CFD with the given order ID changed.
 #[derive(Clone, Copy)]
 pub struct CfdChanged(pub OrderId);
@@ -75,41 +103,56 @@ pub struct CfdChanged(pub OrderId);
 #[derive(Clone, Copy)]
 struct Initialize;
 
+/// Rehydrate the CFDs queued since the last batch
+#[derive(Clone, Copy)]
+struct RehydrateChangedCfds;
+
 pub struct Actor {
     db: sqlite_db::Connection,
     tx: Tx,
     state: State,
     price_feed: MessageChannel<GetLatestQuotes, xtra_bitmex_price_feed::LatestQuotes>,
     role: Role,
+    rehydration_queue: rehydration::Queue,
 }
 
 pub struct FeedReceivers {
     pub quote: watch::Receiver<LatestQuotes>,
     pub offers: watch::Receiver<MakerOffers>,
+    pub offer_book: watch::Receiver<OfferBook>,
     pub cfds: watch::Receiver<Option<Vec<Cfd>>>,
+    pub chain_tip: watch::Receiver<Option<ChainTip>>,
 }
 
 pub struct FeedSenders {
     pub quote: watch::Sender<LatestQuotes>,
     pub offers: watch::Sender<MakerOffers>,
+    pub offer_book: watch::Sender<OfferBook>,
     pub cfds: watch::Sender<Option<Vec<Cfd>>>,
+    pub chain_tip: watch::Sender<Option<ChainTip>>,
 }
 
 pub fn feeds() -> (FeedSenders, FeedReceivers) {
     let (tx_quote, rx_quote) = watch::channel(LatestQuotes::default());
     let (tx_offers, rx_offers) = watch::channel(MakerOffers::default());
+    let (tx_offer_book, rx_offer_book) = watch::channel(OfferBook::default());
     let (tx_cfds, rx_cfds) = watch::channel(None);
+    let (tx_chain_tip, rx_chain_tip) = watch::channel(None);
 
     (
         FeedSenders {
             quote: tx_quote,
             offers: tx_offers,
+            offer_book: tx_offer_book,
             cfds: tx_cfds,
+            chain_tip: tx_chain_tip,
         },
         FeedReceivers {
             quote: rx_quote,
             offers: rx_offers,
+            offer_book: rx_offer_book,
             cfds: rx_cfds,
+            chain_tip: rx_chain_tip,
         },
     )
 }
@@ -124,12 +167,21 @@ impl Actor {
     ) -> Self {
         Self {
             db,
-            tx: Tx(feed_senders),
+            tx: Tx {
+                senders: feed_senders,
+                cfd_feed: Arc::default(),
+            },
             state: State::new(network),
             price_feed,
             role,
+            rehydration_queue: rehydration::Queue::default(),
         }
     }
+
+    pub fn with_missing_attestation_policy(mut self, policy: missing_attestation::Policy) -> Self {
+        self.state.missing_attestation_policy = policy;
+        self
+    }
 }
 
 #[derive(Derivative, Clone, Debug, Serialize)]
@@ -137,24 +189,26 @@ impl Actor {
 pub struct Cfd {
     pub order_id: OrderId,
     pub offer_id: OfferId,
-    #[serde(with = "round_to_two_dp")]
+    #[serde(with = "rounding::price")]
     pub initial_price: Price,
 
     /// Sum of all costs
     ///
     /// Includes the opening fee and all fees that were already charged.
-    #[serde(with = "::bdk::bitcoin::util::amount::serde::as_btc")]
+    #[serde(with = "rounding::fee")]
     pub accumulated_fees: SignedAmount,
 
     /// The taker leverage
     #[serde(rename = "leverage")]
     pub leverage_taker: Leverage,
     pub contract_symbol: ContractSymbol,
+    /// Multiplier the payout of a quanto contract is scaled with, `None` for inverse contracts
+    pub quanto_multiplier: Option<Decimal>,
     pub position: Position,
-    #[serde(with = "round_to_two_dp")]
+    #[serde(with = "rounding::price")]
     pub liquidation_price: Decimal,
 
-    #[serde(with = "round_to_two_dp")]
+    #[serde(with = "rounding::quantity")]
     pub quantity: Contracts,
 
     #[serde(with = "::bdk::bitcoin::util::amount::serde::as_btc")]
@@ -177,6 +231,7 @@ pub struct Cfd {
     /// collborative close) then this is the final payout.
     #[serde(with = "::bdk::bitcoin::util::amount::serde::as_btc::opt")]
     pub payout: Option<Amount>,
+    #[serde(with = "rounding::configured_price::opt")]
     pub closing_price: Option<Price>,
 
     pub state: CfdState,
@@ -191,9 +246,39 @@ pub struct Cfd {
 
     pub counterparty: PeerId,
 
-    #[serde(with = "round_to_two_dp::opt")]
+    #[serde(with = "rounding::price::opt")]
     pub pending_settlement_proposal_price: Option<Price>,
 
+    /// Price at which the taker settles the CFD automatically to realize the profit
+    #[serde(with = "rounding::price::opt")]
+    pub take_profit: Option<Price>,
+    /// Price at which the taker settles the CFD automatically to limit the loss
+    #[serde(with = "rounding::price::opt")]
+    pub stop_loss: Option<Price>,
+
+    /// Set if the oracle attestation of the settlement event is overdue
+    pub missing_attestation: Option<MissingAttestation>,
+
+    /// Set if the last rollover was rejected because the funding rate proposed by the maker
+    /// deviated too much from the funding rate of the maker's latest offer
+    pub rollover_funding_rate_check: Option<FundingRateCheck>,
+
+    /// Set if the maker proposed to settle the CFD at a price that deviates too much from the
+    /// latest quote to settle automatically, until the taker settles
+    pub maker_settlement_proposal: Option<SettlementPriceCheck>,
+
+    /// Number of seconds the order has been waiting for the maker's decision
+    pub pending_age_secs: Option<u64>,
+
+    /// Set if an action was requested while the maker was offline
+    pub queued_intent: Option<QueuedIntent>,
+
+    /// User-defined tags, e.g. the strategy or client the position belongs to
+    pub tags: Vec<String>,
+
+    /// Set once the commit transaction is confirmed and until the refund timelock expires
+    pub refund_estimate: Option<RefundEstimate>,
+
     #[serde(skip)]
     #[derivative(PartialEq = "ignore")]
     aggregated: Aggregated,
@@ -202,6 +287,50 @@ pub struct Cfd {
     network: Network,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MissingAttestation {
+    /// What the daemon does about the missing attestation
+    pub policy: missing_attestation::Policy,
+    /// Since when the attestation is considered missing
+    #[serde(with = "::time::serde::timestamp")]
+    pub missing_since: OffsetDateTime,
+    /// Whether the commit transaction was published, i.e. the refund timelock is running
+    pub commit_published: bool,
+    /// Number of blocks after the confirmation of the commit transaction until the refund
+    /// transaction can be published
+    pub refund_timelock_blocks: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QueuedIntent {
+    pub action: IntentAction,
+    #[serde(with = "::time::serde::timestamp")]
+    pub expires_at: OffsetDateTime,
+    /// Human-readable status, e.g. `queued: close at market, expires in 2h`
+    pub status: String,
+}
+
+impl QueuedIntent {
+    fn new(intent: &Intent, now: OffsetDateTime) -> Result<Self> {
+        let expires_at = OffsetDateTime::from_unix_timestamp(intent.expires_at.seconds())?;
+        let remaining = (expires_at - now).max(time::Duration::ZERO);
+
+        let hours = remaining.whole_hours();
+        let minutes = remaining.whole_minutes() % 60;
+        let remaining = match (hours, minutes) {
+            (0, minutes) => format!("{minutes}m"),
+            (hours, 0) => format!("{hours}h"),
+            (hours, minutes) => format!("{hours}h {minutes}m"),
+        };
+
+        Ok(Self {
+            action: intent.action.clone(),
+            expires_at,
+            status: format!("queued: {}, expires in {remaining}", intent.action),
+        })
+    }
+}
+
 /// Bundle all state extracted from the events in one struct.
 ///
 /// This struct is not serialized but simply carries all state we are interested in from the events.
@@ -229,12 +358,7 @@ pub struct Aggregated {
     commit_published: bool,
     refund_published: bool,
 
-    /// Keep track of persistent state in case a protocol fails and we need to
-    /// return to previous state
-    state: CfdState,
-
-    /// Negotiation state of collaborative settlement protocol.
-    settlement_state: Option<ProtocolNegotiationState>,
+    lifecycle: Lifecycle,
 
     version: u32,
     creation_timestamp: Timestamp,
@@ -252,8 +376,7 @@ impl Aggregated {
             timelocked_cet: None,
             commit_published: false,
             refund_published: false,
-            state: CfdState::PendingSetup,
-            settlement_state: None,
+            lifecycle: Lifecycle::new(),
             version: 0,
             creation_timestamp: Timestamp::now(),
         }
@@ -275,21 +398,6 @@ impl Aggregated {
         Some(extract_payout_amount(tx, &script))
     }
 
-    /// Derive Cfd state based on aggregated state from the events and the
-    /// protocol state
-    fn derive_cfd_state(&self, role: Role) -> CfdState {
-        if let Some(settlement_state) = self.settlement_state {
-            return match settlement_state {
-                ProtocolNegotiationState::Started => match role {
-                    Role::Maker => CfdState::IncomingSettlementProposal,
-                    Role::Taker => CfdState::OutgoingSettlementProposal,
-                },
-                ProtocolNegotiationState::Accepted => CfdState::IncomingSettlementProposal,
-            };
-        };
-        self.state
-    }
-
     // Only used in integration tests
     pub fn latest_dlc(&self) -> &Option<Dlc> {
         &self.latest_dlc
@@ -320,6 +428,107 @@ enum ProtocolNegotiationState {
     Accepted,
 }
 
+/// The [`CfdState`] of a CFD as it evolves with its events.
+#[derive(Clone, Copy, Debug)]
+struct Lifecycle {
+    /// Keep track of persistent state in case a protocol fails and we need to
+    /// return to previous state
+    state: CfdState,
+
+    /// Negotiation state of collaborative settlement protocol.
+    settlement_state: Option<ProtocolNegotiationState>,
+}
+
+impl Lifecycle {
+    fn new() -> Self {
+        Self {
+            state: CfdState::PendingSetup,
+            settlement_state: None,
+        }
+    }
+
+    fn apply(&mut self, event: &EventKind) {
+        use EventKind::*;
+        match event {
+            ContractSetupStarted => {
+                self.state = CfdState::ContractSetup;
+            }
+            ContractSetupCompleted { .. } => {
+                self.state = CfdState::PendingOpen;
+            }
+            ContractSetupFailed => {
+                self.state = CfdState::SetupFailed;
+            }
+            OfferRejected => {
+                self.state = CfdState::Rejected;
+            }
+            RolloverAccepted | RolloverStarted => {
+                self.state = CfdState::RolloverSetup;
+            }
+            RolloverCompleted { .. }
+            | RolloverRejected
+            | RolloverFailed
+            | RolloverFundingRateRejected { .. }
+            | LockConfirmed => {
+                self.state = CfdState::Open;
+            }
+            CollaborativeSettlementStarted { .. } => {
+                self.settlement_state = Some(ProtocolNegotiationState::Started);
+            }
+            CollaborativeSettlementProposalAccepted => {
+                self.settlement_state = Some(ProtocolNegotiationState::Accepted);
+            }
+            CollaborativeSettlementCompleted { .. } => {
+                self.settlement_state = None;
+                self.state = CfdState::PendingClose;
+            }
+            CollaborativeSettlementRejected | CollaborativeSettlementFailed => {
+                self.settlement_state = None;
+            }
+            CommitConfirmed | CetTimelockExpiredPriorOracleAttestation => {
+                self.state = CfdState::OpenCommitted;
+            }
+            // TODO: Implement revoked logic
+            RevokeConfirmed => {
+                self.state = CfdState::OpenCommitted;
+            }
+            CetConfirmed | LockConfirmedAfterFinality | CollaborativeSettlementConfirmed => {
+                self.state = CfdState::Closed;
+            }
+            RefundConfirmed => {
+                self.state = CfdState::Refunded;
+            }
+            CetTimelockExpiredPostOracleAttestation { .. }
+            | OracleAttestedPostCetTimelock { .. }
+            | CetPublishedByCounterparty { .. } => {
+                self.state = CfdState::PendingCet;
+            }
+            RefundTimelockExpired { .. } => {
+                self.state = CfdState::PendingRefund;
+            }
+            OracleAttestedPriorCetTimelock { .. } | ManualCommit { .. } => {
+                self.state = CfdState::PendingCommit;
+            }
+            SettlementProposedByMaker { .. } | PriceLevelsSet { .. } => {}
+        }
+    }
+
+    /// Derive Cfd state based on aggregated state from the events and the
+    /// protocol state
+    fn cfd_state(&self, role: Role) -> CfdState {
+        if let Some(settlement_state) = self.settlement_state {
+            return match settlement_state {
+                ProtocolNegotiationState::Started => match role {
+                    Role::Maker => CfdState::IncomingSettlementProposal,
+                    Role::Taker => CfdState::OutgoingSettlementProposal,
+                },
+                ProtocolNegotiationState::Accepted => CfdState::IncomingSettlementProposal,
+            };
+        };
+        self.state
+    }
+}
+
 impl Cfd {
     fn new(
         sqlite_db::Cfd {
@@ -334,6 +543,7 @@ impl Cfd {
             opening_fee,
             initial_funding_rate,
             contract_symbol,
+            quanto_multiplier,
             ..
         }: sqlite_db::Cfd,
         network: Network,
@@ -343,13 +553,11 @@ impl Cfd {
             Role::Taker => (taker_leverage, Leverage::ONE),
         };
 
-        let margin = calculate_margin(contract_symbol, initial_price, quantity, our_leverage);
-        let margin_counterparty = calculate_margin(
-            contract_symbol,
-            initial_price,
-            quantity,
-            counterparty_leverage,
-        );
+        let payout_curve = symbols::payout_curve(contract_symbol, quanto_multiplier);
+
+        let margin = calculate_margin(payout_curve, initial_price, quantity, our_leverage);
+        let margin_counterparty =
+            calculate_margin(payout_curve, initial_price, quantity, counterparty_leverage);
 
         // This value will be updated as we apply `{ContractSetup,Rollover}Completed` events to the
         // `Cfd`
@@ -372,7 +580,7 @@ impl Cfd {
             short_leverage,
             initial_funding_rate,
             SETTLEMENT_INTERVAL.whole_hours(),
-            contract_symbol,
+            payout_curve,
         )
         .expect("values from db to be sane");
 
@@ -393,6 +601,7 @@ impl Cfd {
             accumulated_fees: fee_account.balance(),
             leverage_taker: taker_leverage,
             contract_symbol,
+            quanto_multiplier: payout_curve.quanto_multiplier(),
             position,
             liquidation_price,
             quantity,
@@ -413,6 +622,15 @@ impl Cfd {
             expiry_timestamp: None,
             counterparty: counterparty_peer_id.unwrap_or_else(PeerId::placeholder),
             pending_settlement_proposal_price: None,
+            take_profit: None,
+            stop_loss: None,
+            missing_attestation: None,
+            rollover_funding_rate_check: None,
+            maker_settlement_proposal: None,
+            pending_age_secs: None,
+            queued_intent: None,
+            tags: Vec::new(),
+            refund_estimate: None,
             aggregated: Aggregated::new(fee_account),
             network,
         }
@@ -423,12 +641,10 @@ impl Cfd {
             self.aggregated.creation_timestamp = event.timestamp;
         }
 
-        // First, try to set state based on event.
+        self.aggregated.lifecycle.apply(&event.event);
+
         use EventKind::*;
         match event.event {
-            ContractSetupStarted => {
-                self.aggregated.state = CfdState::ContractSetup;
-            }
             ContractSetupCompleted { dlc } => {
                 self.expiry_timestamp = dlc.as_ref().map(|dlc| dlc.settlement_event_id.timestamp());
 
@@ -438,14 +654,6 @@ impl Cfd {
                 }
 
                 self.aggregated.latest_dlc = dlc;
-
-                self.aggregated.state = CfdState::PendingOpen;
-            }
-            ContractSetupFailed => {
-                self.aggregated.state = CfdState::SetupFailed;
-            }
-            OfferRejected => {
-                self.aggregated.state = CfdState::Rejected;
             }
             RolloverCompleted {
                 dlc,
@@ -469,23 +677,23 @@ impl Cfd {
                 };
 
                 self.accumulated_fees = self.aggregated.fee_account.balance();
-
-                self.aggregated.state = CfdState::Open;
+                self.rollover_funding_rate_check = None;
             }
-            RolloverAccepted | RolloverStarted { .. } => {
-                self.aggregated.state = CfdState::RolloverSetup;
+            RolloverFundingRateRejected { check } => {
+                self.rollover_funding_rate_check = Some(check);
             }
-            RolloverRejected | RolloverFailed => {
-                self.aggregated.state = CfdState::Open;
+            SettlementProposedByMaker { check } => {
+                if !check.is_within_tolerance() {
+                    self.maker_settlement_proposal = Some(check);
+                }
             }
-            CollaborativeSettlementStarted { proposal } => {
-                self.aggregated.settlement_state = Some(ProtocolNegotiationState::Started);
+            CollaborativeSettlementStarted { proposal, .. } => {
+                self.maker_settlement_proposal = None;
                 if let Role::Maker = self.role {
                     self.pending_settlement_proposal_price = Some(proposal.price);
                 };
             }
             CollaborativeSettlementProposalAccepted => {
-                self.aggregated.settlement_state = Some(ProtocolNegotiationState::Accepted);
                 self.pending_settlement_proposal_price = None;
             }
             CollaborativeSettlementCompleted {
@@ -493,29 +701,19 @@ impl Cfd {
                 script,
                 price,
             } => {
-                self.aggregated.settlement_state = None;
                 self.aggregated.collab_settlement_tx = Some((spend_tx, script));
                 self.closing_price = Some(price);
-
-                self.aggregated.state = CfdState::PendingClose;
             }
             CollaborativeSettlementRejected => {
-                self.aggregated.settlement_state = None;
                 self.pending_settlement_proposal_price = None;
             }
             CollaborativeSettlementFailed => {
-                self.aggregated.settlement_state = None;
                 self.pending_settlement_proposal_price = None;
             }
-            LockConfirmed => {
-                self.aggregated.state = CfdState::Open;
-            }
             CommitConfirmed => {
                 // Commit can be published by either party, meaning it being confirmed might be the
                 // first time we hear about it!
                 self.aggregated.commit_published = true;
-
-                self.aggregated.state = CfdState::OpenCommitted;
             }
             CetConfirmed => {
                 // Needed for cases where CET gets priority over refund in case the refund timelock
@@ -523,29 +721,14 @@ impl Cfd {
                 // refund-tx is not set, otherwise the UI will show the refund-tx.
                 self.aggregated.refund_tx = None;
                 self.aggregated.refund_published = false;
-
-                self.aggregated.state = CfdState::Closed;
-            }
-            RefundConfirmed => {
-                self.aggregated.state = CfdState::Refunded;
-            }
-            LockConfirmedAfterFinality | CollaborativeSettlementConfirmed => {
-                self.aggregated.state = CfdState::Closed;
-            }
-            CetTimelockExpiredPriorOracleAttestation => {
-                self.aggregated.state = CfdState::OpenCommitted;
             }
             CetTimelockExpiredPostOracleAttestation { cet } => {
                 self.aggregated.cet = Some(cet);
-
-                self.aggregated.state = CfdState::PendingCet;
             }
             RefundTimelockExpired { refund_tx } => {
                 self.aggregated.refund_tx = Some(refund_tx);
 
                 self.aggregated.refund_published = true;
-
-                self.aggregated.state = CfdState::PendingRefund;
             }
             OracleAttestedPriorCetTimelock {
                 timelocked_cet,
@@ -556,26 +739,41 @@ impl Cfd {
                 self.closing_price = Some(price);
 
                 self.aggregated.commit_published = true;
-                self.aggregated.state = CfdState::PendingCommit;
             }
             OracleAttestedPostCetTimelock { cet, price, .. } => {
                 self.aggregated.cet = Some(cet);
                 self.closing_price = Some(price);
-
-                self.aggregated.state = CfdState::PendingCet;
+            }
+            CetPublishedByCounterparty { cet, price, .. } => {
+                self.aggregated.cet = Some(cet);
+                self.closing_price = price.or(self.closing_price);
             }
             ManualCommit { .. } => {
                 self.aggregated.commit_published = true;
-
-                self.aggregated.state = CfdState::PendingCommit;
             }
-            RevokeConfirmed => {
-                // TODO: Implement revoked logic
-                self.aggregated.state = CfdState::OpenCommitted;
+            PriceLevelsSet {
+                take_profit,
+                stop_loss,
+            } => {
+                self.take_profit = take_profit;
+                self.stop_loss = stop_loss;
             }
+            ContractSetupStarted
+            | ContractSetupFailed
+            | OfferRejected
+            | RolloverAccepted
+            | RolloverStarted
+            | RolloverRejected
+            | RolloverFailed
+            | LockConfirmed
+            | RefundConfirmed
+            | LockConfirmedAfterFinality
+            | CollaborativeSettlementConfirmed
+            | CetTimelockExpiredPriorOracleAttestation
+            | RevokeConfirmed => {}
         };
 
-        self.state = self.aggregated.derive_cfd_state(self.role);
+        self.state = self.aggregated.lifecycle.cfd_state(self.role);
         self.actions = self.derive_actions();
 
         if let Some(lock_tx_url) = self.lock_tx_url(self.network) {
@@ -599,6 +797,82 @@ impl Cfd {
         self
     }
 
+    /// Report whether the attestation of the settlement event is missing and how it is handled.
+    pub fn with_missing_attestation(
+        self,
+        policy: missing_attestation::Policy,
+        now: OffsetDateTime,
+    ) -> Self {
+        let aggregated = &self.aggregated;
+        let settled = aggregated.cet.is_some()
+            || aggregated.timelocked_cet.is_some()
+            || aggregated.collab_settlement_tx.is_some()
+            || aggregated.refund_tx.is_some();
+
+        let missing_attestation = match &aggregated.latest_dlc {
+            Some(dlc) if !settled => {
+                let missing_since =
+                    dlc.settlement_event_id.timestamp() + missing_attestation::GRACE_PERIOD;
+
+                (now >= missing_since).then_some(MissingAttestation {
+                    policy,
+                    missing_since,
+                    commit_published: aggregated.commit_published,
+                    refund_timelock_blocks: dlc.refund_timelock,
+                })
+            }
+            _ => None,
+        };
+
+        Self {
+            missing_attestation,
+            ..self
+        }
+    }
+
+    /// Report the action queued for execution once the maker is online again.
+    pub fn with_queued_intent(self, intent: Option<&Intent>, now: OffsetDateTime) -> Self {
+        let queued_intent = intent.and_then(|intent| match QueuedIntent::new(intent, now) {
+            Ok(queued_intent) => Some(queued_intent),
+            Err(e) => {
+                tracing::warn!(order_id = %intent.order_id, "Invalid intent expiry: {e:#}");
+                None
+            }
+        });
+
+        Self {
+            queued_intent,
+            ..self
+        }
+    }
+
+    pub fn with_tags(self, tags: Option<&Vec<String>>) -> Self {
+        Self {
+            tags: tags.cloned().unwrap_or_default(),
+            ..self
+        }
+    }
+
+    pub fn with_refund_estimate(self, refund_estimate: Option<&RefundEstimate>) -> Self {
+        Self {
+            refund_estimate: refund_estimate.copied(),
+            ..self
+        }
+    }
+
+    /// Report for how long the order has been waiting for the maker's decision.
+    pub fn with_pending_age(self, now: OffsetDateTime) -> Self {
+        let pending_age_secs = (self.state == CfdState::PendingSetup).then(|| {
+            let age = now.unix_timestamp() - self.aggregated.creation_timestamp.seconds();
+            age.max(0) as u64
+        });
+
+        Self {
+            pending_age_secs,
+            ..self
+        }
+    }
+
     pub fn with_current_quote(self, latest_quotes: Option<&LatestQuotes>) -> Self {
         // If the payout was already set we don't care about the current quote, this applies to
         // closed CFDs
@@ -657,7 +931,7 @@ impl Cfd {
             long_and_short_leverage(self.leverage_taker, self.role, self.position);
 
         let (profit_btc, profit_percent, payout) = match calculate_payout_at_price(
-            self.contract_symbol,
+            symbols::payout_curve(self.contract_symbol, self.quanto_multiplier),
             self.initial_price,
             closing_price,
             self.quantity,
@@ -668,7 +942,14 @@ impl Cfd {
             Ok(payout) => {
                 let (profit_btc, profit_percent) = calculate_profit(payout, self.margin);
 
-                (profit_btc, profit_percent.round_dp(1).to_string(), payout)
+                (
+                    profit_btc,
+                    rounding::policy()
+                        .profit_percent
+                        .round(profit_percent)
+                        .to_string(),
+                    payout,
+                )
             }
             Err(e) => {
                 tracing::warn!("Failed to calculate profit/loss {:#}", e);
@@ -784,30 +1065,64 @@ impl Cfd {
 }
 
 /// Internal struct to keep all the senders around in one place
-struct Tx(Arc<FeedSenders>);
+struct Tx {
+    senders: Arc<FeedSenders>,
+    cfd_feed: Arc<cfd_feed::Generations>,
+}
 
 impl Tx {
-    fn send_cfds_update(&self, cfds: &HashMap<OrderId, Cfd>, quotes: &LatestQuotes) {
-        let cfds_with_quote = cfds
-            .iter()
-            .map(|(_, cfd)| cfd.clone().with_current_quote(Some(quotes)))
-            .sorted_by(|a, b| {
-                Ord::cmp(
-                    &b.aggregated.creation_timestamp,
-                    &a.aggregated.creation_timestamp,
-                )
+    /// Compute the CFD feed from `snapshot` on the blocking thread pool and publish it, unless a
+    /// newer feed was requested in the meantime.
+    ///
+    /// Returns the task to compute the feed on, see [`cfd_feed`].
+    fn send_cfds_update(
+        &self,
+        snapshot: cfd_feed::Snapshot,
+    ) -> impl Future<Output = Result<()>> + Send + 'static {
+        let senders = self.senders.clone();
+        let generations = self.cfd_feed.clone();
+        let generation = generations.next();
+
+        async move {
+            let cfds = offload::run(offload::Task::CfdFeed, {
+                let generations = generations.clone();
+
+                move || {
+                    if generations.is_superseded(generation) {
+                        return None;
+                    }
+
+                    Some(snapshot.into_feed(OffsetDateTime::now_utc()))
+                }
             })
-            .collect();
+            .await?;
+
+            if let Some(cfds) = cfds {
+                generations.publish(generation, || {
+                    let _ = senders.cfds.send(Some(cfds));
+                });
+            }
 
-        let _ = self.0.cfds.send(Some(cfds_with_quote));
+            Ok(())
+        }
     }
 
     fn send_quotes_update(&self, quotes: LatestQuotes) {
-        let _ = self.0.quote.send(quotes);
+        let _ = self.senders.quote.send(quotes);
+    }
+
+    fn send_chain_tip_update(&self, chain_tip: ChainTip) {
+        let _ = self.senders.chain_tip.send(Some(chain_tip));
     }
 
     fn send_offer_update(&self, offers: MakerOffers) -> Result<()> {
-        self.0.offers.send(offers)?;
+        self.senders.offers.send(offers)?;
+
+        Ok(())
+    }
+
+    fn send_offer_book_update(&self, offer_book: OfferBook) -> Result<()> {
+        self.senders.offer_book.send(offer_book)?;
 
         Ok(())
     }
@@ -816,10 +1131,19 @@ impl Tx {
 /// Internal struct to keep state in one place
 struct State {
     network: Network,
+    missing_attestation_policy: missing_attestation::Policy,
     latest_quotes: LatestQuotes,
     offers: MakerOffers,
+    /// Intents queued while the maker was offline
+    intents: Arc<HashMap<OrderId, Intent>>,
+    /// Tags attached to the CFDs
+    tags: Arc<HashMap<OrderId, Vec<String>>>,
+    /// Estimated refund eligibility of the committed CFDs, as reported by the monitor
+    refund_estimates: Arc<HashMap<OrderId, RefundEstimate>>,
     /// All hydrated CFDs.
-    cfds: Option<HashMap<OrderId, Cfd>>,
+    ///
+    /// Shared with the tasks computing the CFD feed, see [`cfd_feed::Snapshot`].
+    cfds: Option<Arc<HashMap<OrderId, Arc<Cfd>>>>,
 }
 
 impl sqlite_db::CfdAggregate for Cfd {
@@ -883,10 +1207,11 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
         let (details, closing_price, payout, state) = {
             let mut tx_url_list = HashSet::default();
 
-            tx_url_list.insert(
-                TxUrl::new(lock.txid, network, TxLabel::Lock)
-                    .with_output_index(lock.dlc_vout.into()),
-            );
+            tx_url_list.insert(TxUrl::with_output(
+                OutPoint::new(lock.txid, lock.dlc_vout.into()),
+                network,
+                TxLabel::Lock,
+            ));
 
             let (price, payout, state) = match settlement {
                 Settlement::Collaborative {
@@ -895,10 +1220,11 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
                     payout,
                     price,
                 } => {
-                    tx_url_list.insert(
-                        TxUrl::new(txid, network, TxLabel::Collaborative)
-                            .with_output_index(vout.into()),
-                    );
+                    tx_url_list.insert(TxUrl::with_output(
+                        OutPoint::new(txid, vout.into()),
+                        network,
+                        TxLabel::Collaborative,
+                    ));
                     (Some(price), payout, CfdState::Closed)
                 }
                 Settlement::Cet {
@@ -908,13 +1234,17 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
                     payout,
                     price,
                 } => {
-                    tx_url_list.insert(
-                        TxUrl::new(commit_txid, network, TxLabel::Commit).with_output_index(0),
-                    );
-
-                    tx_url_list.insert(
-                        TxUrl::new(txid, network, TxLabel::Cet).with_output_index(vout.into()),
-                    );
+                    tx_url_list.insert(TxUrl::with_output(
+                        OutPoint::new(commit_txid, 0),
+                        network,
+                        TxLabel::Commit,
+                    ));
+
+                    tx_url_list.insert(TxUrl::with_output(
+                        OutPoint::new(txid, vout.into()),
+                        network,
+                        TxLabel::Cet,
+                    ));
                     (Some(price), payout, CfdState::Closed)
                 }
                 Settlement::Refund {
@@ -923,13 +1253,17 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
                     vout,
                     payout,
                 } => {
-                    tx_url_list.insert(
-                        TxUrl::new(commit_txid, network, TxLabel::Commit).with_output_index(0),
-                    );
-
-                    tx_url_list.insert(
-                        TxUrl::new(txid, network, TxLabel::Refund).with_output_index(vout.into()),
-                    );
+                    tx_url_list.insert(TxUrl::with_output(
+                        OutPoint::new(commit_txid, 0),
+                        network,
+                        TxLabel::Commit,
+                    ));
+
+                    tx_url_list.insert(TxUrl::with_output(
+                        OutPoint::new(txid, vout.into()),
+                        network,
+                        TxLabel::Refund,
+                    ));
                     (None, payout, CfdState::Refunded)
                 }
             };
@@ -953,6 +1287,7 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
             accumulated_fees: fees.into(),
             leverage_taker: taker_leverage,
             contract_symbol,
+            quanto_multiplier: PayoutCurve::from(contract_symbol).quanto_multiplier(),
             position,
             liquidation_price,
             quantity,
@@ -971,6 +1306,15 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
             expiry_timestamp: Some(expiry_timestamp),
             counterparty: counterparty_peer_id,
             pending_settlement_proposal_price: None,
+            take_profit: None,
+            stop_loss: None,
+            missing_attestation: None,
+            rollover_funding_rate_check: None,
+            maker_settlement_proposal: None,
+            pending_age_secs: None,
+            queued_intent: None,
+            tags: Vec::new(),
+            refund_estimate: None,
             aggregated,
             network,
         }
@@ -1036,6 +1380,7 @@ impl sqlite_db::FailedCfdAggregate for Cfd {
             accumulated_fees: fees.into(),
             leverage_taker: taker_leverage,
             contract_symbol,
+            quanto_multiplier: PayoutCurve::from(contract_symbol).quanto_multiplier(),
             position,
             liquidation_price,
             quantity,
@@ -1056,6 +1401,15 @@ impl sqlite_db::FailedCfdAggregate for Cfd {
             expiry_timestamp: None,
             counterparty: counterparty_peer_id,
             pending_settlement_proposal_price: None,
+            take_profit: None,
+            stop_loss: None,
+            missing_attestation: None,
+            rollover_funding_rate_check: None,
+            maker_settlement_proposal: None,
+            pending_age_secs: None,
+            queued_intent: None,
+            tags: Vec::new(),
+            refund_estimate: None,
             aggregated,
             network,
         }
@@ -1066,21 +1420,28 @@ impl State {
     fn new(network: Network) -> Self {
         Self {
             network,
+            missing_attestation_policy: missing_attestation::Policy::Wait,
             latest_quotes: LatestQuotes::default(),
+            intents: Arc::default(),
+            tags: Arc::default(),
+            refund_estimates: Arc::default(),
             cfds: None,
             offers: MakerOffers::default(),
         }
     }
 
     async fn update_cfd(&mut self, db: &sqlite_db::Connection, id: OrderId) -> Result<()> {
-        let cfd = db.load_open_cfd(id, self.network).await?;
+        let cfd = db
+            .load_open_cfd_with(id, self.network, offload::fold)
+            .await?;
 
         let cfds = self
             .cfds
             .as_mut()
             .context("CFD list has not been initialized yet")?;
 
-        cfds.insert(id, cfd);
+        // Only copies the pointers to the CFDs if a feed is still computed from the previous CFDs
+        Arc::make_mut(cfds).insert(id, Arc::new(cfd));
 
         Ok(())
     }
@@ -1090,6 +1451,11 @@ impl State {
     }
 
     fn update_offers(&mut self, new_offers: Vec<CfdOffer>) {
+        // Offers from the cache are replaced as a whole by the first fresh offers
+        if self.offers.is_cached() {
+            self.offers = MakerOffers::default();
+        }
+
         for new_offer in new_offers.into_iter() {
             match &new_offer {
                 CfdOffer {
@@ -1117,10 +1483,98 @@ impl State {
     }
 }
 
+impl Actor {
+    /// Show the cached offers until fresh offers arrive, in case the maker is not reachable.
+    async fn load_cached_offers(&mut self) {
+        if self.state.offers != MakerOffers::default() {
+            return;
+        }
+
+        let cached = match self.db.load_cached_offers().await {
+            Ok(cached) => cached,
+            Err(e) => {
+                tracing::warn!("Failed to load cached offers: {e:#}");
+                return;
+            }
+        };
+
+        if cached.is_empty() {
+            return;
+        }
+
+        let offers = cached
+            .into_iter()
+            .filter_map(|cached| match CfdOffer::new(cached.offer, self.role) {
+                Ok(offer) => Some(CfdOffer {
+                    cached_at: Some(cached.received_at),
+                    ..offer
+                }),
+                Err(e) => {
+                    tracing::warn!("Failed to build CfdOffer from cached offer: {e:#}");
+                    None
+                }
+            })
+            .collect_vec();
+
+        tracing::info!(count = %offers.len(), "Showing cached offers until fresh offers arrive");
+
+        self.state.update_offers(offers);
+
+        if let Err(e) = self.tx.send_offer_update(self.state.offers.clone()) {
+            tracing::error!("Failed to propagate offer update: {e:#}");
+        }
+    }
+
+    /// Publish the CFD feed, unless the CFDs were not loaded yet.
+    ///
+    /// The feed is computed on a task, the handler does not wait for it.
+    fn publish_cfds(&self, ctx: &mut xtra::Context<Self>) {
+        let cfds = match self.state.cfds.as_ref() {
+            Some(cfds) => cfds.clone(),
+            None => return,
+        };
+
+        let task = self.tx.send_cfds_update(cfd_feed::Snapshot {
+            cfds,
+            quotes: self.state.latest_quotes.clone(),
+            missing_attestation_policy: self.state.missing_attestation_policy,
+            intents: self.state.intents.clone(),
+            tags: self.state.tags.clone(),
+            refund_estimates: self.state.refund_estimates.clone(),
+        });
+
+        let this = ctx.address().expect("we are alive");
+        tokio_extras::spawn_fallible(&this, task, |e| async move {
+            tracing::error!("Failed to publish CFDs: {e:#}");
+        });
+    }
+
+    /// Rehydrate the queued CFDs and publish the CFD feed once for the whole batch.
+    async fn rehydrate_changed_cfds(&mut self, ctx: &mut xtra::Context<Self>) {
+        let batch = self.rehydration_queue.take();
+        if batch.is_empty() {
+            return;
+        }
+
+        for (order_id, queued_at) in batch {
+            if let Err(e) = self.state.update_cfd(&self.db, order_id).await {
+                tracing::error!(%order_id, "Failed to rehydrate CFD: {e:#}");
+                continue;
+            }
+
+            rehydration::observe_latency(queued_at);
+        }
+
+        self.publish_cfds(ctx);
+    }
+}
+
 #[xtra_productivity]
 impl Actor {
-    async fn handle(&mut self, _: Initialize) {
-        let mut stream = self.db.load_all_cfds::<Cfd>(self.state.network);
+    async fn handle(&mut self, _: Initialize, ctx: &mut xtra::Context<Self>) {
+        let mut stream = self
+            .db
+            .load_all_cfds_with::<Cfd, _, _>(self.state.network, offload::fold);
 
         let mut cfds = HashMap::new();
 
@@ -1133,38 +1587,50 @@ impl Actor {
                 }
             };
 
-            cfds.insert(cfd.order_id, cfd);
+            cfds.insert(cfd.order_id, Arc::new(cfd));
         }
 
-        self.state.cfds = Some(cfds);
+        self.state.cfds = Some(Arc::new(cfds));
 
-        self.tx.send_cfds_update(
-            self.state
-                .cfds
-                .as_ref()
-                .expect("we initialized the state above; qed"),
-            &self.state.latest_quotes,
-        );
+        if self.role == Role::Taker {
+            self.load_cached_offers().await;
+        }
+
+        self.publish_cfds(ctx);
     }
 
-    async fn handle(&mut self, msg: CfdChanged) {
-        if let Err(e) = self.state.update_cfd(&self.db, msg.0).await {
-            tracing::error!("Failed to rehydrate CFD: {e:#}");
-            return;
-        };
+    async fn handle(&mut self, msg: CfdChanged, ctx: &mut xtra::Context<Self>) {
+        match self.rehydration_queue.push(msg.0) {
+            rehydration::Next::Schedule => {
+                // Changes that are already in the mailbox are queued before the batch is
+                // rehydrated. The mailbox is bounded, hence it is scheduled from a task, waiting
+                // for room in the mailbox here would never finish.
+                let this = ctx.address().expect("we are alive");
+                tokio_extras::spawn(&this.clone(), async move {
+                    if let Err(e) = this.send_async_safe(RehydrateChangedCfds).await {
+                        tracing::error!("Failed to schedule rehydration of changed CFDs: {e:#}");
+                    }
+                });
+            }
+            rehydration::Next::Wait => {}
+            rehydration::Next::RehydrateNow => self.rehydrate_changed_cfds(ctx).await,
+        }
+    }
 
-        self.tx.send_cfds_update(
-            self.state
-                .cfds
-                .as_ref()
-                .expect("update_cfd fails if the CFDs have not been initialized yet"),
-            &self.state.latest_quotes,
-        );
+    async fn handle(&mut self, _: RehydrateChangedCfds, ctx: &mut xtra::Context<Self>) {
+        self.rehydrate_changed_cfds(ctx).await;
     }
 
-    fn handle(&mut self, msg: Update<Vec<model::Offer>>) {
-        let new_offers = msg
-            .0
+    async fn handle(&mut self, msg: Update<Vec<model::Offer>>) {
+        let offers = msg.0;
+
+        if self.role == Role::Taker {
+            if let Err(e) = self.db.cache_offers(&offers, Timestamp::now()).await {
+                tracing::warn!("Failed to cache offers: {e:#}");
+            }
+        }
+
+        let new_offers = offers
             .into_iter()
             .filter_map(|offer| match CfdOffer::new(offer, self.role) {
                 Ok(offer) => Some(offer),
@@ -1182,23 +1648,98 @@ impl Actor {
         }
     }
 
-    fn handle(&mut self, msg: Update<LatestQuotes>) {
-        self.state.update_quotes(msg.0.clone());
-        self.tx.send_quotes_update(msg.0.clone());
+    async fn handle(&mut self, msg: Update<HashMap<libp2p_core::PeerId, Vec<model::Offer>>>) {
+        let mut makers = msg
+            .0
+            .into_iter()
+            .map(|(maker, offers)| OffersOfMaker {
+                maker: maker.into(),
+                offers: offers
+                    .into_iter()
+                    .filter_map(|offer| match CfdOffer::new(offer, self.role) {
+                        Ok(offer) => Some(offer),
+                        Err(e) => {
+                            tracing::warn!("Failed to build CfdOffer from model::Offer: {e:#}");
+                            None
+                        }
+                    })
+                    .collect(),
+            })
+            .collect_vec();
+        makers.sort_by_key(|offers| offers.maker.to_string());
 
-        match self
-            .state
-            .cfds
-            .as_ref()
-            .context("Cannot update CFDs with new quote until they are initialized.")
-        {
-            Ok(hydrated_cfds) => {
-                self.tx.send_cfds_update(hydrated_cfds, &msg.0);
-            }
-            Err(e) => {
-                tracing::debug!("{e:#}");
+        if let Err(e) = self.tx.send_offer_book_update(OfferBook { makers }) {
+            tracing::error!("Failed to propagate offer book update: {e:#}");
+        }
+    }
+
+    async fn handle(&mut self, _: OffersWithdrawn) {
+        self.state.offers = MakerOffers::default();
+
+        if self.role == Role::Taker {
+            if let Err(e) = self.db.clear_offer_cache().await {
+                tracing::warn!("Failed to clear offer cache: {e:#}");
             }
-        };
+        }
+
+        if let Err(e) = self.tx.send_offer_update(self.state.offers.clone()) {
+            tracing::error!("Failed to propagate offer update: {e:#}");
+        }
+    }
+
+    async fn handle(&mut self, msg: Update<Vec<Intent>>, ctx: &mut xtra::Context<Self>) {
+        self.state.intents = Arc::new(
+            msg.0
+                .into_iter()
+                .map(|intent| (intent.order_id, intent))
+                .collect(),
+        );
+
+        self.publish_cfds(ctx);
+    }
+
+    async fn handle(&mut self, msg: Update<Vec<CfdTag>>, ctx: &mut xtra::Context<Self>) {
+        let tags = msg.0.into_iter().fold(HashMap::new(), |mut tags, cfd_tag| {
+            tags.entry(cfd_tag.order_id)
+                .or_insert_with(Vec::new)
+                .push(cfd_tag.tag);
+            tags
+        });
+        self.state.tags = Arc::new(tags);
+
+        self.publish_cfds(ctx);
+    }
+
+    async fn handle(&mut self, msg: Update<Vec<RefundEstimate>>, ctx: &mut xtra::Context<Self>) {
+        let refund_estimates = msg
+            .0
+            .into_iter()
+            .map(|estimate| (estimate.order_id, estimate))
+            .collect::<HashMap<_, _>>();
+
+        // The monitor reports after every sync, the estimates only change with new blocks
+        if refund_estimates == *self.state.refund_estimates {
+            return;
+        }
+        self.state.refund_estimates = Arc::new(refund_estimates);
+
+        self.publish_cfds(ctx);
+    }
+
+    fn handle(&mut self, msg: Update<ChainTip>) {
+        self.tx.send_chain_tip_update(msg.0);
+    }
+
+    async fn handle(&mut self, msg: Update<LatestQuotes>, ctx: &mut xtra::Context<Self>) {
+        self.state.update_quotes(msg.0.clone());
+        self.tx.send_quotes_update(msg.0);
+
+        if self.state.cfds.is_none() {
+            tracing::debug!("Cannot update CFDs with new quote until they are initialized.");
+            return;
+        }
+
+        self.publish_cfds(ctx);
     }
 }
 
@@ -1247,11 +1788,11 @@ impl xtra::Actor for Actor {
 
 #[derive(Debug, Clone, Copy, Serialize)]
 pub struct Quote {
-    #[serde(with = "round_to_two_dp")]
-    bid: Decimal,
-    #[serde(with = "round_to_two_dp")]
-    ask: Decimal,
-    last_updated_at: Timestamp,
+    #[serde(with = "rounding::price")]
+    pub bid: Decimal,
+    #[serde(with = "rounding::price")]
+    pub ask: Decimal,
+    pub last_updated_at: Timestamp,
 }
 
 impl From<xtra_bitmex_price_feed::Quote> for Quote {
@@ -1282,6 +1823,47 @@ fn into_projection_quotes(latest_quotes: xtra_bitmex_price_feed::LatestQuotes) -
         .collect()
 }
 
+/// The latest block the monitor synced to and the health of the Electrum backend
+///
+/// Allows UIs to show how far the blockchain monitoring has progressed, e.g. "synced to block N,
+/// X seconds ago".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ChainTip {
+    pub height: u32,
+    /// Time of the last successful sync with the Electrum backend
+    pub last_synced_at: Timestamp,
+    /// Whether the most recent sync attempt reached the Electrum backend
+    pub electrum_healthy: bool,
+    /// The committed CFD that becomes refund-eligible first, if any
+    pub soonest_refund: Option<RefundEstimate>,
+}
+
+/// Estimate of when the refund transaction of a committed CFD can be published
+///
+/// The refund timelock starts once the commit transaction is confirmed. A CFD only gets close to
+/// it if no CET can be published, e.g. because the oracle did not attest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RefundEstimate {
+    pub order_id: OrderId,
+    /// Number of blocks until the refund timelock expires
+    pub blocks_remaining: u32,
+    /// Time until the refund timelock expires, assuming one block every ten minutes
+    pub estimated_secs_remaining: u64,
+}
+
+impl RefundEstimate {
+    /// Average time between two blocks in seconds
+    const BLOCK_INTERVAL_SECS: u64 = 600;
+
+    pub fn new(order_id: OrderId, blocks_remaining: u32) -> Self {
+        Self {
+            order_id,
+            blocks_remaining,
+            estimated_secs_remaining: u64::from(blocks_remaining) * Self::BLOCK_INTERVAL_SECS,
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
 pub struct MakerOffers {
     pub btcusd_long: Option<CfdOffer>,
@@ -1290,25 +1872,63 @@ pub struct MakerOffers {
     pub ethusd_short: Option<CfdOffer>,
 }
 
+impl MakerOffers {
+    /// Whether the offers were loaded from the cache and no fresh offers arrived yet.
+    pub fn is_cached(&self) -> bool {
+        [
+            &self.btcusd_long,
+            &self.btcusd_short,
+            &self.ethusd_long,
+            &self.ethusd_short,
+        ]
+        .into_iter()
+        .flatten()
+        .any(|offer| offer.cached_at.is_some())
+    }
+}
+
+/// The latest offers of every maker
+///
+/// Unlike [`MakerOffers`], which only has the preferred offer for each contract symbol and
+/// position, the offer book has the offers of all makers the taker receives offers from.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct OfferBook {
+    /// Ordered by the peer id of the maker
+    pub makers: Vec<OffersOfMaker>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OffersOfMaker {
+    pub maker: PeerId,
+    pub offers: Vec<CfdOffer>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct CfdOffer {
     pub id: OfferId,
 
     pub contract_symbol: ContractSymbol,
+    /// Multiplier the payout of a quanto contract is scaled with, `None` for inverse contracts
+    pub quanto_multiplier: Option<Decimal>,
 
     #[serde(rename = "position")]
     pub position_maker: Position,
 
     /// The maker's price for opening a position
-    #[serde(with = "round_to_two_dp")]
+    #[serde(with = "rounding::price")]
     pub price: Price,
 
     /// Fee charged by the maker for opening a position
     ///
     /// Note: It's a flat fee on top of the fee calculated based on funding rate
-    #[serde(with = "::bdk::bitcoin::util::amount::serde::as_btc::opt")]
+    #[serde(with = "rounding::fee::opt")]
     pub opening_fee: Option<Amount>,
 
+    /// Opening fees depending on the quantity, replacing `opening_fee` if not empty
+    ///
+    /// Use `POST /api/calculate/margin` to get the opening fee for a quantity.
+    pub opening_fee_tiers: OpeningFeeTiers,
+
     /// The interest as annualized percentage
     ///
     /// This is an estimate as the funding rate can fluctuate
@@ -1320,10 +1940,17 @@ pub struct CfdOffer {
     /// The funding rate fluctuates with market movements.
     pub funding_rate_hourly_percent: String,
 
-    #[serde(with = "round_to_two_dp")]
+    /// Determines when funding fees are charged upon rollover
+    pub funding_period: FundingPeriod,
+
+    #[serde(with = "rounding::quantity")]
     pub min_quantity: Contracts,
-    #[serde(with = "round_to_two_dp")]
+    #[serde(with = "rounding::quantity")]
     pub max_quantity: Contracts,
+    /// Largest quantity of a single order, if the maker caps the quantity per order
+    pub max_contracts_per_order: Option<Contracts>,
+    /// Weekly hours in UTC during which the maker accepts orders, always if `null`
+    pub trading_calendar: Option<TradingCalendar>,
 
     /// The user can only buy contracts in multiples of this.
     ///
@@ -1336,13 +1963,19 @@ pub struct CfdOffer {
 
     pub creation_timestamp: Timestamp,
     pub settlement_time_interval_in_secs: u64,
+
+    /// When the offer was received, only set if it was loaded from the cache on startup because
+    /// no fresh offer arrived yet
+    ///
+    /// Cached offers may be outdated, the maker is likely to reject orders on them.
+    pub cached_at: Option<Timestamp>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub struct LeverageDetails {
     pub leverage: Leverage,
     /// Own liquidation price according to position and leverage
-    #[serde(with = "round_to_two_dp")]
+    #[serde(with = "rounding::price")]
     pub liquidation_price: Decimal,
     /// Margin per lot from the perspective of the role
     ///
@@ -1386,7 +2019,7 @@ impl CfdOffer {
                 };
                 // Margin per lot price is dependent on one's own leverage
                 let margin_per_lot = calculate_margin(
-                    offer.contract_symbol,
+                    offer.payout_curve(),
                     offer.price,
                     lot_size.into(),
                     *leverage,
@@ -1402,7 +2035,7 @@ impl CfdOffer {
                     short_leverage,
                     offer.funding_rate,
                     SETTLEMENT_INTERVAL.whole_hours(),
-                    offer.contract_symbol,
+                    offer.payout_curve(),
                 )
                 .context("unable to calculate initial funding fee")?;
 
@@ -1424,10 +2057,13 @@ impl CfdOffer {
         Ok(Self {
             id: offer.id,
             contract_symbol: offer.contract_symbol,
+            quanto_multiplier: offer.payout_curve().quanto_multiplier(),
             position_maker: offer.position_maker,
             price: offer.price,
             min_quantity: offer.min_quantity,
             max_quantity: offer.max_quantity,
+            max_contracts_per_order: offer.max_contracts_per_order,
+            trading_calendar: offer.trading_calendar.clone(),
             lot_size,
             leverage_details,
             creation_timestamp: offer.creation_timestamp_maker,
@@ -1437,9 +2073,12 @@ impl CfdOffer {
                 .try_into()
                 .context("unable to convert settlement interval")?,
             opening_fee: Some(offer.opening_fee.to_inner()),
+            opening_fee_tiers: offer.opening_fee_tiers.clone(),
             funding_rate_annualized_percent: AnnualisedFundingPercent::from(offer.funding_rate)
                 .to_string(),
             funding_rate_hourly_percent: HourlyFundingPercent::from(offer.funding_rate).to_string(),
+            funding_period: offer.funding_period,
+            cached_at: None,
         })
     }
 }
@@ -1464,11 +2103,57 @@ pub enum CfdState {
     SetupFailed,
 }
 
+/// A change of the [`CfdState`] of a CFD, for rendering its history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TimelineEntry {
+    pub state: CfdState,
+    pub timestamp: Timestamp,
+    /// Name of the event that caused the change
+    pub event: String,
+}
+
+/// Derive the changes of the state of a CFD from its events, in the order they were recorded.
+///
+/// Events that do not change the state, e.g. setting price levels, are left out.
+pub fn timeline(role: Role, events: impl IntoIterator<Item = CfdEvent>) -> Vec<TimelineEntry> {
+    let mut lifecycle = Lifecycle::new();
+    let mut current = lifecycle.cfd_state(role);
+
+    events
+        .into_iter()
+        .filter_map(|event| {
+            lifecycle.apply(&event.event);
+
+            let state = lifecycle.cfd_state(role);
+            if state == current {
+                return None;
+            }
+            current = state;
+
+            Some(TimelineEntry {
+                state,
+                timestamp: event.timestamp,
+                event: event.event.to_string(),
+            })
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone, Serialize, PartialEq, Eq)]
 pub struct CfdDetails {
     tx_url_list: HashSet<TxUrl>,
 }
 
+impl CfdDetails {
+    /// URL of the transaction with the given label, if it is known.
+    pub fn tx_url(&self, label: TxLabel) -> Option<&str> {
+        self.tx_url_list
+            .iter()
+            .find(|tx_url| tx_url.label == label)
+            .map(|tx_url| tx_url.url.as_str())
+    }
+}
+
 #[derive(Debug, Clone, Copy, Display, FromStr, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 #[display(style = "camelCase")]
@@ -1479,103 +2164,15 @@ pub enum CfdAction {
     Settle,
     AcceptSettlement,
     RejectSettlement,
+    RollOver,
 }
 
-mod round_to_two_dp {
-    use super::*;
-    use serde::Serializer;
-
-    pub trait ToDecimal {
-        fn to_decimal(&self) -> Decimal;
-    }
-
-    impl ToDecimal for Contracts {
-        fn to_decimal(&self) -> Decimal {
-            self.into_decimal()
-        }
-    }
-
-    impl ToDecimal for Price {
-        fn to_decimal(&self) -> Decimal {
-            self.into_decimal()
-        }
-    }
-
-    impl ToDecimal for Decimal {
-        fn to_decimal(&self) -> Decimal {
-            *self
-        }
-    }
-
-    pub fn serialize<D: ToDecimal, S: Serializer>(
-        value: &D,
-        serializer: S,
-    ) -> Result<S::Ok, S::Error> {
-        let decimal = value.to_decimal();
-        let decimal = decimal.round_dp(2);
-
-        Serialize::serialize(&decimal, serializer)
-    }
-
-    pub mod opt {
-        use super::*;
-
-        pub fn serialize<D: ToDecimal, S: Serializer>(
-            value: &Option<D>,
-            serializer: S,
-        ) -> Result<S::Ok, S::Error> {
-            match value {
-                None => serializer.serialize_none(),
-                Some(value) => super::serialize(value, serializer),
-            }
-        }
-    }
-
-    #[cfg(test)]
-    mod tests {
-        use super::*;
-        use rust_decimal_macros::dec;
-        use serde_test::assert_ser_tokens;
-        use serde_test::Token;
-
-        #[derive(Serialize)]
-        #[serde(transparent)]
-        struct WithOnlyTwoDecimalPlaces<I: ToDecimal> {
-            #[serde(with = "super")]
-            inner: I,
-        }
-
-        #[test]
-        fn usd_serializes_with_only_cents() {
-            let quantity = WithOnlyTwoDecimalPlaces {
-                inner: model::Contracts::new(1000),
-            };
-
-            assert_ser_tokens(&quantity, &[Token::Str("1000")]);
-        }
-
-        #[test]
-        fn price_serializes_with_only_cents() {
-            let price = WithOnlyTwoDecimalPlaces {
-                inner: model::Price::new(dec!(1000.12345)).unwrap(),
-            };
-
-            assert_ser_tokens(&price, &[Token::Str("1000.12")]);
-        }
-    }
-}
-
-/// Construct a mempool.space URL for a given txid
-pub fn to_mempool_url(txid: Txid, network: Network) -> String {
-    match network {
-        Network::Bitcoin => format!("https://mempool.space/tx/{txid}"),
-        Network::Testnet => format!("https://mempool.space/testnet/tx/{txid}"),
-        Network::Signet => format!("https://mempool.space/signet/tx/{txid}"),
-        Network::Regtest => txid.to_string(),
-    }
+/// Construct a block explorer URL for a given txid, the txid itself if the network has no explorer
+pub fn tx_url(txid: Txid, network: Network) -> String {
+    explorer::tx_url(txid, network).unwrap_or_else(|| txid.to_string())
 }
 
-/// Link to transaction on mempool.space for UI representation
+/// Link to transaction on the block explorer for UI representation
 #[derive(Debug, Clone, Serialize, PartialEq, Eq, Hash)]
 struct TxUrl {
     pub label: TxLabel,
@@ -1586,14 +2183,16 @@ impl TxUrl {
     fn new(txid: Txid, network: Network, label: TxLabel) -> Self {
         Self {
             label,
-            url: to_mempool_url(txid, network),
+            url: tx_url(txid, network),
         }
     }
 
     /// Highlight particular transaction output in the TxUrl
-    fn with_output_index(mut self, index: u32) -> Self {
-        let _ = write!(self.url, ":{index}");
-        self
+    fn with_output(outpoint: OutPoint, network: Network, label: TxLabel) -> Self {
+        Self {
+            label,
+            url: explorer::output_url(outpoint, network).unwrap_or_else(|| outpoint.to_string()),
+        }
     }
 
     /// If the Transaction contains the script_pubkey, output will be selected
@@ -1605,11 +2204,9 @@ impl TxUrl {
         label: TxLabel,
     ) -> Self {
         debug_assert!(label != TxLabel::Commit, "commit transaction has a single output which does not belong to either party - this won't highlight anything");
-        let tx_url = Self::new(transaction.txid(), network, label);
-        if let Ok(outpoint) = transaction.outpoint(script_pubkey) {
-            tx_url.with_output_index(outpoint.vout)
-        } else {
-            tx_url
+        match transaction.outpoint(script_pubkey) {
+            Ok(outpoint) => Self::with_output(outpoint, network, label),
+            Err(_) => Self::new(transaction.txid(), network, label),
         }
     }
 }
@@ -1642,7 +2239,10 @@ impl From<FundingRate> for AnnualisedFundingPercent {
 
 impl fmt::Display for AnnualisedFundingPercent {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0.round_dp(2).fmt(f)
+        rounding::policy()
+            .funding_rate_annualized_percent
+            .round(self.0)
+            .fmt(f)
     }
 }
 
@@ -1699,6 +2299,87 @@ mod tests {
         assert_eq!(json, "\"SetupFailed\"");
     }
 
+    #[test]
+    fn timeline_only_contains_state_changes() {
+        let order_id = OrderId::default();
+        let event = |timestamp, event| CfdEvent {
+            timestamp: Timestamp::new(timestamp),
+            id: order_id,
+            event,
+        };
+
+        let timeline = timeline(
+            Role::Taker,
+            [
+                event(1, EventKind::ContractSetupStarted),
+                event(2, EventKind::ContractSetupCompleted { dlc: None }),
+                event(3, EventKind::LockConfirmed),
+                event(
+                    4,
+                    EventKind::PriceLevelsSet {
+                        take_profit: None,
+                        stop_loss: None,
+                    },
+                ),
+                event(5, EventKind::RolloverStarted),
+                event(6, EventKind::RolloverFailed),
+            ],
+        );
+
+        assert_eq!(
+            timeline
+                .iter()
+                .map(|entry| (entry.state, entry.timestamp, entry.event.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    CfdState::ContractSetup,
+                    Timestamp::new(1),
+                    "ContractSetupStarted"
+                ),
+                (
+                    CfdState::PendingOpen,
+                    Timestamp::new(2),
+                    "ContractSetupCompleted"
+                ),
+                (CfdState::Open, Timestamp::new(3), "LockConfirmed"),
+                (
+                    CfdState::RolloverSetup,
+                    Timestamp::new(5),
+                    "RolloverStarted"
+                ),
+                (CfdState::Open, Timestamp::new(6), "RolloverFailed"),
+            ]
+        );
+    }
+
+    #[test]
+    fn queued_intent_status_shows_remaining_time() {
+        let now = OffsetDateTime::from_unix_timestamp(1_000).unwrap();
+        let intent = |expires_in: i64, action| Intent {
+            order_id: OrderId::default(),
+            action,
+            created_at: Timestamp::new(1_000),
+            expires_at: Timestamp::new(1_000 + expires_in),
+        };
+
+        let settle = QueuedIntent::new(
+            &intent(
+                2 * 60 * 60,
+                IntentAction::Settle {
+                    payout_address: None,
+                },
+            ),
+            now,
+        )
+        .unwrap();
+        let rollover =
+            QueuedIntent::new(&intent(90 * 60 + 30, IntentAction::Rollover), now).unwrap();
+
+        assert_eq!(settle.status, "queued: close at market, expires in 2h");
+        assert_eq!(rollover.status, "queued: rollover, expires in 1h 30m");
+    }
+
     pub fn dummy_cfd() -> model::Cfd {
         model::Cfd::new(
             OrderId::default(),
@@ -1717,6 +2398,8 @@ mod tests {
             FundingRate::default(),
             TxFeeRate::default(),
             ContractSymbol::BtcUsd,
+            FundingPeriod::Hourly,
+            None,
         )
     }
 
@@ -1758,6 +2441,8 @@ mod tests {
             FundingRate::default(),
             TxFeeRate::default(),
             ContractSymbol::BtcUsd,
+            FundingPeriod::Hourly,
+            None,
         );
 
         let contract_setup_completed =
@@ -0,0 +1,144 @@
+//! Computation of the CFD feed off the projection's task.
+//!
+//! The feed is recomputed after every quote, which takes a while with many CFDs. The projection
+//! only takes a [`Snapshot`] of its state, which clones reference-counted pointers instead of the
+//! CFDs, and computes and publishes the feed on a spawned task. The projection's mailbox is not
+//! held up by the computation in the meantime.
+//!
+//! Feeds computed concurrently may finish out of order. Every feed therefore has a generation:
+//! feeds that are superseded before their computation starts are not computed, and feeds older
+//! than the last published one are dropped.
+
+use crate::missing_attestation;
+use crate::projection::Cfd;
+use crate::projection::LatestQuotes;
+use crate::projection::RefundEstimate;
+use conquer_once::Lazy;
+use itertools::Itertools;
+use model::OrderId;
+use prometheus::IntCounter;
+use sqlite_db::intents::Intent;
+use std::collections::HashMap;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use time::OffsetDateTime;
+
+static DROPPED_COUNTER: Lazy<IntCounter> = Lazy::new(|| {
+    prometheus::register_int_counter!(
+        "projection_cfd_feeds_dropped_total",
+        "Number of CFD feeds that were not published because a newer feed was requested."
+    )
+    .unwrap()
+});
+
+/// The state of the projection the CFD feed is computed from.
+///
+/// Cloning the state into a snapshot is cheap, the CFDs are only cloned when the feed is computed.
+pub struct Snapshot {
+    pub cfds: Arc<HashMap<OrderId, Arc<Cfd>>>,
+    pub quotes: LatestQuotes,
+    pub missing_attestation_policy: missing_attestation::Policy,
+    pub intents: Arc<HashMap<OrderId, Intent>>,
+    pub tags: Arc<HashMap<OrderId, Vec<String>>>,
+    pub refund_estimates: Arc<HashMap<OrderId, RefundEstimate>>,
+}
+
+impl Snapshot {
+    /// Compute the CFDs with the fields that depend on the current quote etc., newest first.
+    pub fn into_feed(self, now: OffsetDateTime) -> Vec<Cfd> {
+        self.cfds
+            .values()
+            .map(|cfd| {
+                let order_id = cfd.order_id;
+
+                Cfd::clone(cfd)
+                    .with_current_quote(Some(&self.quotes))
+                    .with_missing_attestation(self.missing_attestation_policy, now)
+                    .with_pending_age(now)
+                    .with_queued_intent(self.intents.get(&order_id), now)
+                    .with_tags(self.tags.get(&order_id))
+                    .with_refund_estimate(self.refund_estimates.get(&order_id))
+            })
+            .sorted_by(|a, b| {
+                Ord::cmp(
+                    &b.aggregated.creation_timestamp,
+                    &a.aggregated.creation_timestamp,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Generations of the CFD feed, shared by the tasks computing it.
+#[derive(Default)]
+pub struct Generations {
+    /// Generation of the most recently requested feed
+    requested: AtomicU64,
+    /// Generation of the most recently published feed
+    published: Mutex<u64>,
+}
+
+impl Generations {
+    /// Request a new feed, which supersedes all feeds requested before.
+    pub fn next(&self) -> u64 {
+        self.requested.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Whether a feed newer than `generation` was requested, in which case computing the feed of
+    /// `generation` is wasted.
+    pub fn is_superseded(&self, generation: u64) -> bool {
+        let superseded = self.requested.load(Ordering::SeqCst) > generation;
+        if superseded {
+            DROPPED_COUNTER.inc();
+        }
+
+        superseded
+    }
+
+    /// Publish the feed of `generation` through `publish`, unless a newer feed was published
+    /// already.
+    pub fn publish(&self, generation: u64, publish: impl FnOnce()) {
+        let mut published = self.published.lock().expect("lock not to be poisoned");
+
+        if *published > generation {
+            DROPPED_COUNTER.inc();
+            return;
+        }
+
+        publish();
+        *published = generation;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feed_finishing_after_newer_feed_is_dropped() {
+        let generations = Generations::default();
+        let older = generations.next();
+        let newer = generations.next();
+
+        let mut published = Vec::new();
+        generations.publish(newer, || published.push(newer));
+        generations.publish(older, || published.push(older));
+
+        assert_eq!(published, vec![newer]);
+    }
+
+    #[test]
+    fn only_feeds_requested_before_newer_feed_are_superseded() {
+        let generations = Generations::default();
+        let older = generations.next();
+
+        assert!(!generations.is_superseded(older));
+
+        let newer = generations.next();
+
+        assert!(generations.is_superseded(older));
+        assert!(!generations.is_superseded(newer));
+    }
+}
@@ -0,0 +1,261 @@
+//! Links to transactions on a block explorer.
+//!
+//! By default transactions link to mempool.space, except on regtest where only the txid is shown.
+//! Operators running their own explorer, e.g. a self-hosted mempool or blockstream.info, configure
+//! URL templates per network in [`CONFIG_FILE`] within the data directory:
+//!
+//! ```toml
+//! [bitcoin]
+//! tx = "https://blockstream.info/tx/{txid}"
+//! output = "https://blockstream.info/tx/{txid}?output:{vout}"
+//!
+//! [regtest]
+//! tx = "http://localhost:8080/tx/{txid}"
+//! ```
+//!
+//! `{txid}` is replaced with the txid and `{vout}` with the index of the highlighted output.
+//! Without `output` template, links to an output lead to the transaction. Networks without section
+//! keep their default.
+
+use anyhow::Context;
+use anyhow::Result;
+use bdk::bitcoin::Network;
+use bdk::bitcoin::OutPoint;
+use bdk::bitcoin::Txid;
+use conquer_once::Lazy;
+use conquer_once::OnceCell;
+use serde::Deserialize;
+use std::path::Path;
+
+pub const CONFIG_FILE: &str = "explorer.toml";
+
+const TXID_PLACEHOLDER: &str = "{txid}";
+const VOUT_PLACEHOLDER: &str = "{vout}";
+
+static EXPLORER: OnceCell<Explorer> = OnceCell::uninit();
+
+static DEFAULT_EXPLORER: Lazy<Explorer> = Lazy::new(Explorer::default);
+
+/// URL templates of a block explorer for one network.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Templates {
+    /// Link to a transaction, contains `{txid}`
+    pub tx: String,
+    /// Link to an output of a transaction, contains `{txid}` and `{vout}`
+    #[serde(default)]
+    pub output: Option<String>,
+}
+
+impl Templates {
+    fn mempool_space(path: &str) -> Self {
+        Self {
+            tx: format!("https://mempool.space{path}/tx/{TXID_PLACEHOLDER}"),
+            output: Some(format!(
+                "https://mempool.space{path}/tx/{TXID_PLACEHOLDER}:{VOUT_PLACEHOLDER}"
+            )),
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        anyhow::ensure!(
+            self.tx.contains(TXID_PLACEHOLDER),
+            "Template {} lacks {TXID_PLACEHOLDER}",
+            self.tx
+        );
+
+        if let Some(output) = &self.output {
+            anyhow::ensure!(
+                output.contains(TXID_PLACEHOLDER) && output.contains(VOUT_PLACEHOLDER),
+                "Template {output} lacks {TXID_PLACEHOLDER} or {VOUT_PLACEHOLDER}"
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Block explorer per network, `None` if transactions of the network are not linked.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Explorer {
+    pub bitcoin: Option<Templates>,
+    pub testnet: Option<Templates>,
+    pub signet: Option<Templates>,
+    pub regtest: Option<Templates>,
+}
+
+impl Default for Explorer {
+    fn default() -> Self {
+        Self {
+            bitcoin: Some(Templates::mempool_space("")),
+            testnet: Some(Templates::mempool_space("/testnet")),
+            signet: Some(Templates::mempool_space("/signet")),
+            regtest: None,
+        }
+    }
+}
+
+impl Explorer {
+    /// Load the explorer from the data directory, if it exists.
+    pub async fn load(data_dir: &Path) -> Result<Option<Self>> {
+        let path = data_dir.join(CONFIG_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = tokio::fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let explorer = Self::from_toml(&content)
+            .with_context(|| format!("Invalid explorer configuration in {}", path.display()))?;
+
+        Ok(Some(explorer))
+    }
+
+    pub fn from_toml(s: &str) -> Result<Self> {
+        let explorer = toml::from_str::<Self>(s)?;
+
+        for templates in [
+            &explorer.bitcoin,
+            &explorer.testnet,
+            &explorer.signet,
+            &explorer.regtest,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            templates.validate()?;
+        }
+
+        Ok(explorer)
+    }
+
+    fn templates(&self, network: Network) -> Option<&Templates> {
+        match network {
+            Network::Bitcoin => self.bitcoin.as_ref(),
+            Network::Testnet => self.testnet.as_ref(),
+            Network::Signet => self.signet.as_ref(),
+            Network::Regtest => self.regtest.as_ref(),
+        }
+    }
+
+    fn tx_url(&self, txid: Txid, network: Network) -> Option<String> {
+        let templates = self.templates(network)?;
+
+        Some(templates.tx.replace(TXID_PLACEHOLDER, &txid.to_string()))
+    }
+
+    fn output_url(&self, outpoint: OutPoint, network: Network) -> Option<String> {
+        let templates = self.templates(network)?;
+
+        let url = match &templates.output {
+            Some(output) => output
+                .replace(TXID_PLACEHOLDER, &outpoint.txid.to_string())
+                .replace(VOUT_PLACEHOLDER, &outpoint.vout.to_string()),
+            None => templates
+                .tx
+                .replace(TXID_PLACEHOLDER, &outpoint.txid.to_string()),
+        };
+
+        Some(url)
+    }
+}
+
+/// Link transactions to `explorer` from now on.
+///
+/// Fails if a different explorer was already configured, the explorer cannot change at runtime.
+pub fn init(explorer: Explorer) -> Result<()> {
+    if EXPLORER.try_init_once(|| explorer.clone()).is_err() {
+        anyhow::ensure!(
+            self::explorer() == &explorer,
+            "A different block explorer was already configured"
+        );
+    }
+
+    Ok(())
+}
+
+/// The explorer in effect, the default explorer if none was configured.
+pub fn explorer() -> &'static Explorer {
+    EXPLORER.get().unwrap_or(&DEFAULT_EXPLORER)
+}
+
+/// Link to the transaction, `None` if transactions of the network are not linked.
+pub fn tx_url(txid: Txid, network: Network) -> Option<String> {
+    explorer().tx_url(txid, network)
+}
+
+/// Link to the output of a transaction, `None` if transactions of the network are not linked.
+pub fn output_url(outpoint: OutPoint, network: Network) -> Option<String> {
+    explorer().output_url(outpoint, network)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const TXID: &str = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+
+    #[test]
+    fn given_no_templates_then_mempool_space_except_on_regtest() {
+        let explorer = Explorer::from_toml("").unwrap();
+        let txid = Txid::from_str(TXID).unwrap();
+
+        assert_eq!(explorer, Explorer::default());
+        assert_eq!(
+            explorer.tx_url(txid, Network::Testnet).unwrap(),
+            format!("https://mempool.space/testnet/tx/{TXID}")
+        );
+        assert_eq!(
+            explorer
+                .output_url(OutPoint::new(txid, 1), Network::Bitcoin)
+                .unwrap(),
+            format!("https://mempool.space/tx/{TXID}:1")
+        );
+        assert_eq!(explorer.tx_url(txid, Network::Regtest), None);
+    }
+
+    #[test]
+    fn given_templates_then_placeholders_are_replaced() {
+        let explorer = Explorer::from_toml(
+            r#"
+            [bitcoin]
+            tx = "https://blockstream.info/tx/{txid}"
+            output = "https://blockstream.info/tx/{txid}?output:{vout}"
+
+            [regtest]
+            tx = "http://localhost:8080/tx/{txid}"
+            "#,
+        )
+        .unwrap();
+        let txid = Txid::from_str(TXID).unwrap();
+
+        assert_eq!(
+            explorer
+                .output_url(OutPoint::new(txid, 2), Network::Bitcoin)
+                .unwrap(),
+            format!("https://blockstream.info/tx/{TXID}?output:2")
+        );
+        assert_eq!(
+            explorer
+                .output_url(OutPoint::new(txid, 2), Network::Regtest)
+                .unwrap(),
+            format!("http://localhost:8080/tx/{TXID}")
+        );
+        assert_eq!(explorer.signet, Explorer::default().signet);
+    }
+
+    #[test]
+    fn given_template_without_txid_then_config_rejected() {
+        let result = Explorer::from_toml(
+            r#"
+            [bitcoin]
+            tx = "https://blockstream.info/tx/"
+            "#,
+        );
+
+        assert!(result.is_err());
+    }
+}
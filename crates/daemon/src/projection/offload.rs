@@ -0,0 +1,125 @@
+//! Offloading of CPU-heavy work of the projection to the blocking thread pool.
+//!
+//! Applying hundreds of rollover events to a CFD or computing the payouts of all CFDs after every
+//! quote blocks the thread the projection runs on, which delays unrelated tasks scheduled on the
+//! same thread. Such work runs on the blocking thread pool instead. At most
+//! [`MAX_CONCURRENT_TASKS`] run at a time, so that the projection does not occupy the blocking
+//! thread pool which is shared with e.g. the wallet.
+
+use anyhow::Context;
+use anyhow::Result;
+use conquer_once::Lazy;
+use model::CfdEvent;
+use prometheus::HistogramVec;
+use sqlite_db::CfdAggregate;
+use std::time::Instant;
+use tokio::sync::Semaphore;
+
+/// Number of tasks of the projection that run on the blocking thread pool at the same time
+pub const MAX_CONCURRENT_TASKS: usize = 2;
+
+/// Number of events up to which applying them is cheap enough to do it on the calling task
+const MAX_INLINE_EVENTS: usize = 16;
+
+const TASK_LABEL: &str = "task";
+
+static PERMITS: Lazy<Semaphore> = Lazy::new(|| Semaphore::new(MAX_CONCURRENT_TASKS));
+
+static WAIT_HISTOGRAM: Lazy<HistogramVec> = Lazy::new(|| {
+    prometheus::register_histogram_vec!(
+        "projection_offload_wait_seconds",
+        "Time a task of the projection waited for a slot on the blocking thread pool.",
+        &[TASK_LABEL],
+        vec![0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0]
+    )
+    .unwrap()
+});
+
+static DURATION_HISTOGRAM: Lazy<HistogramVec> = Lazy::new(|| {
+    prometheus::register_histogram_vec!(
+        "projection_offload_duration_seconds",
+        "Time a task of the projection ran on the blocking thread pool.",
+        &[TASK_LABEL],
+        vec![0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0]
+    )
+    .unwrap()
+});
+
+#[derive(Debug, Clone, Copy)]
+pub enum Task {
+    /// Applying the events of a CFD
+    Fold,
+    /// Computing the CFD feed
+    CfdFeed,
+}
+
+impl Task {
+    fn label(&self) -> &'static str {
+        match self {
+            Task::Fold => "fold",
+            Task::CfdFeed => "cfd_feed",
+        }
+    }
+}
+
+/// Run `f` on the blocking thread pool, waiting for a slot if [`MAX_CONCURRENT_TASKS`] run already.
+pub async fn run<T>(task: Task, f: impl FnOnce() -> T + Send + 'static) -> Result<T>
+where
+    T: Send + 'static,
+{
+    let queued_at = Instant::now();
+    let _permit = PERMITS.acquire().await.expect("semaphore is never closed");
+    WAIT_HISTOGRAM
+        .with_label_values(&[task.label()])
+        .observe(queued_at.elapsed().as_secs_f64());
+
+    let span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || {
+        let _entered = span.enter();
+
+        let started_at = Instant::now();
+        let output = f();
+        DURATION_HISTOGRAM
+            .with_label_values(&[task.label()])
+            .observe(started_at.elapsed().as_secs_f64());
+
+        output
+    })
+    .await
+    .with_context(|| format!("Projection task {task:?} failed"))
+}
+
+/// Apply `events` to `cfd`, on the blocking thread pool unless there are only a few of them.
+///
+/// Meant to be passed to [`sqlite_db::Connection::load_open_cfd_with`].
+pub async fn fold<C>(cfd: C, events: Vec<CfdEvent>) -> Result<C>
+where
+    C: CfdAggregate,
+{
+    if events.len() <= MAX_INLINE_EVENTS {
+        return Ok(events.into_iter().fold(cfd, C::apply));
+    }
+
+    run(Task::Fold, move || events.into_iter().fold(cfd, C::apply)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn tasks_beyond_limit_wait_for_a_slot() {
+        let held = PERMITS
+            .acquire_many(MAX_CONCURRENT_TASKS as u32)
+            .await
+            .unwrap();
+
+        let mut task = tokio::spawn(run(Task::CfdFeed, || 42));
+        let waited = tokio::time::timeout(std::time::Duration::from_millis(50), &mut task).await;
+        assert!(waited.is_err(), "task ran although all slots were taken");
+
+        drop(held);
+
+        assert_eq!(task.await.unwrap().unwrap(), 42);
+    }
+}
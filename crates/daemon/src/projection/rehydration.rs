@@ -0,0 +1,150 @@
+//! Coalescing of CFD rehydrations in the projection.
+//!
+//! Bursts of `CfdChanged` messages, e.g. after a restart, would otherwise rehydrate the same CFD
+//! many times and publish the whole CFD feed after each of them. Instead, changed CFDs are queued
+//! once per order id and rehydrated in a batch, followed by a single feed update.
+//!
+//! Backpressure comes from the bounded mailbox of the projection, see
+//! [`super::MAILBOX_CAPACITY`]: senders of `CfdChanged` wait while it is full.
+//!
+//! There is deliberately no on-disk write-ahead queue for changed CFDs. The events table already is
+//! the durable record of every change and the projection is derived from it: changes that are
+//! still queued or in the mailbox when the daemon stops are picked up on startup, when the
+//! projection loads all CFDs from the database.
+
+use conquer_once::Lazy;
+use model::OrderId;
+use prometheus::Histogram;
+use prometheus::IntCounter;
+use prometheus::IntGauge;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Number of queued CFDs at which the batch is rehydrated right away
+///
+/// Keeps batches small while the scheduled rehydration still waits behind many changes in the
+/// mailbox.
+pub const MAX_QUEUE_DEPTH: usize = 500;
+
+static QUEUE_DEPTH_GAUGE: Lazy<IntGauge> = Lazy::new(|| {
+    prometheus::register_int_gauge!(
+        "projection_rehydration_queue_depth",
+        "Number of changed CFDs waiting to be rehydrated by the projection."
+    )
+    .unwrap()
+});
+
+static COALESCED_COUNTER: Lazy<IntCounter> = Lazy::new(|| {
+    prometheus::register_int_counter!(
+        "projection_rehydrations_coalesced_total",
+        "Number of CFD changes merged into an already queued rehydration."
+    )
+    .unwrap()
+});
+
+static LATENCY_HISTOGRAM: Lazy<Histogram> = Lazy::new(|| {
+    prometheus::register_histogram!(
+        "projection_rehydration_latency_seconds",
+        "Time from a CFD change until the projection rehydrated the CFD.",
+        vec![0.001, 0.005, 0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0]
+    )
+    .unwrap()
+});
+
+/// What to do after queueing a changed CFD
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Next {
+    /// Schedule the rehydration of the queued batch
+    Schedule,
+    /// A rehydration is already scheduled
+    Wait,
+    /// The queue is full, rehydrate the batch right away
+    RehydrateNow,
+}
+
+#[derive(Default)]
+pub struct Queue {
+    /// Changed CFDs and since when they are queued
+    pending: HashMap<OrderId, Instant>,
+    /// Queued CFDs in the order they changed first
+    order: Vec<OrderId>,
+    scheduled: bool,
+}
+
+impl Queue {
+    pub fn push(&mut self, order_id: OrderId) -> Next {
+        if self.pending.contains_key(&order_id) {
+            COALESCED_COUNTER.inc();
+        } else {
+            self.pending.insert(order_id, Instant::now());
+            self.order.push(order_id);
+        }
+
+        QUEUE_DEPTH_GAUGE.set(self.pending.len() as i64);
+
+        if self.pending.len() >= MAX_QUEUE_DEPTH {
+            return Next::RehydrateNow;
+        }
+
+        if self.scheduled {
+            return Next::Wait;
+        }
+
+        self.scheduled = true;
+        Next::Schedule
+    }
+
+    /// Take the queued batch, oldest changes first.
+    pub fn take(&mut self) -> Vec<(OrderId, Instant)> {
+        self.scheduled = false;
+        QUEUE_DEPTH_GAUGE.set(0);
+
+        let mut pending = std::mem::take(&mut self.pending);
+
+        std::mem::take(&mut self.order)
+            .into_iter()
+            .filter_map(|order_id| Some((order_id, pending.remove(&order_id)?)))
+            .collect()
+    }
+}
+
+/// Record how long a CFD waited to be rehydrated.
+pub fn observe_latency(queued_at: Instant) {
+    LATENCY_HISTOGRAM.observe(queued_at.elapsed().as_secs_f64());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_changes_of_one_cfd_are_coalesced() {
+        let mut queue = Queue::default();
+        let order_id = OrderId::default();
+        let other_order_id = OrderId::default();
+
+        assert_eq!(queue.push(order_id), Next::Schedule);
+        assert_eq!(queue.push(order_id), Next::Wait);
+        assert_eq!(queue.push(other_order_id), Next::Wait);
+
+        let batch = queue
+            .take()
+            .into_iter()
+            .map(|(order_id, _)| order_id)
+            .collect::<Vec<_>>();
+        assert_eq!(batch, vec![order_id, other_order_id]);
+
+        assert_eq!(queue.push(order_id), Next::Schedule);
+    }
+
+    #[test]
+    fn full_queue_is_rehydrated_right_away() {
+        let mut queue = Queue::default();
+
+        for _ in 0..MAX_QUEUE_DEPTH - 1 {
+            assert_ne!(queue.push(OrderId::default()), Next::RehydrateNow);
+        }
+
+        assert_eq!(queue.push(OrderId::default()), Next::RehydrateNow);
+    }
+}
@@ -0,0 +1,484 @@
+//! Rounding of the figures of the projection for display.
+//!
+//! Reports in different jurisdictions require different rounding of prices and fees. The
+//! [`Policy`] defines the decimal places and the rounding mode per kind of figure and is applied
+//! wherever the projection is presented, i.e. the HTTP API and the email notifications. It is
+//! loaded once on startup from [`CONFIG_FILE`] within the data directory:
+//!
+//! ```toml
+//! [price]
+//! decimal_places = 1
+//! mode = "half_up"
+//!
+//! [fee]
+//! decimal_places = 6
+//! mode = "down"
+//! ```
+//!
+//! Figures without a rule keep the default of [`Policy`], which rounds prices and quantities to
+//! two decimal places using banker's rounding.
+//!
+//! Without the file, figures are serialized exactly as before rounding was configurable: fees are
+//! serialized like [`bdk::bitcoin::util::amount::serde::as_btc`] and the closing price is not
+//! rounded at all.
+
+use anyhow::Context;
+use anyhow::Result;
+use bdk::bitcoin::Amount;
+use bdk::bitcoin::SignedAmount;
+use conquer_once::OnceCell;
+use model::Contracts;
+use model::Price;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use rust_decimal::RoundingStrategy;
+use serde::Deserialize;
+use serde::Serialize;
+use serde::Serializer;
+use std::path::Path;
+
+pub const CONFIG_FILE: &str = "rounding.toml";
+
+/// Highest number of decimal places of bitcoin amounts, i.e. satoshis
+const MAX_FEE_DECIMAL_PLACES: u32 = 8;
+
+/// Highest number of decimal places supported by [`Decimal`]
+const MAX_DECIMAL_PLACES: u32 = 28;
+
+static POLICY: OnceCell<Policy> = OnceCell::uninit();
+
+static DEFAULT_POLICY: Policy = Policy {
+    price: Rule::new(2),
+    quantity: Rule::new(2),
+    profit_percent: Rule::new(1),
+    funding_rate_annualized_percent: Rule::new(2),
+    fee: Rule::new(MAX_FEE_DECIMAL_PLACES),
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    /// Round to the nearest value, midpoints to the even neighbour (banker's rounding)
+    HalfEven,
+    /// Round to the nearest value, midpoints away from zero
+    HalfUp,
+    /// Round to the nearest value, midpoints towards zero
+    HalfDown,
+    /// Round towards zero, i.e. truncate
+    Down,
+    /// Round away from zero
+    Up,
+}
+
+impl From<RoundingMode> for RoundingStrategy {
+    fn from(mode: RoundingMode) -> Self {
+        match mode {
+            RoundingMode::HalfEven => RoundingStrategy::MidpointNearestEven,
+            RoundingMode::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            RoundingMode::HalfDown => RoundingStrategy::MidpointTowardZero,
+            RoundingMode::Down => RoundingStrategy::ToZero,
+            RoundingMode::Up => RoundingStrategy::AwayFromZero,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    pub decimal_places: u32,
+    #[serde(default = "half_even")]
+    pub mode: RoundingMode,
+}
+
+impl Rule {
+    const fn new(decimal_places: u32) -> Self {
+        Self {
+            decimal_places,
+            mode: RoundingMode::HalfEven,
+        }
+    }
+
+    pub fn round(&self, value: Decimal) -> Decimal {
+        value.round_dp_with_strategy(self.decimal_places, self.mode.into())
+    }
+}
+
+fn half_even() -> RoundingMode {
+    RoundingMode::HalfEven
+}
+
+/// Rounding rules per kind of figure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+    /// Prices, including liquidation prices and quotes
+    pub price: Rule,
+    /// Quantities in contracts
+    pub quantity: Rule,
+    pub profit_percent: Rule,
+    pub funding_rate_annualized_percent: Rule,
+    /// Fees in BTC
+    pub fee: Rule,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        DEFAULT_POLICY
+    }
+}
+
+impl Policy {
+    /// Load the policy from the data directory, if it exists.
+    pub async fn load(data_dir: &Path) -> Result<Option<Self>> {
+        let path = data_dir.join(CONFIG_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = tokio::fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let policy = Self::from_toml(&content)
+            .with_context(|| format!("Invalid rounding policy in {}", path.display()))?;
+
+        Ok(Some(policy))
+    }
+
+    pub fn from_toml(s: &str) -> Result<Self> {
+        let policy = toml::from_str::<Self>(s)?;
+
+        anyhow::ensure!(
+            policy.fee.decimal_places <= MAX_FEE_DECIMAL_PLACES,
+            "Fees cannot be rounded to more than {MAX_FEE_DECIMAL_PLACES} decimal places, got {}",
+            policy.fee.decimal_places
+        );
+        for rule in [
+            policy.price,
+            policy.quantity,
+            policy.profit_percent,
+            policy.funding_rate_annualized_percent,
+        ] {
+            anyhow::ensure!(
+                rule.decimal_places <= MAX_DECIMAL_PLACES,
+                "Cannot round to more than {MAX_DECIMAL_PLACES} decimal places, got {}",
+                rule.decimal_places
+            );
+        }
+
+        Ok(policy)
+    }
+}
+
+/// Apply `rules` to all figures presented from now on.
+///
+/// Fails if a different policy was already applied, the policy cannot change at runtime.
+pub fn init(rules: Policy) -> Result<()> {
+    if POLICY.try_init_once(|| rules).is_err() {
+        anyhow::ensure!(
+            policy() == &rules,
+            "A different rounding policy was already applied"
+        );
+    }
+
+    Ok(())
+}
+
+/// The policy in effect, the default policy if none was applied.
+pub fn policy() -> &'static Policy {
+    configured().unwrap_or(&DEFAULT_POLICY)
+}
+
+/// The policy applied through [`init`], if any.
+fn configured() -> Option<&'static Policy> {
+    POLICY.get()
+}
+
+pub trait ToDecimal {
+    fn to_decimal(&self) -> Decimal;
+}
+
+impl ToDecimal for Contracts {
+    fn to_decimal(&self) -> Decimal {
+        self.into_decimal()
+    }
+}
+
+impl ToDecimal for Price {
+    fn to_decimal(&self) -> Decimal {
+        self.into_decimal()
+    }
+}
+
+impl ToDecimal for Decimal {
+    fn to_decimal(&self) -> Decimal {
+        *self
+    }
+}
+
+impl ToDecimal for Amount {
+    fn to_decimal(&self) -> Decimal {
+        Decimal::new(self.as_sat() as i64, MAX_FEE_DECIMAL_PLACES)
+    }
+}
+
+impl ToDecimal for SignedAmount {
+    fn to_decimal(&self) -> Decimal {
+        Decimal::new(self.as_sat(), MAX_FEE_DECIMAL_PLACES)
+    }
+}
+
+/// Bitcoin amounts, for serializing fees.
+pub trait ToBtc: ToDecimal {
+    fn to_btc(&self) -> f64;
+}
+
+impl ToBtc for Amount {
+    fn to_btc(&self) -> f64 {
+        self.as_btc()
+    }
+}
+
+impl ToBtc for SignedAmount {
+    fn to_btc(&self) -> f64 {
+        self.as_btc()
+    }
+}
+
+/// Round a fee in BTC, e.g. for email notifications.
+pub fn format_fee(fee: impl ToDecimal) -> String {
+    format!("{} BTC", policy().fee.round(fee.to_decimal()))
+}
+
+fn serialize_opt<D, S: Serializer>(
+    value: &Option<D>,
+    serializer: S,
+    serialize: fn(&D, S) -> Result<S::Ok, S::Error>,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        None => serializer.serialize_none(),
+        Some(value) => serialize(value, serializer),
+    }
+}
+
+/// Serialize a price according to the policy.
+pub mod price {
+    use super::*;
+
+    pub fn serialize<D: ToDecimal, S: Serializer>(
+        value: &D,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        policy()
+            .price
+            .round(value.to_decimal())
+            .serialize(serializer)
+    }
+
+    pub mod opt {
+        use super::*;
+
+        pub fn serialize<D: ToDecimal, S: Serializer>(
+            value: &Option<D>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serialize_opt(value, serializer, super::serialize)
+        }
+    }
+}
+
+/// Serialize a price that is only rounded if a policy was applied.
+///
+/// Used for prices that were serialized as they are before rounding was configurable.
+pub mod configured_price {
+    use super::*;
+
+    pub mod opt {
+        use super::*;
+
+        pub fn serialize<D: ToDecimal + Serialize, S: Serializer>(
+            value: &Option<D>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match configured() {
+                Some(_) => super::super::price::opt::serialize(value, serializer),
+                None => value.serialize(serializer),
+            }
+        }
+    }
+}
+
+/// Serialize a quantity according to the policy.
+pub mod quantity {
+    use super::*;
+
+    pub fn serialize<D: ToDecimal, S: Serializer>(
+        value: &D,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        policy()
+            .quantity
+            .round(value.to_decimal())
+            .serialize(serializer)
+    }
+}
+
+/// Serialize a fee as BTC according to the policy.
+///
+/// Like [`bdk::bitcoin::util::amount::serde::as_btc`] the fee is serialized as a number, and
+/// exactly like it if no policy was applied.
+pub mod fee {
+    use super::*;
+
+    pub fn serialize<D: ToBtc, S: Serializer>(value: &D, serializer: S) -> Result<S::Ok, S::Error> {
+        let btc = match configured() {
+            Some(policy) => policy
+                .fee
+                .round(value.to_decimal())
+                .to_f64()
+                .unwrap_or_default(),
+            None => value.to_btc(),
+        };
+
+        serializer.serialize_f64(btc)
+    }
+
+    pub mod opt {
+        use super::*;
+
+        pub fn serialize<D: ToBtc, S: Serializer>(
+            value: &Option<D>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serialize_opt(value, serializer, super::serialize)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use serde_test::assert_ser_tokens;
+    use serde_test::Token;
+
+    #[derive(Serialize)]
+    #[serde(transparent)]
+    struct RoundedPrice<I: ToDecimal> {
+        #[serde(with = "price")]
+        inner: I,
+    }
+
+    #[derive(Serialize)]
+    #[serde(transparent)]
+    struct RoundedQuantity<I: ToDecimal> {
+        #[serde(with = "quantity")]
+        inner: I,
+    }
+
+    #[test]
+    fn usd_serializes_with_only_cents() {
+        let quantity = RoundedQuantity {
+            inner: model::Contracts::new(1000),
+        };
+
+        assert_ser_tokens(&quantity, &[Token::Str("1000")]);
+    }
+
+    #[test]
+    fn price_serializes_with_only_cents() {
+        let price = RoundedPrice {
+            inner: model::Price::new(dec!(1000.12345)).unwrap(),
+        };
+
+        assert_ser_tokens(&price, &[Token::Str("1000.12")]);
+    }
+
+    #[test]
+    fn without_policy_figures_serialize_as_before_rounding_was_configurable() {
+        #[derive(Serialize)]
+        struct Figures {
+            #[serde(with = "price")]
+            price: Price,
+            #[serde(with = "configured_price::opt")]
+            closing_price: Option<Price>,
+            #[serde(with = "quantity")]
+            quantity: Contracts,
+            #[serde(with = "fee")]
+            accumulated_fees: SignedAmount,
+            #[serde(with = "fee::opt")]
+            opening_fee: Option<Amount>,
+        }
+
+        #[derive(Serialize)]
+        struct Before {
+            price: Decimal,
+            closing_price: Option<Price>,
+            quantity: Decimal,
+            #[serde(with = "::bdk::bitcoin::util::amount::serde::as_btc")]
+            accumulated_fees: SignedAmount,
+            #[serde(with = "::bdk::bitcoin::util::amount::serde::as_btc::opt")]
+            opening_fee: Option<Amount>,
+        }
+
+        let figures = Figures {
+            price: Price::new(dec!(1000.125)).unwrap(),
+            closing_price: Some(Price::new(dec!(1000.12345)).unwrap()),
+            quantity: Contracts::new(1000),
+            accumulated_fees: SignedAmount::from_sat(-1_599),
+            opening_fee: Some(Amount::from_sat(12_345_678)),
+        };
+        let before = Before {
+            price: dec!(1000.125).round_dp(2),
+            closing_price: Some(Price::new(dec!(1000.12345)).unwrap()),
+            quantity: dec!(1000).round_dp(2),
+            accumulated_fees: SignedAmount::from_sat(-1_599),
+            opening_fee: Some(Amount::from_sat(12_345_678)),
+        };
+
+        assert_eq!(
+            serde_json::to_string(&figures).unwrap(),
+            serde_json::to_string(&before).unwrap()
+        );
+        assert_eq!(
+            format_fee(SignedAmount::from_sat(-1_599)),
+            SignedAmount::from_sat(-1_599).to_string()
+        );
+    }
+
+    #[test]
+    fn rules_apply_decimal_places_and_mode() {
+        let policy = Policy::from_toml(
+            r#"
+            [price]
+            decimal_places = 1
+            mode = "half_up"
+
+            [fee]
+            decimal_places = 6
+            mode = "down"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(policy.price.round(dec!(1000.25)), dec!(1000.3));
+        assert_eq!(policy.quantity.round(dec!(0.125)), dec!(0.12));
+        assert_eq!(
+            policy
+                .fee
+                .round(SignedAmount::from_sat(-1_599).to_decimal()),
+            dec!(-0.000015)
+        );
+    }
+
+    #[test]
+    fn fee_cannot_be_more_precise_than_satoshis() {
+        let result = Policy::from_toml(
+            r#"
+            [fee]
+            decimal_places = 9
+            "#,
+        );
+
+        assert!(result.is_err());
+    }
+}
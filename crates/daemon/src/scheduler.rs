@@ -0,0 +1,343 @@
+//! Scheduler for periodic maintenance jobs.
+//!
+//! Jobs are registered by name with a default interval which can be overridden on the command
+//! line. A job never runs concurrently with itself: if a run is still in progress when the job is
+//! due again, that run is skipped. The outcome of the last run of every job can be queried with
+//! [`GetJobStatuses`].
+
+use crate::wallet;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use bdk::bitcoin::Txid;
+use futures::future::BoxFuture;
+use futures::Future;
+use futures::FutureExt;
+use model::Timestamp;
+use serde::Serialize;
+use std::str::FromStr;
+use std::time::Duration;
+use std::time::Instant;
+use xtra::prelude::MessageChannel;
+use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+pub const DB_COMPACTION: &str = "db_compaction";
+pub const CLOSED_CFD_MIGRATION: &str = "closed_cfd_migration";
+pub const FAILED_CFD_MIGRATION: &str = "failed_cfd_migration";
+pub const UTXO_CONSOLIDATION: &str = "utxo_consolidation";
+
+/// Number of UTXOs above which the wallet is consolidated
+const MAX_UTXOS: usize = 50;
+
+type RunJob = Box<dyn Fn() -> BoxFuture<'static, Result<()>> + Send + Sync>;
+
+pub struct Job {
+    name: &'static str,
+    interval: Duration,
+    run: RunJob,
+}
+
+impl Job {
+    pub fn new<F, Fut>(name: &'static str, interval: Duration, run: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        Self {
+            name,
+            interval,
+            run: Box::new(move || run().boxed()),
+        }
+    }
+}
+
+/// The maintenance jobs of both maker and taker.
+pub fn maintenance_jobs(
+    db: sqlite_db::Connection,
+    wallet: MessageChannel<wallet::ConsolidateUtxos, Result<Option<Txid>>>,
+) -> Vec<Job> {
+    vec![
+        Job::new(DB_COMPACTION, Duration::from_secs(24 * 60 * 60), {
+            let db = db.clone();
+            move || {
+                let db = db.clone();
+                async move { db.compact().await }
+            }
+        }),
+        Job::new(CLOSED_CFD_MIGRATION, Duration::from_secs(5 * 60), {
+            let db = db.clone();
+            move || {
+                let db = db.clone();
+                async move { db.move_to_closed_cfds().await }
+            }
+        }),
+        Job::new(FAILED_CFD_MIGRATION, Duration::from_secs(30 * 60), {
+            move || {
+                let db = db.clone();
+                async move { db.move_to_failed_cfds().await }
+            }
+        }),
+        Job::new(UTXO_CONSOLIDATION, Duration::from_secs(24 * 60 * 60), {
+            move || {
+                let wallet = wallet.clone();
+                async move {
+                    wallet
+                        .send(wallet::ConsolidateUtxos {
+                            max_utxos: MAX_UTXOS,
+                        })
+                        .await
+                        .context("Wallet actor disconnected")??;
+
+                    Ok(())
+                }
+            }
+        }),
+    ]
+}
+
+/// Override of the interval of a job, parsed from `<name>=<seconds>`.
+///
+/// An interval of `0` disables the job.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobInterval {
+    pub name: String,
+    pub interval: Duration,
+}
+
+impl FromStr for JobInterval {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, seconds) = s
+            .split_once('=')
+            .context("Expected job interval in the format <name>=<seconds>")?;
+        let seconds = seconds
+            .parse()
+            .with_context(|| format!("Invalid number of seconds: {seconds}"))?;
+
+        Ok(Self {
+            name: name.to_owned(),
+            interval: Duration::from_secs(seconds),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JobStatus {
+    pub name: &'static str,
+    /// `0` if the job is disabled
+    pub interval_secs: u64,
+    pub running: bool,
+    pub num_runs: u64,
+    pub num_failures: u64,
+    /// When the last run started
+    pub last_run_at: Option<Timestamp>,
+    pub last_duration_ms: Option<u64>,
+    /// Error of the last run, if it failed
+    pub last_error: Option<String>,
+}
+
+impl JobStatus {
+    fn new(job: &Job) -> Self {
+        Self {
+            name: job.name,
+            interval_secs: job.interval.as_secs(),
+            running: false,
+            num_runs: 0,
+            num_failures: 0,
+            last_run_at: None,
+            last_duration_ms: None,
+            last_error: None,
+        }
+    }
+}
+
+pub struct Actor {
+    jobs: Vec<(Job, JobStatus)>,
+}
+
+impl Actor {
+    pub fn new(jobs: Vec<Job>, intervals: &[JobInterval]) -> Result<Self> {
+        let mut jobs = jobs;
+
+        for JobInterval { name, interval } in intervals {
+            match jobs.iter_mut().find(|job| job.name == name) {
+                Some(job) => job.interval = *interval,
+                None => {
+                    let known = jobs.iter().map(|job| job.name).collect::<Vec<_>>();
+                    bail!("Unknown job {name}, expected one of {}", known.join(", "))
+                }
+            }
+        }
+
+        Ok(Self {
+            jobs: jobs
+                .into_iter()
+                .map(|job| {
+                    let status = JobStatus::new(&job);
+                    (job, status)
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Get the status of all jobs.
+#[derive(Clone, Copy)]
+pub struct GetJobStatuses;
+
+#[derive(Clone, Copy)]
+struct Run {
+    index: usize,
+}
+
+struct Completed {
+    index: usize,
+    duration: Duration,
+    result: Result<()>,
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: GetJobStatuses) -> Vec<JobStatus> {
+        self.jobs.iter().map(|(_, status)| status.clone()).collect()
+    }
+
+    async fn handle(&mut self, msg: Run, ctx: &mut xtra::Context<Self>) {
+        let (job, status) = &mut self.jobs[msg.index];
+
+        if status.running {
+            tracing::debug!(job = %job.name, "Previous run still in progress, skipping run");
+            return;
+        }
+
+        status.running = true;
+        status.last_run_at = Some(Timestamp::now());
+
+        let index = msg.index;
+        let run = (job.run)();
+        let this = ctx.address().expect("we are alive");
+
+        tokio_extras::spawn(&this.clone(), async move {
+            let started = Instant::now();
+            let result = run.await;
+            let duration = started.elapsed();
+
+            let _ = this
+                .send(Completed {
+                    index,
+                    duration,
+                    result,
+                })
+                .await;
+        });
+    }
+
+    async fn handle(&mut self, msg: Completed) {
+        let (job, status) = &mut self.jobs[msg.index];
+
+        status.running = false;
+        status.num_runs += 1;
+        status.last_duration_ms = Some(msg.duration.as_millis() as u64);
+        status.last_error = match msg.result {
+            Ok(()) => {
+                tracing::debug!(job = %job.name, duration_ms = %msg.duration.as_millis(), "Job completed");
+                None
+            }
+            Err(e) => {
+                tracing::warn!(job = %job.name, "Job failed: {e:#}");
+                status.num_failures += 1;
+                Some(format!("{e:#}"))
+            }
+        };
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we just started");
+
+        for (index, (job, _)) in self.jobs.iter().enumerate() {
+            if job.interval.is_zero() {
+                tracing::info!(job = %job.name, "Job is disabled");
+                continue;
+            }
+
+            tokio_extras::spawn(
+                &this.clone(),
+                this.clone().send_interval(
+                    job.interval,
+                    move || Run { index },
+                    xtras::IncludeSpan::Never,
+                ),
+            );
+        }
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_job_interval() {
+        let interval = "db_compaction=3600".parse::<JobInterval>().unwrap();
+
+        assert_eq!(
+            interval,
+            JobInterval {
+                name: DB_COMPACTION.to_owned(),
+                interval: Duration::from_secs(3600)
+            }
+        );
+        assert!("db_compaction".parse::<JobInterval>().is_err());
+        assert!("db_compaction=1h".parse::<JobInterval>().is_err());
+    }
+
+    #[test]
+    fn interval_overrides_default_interval() {
+        let actor = Actor::new(
+            vec![dummy_job(DB_COMPACTION), dummy_job(CLOSED_CFD_MIGRATION)],
+            &[JobInterval {
+                name: CLOSED_CFD_MIGRATION.to_owned(),
+                interval: Duration::ZERO,
+            }],
+        )
+        .unwrap();
+
+        let intervals = actor
+            .jobs
+            .iter()
+            .map(|(_, status)| (status.name, status.interval_secs))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            intervals,
+            vec![(DB_COMPACTION, 60), (CLOSED_CFD_MIGRATION, 0)]
+        );
+    }
+
+    #[test]
+    fn given_interval_of_unknown_job_then_rejected() {
+        let result = Actor::new(
+            vec![dummy_job(DB_COMPACTION)],
+            &[JobInterval {
+                name: "peer_store_pruning".to_owned(),
+                interval: Duration::from_secs(60),
+            }],
+        );
+
+        assert!(result.is_err());
+    }
+
+    fn dummy_job(name: &'static str) -> Job {
+        Job::new(name, Duration::from_secs(60), || async { Ok(()) })
+    }
+}
@@ -1,3 +1,4 @@
+use crate::backup;
 use anyhow::anyhow;
 use anyhow::bail;
 use anyhow::Result;
@@ -80,6 +81,17 @@ pub trait Seed {
         )
     }
 
+    /// Derive the key used to encrypt the CFD backups.
+    fn derive_backup_key(&self) -> backup::Key {
+        let mut key = [0u8; 32];
+
+        Hkdf::<Sha256>::new(None, &self.seed())
+            .expand(b"CFD_BACKUP_KEY", &mut key)
+            .expect("okm array is of correct length");
+
+        backup::Key::from(key)
+    }
+
     fn derive_identities(&self) -> Identities {
         let (identity_pk, identity_sk) = self.derive_identity();
         let keypair_libp2p = self.derive_ed25519_keypair();
@@ -125,7 +137,8 @@ impl RandomSeed {
         Ok(seed)
     }
 
-    async fn read_from(path: &Path) -> Result<Self> {
+    /// Read an existing [`Seed`] from a path.
+    pub async fn read_from(path: &Path) -> Result<Self> {
         let bytes = tokio::fs::read(path).await?;
 
         let bytes = bytes
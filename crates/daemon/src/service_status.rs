@@ -0,0 +1,153 @@
+//! Operational status of the maker.
+//!
+//! Being connected to the maker does not tell the taker whether the maker is currently taking
+//! orders or rolling over CFDs. The maker therefore sends its [`ServiceStatus`] to every taker that
+//! connects and to all connected takers whenever the operator changes it. Besides whether new
+//! orders and rollovers are accepted, the status carries an optional maintenance message and the
+//! minimum taker version the maker supports, so the taker can tell its user why actions are
+//! disabled rather than only that the maker is online.
+
+use anyhow::ensure;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+
+pub mod maker;
+pub mod taker;
+
+pub const PROTOCOL: &str = "/itchysats/service-status/1.0.0";
+
+/// Maximum size of an encoded status in bytes
+pub const MAX_FRAME_SIZE: usize = 4 * 1024;
+
+/// Maximum length of the maintenance message in bytes
+pub const MAX_MAINTENANCE_MESSAGE_LEN: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceStatus {
+    pub accepting_orders: bool,
+    pub accepting_rollovers: bool,
+    #[serde(default)]
+    pub maintenance_message: Option<String>,
+    /// Oldest taker version the maker supports, e.g. `0.7.0`
+    #[serde(default)]
+    pub min_taker_version: Option<String>,
+}
+
+impl ServiceStatus {
+    pub fn validate(&self) -> Result<()> {
+        if let Some(message) = &self.maintenance_message {
+            ensure!(
+                message.len() <= MAX_MAINTENANCE_MESSAGE_LEN,
+                "Maintenance message exceeds {MAX_MAINTENANCE_MESSAGE_LEN} bytes"
+            );
+        }
+
+        if let Some(version) = &self.min_taker_version {
+            ensure!(
+                parse_version(version).is_some(),
+                "Minimum taker version {version} is not of the form major.minor.patch"
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for ServiceStatus {
+    fn default() -> Self {
+        Self {
+            accepting_orders: true,
+            accepting_rollovers: true,
+            maintenance_message: None,
+            min_taker_version: None,
+        }
+    }
+}
+
+/// The status of the maker as surfaced to the user of the taker
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MakerServiceStatus {
+    #[serde(flatten)]
+    pub status: ServiceStatus,
+    /// Whether this taker is older than the minimum taker version of the maker
+    pub taker_outdated: bool,
+}
+
+impl MakerServiceStatus {
+    pub fn new(status: ServiceStatus, taker_version: &str) -> Self {
+        let taker_outdated = status
+            .min_taker_version
+            .as_deref()
+            .map_or(false, |min_version| is_outdated(taker_version, min_version));
+
+        Self {
+            status,
+            taker_outdated,
+        }
+    }
+}
+
+/// Whether `version` is older than `min_version`.
+///
+/// Only major, minor and patch are compared, pre-release and build metadata are ignored. Versions
+/// that cannot be parsed are never considered outdated.
+fn is_outdated(version: &str, min_version: &str) -> bool {
+    match (parse_version(version), parse_version(min_version)) {
+        (Some(version), Some(min_version)) => version < min_version,
+        _ => false,
+    }
+}
+
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let core = version.split(|c| c == '-' || c == '+').next()?;
+
+    let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+    let parsed = (parts.next()??, parts.next()??, parts.next()??);
+
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn older_versions_are_outdated() {
+        assert!(is_outdated("0.6.9", "0.7.0"));
+        assert!(is_outdated("0.7.0-rc.1", "0.7.1"));
+        assert!(!is_outdated("0.7.0", "0.7.0"));
+        assert!(!is_outdated("1.0.0", "0.7.0"));
+        assert!(!is_outdated("unknown", "0.7.0"));
+    }
+
+    #[test]
+    fn status_with_invalid_min_taker_version_is_rejected() {
+        let status = ServiceStatus {
+            min_taker_version: Some("0.7".to_owned()),
+            ..ServiceStatus::default()
+        };
+
+        assert!(status.validate().is_err());
+    }
+
+    #[test]
+    fn status_without_optional_fields_deserializes() {
+        let status = serde_json::from_str::<ServiceStatus>(
+            r#"{"accepting_orders":false,"accepting_rollovers":true}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            status,
+            ServiceStatus {
+                accepting_orders: false,
+                ..ServiceStatus::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use crate::service_status::ServiceStatus;
+use crate::service_status::MAX_FRAME_SIZE;
+use crate::service_status::PROTOCOL;
+use anyhow::Result;
+use async_trait::async_trait;
+use asynchronous_codec::FramedWrite;
+use futures::SinkExt;
+use std::collections::HashSet;
+use xtra_libp2p::endpoint;
+use xtra_libp2p::libp2p::PeerId;
+use xtra_libp2p::limited::LimitedJsonCodec;
+use xtra_libp2p::Endpoint;
+use xtra_libp2p::GetConnectionStats;
+use xtra_libp2p::OpenSubstream;
+use xtra_productivity::xtra_productivity;
+
+/// Sends the status of the maker to connected takers
+pub struct Actor {
+    endpoint: xtra::Address<Endpoint>,
+    connected_peers: HashSet<PeerId>,
+    status: ServiceStatus,
+}
+
+impl Actor {
+    pub fn new(endpoint: xtra::Address<Endpoint>) -> Self {
+        Self {
+            endpoint,
+            connected_peers: HashSet::default(),
+            status: ServiceStatus::default(),
+        }
+    }
+
+    fn send_status(&self, peer_id: PeerId, ctx: &mut xtra::Context<Self>) {
+        let endpoint = self.endpoint.clone();
+        let status = self.status.clone();
+
+        let task = async move {
+            let stream = endpoint
+                .send(OpenSubstream::single_protocol(peer_id, PROTOCOL))
+                .await??
+                .await?;
+
+            let mut framed = FramedWrite::new(
+                stream,
+                LimitedJsonCodec::<ServiceStatus, ()>::new(MAX_FRAME_SIZE),
+            );
+            framed.send(status).await?;
+
+            anyhow::Ok(())
+        };
+
+        let err_handler = move |e: anyhow::Error| async move {
+            match e.downcast_ref::<xtra_libp2p::Error>() {
+                Some(xtra_libp2p::Error::ProtocolNotSupportedByPeer) => {
+                    // Takers that predate the protocol do not listen for it
+                }
+                _ => tracing::debug!(%peer_id, "Failed to send service status: {e:#}"),
+            }
+        };
+
+        let this = ctx.address().expect("self to be alive");
+        tokio_extras::spawn_fallible(&this, task, err_handler);
+    }
+}
+
+/// Replace the status of the maker and send it to all connected takers.
+pub struct UpdateServiceStatus(pub ServiceStatus);
+
+#[derive(Clone, Copy)]
+pub struct GetServiceStatus;
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(
+        &mut self,
+        msg: UpdateServiceStatus,
+        ctx: &mut xtra::Context<Self>,
+    ) -> Result<()> {
+        let status = msg.0;
+        status.validate()?;
+
+        tracing::info!(?status, "Updated service status");
+        self.status = status;
+
+        for peer_id in self.connected_peers.iter().copied() {
+            self.send_status(peer_id, ctx);
+        }
+
+        Ok(())
+    }
+
+    async fn handle(&mut self, _: GetServiceStatus) -> ServiceStatus {
+        self.status.clone()
+    }
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle_connection_established(
+        &mut self,
+        msg: endpoint::ConnectionEstablished,
+        ctx: &mut xtra::Context<Self>,
+    ) {
+        self.connected_peers.insert(msg.peer_id);
+        self.send_status(msg.peer_id, ctx);
+    }
+
+    async fn handle_connection_dropped(&mut self, msg: endpoint::ConnectionDropped) {
+        self.connected_peers.remove(&msg.peer_id);
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, _: &mut xtra::Context<Self>) {
+        match self.endpoint.send(GetConnectionStats).await {
+            Ok(connection_stats) => self
+                .connected_peers
+                .extend(connection_stats.connected_peers),
+            Err(e) => tracing::warn!(
+                "Unable to receive connection stats from the endpoint upon startup: {e:#}"
+            ),
+        }
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
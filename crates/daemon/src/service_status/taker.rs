@@ -0,0 +1,107 @@
+use crate::service_status::MakerServiceStatus;
+use crate::service_status::ServiceStatus;
+use crate::service_status::MAX_FRAME_SIZE;
+use anyhow::bail;
+use anyhow::Context;
+use async_trait::async_trait;
+use asynchronous_codec::FramedRead;
+use futures::StreamExt;
+use libp2p_core::PeerId;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio_extras::FutureExt;
+use xtra_libp2p::limited::LimitedJsonCodec;
+use xtra_libp2p::NewInboundSubstream;
+use xtra_productivity::xtra_productivity;
+
+const STATUS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Receives the status of the maker
+///
+/// The status is `None` until the maker sent it for the first time, i.e. also if the maker does
+/// not support the protocol yet.
+pub struct Actor {
+    maker_peer_id: PeerId,
+    sender: watch::Sender<Option<MakerServiceStatus>>,
+}
+
+impl Actor {
+    pub fn new(maker_peer_id: PeerId) -> (Self, watch::Receiver<Option<MakerServiceStatus>>) {
+        let (sender, receiver) = watch::channel(None);
+
+        (
+            Self {
+                maker_peer_id,
+                sender,
+            },
+            receiver,
+        )
+    }
+}
+
+struct StatusReceived(ServiceStatus);
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: NewInboundSubstream, ctx: &mut xtra::Context<Self>) {
+        let NewInboundSubstream { peer_id, stream } = msg;
+        let maker_peer_id = self.maker_peer_id;
+        let this = ctx.address().expect("self to be alive");
+
+        let task = {
+            let this = this.clone();
+            async move {
+                if peer_id != maker_peer_id {
+                    bail!("Service status from peer that is not the maker");
+                }
+
+                let mut framed = FramedRead::new(
+                    stream,
+                    LimitedJsonCodec::<(), ServiceStatus>::new(MAX_FRAME_SIZE),
+                );
+
+                let status = framed
+                    .next()
+                    .timeout(STATUS_TIMEOUT, || {
+                        tracing::debug_span!("receive service status")
+                    })
+                    .await
+                    .context("Maker did not send status in time")?
+                    .context("Stream terminated")?
+                    .context("Failed to decode status")?;
+
+                this.send(StatusReceived(status)).await?;
+
+                anyhow::Ok(())
+            }
+        };
+
+        let err_handler = move |e: anyhow::Error| async move {
+            tracing::debug!(%peer_id, "Failed to receive service status: {e:#}")
+        };
+
+        tokio_extras::spawn_fallible(&this, task, err_handler);
+    }
+
+    async fn handle(&mut self, msg: StatusReceived) {
+        let status = MakerServiceStatus::new(msg.0, &crate::version());
+
+        if status.taker_outdated {
+            tracing::warn!(
+                min_taker_version = ?status.status.min_taker_version,
+                "The maker requires a newer taker version, please upgrade"
+            );
+        }
+
+        tracing::debug!(?status, "Received service status of maker");
+
+        let _ = self.sender.send(Some(status));
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn stopped(self) -> Self::Stop {}
+}
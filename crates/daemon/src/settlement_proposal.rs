@@ -0,0 +1,17 @@
+//! Settlement proposals of the maker.
+//!
+//! Collaborative settlement is always initiated by the taker. The maker can however ask takers to
+//! settle, e.g. when winding down. Such a proposal carries the price the maker would settle at.
+//! The taker compares it with the price it closes at according to its own latest quote: if the
+//! deviation is within the configured tolerance the taker proposes settlement right away,
+//! otherwise the proposal is surfaced for the user to decide on. Either way the decision and its
+//! rationale are recorded as an event of the CFD.
+
+pub mod maker;
+mod protocol;
+pub mod taker;
+
+pub const PROTOCOL: &str = "/itchysats/settlement-proposal/1.0.0";
+
+/// Maximum size of an encoded settlement proposal in bytes
+pub const MAX_FRAME_SIZE: usize = 1024;
@@ -0,0 +1,114 @@
+use crate::into_price_feed_symbol;
+use crate::settlement_proposal::protocol::Proposal;
+use crate::settlement_proposal::MAX_FRAME_SIZE;
+use crate::settlement_proposal::PROTOCOL;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use asynchronous_codec::FramedWrite;
+use futures::SinkExt;
+use model::market_closing_price;
+use model::OrderId;
+use model::Price;
+use model::Role;
+use time::ext::NumericalDuration;
+use xtra::prelude::MessageChannel;
+use xtra_bitmex_price_feed::GetLatestQuotes;
+use xtra_bitmex_price_feed::LatestQuotes;
+use xtra_bitmex_price_feed::QUOTE_INTERVAL_MINUTES;
+use xtra_libp2p::limited::LimitedJsonCodec;
+use xtra_libp2p::Endpoint;
+use xtra_libp2p::OpenSubstream;
+use xtra_productivity::xtra_productivity;
+
+/// Proposes takers to settle their CFDs at the current market price
+pub struct Actor {
+    endpoint: xtra::Address<Endpoint>,
+    price_feed: MessageChannel<GetLatestQuotes, LatestQuotes>,
+    db: sqlite_db::Connection,
+}
+
+impl Actor {
+    pub fn new(
+        endpoint: xtra::Address<Endpoint>,
+        price_feed: MessageChannel<GetLatestQuotes, LatestQuotes>,
+        db: sqlite_db::Connection,
+    ) -> Self {
+        Self {
+            endpoint,
+            price_feed,
+            db,
+        }
+    }
+}
+
+/// Propose the taker of a CFD to settle it collaboratively.
+///
+/// Whether the taker settles is up to the taker; the maker only learns about it through the
+/// settlement proposal the taker sends if it does.
+#[derive(Clone, Copy)]
+pub struct ProposeSettlement {
+    pub order_id: OrderId,
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: ProposeSettlement) -> Result<()> {
+        let ProposeSettlement { order_id } = msg;
+
+        let cfd = self.db.load_open_cfd::<model::Cfd>(order_id, ()).await?;
+        let taker_peer_id = cfd
+            .counterparty_peer_id()
+            .context("No counterparty peer id found")?;
+
+        let quotes = self
+            .price_feed
+            .send(GetLatestQuotes)
+            .await
+            .context("Price feed not available")?;
+        let quote = quotes
+            .get(&into_price_feed_symbol(cfd.contract_symbol()))
+            .context("No quote available")?;
+
+        if quote.is_older_than(QUOTE_INTERVAL_MINUTES.minutes() * 2) {
+            bail!("Latest quote is too old to propose settlement at market");
+        }
+
+        let price = market_closing_price(
+            Price::new(quote.bid())?,
+            Price::new(quote.ask())?,
+            Role::Maker,
+            cfd.position(),
+        );
+
+        let stream = self
+            .endpoint
+            .send(OpenSubstream::single_protocol(
+                taker_peer_id.inner(),
+                PROTOCOL,
+            ))
+            .await
+            .context("Endpoint is disconnected")?
+            .context("No connection to taker")?
+            .await
+            .context("Failed to open substream")?;
+
+        let mut framed = FramedWrite::new(
+            stream,
+            LimitedJsonCodec::<Proposal, ()>::new(MAX_FRAME_SIZE),
+        );
+        framed.send(Proposal { order_id, price }).await?;
+
+        tracing::info!(%order_id, %taker_peer_id, %price, "Proposed settlement to taker");
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn stopped(self) -> Self::Stop {}
+}
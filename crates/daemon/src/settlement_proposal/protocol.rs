@@ -0,0 +1,11 @@
+use model::OrderId;
+use model::Price;
+use serde::Deserialize;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct Proposal {
+    pub order_id: OrderId,
+    /// The price the maker proposes to settle at
+    pub price: Price,
+}
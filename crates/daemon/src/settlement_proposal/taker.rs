@@ -0,0 +1,157 @@
+use crate::command;
+use crate::into_price_feed_symbol;
+use crate::settlement_proposal::protocol::Proposal;
+use crate::settlement_proposal::MAX_FRAME_SIZE;
+use crate::taker_cfd;
+use anyhow::bail;
+use anyhow::Context;
+use async_trait::async_trait;
+use asynchronous_codec::FramedRead;
+use futures::StreamExt;
+use model::market_closing_price;
+use model::Price;
+use model::Role;
+use model::SettlementPriceCheck;
+use rust_decimal::Decimal;
+use std::time::Duration;
+use time::ext::NumericalDuration;
+use tokio_extras::FutureExt;
+use xtra::prelude::MessageChannel;
+use xtra::Address;
+use xtra_bitmex_price_feed::GetLatestQuotes;
+use xtra_bitmex_price_feed::LatestQuotes;
+use xtra_bitmex_price_feed::QUOTE_INTERVAL_MINUTES;
+use xtra_libp2p::limited::LimitedJsonCodec;
+use xtra_libp2p::NewInboundSubstream;
+use xtra_productivity::xtra_productivity;
+
+const PROPOSAL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Decides on settlement proposals of the maker
+///
+/// Proposals within `tolerance` of the latest quote are accepted by proposing settlement at the
+/// latest quote, all other proposals are left for the user to decide on.
+pub struct Actor {
+    executor: command::Executor,
+    price_feed: MessageChannel<GetLatestQuotes, LatestQuotes>,
+    cfd_actor: Address<taker_cfd::Actor>,
+    tolerance: Option<Decimal>,
+}
+
+impl Actor {
+    pub fn new(
+        executor: command::Executor,
+        price_feed: MessageChannel<GetLatestQuotes, LatestQuotes>,
+        cfd_actor: Address<taker_cfd::Actor>,
+        tolerance: Option<Decimal>,
+    ) -> Self {
+        Self {
+            executor,
+            price_feed,
+            cfd_actor,
+            tolerance,
+        }
+    }
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: NewInboundSubstream, ctx: &mut xtra::Context<Self>) {
+        let NewInboundSubstream { peer_id, stream } = msg;
+        let executor = self.executor.clone();
+        let price_feed = self.price_feed.clone();
+        let cfd_actor = self.cfd_actor.clone();
+        let tolerance = self.tolerance;
+
+        let task = async move {
+            let mut framed = FramedRead::new(
+                stream,
+                LimitedJsonCodec::<(), Proposal>::new(MAX_FRAME_SIZE),
+            );
+
+            let Proposal { order_id, price } = framed
+                .next()
+                .timeout(PROPOSAL_TIMEOUT, || {
+                    tracing::debug_span!("receive settlement proposal")
+                })
+                .await
+                .context("Maker did not send proposal in time")?
+                .context("Stream terminated")?
+                .context("Failed to decode proposal")?;
+
+            let (contract_symbol, position, counterparty) = executor
+                .query(order_id, |cfd| {
+                    Ok((
+                        cfd.contract_symbol(),
+                        cfd.position(),
+                        cfd.counterparty_peer_id(),
+                    ))
+                })
+                .await?;
+
+            if counterparty.map(|counterparty| counterparty.inner()) != Some(peer_id) {
+                bail!(
+                    "Settlement proposal for CFD {order_id} from peer that is not its counterparty"
+                );
+            }
+
+            let quotes = price_feed
+                .send(GetLatestQuotes)
+                .await
+                .context("Price feed not available")?;
+            let quote = quotes
+                .get(&into_price_feed_symbol(contract_symbol))
+                .context("No quote available")?;
+
+            if quote.is_older_than(QUOTE_INTERVAL_MINUTES.minutes() * 2) {
+                bail!("Latest quote is too old to compare the proposed price with");
+            }
+
+            let bid = Price::new(quote.bid())?;
+            let ask = Price::new(quote.ask())?;
+            let quoted = market_closing_price(bid, ask, Role::Taker, position);
+
+            let check = SettlementPriceCheck::new(price, quoted, tolerance);
+            executor
+                .execute(order_id, |cfd| cfd.receive_settlement_proposal(check))
+                .await?;
+
+            if !check.is_within_tolerance() {
+                tracing::info!(%order_id, proposed = %price, %quoted, ?tolerance, "Settlement proposal of maker is outside of tolerance, leaving the decision to the user");
+                return anyhow::Ok(());
+            }
+
+            tracing::info!(%order_id, proposed = %price, %quoted, ?tolerance, "Accepting settlement proposal of maker");
+
+            cfd_actor
+                .send(taker_cfd::ProposeSettlement {
+                    order_id,
+                    bid,
+                    ask,
+                    quote_timestamp: quote
+                        .timestamp
+                        .format(&time::format_description::well_known::Rfc3339)
+                        .context("Failed to format timestamp")?,
+                    payout_address: None,
+                })
+                .await
+                .context("CFD actor not available")??;
+
+            anyhow::Ok(())
+        };
+
+        let err_handler = move |e: anyhow::Error| async move {
+            tracing::warn!(%peer_id, "Failed to handle settlement proposal of maker: {e:#}")
+        };
+
+        let this = ctx.address().expect("self to be alive");
+        tokio_extras::spawn_fallible(&this, task, err_handler);
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn stopped(self) -> Self::Stop {}
+}
@@ -0,0 +1,151 @@
+//! Status of all supervised actors of the daemon.
+//!
+//! Every supervisor tracks how often it spawned its actor, how often the actor panicked and when it
+//! was last restarted. The supervisors are arranged into a tree by the module path of the actor
+//! they supervise, so that operators can tell which subsystem keeps restarting.
+
+use model::Timestamp;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::UNIX_EPOCH;
+use xtras::supervisor::SupervisorStatus;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SupervisedActor {
+    /// Full type name of the actor
+    pub actor: String,
+    /// How many times an instance of the actor was spawned, including the initial spawn
+    pub num_spawns: u64,
+    /// How many times the actor panicked
+    pub num_panics: u64,
+    pub last_restart_at: Option<Timestamp>,
+    /// Whether an instance of the actor is currently running
+    pub running: bool,
+}
+
+impl From<SupervisorStatus> for SupervisedActor {
+    fn from(status: SupervisorStatus) -> Self {
+        let last_restart_at = status.last_restart_at.map(|time| {
+            let seconds = time
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs() as i64)
+                .unwrap_or_default();
+            Timestamp::new(seconds)
+        });
+
+        Self {
+            actor: status.actor,
+            num_spawns: status.metrics.num_spawns,
+            num_panics: status.metrics.num_panics,
+            last_restart_at,
+            running: status.running,
+        }
+    }
+}
+
+/// A module of the actor hierarchy.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Node {
+    /// Supervised actors defined in this module
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub actors: Vec<SupervisedActor>,
+    /// Submodules, keyed by their name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub modules: BTreeMap<String, Node>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SupervisionTree {
+    pub num_supervisors: usize,
+    /// Supervised actors that are currently not running, e.g. while waiting to be restarted
+    pub num_not_running: usize,
+    pub total_panics: u64,
+    /// Top-level modules, keyed by their name
+    pub modules: BTreeMap<String, Node>,
+}
+
+/// Get the status of all supervisors of the daemon, arranged by module.
+#[derive(Clone, Copy)]
+pub struct GetSupervisionTree;
+
+impl GetSupervisionTree {
+    pub fn query(self) -> SupervisionTree {
+        tree(xtras::supervisor::statuses())
+    }
+}
+
+fn tree(statuses: Vec<SupervisorStatus>) -> SupervisionTree {
+    let actors = statuses
+        .into_iter()
+        .map(SupervisedActor::from)
+        .collect::<Vec<_>>();
+
+    let num_supervisors = actors.len();
+    let num_not_running = actors.iter().filter(|actor| !actor.running).count();
+    let total_panics = actors.iter().map(|actor| actor.num_panics).sum();
+
+    let mut root = Node::default();
+    for actor in actors {
+        // Generic parameters contain paths too, only the path of the actor itself is relevant
+        let path = actor.actor.split('<').next().unwrap_or_default().to_owned();
+        let mut segments = path.split("::").collect::<Vec<_>>();
+        segments.pop(); // The name of the actor type
+
+        let node = segments.into_iter().fold(&mut root, |node, segment| {
+            node.modules.entry(segment.to_owned()).or_default()
+        });
+        node.actors.push(actor);
+    }
+
+    SupervisionTree {
+        num_supervisors,
+        num_not_running,
+        total_panics,
+        modules: root.modules,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xtras::supervisor::Metrics;
+
+    #[test]
+    fn actors_are_arranged_by_module_path() {
+        let tree = tree(vec![
+            status("daemon::oracle::Actor", 1, 0, true),
+            status("daemon::collab_settlement::maker::Actor", 3, 2, false),
+            status(
+                "daemon::wallet::Actor<bdk::blockchain::electrum::ElectrumBlockchain>",
+                1,
+                0,
+                true,
+            ),
+        ]);
+
+        assert_eq!(tree.num_supervisors, 3);
+        assert_eq!(tree.num_not_running, 1);
+        assert_eq!(tree.total_panics, 2);
+
+        let daemon = &tree.modules["daemon"];
+        assert!(daemon.actors.is_empty());
+        assert_eq!(daemon.modules["oracle"].actors.len(), 1);
+        assert_eq!(daemon.modules["wallet"].actors.len(), 1);
+        assert_eq!(
+            daemon.modules["collab_settlement"].modules["maker"].actors[0].num_panics,
+            2
+        );
+    }
+
+    fn status(actor: &str, num_spawns: u64, num_panics: u64, running: bool) -> SupervisorStatus {
+        SupervisorStatus {
+            actor: actor.to_owned(),
+            metrics: Metrics {
+                num_spawns,
+                num_panics,
+            },
+            last_restart_at: None,
+            running,
+        }
+    }
+}
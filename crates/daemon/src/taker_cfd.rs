@@ -1,15 +1,21 @@
-use crate::collab_settlement;
-use crate::collab_settlement::taker::Settle;
+use crate::bitcoin::Address;
+use crate::command;
+use crate::maker_selection;
+use crate::maker_selection::Preferences;
 use crate::order;
 use crate::projection;
+use crate::ENDPOINT_CONNECTION_TIMEOUT;
 use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
-use model::libp2p::PeerId;
+use collab_settlement::taker::Settle;
+use itertools::Itertools;
+use libp2p_core::PeerId;
 use model::market_closing_price;
 use model::Cfd;
 use model::Contracts;
+use model::FundingRate;
 use model::Identity;
 use model::Leverage;
 use model::OfferId;
@@ -18,40 +24,78 @@ use model::Price;
 use model::Role;
 use sqlite_db;
 use std::collections::HashMap;
+use std::time::Duration;
+use std::time::Instant;
 use time::OffsetDateTime;
+use xtra_libp2p::libp2p::multiaddr::Protocol;
+use xtra_libp2p::multiaddress_ext::MultiaddrExt;
+use xtra_libp2p::Connect;
+use xtra_libp2p::Endpoint;
+use xtra_libp2p::GetConnectionStats;
 use xtra_productivity::xtra_productivity;
 use xtras::SendAsyncSafe;
+use xtras::SendInterval;
+
+/// Interval at which the connection to a discovered maker is checked after dialing it
+const CONNECTION_CHECK_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Interval at which the offer book is recomputed, so that offers which are no longer safe to
+/// take drop out even if their maker stopped sending offers
+const OFFER_BOOK_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
 
 #[derive(Clone, Copy)]
 pub struct PlaceOrder {
     pub offer_id: OfferId,
     pub quantity: Contracts,
     pub leverage: Leverage,
+    /// Take the equivalent offer of this maker instead of selecting the maker according to the
+    /// [`Preferences`]
+    pub maker: Option<PeerId>,
+}
+
+/// Look up one of the maker's current offers, regardless of whether it is still safe to take.
+#[derive(Clone, Copy)]
+pub struct GetOffer {
+    pub offer_id: OfferId,
 }
 
+/// Look up the latest offers of every maker that are still safe to take.
+///
+/// Makers without such offers are left out.
+#[derive(Clone, Copy)]
+pub struct GetOffersByMaker;
+
+/// Recompute the offer book, see [`OFFER_BOOK_REFRESH_INTERVAL`].
+#[derive(Clone, Copy)]
+struct RefreshOfferBook;
+
 #[derive(Clone)]
 pub struct ProposeSettlement {
     pub order_id: OrderId,
     pub bid: Price,
     pub ask: Price,
     pub quote_timestamp: String,
+    pub payout_address: Option<Address>,
 }
 
 pub struct Actor {
     db: sqlite_db::Connection,
     projection_actor: xtra::Address<projection::Actor>,
-    collab_settlement_actor: xtra::Address<collab_settlement::taker::Actor>,
+    collab_settlement_actor: xtra::Address<collab_settlement::taker::Actor<command::Executor>>,
     order_actor: xtra::Address<order::taker::Actor>,
     offers: Offers,
-    maker_identity: Identity,
-    maker_peer_id: PeerId,
+    /// The offer book last sent to the projection
+    published_offer_book: HashMap<PeerId, Vec<model::Offer>>,
+    makers: HashMap<PeerId, Identity>,
+    preferences: Preferences,
+    discovery: Option<Discovery>,
 }
 
 impl Actor {
     pub fn new(
         db: sqlite_db::Connection,
         projection_actor: xtra::Address<projection::Actor>,
-        collab_settlement_actor: xtra::Address<collab_settlement::taker::Actor>,
+        collab_settlement_actor: xtra::Address<collab_settlement::taker::Actor<command::Executor>>,
         order_actor: xtra::Address<order::taker::Actor>,
         maker_identity: Identity,
         maker_peer_id: PeerId,
@@ -62,20 +106,161 @@ impl Actor {
             collab_settlement_actor,
             order_actor,
             offers: Offers::default(),
-            maker_identity,
-            maker_peer_id,
+            published_offer_book: HashMap::default(),
+            makers: HashMap::from([(maker_peer_id, maker_identity)]),
+            preferences: Preferences::default(),
+            discovery: None,
         }
     }
+
+    /// Take offers of the given makers in addition to the offers of the main maker.
+    pub fn with_additional_makers(mut self, makers: Vec<(Identity, PeerId)>) -> Self {
+        self.makers.extend(
+            makers
+                .into_iter()
+                .map(|(identity, peer_id)| (peer_id, identity)),
+        );
+        self
+    }
+
+    /// Choose between equivalent offers of several makers according to `preferences`.
+    pub fn with_maker_preferences(self, preferences: Preferences) -> Self {
+        Self {
+            preferences,
+            ..self
+        }
+    }
+
+    /// Take offers of makers discovered through the gossip network, dialing them through the
+    /// `endpoint` when one of their offers is taken.
+    pub fn with_discovery(
+        self,
+        discovery: xtra::Address<offer::relay::Discovery>,
+        endpoint: xtra::Address<Endpoint>,
+    ) -> Self {
+        Self {
+            discovery: Some(Discovery {
+                makers: discovery,
+                endpoint,
+            }),
+            ..self
+        }
+    }
+
+    /// The network identity of the maker, connecting to the maker first if it was discovered
+    /// through the gossip network.
+    async fn maker_identity(&self, maker: PeerId) -> Result<Identity> {
+        if let Some(identity) = self.makers.get(&maker) {
+            return Ok(*identity);
+        }
+
+        match &self.discovery {
+            Some(discovery) => discovery.connect(maker).await,
+            None => bail!("Maker {maker} is not configured"),
+        }
+    }
+
+    /// Send the offer book and the preferred offers to the projection.
+    async fn publish_offers(&mut self) {
+        let offer_book = self.offers.latest_by_maker();
+        if let Err(e) = self
+            .projection_actor
+            .send(projection::Update(offer_book.clone()))
+            .await
+        {
+            tracing::warn!("Failed to send offer book to projection actor: {e:#}");
+        }
+        self.published_offer_book = offer_book;
+
+        // A maker sends an empty list of offers if it withdrew all offers
+        let offers = self.preferred_offers();
+        let res = if offers.is_empty() {
+            self.projection_actor
+                .send(projection::OffersWithdrawn)
+                .await
+        } else {
+            self.projection_actor.send(projection::Update(offers)).await
+        };
+
+        if let Err(e) = res {
+            tracing::warn!("Failed to send current offers to projection actor: {e:#}");
+        };
+    }
+
+    /// The preferred offer for each contract symbol and position among the latest offers of all
+    /// makers.
+    fn preferred_offers(&self) -> Vec<model::Offer> {
+        let candidates = self.offers.latest();
+
+        candidates
+            .iter()
+            .map(|(_, offer)| (offer.contract_symbol, offer.position_maker))
+            .unique()
+            .filter_map(|(contract_symbol, position_maker)| {
+                let group = candidates
+                    .iter()
+                    .filter(|(_, offer)| {
+                        offer.contract_symbol == contract_symbol
+                            && offer.position_maker == position_maker
+                    })
+                    .cloned()
+                    .collect_vec();
+
+                self.preferences
+                    .select(&group)
+                    .map(|(_, offer)| offer.clone())
+            })
+            .collect()
+    }
 }
 
 #[xtra_productivity]
 impl Actor {
     async fn handle_latest_offers(&mut self, msg: offer::taker::LatestOffers) {
-        self.offers.insert(msg.0.clone());
+        let offer::taker::LatestOffers { maker, offers } = msg;
+        self.offers.insert(maker, offers);
 
-        if let Err(e) = self.projection_actor.send(projection::Update(msg.0)).await {
-            tracing::warn!("Failed to send current offers to projection actor: {e:#}");
-        };
+        self.publish_offers().await;
+    }
+
+    async fn handle_refresh_offer_book(&mut self, _: RefreshOfferBook) {
+        if self.offers.latest_by_maker() != self.published_offer_book {
+            self.publish_offers().await;
+        }
+    }
+
+    async fn handle_get_published_funding_rate(
+        &mut self,
+        msg: rollover::taker::GetPublishedFundingRate,
+    ) -> Option<FundingRate> {
+        let rollover::taker::GetPublishedFundingRate {
+            maker_peer_id,
+            contract_symbol,
+            position_maker,
+        } = msg;
+
+        self.offers
+            .latest
+            .get(&maker_peer_id.inner())?
+            .iter()
+            .find(|offer| {
+                offer.contract_symbol == contract_symbol && offer.position_maker == position_maker
+            })
+            .map(|offer| offer.funding_rate)
+    }
+
+    async fn handle_get_offer(&mut self, msg: GetOffer) -> Option<model::Offer> {
+        self.offers
+            .all
+            .get(&msg.offer_id)
+            .map(|(_, offer)| offer.clone())
+    }
+
+    async fn handle_get_offers_by_maker(
+        &mut self,
+        _: GetOffersByMaker,
+    ) -> HashMap<PeerId, Vec<model::Offer>> {
+        self.offers.latest_by_maker()
     }
 
     async fn handle_propose_settlement(&mut self, msg: ProposeSettlement) -> Result<()> {
@@ -84,6 +269,7 @@ impl Actor {
             bid,
             ask,
             quote_timestamp,
+            payout_address,
         } = msg;
 
         let cfd = self.db.load_open_cfd::<Cfd>(order_id, ()).await?;
@@ -100,6 +286,7 @@ impl Actor {
                 maker_peer_id: cfd
                     .counterparty_peer_id()
                     .context("No counterparty peer id found")?,
+                payout_address,
             })
             .await??;
 
@@ -111,24 +298,58 @@ impl Actor {
             offer_id,
             quantity,
             leverage,
+            maker,
         } = msg;
 
-        let offer = self
+        let reference = self
             .offers
             .get(&offer_id)
             .context("Offer to take could not be found in current maker offers, you might have an outdated offer")?;
 
-        if !offer.is_safe_to_take(OffsetDateTime::now_utc()) {
+        if !reference.1.is_safe_to_take(OffsetDateTime::now_utc()) {
             bail!("The maker's offer appears to be outdated, refusing to place order");
         }
 
+        let mut candidates = self
+            .offers
+            .latest()
+            .into_iter()
+            .filter(|(_, offer)| {
+                maker_selection::is_equivalent(offer, &reference.1, quantity, leverage)
+            })
+            .collect_vec();
+        if !candidates.iter().any(|(_, offer)| offer.id == offer_id) {
+            candidates.push(reference);
+        }
+
+        if let Some(maker) = maker {
+            if !self.makers.contains_key(&maker) && self.discovery.is_none() {
+                bail!("Maker {maker} is not configured");
+            }
+
+            candidates.retain(|(candidate, _)| *candidate == maker);
+        }
+
+        let (maker_peer_id, offer) =
+            self.preferences
+                .select(&candidates)
+                .cloned()
+                .with_context(|| {
+                    format!("No offer of the requested maker is equivalent to offer {offer_id}")
+                })?;
+        let maker_identity = self.maker_identity(maker_peer_id).await?;
+
+        if offer.id != offer_id {
+            tracing::info!(%offer_id, selected_offer_id = %offer.id, maker = %maker_peer_id, "Selected equivalent offer of preferred maker");
+        }
+
         let order_id = OrderId::default();
         let place_order = order::taker::PlaceOrder::new(
             order_id,
             offer,
             (quantity, leverage),
-            self.maker_peer_id.inner(),
-            self.maker_identity,
+            maker_peer_id,
+            maker_identity,
         );
 
         self.order_actor
@@ -140,25 +361,126 @@ impl Actor {
     }
 }
 
+/// Makers discovered through the gossip network, see [`offer::relay`].
+struct Discovery {
+    makers: xtra::Address<offer::relay::Discovery>,
+    endpoint: xtra::Address<Endpoint>,
+}
+
+impl Discovery {
+    /// Look up the network identity of the discovered `maker` and connect to it at one of the
+    /// addresses it announced, unless already connected.
+    async fn connect(&self, maker: PeerId) -> Result<Identity> {
+        let discovered = self
+            .makers
+            .send(offer::relay::GetDiscoveredMakers)
+            .await
+            .context("Offer discovery actor not available")?
+            .into_iter()
+            .find(|discovered| discovered.peer_id == maker)
+            .with_context(|| format!("Maker {maker} is neither configured nor discovered"))?;
+
+        for address in discovered.addresses {
+            if self.is_connected(maker).await? {
+                break;
+            }
+
+            let address = match address.clone().extract_peer_id() {
+                Some(peer_id) if peer_id == maker => address,
+                Some(peer_id) => {
+                    tracing::warn!(%maker, %address, "Ignoring address of other peer {peer_id} announced by maker");
+                    continue;
+                }
+                None => address.with(Protocol::P2p(maker.into())),
+            };
+
+            tracing::info!(%maker, %address, "Dialing discovered maker");
+            if let Err(e) = self
+                .endpoint
+                .send(Connect(address.clone()))
+                .await
+                .context("Endpoint not available")?
+            {
+                tracing::debug!(%maker, %address, "Failed to dial discovered maker: {e:#}");
+            }
+
+            let deadline = Instant::now() + ENDPOINT_CONNECTION_TIMEOUT;
+            while Instant::now() < deadline && !self.is_connected(maker).await? {
+                tokio_extras::time::sleep(CONNECTION_CHECK_INTERVAL).await;
+            }
+        }
+
+        if !self.is_connected(maker).await? {
+            bail!("Failed to connect to discovered maker {maker}");
+        }
+
+        Ok(discovered.identity)
+    }
+
+    async fn is_connected(&self, maker: PeerId) -> Result<bool> {
+        let stats = self
+            .endpoint
+            .send(GetConnectionStats)
+            .await
+            .context("Endpoint not available")?;
+
+        Ok(stats.connected_peers.contains(&maker))
+    }
+}
+
 #[derive(Default)]
-struct Offers(HashMap<OfferId, model::Offer>);
+struct Offers {
+    /// All offers received from the makers which are still safe to take
+    all: HashMap<OfferId, (PeerId, model::Offer)>,
+    /// The offers each maker sent most recently
+    latest: HashMap<PeerId, Vec<model::Offer>>,
+}
 
 impl Offers {
-    fn insert(&mut self, offers: Vec<model::Offer>) {
-        for offer in offers.into_iter() {
-            self.0.insert(offer.id, offer);
+    fn insert(&mut self, maker: PeerId, offers: Vec<model::Offer>) {
+        for offer in offers.iter() {
+            self.all.insert(offer.id, (maker, offer.clone()));
         }
+
+        self.latest.insert(maker, offers);
     }
 
-    fn get(&mut self, id: &OfferId) -> Option<model::Offer> {
+    fn get(&mut self, id: &OfferId) -> Option<(PeerId, model::Offer)> {
         self.remove_old_offers();
 
-        self.0.get(id).cloned()
+        self.all.get(id).cloned()
+    }
+
+    /// The latest offers of all makers that are still safe to take.
+    fn latest(&self) -> Vec<(PeerId, model::Offer)> {
+        self.latest_by_maker()
+            .into_iter()
+            .flat_map(|(maker, offers)| offers.into_iter().map(move |offer| (maker, offer)))
+            .collect()
+    }
+
+    /// The latest offers that are still safe to take, by maker.
+    fn latest_by_maker(&self) -> HashMap<PeerId, Vec<model::Offer>> {
+        let now = OffsetDateTime::now_utc();
+
+        self.latest
+            .iter()
+            .map(|(maker, offers)| {
+                let offers = offers
+                    .iter()
+                    .filter(|offer| offer.is_safe_to_take(now))
+                    .cloned()
+                    .collect_vec();
+
+                (*maker, offers)
+            })
+            .filter(|(_, offers)| !offers.is_empty())
+            .collect()
     }
 
     fn remove_old_offers(&mut self) {
-        self.0
-            .retain(|_, offer| offer.is_safe_to_take(OffsetDateTime::now_utc()));
+        self.all
+            .retain(|_, (_, offer)| offer.is_safe_to_take(OffsetDateTime::now_utc()));
     }
 }
 
@@ -166,5 +488,85 @@ impl Offers {
 impl xtra::Actor for Actor {
     type Stop = ();
 
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we are alive");
+
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(
+                OFFER_BOOK_REFRESH_INTERVAL,
+                || RefreshOfferBook,
+                xtras::IncludeSpan::Never,
+            ),
+        );
+    }
+
     async fn stopped(self) -> Self::Stop {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::ContractSymbol;
+
+    #[test]
+    fn latest_by_maker_groups_the_offers_of_several_makers() {
+        let (maker_a, maker_b) = (PeerId::random(), PeerId::random());
+        let offer_a = model::Offer::dummy_short(ContractSymbol::BtcUsd);
+        let offer_b = model::Offer::dummy_short(ContractSymbol::EthUsd);
+
+        let mut offers = Offers::default();
+        offers.insert(maker_a, vec![offer_a.clone()]);
+        offers.insert(maker_b, vec![offer_b.clone()]);
+
+        assert_eq!(
+            offers.latest_by_maker(),
+            HashMap::from([(maker_a, vec![offer_a]), (maker_b, vec![offer_b])])
+        );
+    }
+
+    #[test]
+    fn latest_offers_of_a_maker_replace_its_previous_offers() {
+        let (maker_a, maker_b) = (PeerId::random(), PeerId::random());
+        let offer_b = model::Offer::dummy_short(ContractSymbol::BtcUsd);
+        let replacement = model::Offer::dummy_short(ContractSymbol::EthUsd);
+
+        let mut offers = Offers::default();
+        offers.insert(
+            maker_a,
+            vec![model::Offer::dummy_short(ContractSymbol::BtcUsd)],
+        );
+        offers.insert(maker_b, vec![offer_b.clone()]);
+        offers.insert(maker_a, vec![replacement.clone()]);
+
+        assert_eq!(
+            offers.latest_by_maker(),
+            HashMap::from([(maker_a, vec![replacement]), (maker_b, vec![offer_b])])
+        );
+
+        offers.insert(maker_a, vec![]);
+
+        assert_eq!(
+            offers.latest_by_maker(),
+            HashMap::from([(maker_b, vec![offer_b])])
+        );
+    }
+
+    #[test]
+    fn latest_by_maker_leaves_out_offers_that_are_not_safe_to_take() {
+        let (maker_a, maker_b) = (PeerId::random(), PeerId::random());
+        let safe = model::Offer::dummy_short(ContractSymbol::BtcUsd);
+        let outdated = model::Offer::dummy_short(ContractSymbol::EthUsd)
+            .reissue(OffsetDateTime::now_utc() - time::Duration::hours(1));
+        assert!(!outdated.is_safe_to_take(OffsetDateTime::now_utc()));
+
+        let mut offers = Offers::default();
+        offers.insert(maker_a, vec![safe.clone(), outdated.clone()]);
+        offers.insert(maker_b, vec![outdated]);
+
+        assert_eq!(
+            offers.latest_by_maker(),
+            HashMap::from([(maker_a, vec![safe])])
+        );
+    }
+}
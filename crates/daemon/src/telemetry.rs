@@ -0,0 +1,216 @@
+//! Opt-in anonymous usage statistics.
+//!
+//! Daemons that opted in count coarse events of the CFD lifecycle, e.g. opened trades, rollovers
+//! and failed protocols by the step that failed. Only the category and the time of an event are
+//! stored locally, no order ids, amounts, addresses or peers. The counts can be exported through
+//! the API and can optionally be pushed to a collector periodically, which gives the maintainers
+//! real-world data to prioritize fixes. Events older than the retention period are pruned.
+
+use crate::scheduler::Job;
+use anyhow::Context;
+use anyhow::Result;
+use model::EventKind;
+use model::Role;
+use model::Timestamp;
+use reqwest::Url;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+pub const PRUNING_JOB: &str = "telemetry_pruning";
+pub const PUSH_JOB: &str = "telemetry_push";
+
+pub const DEFAULT_RETENTION_DAYS: u64 = 30;
+
+const PRUNING_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+const PUSH_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    TradeOpened,
+    Rollover,
+    CollaborativeSettlement,
+    /// The CFD was settled by publishing a CET
+    NonCollaborativeSettlement,
+    Refund,
+    ContractSetupFailed,
+    RolloverFailed,
+    CollaborativeSettlementFailed,
+}
+
+impl Category {
+    /// The category an event is counted in, `None` for events that are not counted.
+    pub fn of(event: &EventKind) -> Option<Self> {
+        use EventKind::*;
+
+        let category = match event {
+            ContractSetupCompleted { .. } => Category::TradeOpened,
+            RolloverCompleted { .. } => Category::Rollover,
+            CollaborativeSettlementCompleted { .. } => Category::CollaborativeSettlement,
+            CetConfirmed => Category::NonCollaborativeSettlement,
+            RefundConfirmed => Category::Refund,
+            ContractSetupFailed => Category::ContractSetupFailed,
+            RolloverFailed => Category::RolloverFailed,
+            CollaborativeSettlementFailed => Category::CollaborativeSettlementFailed,
+            _ => return None,
+        };
+
+        Some(category)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Category::TradeOpened => "trade_opened",
+            Category::Rollover => "rollover",
+            Category::CollaborativeSettlement => "collaborative_settlement",
+            Category::NonCollaborativeSettlement => "non_collaborative_settlement",
+            Category::Refund => "refund",
+            Category::ContractSetupFailed => "contract_setup_failed",
+            Category::RolloverFailed => "rollover_failed",
+            Category::CollaborativeSettlementFailed => "collaborative_settlement_failed",
+        }
+    }
+}
+
+/// The counts of the retention period, as exported and pushed to the collector.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub daemon_version: String,
+    pub role: Role,
+    /// Start of the period the counts cover
+    pub since: Timestamp,
+    pub counts: BTreeMap<String, u64>,
+}
+
+#[derive(Clone)]
+pub struct Telemetry {
+    db: sqlite_db::Connection,
+    role: Role,
+    retention: Duration,
+    /// Endpoint the report is `POST`ed to periodically, the report is only kept locally if `None`
+    collector: Option<Url>,
+    client: reqwest::Client,
+}
+
+impl Telemetry {
+    pub fn new(
+        db: sqlite_db::Connection,
+        role: Role,
+        retention: Duration,
+        collector: Option<Url>,
+    ) -> Self {
+        Self {
+            db,
+            role,
+            retention,
+            collector,
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .expect("to build from static arguments"),
+        }
+    }
+
+    /// Count the event if it falls into a [`Category`].
+    pub async fn record(&self, event: &EventKind) -> Result<()> {
+        if let Some(category) = Category::of(event) {
+            self.db
+                .insert_telemetry_event(category.as_str(), Timestamp::now())
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn report(&self) -> Result<Report> {
+        let since = self.retention_start();
+        let counts = self.db.load_telemetry_counts(since).await?;
+
+        Ok(Report {
+            daemon_version: crate::version(),
+            role: self.role,
+            since,
+            counts: counts.into_iter().collect(),
+        })
+    }
+
+    /// The pruning job and, if a collector is configured, the push job.
+    pub fn jobs(&self) -> Vec<Job> {
+        let mut jobs = vec![Job::new(PRUNING_JOB, PRUNING_INTERVAL, {
+            let telemetry = self.clone();
+            move || {
+                let telemetry = telemetry.clone();
+                async move { telemetry.prune().await }
+            }
+        })];
+
+        if self.collector.is_some() {
+            jobs.push(Job::new(PUSH_JOB, PUSH_INTERVAL, {
+                let telemetry = self.clone();
+                move || {
+                    let telemetry = telemetry.clone();
+                    async move { telemetry.push().await }
+                }
+            }));
+        }
+
+        jobs
+    }
+
+    async fn prune(&self) -> Result<()> {
+        let pruned = self
+            .db
+            .prune_telemetry_events(self.retention_start())
+            .await?;
+
+        tracing::debug!(%pruned, "Pruned telemetry events");
+
+        Ok(())
+    }
+
+    async fn push(&self) -> Result<()> {
+        let collector = match &self.collector {
+            Some(collector) => collector.clone(),
+            None => return Ok(()),
+        };
+
+        let report = self.report().await?;
+
+        self.client
+            .post(collector)
+            .json(&report)
+            .send()
+            .await
+            .context("Failed to send telemetry report")?
+            .error_for_status()
+            .context("Collector rejected telemetry report")?;
+
+        Ok(())
+    }
+
+    fn retention_start(&self) -> Timestamp {
+        Timestamp::new(Timestamp::now().seconds() - self.retention.as_secs() as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_outcomes_of_the_lifecycle_are_counted() {
+        assert_eq!(
+            Category::of(&EventKind::RolloverFailed),
+            Some(Category::RolloverFailed)
+        );
+        assert_eq!(
+            Category::of(&EventKind::CetConfirmed),
+            Some(Category::NonCollaborativeSettlement)
+        );
+        assert_eq!(Category::of(&EventKind::ContractSetupStarted), None);
+        assert_eq!(Category::of(&EventKind::LockConfirmed), None);
+    }
+}
@@ -0,0 +1,64 @@
+//! Protocol transcripts of individual orders for debugging.
+//!
+//! Enabling the transcript of an order captures the full messages of the next protocol run for the
+//! order, e.g. a rollover, into `transcripts/<order_id>.jsonl` within the data directory. Secrets
+//! are redacted and the transcript is disabled automatically once the protocol run completed, see
+//! [`xtra_libp2p::transcript`].
+
+use anyhow::Context;
+use anyhow::Result;
+use model::OrderId;
+use serde::Serialize;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Transcript {
+    pub order_id: OrderId,
+    pub path: PathBuf,
+}
+
+/// Enables and disables protocol transcripts of orders.
+#[derive(Debug, Clone)]
+pub struct Transcripts {
+    dir: PathBuf,
+}
+
+impl Transcripts {
+    pub fn new(data_dir: PathBuf) -> Self {
+        Self {
+            dir: data_dir.join("transcripts"),
+        }
+    }
+
+    /// Capture the next protocol run of `order_id`.
+    pub fn enable(&self, order_id: OrderId) -> Result<Transcript> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+
+        let path = self.dir.join(format!("{order_id}.jsonl"));
+        xtra_libp2p::transcript::enable(order_id, path.clone());
+
+        tracing::info!(%order_id, path = %path.display(), "Enabled protocol transcript");
+
+        Ok(Transcript { order_id, path })
+    }
+
+    /// Stop capturing protocol runs of `order_id`.
+    ///
+    /// Returns `false` if the transcript was not enabled.
+    pub fn disable(&self, order_id: OrderId) -> bool {
+        xtra_libp2p::transcript::disable(&order_id.to_string())
+    }
+
+    /// Transcripts that are waiting for or capturing a protocol run.
+    pub fn enabled(&self) -> Vec<Transcript> {
+        xtra_libp2p::transcript::list()
+            .into_iter()
+            .filter_map(|(key, path)| {
+                let order_id = key.parse::<Uuid>().ok()?.into();
+                Some(Transcript { order_id, path })
+            })
+            .collect()
+    }
+}
@@ -1,4 +1,7 @@
 use crate::bitcoin::secp256k1::Secp256k1;
+use crate::electrum_health::ElectrumStatus;
+use crate::electrum_health::EndpointWatcher;
+use crate::janitor;
 use crate::seed::RandomSeed;
 use crate::seed::Seed;
 use crate::seed::RANDOM_SEED_SIZE;
@@ -19,10 +22,15 @@ use bdk::bitcoin::BlockHash;
 use bdk::bitcoin::Network;
 use bdk::bitcoin::OutPoint;
 use bdk::bitcoin::PublicKey;
+use bdk::bitcoin::Transaction;
 use bdk::bitcoin::Txid;
 use bdk::blockchain::Blockchain;
 use bdk::blockchain::ElectrumBlockchain;
+use bdk::blockchain::GetHeight;
 use bdk::database::BatchDatabase;
+use bdk::descriptor::ExtendedDescriptor;
+use bdk::descriptor::IntoWalletDescriptor;
+use bdk::descriptor::KeyMap;
 use bdk::electrum_client;
 use bdk::electrum_client::ElectrumApi;
 use bdk::sled;
@@ -33,17 +41,24 @@ use bdk::wallet::AddressIndex;
 use bdk::wallet::AddressInfo;
 use bdk::FeeRate;
 use bdk::KeychainKind;
+use bdk::LocalUtxo;
 use bdk::SignOptions;
 use bdk::SyncOptions;
 use bdk::Wallet;
 use maia_core::PartyParams;
 use maia_core::TxBuilderExt;
+use model::shared_protocol::MAX_LOCK_INPUTS;
+use model::OrderId;
+use model::PendingDeposit;
 use model::Timestamp;
 use model::TxFeeRate;
 use model::WalletInfo;
 use statrs::statistics::*;
+use std::collections::HashMap;
 use std::collections::HashSet;
+use std::fmt;
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::time::Duration;
 use std::time::Instant;
 use tokio::runtime::Handle;
@@ -53,6 +68,18 @@ use xtra_productivity::xtra_productivity;
 use xtras::SendInterval;
 
 const SYNC_INTERVAL: Duration = Duration::from_secs(3 * 60);
+/// Interval at which the wallet of the previous address type is synced, funds only arrive there if
+/// someone still uses an old address
+const PREVIOUS_WALLET_SYNC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Confirmation targets in blocks, used to estimate when a pending deposit confirms
+const CONFIRMATION_TARGETS: [u32; 5] = [1, 3, 6, 12, 25];
+
+/// Confirmation target in blocks for the fee rate of transactions forwarding funds
+pub const FORWARD_CONFIRMATION_TARGET: usize = 6;
+
+/// Smallest amount in satoshis worth forwarding, to not waste fees on tiny outputs
+pub const MIN_FORWARD_AMOUNT_SAT: u64 = 10_000;
+
 pub const MAKER_WALLET_ID: &str = "maker-wallet";
 pub const TAKER_WALLET_ID: &str = "taker-wallet";
 
@@ -105,23 +132,72 @@ static STD_DEV_UTXO_VALUE_GAUGE: conquer_once::Lazy<prometheus::Gauge> =
         .unwrap()
     });
 
+/// The type of the addresses the wallet derives for receiving funds and change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressType {
+    /// Native segwit v0 addresses derived according to BIP84
+    Wpkh,
+    /// Taproot addresses spendable through the key path, derived according to BIP86
+    Taproot,
+}
+
+impl AddressType {
+    /// The address type a wallet with the same seed may have used before switching to `self`.
+    fn other(&self) -> Self {
+        match self {
+            AddressType::Wpkh => AddressType::Taproot,
+            AddressType::Taproot => AddressType::Wpkh,
+        }
+    }
+}
+
+impl FromStr for AddressType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wpkh" => Ok(AddressType::Wpkh),
+            "tr" | "taproot" => Ok(AddressType::Taproot),
+            other => bail!("Unsupported address type {other}, expected `wpkh` or `tr`"),
+        }
+    }
+}
+
+impl fmt::Display for AddressType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressType::Wpkh => write!(f, "wpkh"),
+            AddressType::Taproot => write!(f, "tr"),
+        }
+    }
+}
+
 pub struct Actor<B, DB> {
     wallet: Wallet<DB>,
+    address_type: AddressType,
+    /// Wallet of the address type used before the configured one
+    previous_wallet: Option<Wallet<DB>>,
+    /// Whether funds of the `previous_wallet` are swept into the wallet
+    sweep_previous_wallet: bool,
     blockchain_client: B,
     used_utxos: LockedUtxos,
     sender: watch::Sender<Option<WalletInfo>>,
     db: Option<Db>,
     managed_wallet: bool,
+    electrum_endpoint: Option<EndpointWatcher>,
 }
 
 impl Actor<ElectrumBlockchain, Tree> {
     pub fn spawn(
-        electrum_rpc_url: &str,
+        electrum: watch::Receiver<ElectrumStatus>,
         ext_priv_key: ExtendedPrivKey,
         db_path: PathBuf,
         managed_wallet: bool,
+        address_type: AddressType,
+        sweep_previous_wallet: bool,
     ) -> Result<(xtra::Address<Self>, watch::Receiver<Option<WalletInfo>>)> {
-        let client = electrum_client::Client::new(electrum_rpc_url)
+        let electrum_endpoint = EndpointWatcher::new(electrum);
+        let client = electrum_client::Client::new(electrum_endpoint.current())
             .context("Failed to initialize Electrum RPC client")?;
 
         ensure!(
@@ -131,7 +207,10 @@ impl Actor<ElectrumBlockchain, Tree> {
 
         // Create a database (using default sled type) to store wallet data
         let db = sled::open(db_path)?;
-        let wallet = Actor::build_wallet(ext_priv_key, db.clone())?;
+        let wallet = Actor::build_wallet(ext_priv_key, address_type, &db)?;
+        let previous_wallet = Actor::build_previous_wallet(ext_priv_key, address_type, &db)?;
+
+        tracing::info!(%address_type, "Deriving wallet addresses");
 
         // UTXOs chosen after coin selection will only be locked for a
         // few wallet sync intervals. UTXOs which were actually
@@ -145,11 +224,15 @@ impl Actor<ElectrumBlockchain, Tree> {
 
         let actor = Self {
             wallet,
+            address_type,
+            previous_wallet,
+            sweep_previous_wallet,
             sender,
             used_utxos: LockedUtxos::new(time_to_lock),
             blockchain_client: ElectrumBlockchain::from(client),
             db: Some(db),
             managed_wallet,
+            electrum_endpoint: Some(electrum_endpoint),
         };
 
         let (addr, fut) = actor.create(None).run();
@@ -159,24 +242,63 @@ impl Actor<ElectrumBlockchain, Tree> {
         Ok((addr, receiver))
     }
 
-    fn build_wallet(ext_priv_key: ExtendedPrivKey, db: Db) -> Result<Wallet<Tree>> {
+    /// Build the wallet deriving addresses of `address_type`.
+    ///
+    /// Every address type is stored in its own tree named after the descriptors, so that switching
+    /// the address type never mixes up the derivation indices of both wallets.
+    fn build_wallet(
+        ext_priv_key: ExtendedPrivKey,
+        address_type: AddressType,
+        db: &Db,
+    ) -> Result<Wallet<Tree>> {
+        let [external, internal] = descriptors(ext_priv_key, address_type)?;
+
         let wallet_name = wallet_name_from_descriptor(
-            bdk::template::Bip84(ext_priv_key, KeychainKind::External),
-            Some(bdk::template::Bip84(ext_priv_key, KeychainKind::Internal)),
+            external.clone(),
+            Some(internal.clone()),
             ext_priv_key.network,
             &Secp256k1::new(),
         )?;
 
         let db = db.open_tree(wallet_name)?;
 
-        let wallet = Wallet::new(
-            bdk::template::Bip84(ext_priv_key, KeychainKind::External),
-            Some(bdk::template::Bip84(ext_priv_key, KeychainKind::Internal)),
+        let wallet = Wallet::new(external, Some(internal), ext_priv_key.network, db)?;
+
+        Ok(wallet)
+    }
+
+    /// Build the wallet of the other address type if it was used with this seed before.
+    ///
+    /// Funds received on its addresses are swept into the wallet of the configured address type if
+    /// the operator opted in, see [`Actor::sync_previous_wallet`].
+    fn build_previous_wallet(
+        ext_priv_key: ExtendedPrivKey,
+        address_type: AddressType,
+        db: &Db,
+    ) -> Result<Option<Wallet<Tree>>> {
+        let previous_type = address_type.other();
+        let [external, internal] = descriptors(ext_priv_key, previous_type)?;
+
+        let wallet_name = wallet_name_from_descriptor(
+            external,
+            Some(internal),
             ext_priv_key.network,
-            db,
+            &Secp256k1::new(),
         )?;
 
-        Ok(wallet)
+        let previously_used = db
+            .tree_names()
+            .iter()
+            .any(|tree| tree.as_ref() == wallet_name.as_bytes());
+        if !previously_used {
+            return Ok(None);
+        }
+
+        tracing::info!(%previous_type, "Found wallet of previous address type");
+
+        let wallet = Actor::build_wallet(ext_priv_key, previous_type, db)?;
+
+        Ok(Some(wallet))
     }
 }
 
@@ -199,7 +321,8 @@ where
         let db = self.db.clone().expect("database should be existing.");
 
         // recreate and update wallet
-        self.wallet = Actor::build_wallet(ext_priv_key, db)?;
+        self.wallet = Actor::build_wallet(ext_priv_key, self.address_type, &db)?;
+        self.previous_wallet = Actor::build_previous_wallet(ext_priv_key, self.address_type, &db)?;
 
         let name = msg.name;
         let wallet_seed = msg.path.join(&name);
@@ -225,11 +348,152 @@ impl<DB> Actor<ElectrumBlockchain, DB>
 where
     DB: BatchDatabase,
 {
+    /// Reconnect to the active Electrum endpoint if it changed since the last sync.
+    fn reconnect_if_endpoint_changed(&mut self) -> Result<()> {
+        let watcher = match self.electrum_endpoint.as_mut() {
+            Some(watcher) => watcher,
+            None => return Ok(()),
+        };
+        let endpoint = match watcher.changed() {
+            Some(endpoint) => endpoint,
+            None => return Ok(()),
+        };
+
+        let client = electrum_client::Client::new(&endpoint)
+            .context("Failed to initialize Electrum RPC client")?;
+
+        ensure!(
+            seed_and_rpc_on_same_network(&client, self.wallet.network())?,
+            "Wallet seed and Electrum RPC client on different networks."
+        );
+
+        self.blockchain_client = ElectrumBlockchain::from(client);
+
+        tracing::info!(%endpoint, "Wallet switched Electrum endpoint");
+        watcher.connected_to(endpoint);
+
+        Ok(())
+    }
+
+    /// Sync the wallet of the previous address type and sweep its funds into the wallet.
+    ///
+    /// The funds are only swept if the operator opted in, otherwise they are reported. The sweep
+    /// pays the fee rate estimated for confirmation within [`FORWARD_CONFIRMATION_TARGET`] blocks.
+    /// The previous wallet is kept once it is empty, funds sent to its addresses later on are swept
+    /// as well.
+    fn sync_previous_wallet(&mut self) -> Result<()> {
+        let previous_wallet = match self.previous_wallet.as_ref() {
+            Some(previous_wallet) => previous_wallet,
+            None => return Ok(()),
+        };
+
+        previous_wallet
+            .sync(&self.blockchain_client, SyncOptions::default())
+            .context("Failed to sync wallet of previous address type")?;
+
+        let balance = Amount::from_sat(previous_wallet.get_balance()?.get_spendable());
+        if balance == Amount::ZERO {
+            return Ok(());
+        }
+
+        let previous_type = self.address_type.other();
+        if !self.sweep_previous_wallet {
+            tracing::warn!(%previous_type, %balance, "Funds on addresses of the previous address type, restart with --sweep-previous-wallet to sweep them into the wallet");
+            return Ok(());
+        }
+
+        let fee_rate = self
+            .blockchain_client
+            .estimate_fee(FORWARD_CONFIRMATION_TARGET)
+            .context("Failed to estimate fee rate")?;
+        let address = self.wallet.get_address(AddressIndex::New)?.address;
+
+        let mut psbt = {
+            let mut tx_builder = previous_wallet.build_tx();
+
+            tx_builder
+                .fee_rate(fee_rate)
+                .enable_rbf()
+                .drain_wallet()
+                .drain_to(address.script_pubkey());
+
+            let (psbt, _) = tx_builder.finish()?;
+
+            psbt
+        };
+
+        previous_wallet.sign(&mut psbt, SignOptions::default())?;
+
+        let tx = psbt.extract_tx();
+        let txid = tx.txid();
+        self.blockchain_client.broadcast(&tx)?;
+
+        tracing::info!(%txid, %address, %previous_type, %balance, "Swept funds of previous address type");
+
+        Ok(())
+    }
+
+    /// Collect the unconfirmed incoming transactions and estimate when they confirm.
+    fn pending_deposits(&self, transactions: &[bdk::TransactionDetails]) -> Vec<PendingDeposit> {
+        let incoming = transactions
+            .iter()
+            .filter(|tx| tx.confirmation_time.is_none() && tx.received > tx.sent)
+            .collect::<Vec<_>>();
+
+        if incoming.is_empty() {
+            return Vec::new();
+        }
+
+        let fee_estimates = CONFIRMATION_TARGETS
+            .into_iter()
+            .filter_map(
+                |target| match self.blockchain_client.estimate_fee(target as usize) {
+                    Ok(fee_rate) => Some((target, fee_rate)),
+                    Err(e) => {
+                        tracing::debug!(%target, "Failed to estimate fee rate: {e:#}");
+                        None
+                    }
+                },
+            )
+            .collect::<Vec<_>>();
+
+        incoming
+            .into_iter()
+            .map(|tx| {
+                let estimated_confirmation_blocks = self
+                    .fee_rate(tx)
+                    .and_then(|fee_rate| estimate_confirmation_blocks(fee_rate, &fee_estimates));
+
+                PendingDeposit {
+                    txid: tx.txid,
+                    amount: Amount::from_sat(tx.received - tx.sent),
+                    estimated_confirmation_blocks,
+                }
+            })
+            .collect()
+    }
+
+    /// The fee rate of a wallet transaction, if the fee is known.
+    fn fee_rate(&self, tx: &bdk::TransactionDetails) -> Option<FeeRate> {
+        let fee = tx.fee?;
+        let transaction = match self.wallet.get_tx(&tx.txid, true) {
+            Ok(details) => details?.transaction?,
+            Err(e) => {
+                tracing::debug!(txid = %tx.txid, "Failed to load transaction: {e:#}");
+                return None;
+            }
+        };
+
+        Some(FeeRate::from_wu(fee, transaction.weight()))
+    }
+
     #[tracing::instrument(name = "Sync wallet", skip_all, err)]
     fn sync_internal(&mut self) -> Result<WalletInfo> {
         let now = Instant::now();
         tracing::trace!(target : "wallet", "Wallet sync started");
 
+        self.reconnect_if_endpoint_changed()?;
+
         tracing::debug_span!("Sync wallet database with blockchain").in_scope(|| {
             self.wallet
                 .sync(&self.blockchain_client, SyncOptions::default())
@@ -262,15 +526,22 @@ where
         MEAN_UTXO_VALUE_GAUGE.set(utxo_values.mean().unwrap_or_default());
         STD_DEV_UTXO_VALUE_GAUGE.set(utxo_values.std_dev().unwrap_or_default());
 
+        let block_height = self
+            .blockchain_client
+            .get_height()
+            .context("Failed to get chain tip height")?;
         let address = self.wallet.get_address(AddressIndex::LastUnused)?.address;
         let transactions = self.wallet.list_transactions(false)?;
+        let pending_deposits = self.pending_deposits(&transactions);
 
         let wallet_info = WalletInfo {
             network: self.wallet.network(),
             balance: Amount::from_sat(balance),
             address,
             last_updated_at: Timestamp::now(),
+            block_height,
             transactions,
+            pending_deposits,
             managed_wallet: self.managed_wallet,
         };
 
@@ -295,6 +566,12 @@ where
         let _ = self.sender.send(wallet_info_update);
     }
 
+    pub fn handle_sync_previous_wallet(&mut self, _msg: SyncPreviousWallet) {
+        if let Err(e) = self.sync_previous_wallet() {
+            tracing::warn!("Failed to sync wallet of previous address type: {e:#}");
+        }
+    }
+
     pub fn handle_withdraw(&mut self, msg: Withdraw) -> Result<Txid> {
         self.sync_internal()?;
 
@@ -345,6 +622,173 @@ where
 
         Ok(txid)
     }
+
+    pub fn handle_sweep(&mut self, msg: Sweep) -> Result<Txid> {
+        self.sync_internal()?;
+
+        let (last, others) = msg
+            .addresses
+            .split_last()
+            .context("At least one address is required")?;
+
+        let network = self.wallet.network();
+        if let Some(address) = msg
+            .addresses
+            .iter()
+            .find(|address| address.network != network)
+        {
+            bail!(
+                "Address {address} has invalid network. It was {} but the wallet is connected to {network}",
+                address.network,
+            )
+        }
+
+        let balance = self.wallet.get_balance()?.get_spendable();
+        let share = balance / msg.addresses.len() as u64;
+
+        let fee_rate = msg.fee.unwrap_or_else(FeeRate::default_min_relay_fee);
+
+        let mut psbt = {
+            let mut tx_builder = self.wallet.build_tx();
+
+            tx_builder.fee_rate(fee_rate).enable_rbf().drain_wallet();
+
+            for address in others {
+                tx_builder.add_recipient(address.script_pubkey(), share);
+            }
+
+            // The last address pays the fee
+            tx_builder.drain_to(last.script_pubkey());
+
+            let (psbt, _) = tx_builder.finish()?;
+
+            psbt
+        };
+
+        self.wallet.sign(&mut psbt, SignOptions::default())?;
+
+        let tx = psbt.extract_tx();
+        let txid = tx.txid();
+        self.blockchain_client.broadcast(&tx)?;
+
+        tracing::info!(%txid, num_addresses = %msg.addresses.len(), "Swept wallet");
+
+        Ok(txid)
+    }
+
+    pub fn handle_consolidate_utxos(&mut self, msg: ConsolidateUtxos) -> Result<Option<Txid>> {
+        let locked_utxos = self.used_utxos.list();
+
+        let utxos = self
+            .wallet
+            .list_unspent()?
+            .into_iter()
+            .map(|utxo| utxo.outpoint)
+            .filter(|outpoint| !locked_utxos.contains(outpoint))
+            .collect::<Vec<_>>();
+
+        if utxos.len() <= msg.max_utxos {
+            return Ok(None);
+        }
+
+        let address = self.wallet.get_address(AddressIndex::New)?.address;
+
+        let mut psbt = {
+            let mut tx_builder = self.wallet.build_tx();
+
+            tx_builder
+                .fee_rate(FeeRate::default_min_relay_fee())
+                .enable_rbf()
+                .add_utxos(&utxos)?
+                .manually_selected_only()
+                .drain_to(address.script_pubkey());
+
+            let (psbt, _) = tx_builder.finish()?;
+
+            psbt
+        };
+
+        self.wallet.sign(&mut psbt, SignOptions::default())?;
+
+        let tx = psbt.extract_tx();
+        let txid = tx.txid();
+        self.blockchain_client.broadcast(&tx)?;
+
+        // The UTXOs are only gone from the wallet after the next sync
+        self.used_utxos.extend(utxos.iter().copied());
+
+        tracing::info!(%txid, num_utxos = %utxos.len(), "Consolidated UTXOs");
+
+        Ok(Some(txid))
+    }
+
+    pub fn handle_forward_excess(&mut self, msg: ForwardExcess) -> Result<ForwardOutcome> {
+        self.sync_internal()?;
+
+        if msg.address.network != self.wallet.network() {
+            bail!(
+                "Address has invalid network. It was {} but the wallet is connected to {}",
+                msg.address.network,
+                self.wallet.network()
+            )
+        }
+
+        let locked_utxos = self.used_utxos.list();
+        let unlocked_balance = self
+            .wallet
+            .list_unspent()?
+            .into_iter()
+            .filter(|utxo| !locked_utxos.contains(&utxo.outpoint))
+            .map(|utxo| utxo.txout.value)
+            .sum();
+
+        let amount = Amount::from_sat(unlocked_balance)
+            .checked_sub(msg.threshold)
+            .unwrap_or(Amount::ZERO);
+        if amount.as_sat() < MIN_FORWARD_AMOUNT_SAT {
+            return Ok(ForwardOutcome::NothingToForward);
+        }
+
+        let fee_rate = self
+            .blockchain_client
+            .estimate_fee(FORWARD_CONFIRMATION_TARGET)
+            .context("Failed to estimate fee rate")?;
+        if fee_rate.as_sat_per_vb() > msg.max_fee_rate.as_sat_per_vb() {
+            return Ok(ForwardOutcome::FeeRateTooHigh {
+                estimated: fee_rate,
+            });
+        }
+
+        let address = msg.address;
+
+        let mut psbt = {
+            let mut tx_builder = self.wallet.build_tx();
+
+            tx_builder
+                .fee_rate(fee_rate)
+                .enable_rbf()
+                .unspendable(locked_utxos)
+                .add_recipient(address.script_pubkey(), amount.as_sat());
+
+            let (psbt, _) = tx_builder.finish()?;
+
+            psbt
+        };
+
+        self.wallet.sign(&mut psbt, SignOptions::default())?;
+
+        let tx = psbt.extract_tx();
+        let txid = tx.txid();
+        self.blockchain_client.broadcast(&tx)?;
+
+        tracing::info!(%txid, %amount, %address, "Forwarded funds above threshold");
+
+        Ok(ForwardOutcome::Forwarded {
+            txid,
+            amount,
+            fee_rate,
+        })
+    }
 }
 
 #[xtra_productivity]
@@ -356,6 +800,22 @@ where
     pub fn handle_sign(&mut self, msg: Sign) -> Result<PartiallySignedTransaction> {
         let mut psbt = msg.psbt;
 
+        if self.address_type == AddressType::Taproot {
+            // Taproot signatures commit to all outputs spent by the transaction, e.g. the lock
+            // transaction spends outputs of both parties
+            if let Some(input) = psbt
+                .inputs
+                .iter()
+                .zip(psbt.unsigned_tx.input.iter())
+                .find_map(|(input, txin)| input.witness_utxo.is_none().then_some(txin))
+            {
+                bail!(
+                    "Cannot sign with taproot wallet, spent output {} of input is unknown",
+                    input.previous_output
+                );
+            }
+        }
+
         self.wallet
             .sign(
                 &mut psbt,
@@ -375,11 +835,19 @@ where
             amount,
             identity_pk,
             fee_rate,
+            order_id,
         }: BuildPartyParams,
     ) -> Result<PartyParams> {
         let psbt = self
             .wallet
             .build_lock_tx(amount, &mut self.used_utxos, fee_rate.into())?;
+        self.used_utxos.assign(
+            psbt.unsigned_tx
+                .input
+                .iter()
+                .map(|input| input.previous_output),
+            order_id,
+        );
 
         Ok(PartyParams {
             lock_psbt: psbt,
@@ -388,6 +856,52 @@ where
             address: self.wallet.get_address(AddressIndex::New)?.address,
         })
     }
+
+    pub fn get_unlocked_balance(&mut self, _: GetUnlockedBalance) -> Result<Amount> {
+        let locked_utxos = self.used_utxos.list();
+
+        let unlocked_balance = self
+            .wallet
+            .list_unspent()?
+            .into_iter()
+            .filter(|utxo| !locked_utxos.contains(&utxo.outpoint))
+            .map(|utxo| utxo.txout.value)
+            .sum();
+
+        Ok(Amount::from_sat(unlocked_balance))
+    }
+
+    pub fn get_transaction(&mut self, msg: GetTransaction) -> Result<Option<Transaction>> {
+        let details = self.wallet.get_tx(&msg.txid, true)?;
+
+        Ok(details.and_then(|details| details.transaction))
+    }
+
+    pub fn get_confirmations(&mut self, msg: GetConfirmations) -> Result<Option<u32>> {
+        let details = match self.wallet.get_tx(&msg.txid, false)? {
+            Some(details) => details,
+            None => return Ok(None),
+        };
+
+        let confirmations = match details.confirmation_time {
+            Some(block) => {
+                let tip = self
+                    .blockchain_client
+                    .get_height()
+                    .context("Failed to get chain tip height")?;
+
+                tip.saturating_sub(block.height) + 1
+            }
+            None => 0,
+        };
+
+        Ok(Some(confirmations))
+    }
+
+    pub fn handle_collect_orphans(&mut self, msg: janitor::CollectOrphans) -> usize {
+        self.used_utxos
+            .release_orphaned(&msg.live_orders, janitor::MIN_AGE, msg.dry_run)
+    }
 }
 
 #[async_trait]
@@ -403,6 +917,14 @@ where
             &this.clone(),
             this.send_interval(SYNC_INTERVAL, || Sync, xtras::IncludeSpan::Always),
         );
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(
+                PREVIOUS_WALLET_SYNC_INTERVAL,
+                || SyncPreviousWallet,
+                xtras::IncludeSpan::Always,
+            ),
+        );
     }
 
     async fn stopped(self) -> Self::Stop {}
@@ -413,12 +935,45 @@ pub struct BuildPartyParams {
     pub amount: Amount,
     pub identity_pk: PublicKey,
     pub fee_rate: TxFeeRate,
+    /// Order whose contract setup the UTXOs of the lock transaction are locked for
+    pub order_id: OrderId,
+}
+
+/// Get the sum of all UTXOs which are not locked by an ongoing contract setup.
+#[derive(Clone, Copy)]
+pub struct GetUnlockedBalance;
+
+/// Get a wallet transaction including its inputs and outputs.
+#[derive(Clone, Copy)]
+pub struct GetTransaction {
+    pub txid: Txid,
+}
+
+/// Get the number of confirmations of a wallet transaction as of the last sync.
+///
+/// Returns `None` if the wallet does not know the transaction.
+#[derive(Clone, Copy)]
+pub struct GetConfirmations {
+    pub txid: Txid,
 }
 
 /// Message to trigger a sync.
 #[derive(Clone, Copy)]
 pub struct Sync;
 
+/// Message to trigger a sync of the wallet of the address type used before the configured one.
+#[derive(Clone, Copy)]
+pub struct SyncPreviousWallet;
+
+/// Spend all unlocked UTXOs into a single output if there are more than `max_utxos`.
+///
+/// Returns the txid of the consolidation transaction, if one was broadcast. It pays the minimum
+/// relay fee, hence it may take a while to confirm.
+#[derive(Clone, Copy)]
+pub struct ConsolidateUtxos {
+    pub max_utxos: usize,
+}
+
 pub struct Sign {
     pub psbt: PartiallySignedTransaction,
 }
@@ -436,6 +991,37 @@ pub struct Withdraw {
     pub address: Address,
 }
 
+/// Send the entire spendable balance of the wallet to `addresses`, split evenly.
+///
+/// The fee is deducted from the share of the last address.
+pub struct Sweep {
+    pub addresses: Vec<Address>,
+    pub fee: Option<FeeRate>,
+}
+
+/// Send the unlocked balance above `threshold` to `address`.
+///
+/// The transaction pays the fee rate estimated for confirmation within
+/// [`FORWARD_CONFIRMATION_TARGET`] blocks, taken from the balance that stays in the wallet.
+pub struct ForwardExcess {
+    pub address: Address,
+    pub threshold: Amount,
+    pub max_fee_rate: FeeRate,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ForwardOutcome {
+    Forwarded {
+        txid: Txid,
+        amount: Amount,
+        fee_rate: FeeRate,
+    },
+    /// The balance above the threshold is less than [`MIN_FORWARD_AMOUNT_SAT`]
+    NothingToForward,
+    /// The estimated fee rate exceeds the maximum, nothing was sent
+    FeeRateTooHigh { estimated: FeeRate },
+}
+
 /// Bitcoin error codes: <https://github.com/bitcoin/bitcoin/blob/97d3500601c1d28642347d014a6de1e38f53ae4e/src/rpc/protocol.h#L23>
 #[derive(Clone, Copy)]
 pub enum RpcErrorCode {
@@ -477,15 +1063,22 @@ where
         used_utxos: &mut LockedUtxos,
         fee_rate: FeeRate,
     ) -> Result<PartiallySignedTransaction> {
-        let mut builder = self.build_tx();
+        let unspendable = used_utxos.list();
 
-        builder
-            .ordering(TxOrdering::Bip69Lexicographic) // TODO: I think this is pointless but we did this in maia.
-            .fee_rate(fee_rate)
-            .unspendable(used_utxos.list())
-            .add_2of2_multisig_recipient(amount);
+        let mut psbt = build_lock_psbt(self, amount, &unspendable, &[], fee_rate)?;
 
-        let (psbt, _) = builder.finish()?;
+        // The coin selection does not minimise the number of inputs. Spending the largest UTXOs
+        // funds the margin with as few inputs as possible.
+        if psbt.unsigned_tx.input.len() > MAX_LOCK_INPUTS {
+            let largest = largest_utxos(self.list_unspent()?, &unspendable, amount);
+            psbt = build_lock_psbt(self, amount, &unspendable, &largest, fee_rate)?;
+        }
+
+        let num_inputs = psbt.unsigned_tx.input.len();
+        ensure!(
+            num_inputs <= MAX_LOCK_INPUTS,
+            "Funding a margin of {amount} requires {num_inputs} UTXOs but at most {MAX_LOCK_INPUTS} inputs are supported, consolidate the UTXOs of the wallet first"
+        );
 
         let used_inputs = psbt
             .unsigned_tx
@@ -498,8 +1091,54 @@ where
     }
 }
 
+fn build_lock_psbt<DB>(
+    wallet: &Wallet<DB>,
+    amount: Amount,
+    unspendable: &[OutPoint],
+    must_spend: &[OutPoint],
+    fee_rate: FeeRate,
+) -> Result<PartiallySignedTransaction>
+where
+    DB: BatchDatabase,
+{
+    let mut builder = wallet.build_tx();
+
+    builder
+        .ordering(TxOrdering::Bip69Lexicographic) // TODO: I think this is pointless but we did this in maia.
+        .fee_rate(fee_rate)
+        .unspendable(unspendable.to_vec())
+        .add_utxos(must_spend)?
+        .add_2of2_multisig_recipient(amount);
+
+    let (psbt, _) = builder.finish()?;
+
+    Ok(psbt)
+}
+
+/// The largest spendable UTXOs that add up to at least `amount`.
+fn largest_utxos(utxos: Vec<LocalUtxo>, unspendable: &[OutPoint], amount: Amount) -> Vec<OutPoint> {
+    let mut utxos = utxos
+        .into_iter()
+        .filter(|utxo| !unspendable.contains(&utxo.outpoint))
+        .collect::<Vec<_>>();
+    utxos.sort_by_key(|utxo| std::cmp::Reverse(utxo.txout.value));
+
+    let mut total = 0;
+    utxos
+        .into_iter()
+        .take_while(|utxo| {
+            let needed = total < amount.to_sat();
+            total += utxo.txout.value;
+            needed
+        })
+        .map(|utxo| utxo.outpoint)
+        .collect()
+}
+
 struct LockedUtxos {
     inner: HashSet<(Instant, OutPoint)>,
+    /// Orders whose contract setup locked a UTXO
+    orders: HashMap<OutPoint, OrderId>,
     time_to_lock: Duration,
 }
 
@@ -507,10 +1146,58 @@ impl LockedUtxos {
     fn new(time_to_lock: Duration) -> Self {
         Self {
             inner: HashSet::default(),
+            orders: HashMap::default(),
             time_to_lock,
         }
     }
 
+    /// Record that locked UTXOs are used by the contract setup of an order.
+    fn assign<T: IntoIterator<Item = OutPoint>>(&mut self, utxos: T, order_id: OrderId) {
+        self.orders
+            .extend(utxos.into_iter().map(|utxo| (utxo, order_id)));
+    }
+
+    /// Unlock the UTXOs that were locked for orders that are not live anymore.
+    ///
+    /// UTXOs locked for less than `min_age` are kept, their order might not be known as live yet.
+    /// UTXOs that were not locked for an order are left to expire. Returns the number of orphaned
+    /// UTXOs, which are only unlocked unless `dry_run`.
+    fn release_orphaned(
+        &mut self,
+        live_orders: &HashSet<OrderId>,
+        min_age: Duration,
+        dry_run: bool,
+    ) -> usize {
+        self.remove_expired();
+
+        let now = Instant::now();
+        let orphaned = self
+            .inner
+            .iter()
+            .filter(|(locked_at, utxo)| {
+                let order_id = match self.orders.get(utxo) {
+                    Some(order_id) => order_id,
+                    None => return false,
+                };
+
+                now >= *locked_at + min_age && !live_orders.contains(order_id)
+            })
+            .copied()
+            .collect::<Vec<_>>();
+
+        if !dry_run {
+            for lock in orphaned.iter() {
+                let (_, utxo) = lock;
+                tracing::debug!(%utxo, order_id = ?self.orders.get(utxo), "Unlocking orphaned UTXO");
+
+                self.inner.remove(lock);
+                self.orders.remove(utxo);
+            }
+        }
+
+        orphaned.len()
+    }
+
     /// Add new elements to the set of locked UTXOs.
     fn extend<T: IntoIterator<Item = OutPoint>>(&mut self, utxos: T) {
         let now = Instant::now();
@@ -538,9 +1225,48 @@ impl LockedUtxos {
             .drain()
             .skip_while(|(locked_at, _)| now >= *locked_at + self.time_to_lock)
             .collect();
+
+        let inner = &self.inner;
+        self.orders
+            .retain(|utxo, _| inner.iter().any(|(_, locked)| locked == utxo));
     }
 }
 
+/// The descriptors of the external and internal keychain of `address_type`.
+fn descriptors(
+    ext_priv_key: ExtendedPrivKey,
+    address_type: AddressType,
+) -> Result<[(ExtendedDescriptor, KeyMap); 2]> {
+    let secp = Secp256k1::new();
+    let network = ext_priv_key.network;
+
+    let descriptors = match address_type {
+        AddressType::Wpkh => [
+            bdk::template::Bip84(ext_priv_key, KeychainKind::External)
+                .into_wallet_descriptor(&secp, network)?,
+            bdk::template::Bip84(ext_priv_key, KeychainKind::Internal)
+                .into_wallet_descriptor(&secp, network)?,
+        ],
+        AddressType::Taproot => {
+            let coin_type = match network {
+                Network::Bitcoin => 0,
+                _ => 1,
+            };
+
+            [
+                format!("tr({ext_priv_key}/86'/{coin_type}'/0'/0/*)")
+                    .as_str()
+                    .into_wallet_descriptor(&secp, network)?,
+                format!("tr({ext_priv_key}/86'/{coin_type}'/0'/1/*)")
+                    .as_str()
+                    .into_wallet_descriptor(&secp, network)?,
+            ]
+        }
+    };
+
+    Ok(descriptors)
+}
+
 /// Compare the hash of the genesis block of the electrum RPC endpoint to the expected network's
 /// genesis block hash. If they differ, the electrum RPC is not for the network that we expect.
 fn seed_and_rpc_on_same_network(rpc: &electrum_client::Client, network: Network) -> Result<bool> {
@@ -553,6 +1279,21 @@ fn seed_and_rpc_on_same_network(rpc: &electrum_client::Client, network: Network)
     Ok(network_hash == rpc_hash)
 }
 
+/// The smallest confirmation target whose estimated fee rate is paid by `fee_rate`.
+///
+/// `fee_estimates` are pairs of confirmation target in blocks and the estimated fee rate to
+/// confirm within that target.
+fn estimate_confirmation_blocks(
+    fee_rate: FeeRate,
+    fee_estimates: &[(u32, FeeRate)],
+) -> Option<u32> {
+    fee_estimates
+        .iter()
+        .filter(|(_, estimate)| fee_rate.as_sat_per_vb() >= estimate.as_sat_per_vb())
+        .map(|(target, _)| *target)
+        .min()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -586,14 +1327,14 @@ mod tests {
 
             Ok(Self {
                 wallet,
+                address_type: AddressType::Wpkh,
+                previous_wallet: None,
                 sender,
-                used_utxos: LockedUtxos {
-                    inner: HashSet::default(),
-                    time_to_lock,
-                },
+                used_utxos: LockedUtxos::new(time_to_lock),
                 blockchain_client: (),
                 db: None,
                 managed_wallet: true,
+                electrum_endpoint: None,
             })
         }
     }
@@ -724,10 +1465,7 @@ mod tests {
     #[test]
     fn creating_two_lock_transactions_uses_different_utxos() {
         let mut wallet = new_test_wallet(&mut thread_rng(), Amount::from_sat(1000), 10).unwrap();
-        let mut used_utxos = LockedUtxos {
-            inner: HashSet::default(),
-            time_to_lock: Duration::from_secs(120),
-        };
+        let mut used_utxos = LockedUtxos::new(Duration::from_secs(120));
 
         let lock_tx_1 = wallet
             .build_lock_tx(
@@ -794,6 +1532,7 @@ mod tests {
                 amount: Amount::from_btc(0.2).unwrap(),
                 identity_pk,
                 fee_rate: TxFeeRate::default(),
+                order_id: OrderId::default(),
             })
             .await
             .unwrap()
@@ -806,12 +1545,99 @@ mod tests {
                 amount: Amount::from_btc(0.2).unwrap(),
                 identity_pk,
                 fee_rate: TxFeeRate::default(),
+                order_id: OrderId::default(),
             })
             .await
             .unwrap()
             .expect_err("single UTXO to remain locked");
     }
 
+    #[test]
+    fn margin_requiring_too_many_inputs_is_rejected() {
+        let mut wallet = new_test_wallet(&mut thread_rng(), Amount::from_sat(1000), 250).unwrap();
+        let mut used_utxos = LockedUtxos::new(Duration::from_secs(120));
+
+        let result = wallet.build_lock_tx(
+            Amount::from_sat(230_000),
+            &mut used_utxos,
+            FeeRate::default_min_relay_fee(),
+        );
+
+        assert!(result.is_err());
+        assert!(used_utxos.list().is_empty());
+    }
+
+    #[test]
+    fn only_utxos_of_orders_that_are_not_live_are_released() {
+        let utxo = |vout| OutPoint::new(Txid::all_zeros(), vout);
+        let live = OrderId::default();
+        let orphaned = OrderId::default();
+
+        let mut used_utxos = LockedUtxos::new(Duration::from_secs(120));
+        used_utxos.extend([utxo(0), utxo(1), utxo(2)]);
+        used_utxos.assign([utxo(0)], live);
+        used_utxos.assign([utxo(1)], orphaned);
+
+        let live_orders = HashSet::from([live]);
+
+        // dry run only reports the orphaned UTXO
+        assert_eq!(
+            used_utxos.release_orphaned(&live_orders, Duration::ZERO, true),
+            1
+        );
+        assert_eq!(used_utxos.list().len(), 3);
+
+        // recently locked UTXOs are kept
+        assert_eq!(
+            used_utxos.release_orphaned(&live_orders, Duration::from_secs(60), false),
+            0
+        );
+
+        assert_eq!(
+            used_utxos.release_orphaned(&live_orders, Duration::ZERO, false),
+            1
+        );
+        assert_eq!(
+            used_utxos.list().into_iter().sorted().collect::<Vec<_>>(),
+            vec![utxo(0), utxo(2)]
+        );
+    }
+
+    #[tokio::test]
+    async fn locked_utxos_are_not_part_of_unlocked_balance() {
+        let mut tasks = Tasks::default();
+
+        let actor = Actor::new_offline::<MemoryDatabase>(
+            Amount::ONE_BTC,
+            2,
+            Duration::from_secs(120),
+            MemoryDatabase::new(),
+        )
+        .unwrap()
+        .create(None)
+        .spawn(&mut tasks);
+
+        let unlocked_balance = actor.send(GetUnlockedBalance).await.unwrap().unwrap();
+        assert_eq!(unlocked_balance, Amount::from_btc(2.0).unwrap());
+
+        let (_, identity_pk) = keypair::new(&mut thread_rng());
+
+        // building party params locks one of our UTXOs
+        actor
+            .send(BuildPartyParams {
+                amount: Amount::from_btc(0.2).unwrap(),
+                identity_pk,
+                fee_rate: TxFeeRate::default(),
+                order_id: OrderId::default(),
+            })
+            .await
+            .unwrap()
+            .expect("UTXO to be available");
+
+        let unlocked_balance = actor.send(GetUnlockedBalance).await.unwrap().unwrap();
+        assert_eq!(unlocked_balance, Amount::ONE_BTC);
+    }
+
     #[tokio::test]
     async fn utxo_can_be_unlocked_after_marking_as_unspendable() {
         let mut tasks = Tasks::default();
@@ -837,6 +1663,7 @@ mod tests {
                 amount: Amount::from_btc(0.2).unwrap(),
                 identity_pk,
                 fee_rate: TxFeeRate::default(),
+                order_id: OrderId::default(),
             })
             .await
             .unwrap()
@@ -849,6 +1676,7 @@ mod tests {
                 amount: Amount::from_btc(0.2).unwrap(),
                 identity_pk,
                 fee_rate: TxFeeRate::default(),
+                order_id: OrderId::default(),
             })
             .await
             .unwrap()
@@ -864,9 +1692,81 @@ mod tests {
                 amount: Amount::from_btc(0.2).unwrap(),
                 identity_pk,
                 fee_rate: TxFeeRate::default(),
+                order_id: OrderId::default(),
             })
             .await
             .unwrap()
             .expect("single UTXO to be available after unlocking it");
     }
+
+    #[test]
+    fn switching_to_taproot_keeps_wpkh_wallet_for_sweeping() {
+        let ext_priv_key = ExtendedPrivKey::new_master(Network::Regtest, &[1u8; 32]).unwrap();
+        let db = sled::Config::new().temporary(true).open().unwrap();
+
+        let wpkh_wallet =
+            Actor::<ElectrumBlockchain, Tree>::build_wallet(ext_priv_key, AddressType::Wpkh, &db)
+                .unwrap();
+        let wpkh_address = wpkh_wallet.get_address(AddressIndex::New).unwrap().address;
+        assert_eq!(
+            wpkh_address.address_type(),
+            Some(bdk::bitcoin::AddressType::P2wpkh)
+        );
+
+        let taproot_wallet = Actor::<ElectrumBlockchain, Tree>::build_wallet(
+            ext_priv_key,
+            AddressType::Taproot,
+            &db,
+        )
+        .unwrap();
+        let taproot_address = taproot_wallet
+            .get_address(AddressIndex::New)
+            .unwrap()
+            .address;
+        assert_eq!(
+            taproot_address.address_type(),
+            Some(bdk::bitcoin::AddressType::P2tr)
+        );
+
+        let previous_wallet = Actor::<ElectrumBlockchain, Tree>::build_previous_wallet(
+            ext_priv_key,
+            AddressType::Taproot,
+            &db,
+        )
+        .unwrap()
+        .expect("wpkh wallet to have been used before");
+        assert_eq!(
+            previous_wallet
+                .get_address(AddressIndex::Peek(0))
+                .unwrap()
+                .address,
+            wpkh_address
+        );
+    }
+
+    #[test]
+    fn pending_deposit_confirms_within_smallest_target_its_fee_rate_pays_for() {
+        let fee_estimates = [
+            (1, FeeRate::from_sat_per_vb(20.0)),
+            (3, FeeRate::from_sat_per_vb(10.0)),
+            (6, FeeRate::from_sat_per_vb(5.0)),
+        ];
+
+        assert_eq!(
+            estimate_confirmation_blocks(FeeRate::from_sat_per_vb(25.0), &fee_estimates),
+            Some(1)
+        );
+        assert_eq!(
+            estimate_confirmation_blocks(FeeRate::from_sat_per_vb(10.0), &fee_estimates),
+            Some(3)
+        );
+        assert_eq!(
+            estimate_confirmation_blocks(FeeRate::from_sat_per_vb(1.0), &fee_estimates),
+            None
+        );
+        assert_eq!(
+            estimate_confirmation_blocks(FeeRate::from_sat_per_vb(25.0), &[]),
+            None
+        );
+    }
 }
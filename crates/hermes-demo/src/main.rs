@@ -0,0 +1,155 @@
+//! Maker and taker running in a single process, to try out the full flow with a single command.
+//!
+//! Both run on regtest and connect through the in-memory transport of libp2p, hence no ports need
+//! to be opened between them. Each serves its HTTP API on its own port. The wallets need an
+//! electrum server for regtest, e.g. the one started by `nigiri`.
+//!
+//! Run with `cargo run -p hermes-demo --features demo`.
+
+use anyhow::Context;
+use anyhow::Result;
+use clap::Parser;
+use daemon::seed;
+use daemon::seed::RandomSeed;
+use daemon::seed::Seed;
+use shared_bin::cli::Network;
+use shared_bin::logger;
+use shared_bin::logger::LevelFilter;
+use shared_bin::logger::LOCAL_COLLECTOR_ENDPOINT;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::path::PathBuf;
+use taker::TakerHandle;
+
+/// The address the maker listens on for the taker
+const MAKER_P2P_ADDRESS: &str = "/memory/10000";
+
+#[derive(Parser)]
+struct Opts {
+    /// URL to the electrum backend of the regtest network.
+    #[clap(long, default_value = "tcp://127.0.0.1:50000")]
+    electrum: String,
+
+    /// Where to store the data of the maker and the taker, defaults to `hermes-demo` in the
+    /// temporary directory.
+    #[clap(long)]
+    data_dir: Option<PathBuf>,
+
+    /// The IP address to listen on for the HTTP API of the maker.
+    #[clap(long, default_value = "127.0.0.1:8001")]
+    maker_http_address: SocketAddr,
+
+    /// The IP address to listen on for the HTTP API of the taker.
+    #[clap(long, default_value = "127.0.0.1:8000")]
+    taker_http_address: SocketAddr,
+
+    /// Configure the log level, e.g.: one of Error, Warn, Info, Debug, Trace
+    #[clap(short, long, default_value = "Info")]
+    log_level: LevelFilter,
+}
+
+#[rocket::main]
+async fn main() -> Result<()> {
+    let opts = Opts::parse();
+
+    let data_dir = opts
+        .data_dir
+        .unwrap_or_else(|| std::env::temp_dir().join("hermes-demo"));
+    let maker_data_dir = data_dir.join("maker");
+    let taker_data_dir = data_dir.join("taker");
+
+    tokio::fs::create_dir_all(&data_dir).await?;
+
+    // The maker and the taker share the logger, it can only be initialized once per process
+    let _guard = logger::init(
+        opts.log_level,
+        false,
+        false,
+        false,
+        false,
+        false,
+        "hermes-demo",
+        LOCAL_COLLECTOR_ENDPOINT,
+        false,
+        path_str(&data_dir)?,
+    )
+    .context("initialize logger")?;
+
+    let (maker_id, maker_peer_id) = maker_identity(&maker_data_dir, &opts.electrum).await?;
+
+    let maker_opts = maker::Opts::try_parse_from([
+        "maker",
+        "--data-dir",
+        path_str(&maker_data_dir)?,
+        "--http-address",
+        opts.maker_http_address.to_string().as_str(),
+        "--p2p-listen-address",
+        MAKER_P2P_ADDRESS,
+        "--headless",
+        "regtest",
+        "--electrum",
+        opts.electrum.as_str(),
+    ])?;
+
+    let taker_opts = taker::Opts::try_parse_from([
+        "taker",
+        "--data-dir",
+        path_str(&taker_data_dir)?,
+        "--maker",
+        MAKER_P2P_ADDRESS,
+        "--maker-id",
+        maker_id.as_str(),
+        "--maker-peer-id",
+        maker_peer_id.as_str(),
+        "--headless",
+        "regtest",
+        "--electrum",
+        opts.electrum.as_str(),
+    ])?;
+
+    tracing::info!(
+        maker = %opts.maker_http_address,
+        taker = %opts.taker_http_address,
+        data_dir = %data_dir.display(),
+        "Starting maker and taker"
+    );
+
+    let taker = async {
+        TakerHandle::start(&taker_opts)
+            .await?
+            .serve_http(opts.taker_http_address, false)
+            .await
+    };
+
+    tokio::try_join!(maker::serve(maker_opts), taker)?;
+
+    Ok(())
+}
+
+/// The maker id and peer id of the maker, which the taker needs to connect to it.
+///
+/// The identity seed of the maker is created upfront if it does not exist yet.
+async fn maker_identity(maker_data_dir: &Path, electrum: &str) -> Result<(String, String)> {
+    let network = Network::Regtest {
+        electrum: electrum.to_string(),
+        electrum_backup: Vec::new(),
+        command: None,
+    };
+    let data_dir = network.data_dir(maker_data_dir.to_path_buf());
+
+    tokio::fs::create_dir_all(&data_dir).await?;
+
+    let identities = RandomSeed::initialize(&data_dir.join(seed::MAKER_IDENTITY_SEED_FILE))
+        .await?
+        .derive_identities();
+
+    Ok((
+        hex::encode(identities.identity_pk.to_bytes()),
+        identities.peer_id().to_string(),
+    ))
+}
+
+fn path_str(path: &Path) -> Result<&str> {
+    path.to_str()
+        .with_context(|| format!("Data directory {} is not valid UTF-8", path.display()))
+}
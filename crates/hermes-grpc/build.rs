@@ -0,0 +1,10 @@
+fn main() -> anyhow::Result<()> {
+    // Use a vendored `protoc` so that building does not depend on a system installation
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+
+    tonic_build::configure()
+        .build_client(true)
+        .compile(&["proto/hermes/v1/projection.proto"], &["proto"])?;
+
+    Ok(())
+}
@@ -0,0 +1,205 @@
+//! Conversions from the projection into the protobuf messages.
+
+use crate::proto;
+use daemon::projection;
+use daemon::projection::CfdState;
+use model::ContractSymbol;
+use model::Position;
+use model::Role;
+use model::WalletInfo;
+
+impl From<ContractSymbol> for proto::ContractSymbol {
+    fn from(symbol: ContractSymbol) -> Self {
+        match symbol {
+            ContractSymbol::BtcUsd => proto::ContractSymbol::Btcusd,
+            ContractSymbol::EthUsd => proto::ContractSymbol::Ethusd,
+        }
+    }
+}
+
+impl From<Position> for proto::Position {
+    fn from(position: Position) -> Self {
+        match position {
+            Position::Long => proto::Position::Long,
+            Position::Short => proto::Position::Short,
+        }
+    }
+}
+
+impl From<Role> for proto::Role {
+    fn from(role: Role) -> Self {
+        match role {
+            Role::Maker => proto::Role::Maker,
+            Role::Taker => proto::Role::Taker,
+        }
+    }
+}
+
+impl From<CfdState> for proto::CfdState {
+    fn from(state: CfdState) -> Self {
+        match state {
+            CfdState::PendingSetup => proto::CfdState::PendingSetup,
+            CfdState::ContractSetup => proto::CfdState::ContractSetup,
+            CfdState::Rejected => proto::CfdState::Rejected,
+            CfdState::PendingOpen => proto::CfdState::PendingOpen,
+            CfdState::Open => proto::CfdState::Open,
+            CfdState::PendingCommit => proto::CfdState::PendingCommit,
+            CfdState::PendingCet => proto::CfdState::PendingCet,
+            CfdState::PendingClose => proto::CfdState::PendingClose,
+            CfdState::OpenCommitted => proto::CfdState::OpenCommitted,
+            CfdState::IncomingSettlementProposal => proto::CfdState::IncomingSettlementProposal,
+            CfdState::OutgoingSettlementProposal => proto::CfdState::OutgoingSettlementProposal,
+            CfdState::RolloverSetup => proto::CfdState::RolloverSetup,
+            CfdState::Closed => proto::CfdState::Closed,
+            CfdState::PendingRefund => proto::CfdState::PendingRefund,
+            CfdState::Refunded => proto::CfdState::Refunded,
+            CfdState::SetupFailed => proto::CfdState::SetupFailed,
+        }
+    }
+}
+
+impl From<&projection::Cfd> for proto::Cfd {
+    fn from(cfd: &projection::Cfd) -> Self {
+        let mut actions = cfd
+            .actions
+            .iter()
+            .map(|action| action.to_string())
+            .collect::<Vec<_>>();
+        actions.sort();
+
+        Self {
+            order_id: cfd.order_id.to_string(),
+            offer_id: cfd.offer_id.to_string(),
+            contract_symbol: proto::ContractSymbol::from(cfd.contract_symbol).into(),
+            position: proto::Position::from(cfd.position).into(),
+            role: proto::Role::from(cfd.role).into(),
+            state: proto::CfdState::from(cfd.state).into(),
+            initial_price: cfd.initial_price.into_decimal().to_string(),
+            quantity: cfd.quantity.into_decimal().to_string(),
+            leverage: cfd.leverage_taker.get().into(),
+            liquidation_price: cfd.liquidation_price.to_string(),
+            margin_sat: cfd.margin.as_sat(),
+            margin_counterparty_sat: cfd.margin_counterparty.as_sat(),
+            accumulated_fees_sat: cfd.accumulated_fees.as_sat(),
+            profit_sat: cfd.profit_btc.map(|profit| profit.as_sat()),
+            profit_percent: cfd.profit_percent.clone(),
+            payout_sat: cfd.payout.map(|payout| payout.as_sat()),
+            closing_price: cfd
+                .closing_price
+                .map(|price| price.into_decimal().to_string()),
+            expiry_timestamp: cfd.expiry_timestamp.map(|expiry| expiry.unix_timestamp()),
+            counterparty: cfd.counterparty.to_string(),
+            actions,
+            tags: cfd.tags.clone(),
+        }
+    }
+}
+
+impl From<&projection::CfdOffer> for proto::Offer {
+    fn from(offer: &projection::CfdOffer) -> Self {
+        Self {
+            id: offer.id.to_string(),
+            contract_symbol: proto::ContractSymbol::from(offer.contract_symbol).into(),
+            position: proto::Position::from(offer.position_maker).into(),
+            price: offer.price.into_decimal().to_string(),
+            min_quantity: offer.min_quantity.into_decimal().to_string(),
+            max_quantity: offer.max_quantity.into_decimal().to_string(),
+            funding_rate_hourly_percent: offer.funding_rate_hourly_percent.clone(),
+            leverage_choices: offer
+                .leverage_details
+                .iter()
+                .map(|details| details.leverage.get().into())
+                .collect(),
+            creation_timestamp: offer.creation_timestamp.seconds(),
+            settlement_time_interval_secs: offer.settlement_time_interval_in_secs,
+        }
+    }
+}
+
+impl From<&projection::MakerOffers> for proto::Offers {
+    fn from(offers: &projection::MakerOffers) -> Self {
+        let offers = [
+            &offers.btcusd_long,
+            &offers.btcusd_short,
+            &offers.ethusd_long,
+            &offers.ethusd_short,
+        ]
+        .into_iter()
+        .flatten()
+        .map(proto::Offer::from)
+        .collect();
+
+        Self { offers }
+    }
+}
+
+impl From<&projection::LatestQuotes> for proto::Quotes {
+    fn from(quotes: &projection::LatestQuotes) -> Self {
+        let mut quotes = quotes
+            .iter()
+            .map(|(symbol, quote)| proto::Quote {
+                contract_symbol: proto::ContractSymbol::from(*symbol).into(),
+                bid: quote.bid.to_string(),
+                ask: quote.ask.to_string(),
+                last_updated_at: quote.last_updated_at.seconds(),
+            })
+            .collect::<Vec<_>>();
+        quotes.sort_by_key(|quote| quote.contract_symbol);
+
+        Self { quotes }
+    }
+}
+
+impl From<&WalletInfo> for proto::Wallet {
+    fn from(wallet: &WalletInfo) -> Self {
+        Self {
+            balance_sat: wallet.balance.as_sat(),
+            address: wallet.address.to_string(),
+            last_updated_at: wallet.last_updated_at.seconds(),
+            block_height: wallet.block_height,
+            pending_deposits: wallet
+                .pending_deposits
+                .iter()
+                .map(|deposit| proto::PendingDeposit {
+                    txid: deposit.txid.to_string(),
+                    amount_sat: deposit.amount.as_sat(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use daemon::projection::Quote;
+    use model::Timestamp;
+    use std::collections::HashMap;
+
+    #[test]
+    fn quotes_are_sorted_by_symbol() {
+        let quotes = HashMap::from([
+            (ContractSymbol::EthUsd, quote(1_500)),
+            (ContractSymbol::BtcUsd, quote(20_000)),
+        ]);
+
+        let proto::Quotes { quotes } = proto::Quotes::from(&quotes);
+
+        assert_eq!(
+            quotes
+                .iter()
+                .map(|quote| quote.contract_symbol())
+                .collect::<Vec<_>>(),
+            vec![proto::ContractSymbol::Btcusd, proto::ContractSymbol::Ethusd]
+        );
+        assert_eq!(quotes[0].bid, "20000");
+    }
+
+    fn quote(price: u32) -> Quote {
+        Quote {
+            bid: price.into(),
+            ask: price.into(),
+            last_updated_at: Timestamp::new(0),
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Read-only gRPC streaming API over the projection of the maker and taker daemons.
+//!
+//! Serves typed and versioned access to the CFDs, offers, quotes and wallet, e.g. for trading
+//! bots or monitoring, without scraping the JSON event stream consumed by the browser UI. See
+//! `proto/hermes/v1/projection.proto` for the message definitions.
+
+use anyhow::Result;
+use daemon::projection;
+use futures::Stream;
+use futures::StreamExt;
+use model::WalletInfo;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use tokio::sync::watch;
+use tokio_stream::wrappers::WatchStream;
+use tonic::metadata::MetadataValue;
+use tonic::Request;
+use tonic::Response;
+use tonic::Status;
+
+mod convert;
+
+pub mod proto {
+    tonic::include_proto!("hermes.v1");
+}
+
+type Subscription<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+/// Serves the projection from the feeds that also back the HTTP API.
+#[derive(Clone)]
+pub struct Service {
+    cfds: watch::Receiver<Option<Vec<projection::Cfd>>>,
+    offers: watch::Receiver<projection::MakerOffers>,
+    quotes: watch::Receiver<projection::LatestQuotes>,
+    wallet: watch::Receiver<Option<WalletInfo>>,
+}
+
+impl Service {
+    pub fn new(
+        cfds: watch::Receiver<Option<Vec<projection::Cfd>>>,
+        offers: watch::Receiver<projection::MakerOffers>,
+        quotes: watch::Receiver<projection::LatestQuotes>,
+        wallet: watch::Receiver<Option<WalletInfo>>,
+    ) -> Self {
+        Self {
+            cfds,
+            offers,
+            quotes,
+            wallet,
+        }
+    }
+
+    /// Serve the API on `address` until the process is stopped.
+    ///
+    /// Requests need to carry the `auth_token` as `authorization: Bearer <token>` metadata.
+    pub async fn serve(self, address: SocketAddr, auth_token: String) -> Result<()> {
+        let expected: MetadataValue<_> = format!("Bearer {auth_token}").parse()?;
+
+        let check_auth = move |request: Request<()>| match request.metadata().get("authorization") {
+            Some(token) if token == expected => Ok(request),
+            _ => Err(Status::unauthenticated("Invalid or missing auth token")),
+        };
+
+        tracing::info!(%address, "Serving gRPC API");
+
+        tonic::transport::Server::builder()
+            .add_service(
+                proto::projection_server::ProjectionServer::with_interceptor(self, check_auth),
+            )
+            .serve(address)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Stream the current value of `feed` and every change, skipping `None` values.
+fn subscribe<T, M>(feed: watch::Receiver<Option<T>>, convert: fn(&T) -> M) -> Subscription<M>
+where
+    T: Clone + Send + Sync + 'static,
+    M: Send + 'static,
+{
+    let stream = WatchStream::new(feed)
+        .filter_map(move |value| futures::future::ready(value.as_ref().map(convert).map(Ok)));
+
+    Box::pin(stream)
+}
+
+#[tonic::async_trait]
+impl proto::projection_server::Projection for Service {
+    type SubscribeCfdsStream = Subscription<proto::CfdList>;
+    type SubscribeOffersStream = Subscription<proto::Offers>;
+    type SubscribeQuotesStream = Subscription<proto::Quotes>;
+    type SubscribeWalletStream = Subscription<proto::Wallet>;
+
+    async fn subscribe_cfds(
+        &self,
+        _: Request<proto::SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeCfdsStream>, Status> {
+        Ok(Response::new(subscribe(self.cfds.clone(), |cfds| {
+            proto::CfdList {
+                cfds: cfds.iter().map(proto::Cfd::from).collect(),
+            }
+        })))
+    }
+
+    async fn subscribe_offers(
+        &self,
+        _: Request<proto::SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeOffersStream>, Status> {
+        let stream =
+            WatchStream::new(self.offers.clone()).map(|offers| Ok(proto::Offers::from(&offers)));
+
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn subscribe_quotes(
+        &self,
+        _: Request<proto::SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeQuotesStream>, Status> {
+        let stream =
+            WatchStream::new(self.quotes.clone()).map(|quotes| Ok(proto::Quotes::from(&quotes)));
+
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn subscribe_wallet(
+        &self,
+        _: Request<proto::SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeWalletStream>, Status> {
+        Ok(Response::new(subscribe(self.wallet.clone(), |wallet| {
+            proto::Wallet::from(wallet)
+        })))
+    }
+}
@@ -0,0 +1,143 @@
+//! Enforce a CFD from an encrypted backup without running the daemon.
+//!
+//! Backups are downloaded from `/api/cfds/<order_id>/backup` of the maker or taker and can only be
+//! decrypted with the identity seed of the instance that created them.
+
+use anyhow::Context;
+use anyhow::Result;
+use bdk::bitcoin::consensus::encode::serialize_hex;
+use bdk::bitcoin::Transaction;
+use bdk::electrum_client;
+use bdk::electrum_client::ElectrumApi;
+use clap::Parser;
+use clap::Subcommand;
+use daemon::backup::Backup;
+use daemon::seed::RandomSeed;
+use daemon::seed::Seed;
+use model::olivia;
+use std::path::PathBuf;
+
+#[derive(Parser)]
+struct Opts {
+    /// The encrypted backup of the CFD.
+    #[clap(long)]
+    backup: PathBuf,
+
+    /// The identity seed file of the instance that created the backup, i.e. `maker_id_seed` or
+    /// `taker_id_seed` in its data directory.
+    #[clap(long)]
+    seed_file: PathBuf,
+
+    /// Publish the transaction through this Electrum server instead of only printing it.
+    #[clap(long)]
+    electrum: Option<String>,
+
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the contents of the backup.
+    Inspect,
+    /// Sign the commit transaction.
+    Commit,
+    /// Sign the CET matching an attestation of the oracle.
+    ///
+    /// The commit transaction has to be confirmed and its timelock expired before the CET can be
+    /// published.
+    Cet {
+        /// Read the attestation from this file instead of fetching it from the oracle.
+        #[clap(long)]
+        attestation: Option<PathBuf>,
+
+        /// The oracle event to fetch the attestation of, defaults to the settlement event.
+        #[clap(long)]
+        event_id: Option<olivia::BitMexPriceEventId>,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let opts = Opts::parse();
+
+    let seed = RandomSeed::read_from(&opts.seed_file)
+        .await
+        .with_context(|| format!("Failed to read seed file {}", opts.seed_file.display()))?;
+    let blob = tokio::fs::read(&opts.backup)
+        .await
+        .with_context(|| format!("Failed to read backup {}", opts.backup.display()))?;
+    let backup = Backup::decrypt(&blob, &seed.derive_backup_key())?;
+
+    let tx = match opts.command {
+        Command::Inspect => {
+            println!("Order id: {}", backup.order_id);
+            println!("Role: {:?}", backup.role);
+            println!("Position: {:?}", backup.position);
+            println!("Contract symbol: {}", backup.contract_symbol);
+            println!("Backup created at: {}", backup.created_at);
+            println!("Lock txid: {}", backup.dlc.lock.0.txid());
+            println!("Commit txid: {}", backup.dlc.commit.0.txid());
+            println!("Refund timelock: {}", backup.dlc.refund_timelock);
+            println!("Settlement event: {}", backup.settlement_event_id);
+            println!("Oracle events:");
+            for event_id in backup.oracle_event_ids.iter() {
+                println!("  {event_id}");
+            }
+
+            return Ok(());
+        }
+        Command::Commit => backup.dlc.signed_commit_tx()?,
+        Command::Cet {
+            attestation,
+            event_id,
+        } => {
+            let attestation = match attestation {
+                Some(path) => {
+                    let json = tokio::fs::read(&path).await.with_context(|| {
+                        format!("Failed to read attestation {}", path.display())
+                    })?;
+                    serde_json::from_slice(&json).context("Failed to decode attestation")?
+                }
+                None => fetch_attestation(event_id.unwrap_or(backup.settlement_event_id)).await?,
+            };
+
+            backup.dlc.signed_cet(&attestation)?
+        }
+    };
+
+    println!("{}", serialize_hex(&tx));
+
+    if let Some(url) = opts.electrum {
+        let txid = broadcast(&url, &tx)?;
+        println!("Published transaction {txid}");
+    }
+
+    Ok(())
+}
+
+async fn fetch_attestation(event_id: olivia::BitMexPriceEventId) -> Result<olivia::Attestation> {
+    let url = event_id.to_olivia_url();
+
+    let attestation = reqwest::get(url.clone())
+        .await
+        .with_context(|| format!("Failed to fetch attestation from {url}"))?
+        .error_for_status()
+        .with_context(|| format!("Oracle has no attestation for {event_id} yet"))?
+        .json()
+        .await
+        .context("Failed to deserialize as Attestation")?;
+
+    Ok(attestation)
+}
+
+fn broadcast(url: &str, tx: &Transaction) -> Result<bdk::bitcoin::Txid> {
+    let client = electrum_client::Client::new(url)
+        .with_context(|| format!("Failed to connect to Electrum server {url}"))?;
+
+    let txid = client
+        .transaction_broadcast(tx)
+        .context("Failed to publish transaction")?;
+
+    Ok(txid)
+}
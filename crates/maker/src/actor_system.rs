@@ -1,45 +1,68 @@
 use crate::cfd;
+use crate::collateral_forecast;
 use crate::metrics::time_to_first_position;
+use crate::offer_history;
+use crate::peer_sessions;
+use crate::shadow_pricing;
+use crate::volatility_spread::VolatilitySpreads;
+use anyhow::ensure;
 use anyhow::Result;
 use bdk::bitcoin;
 use bdk::bitcoin::util::psbt::PartiallySignedTransaction;
 use bdk::bitcoin::Amount;
 use bdk::bitcoin::Txid;
-use daemon::archive_closed_cfds;
-use daemon::archive_failed_cfds;
-use daemon::collab_settlement;
+use daemon::cfd_tags;
 use daemon::command;
+use daemon::daily_marks;
+use daemon::failure_report;
+use daemon::funding_rate_history;
 use daemon::identify;
+use daemon::instance_fence;
+use daemon::janitor;
+use daemon::libp2p_utils;
 use daemon::listen_protocols::MAKER_LISTEN_PROTOCOLS;
 use daemon::monitor;
 use daemon::oracle;
 use daemon::oracle::NoAnnouncement;
 use daemon::order;
+use daemon::order::pending_timeout::PendingOrderTimeouts;
 use daemon::position_metrics;
 use daemon::process_manager;
 use daemon::projection;
+use daemon::scheduler;
 use daemon::seed::Identities;
+use daemon::service_status;
+use daemon::telemetry;
 use daemon::wallet;
 use daemon::Environment;
-use libp2p_tcp::TokioTcpConfig;
 use maia_core::secp256k1_zkp::XOnlyPublicKey;
 use maia_core::PartyParams;
 use model::olivia::Announcement;
+use model::trading_calendar::TradingCalendar;
 use model::ContractSymbol;
 use model::Contracts;
+use model::FundingPeriod;
 use model::FundingRate;
+use model::Identity;
 use model::Leverage;
 use model::LotSize;
+use model::OfferUpdate;
 use model::OpeningFee;
+use model::OpeningFeeTiers;
 use model::OrderId;
 use model::Price;
 use model::Role;
+use model::Timestamp;
 use model::TxFeeRate;
 use ping_pong::ping;
 use ping_pong::pong;
+use serde::Serialize;
+use sqlite_db::offer_history::OfferRecord;
 use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::sync::watch;
 use tokio_extras::Tasks;
 use xtra::Actor;
 use xtra::Address;
@@ -74,10 +97,22 @@ pub struct ActorSystem<O: 'static, W: 'static> {
             cfd::RatesChannel,
         >,
     >,
-    _oracle_actor: Address<O>,
-    _archive_closed_cfds_actor: Address<archive_closed_cfds::Actor>,
-    _archive_failed_cfds_actor: Address<archive_failed_cfds::Actor>,
-    executor: command::Executor,
+    pub oracle_actor: Address<O>,
+    pub endpoint: Address<Endpoint>,
+    pub scheduler_actor: Address<scheduler::Actor>,
+    pub executor: command::Executor,
+    pub position_metrics: Address<position_metrics::Actor>,
+    offer: Address<offer::maker::Actor>,
+    offer_deprecated: Address<offer::deprecated::maker::Actor>,
+    offer_deprecated_v2: Address<offer::deprecated_v2::maker::Actor>,
+    order: Address<order::maker::Actor>,
+    order_deprecated: Address<order::deprecated::maker::Actor>,
+    service_status: Address<service_status::maker::Actor>,
+    deprecated_offer_protocol_cutoff: Option<OffsetDateTime>,
+    offer_history: Address<offer_history::Actor>,
+    peer_sessions: Address<peer_sessions::Actor>,
+    cfd_tags: Address<cfd_tags::Actor>,
+    allow_overcommit: bool,
     _tasks: Tasks,
     _pong_actor: Address<pong::Actor>,
 }
@@ -86,11 +121,15 @@ impl<O, W> ActorSystem<O, W>
 where
     O: Handler<oracle::MonitorAttestations, Return = ()>
         + Handler<oracle::GetAnnouncements, Return = Result<Vec<Announcement>, NoAnnouncement>>
+        + Handler<janitor::CollectOrphans, Return = usize>
         + Actor<Stop = ()>,
     W: Handler<wallet::BuildPartyParams, Return = Result<PartyParams>>
         + Handler<wallet::Sign, Return = Result<PartiallySignedTransaction>>
         + Handler<wallet::Withdraw, Return = Result<Txid>>
         + Handler<wallet::Sync, Return = ()>
+        + Handler<wallet::ConsolidateUtxos, Return = Result<Option<Txid>>>
+        + Handler<wallet::GetUnlockedBalance, Return = Result<Amount>>
+        + Handler<janitor::CollectOrphans, Return = usize>
         + Actor<Stop = ()>,
 {
     #[allow(clippy::too_many_arguments)]
@@ -106,6 +145,21 @@ where
         identity: Identities,
         listen_multiaddr: Multiaddr,
         blocked_peers: HashSet<PeerId>,
+        announce_addresses: Vec<Multiaddr>,
+        max_contracts_per_taker: Option<Contracts>,
+        deprecated_offer_protocol_cutoff: Option<OffsetDateTime>,
+        offer_history_retention: Option<time::Duration>,
+        job_intervals: Vec<scheduler::JobInterval>,
+        telemetry: Option<telemetry::Telemetry>,
+        daily_marks: Option<daily_marks::DailyMarks>,
+        janitor: Option<janitor::Janitor>,
+        max_concurrent_setups: usize,
+        pending_order_timeouts: PendingOrderTimeouts,
+        max_ping_interval: Option<Duration>,
+        volatility_spreads: Option<watch::Receiver<VolatilitySpreads>>,
+        allow_overcommit: bool,
+        shadow_pricing: Option<Address<shadow_pricing::Actor>>,
+        offer_time_to_live: Option<time::Duration>,
     ) -> Result<Self>
     where
         M: Handler<monitor::MonitorAfterContractSetup, Return = ()>
@@ -116,6 +170,11 @@ where
             + Handler<monitor::MonitorCetFinality, Return = Result<()>>
             + Actor<Stop = ()>,
     {
+        ensure!(
+            max_concurrent_setups > 0,
+            "At least one contract setup has to be allowed to run"
+        );
+
         let (monitor_addr, monitor_ctx) = Context::new(None);
         let (oracle_addr, oracle_ctx) = Context::new(None);
         let (process_manager_addr, process_manager_ctx) = Context::new(None);
@@ -129,30 +188,89 @@ where
             .create(None)
             .spawn(&mut tasks);
 
-        tasks.add(process_manager_ctx.run(process_manager::Actor::new(
+        let process_manager = process_manager::Actor::new(
             db.clone(),
             Role::Maker,
             projection_actor.clone().into(),
-            position_metrics_actor.into(),
+            position_metrics_actor.clone().into(),
             monitor_addr.clone().into(),
             monitor_addr.clone().into(),
             monitor_addr.clone().into(),
             monitor_addr.clone().into(),
             monitor_addr.into(),
             oracle_addr.clone().into(),
-        )));
+        );
+        tasks.add(process_manager_ctx.run(match &telemetry {
+            Some(telemetry) => process_manager.with_telemetry(telemetry.clone()),
+            None => process_manager,
+        }));
 
         let (endpoint_addr, endpoint_context) = Context::new(None);
 
         let (supervisor, maker_offer_address_deprecated) = Supervisor::new({
             let endpoint_addr = endpoint_addr.clone();
-            move || offer::deprecated::maker::Actor::new(endpoint_addr.clone())
+            move || {
+                let actor = offer::deprecated::maker::Actor::new(endpoint_addr.clone());
+
+                match deprecated_offer_protocol_cutoff {
+                    Some(cutoff) => actor.with_cutoff(cutoff),
+                    None => actor,
+                }
+            }
+        });
+        tasks.add(supervisor.run_log_summary());
+
+        let (supervisor, maker_offer_address_deprecated_v2) = Supervisor::new({
+            let endpoint_addr = endpoint_addr.clone();
+            move || {
+                let actor = offer::deprecated_v2::maker::Actor::new(endpoint_addr.clone());
+
+                match deprecated_offer_protocol_cutoff {
+                    Some(cutoff) => actor.with_cutoff(cutoff),
+                    None => actor,
+                }
+            }
+        });
+        tasks.add(supervisor.run_log_summary());
+
+        let offer_discovery = offer::relay::Discovery::new()
+            .create(None)
+            .spawn(&mut tasks);
+
+        let (supervisor, gossip_addr) = Supervisor::new({
+            let endpoint_addr = endpoint_addr.clone();
+            move || {
+                let subscriptions = offer::relay::topics()
+                    .map(|topic| (topic, offer_discovery.clone().into()))
+                    .collect();
+
+                gossip::Actor::new(endpoint_addr.clone(), subscriptions)
+            }
         });
         tasks.add(supervisor.run_log_summary());
 
         let (supervisor, maker_offer_address) = Supervisor::new({
             let endpoint_addr = endpoint_addr.clone();
-            move || offer::maker::Actor::new(endpoint_addr.clone())
+            let network_identity = Identity::new(identity.identity_pk);
+            let identity = identity.libp2p.clone();
+            let gossip_addr = gossip_addr.clone();
+            move || {
+                let actor = offer::maker::Actor::new(endpoint_addr.clone(), identity.clone());
+                let actor = match offer_time_to_live {
+                    Some(time_to_live) => actor.with_time_to_live(time_to_live),
+                    None => actor,
+                };
+
+                if announce_addresses.is_empty() {
+                    actor
+                } else {
+                    actor.with_relay(
+                        gossip_addr.clone().into(),
+                        network_identity,
+                        announce_addresses.clone(),
+                    )
+                }
+            }
         });
         tasks.add(supervisor.run_log_summary());
 
@@ -163,16 +281,24 @@ where
             let wallet = wallet_addr.clone();
             let projection = projection_actor.clone();
             let maker_offer_address = maker_offer_address.clone();
+            let setup_queue = order::setup_queue::SetupQueue::new(max_concurrent_setups);
             move || {
                 order::maker::Actor::new(
                     n_payouts,
                     oracle_pk,
                     oracle.clone().into(),
                     (db.clone(), process_manager.clone()),
-                    (wallet.clone().into(), wallet.clone().into()),
+                    (
+                        wallet.clone().into(),
+                        wallet.clone().into(),
+                        wallet.clone().into(),
+                    ),
                     projection.clone(),
                     maker_offer_address.clone().into(),
+                    max_contracts_per_taker,
+                    setup_queue.clone(),
                 )
+                .with_pending_order_timeouts(pending_order_timeouts.clone())
             }
         });
         tasks.add(order_supervisor.run_log_summary());
@@ -193,6 +319,7 @@ where
                     (wallet.clone().into(), wallet.clone().into()),
                     projection.clone(),
                     maker_offer_address.clone().into(),
+                    max_contracts_per_taker,
                 )
             }
         });
@@ -213,7 +340,50 @@ where
             });
         tasks.add(collab_settlement_deprecated_supervisor.run_log_summary());
 
-        let cfd_actor_addr = cfd::Actor::new(
+        let (collab_settlement_resume_supervisor, collab_settlement_resume_addr) =
+            Supervisor::new({
+                let db = db.clone();
+                move || collab_settlement::resume::Actor::new(db.clone())
+            });
+        tasks.add(collab_settlement_resume_supervisor.run_log_summary());
+
+        let (funding_rate_history_supervisor, funding_rate_history_addr) = Supervisor::new({
+            let db = db.clone();
+            move || funding_rate_history::maker::Actor::new(db.clone())
+        });
+        tasks.add(funding_rate_history_supervisor.run_log_summary());
+
+        let (offer_history_supervisor, offer_history_addr) = Supervisor::new({
+            let db = db.clone();
+            move || offer_history::Actor::new(db.clone(), offer_history_retention)
+        });
+        tasks.add(offer_history_supervisor.run_log_summary());
+
+        let (peer_sessions_supervisor, peer_sessions_addr) = Supervisor::new({
+            let db = db.clone();
+            move || peer_sessions::Actor::new(db.clone())
+        });
+        tasks.add(peer_sessions_supervisor.run_log_summary());
+
+        let (failure_report_supervisor, failure_report_addr) = Supervisor::new({
+            let db = db.clone();
+            move || failure_report::maker::Actor::new(db.clone())
+        });
+        tasks.add(failure_report_supervisor.run_log_summary());
+
+        let instance_fence_addr = instance_fence::maker::Actor::new(endpoint_addr.clone())
+            .create(None)
+            .spawn(&mut tasks);
+
+        let service_status_addr = service_status::maker::Actor::new(endpoint_addr.clone())
+            .create(None)
+            .spawn(&mut tasks);
+
+        let cfd_tags_addr = cfd_tags::Actor::new(db.clone(), projection_actor.clone())
+            .create(None)
+            .spawn(&mut tasks);
+
+        let mut cfd_actor = cfd::Actor::new(
             settlement_interval,
             projection_actor,
             time_to_first_position_addr,
@@ -223,12 +393,20 @@ where
             ),
             (
                 maker_offer_address.clone(),
+                maker_offer_address_deprecated_v2.clone(),
                 maker_offer_address_deprecated.clone(),
             ),
             (order.clone(), order_deprecated.clone()),
-        )
-        .create(None)
-        .spawn(&mut tasks);
+            funding_rate_history_addr.clone(),
+            offer_history_addr.clone(),
+        );
+        if let Some(volatility_spreads) = volatility_spreads {
+            cfd_actor = cfd_actor.with_volatility_spreads(volatility_spreads);
+        }
+        if let Some(shadow_pricing) = shadow_pricing {
+            cfd_actor = cfd_actor.with_shadow_pricing(shadow_pricing);
+        }
+        let cfd_actor_addr = cfd_actor.create(None).spawn(&mut tasks);
 
         let (rollover_deprecated_supervisor, rollover_deprecated_addr) = Supervisor::new({
             let executor = executor.clone();
@@ -282,42 +460,66 @@ where
         let (identify_listener_supervisor, identify_listener_actor) = Supervisor::new({
             let identity = identity.libp2p.clone();
             move || {
-                identify::listener::Actor::new(
+                let listener = identify::listener::Actor::new(
                     daemon::version(),
                     Environment::unknown(),
                     identity.public(),
                     HashSet::from([listen_multiaddr.clone()]),
                     MAKER_LISTEN_PROTOCOLS.into(),
-                )
+                );
+
+                match max_ping_interval {
+                    Some(max_ping_interval) => listener.with_max_ping_interval(max_ping_interval),
+                    None => listener,
+                }
             }
         });
 
-        let (identify_dialer_supervisor, identify_dialer_actor) =
-            Supervisor::new(move || identify::dialer::Actor::new(endpoint_addr.clone()));
+        let (identify_dialer_supervisor, identify_dialer_actor) = Supervisor::new({
+            let endpoint_addr = endpoint_addr.clone();
+            let peer_sessions_addr = peer_sessions_addr.clone();
+            move || {
+                identify::dialer::Actor::new(endpoint_addr.clone())
+                    .with_subscriber(peer_sessions_addr.clone().into())
+            }
+        });
 
         let endpoint = Endpoint::new(
-            Box::new(TokioTcpConfig::new),
+            Box::new(libp2p_utils::transport),
             identity.libp2p,
             ENDPOINT_CONNECTION_TIMEOUT,
             MAKER_LISTEN_PROTOCOLS.inbound_substream_handlers(
                 pong_address.clone(),
                 identify_listener_actor,
-                (order, order_deprecated),
+                (order.clone(), order_deprecated.clone()),
                 (rollover_addr.clone(), rollover_deprecated_addr.clone()),
                 (collab_settlement_addr, collab_settlement_deprecated_addr),
+                collab_settlement_resume_addr,
+                funding_rate_history_addr,
+                gossip_addr.clone(),
+                failure_report_addr,
+                instance_fence_addr,
             ),
             endpoint::Subscribers::new(
                 vec![
                     ping_address.clone().into(),
                     maker_offer_address.clone().into(),
+                    maker_offer_address_deprecated_v2.clone().into(),
                     maker_offer_address_deprecated.clone().into(),
                     identify_dialer_actor.clone().into(),
+                    gossip_addr.clone().into(),
+                    peer_sessions_addr.clone().into(),
+                    service_status_addr.clone().into(),
                 ],
                 vec![
                     ping_address.into(),
-                    maker_offer_address.into(),
-                    maker_offer_address_deprecated.into(),
+                    maker_offer_address.clone().into(),
+                    maker_offer_address_deprecated_v2.clone().into(),
+                    maker_offer_address_deprecated.clone().into(),
                     identify_dialer_actor.into(),
+                    gossip_addr.into(),
+                    peer_sessions_addr.clone().into(),
+                    service_status_addr.clone().into(),
                 ],
                 vec![],
                 vec![listener_actor.into()],
@@ -336,11 +538,29 @@ where
 
         tasks.add(oracle_ctx.run(oracle_constructor(executor.clone())));
 
-        let archive_closed_cfds_actor = archive_closed_cfds::Actor::new(db.clone())
-            .create(None)
-            .spawn(&mut tasks);
-
-        let archive_failed_cfds_actor = archive_failed_cfds::Actor::new(db.clone())
+        let mut jobs = scheduler::maintenance_jobs(db.clone(), wallet_addr.clone().into());
+        if let Some(telemetry) = &telemetry {
+            jobs.extend(telemetry.jobs());
+        }
+        if let Some(daily_marks) = &daily_marks {
+            jobs.push(daily_marks.job());
+        }
+        if let Some(janitor) = janitor {
+            let janitor = janitor
+                .with_collector(janitor::Artifact::ReservedUtxo, wallet_addr.clone().into())
+                .with_collector(janitor::Artifact::PendingDecision, order.clone().into())
+                .with_collector(
+                    janitor::Artifact::PendingDecision,
+                    order_deprecated.clone().into(),
+                )
+                .with_collector(
+                    janitor::Artifact::OracleAnnouncement,
+                    oracle_addr.clone().into(),
+                );
+            jobs.push(janitor.job());
+        }
+
+        let scheduler_actor = scheduler::Actor::new(jobs, &job_intervals)?
             .create(None)
             .spawn(&mut tasks);
 
@@ -353,10 +573,22 @@ where
             wallet_actor: wallet_addr,
             rollover_actor: rollover_addr,
             rollover_actor_deprecated: rollover_deprecated_addr,
-            _archive_closed_cfds_actor: archive_closed_cfds_actor,
-            _archive_failed_cfds_actor: archive_failed_cfds_actor,
+            scheduler_actor,
             executor,
-            _oracle_actor: oracle_addr,
+            position_metrics: position_metrics_actor,
+            oracle_actor: oracle_addr,
+            endpoint: endpoint_addr,
+            offer: maker_offer_address,
+            offer_deprecated: maker_offer_address_deprecated,
+            offer_deprecated_v2: maker_offer_address_deprecated_v2,
+            order,
+            order_deprecated,
+            service_status: service_status_addr,
+            deprecated_offer_protocol_cutoff,
+            offer_history: offer_history_addr,
+            peer_sessions: peer_sessions_addr,
+            cfd_tags: cfd_tags_addr,
+            allow_overcommit,
             _tasks: tasks,
             _pong_actor: pong_address,
         })
@@ -365,6 +597,10 @@ where
     /// Adjust the parameters which create offers for the connected takers.
     ///
     /// Once one offer is taken, another one with the same parameters is created.
+    ///
+    /// Returns the collateral the offers would lock if they were taken at their maximum quantity.
+    /// Fails with [`collateral_forecast::Overcommitted`] if that exceeds the unlocked balance,
+    /// unless overcommitting was allowed.
     #[allow(clippy::too_many_arguments)]
     pub async fn set_offer_params(
         &self,
@@ -372,27 +608,79 @@ where
         price_short: Option<Price>,
         min_quantity: Contracts,
         max_quantity: Contracts,
+        max_contracts_per_order: Option<Contracts>,
         tx_fee_rate: TxFeeRate,
         funding_rate_long: FundingRate,
         funding_rate_short: FundingRate,
+        funding_period: FundingPeriod,
         opening_fee: OpeningFee,
+        opening_fee_tiers: OpeningFeeTiers,
         leverage_choices: Vec<Leverage>,
         contract_symbol: ContractSymbol,
         lot_size: LotSize,
-    ) -> Result<()> {
+        trading_calendar: Option<TradingCalendar>,
+    ) -> Result<collateral_forecast::Lockup> {
+        let available = self.wallet_actor.send(wallet::GetUnlockedBalance).await??;
+        let lockup = collateral_forecast::Lockup::new(
+            contract_symbol,
+            (price_long, price_short),
+            max_quantity,
+            &leverage_choices,
+            available,
+        );
+
+        tracing::info!(
+            %contract_symbol,
+            required = %lockup.required,
+            %available,
+            "Worst-case collateral of offers"
+        );
+
+        if lockup.is_overcommitted() && !self.allow_overcommit {
+            return Err(collateral_forecast::Overcommitted {
+                required: lockup.required,
+                available,
+            }
+            .into());
+        }
+
         self.cfd_actor
             .send(cfd::OfferParams {
                 price_long,
                 price_short,
                 min_quantity,
                 max_quantity,
+                max_contracts_per_order,
                 tx_fee_rate,
                 funding_rate_long,
                 funding_rate_short,
+                funding_period,
                 opening_fee,
+                opening_fee_tiers,
                 leverage_choices,
                 contract_symbol,
                 lot_size,
+                trading_calendar,
+            })
+            .await??;
+
+        Ok(lockup)
+    }
+
+    /// Partially update the live offers of `contract_symbol`, keeping their ids.
+    pub async fn revise_offers(
+        &self,
+        contract_symbol: ContractSymbol,
+        leverage_choices: Option<Vec<Leverage>>,
+        lot_size: Option<LotSize>,
+    ) -> Result<()> {
+        self.cfd_actor
+            .send(cfd::ReviseOffers {
+                contract_symbol,
+                update: OfferUpdate {
+                    leverage_choices,
+                    lot_size,
+                },
             })
             .await??;
 
@@ -464,4 +752,94 @@ where
             .await?;
         Ok(())
     }
+
+    /// Change whether new orders and rollovers are accepted and inform the connected takers about
+    /// the new status.
+    pub async fn update_service_status(&self, status: service_status::ServiceStatus) -> Result<()> {
+        status.validate()?;
+
+        self.order
+            .send(order::maker::UpdateConfiguration::new(
+                status.accepting_orders,
+            ))
+            .await?;
+        self.order_deprecated
+            .send(order::deprecated::maker::UpdateConfiguration::new(
+                status.accepting_orders,
+            ))
+            .await?;
+        self.update_rollover_configuration(status.accepting_rollovers)
+            .await?;
+
+        self.service_status
+            .send(service_status::maker::UpdateServiceStatus(status))
+            .await??;
+
+        Ok(())
+    }
+
+    pub async fn service_status(&self) -> Result<service_status::ServiceStatus> {
+        let status = self
+            .service_status
+            .send(service_status::maker::GetServiceStatus)
+            .await?;
+
+        Ok(status)
+    }
+
+    /// The connected takers using each version of the offer protocol.
+    pub async fn offer_protocol_usage(&self) -> Result<OfferProtocolUsage> {
+        let current = self.offer.send(offer::usage::GetProtocolUsage).await?;
+        let deprecated = vec![
+            self.offer_deprecated_v2
+                .send(offer::usage::GetProtocolUsage)
+                .await?,
+            self.offer_deprecated
+                .send(offer::usage::GetProtocolUsage)
+                .await?,
+        ];
+
+        Ok(OfferProtocolUsage {
+            current,
+            deprecated,
+            deprecated_cutoff: self.deprecated_offer_protocol_cutoff,
+        })
+    }
+
+    /// The offer revisions published in `[from, to)`, oldest first.
+    pub async fn offer_history(&self, from: Timestamp, to: Timestamp) -> Result<Vec<OfferRecord>> {
+        self.offer_history
+            .send(offer_history::Export { from, to })
+            .await?
+    }
+
+    /// Analytics about the takers that connected in the last `days` days, including today.
+    pub async fn peer_analytics(&self, days: u32) -> Result<peer_sessions::PeerAnalytics> {
+        self.peer_sessions
+            .send(peer_sessions::GetAnalytics { days })
+            .await?
+    }
+
+    /// Attach a user-defined tag to the CFD, e.g. the strategy or client it belongs to.
+    pub async fn add_cfd_tag(&self, order_id: OrderId, tag: String) -> Result<()> {
+        self.cfd_tags
+            .send(cfd_tags::AddTag { order_id, tag })
+            .await?
+    }
+
+    pub async fn remove_cfd_tag(&self, order_id: OrderId, tag: String) -> Result<()> {
+        self.cfd_tags
+            .send(cfd_tags::RemoveTag { order_id, tag })
+            .await?
+    }
+}
+
+/// The connected takers using each version of the offer protocol.
+#[derive(Debug, Clone, Serialize)]
+pub struct OfferProtocolUsage {
+    pub current: offer::usage::ProtocolUsage,
+    pub deprecated: Vec<offer::usage::ProtocolUsage>,
+    /// Point in time after which the deprecated protocols are refused
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub deprecated_cutoff: Option<OffsetDateTime>,
 }
@@ -0,0 +1,276 @@
+//! Re-publication of the maker's offers to an HTTP market aggregator.
+//!
+//! Listing the offers on an aggregator, e.g. a public market list, gains the maker distribution
+//! without running additional services. Whenever the offers change, the current offers are
+//! `POST`ed to the configured endpoint. The body is signed with the libp2p identity key of the
+//! maker, which allows the aggregator to verify that the offers were published by the peer in
+//! [`PEER_ID_HEADER`]. Failed publications are retried with exponential backoff until they succeed
+//! or newer offers supersede them.
+//!
+//! Publishing can be stopped at runtime through the kill switch, which publishes an empty list of
+//! offers once to withdraw the listing.
+
+use crate::public_api;
+use crate::public_api::PublicOffer;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use daemon::projection::MakerOffers;
+use model::libp2p::PeerId;
+use model::Timestamp;
+use reqwest::Url;
+use serde::Serialize;
+use std::time::Duration;
+use tokio::sync::watch;
+use xtra_libp2p::libp2p::identity::Keypair;
+use xtra_productivity::xtra_productivity;
+
+/// Peer id of the maker that published the offers
+pub const PEER_ID_HEADER: &str = "X-Hermes-Peer-Id";
+
+/// Hex-encoded protobuf encoding of the public key the body was signed with
+pub const PUBLIC_KEY_HEADER: &str = "X-Hermes-Public-Key";
+
+/// Hex-encoded signature of the body
+pub const SIGNATURE_HEADER: &str = "X-Hermes-Signature";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+/// Body of a publication
+///
+/// Aggregators should discard publications that are older than the latest one they accepted.
+#[derive(Debug, Serialize)]
+struct Publication {
+    peer_id: PeerId,
+    published_at: Timestamp,
+    offers: Vec<PublicOffer>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Status {
+    pub url: String,
+    pub enabled: bool,
+    pub last_published_at: Option<Timestamp>,
+    /// Number of failed attempts to publish the current offers
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+}
+
+pub struct Actor {
+    client: reqwest::Client,
+    url: Url,
+    keypair: Keypair,
+    offers: watch::Receiver<MakerOffers>,
+    status: Status,
+    /// Incremented with every change of the offers, retries of older publications are dropped
+    generation: u64,
+}
+
+impl Actor {
+    pub fn new(url: Url, keypair: Keypair, offers: watch::Receiver<MakerOffers>) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .expect("to build from static arguments"),
+            status: Status {
+                url: url.to_string(),
+                enabled: true,
+                last_published_at: None,
+                consecutive_failures: 0,
+                last_error: None,
+            },
+            url,
+            keypair,
+            offers,
+            generation: 0,
+        }
+    }
+
+    async fn publish(&self, offers: Vec<PublicOffer>) -> Result<Timestamp> {
+        let published_at = Timestamp::now();
+        let body = serde_json::to_vec(&Publication {
+            peer_id: PeerId::from(self.keypair.public().to_peer_id()),
+            published_at,
+            offers,
+        })?;
+        let signature = self
+            .keypair
+            .sign(&body)
+            .context("Failed to sign publication")?;
+
+        self.client
+            .post(self.url.clone())
+            .header(
+                PEER_ID_HEADER,
+                self.keypair.public().to_peer_id().to_string(),
+            )
+            .header(
+                PUBLIC_KEY_HEADER,
+                hex::encode(self.keypair.public().to_protobuf_encoding()),
+            )
+            .header(SIGNATURE_HEADER, hex::encode(signature))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .context("Failed to reach aggregator")?
+            .error_for_status()
+            .context("Aggregator rejected publication")?;
+
+        Ok(published_at)
+    }
+
+    async fn attempt(&mut self, attempt: u32, ctx: &mut xtra::Context<Self>) {
+        let offers = public_api::public_offers(self.offers.borrow().clone());
+
+        match self.publish(offers).await {
+            Ok(published_at) => {
+                tracing::debug!(url = %self.url, "Published offers to aggregator");
+
+                self.status.last_published_at = Some(published_at);
+                self.status.consecutive_failures = 0;
+                self.status.last_error = None;
+            }
+            Err(e) => {
+                let backoff = backoff(attempt);
+                tracing::warn!(
+                    url = %self.url,
+                    retry_in_secs = %backoff.as_secs(),
+                    "Failed to publish offers to aggregator: {e:#}"
+                );
+
+                self.status.consecutive_failures += 1;
+                self.status.last_error = Some(format!("{e:#}"));
+
+                let this = ctx.address().expect("we are alive");
+                let retry = Retry {
+                    generation: self.generation,
+                    attempt: attempt + 1,
+                };
+                tokio_extras::spawn(&this.clone(), async move {
+                    tokio_extras::time::sleep(backoff).await;
+                    let _ = this.send(retry).await;
+                });
+            }
+        }
+    }
+}
+
+fn backoff(attempt: u32) -> Duration {
+    INITIAL_BACKOFF
+        .checked_mul(2u32.saturating_pow(attempt))
+        .unwrap_or(MAX_BACKOFF)
+        .min(MAX_BACKOFF)
+}
+
+/// Turn the publication of offers on or off.
+#[derive(Clone, Copy)]
+pub struct SetEnabled(pub bool);
+
+#[derive(Clone, Copy)]
+pub struct GetStatus;
+
+#[derive(Clone, Copy)]
+struct OffersChanged;
+
+#[derive(Clone, Copy)]
+struct Retry {
+    generation: u64,
+    attempt: u32,
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: OffersChanged, ctx: &mut xtra::Context<Self>) {
+        self.generation += 1;
+        self.status.consecutive_failures = 0;
+
+        if !self.status.enabled {
+            return;
+        }
+
+        self.attempt(0, ctx).await;
+    }
+
+    async fn handle(&mut self, msg: Retry, ctx: &mut xtra::Context<Self>) {
+        if !self.status.enabled || msg.generation != self.generation {
+            return;
+        }
+
+        self.attempt(msg.attempt, ctx).await;
+    }
+
+    async fn handle(&mut self, msg: SetEnabled, ctx: &mut xtra::Context<Self>) {
+        let SetEnabled(enabled) = msg;
+        if enabled == self.status.enabled {
+            return;
+        }
+
+        self.status.enabled = enabled;
+        self.generation += 1;
+        self.status.consecutive_failures = 0;
+
+        if enabled {
+            tracing::info!(url = %self.url, "Resumed publishing offers to aggregator");
+
+            self.attempt(0, ctx).await;
+        } else {
+            tracing::info!(url = %self.url, "Stopped publishing offers to aggregator");
+
+            // Best effort, the aggregator is expected to expire listings that are not refreshed
+            if let Err(e) = self.publish(Vec::new()).await {
+                tracing::warn!(url = %self.url, "Failed to withdraw offers from aggregator: {e:#}");
+            }
+        }
+    }
+
+    async fn handle(&mut self, _: GetStatus) -> Status {
+        self.status.clone()
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we just started");
+
+        tokio_extras::spawn(&this.clone(), {
+            let mut offers = self.offers.clone();
+
+            async move {
+                // Publish the offers the maker starts with as well
+                if this.send(OffersChanged).await.is_err() {
+                    return;
+                }
+
+                while offers.changed().await.is_ok() {
+                    if this.send(OffersChanged).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_maximum() {
+        assert_eq!(backoff(0), Duration::from_secs(1));
+        assert_eq!(backoff(3), Duration::from_secs(8));
+        assert_eq!(backoff(9), MAX_BACKOFF);
+        assert_eq!(backoff(u32::MAX), MAX_BACKOFF);
+    }
+}
@@ -0,0 +1,371 @@
+//! Target working balance of the maker's wallet.
+//!
+//! Profits accumulate in the hot wallet of the maker. The operator configures the balance the
+//! wallet should work with, and once the balance exceeds it by more than a margin, the excess is
+//! either withdrawn to a cold address or the operator is alerted to withdraw it by hand.
+//!
+//! The balance never drops below the collateral the published offers require at their maximum
+//! quantity: if this reserve is higher than the target, the reserve is kept instead. Nothing is
+//! withdrawn while the estimated fee rate exceeds the configured maximum.
+//!
+//! Withdrawals are recorded as cold sweeps, hence the ledger labels them with the cold address.
+
+use crate::collateral_forecast::Forecast;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use bdk::bitcoin::Address;
+use bdk::bitcoin::Amount;
+use bdk::FeeRate;
+use daemon::bdk;
+use daemon::notifications::kind_name;
+use daemon::notifications::Notification;
+use daemon::notifications::NotificationKind;
+use daemon::notifications::Severity;
+use daemon::projection::explorer;
+use daemon::wallet;
+use model::Timestamp;
+use model::WalletInfo;
+use serde::Serialize;
+use sqlite_db::cold_sweeps::ColdSweep;
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::sync::watch;
+use xtra::prelude::MessageChannel;
+use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+/// Interval at which the balance is compared with the target
+const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Number of notifications kept around for consumers that (re-)connect
+const MAX_RECENT_NOTIFICATIONS: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// The balance the wallet should work with
+    pub target: Amount,
+    /// How far the balance may exceed the target before the excess is withdrawn
+    pub margin: Amount,
+    /// Where to withdraw the excess to, the operator is only alerted if `None`
+    pub auto_withdraw: Option<AutoWithdraw>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AutoWithdraw {
+    pub address: Address,
+    /// Fee rate in satoshis per vbyte above which nothing is withdrawn
+    pub max_fee_rate: f32,
+}
+
+/// Get the target balance and the withdrawals of the excess.
+#[derive(Clone, Copy)]
+pub struct GetStatus;
+
+#[derive(Clone, Copy)]
+struct Check;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Status {
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub target: Amount,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub margin: Amount,
+    /// Collateral required by the published offers, kept in the wallet even if above the target
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub reserve: Amount,
+    /// Balance of the wallet, `null` until the wallet was synced
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat::opt")]
+    pub balance: Option<Amount>,
+    /// Cold address the excess is withdrawn to, `null` if the operator is only alerted
+    pub address: Option<Address>,
+    /// Fee rate in satoshis per vbyte above which nothing is withdrawn
+    pub max_fee_rate: Option<f32>,
+    /// Whether the balance exceeds the target by more than the margin
+    pub above_target: bool,
+    /// Withdrawals of the excess, newest first
+    pub withdrawals: Vec<ColdSweep>,
+}
+
+pub struct Actor {
+    config: Config,
+    db: sqlite_db::Connection,
+    wallet: MessageChannel<wallet::ForwardExcess, Result<wallet::ForwardOutcome>>,
+    wallet_info: watch::Receiver<Option<WalletInfo>>,
+    forecast: watch::Receiver<Forecast>,
+    /// Whether the operator was alerted since the balance last exceeded the target
+    alerted: bool,
+    sequence: u64,
+    recent: VecDeque<Notification>,
+    notifications: watch::Sender<Vec<Notification>>,
+}
+
+impl Actor {
+    /// Returns the actor and the feed of its notifications.
+    pub fn new(
+        config: Config,
+        db: sqlite_db::Connection,
+        wallet: MessageChannel<wallet::ForwardExcess, Result<wallet::ForwardOutcome>>,
+        wallet_info: watch::Receiver<Option<WalletInfo>>,
+        forecast: watch::Receiver<Forecast>,
+    ) -> (Self, watch::Receiver<Vec<Notification>>) {
+        let (notifications, receiver) = watch::channel(Vec::new());
+
+        let actor = Self {
+            config,
+            db,
+            wallet,
+            wallet_info,
+            forecast,
+            alerted: false,
+            sequence: 0,
+            recent: VecDeque::with_capacity(MAX_RECENT_NOTIFICATIONS),
+            notifications,
+        };
+
+        (actor, receiver)
+    }
+
+    fn balance(&self) -> Option<Amount> {
+        self.wallet_info.borrow().as_ref().map(|info| info.balance)
+    }
+
+    fn reserve(&self) -> Amount {
+        self.forecast.borrow().required
+    }
+
+    fn notify(
+        &mut self,
+        kind: NotificationKind,
+        severity: Severity,
+        message: String,
+        tx_url: Option<String>,
+    ) {
+        let now = Timestamp::now();
+        self.sequence += 1;
+
+        let notification = Notification {
+            id: format!("{}-{}", kind_name(kind), now.seconds()),
+            sequence: self.sequence,
+            kind,
+            severity,
+            order_id: None,
+            message,
+            tx_url,
+            timestamp: now,
+        };
+
+        tracing::debug!(id = %notification.id, "New notification");
+
+        if self.recent.len() == MAX_RECENT_NOTIFICATIONS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(notification);
+
+        let _ = self
+            .notifications
+            .send(self.recent.iter().cloned().collect());
+    }
+
+    async fn withdraw(&mut self, auto_withdraw: AutoWithdraw, threshold: Amount) -> Result<()> {
+        let outcome = self
+            .wallet
+            .send(wallet::ForwardExcess {
+                address: auto_withdraw.address.clone(),
+                threshold,
+                max_fee_rate: FeeRate::from_sat_per_vb(auto_withdraw.max_fee_rate),
+            })
+            .await
+            .context("Wallet actor not available")??;
+
+        match outcome {
+            wallet::ForwardOutcome::Forwarded {
+                txid,
+                amount,
+                fee_rate,
+            } => {
+                self.db
+                    .insert_cold_sweep(&ColdSweep {
+                        txid,
+                        address: auto_withdraw.address.clone(),
+                        amount,
+                        fee_rate: fee_rate.as_sat_per_vb(),
+                        created_at: Timestamp::now(),
+                    })
+                    .await?;
+
+                tracing::info!(%txid, %amount, address = %auto_withdraw.address, "Withdrew balance above target");
+
+                let tx_url = self
+                    .wallet_info
+                    .borrow()
+                    .as_ref()
+                    .and_then(|info| explorer::tx_url(txid, info.network));
+
+                self.notify(
+                    NotificationKind::ExcessWithdrawn,
+                    Severity::Success,
+                    format!(
+                        "Withdrew {amount} above the target balance to {}",
+                        auto_withdraw.address
+                    ),
+                    tx_url,
+                );
+            }
+            wallet::ForwardOutcome::NothingToForward => {
+                // The balance above the threshold is locked, e.g. in a contract setup
+                tracing::debug!(%threshold, "Unlocked balance does not exceed target");
+            }
+            wallet::ForwardOutcome::FeeRateTooHigh { estimated } => {
+                tracing::info!(
+                    estimated = %estimated.as_sat_per_vb(),
+                    max = %auto_withdraw.max_fee_rate,
+                    "Postponing withdrawal of balance above target because the fee rate is too high"
+                );
+
+                if !self.alerted {
+                    self.alerted = true;
+                    self.notify(
+                        NotificationKind::BalanceAboveTarget,
+                        Severity::Warning,
+                        format!(
+                            "Balance exceeds the target of {}, withdrawal postponed because the fee rate of {} sat/vB is too high",
+                            self.config.target,
+                            estimated.as_sat_per_vb()
+                        ),
+                        None,
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The balance kept in the wallet: the target, unless the reserve is higher.
+fn threshold(target: Amount, reserve: Amount) -> Amount {
+    target.max(reserve)
+}
+
+/// The balance above `threshold`, `None` unless it exceeds `margin`.
+fn excess(balance: Amount, threshold: Amount, margin: Amount) -> Option<Amount> {
+    let excess = balance.checked_sub(threshold)?;
+
+    (excess > margin).then_some(excess)
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: Check) {
+        let balance = match self.balance() {
+            Some(balance) => balance,
+            None => return,
+        };
+        let threshold = threshold(self.config.target, self.reserve());
+
+        let excess = match excess(balance, threshold, self.config.margin) {
+            Some(excess) => excess,
+            None => {
+                self.alerted = false;
+                return;
+            }
+        };
+
+        match self.config.auto_withdraw.clone() {
+            Some(auto_withdraw) => {
+                if let Err(e) = self.withdraw(auto_withdraw, threshold).await {
+                    tracing::warn!("Failed to withdraw balance above target: {e:#}");
+                }
+            }
+            None if !self.alerted => {
+                self.alerted = true;
+
+                tracing::info!(%balance, %threshold, "Balance exceeds target");
+
+                self.notify(
+                    NotificationKind::BalanceAboveTarget,
+                    Severity::Warning,
+                    format!("Balance exceeds the target of {threshold} by {excess}, consider withdrawing the excess"),
+                    None,
+                );
+            }
+            None => {}
+        }
+    }
+
+    async fn handle(&mut self, _: GetStatus) -> Result<Status> {
+        let balance = self.balance();
+        let reserve = self.reserve();
+        let threshold = threshold(self.config.target, reserve);
+
+        Ok(Status {
+            target: self.config.target,
+            margin: self.config.margin,
+            reserve,
+            balance,
+            address: self
+                .config
+                .auto_withdraw
+                .as_ref()
+                .map(|auto_withdraw| auto_withdraw.address.clone()),
+            max_fee_rate: self
+                .config
+                .auto_withdraw
+                .as_ref()
+                .map(|auto_withdraw| auto_withdraw.max_fee_rate),
+            above_target: balance
+                .and_then(|balance| excess(balance, threshold, self.config.margin))
+                .is_some(),
+            withdrawals: self.db.load_cold_sweeps().await?,
+        })
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we just started");
+
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(CHECK_INTERVAL, || Check, xtras::IncludeSpan::Never),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn excess_is_only_reported_beyond_margin() {
+        let threshold = Amount::from_sat(10_000_000);
+        let margin = Amount::from_sat(1_000_000);
+
+        assert_eq!(excess(Amount::from_sat(9_000_000), threshold, margin), None);
+        assert_eq!(
+            excess(Amount::from_sat(11_000_000), threshold, margin),
+            None
+        );
+        assert_eq!(
+            excess(Amount::from_sat(11_000_001), threshold, margin),
+            Some(Amount::from_sat(1_000_001))
+        );
+    }
+
+    #[test]
+    fn reserve_above_target_is_kept() {
+        let target = Amount::from_sat(10_000_000);
+
+        assert_eq!(threshold(target, Amount::from_sat(5_000_000)), target);
+        assert_eq!(
+            threshold(target, Amount::from_sat(15_000_000)),
+            Amount::from_sat(15_000_000)
+        );
+    }
+}
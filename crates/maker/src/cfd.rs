@@ -1,17 +1,26 @@
 use crate::metrics::time_to_first_position;
+use crate::offer_history;
+use crate::shadow_pricing;
+use crate::volatility_spread::VolatilitySpreads;
 use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
+use daemon::command;
+use daemon::funding_rate_history;
 use daemon::order;
 use daemon::projection;
+use model::trading_calendar::TradingCalendar;
 use model::ContractSymbol;
 use model::Contracts;
+use model::FundingPeriod;
 use model::FundingRate;
 use model::Identity;
 use model::Leverage;
 use model::LotSize;
+use model::OfferUpdate;
 use model::OpeningFee;
+use model::OpeningFeeTiers;
 use model::OrderId;
 use model::Position;
 use model::Price;
@@ -21,6 +30,7 @@ use nonempty::NonEmpty;
 use std::collections::HashMap;
 use time::Duration;
 use time::OffsetDateTime;
+use tokio::sync::watch;
 use xtra::prelude::MessageChannel;
 use xtra_productivity::xtra_productivity;
 use xtras::SendAsyncSafe;
@@ -65,19 +75,42 @@ pub struct TakerDisconnected {
 #[derive(Clone, Copy)]
 pub struct GetRolloverParams(ContractSymbol);
 
+/// Withdraw all offers and stop publishing new ones.
+///
+/// Once offers were withdrawn, new offer parameters are rejected.
+#[derive(Clone, Copy)]
+pub struct WithdrawOffers;
+
+/// Partially update the live offers of a contract symbol without replacing them.
+///
+/// Unlike new [`OfferParams`], the offers keep their ids. Orders referring to the previous
+/// revision of an offer are rejected.
+#[derive(Clone, Debug)]
+pub struct ReviseOffers {
+    pub contract_symbol: ContractSymbol,
+    pub update: OfferUpdate,
+}
+
 #[derive(Clone, Debug)]
 pub struct OfferParams {
     pub price_long: Option<Price>,
     pub price_short: Option<Price>,
     pub min_quantity: Contracts,
     pub max_quantity: Contracts,
+    /// Largest quantity a single order may have, announced to the takers
+    pub max_contracts_per_order: Option<Contracts>,
     pub tx_fee_rate: TxFeeRate,
     pub funding_rate_long: FundingRate,
     pub funding_rate_short: FundingRate,
+    pub funding_period: FundingPeriod,
     pub opening_fee: OpeningFee,
+    /// Opening fees depending on the quantity, overriding `opening_fee` if not empty
+    pub opening_fee_tiers: OpeningFeeTiers,
     pub leverage_choices: Vec<Leverage>,
     pub contract_symbol: ContractSymbol,
     pub lot_size: LotSize,
+    /// Weekly hours during which orders are accepted, always if `None`
+    pub trading_calendar: Option<TradingCalendar>,
 }
 
 impl OfferParams {
@@ -87,13 +120,17 @@ impl OfferParams {
             price_short,
             min_quantity,
             max_quantity,
+            max_contracts_per_order,
             tx_fee_rate,
             funding_rate_long,
             funding_rate_short,
+            funding_period,
             opening_fee,
+            opening_fee_tiers,
             leverage_choices,
             contract_symbol,
             lot_size,
+            trading_calendar,
         } = self;
 
         let mut offers = Vec::new();
@@ -111,7 +148,11 @@ impl OfferParams {
                 leverage_choices.clone(),
                 contract_symbol,
                 lot_size,
-            );
+                funding_period,
+            )
+            .with_opening_fee_tiers(opening_fee_tiers.clone())
+            .with_max_contracts_per_order(max_contracts_per_order)
+            .with_trading_calendar(trading_calendar.clone());
 
             offers.push(long);
         }
@@ -129,7 +170,11 @@ impl OfferParams {
                 leverage_choices,
                 contract_symbol,
                 lot_size,
-            );
+                funding_period,
+            )
+            .with_opening_fee_tiers(opening_fee_tiers)
+            .with_max_contracts_per_order(max_contracts_per_order)
+            .with_trading_calendar(trading_calendar);
 
             offers.push(short);
         }
@@ -162,13 +207,19 @@ pub struct Actor {
     projection: xtra::Address<projection::Actor>,
     rollover_params: RolloverParams,
     time_to_first_position: xtra::Address<time_to_first_position::Actor>,
-    collab_settlement: xtra::Address<daemon::collab_settlement::maker::Actor>,
+    collab_settlement: xtra::Address<collab_settlement::maker::Actor<command::Executor>>,
     collab_settlement_deprecated:
-        xtra::Address<daemon::collab_settlement::deprecated::maker::Actor>,
+        xtra::Address<collab_settlement::deprecated::maker::Actor<command::Executor>>,
     offer: xtra::Address<offer::maker::Actor>,
+    offer_deprecated_v2: xtra::Address<offer::deprecated_v2::maker::Actor>,
     offer_deprecated: xtra::Address<offer::deprecated::maker::Actor>,
     order: xtra::Address<order::maker::Actor>,
     order_deprecated: xtra::Address<order::deprecated::maker::Actor>,
+    funding_rate_history: xtra::Address<funding_rate_history::maker::Actor>,
+    offer_history: xtra::Address<offer_history::Actor>,
+    volatility_spreads: Option<watch::Receiver<VolatilitySpreads>>,
+    shadow_pricing: Option<xtra::Address<shadow_pricing::Actor>>,
+    offers_withdrawn: bool,
 }
 
 impl Actor {
@@ -177,17 +228,20 @@ impl Actor {
         projection: xtra::Address<projection::Actor>,
         time_to_first_position: xtra::Address<time_to_first_position::Actor>,
         (collab_settlement, collab_settlement_deprecated): (
-            xtra::Address<daemon::collab_settlement::maker::Actor>,
-            xtra::Address<daemon::collab_settlement::deprecated::maker::Actor>,
+            xtra::Address<collab_settlement::maker::Actor<command::Executor>>,
+            xtra::Address<collab_settlement::deprecated::maker::Actor<command::Executor>>,
         ),
-        (offer, offer_deprecated): (
+        (offer, offer_deprecated_v2, offer_deprecated): (
             xtra::Address<offer::maker::Actor>,
+            xtra::Address<offer::deprecated_v2::maker::Actor>,
             xtra::Address<offer::deprecated::maker::Actor>,
         ),
         (order, order_deprecated): (
             xtra::Address<order::maker::Actor>,
             xtra::Address<order::deprecated::maker::Actor>,
         ),
+        funding_rate_history: xtra::Address<funding_rate_history::maker::Actor>,
+        offer_history: xtra::Address<offer_history::Actor>,
     ) -> Self {
         Self {
             settlement_interval,
@@ -197,12 +251,64 @@ impl Actor {
             collab_settlement,
             collab_settlement_deprecated,
             offer,
+            offer_deprecated_v2,
             offer_deprecated,
             order,
             order_deprecated,
+            funding_rate_history,
+            offer_history,
+            volatility_spreads: None,
+            shadow_pricing: None,
+            offers_withdrawn: false,
         }
     }
 
+    /// Widen the prices of published offers by the spread derived from the volatility.
+    pub fn with_volatility_spreads(
+        mut self,
+        volatility_spreads: watch::Receiver<VolatilitySpreads>,
+    ) -> Self {
+        self.volatility_spreads = Some(volatility_spreads);
+        self
+    }
+
+    /// Record the offers a candidate pricing configuration would publish alongside the offers.
+    pub fn with_shadow_pricing(
+        mut self,
+        shadow_pricing: xtra::Address<shadow_pricing::Actor>,
+    ) -> Self {
+        self.shadow_pricing = Some(shadow_pricing);
+        self
+    }
+
+    fn widen_prices(&self, mut offer_params: OfferParams) -> Result<OfferParams> {
+        let volatility_spread = match self
+            .volatility_spreads
+            .as_ref()
+            .and_then(|spreads| spreads.borrow().get(&offer_params.contract_symbol).copied())
+        {
+            Some(volatility_spread) if volatility_spread.spread > 0.0 => volatility_spread,
+            _ => return Ok(offer_params),
+        };
+
+        offer_params.price_long = offer_params
+            .price_long
+            .map(|price| volatility_spread.widen(price, Position::Long))
+            .transpose()?;
+        offer_params.price_short = offer_params
+            .price_short
+            .map(|price| volatility_spread.widen(price, Position::Short))
+            .transpose()?;
+
+        tracing::debug!(
+            contract_symbol = %offer_params.contract_symbol,
+            spread = %volatility_spread.spread,
+            "Widened offer prices by volatility spread"
+        );
+
+        Ok(offer_params)
+    }
+
     fn udpate_rollover_params(
         &mut self,
         contract_symbol: ContractSymbol,
@@ -296,7 +402,7 @@ impl Actor {
 
         let res = self
             .collab_settlement
-            .send(daemon::collab_settlement::maker::Accept { order_id })
+            .send(collab_settlement::maker::Accept { order_id })
             .await
             .map_err(anyhow::Error::new);
 
@@ -304,7 +410,7 @@ impl Actor {
         if let Err(e0) | Ok(Err(e0)) = res {
             if let Err(e1) | Ok(Err(e1)) = self
                 .collab_settlement_deprecated
-                .send(daemon::collab_settlement::deprecated::maker::Accept { order_id })
+                .send(collab_settlement::deprecated::maker::Accept { order_id })
                 .await
                 .map_err(anyhow::Error::new)
             {
@@ -324,7 +430,7 @@ impl Actor {
 
         let res = self
             .collab_settlement
-            .send(daemon::collab_settlement::maker::Reject { order_id })
+            .send(collab_settlement::maker::Reject { order_id })
             .await
             .map_err(anyhow::Error::new);
 
@@ -332,7 +438,7 @@ impl Actor {
         if let Err(e0) | Ok(Err(e0)) = res {
             if let Err(e1) | Ok(Err(e1)) = self
                 .collab_settlement_deprecated
-                .send(daemon::collab_settlement::deprecated::maker::Reject { order_id })
+                .send(collab_settlement::deprecated::maker::Reject { order_id })
                 .await
                 .map_err(anyhow::Error::new)
             {
@@ -370,6 +476,10 @@ impl Actor {
 #[xtra_productivity]
 impl Actor {
     async fn handle_offer_params(&mut self, offer_params: OfferParams) -> Result<()> {
+        if self.offers_withdrawn {
+            bail!("Offers were withdrawn, not publishing new offers");
+        }
+
         // 1. Update internal state for rollovers
         self.udpate_rollover_params(
             offer_params.contract_symbol,
@@ -378,14 +488,50 @@ impl Actor {
             offer_params.tx_fee_rate,
         );
 
-        let offers = offer_params.into_offers(self.settlement_interval);
+        let base_params = self.shadow_pricing.is_some().then(|| offer_params.clone());
+        let offers = self
+            .widen_prices(offer_params)?
+            .into_offers(self.settlement_interval);
 
         // 2. Notify UI via feed
         self.projection
             .send(projection::Update(offers.clone()))
             .await?;
 
-        // 3. Broadcast to all peers via offer actor
+        // 3. Record funding rates for takers querying the funding rate history
+        if let Err(e) = self
+            .funding_rate_history
+            .send_async_safe(funding_rate_history::maker::RecordFundingRates(
+                offers.clone(),
+            ))
+            .await
+        {
+            tracing::warn!("{e:#}");
+        }
+
+        // 4. Record the offers in the audit trail
+        if let Err(e) = self
+            .offer_history
+            .send_async_safe(offer_history::RecordOffers(offers.clone()))
+            .await
+        {
+            tracing::warn!("{e:#}");
+        }
+
+        // 5. Let the candidate pricing configuration shadow the offers
+        if let (Some(shadow_pricing), Some(params)) = (&self.shadow_pricing, base_params) {
+            if let Err(e) = shadow_pricing
+                .send_async_safe(shadow_pricing::RecordOffers {
+                    params,
+                    offers: offers.clone(),
+                })
+                .await
+            {
+                tracing::warn!("{e:#}");
+            }
+        }
+
+        // 6. Broadcast to all peers via offer actor
         if let Err(e) = self
             .offer
             .send_async_safe(offer::maker::NewOffers::new(offers.clone()))
@@ -394,7 +540,15 @@ impl Actor {
             tracing::warn!("{e:#}");
         }
 
-        // 4. Broadcast to all peers via deprecated offer actor
+        // 7. Broadcast to all peers via deprecated offer actors
+        if let Err(e) = self
+            .offer_deprecated_v2
+            .send_async_safe(offer::deprecated_v2::maker::NewOffers::new(offers.clone()))
+            .await
+        {
+            tracing::warn!("{e:#}");
+        }
+
         {
             // Takers on the deprecated version only care (and know how to handle) BTCUSD offers
             let btcusd_offers = offers
@@ -416,6 +570,74 @@ impl Actor {
         Ok(())
     }
 
+    async fn handle_revise_offers(&mut self, msg: ReviseOffers) -> Result<()> {
+        if self.offers_withdrawn {
+            bail!("Offers were withdrawn, not revising offers");
+        }
+
+        let ReviseOffers {
+            contract_symbol,
+            update,
+        } = msg;
+
+        // The offer actor revises the offers it hands out to incoming orders, hence it is the
+        // source of truth for the revised offers
+        let offers = self
+            .offer
+            .send(offer::maker::ReviseOffers {
+                contract_symbol,
+                update,
+            })
+            .await
+            .context("Offer actor disconnected")??;
+
+        if let Err(e) = self
+            .offer_history
+            .send_async_safe(offer_history::RecordOffers(offers.clone()))
+            .await
+        {
+            tracing::warn!("{e:#}");
+        }
+
+        // Takers on the deprecated protocols do not know about revisions, their orders for
+        // revised offers are rejected until the next offer params replace the offers
+        self.projection.send(projection::Update(offers)).await?;
+
+        Ok(())
+    }
+
+    async fn handle_withdraw_offers(&mut self, _: WithdrawOffers) -> Result<()> {
+        self.offers_withdrawn = true;
+
+        self.projection.send(projection::OffersWithdrawn).await?;
+
+        if let Err(e) = self
+            .offer
+            .send_async_safe(offer::maker::WithdrawOffers)
+            .await
+        {
+            tracing::warn!("{e:#}");
+        }
+
+        if let Err(e) = self
+            .offer_deprecated_v2
+            .send_async_safe(offer::deprecated_v2::maker::WithdrawOffers)
+            .await
+        {
+            tracing::warn!("{e:#}");
+        }
+
+        if let Err(e) = self
+            .offer_deprecated
+            .send_async_safe(offer::deprecated::maker::WithdrawOffers)
+            .await
+        {
+            tracing::warn!("{e:#}");
+        }
+
+        Ok(())
+    }
+
     async fn handle(&mut self, msg: TakerConnected) -> Result<()> {
         self.handle_taker_connected(msg.id).await
     }
@@ -0,0 +1,251 @@
+//! Cooperative close of all CFDs with a single taker.
+//!
+//! The maker asks the taker to settle each open CFD they have together and accepts all settlement
+//! proposals the taker sends for these CFDs until the grace period is over. CFDs that are still
+//! open afterwards are left as they are.
+
+use crate::cfd;
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+use daemon::close_all;
+use daemon::close_all::Summary;
+use daemon::projection;
+use daemon::projection::CfdAction;
+use daemon::settlement_proposal;
+use model::libp2p::PeerId;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::sync::watch;
+use xtra::prelude::MessageChannel;
+use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+/// Interval at which the progress of the sessions is checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Grace period for collaborative settlement if none is specified
+pub const DEFAULT_GRACE_PERIOD: time::Duration = time::Duration::hours(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// Waiting for the taker to settle its CFDs collaboratively
+    AwaitingSettlement,
+    /// All CFDs are either settled or failed to be settled
+    Completed,
+    /// The grace period is over before all CFDs were settled
+    Expired,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Session {
+    pub phase: Phase,
+    #[serde(with = "time::serde::timestamp")]
+    pub started_at: OffsetDateTime,
+    /// End of the grace period for collaborative settlement
+    #[serde(with = "time::serde::timestamp")]
+    pub deadline: OffsetDateTime,
+    #[serde(flatten)]
+    pub summary: Summary,
+}
+
+/// Start closing all CFDs with a taker.
+#[derive(Clone, Copy)]
+pub struct Start {
+    pub peer_id: PeerId,
+    /// How long the taker has to settle collaboratively
+    pub grace_period: time::Duration,
+}
+
+/// Get the latest session with a taker.
+#[derive(Clone, Copy)]
+pub struct GetSession {
+    pub peer_id: PeerId,
+}
+
+#[derive(Clone, Copy)]
+struct CheckProgress;
+
+pub struct Actor {
+    cfd_actor: xtra::Address<cfd::Actor>,
+    settlement_proposals: MessageChannel<settlement_proposal::maker::ProposeSettlement, Result<()>>,
+    cfds: watch::Receiver<Option<Vec<projection::Cfd>>>,
+    sessions: HashMap<PeerId, Session>,
+}
+
+impl Actor {
+    pub fn new(
+        cfd_actor: xtra::Address<cfd::Actor>,
+        settlement_proposals: MessageChannel<
+            settlement_proposal::maker::ProposeSettlement,
+            Result<()>,
+        >,
+        cfds: watch::Receiver<Option<Vec<projection::Cfd>>>,
+    ) -> Self {
+        Self {
+            cfd_actor,
+            settlement_proposals,
+            cfds,
+            sessions: HashMap::new(),
+        }
+    }
+
+    async fn check_session(&self, session: &mut Session) {
+        let cfds = self
+            .cfds
+            .borrow()
+            .iter()
+            .flatten()
+            .filter(|cfd| session.summary.pending.contains(&cfd.order_id))
+            .map(|cfd| {
+                (
+                    cfd.order_id,
+                    cfd.state,
+                    cfd.actions.contains(&CfdAction::AcceptSettlement),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        for (order_id, state, can_accept_settlement) in cfds {
+            match close_all::settlement_outcome(state) {
+                Some(Ok(())) => {
+                    session.summary.record_settled(order_id);
+                    continue;
+                }
+                Some(Err(e)) => {
+                    session.summary.record_failure(order_id, &e);
+                    continue;
+                }
+                None => {}
+            }
+
+            if !can_accept_settlement {
+                continue;
+            }
+
+            let res = match self
+                .cfd_actor
+                .send(cfd::AcceptSettlement { order_id })
+                .await
+            {
+                Ok(res) => res,
+                Err(e) => Err(anyhow::Error::new(e)),
+            };
+
+            match res {
+                Ok(()) => tracing::info!(%order_id, "Accepted settlement proposal of taker"),
+                Err(e) => session.summary.record_failure(order_id, &e),
+            }
+        }
+
+        if session.summary.is_completed() {
+            session.phase = Phase::Completed;
+        } else if OffsetDateTime::now_utc() >= session.deadline {
+            session.phase = Phase::Expired;
+        }
+    }
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle_start(&mut self, msg: Start) -> Result<Session> {
+        let Start {
+            peer_id,
+            grace_period,
+        } = msg;
+
+        let in_progress = self
+            .sessions
+            .get(&peer_id)
+            .map_or(false, |session| session.phase == Phase::AwaitingSettlement);
+        if in_progress {
+            bail!("Already closing all CFDs with taker {peer_id}");
+        }
+
+        let order_ids =
+            close_all::open_cfds_with(self.cfds.borrow().as_deref().unwrap_or_default(), peer_id);
+        if order_ids.is_empty() {
+            bail!("No open CFDs with taker {peer_id}");
+        }
+
+        let started_at = OffsetDateTime::now_utc();
+        let mut session = Session {
+            phase: Phase::AwaitingSettlement,
+            started_at,
+            deadline: started_at + grace_period,
+            summary: Summary::new(order_ids.clone()),
+        };
+
+        for order_id in order_ids {
+            let res = match self
+                .settlement_proposals
+                .send(settlement_proposal::maker::ProposeSettlement { order_id })
+                .await
+            {
+                Ok(res) => res,
+                Err(e) => Err(anyhow::Error::new(e)),
+            };
+
+            if let Err(e) = res {
+                session.summary.record_failure(order_id, &e);
+            }
+        }
+
+        if session.summary.is_completed() {
+            session.phase = Phase::Completed;
+        }
+
+        tracing::info!(%peer_id, pending = %session.summary.pending.len(), "Started closing all CFDs with taker");
+
+        self.sessions.insert(peer_id, session.clone());
+
+        Ok(session)
+    }
+
+    async fn handle_get_session(&mut self, msg: GetSession) -> Option<Session> {
+        self.sessions.get(&msg.peer_id).cloned()
+    }
+
+    async fn handle(&mut self, _: CheckProgress) {
+        let mut sessions = std::mem::take(&mut self.sessions);
+
+        for (peer_id, session) in sessions.iter_mut() {
+            if session.phase != Phase::AwaitingSettlement {
+                continue;
+            }
+
+            self.check_session(session).await;
+
+            match session.phase {
+                Phase::Completed => {
+                    tracing::info!(%peer_id, settled = %session.summary.settled.len(), failed = %session.summary.failures.len(), "Closed all CFDs with taker")
+                }
+                Phase::Expired => {
+                    tracing::warn!(%peer_id, pending = %session.summary.pending.len(), "Grace period for closing all CFDs with taker is over")
+                }
+                Phase::AwaitingSettlement => {}
+            }
+        }
+
+        self.sessions = sessions;
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we just started");
+
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(CHECK_INTERVAL, || CheckProgress, xtras::IncludeSpan::Never),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
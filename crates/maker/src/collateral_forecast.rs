@@ -0,0 +1,447 @@
+//! Forecast of the collateral required by the published offers.
+//!
+//! If all published offers were taken at their maximum quantity, the maker would have to lock its
+//! margin for each of them. The forecast compares the sum of these margins with the balance of the
+//! wallet, so that the maker can size its offers to what it can actually serve. Whenever the
+//! utilization crosses one of the configured thresholds an alert is logged and the figures are
+//! exported as metrics.
+//!
+//! Before offers are published, the [`Lockup`] of the offers alone is compared with the balance
+//! that is not locked yet, so that the operator learns about offers that cannot be served before
+//! takers do.
+
+use async_trait::async_trait;
+use bdk::bitcoin::Amount;
+use daemon::projection::CfdOffer;
+use daemon::projection::MakerOffers;
+use model::calculate_margin;
+use model::ContractSymbol;
+use model::Contracts;
+use model::Leverage;
+use model::OfferId;
+use model::Position;
+use model::Price;
+use model::WalletInfo;
+use serde::Serialize;
+use tokio::sync::watch;
+use xtra_productivity::xtra_productivity;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Thresholds {
+    /// Utilization from which on a warning is logged, e.g. `0.8`
+    pub warning: f64,
+    /// Utilization from which on an error is logged, e.g. `1.0` if the balance does not suffice
+    pub critical: f64,
+}
+
+impl Thresholds {
+    fn level(&self, utilization: Option<f64>) -> Level {
+        match utilization {
+            // Offers without any balance to back them cannot be served at all
+            None => Level::Critical,
+            Some(utilization) if utilization >= self.critical => Level::Critical,
+            Some(utilization) if utilization >= self.warning => Level::Warning,
+            Some(_) => Level::Ok,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    Ok,
+    Warning,
+    Critical,
+}
+
+/// The margin the maker would lock if the offer was taken at its maximum quantity.
+#[derive(Debug, Clone, Serialize)]
+pub struct OfferRequirement {
+    pub offer_id: OfferId,
+    pub contract_symbol: ContractSymbol,
+    pub position_maker: Position,
+    pub max_quantity: Contracts,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub margin: Amount,
+}
+
+impl From<&CfdOffer> for OfferRequirement {
+    fn from(offer: &CfdOffer) -> Self {
+        Self {
+            offer_id: offer.id,
+            contract_symbol: offer.contract_symbol,
+            position_maker: offer.position_maker,
+            max_quantity: offer.max_quantity,
+            // The maker never takes leverage
+            margin: calculate_margin(
+                offer.contract_symbol,
+                offer.price,
+                offer.max_quantity,
+                Leverage::ONE,
+            ),
+        }
+    }
+}
+
+/// The margin the maker would lock if an offer was taken at its maximum quantity with a leverage
+/// choice.
+#[derive(Debug, Clone, Serialize)]
+pub struct LeverageLockup {
+    /// Leverage of the taker
+    pub leverage: Leverage,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub maker_margin: Amount,
+    /// Margin of maker and taker
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub total_locked: Amount,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OfferLockup {
+    pub position_maker: Position,
+    pub price: Price,
+    pub max_quantity: Contracts,
+    pub leverage_choices: Vec<LeverageLockup>,
+    /// Largest margin of the maker across all leverage choices
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub worst_case: Amount,
+}
+
+impl OfferLockup {
+    fn new(
+        contract_symbol: ContractSymbol,
+        position_maker: Position,
+        price: Price,
+        max_quantity: Contracts,
+        leverage_choices: &[Leverage],
+    ) -> Self {
+        let leverage_choices = leverage_choices
+            .iter()
+            .map(|leverage| {
+                // The maker never takes leverage
+                let maker_margin =
+                    calculate_margin(contract_symbol, price, max_quantity, Leverage::ONE);
+                let taker_margin =
+                    calculate_margin(contract_symbol, price, max_quantity, *leverage);
+
+                LeverageLockup {
+                    leverage: *leverage,
+                    maker_margin,
+                    total_locked: maker_margin + taker_margin,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let worst_case = leverage_choices
+            .iter()
+            .map(|lockup| lockup.maker_margin)
+            .max()
+            .unwrap_or(Amount::ZERO);
+
+        Self {
+            position_maker,
+            price,
+            max_quantity,
+            leverage_choices,
+            worst_case,
+        }
+    }
+}
+
+/// The collateral the maker would lock if the offers of a contract symbol were fully taken.
+#[derive(Debug, Clone, Serialize)]
+pub struct Lockup {
+    pub contract_symbol: ContractSymbol,
+    pub offers: Vec<OfferLockup>,
+    /// Sum of the worst-case margin of all offers
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub required: Amount,
+    /// Balance of the wallet that is not locked by ongoing contract setups
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub available: Amount,
+}
+
+impl Lockup {
+    pub fn new(
+        contract_symbol: ContractSymbol,
+        (price_long, price_short): (Option<Price>, Option<Price>),
+        max_quantity: Contracts,
+        leverage_choices: &[Leverage],
+        available: Amount,
+    ) -> Self {
+        let offers = [(Position::Long, price_long), (Position::Short, price_short)]
+            .into_iter()
+            .filter_map(|(position, price)| {
+                let price = price?;
+
+                Some(OfferLockup::new(
+                    contract_symbol,
+                    position,
+                    price,
+                    max_quantity,
+                    leverage_choices,
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        let required = offers
+            .iter()
+            .fold(Amount::ZERO, |sum, offer| sum + offer.worst_case);
+
+        Self {
+            contract_symbol,
+            offers,
+            required,
+            available,
+        }
+    }
+
+    pub fn is_overcommitted(&self) -> bool {
+        self.required > self.available
+    }
+}
+
+/// The offers would lock more collateral than available and overcommitting is not allowed.
+#[derive(Debug, thiserror::Error)]
+#[error("Offers would lock up to {required} but only {available} are available, publish them with --allow-overcommit to override")]
+pub struct Overcommitted {
+    pub required: Amount,
+    pub available: Amount,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Forecast {
+    pub offers: Vec<OfferRequirement>,
+    /// Sum of the margin of all offers
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub required: Amount,
+    /// Balance of the wallet, `null` until the wallet was synced
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat::opt")]
+    pub available: Option<Amount>,
+    /// Fraction of the balance required by the offers, `null` if the balance is zero or unknown
+    pub utilization: Option<f64>,
+    pub level: Level,
+}
+
+impl Forecast {
+    pub fn new(offers: &MakerOffers, wallet: Option<&WalletInfo>, thresholds: Thresholds) -> Self {
+        let offers = [
+            &offers.btcusd_long,
+            &offers.btcusd_short,
+            &offers.ethusd_long,
+            &offers.ethusd_short,
+        ]
+        .into_iter()
+        .flatten()
+        .map(OfferRequirement::from)
+        .collect::<Vec<_>>();
+
+        let required = offers
+            .iter()
+            .fold(Amount::ZERO, |sum, offer| sum + offer.margin);
+        let available = wallet.map(|wallet| wallet.balance);
+        let utilization = match available {
+            _ if required == Amount::ZERO => Some(0.0),
+            Some(available) if available > Amount::ZERO => {
+                Some(required.as_sat() as f64 / available.as_sat() as f64)
+            }
+            _ => None,
+        };
+
+        // The utilization cannot be judged before the wallet was synced
+        let level = match available {
+            None => Level::Ok,
+            Some(_) => thresholds.level(utilization),
+        };
+
+        Self {
+            offers,
+            required,
+            available,
+            utilization,
+            level,
+        }
+    }
+}
+
+pub struct Actor {
+    thresholds: Thresholds,
+    offers: watch::Receiver<MakerOffers>,
+    wallet: watch::Receiver<Option<WalletInfo>>,
+    sender: watch::Sender<Forecast>,
+}
+
+impl Actor {
+    pub fn new(
+        thresholds: Thresholds,
+        offers: watch::Receiver<MakerOffers>,
+        wallet: watch::Receiver<Option<WalletInfo>>,
+    ) -> (Self, watch::Receiver<Forecast>) {
+        let forecast = Forecast::new(&offers.borrow(), wallet.borrow().as_ref(), thresholds);
+        let (sender, receiver) = watch::channel(forecast);
+
+        (
+            Self {
+                thresholds,
+                offers,
+                wallet,
+                sender,
+            },
+            receiver,
+        )
+    }
+
+    fn update(&mut self) {
+        let forecast = Forecast::new(
+            &self.offers.borrow(),
+            self.wallet.borrow().as_ref(),
+            self.thresholds,
+        );
+
+        metrics::record(&forecast);
+
+        let previous_level = self.sender.borrow().level;
+        if forecast.level != previous_level {
+            let required = forecast.required;
+            let available = forecast.available.unwrap_or(Amount::ZERO);
+
+            match forecast.level {
+                Level::Critical => tracing::error!(
+                    %required,
+                    %available,
+                    "Balance does not suffice to serve all offers at their maximum quantity"
+                ),
+                Level::Warning => tracing::warn!(
+                    %required,
+                    %available,
+                    utilization = ?forecast.utilization,
+                    "Offers at their maximum quantity would use most of the balance"
+                ),
+                Level::Ok => tracing::info!(
+                    %required,
+                    %available,
+                    "Balance suffices to serve all offers at their maximum quantity"
+                ),
+            }
+        }
+
+        let _ = self.sender.send(forecast);
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we just started");
+
+        tokio_extras::spawn(&this.clone(), {
+            let mut offers = self.offers.clone();
+            let mut wallet = self.wallet.clone();
+
+            async move {
+                loop {
+                    let changed = tokio::select! {
+                        changed = offers.changed() => changed,
+                        changed = wallet.changed() => changed,
+                    };
+
+                    if changed.is_err() || this.send(Update).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[derive(Clone, Copy)]
+struct Update;
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: Update) {
+        self.update();
+    }
+}
+
+mod metrics {
+    use super::*;
+
+    static REQUIRED_COLLATERAL_GAUGE: conquer_once::Lazy<prometheus::IntGauge> =
+        conquer_once::Lazy::new(|| {
+            prometheus::register_int_gauge!(
+                "collateral_forecast_required_sats",
+                "The margin the maker would lock if all offers were taken at their maximum quantity in satoshis."
+            )
+            .unwrap()
+        });
+
+    static COLLATERAL_UTILIZATION_GAUGE: conquer_once::Lazy<prometheus::Gauge> =
+        conquer_once::Lazy::new(|| {
+            prometheus::register_gauge!(
+                "collateral_forecast_utilization",
+                "Fraction of the wallet balance required if all offers were taken at their maximum quantity."
+            )
+            .unwrap()
+        });
+
+    pub fn record(forecast: &Forecast) {
+        REQUIRED_COLLATERAL_GAUGE.set(forecast.required.as_sat() as i64);
+        if let Some(utilization) = forecast.utilization {
+            COLLATERAL_UTILIZATION_GAUGE.set(utilization);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    const THRESHOLDS: Thresholds = Thresholds {
+        warning: 0.8,
+        critical: 1.0,
+    };
+
+    #[test]
+    fn level_depends_on_utilization() {
+        assert_eq!(THRESHOLDS.level(Some(0.0)), Level::Ok);
+        assert_eq!(THRESHOLDS.level(Some(0.8)), Level::Warning);
+        assert_eq!(THRESHOLDS.level(Some(1.2)), Level::Critical);
+        assert_eq!(THRESHOLDS.level(None), Level::Critical);
+    }
+
+    #[test]
+    fn lockup_sums_worst_case_margin_of_both_offers() {
+        let price = Price::new(dec!(20_000)).unwrap();
+        let lockup = Lockup::new(
+            ContractSymbol::BtcUsd,
+            (Some(price), Some(price)),
+            Contracts::new(20_000),
+            &[Leverage::ONE, Leverage::TWO],
+            Amount::ONE_BTC,
+        );
+
+        assert_eq!(lockup.offers.len(), 2);
+        assert_eq!(lockup.offers[0].worst_case, Amount::ONE_BTC);
+        assert_eq!(
+            lockup.offers[0].leverage_choices[1].total_locked,
+            Amount::from_btc(1.5).unwrap()
+        );
+        assert_eq!(lockup.required, Amount::from_btc(2.0).unwrap());
+        assert!(lockup.is_overcommitted());
+    }
+
+    #[test]
+    fn no_offers_require_no_collateral() {
+        let forecast = Forecast::new(&MakerOffers::default(), None, THRESHOLDS);
+
+        assert_eq!(forecast.required, Amount::ZERO);
+        assert_eq!(forecast.utilization, Some(0.0));
+        assert_eq!(forecast.level, Level::Ok);
+    }
+}
@@ -0,0 +1,265 @@
+//! Watch-list of deposits the maker expects from clients.
+//!
+//! The operator registers the address a client pays to together with a label and optionally the
+//! expected amount. Confirmed incoming wallet transactions are matched against the pending
+//! expected deposits by their outputs. Matched deposits are published through a `watch` channel to
+//! notify the UI, and the ledger entry of the transaction carries the label.
+
+use anyhow::ensure;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use bdk::bitcoin::Address;
+use bdk::bitcoin::Amount;
+use bdk::bitcoin::Transaction;
+use bdk::bitcoin::Txid;
+use daemon::wallet;
+use model::Timestamp;
+use model::WalletInfo;
+use sqlite_db::expected_deposits::ExpectedDeposit;
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::sync::watch;
+use xtra::prelude::MessageChannel;
+use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+/// Interval at which confirmed wallet transactions are matched against the watch-list
+const MATCH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Maximum length of the label of an expected deposit
+const MAX_LABEL_LENGTH: usize = 64;
+
+pub struct Actor {
+    db: sqlite_db::Connection,
+    wallet: MessageChannel<wallet::GetTransaction, Result<Option<Transaction>>>,
+    wallet_info: watch::Receiver<Option<WalletInfo>>,
+    /// Transactions that were already matched against the watch-list
+    matched: HashSet<Txid>,
+    sender: watch::Sender<Vec<ExpectedDeposit>>,
+}
+
+impl Actor {
+    pub fn new(
+        db: sqlite_db::Connection,
+        wallet: MessageChannel<wallet::GetTransaction, Result<Option<Transaction>>>,
+        wallet_info: watch::Receiver<Option<WalletInfo>>,
+    ) -> (Self, watch::Receiver<Vec<ExpectedDeposit>>) {
+        let (sender, receiver) = watch::channel(Vec::new());
+
+        (
+            Self {
+                db,
+                wallet,
+                wallet_info,
+                matched: HashSet::new(),
+                sender,
+            },
+            receiver,
+        )
+    }
+
+    async fn publish(&self) -> Result<()> {
+        let deposits = self.db.load_expected_deposits().await?;
+        let _ = self.sender.send(deposits);
+
+        Ok(())
+    }
+
+    async fn match_transactions(&mut self) -> Result<()> {
+        let incoming = match &*self.wallet_info.borrow() {
+            Some(wallet_info) => wallet_info
+                .transactions
+                .iter()
+                .filter(|tx| tx.received > tx.sent && !self.matched.contains(&tx.txid))
+                .filter_map(|tx| {
+                    let confirmation_time = tx.confirmation_time.as_ref()?;
+                    Some((tx.txid, Timestamp::new(confirmation_time.timestamp as i64)))
+                })
+                .collect::<Vec<_>>(),
+            None => return Ok(()),
+        };
+
+        if incoming.is_empty() {
+            return Ok(());
+        }
+
+        let pending = self
+            .db
+            .load_expected_deposits()
+            .await?
+            .into_iter()
+            .filter(|deposit| deposit.received.is_none())
+            .collect::<Vec<_>>();
+
+        for (txid, received_at) in incoming {
+            if !pending.is_empty() {
+                let transaction = self
+                    .wallet
+                    .send(wallet::GetTransaction { txid })
+                    .await
+                    .context("Wallet actor not available")??
+                    .with_context(|| format!("Wallet transaction {txid} not found"))?;
+
+                for deposit in &pending {
+                    let amount = paid_to(&transaction, &deposit.address);
+                    if amount == Amount::ZERO {
+                        continue;
+                    }
+
+                    self.db
+                        .record_received_deposit(&deposit.label, txid, amount, received_at)
+                        .await?;
+
+                    match deposit.amount {
+                        Some(expected) if expected != amount => tracing::warn!(
+                            label = %deposit.label,
+                            %txid,
+                            %expected,
+                            received = %amount,
+                            "Expected deposit arrived with a different amount"
+                        ),
+                        _ => tracing::info!(
+                            label = %deposit.label,
+                            %txid,
+                            %amount,
+                            "Expected deposit arrived"
+                        ),
+                    }
+                }
+            }
+
+            self.matched.insert(txid);
+        }
+
+        self.publish().await
+    }
+}
+
+/// Sum of the outputs of `transaction` paying to `address`.
+fn paid_to(transaction: &Transaction, address: &Address) -> Amount {
+    let script_pubkey = address.script_pubkey();
+
+    let amount = transaction
+        .output
+        .iter()
+        .filter(|output| output.script_pubkey == script_pubkey)
+        .map(|output| output.value)
+        .sum();
+
+    Amount::from_sat(amount)
+}
+
+/// Register a deposit expected at `address`.
+pub struct AddExpectedDeposit {
+    pub label: String,
+    pub address: Address,
+    /// `None` if any amount is expected
+    pub amount: Option<Amount>,
+}
+
+/// Remove an expected deposit that did not arrive yet.
+pub struct RemoveExpectedDeposit {
+    pub label: String,
+}
+
+#[derive(Clone, Copy)]
+struct MatchTransactions;
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: AddExpectedDeposit) -> Result<()> {
+        let AddExpectedDeposit {
+            label,
+            address,
+            amount,
+        } = msg;
+
+        ensure!(
+            !label.is_empty() && label.chars().count() <= MAX_LABEL_LENGTH,
+            "Label must have between 1 and {MAX_LABEL_LENGTH} characters"
+        );
+
+        self.db
+            .insert_expected_deposit(&label, &address, amount)
+            .await?;
+        self.publish().await?;
+
+        tracing::info!(%label, %address, ?amount, "Added expected deposit to watch-list");
+
+        Ok(())
+    }
+
+    async fn handle(&mut self, msg: RemoveExpectedDeposit) -> Result<()> {
+        self.db.delete_expected_deposit(&msg.label).await?;
+        self.publish().await
+    }
+
+    async fn handle(&mut self, _: MatchTransactions) {
+        if let Err(e) = self.match_transactions().await {
+            tracing::warn!("Failed to match wallet transactions against watch-list: {e:#}");
+        }
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        if let Err(e) = self.publish().await {
+            tracing::warn!("Failed to load expected deposits: {e:#}");
+        }
+
+        let this = ctx.address().expect("we just started");
+
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(
+                MATCH_INTERVAL,
+                || MatchTransactions,
+                xtras::IncludeSpan::Never,
+            ),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bdk::bitcoin::TxOut;
+
+    #[test]
+    fn sums_outputs_paying_to_address() {
+        let address = "bcrt1qxw9v2aes9dx3dlan3p8ggts0jl2fpvcwrnyn0z"
+            .parse::<Address>()
+            .unwrap();
+        let other = "bcrt1q8n6c9tdlntcf3dcljpnkc6vqqz4kkf3w6scvvm"
+            .parse::<Address>()
+            .unwrap();
+
+        let transaction = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![],
+            output: vec![
+                TxOut {
+                    value: 60_000,
+                    script_pubkey: address.script_pubkey(),
+                },
+                TxOut {
+                    value: 10_000,
+                    script_pubkey: other.script_pubkey(),
+                },
+                TxOut {
+                    value: 40_000,
+                    script_pubkey: address.script_pubkey(),
+                },
+            ],
+        };
+
+        assert_eq!(paid_to(&transaction, &address), Amount::from_sat(100_000));
+    }
+}
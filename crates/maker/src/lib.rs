@@ -1,21 +1,48 @@
 use bdk::bitcoin::util::bip32::ExtendedPrivKey;
 use clap::Parser;
 use daemon::bdk;
+use daemon::cet_broadcast;
+use daemon::daily_marks;
+use daemon::missing_attestation;
+use daemon::order::pending_timeout::PendingOrderTimeout;
+use daemon::scheduler;
+use daemon::telemetry;
+use daemon::wallet;
 use shared_bin::cli::Network;
 use shared_bin::logger::LevelFilter;
 use shared_bin::logger::LOCAL_COLLECTOR_ENDPOINT;
 use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use xtra_libp2p::libp2p::Multiaddr;
 
 pub use actor_system::ActorSystem;
+pub use actor_system::OfferProtocolUsage;
 pub use blocked_peers::load_blocked_peers;
+pub use run::run;
+pub use run::serve;
 
 mod actor_system;
+pub mod aggregator;
+pub mod balance_target;
 mod blocked_peers;
 pub mod cfd;
+pub mod close_all;
+pub mod collateral_forecast;
+pub mod deposit_watch_list;
 mod metrics;
+pub mod offer_history;
+pub mod openapi;
+pub mod peer_sessions;
+pub mod public_api;
 pub mod routes;
+mod run;
+pub mod shadow_pricing;
+pub mod volatility_spread;
+pub mod wind_down;
+pub mod withdrawal;
 
 #[derive(Clone, Debug)]
 pub struct Password(String);
@@ -46,6 +73,13 @@ pub struct Opts {
     #[clap(long, default_value = "10000")]
     pub p2p_port: u16,
 
+    /// The multiaddr to listen on for libp2p connections, overrides `--p2p-port`.
+    ///
+    /// Builds with the `memory-transport` feature of the daemon also accept `/memory/<port>` to
+    /// let takers in the same process connect.
+    #[clap(long)]
+    pub p2p_listen_address: Option<Multiaddr>,
+
     /// The IP address to listen on for the HTTP API.
     #[clap(long, default_value = "127.0.0.1:8001")]
     pub http_address: SocketAddr,
@@ -118,4 +152,279 @@ pub struct Opts {
     /// If enabled, the log will be printed to {service_name}.log in the data dir
     #[clap(long)]
     pub log_to_file: bool,
+
+    /// Database queries taking longer than this many milliseconds are logged as slow queries.
+    #[clap(long, default_value = "500")]
+    pub slow_query_threshold_ms: u64,
+
+    /// If enabled, the currently published offers and quotes are served without authentication
+    /// at `/public/offers`.
+    #[clap(long)]
+    pub public_api: bool,
+
+    /// Origins that are allowed to make cross-origin requests to the public API, `*` allows all
+    /// origins.
+    #[clap(long, value_delimiter = ',')]
+    pub public_api_cors_origins: Vec<String>,
+
+    /// Maximum number of requests per minute a client can make to the public API.
+    #[clap(long, default_value = "60")]
+    pub public_api_rate_limit: u32,
+
+    /// If enabled, the aggregate open interest and the volume traded today per contract symbol
+    /// are included in the public market data.
+    #[clap(long)]
+    pub public_market_stats: bool,
+
+    /// Public address of the maker, e.g. `/dns4/maker.example.com/tcp/10000/p2p/<peer-id>`.
+    ///
+    /// If specified, offers are announced together with these addresses through the gossip network
+    /// so that takers which are not connected to the maker yet can discover its offers.
+    #[clap(long)]
+    pub announce_address: Vec<Multiaddr>,
+
+    /// Maximum number of contracts a single taker may hold in open CFDs per contract symbol.
+    ///
+    /// Orders that would exceed the limit are rejected. Not limited if not specified.
+    #[clap(long)]
+    pub max_contracts_per_taker: Option<u64>,
+
+    /// Path to a JSON file with the oracle event, digits and payout curve per contract symbol.
+    ///
+    /// Symbols not listed in the file use the built-in parameters.
+    #[clap(long)]
+    pub symbol_config: Option<PathBuf>,
+
+    /// Type of the addresses the wallet derives, either `wpkh` or `tr` (taproot).
+    ///
+    /// Funds received on addresses of the other type after switching are reported, see
+    /// `--sweep-previous-wallet`.
+    #[clap(long, default_value = "wpkh")]
+    pub wallet_address_type: wallet::AddressType,
+
+    /// Sweep funds received on addresses of the previously used address type into the wallet.
+    ///
+    /// The wallet of the previous address type is synced every hour. Sweeps pay the estimated fee
+    /// rate for confirmation within 6 blocks.
+    #[clap(long)]
+    pub sweep_previous_wallet: bool,
+
+    /// If enabled, the cached state of all open CFDs is verified against the event log upon
+    /// startup. Mismatches are reported and the affected cache entries invalidated.
+    #[clap(long)]
+    pub verify_state_on_start: bool,
+
+    /// What to do if the oracle attestation is missing one hour after the settlement time: `wait`
+    /// for it, `alert` the operator or publish the commit transaction to `refund` the CFD once the
+    /// refund timelock expired.
+    #[clap(long, default_value = "wait")]
+    pub missing_attestation_policy: missing_attestation::Policy,
+
+    /// When to publish the CET once the oracle attested: `immediate`ly, after
+    /// `--cet-broadcast-delay-mins` if the counterparty usually publishes it (`delayed`), or only
+    /// if the counterparty did not publish it before the `deadline`. The CET is always published
+    /// about one day before the refund timelock expires.
+    #[clap(long, default_value = "immediate")]
+    pub cet_broadcast: cet_broadcast::Strategy,
+
+    /// Minutes to wait for the counterparty to publish the CET with `--cet-broadcast delayed`.
+    #[clap(long, default_value = "30")]
+    pub cet_broadcast_delay_mins: u64,
+
+    /// Point in time in RFC 3339 format, e.g. `2023-01-31T00:00:00Z`, after which no offers are
+    /// sent over the deprecated offer protocols anymore.
+    ///
+    /// Takers that did not upgrade are told that there are no offers. Offers are sent over the
+    /// deprecated protocols indefinitely if not specified.
+    #[clap(long, value_parser(parse_rfc3339))]
+    pub deprecated_offer_protocol_cutoff: Option<OffsetDateTime>,
+
+    /// Number of days published offers are kept in the offer history.
+    ///
+    /// The offer history is kept indefinitely if not specified.
+    #[clap(long)]
+    pub offer_history_retention_days: Option<u32>,
+
+    /// Number of minutes after which takers drop an offer, unless it is re-issued.
+    ///
+    /// Offers are re-issued after half of this time as long as they were updated within the last
+    /// 10 minutes, so takers stop showing them if the maker or its offer updates stop. Offers do
+    /// not expire if not specified. Takers that do not support expiring offers ignore them.
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub offer_time_to_live_mins: Option<u32>,
+
+    /// Interval of a maintenance job in the format `<name>=<seconds>`, e.g.
+    /// `db_compaction=3600`. An interval of `0` disables the job.
+    ///
+    /// Can be given multiple times. The jobs are `db_compaction`, `closed_cfd_migration`,
+    /// `failed_cfd_migration`, `utxo_consolidation`, `daily_marks`, `janitor` and, if telemetry is
+    /// enabled, `telemetry_pruning` and `telemetry_push`.
+    #[clap(long = "job-interval")]
+    pub job_intervals: Vec<scheduler::JobInterval>,
+
+    /// Count anonymous usage statistics, e.g. the number of trades and failed rollovers, in the
+    /// local database.
+    ///
+    /// The statistics contain no order ids, amounts or addresses and can be exported through
+    /// `GET /api/telemetry`. They only leave the machine if `--telemetry-collector` is specified.
+    #[clap(long)]
+    pub telemetry: bool,
+
+    /// Endpoint the usage statistics are `POST`ed to once a day if `--telemetry` is enabled.
+    #[clap(long, requires = "telemetry")]
+    pub telemetry_collector: Option<reqwest::Url>,
+
+    /// Number of days the usage statistics are kept for.
+    #[clap(long, default_value_t = telemetry::DEFAULT_RETENTION_DAYS)]
+    pub telemetry_retention_days: u64,
+
+    /// Time of the day in UTC at which the open CFDs are marked to the market, in the format
+    /// `HH:MM`.
+    ///
+    /// The marks are exported through `GET /api/reports/marks?date=<YYYY-MM-DD>`.
+    #[clap(long, default_value = "00:00")]
+    pub daily_mark_time: daily_marks::MarkTime,
+
+    /// Only report orphaned protocol artifacts, e.g. UTXOs locked by failed contract setups,
+    /// instead of releasing them.
+    ///
+    /// The report of the last run is exported through `GET /api/debug/janitor`.
+    #[clap(long)]
+    pub janitor_dry_run: bool,
+
+    /// Maximum number of contract setups that run at the same time.
+    ///
+    /// Further accepted orders wait in a queue until a running setup finishes.
+    #[clap(long, default_value = "10")]
+    pub max_concurrent_setups: usize,
+
+    /// Grace period in minutes after which orders that were neither accepted nor rejected are
+    /// rejected as timed out, either `<minutes>` for all contract symbols or `<symbol>=<minutes>`,
+    /// e.g. `BTCUSD=5`.
+    ///
+    /// Can be given multiple times. Orders wait for a decision indefinitely if not specified.
+    #[clap(long = "pending-order-timeout")]
+    pub pending_order_timeouts: Vec<PendingOrderTimeout>,
+
+    /// Interval in seconds at which long-horizon counters like the total volume are persisted.
+    ///
+    /// The persisted values are restored at startup so that these counters survive restarts.
+    #[clap(long, default_value = "300", value_parser = clap::value_parser!(u64).range(1..))]
+    pub metrics_persistence_interval_secs: u64,
+
+    /// Withdrawals through the API need approval with a one-time password once they, together
+    /// with the withdrawals of the last 24 hours, exceed this amount in BTC.
+    ///
+    /// Withdrawals of the whole balance always need approval if set. The TOTP secret for the
+    /// one-time passwords is provisioned through the API.
+    #[clap(long, value_parser(parse_btc))]
+    pub withdrawal_approval_threshold: Option<bdk::bitcoin::Amount>,
+
+    /// Longest interval in seconds at which idle takers may ping the maker to keep their
+    /// connection alive.
+    ///
+    /// Announced to takers when they connect. Takers ping more often while they have ongoing
+    /// protocols with the maker or the settlement of one of their CFDs approaches.
+    #[clap(long, default_value = "120", value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_ping_interval_secs: u64,
+
+    /// Address to serve the read-only gRPC API on, e.g. `127.0.0.1:8002`.
+    ///
+    /// The API streams the CFDs, offers, quotes and wallet. Not served if not specified.
+    #[cfg(feature = "grpc")]
+    #[clap(long, requires = "grpc_auth_token")]
+    pub grpc_address: Option<SocketAddr>,
+
+    /// Token gRPC clients have to present as `authorization: Bearer <token>` metadata.
+    #[cfg(feature = "grpc")]
+    #[clap(long)]
+    pub grpc_auth_token: Option<String>,
+
+    /// Factor the realized volatility of the quotes is multiplied with to widen the prices of
+    /// published offers, e.g. `2.0`.
+    ///
+    /// The maker's long price is lowered and its short price raised by the derived spread. Offers
+    /// are published at the given prices if not specified.
+    #[clap(long)]
+    pub volatility_spread_multiplier: Option<f64>,
+
+    /// Period in minutes the realized volatility is computed over.
+    #[clap(long, default_value = "60", value_parser = clap::value_parser!(u64).range(1..))]
+    pub volatility_window_mins: u64,
+
+    /// Upper bound of the volatility spread as a fraction of the price, e.g. `0.01` for 1%.
+    #[clap(long, default_value = "0.01")]
+    pub max_volatility_spread: f64,
+
+    /// Volatility spread multiplier of a candidate pricing configuration to run in shadow mode,
+    /// e.g. `3.0`.
+    ///
+    /// The offers of the candidate configuration are computed alongside the published offers
+    /// and compared in `GET /api/analytics/shadow-pricing`, but never published. Shadow mode is
+    /// off if not specified.
+    #[clap(long)]
+    pub shadow_volatility_spread_multiplier: Option<f64>,
+
+    /// Period in minutes the realized volatility of the candidate configuration is computed over,
+    /// `--volatility-window-mins` if not specified.
+    #[clap(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub shadow_volatility_window_mins: Option<u64>,
+
+    /// Upper bound of the volatility spread of the candidate configuration,
+    /// `--max-volatility-spread` if not specified.
+    #[clap(long)]
+    pub shadow_max_volatility_spread: Option<f64>,
+
+    /// Endpoint of a market aggregator the current offers are `POST`ed to whenever they change,
+    /// e.g. `https://aggregator.example.com/api/offers`.
+    ///
+    /// Publications are signed with the libp2p identity of the maker. Publishing can be stopped
+    /// at runtime through `PUT /api/aggregator`. Offers are not published if not specified.
+    #[clap(long)]
+    pub aggregator_url: Option<reqwest::Url>,
+
+    /// Fraction of the wallet balance the published offers may require at their maximum quantity
+    /// before a warning is logged, e.g. `0.8` for 80%.
+    #[clap(long, default_value = "0.8")]
+    pub collateral_utilization_warning: f64,
+
+    /// Fraction of the wallet balance the published offers may require at their maximum quantity
+    /// before an error is logged.
+    #[clap(long, default_value = "1.0")]
+    pub collateral_utilization_critical: f64,
+
+    /// Publish offers even if the collateral they would lock at their maximum quantity exceeds
+    /// the unlocked balance of the wallet.
+    #[clap(long)]
+    pub allow_overcommit: bool,
+
+    /// Balance in BTC the wallet should work with, e.g. `0.5`.
+    ///
+    /// Once the balance exceeds the target by more than `--target-balance-margin`, the excess is
+    /// withdrawn to `--auto-withdraw-address`, or the operator is alerted if no address is
+    /// specified. The collateral required by the published offers is kept in the wallet even if it
+    /// exceeds the target.
+    #[clap(long, value_parser(parse_btc))]
+    pub target_balance: Option<bdk::bitcoin::Amount>,
+
+    /// How far in BTC the balance may exceed the target before the excess is withdrawn.
+    #[clap(long, value_parser(parse_btc), default_value = "0.01")]
+    pub target_balance_margin: bdk::bitcoin::Amount,
+
+    /// Cold address the balance above the target is withdrawn to.
+    #[clap(long, requires = "target_balance")]
+    pub auto_withdraw_address: Option<bdk::bitcoin::Address>,
+
+    /// Fee rate in satoshis per vbyte above which the balance above the target is not withdrawn.
+    #[clap(long, default_value = "10")]
+    pub auto_withdraw_max_fee_rate: f32,
+}
+
+fn parse_rfc3339(s: &str) -> anyhow::Result<OffsetDateTime> {
+    Ok(OffsetDateTime::parse(s, &Rfc3339)?)
+}
+
+fn parse_btc(s: &str) -> anyhow::Result<bdk::bitcoin::Amount> {
+    let amount = bdk::bitcoin::Amount::from_str_in(s, bdk::bitcoin::Denomination::Bitcoin)?;
+    Ok(amount)
 }
@@ -0,0 +1,106 @@
+//! Audit trail of the offers published by the maker.
+//!
+//! Every published offer and every revision of it is recorded in the append-only offer history of
+//! the database. If a retention period is configured, revisions older than that are deleted
+//! periodically.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use model::Offer;
+use model::Timestamp;
+use sqlite_db::offer_history::OfferRecord;
+use std::time::Duration;
+use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+/// Interval at which revisions past the retention period are deleted
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+pub struct Actor {
+    db: sqlite_db::Connection,
+    retention: Option<time::Duration>,
+}
+
+impl Actor {
+    /// Revisions are kept indefinitely if `retention` is `None`.
+    pub fn new(db: sqlite_db::Connection, retention: Option<time::Duration>) -> Self {
+        Self { db, retention }
+    }
+}
+
+/// Record the publication of offers or revisions of offers.
+pub struct RecordOffers(pub Vec<Offer>);
+
+/// Load the offer revisions published in `[from, to)`.
+#[derive(Clone, Copy)]
+pub struct Export {
+    pub from: Timestamp,
+    pub to: Timestamp,
+}
+
+#[derive(Clone, Copy)]
+struct Prune;
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: RecordOffers) -> Result<()> {
+        let published_at = Timestamp::now();
+
+        for offer in msg.0 {
+            let record = OfferRecord {
+                offer,
+                published_at,
+            };
+
+            if !self.db.record_offer(&record).await? {
+                tracing::debug!(
+                    offer_id = %record.offer.id,
+                    revision = %record.offer.revision,
+                    "Offer revision already recorded"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn handle(&mut self, msg: Export) -> Result<Vec<OfferRecord>> {
+        self.db.load_offer_history(msg.from, msg.to).await
+    }
+
+    async fn handle(&mut self, _: Prune) {
+        let retention = match self.retention {
+            Some(retention) => retention,
+            None => return,
+        };
+
+        let before = Timestamp::new(Timestamp::now().seconds() - retention.whole_seconds());
+
+        match self.db.prune_offer_history(before).await {
+            Ok(0) => {}
+            Ok(deleted) => {
+                tracing::info!(%deleted, "Deleted offer revisions past the retention period")
+            }
+            Err(e) => tracing::warn!("Failed to prune offer history: {e:#}"),
+        }
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        if self.retention.is_none() {
+            return;
+        }
+
+        let this = ctx.address().expect("we just started");
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(PRUNE_INTERVAL, || Prune, xtras::IncludeSpan::Never),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
@@ -0,0 +1,217 @@
+//! OpenAPI specification of the maker's HTTP API, see [`shared_bin::openapi`].
+
+use crate::routes;
+use anyhow::Result;
+use serde_json::Value;
+use shared_bin::openapi::Body;
+use shared_bin::openapi::Doc;
+use shared_bin::openapi::Response;
+
+/// The committed specification, kept in sync with the routes by the tests
+pub const SPEC: &str = include_str!("../openapi.json");
+
+pub const DOCS: &[Doc] = &[
+    Doc::new(
+        "maker_feed",
+        "Stream of the maker's state, starting with the current state",
+        Response::EventStream(&[
+            "wallet",
+            "btcusd_long_offer",
+            "btcusd_short_offer",
+            "ethusd_long_offer",
+            "ethusd_short_offer",
+            "btcusd_quote",
+            "ethusd_quote",
+            "cfds",
+            "chain_tip",
+            "expected_deposits",
+        ]),
+    ),
+    Doc::new(
+        "put_offer_params",
+        "Publish new offers for the contract symbol in the body, returns the collateral they would lock",
+        Response::Json("Collateral the offers would lock"),
+    )
+    .body(Body::Json("Offer parameters")),
+    Doc::new(
+        "put_offer_params_for_symbol",
+        "Publish new offers for a contract symbol, returns the collateral they would lock",
+        Response::Json("Collateral the offers would lock"),
+    )
+    .body(Body::Json("Offer parameters")),
+    Doc::new(
+        "patch_offer_params_for_symbol",
+        "Update some parameters of the published offers of a contract symbol",
+        Response::Empty,
+    )
+    .body(Body::Json("Offer parameters to update")),
+    Doc::new(
+        "post_cfd_action",
+        "Act on a CFD, e.g. `acceptOrder` or `acceptSettlement`",
+        Response::Empty,
+    ),
+    Doc::new("put_cfd_tag", "Tag a CFD", Response::Empty),
+    Doc::new("delete_cfd_tag", "Remove a tag from a CFD", Response::Empty),
+    Doc::new(
+        "get_cfds",
+        "All CFDs, optionally only those with a tag",
+        Response::Json("CFDs"),
+    ),
+    Doc::new("put_sync_wallet", "Sync the wallet", Response::Empty),
+    Doc::new(
+        "post_wind_down",
+        "Stop accepting orders and close all CFDs gradually",
+        Response::Empty,
+    )
+    .body(Body::Json("Wind-down parameters")),
+    Doc::new(
+        "get_wind_down",
+        "Progress of the wind-down",
+        Response::Json("Wind-down status"),
+    ),
+    Doc::new(
+        "post_close_all",
+        "Propose to close all CFDs with a taker",
+        Response::Json("Close-all session"),
+    ),
+    Doc::new(
+        "get_close_all",
+        "Progress of closing all CFDs with a taker",
+        Response::Json("Close-all session"),
+    ),
+    Doc::new(
+        "get_offer_protocol_usage",
+        "Version of the offer protocol used by the connected takers",
+        Response::Json("Offer protocol usage"),
+    ),
+    Doc::new(
+        "get_offer_history",
+        "Offers published between the Unix timestamps `from` and `to`",
+        Response::Json("Published offers"),
+    ),
+    Doc::new(
+        "get_peer_analytics",
+        "Activity of the takers over the last `days`",
+        Response::Json("Peer analytics"),
+    ),
+    Doc::new(
+        "get_offer_analytics",
+        "Realized volatility and derived spread per contract symbol",
+        Response::Json("Offer analytics"),
+    ),
+    Doc::new(
+        "get_collateral_forecast",
+        "Collateral required if all offers were taken at their maximum quantity",
+        Response::Json("Collateral forecast"),
+    ),
+    Doc::new(
+        "get_shadow_pricing",
+        "Comparison of the candidate pricing run in shadow mode, `null` if off",
+        Response::Json("Shadow pricing report"),
+    ),
+    Doc::new(
+        "post_withdraw_request",
+        "Withdraw from the wallet, pending approval above the limits",
+        Response::Json("Transaction id or pending withdrawal"),
+    )
+    .body(Body::Json("Withdrawal")),
+    Doc::new(
+        "post_approve_withdrawal",
+        "Approve a pending withdrawal",
+        Response::Text("Transaction id"),
+    )
+    .body(Body::Json("TOTP code")),
+    Doc::new(
+        "post_provision_totp",
+        "Provision the TOTP secret for approving withdrawals",
+        Response::Json("TOTP provisioning"),
+    )
+    .body(Body::Json(
+        "One-time password of the current secret, if any",
+    )),
+    Doc::new(
+        "get_withdrawals",
+        "Pending and past withdrawals",
+        Response::Json("Withdrawals"),
+    ),
+    Doc::new(
+        "put_expected_deposit",
+        "Watch for a deposit",
+        Response::Empty,
+    )
+    .body(Body::Json("Expected deposit")),
+    Doc::new(
+        "delete_expected_deposit",
+        "Stop watching for a deposit",
+        Response::Empty,
+    ),
+    Doc::new(
+        "get_expected_deposits",
+        "Watched deposits",
+        Response::Json("Expected deposits"),
+    ),
+    Doc::new(
+        "get_aggregator",
+        "Status of the publication to the market aggregator, `null` if not configured",
+        Response::Json("Aggregator status"),
+    ),
+    Doc::new(
+        "put_aggregator",
+        "Start or stop publishing offers to the market aggregator",
+        Response::Empty,
+    )
+    .body(Body::Json("Whether to publish")),
+    Doc::new(
+        "get_balance_target",
+        "Target balance of the wallet and withdrawals of the excess, `null` if not configured",
+        Response::Json("Balance target status"),
+    ),
+    Doc::new(
+        "get_service_status",
+        "Whether new orders and rollovers are accepted, as announced to the takers",
+        Response::Json("Service status"),
+    ),
+    Doc::new(
+        "put_service_status",
+        "Change whether new orders and rollovers are accepted and announce it to the takers",
+        Response::Empty,
+    )
+    .body(Body::Json("Service status")),
+];
+
+/// Generate the specification from the mounted routes.
+pub fn generate() -> Result<Value> {
+    shared_bin::openapi::generate(
+        "Hermes maker",
+        env!("CARGO_PKG_VERSION"),
+        &routes::api_routes(),
+        &[DOCS, shared_bin::openapi::SHARED],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn committed_specification_is_up_to_date() {
+        let generated = generate().unwrap();
+
+        if std::env::var_os(shared_bin::openapi::UPDATE_ENV_VAR).is_some() {
+            let spec = serde_json::to_string_pretty(&generated).unwrap();
+            std::fs::write(
+                concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json"),
+                spec + "\n",
+            )
+            .unwrap();
+            return;
+        }
+
+        let committed = serde_json::from_str::<Value>(SPEC).unwrap();
+        assert!(
+            generated == committed,
+            "openapi.json is outdated, run the tests with {}=1 to regenerate it",
+            shared_bin::openapi::UPDATE_ENV_VAR
+        );
+    }
+}
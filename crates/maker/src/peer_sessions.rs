@@ -0,0 +1,346 @@
+//! Analytics about the takers connecting to the maker.
+//!
+//! Every connection of a taker is recorded as a session, together with the address it connected
+//! from and the daemon version it identified itself with. The sessions are aggregated into daily
+//! active takers, retention and the average session length.
+
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use daemon::identify::dialer::PeerIdentified;
+use model::libp2p::PeerId;
+use model::Timestamp;
+use serde::Serialize;
+use sqlite_db::peer_sessions::PeerSession;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use time::Date;
+use time::OffsetDateTime;
+use xtra_libp2p::endpoint;
+use xtra_productivity::xtra_productivity;
+
+/// Number of days the analytics cover by default
+pub const DEFAULT_DAYS: u32 = 30;
+
+/// Number of days the analytics can cover at most
+pub const MAX_DAYS: u32 = 365;
+
+/// The days after the first connection for which retention is reported
+const RETENTION_DAYS: [i64; 3] = [1, 7, 30];
+
+pub struct Actor {
+    db: sqlite_db::Connection,
+    /// The ongoing session of every connected peer
+    sessions: HashMap<PeerId, i64>,
+}
+
+impl Actor {
+    pub fn new(db: sqlite_db::Connection) -> Self {
+        Self {
+            db,
+            sessions: HashMap::new(),
+        }
+    }
+}
+
+/// Compute the analytics of the last `days` days, including today.
+#[derive(Clone, Copy)]
+pub struct GetAnalytics {
+    pub days: u32,
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle_connection_established(&mut self, msg: endpoint::ConnectionEstablished) {
+        let peer_id = PeerId::from(msg.peer_id);
+        let address = msg.address.to_string();
+
+        match self
+            .db
+            .start_peer_session(peer_id, &address, Timestamp::now())
+            .await
+        {
+            Ok(id) => {
+                self.sessions.insert(peer_id, id);
+            }
+            Err(e) => tracing::warn!(%peer_id, "Failed to record peer session: {e:#}"),
+        }
+    }
+
+    async fn handle_connection_dropped(&mut self, msg: endpoint::ConnectionDropped) {
+        let peer_id = PeerId::from(msg.peer_id);
+
+        let id = match self.sessions.remove(&peer_id) {
+            Some(id) => id,
+            None => return,
+        };
+
+        if let Err(e) = self.db.end_peer_session(id, Timestamp::now()).await {
+            tracing::warn!(%peer_id, "Failed to end peer session: {e:#}");
+        }
+    }
+
+    async fn handle(&mut self, msg: PeerIdentified) {
+        let peer_id = PeerId::from(msg.peer_id);
+
+        let id = match self.sessions.get(&peer_id) {
+            Some(id) => *id,
+            None => return,
+        };
+
+        if let Err(e) = self
+            .db
+            .set_peer_session_version(id, &msg.peer_info.daemon_version)
+            .await
+        {
+            tracing::warn!(%peer_id, "Failed to record daemon version of peer session: {e:#}");
+        }
+    }
+
+    async fn handle(&mut self, msg: GetAnalytics) -> Result<PeerAnalytics> {
+        let now = OffsetDateTime::now_utc();
+        let first_day = first_day(now, msg.days)?;
+        let since = Timestamp::new(first_day.midnight().assume_utc().unix_timestamp());
+
+        let sessions = self.db.load_peer_sessions(since).await?;
+        let first_connections = self.db.load_first_peer_connections().await?;
+
+        PeerAnalytics::new(&sessions, &first_connections, first_day, now)
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, _: &mut xtra::Context<Self>) {
+        // Connections are only tracked while we are running, peers connected before are
+        // considered disconnected at the time we (re)started.
+        match self.db.end_open_peer_sessions(Timestamp::now()).await {
+            Ok(0) => {}
+            Ok(ended) => tracing::info!(%ended, "Ended peer sessions of a previous run"),
+            Err(e) => tracing::warn!("Failed to end peer sessions of a previous run: {e:#}"),
+        }
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PeerAnalytics {
+    pub daily_active_takers: Vec<DailyActiveTakers>,
+    pub retention: Vec<Retention>,
+    /// Average length in seconds of the sessions started in the covered days; ongoing sessions
+    /// count until now
+    pub average_session_length_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DailyActiveTakers {
+    /// UTC day, e.g. `2022-10-24`
+    pub date: String,
+    pub active_takers: usize,
+}
+
+/// Of the takers which connected for the first time in the covered days at least `days` days
+/// ago, the ones that connected again `days` days or more after their first connection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Retention {
+    pub days: i64,
+    pub new_takers: usize,
+    pub retained_takers: usize,
+}
+
+impl PeerAnalytics {
+    fn new(
+        sessions: &[PeerSession],
+        first_connections: &HashMap<PeerId, Timestamp>,
+        first_day: Date,
+        now: OffsetDateTime,
+    ) -> Result<Self> {
+        let today = now.date();
+
+        let mut active_takers = HashMap::<Date, HashSet<PeerId>>::new();
+        let mut last_active = HashMap::<PeerId, Date>::new();
+        let mut session_lengths = Vec::new();
+
+        for session in sessions {
+            let connected_at = to_date_time(session.connected_at)?;
+            let disconnected_at = match session.disconnected_at {
+                Some(disconnected_at) => to_date_time(disconnected_at)?,
+                None => now,
+            };
+
+            let mut day = connected_at.date().max(first_day);
+            while day <= disconnected_at.date().min(today) {
+                active_takers
+                    .entry(day)
+                    .or_default()
+                    .insert(session.peer_id);
+                day = day.next_day().context("Date out of range")?;
+            }
+
+            let last_active = last_active.entry(session.peer_id).or_insert(first_day);
+            *last_active = (*last_active).max(disconnected_at.date());
+
+            if connected_at.date() >= first_day {
+                let length = (disconnected_at - connected_at).whole_seconds().max(0);
+                session_lengths.push(length as u64);
+            }
+        }
+
+        let mut daily_active_takers = Vec::new();
+        let mut day = first_day;
+        while day <= today {
+            daily_active_takers.push(DailyActiveTakers {
+                date: day.to_string(),
+                active_takers: active_takers.get(&day).map_or(0, HashSet::len),
+            });
+            day = day.next_day().context("Date out of range")?;
+        }
+
+        let mut retention = Vec::new();
+        for days in RETENTION_DAYS {
+            let mut new_takers = 0;
+            let mut retained_takers = 0;
+
+            for (peer_id, first_connection) in first_connections {
+                let first_day_of_taker = to_date_time(*first_connection)?.date();
+                let retention_day = first_day_of_taker + time::Duration::days(days);
+
+                if first_day_of_taker < first_day || retention_day > today {
+                    continue;
+                }
+
+                new_takers += 1;
+                if last_active
+                    .get(peer_id)
+                    .map_or(false, |last_active| *last_active >= retention_day)
+                {
+                    retained_takers += 1;
+                }
+            }
+
+            retention.push(Retention {
+                days,
+                new_takers,
+                retained_takers,
+            });
+        }
+
+        let average_session_length_secs = match session_lengths.len() {
+            0 => None,
+            n => Some(session_lengths.iter().sum::<u64>() / n as u64),
+        };
+
+        Ok(Self {
+            daily_active_takers,
+            retention,
+            average_session_length_secs,
+        })
+    }
+}
+
+/// The first day covered by analytics of `days` days, including today.
+fn first_day(now: OffsetDateTime, days: u32) -> Result<Date> {
+    anyhow::ensure!(
+        (1..=MAX_DAYS).contains(&days),
+        "Analytics can cover between 1 and {MAX_DAYS} days"
+    );
+
+    Ok(now.date() - time::Duration::days(i64::from(days) - 1))
+}
+
+fn to_date_time(timestamp: Timestamp) -> Result<OffsetDateTime> {
+    OffsetDateTime::from_unix_timestamp(timestamp.seconds()).context("Timestamp out of range")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn analytics_of_returning_and_one_time_takers() {
+        let now = datetime!(2022-10-10 12:00 UTC);
+        let returning = PeerId::random();
+        let one_time = PeerId::random();
+
+        let sessions = vec![
+            session(
+                returning,
+                datetime!(2022-10-08 23:00 UTC),
+                Some(datetime!(2022-10-09 01:00 UTC)),
+            ),
+            session(
+                one_time,
+                datetime!(2022-10-09 10:00 UTC),
+                Some(datetime!(2022-10-09 10:30 UTC)),
+            ),
+            session(returning, datetime!(2022-10-10 11:00 UTC), None),
+        ];
+        let first_connections = HashMap::from([
+            (returning, timestamp(datetime!(2022-10-08 23:00 UTC))),
+            (one_time, timestamp(datetime!(2022-10-09 10:00 UTC))),
+        ]);
+
+        let analytics = PeerAnalytics::new(
+            &sessions,
+            &first_connections,
+            first_day(now, 3).unwrap(),
+            now,
+        )
+        .unwrap();
+
+        assert_eq!(
+            analytics.daily_active_takers,
+            vec![
+                daily("2022-10-08", 1),
+                daily("2022-10-09", 2),
+                daily("2022-10-10", 1),
+            ]
+        );
+        assert_eq!(
+            analytics.retention[0],
+            Retention {
+                days: 1,
+                new_takers: 2,
+                retained_takers: 1,
+            }
+        );
+        assert_eq!(analytics.average_session_length_secs, Some(4_200));
+    }
+
+    #[test]
+    fn analytics_cover_at_least_one_day() {
+        let now = datetime!(2022-10-10 12:00 UTC);
+
+        assert!(first_day(now, 0).is_err());
+        assert_eq!(first_day(now, 1).unwrap(), now.date());
+    }
+
+    fn session(
+        peer_id: PeerId,
+        connected_at: OffsetDateTime,
+        disconnected_at: Option<OffsetDateTime>,
+    ) -> PeerSession {
+        PeerSession {
+            peer_id,
+            address: "/ip4/127.0.0.1/tcp/10000".to_string(),
+            daemon_version: None,
+            connected_at: timestamp(connected_at),
+            disconnected_at: disconnected_at.map(timestamp),
+        }
+    }
+
+    fn timestamp(date_time: OffsetDateTime) -> Timestamp {
+        Timestamp::new(date_time.unix_timestamp())
+    }
+
+    fn daily(date: &str, active_takers: usize) -> DailyActiveTakers {
+        DailyActiveTakers {
+            date: date.to_string(),
+            active_takers,
+        }
+    }
+}
@@ -0,0 +1,256 @@
+//! Unauthenticated, read-only market data.
+//!
+//! Allows makers to advertise their offers (e.g. on a website) without exposing the authenticated
+//! API. Only mounted if enabled through the command line. Requests are rate-limited per client IP
+//! and cross-origin requests are only allowed for the configured origins.
+
+use daemon::position_metrics::GetMarketStats;
+use daemon::position_metrics::MarketStats;
+use daemon::projection::CfdOffer;
+use daemon::projection::FeedReceivers;
+use daemon::projection::LatestQuotes;
+use daemon::projection::MakerOffers;
+use model::trading_calendar::TradingCalendar;
+use model::ContractSymbol;
+use model::Contracts;
+use model::FundingPeriod;
+use model::Leverage;
+use model::LotSize;
+use model::OfferId;
+use model::Position;
+use model::Price;
+use model::Timestamp;
+use rocket::fairing::AdHoc;
+use rocket::fairing::Fairing;
+use rocket::http::Header;
+use rocket::http::Status;
+use rocket::request::FromRequest;
+use rocket::request::Outcome;
+use rocket::serde::json::Json;
+use rocket::Request;
+use rocket::State;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+use xtra::prelude::MessageChannel;
+
+/// Path the public API is mounted at
+pub const BASE_PATH: &str = "/public";
+
+/// Length of the window in which the number of requests of a client is limited
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PublicOffer {
+    pub id: OfferId,
+    pub contract_symbol: ContractSymbol,
+    pub position_maker: Position,
+    pub price: Price,
+    pub min_quantity: Contracts,
+    pub max_quantity: Contracts,
+    pub max_contracts_per_order: Option<Contracts>,
+    pub trading_calendar: Option<TradingCalendar>,
+    pub lot_size: LotSize,
+    pub leverage_choices: Vec<Leverage>,
+    pub funding_rate_annualized_percent: String,
+    pub funding_period: FundingPeriod,
+    pub creation_timestamp: Timestamp,
+}
+
+impl From<CfdOffer> for PublicOffer {
+    fn from(offer: CfdOffer) -> Self {
+        Self {
+            id: offer.id,
+            contract_symbol: offer.contract_symbol,
+            position_maker: offer.position_maker,
+            price: offer.price,
+            min_quantity: offer.min_quantity,
+            max_quantity: offer.max_quantity,
+            max_contracts_per_order: offer.max_contracts_per_order,
+            trading_calendar: offer.trading_calendar,
+            lot_size: offer.lot_size,
+            leverage_choices: offer
+                .leverage_details
+                .iter()
+                .map(|details| details.leverage)
+                .collect(),
+            funding_rate_annualized_percent: offer.funding_rate_annualized_percent,
+            funding_period: offer.funding_period,
+            creation_timestamp: offer.creation_timestamp,
+        }
+    }
+}
+
+/// The offers of the maker as advertised to the public.
+pub fn public_offers(offers: MakerOffers) -> Vec<PublicOffer> {
+    [
+        offers.btcusd_long,
+        offers.btcusd_short,
+        offers.ethusd_long,
+        offers.ethusd_short,
+    ]
+    .into_iter()
+    .flatten()
+    .map(PublicOffer::from)
+    .collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MarketData {
+    pub offers: Vec<PublicOffer>,
+    pub quotes: LatestQuotes,
+    /// Only reported if enabled through the command line
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<MarketStats>,
+}
+
+/// Where the aggregate market statistics are taken from, if they are reported at all
+pub struct MarketStatsSource(Option<MessageChannel<GetMarketStats, MarketStats>>);
+
+impl MarketStatsSource {
+    pub fn new(channel: Option<MessageChannel<GetMarketStats, MarketStats>>) -> Self {
+        Self(channel)
+    }
+
+    async fn get(&self) -> Option<MarketStats> {
+        let channel = self.0.as_ref()?;
+
+        match channel.send(GetMarketStats).await {
+            Ok(stats) => Some(stats),
+            Err(e) => {
+                tracing::warn!("Failed to get market stats: {e:#}");
+                None
+            }
+        }
+    }
+}
+
+#[rocket::get("/offers")]
+pub async fn get_offers(
+    rx: &State<FeedReceivers>,
+    market_stats: &State<MarketStatsSource>,
+    _rate_limit: RateLimited,
+) -> Json<MarketData> {
+    let stats = market_stats.get().await;
+    let offers = public_offers(rx.offers.borrow().clone());
+    let quotes = rx.quote.borrow().clone();
+
+    Json(MarketData {
+        offers,
+        quotes,
+        stats,
+    })
+}
+
+/// Answers CORS preflight requests, the headers are added by the [`cors`] fairing.
+#[rocket::options("/offers")]
+pub fn options_offers() {}
+
+/// Limits the number of requests a client can make within [`RATE_LIMIT_WINDOW`]
+pub struct RateLimiter {
+    max_requests: u32,
+    windows: Mutex<HashMap<Option<IpAddr>, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    pub fn new(max_requests_per_minute: u32) -> Self {
+        Self {
+            max_requests: max_requests_per_minute,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records a request of the client and returns whether it is within the limit.
+    fn check(&self, client: Option<IpAddr>, now: Instant) -> bool {
+        let mut windows = self.windows.lock().expect("lock not to be poisoned");
+
+        // Forget about clients whose window expired to bound memory usage
+        windows.retain(|_, (start, _)| now.duration_since(*start) < RATE_LIMIT_WINDOW);
+
+        let (_, count) = windows.entry(client).or_insert((now, 0));
+        *count += 1;
+
+        *count <= self.max_requests
+    }
+}
+
+/// Request guard that fails with `429 Too Many Requests` if the client exceeded the rate limit
+pub struct RateLimited;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RateLimited {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let rate_limiter = match request.rocket().state::<RateLimiter>() {
+            Some(rate_limiter) => rate_limiter,
+            None => return Outcome::Failure((Status::InternalServerError, ())),
+        };
+
+        if !rate_limiter.check(request.client_ip(), Instant::now()) {
+            return Outcome::Failure((Status::TooManyRequests, ()));
+        }
+
+        Outcome::Success(RateLimited)
+    }
+}
+
+/// Attach this fairing to allow cross-origin requests to the public API from the given origins
+///
+/// An origin of `*` allows requests from any origin.
+pub fn cors(allowed_origins: Vec<String>) -> impl Fairing {
+    AdHoc::on_response("CORS for public API", move |request, response| {
+        let allowed_origins = allowed_origins.clone();
+
+        Box::pin(async move {
+            if !request.uri().path().starts_with(BASE_PATH) {
+                return;
+            }
+
+            let origin = match request.headers().get_one("Origin") {
+                Some(origin) => origin,
+                None => return,
+            };
+
+            let allow_origin = if allowed_origins.iter().any(|allowed| allowed == "*") {
+                "*"
+            } else if allowed_origins.iter().any(|allowed| allowed == origin) {
+                origin
+            } else {
+                return;
+            };
+
+            response.set_header(Header::new(
+                "Access-Control-Allow-Origin",
+                allow_origin.to_owned(),
+            ));
+            response.set_header(Header::new("Access-Control-Allow-Methods", "GET, OPTIONS"));
+            response.set_header(Header::new("Vary", "Origin"));
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn given_too_many_requests_within_window_then_rate_limited_until_window_expired() {
+        let rate_limiter = RateLimiter::new(2);
+        let client = Some(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let other_client = Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+        let now = Instant::now();
+
+        assert!(rate_limiter.check(client, now));
+        assert!(rate_limiter.check(client, now));
+        assert!(!rate_limiter.check(client, now));
+
+        assert!(rate_limiter.check(other_client, now));
+
+        assert!(rate_limiter.check(client, now + RATE_LIMIT_WINDOW));
+    }
+}
@@ -1,5 +1,20 @@
 #![allow(clippy::let_unit_value)] // see: https://github.com/SergioBenitez/Rocket/issues/2211
 use crate::actor_system::ActorSystem;
+use crate::actor_system::OfferProtocolUsage;
+use crate::aggregator;
+use crate::balance_target;
+use crate::close_all;
+use crate::collateral_forecast::Forecast;
+use crate::collateral_forecast::Lockup;
+use crate::collateral_forecast::Overcommitted;
+use crate::deposit_watch_list;
+use crate::peer_sessions;
+use crate::peer_sessions::PeerAnalytics;
+use crate::shadow_pricing;
+use crate::volatility_spread::VolatilitySpread;
+use crate::volatility_spread::VolatilitySpreads;
+use crate::wind_down;
+use crate::withdrawal;
 use anyhow::Result;
 use bdk::sled;
 use daemon::bdk::blockchain::ElectrumBlockchain;
@@ -7,16 +22,23 @@ use daemon::oracle;
 use daemon::projection::Cfd;
 use daemon::projection::CfdAction;
 use daemon::projection::FeedReceivers;
+use daemon::service_status::ServiceStatus;
+use daemon::settlement_proposal;
 use daemon::wallet;
 use http_api_problem::HttpApiProblem;
 use http_api_problem::StatusCode;
+use model::libp2p::PeerId;
+use model::trading_calendar::TradingCalendar;
 use model::Contracts;
+use model::FundingPeriod;
 use model::FundingRate;
 use model::Leverage;
 use model::LotSize;
 use model::OpeningFee;
+use model::OpeningFeeTiers;
 use model::OrderId;
 use model::Price;
+use model::Timestamp;
 use model::TxFeeRate;
 use model::WalletInfo;
 use rocket::http::ContentType;
@@ -31,7 +53,10 @@ use rocket_cookie_auth::user::User;
 use rust_embed::RustEmbed;
 use rust_embed_rocket::EmbeddedFileExt;
 use serde::Deserialize;
+use serde::Serialize;
 use shared_bin::ToSseEvent;
+use sqlite_db::expected_deposits::ExpectedDeposit;
+use sqlite_db::offer_history::OfferRecord;
 use std::borrow::Cow;
 use std::path::PathBuf;
 use tokio::select;
@@ -46,6 +71,7 @@ pub type Maker = ActorSystem<oracle::Actor, wallet::Actor<ElectrumBlockchain, sl
 pub async fn maker_feed(
     rx: &State<FeedReceivers>,
     rx_wallet: &State<watch::Receiver<Option<WalletInfo>>>,
+    rx_expected_deposits: &State<watch::Receiver<Vec<ExpectedDeposit>>>,
     _user: User,
 ) -> EventStream![] {
     let rx = rx.inner();
@@ -53,6 +79,8 @@ pub async fn maker_feed(
     let mut rx_wallet = rx_wallet.inner().clone();
     let mut rx_offers = rx.offers.clone();
     let mut rx_quote = rx.quote.clone();
+    let mut rx_chain_tip = rx.chain_tip.clone();
+    let mut rx_expected_deposits = rx_expected_deposits.inner().clone();
 
     EventStream! {
         let wallet_info = rx_wallet.borrow().clone();
@@ -73,6 +101,12 @@ pub async fn maker_feed(
             yield cfds.to_sse_event()
         }
 
+        let chain_tip = *rx_chain_tip.borrow();
+        yield chain_tip.to_sse_event();
+
+        let expected_deposits = rx_expected_deposits.borrow().clone();
+        yield Event::json(&expected_deposits).event("expected_deposits");
+
         loop{
             select! {
                 Ok(()) = rx_wallet.changed() => {
@@ -97,6 +131,14 @@ pub async fn maker_feed(
                     yield Event::json(&quote.get(&model::ContractSymbol::BtcUsd)).event("btcusd_quote");
                     yield Event::json(&quote.get(&model::ContractSymbol::EthUsd)).event("ethusd_quote");
                 }
+                Ok(()) = rx_chain_tip.changed() => {
+                    let chain_tip = *rx_chain_tip.borrow();
+                    yield chain_tip.to_sse_event();
+                }
+                Ok(()) = rx_expected_deposits.changed() => {
+                    let expected_deposits = rx_expected_deposits.borrow().clone();
+                    yield Event::json(&expected_deposits).event("expected_deposits");
+                }
             }
         }
     }
@@ -109,18 +151,30 @@ pub struct CfdNewOfferParamsRequest {
     pub price_short: Option<Price>,
     pub min_quantity: Contracts,
     pub max_quantity: Contracts,
+    /// Largest quantity a single order may have, not capped if not specified
+    #[serde(default)]
+    pub max_contracts_per_order: Option<Contracts>,
     /// The current _daily_ funding rate for the maker's long position
     pub daily_funding_rate_long: FundingRate,
     /// The current _daily_ funding rate for the maker's short position
     pub daily_funding_rate_short: FundingRate,
+    /// Determines when funding fees are charged upon rollover, defaults to hourly
+    #[serde(default)]
+    pub funding_period: FundingPeriod,
     pub tx_fee_rate: TxFeeRate,
     // TODO: This is not inline with other parts of the API! We should not expose internal types
     // here. We have to specify sats for here because of that.
     pub opening_fee: OpeningFee,
+    /// Opening fees depending on the quantity, `opening_fee` applies if empty
+    #[serde(default)]
+    pub opening_fee_tiers: OpeningFeeTiers,
     #[serde(default = "empty_leverage")]
     pub leverage_choices: Vec<Leverage>,
     #[serde(default = "default_lot_size")]
     pub lot_size: LotSize,
+    /// Weekly sessions in UTC during which orders are accepted, always if not specified
+    #[serde(default)]
+    pub trading_calendar: Option<TradingCalendar>,
 }
 
 fn empty_leverage() -> Vec<Leverage> {
@@ -138,30 +192,42 @@ pub async fn put_offer_params(
     offer_params: Json<CfdNewOfferParamsRequest>,
     maker: &State<Maker>,
     _user: User,
-) -> Result<(), HttpApiProblem> {
+) -> Result<Json<Lockup>, HttpApiProblem> {
     tracing::warn!("Deprecated /offer was called. Please use /<contract_symbol>/offer from now.");
-    maker
+    let lockup = maker
         .set_offer_params(
             offer_params.price_long,
             offer_params.price_short,
             offer_params.min_quantity,
             offer_params.max_quantity,
+            offer_params.max_contracts_per_order,
             offer_params.tx_fee_rate,
             offer_params.daily_funding_rate_long,
             offer_params.daily_funding_rate_short,
+            offer_params.funding_period,
             offer_params.opening_fee,
+            offer_params.opening_fee_tiers.clone(),
             offer_params.leverage_choices.clone(),
             ContractSymbol::BtcUsd.into(),
             offer_params.lot_size,
+            offer_params.trading_calendar.clone(),
         )
         .await
-        .map_err(|e| {
-            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
-                .title("Posting offer failed")
-                .detail(format!("{e:#}"))
-        })?;
+        .map_err(offer_problem)?;
 
-    Ok(())
+    Ok(Json(lockup))
+}
+
+fn offer_problem(e: anyhow::Error) -> HttpApiProblem {
+    if e.downcast_ref::<Overcommitted>().is_some() {
+        return HttpApiProblem::new(StatusCode::CONFLICT)
+            .title("Offers exceed available balance")
+            .detail(format!("{e:#}"));
+    }
+
+    HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+        .title("Posting offer failed")
+        .detail(format!("{e:#}"))
 }
 
 #[derive(Debug, Copy, Clone, strum_macros::Display)]
@@ -198,31 +264,79 @@ pub async fn put_offer_params_for_symbol(
     offer_params: Json<CfdNewOfferParamsRequest>,
     maker: &State<Maker>,
     _user: User,
-) -> Result<(), HttpApiProblem> {
+) -> Result<Json<Lockup>, HttpApiProblem> {
     // if we use `ContractSymbol` as arg directly the error gets lost. So we need to do this:
     let symbol = symbol.map_err(|e| {
         HttpApiProblem::new(StatusCode::BAD_REQUEST)
             .title("Unknown ContractSymbol provided")
             .detail(format!("{e:#}"))
     })?;
-    maker
+    let lockup = maker
         .set_offer_params(
             offer_params.price_long,
             offer_params.price_short,
             offer_params.min_quantity,
             offer_params.max_quantity,
+            offer_params.max_contracts_per_order,
             offer_params.tx_fee_rate,
             offer_params.daily_funding_rate_long,
             offer_params.daily_funding_rate_short,
+            offer_params.funding_period,
             offer_params.opening_fee,
+            offer_params.opening_fee_tiers.clone(),
             offer_params.leverage_choices.clone(),
             symbol.into(),
             offer_params.lot_size,
+            offer_params.trading_calendar.clone(),
         )
         .await
+        .map_err(offer_problem)?;
+
+    Ok(Json(lockup))
+}
+
+/// The maker PATCHes this to partially update the live offers of a contract symbol
+///
+/// Omitted fields are left untouched. The offers keep their ids, orders referring to the previous
+/// revision of an offer are rejected.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CfdOfferUpdateRequest {
+    #[serde(default)]
+    pub leverage_choices: Option<Vec<Leverage>>,
+    #[serde(default)]
+    pub lot_size: Option<LotSize>,
+}
+
+#[rocket::patch("/<symbol>/offer", data = "<offer_update>")]
+#[instrument(name = "PATCH /offer", skip(maker, _user), err)]
+pub async fn patch_offer_params_for_symbol(
+    symbol: Result<ContractSymbol>,
+    offer_update: Json<CfdOfferUpdateRequest>,
+    maker: &State<Maker>,
+    _user: User,
+) -> Result<(), HttpApiProblem> {
+    let symbol = symbol.map_err(|e| {
+        HttpApiProblem::new(StatusCode::BAD_REQUEST)
+            .title("Unknown ContractSymbol provided")
+            .detail(format!("{e:#}"))
+    })?;
+    let CfdOfferUpdateRequest {
+        leverage_choices,
+        lot_size,
+    } = offer_update.into_inner();
+
+    if matches!(&leverage_choices, Some(leverage_choices) if leverage_choices.is_empty()) {
+        return Err(HttpApiProblem::new(StatusCode::BAD_REQUEST)
+            .title("Updating offer failed")
+            .detail("At least one leverage choice is required"));
+    }
+
+    maker
+        .revise_offers(symbol.into(), leverage_choices, lot_size)
+        .await
         .map_err(|e| {
             HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
-                .title("Posting offer failed")
+                .title("Updating offer failed")
                 .detail(format!("{e:#}"))
         })?;
 
@@ -230,11 +344,16 @@ pub async fn put_offer_params_for_symbol(
 }
 
 #[rocket::post("/cfd/<order_id>/<action>")]
-#[instrument(name = "POST /cfd/<order_id>/<action>", skip(maker, _user), err)]
+#[instrument(
+    name = "POST /cfd/<order_id>/<action>",
+    skip(maker, settlement_proposal, _user),
+    err
+)]
 pub async fn post_cfd_action(
     order_id: Uuid,
     action: String,
     maker: &State<Maker>,
+    settlement_proposal: &State<xtra::Address<settlement_proposal::maker::Actor>>,
     _user: User,
 ) -> Result<(), HttpApiProblem> {
     let order_id = OrderId::from(order_id);
@@ -248,9 +367,17 @@ pub async fn post_cfd_action(
         CfdAction::AcceptSettlement => maker.accept_settlement(order_id).await,
         CfdAction::RejectSettlement => maker.reject_settlement(order_id).await,
         CfdAction::Commit => maker.commit(order_id).await,
-        CfdAction::Settle => {
+        // The maker cannot settle itself, but can propose the taker to settle
+        CfdAction::Settle => match settlement_proposal
+            .send(settlement_proposal::maker::ProposeSettlement { order_id })
+            .await
+        {
+            Ok(res) => res,
+            Err(e) => Err(anyhow::Error::new(e)),
+        },
+        CfdAction::RollOver => {
             return Err(HttpApiProblem::new(StatusCode::BAD_REQUEST)
-                .detail("Collaborative settlement can only be triggered by taker"));
+                .detail("Rollover can only be triggered by taker"));
         }
     };
 
@@ -263,6 +390,48 @@ pub async fn post_cfd_action(
     Ok(())
 }
 
+/// Attach a user-defined tag to the CFD.
+#[rocket::put("/cfd/<order_id>/tags/<tag>")]
+#[instrument(name = "PUT /cfd/<order_id>/tags/<tag>", skip(maker, _user), err)]
+pub async fn put_cfd_tag(
+    order_id: Uuid,
+    tag: String,
+    maker: &State<Maker>,
+    _user: User,
+) -> Result<(), HttpApiProblem> {
+    maker
+        .add_cfd_tag(OrderId::from(order_id), tag)
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::BAD_REQUEST)
+                .title("Could not tag CFD")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(())
+}
+
+/// Remove a tag from the CFD.
+#[rocket::delete("/cfd/<order_id>/tags/<tag>")]
+#[instrument(name = "DELETE /cfd/<order_id>/tags/<tag>", skip(maker, _user), err)]
+pub async fn delete_cfd_tag(
+    order_id: Uuid,
+    tag: String,
+    maker: &State<Maker>,
+    _user: User,
+) -> Result<(), HttpApiProblem> {
+    maker
+        .remove_cfd_tag(OrderId::from(order_id), tag)
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Could not remove tag from CFD")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(())
+}
+
 #[derive(RustEmbed)]
 #[folder = "../../maker-frontend/dist/maker"]
 struct Asset;
@@ -293,9 +462,11 @@ pub async fn put_sync_wallet(maker: &State<Maker>, _user: User) -> Result<(), Ht
     Ok(())
 }
 
-#[rocket::get("/cfds")]
-#[instrument(name = "GET /cfds", skip_all, err)]
+/// All CFDs, only those with the given tag if specified.
+#[rocket::get("/cfds?<tag>")]
+#[instrument(name = "GET /cfds", skip(rx, _user), err)]
 pub async fn get_cfds<'r>(
+    tag: Option<String>,
     rx: &State<FeedReceivers>,
     _user: User,
 ) -> Result<Json<Vec<Cfd>>, HttpApiProblem> {
@@ -304,13 +475,144 @@ pub async fn get_cfds<'r>(
     let cfds = rx_cfds.borrow().clone();
 
     match cfds {
-        Some(cfds) => Ok(Json(cfds)),
+        Some(cfds) => Ok(Json(
+            cfds.into_iter()
+                .filter(|cfd| tag.as_ref().map_or(true, |tag| cfd.tags.contains(tag)))
+                .collect(),
+        )),
         None => Err(HttpApiProblem::new(StatusCode::SERVICE_UNAVAILABLE)
             .title("CFDs not yet available")
             .detail("CFDs are still being loaded from the database. Please retry later.")),
     }
 }
 
+/// The connected takers using each version of the offer protocol.
+#[rocket::get("/peers/offer-protocol")]
+#[instrument(name = "GET /peers/offer-protocol", skip_all, err)]
+pub async fn get_offer_protocol_usage(
+    maker: &State<Maker>,
+    _user: User,
+) -> Result<Json<OfferProtocolUsage>, HttpApiProblem> {
+    let usage = maker.offer_protocol_usage().await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Could not determine offer protocol usage")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(usage))
+}
+
+/// The offer revisions published between the unix timestamps `from` (inclusive) and `to`
+/// (exclusive), oldest first.
+///
+/// Without bounds the complete offer history is exported.
+#[rocket::get("/offers/history?<from>&<to>")]
+#[instrument(name = "GET /offers/history", skip(maker, _user), err)]
+pub async fn get_offer_history(
+    from: Option<i64>,
+    to: Option<i64>,
+    maker: &State<Maker>,
+    _user: User,
+) -> Result<Json<Vec<OfferRecord>>, HttpApiProblem> {
+    let from = Timestamp::new(from.unwrap_or(0));
+    let to = Timestamp::new(to.unwrap_or(i64::MAX));
+
+    let history = maker.offer_history(from, to).await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Failed to load offer history")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(history))
+}
+
+/// Daily active takers, retention and average session length of the last `days` days.
+#[rocket::get("/analytics/peers?<days>")]
+#[instrument(name = "GET /analytics/peers", skip(maker, _user), err)]
+pub async fn get_peer_analytics(
+    days: Option<u32>,
+    maker: &State<Maker>,
+    _user: User,
+) -> Result<Json<PeerAnalytics>, HttpApiProblem> {
+    let days = days.unwrap_or(peer_sessions::DEFAULT_DAYS);
+    if !(1..=peer_sessions::MAX_DAYS).contains(&days) {
+        return Err(HttpApiProblem::new(StatusCode::BAD_REQUEST).detail(format!(
+            "days must be between 1 and {}",
+            peer_sessions::MAX_DAYS
+        )));
+    }
+
+    let analytics = maker.peer_analytics(days).await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Failed to compute peer analytics")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(analytics))
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct OfferAnalytics {
+    pub contract_symbol: model::ContractSymbol,
+    #[serde(flatten)]
+    pub volatility_spread: VolatilitySpread,
+}
+
+/// Realized volatility of the quotes and the spread derived from it per contract symbol.
+#[rocket::get("/analytics/offers")]
+#[instrument(name = "GET /analytics/offers", skip_all)]
+pub async fn get_offer_analytics(
+    volatility_spreads: &State<watch::Receiver<VolatilitySpreads>>,
+    _user: User,
+) -> Json<Vec<OfferAnalytics>> {
+    let mut analytics = volatility_spreads
+        .borrow()
+        .iter()
+        .map(|(contract_symbol, volatility_spread)| OfferAnalytics {
+            contract_symbol: *contract_symbol,
+            volatility_spread: *volatility_spread,
+        })
+        .collect::<Vec<_>>();
+    analytics.sort_by_key(|analytics| analytics.contract_symbol.to_string());
+
+    Json(analytics)
+}
+
+/// The collateral required if all published offers were taken at their maximum quantity.
+#[rocket::get("/analytics/collateral")]
+#[instrument(name = "GET /analytics/collateral", skip_all)]
+pub async fn get_collateral_forecast(
+    forecast: &State<watch::Receiver<Forecast>>,
+    _user: User,
+) -> Json<Forecast> {
+    Json(forecast.borrow().clone())
+}
+
+/// Comparison of the candidate pricing configuration run in shadow mode with the published offers,
+/// `null` if shadow mode is off.
+#[rocket::get("/analytics/shadow-pricing")]
+#[instrument(name = "GET /analytics/shadow-pricing", skip_all, err)]
+pub async fn get_shadow_pricing(
+    shadow_pricing: &State<Option<xtra::Address<shadow_pricing::Actor>>>,
+    _user: User,
+) -> Result<Json<Option<shadow_pricing::Report>>, HttpApiProblem> {
+    let shadow_pricing = match shadow_pricing.inner() {
+        Some(shadow_pricing) => shadow_pricing,
+        None => return Ok(Json(None)),
+    };
+
+    let report = shadow_pricing
+        .send(shadow_pricing::GetReport)
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Failed to get shadow pricing report")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(Some(report)))
+}
+
 #[derive(Debug, Clone, Copy, Deserialize)]
 pub struct RolloverConfig {
     is_accepting_rollovers: bool,
@@ -334,3 +636,489 @@ pub async fn update_rollover_configuration(
 
     Ok(())
 }
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExpectedDepositRequest {
+    address: bdk::bitcoin::Address,
+    /// Accept any amount if not set
+    #[serde(default, with = "bdk::bitcoin::util::amount::serde::as_sat::opt")]
+    amount: Option<bdk::bitcoin::Amount>,
+}
+
+/// Add a deposit the maker expects from a client to the watch-list.
+///
+/// Once a confirmed transaction pays to the address, the deposit is marked as received and its
+/// ledger entry carries the `label`.
+#[rocket::put("/watch-list/<label>", data = "<request>")]
+#[instrument(name = "PUT /watch-list/<label>", skip(deposit_watch_list, _user), err)]
+pub async fn put_expected_deposit(
+    label: &str,
+    request: Json<ExpectedDepositRequest>,
+    deposit_watch_list: &State<xtra::Address<deposit_watch_list::Actor>>,
+    _user: User,
+) -> Result<(), HttpApiProblem> {
+    let ExpectedDepositRequest { address, amount } = request.into_inner();
+
+    deposit_watch_list
+        .send(deposit_watch_list::AddExpectedDeposit {
+            label: label.to_owned(),
+            address,
+            amount,
+        })
+        .await
+        .map_err(anyhow::Error::new)
+        .and_then(|result| result)
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::BAD_REQUEST)
+                .title("Could not add expected deposit")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(())
+}
+
+#[rocket::delete("/watch-list/<label>")]
+#[instrument(
+    name = "DELETE /watch-list/<label>",
+    skip(deposit_watch_list, _user),
+    err
+)]
+pub async fn delete_expected_deposit(
+    label: &str,
+    deposit_watch_list: &State<xtra::Address<deposit_watch_list::Actor>>,
+    _user: User,
+) -> Result<(), HttpApiProblem> {
+    deposit_watch_list
+        .send(deposit_watch_list::RemoveExpectedDeposit {
+            label: label.to_owned(),
+        })
+        .await
+        .map_err(anyhow::Error::new)
+        .and_then(|result| result)
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::BAD_REQUEST)
+                .title("Could not remove expected deposit")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(())
+}
+
+#[rocket::get("/watch-list")]
+#[instrument(name = "GET /watch-list", skip_all)]
+pub async fn get_expected_deposits(
+    rx_expected_deposits: &State<watch::Receiver<Vec<ExpectedDeposit>>>,
+    _user: User,
+) -> Json<Vec<ExpectedDeposit>> {
+    Json(rx_expected_deposits.borrow().clone())
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WithdrawRequest {
+    address: bdk::bitcoin::Address,
+    /// Withdraw the whole balance if not set
+    #[serde(default, with = "bdk::bitcoin::util::amount::serde::as_btc::opt")]
+    amount: Option<bdk::bitcoin::Amount>,
+    /// Fee rate in satoshis per vbyte
+    fee: f32,
+}
+
+#[rocket::post("/withdraw", data = "<request>")]
+#[instrument(name = "POST /withdraw", skip(withdrawal, _user), err)]
+pub async fn post_withdraw_request(
+    request: Json<WithdrawRequest>,
+    withdrawal: &State<xtra::Address<withdrawal::Actor>>,
+    _user: User,
+) -> Result<Json<withdrawal::WithdrawalOutcome>, HttpApiProblem> {
+    let WithdrawRequest {
+        address,
+        amount,
+        fee,
+    } = request.into_inner();
+
+    let outcome = withdrawal
+        .send(withdrawal::RequestWithdrawal {
+            amount,
+            address,
+            fee_rate: fee,
+        })
+        .await
+        .map_err(anyhow::Error::new)
+        .and_then(|result| result)
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Could not proceed with withdraw request")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(outcome))
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApproveWithdrawalRequest {
+    /// One-time password of the provisioned TOTP secret
+    code: String,
+}
+
+#[rocket::post("/withdraw/<id>/approve", data = "<request>")]
+#[instrument(
+    name = "POST /withdraw/<id>/approve",
+    skip(request, withdrawal, _user),
+    err
+)]
+pub async fn post_approve_withdrawal(
+    id: i64,
+    request: Json<ApproveWithdrawalRequest>,
+    withdrawal: &State<xtra::Address<withdrawal::Actor>>,
+    _user: User,
+) -> Result<String, HttpApiProblem> {
+    let txid = withdrawal
+        .send(withdrawal::ApproveWithdrawal {
+            id,
+            code: request.into_inner().code,
+        })
+        .await
+        .map_err(anyhow::Error::new)
+        .and_then(|result| result)
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::BAD_REQUEST)
+                .title("Could not approve withdrawal")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(txid.to_string())
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProvisionTotpRequest {
+    /// One-time password of the current secret, required to replace it
+    code: Option<String>,
+}
+
+#[rocket::post("/withdraw/totp", data = "<request>")]
+#[instrument(name = "POST /withdraw/totp", skip_all, err)]
+pub async fn post_provision_totp(
+    request: Json<ProvisionTotpRequest>,
+    withdrawal: &State<xtra::Address<withdrawal::Actor>>,
+    _user: User,
+) -> Result<Json<withdrawal::TotpProvisioning>, HttpApiProblem> {
+    let provisioning = withdrawal
+        .send(withdrawal::ProvisionTotp {
+            code: request.into_inner().code,
+        })
+        .await
+        .map_err(anyhow::Error::new)
+        .and_then(|result| result)
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::BAD_REQUEST)
+                .title("Could not provision TOTP secret")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(provisioning))
+}
+
+/// All withdrawal requests and their audit log.
+#[rocket::get("/withdrawals")]
+#[instrument(name = "GET /withdrawals", skip_all, err)]
+pub async fn get_withdrawals(
+    withdrawal: &State<xtra::Address<withdrawal::Actor>>,
+    _user: User,
+) -> Result<Json<withdrawal::Withdrawals>, HttpApiProblem> {
+    let withdrawals = withdrawal
+        .send(withdrawal::GetWithdrawals)
+        .await
+        .map_err(anyhow::Error::new)
+        .and_then(|result| result)
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Failed to load withdrawals")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(withdrawals))
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct WindDownRequest {
+    /// How long takers have to settle collaboratively before their CFDs are committed
+    grace_period_secs: u64,
+}
+
+#[rocket::post("/wind-down", data = "<request>")]
+#[instrument(name = "POST /wind-down", skip(wind_down, _user), err)]
+pub async fn post_wind_down(
+    request: Json<WindDownRequest>,
+    wind_down: &State<xtra::Address<wind_down::Actor>>,
+    _user: User,
+) -> Result<(), HttpApiProblem> {
+    let grace_period = time::Duration::seconds(request.grace_period_secs as i64);
+
+    wind_down
+        .send(wind_down::Start { grace_period })
+        .await
+        .map_err(anyhow::Error::new)
+        .and_then(|result| result)
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Starting wind-down failed")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(())
+}
+
+#[rocket::get("/wind-down")]
+#[instrument(name = "GET /wind-down", skip_all)]
+pub async fn get_wind_down(
+    status: &State<watch::Receiver<wind_down::Status>>,
+    _user: User,
+) -> Json<wind_down::Status> {
+    Json(status.borrow().clone())
+}
+
+fn parse_peer_id(peer_id: &str) -> Result<PeerId, HttpApiProblem> {
+    peer_id.parse().map_err(|e: anyhow::Error| {
+        HttpApiProblem::new(StatusCode::BAD_REQUEST)
+            .title("Invalid peer id")
+            .detail(format!("{e:#}"))
+    })
+}
+
+/// Ask the taker to settle all open CFDs with the maker and accept its settlement proposals for
+/// `grace_period_secs`, one hour by default.
+#[rocket::post("/peers/<peer_id>/close-all?<grace_period_secs>")]
+#[instrument(name = "POST /peers/<peer_id>/close-all", skip(close_all, _user), err)]
+pub async fn post_close_all(
+    peer_id: &str,
+    grace_period_secs: Option<u64>,
+    close_all: &State<xtra::Address<close_all::Actor>>,
+    _user: User,
+) -> Result<Json<close_all::Session>, HttpApiProblem> {
+    let peer_id = parse_peer_id(peer_id)?;
+    let grace_period = grace_period_secs
+        .map(|secs| time::Duration::seconds(secs as i64))
+        .unwrap_or(close_all::DEFAULT_GRACE_PERIOD);
+
+    let session = close_all
+        .send(close_all::Start {
+            peer_id,
+            grace_period,
+        })
+        .await
+        .map_err(anyhow::Error::new)
+        .and_then(|result| result)
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Closing all CFDs with peer failed")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(session))
+}
+
+#[rocket::get("/peers/<peer_id>/close-all")]
+#[instrument(name = "GET /peers/<peer_id>/close-all", skip(close_all, _user), err)]
+pub async fn get_close_all(
+    peer_id: &str,
+    close_all: &State<xtra::Address<close_all::Actor>>,
+    _user: User,
+) -> Result<Json<close_all::Session>, HttpApiProblem> {
+    let peer_id = parse_peer_id(peer_id)?;
+
+    let session = close_all
+        .send(close_all::GetSession { peer_id })
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Failed to load close-all session")
+                .detail(format!("{e:#}"))
+        })?
+        .ok_or_else(|| {
+            HttpApiProblem::new(StatusCode::NOT_FOUND)
+                .title("No close-all session with peer")
+                .detail(format!(
+                    "All CFDs with {peer_id} were never asked to be closed"
+                ))
+        })?;
+
+    Ok(Json(session))
+}
+
+/// The status of the publication to the market aggregator, `null` if no aggregator is configured.
+#[rocket::get("/aggregator")]
+#[instrument(name = "GET /aggregator", skip(aggregator, _user), err)]
+pub async fn get_aggregator(
+    aggregator: &State<Option<xtra::Address<aggregator::Actor>>>,
+    _user: User,
+) -> Result<Json<Option<aggregator::Status>>, HttpApiProblem> {
+    let aggregator = match aggregator.inner() {
+        Some(aggregator) => aggregator,
+        None => return Ok(Json(None)),
+    };
+
+    let status = aggregator.send(aggregator::GetStatus).await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Failed to get aggregator status")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(Some(status)))
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct AggregatorRequest {
+    pub enabled: bool,
+}
+
+/// Start or stop publishing the offers to the market aggregator.
+#[rocket::put("/aggregator", data = "<request>")]
+#[instrument(name = "PUT /aggregator", skip(aggregator, _user), err)]
+pub async fn put_aggregator(
+    request: Json<AggregatorRequest>,
+    aggregator: &State<Option<xtra::Address<aggregator::Actor>>>,
+    _user: User,
+) -> Result<(), HttpApiProblem> {
+    let aggregator = aggregator.inner().as_ref().ok_or_else(|| {
+        HttpApiProblem::new(StatusCode::NOT_FOUND).title("No market aggregator configured")
+    })?;
+
+    aggregator
+        .send(aggregator::SetEnabled(request.enabled))
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Failed to update aggregator publication")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(())
+}
+
+/// The target balance of the wallet and the withdrawals of the excess, `null` if no target is
+/// configured.
+#[rocket::get("/balance-target")]
+#[instrument(name = "GET /balance-target", skip_all, err)]
+pub async fn get_balance_target(
+    balance_target: &State<Option<xtra::Address<balance_target::Actor>>>,
+    _user: User,
+) -> Result<Json<Option<balance_target::Status>>, HttpApiProblem> {
+    let balance_target = match balance_target.inner() {
+        Some(balance_target) => balance_target,
+        None => return Ok(Json(None)),
+    };
+
+    let status = balance_target
+        .send(balance_target::GetStatus)
+        .await
+        .map_err(anyhow::Error::new)
+        .and_then(|result| result)
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Failed to get balance target status")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(Some(status)))
+}
+
+/// Whether new orders and rollovers are accepted, as announced to the connected takers.
+#[rocket::get("/service-status")]
+#[instrument(name = "GET /service-status", skip_all, err)]
+pub async fn get_service_status(
+    maker: &State<Maker>,
+    _user: User,
+) -> Result<Json<ServiceStatus>, HttpApiProblem> {
+    let status = maker.service_status().await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Failed to get service status")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(status))
+}
+
+/// Change whether new orders and rollovers are accepted and announce the status to the connected
+/// takers.
+#[rocket::put("/service-status", data = "<status>")]
+#[instrument(name = "PUT /service-status", skip(maker, _user), err)]
+pub async fn put_service_status(
+    status: Json<ServiceStatus>,
+    maker: &State<Maker>,
+    _user: User,
+) -> Result<(), HttpApiProblem> {
+    let status = status.into_inner();
+
+    status.validate().map_err(|e| {
+        HttpApiProblem::new(StatusCode::BAD_REQUEST)
+            .title("Invalid service status")
+            .detail(format!("{e:#}"))
+    })?;
+
+    maker.update_service_status(status).await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Failed to update service status")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(())
+}
+
+/// All routes mounted at `/api`, see [`crate::openapi`] for their documentation.
+pub fn api_routes() -> Vec<rocket::Route> {
+    rocket::routes![
+        maker_feed,
+        put_offer_params,
+        put_offer_params_for_symbol,
+        patch_offer_params_for_symbol,
+        post_cfd_action,
+        put_cfd_tag,
+        delete_cfd_tag,
+        get_cfds,
+        put_sync_wallet,
+        post_wind_down,
+        get_wind_down,
+        post_close_all,
+        get_close_all,
+        get_offer_protocol_usage,
+        get_offer_history,
+        get_peer_analytics,
+        get_offer_analytics,
+        get_collateral_forecast,
+        get_shadow_pricing,
+        post_withdraw_request,
+        post_approve_withdrawal,
+        post_provision_totp,
+        get_withdrawals,
+        put_expected_deposit,
+        delete_expected_deposit,
+        get_expected_deposits,
+        get_aggregator,
+        put_aggregator,
+        get_balance_target,
+        get_service_status,
+        put_service_status,
+        shared_bin::routes::get_alive,
+        shared_bin::routes::get_health_check,
+        shared_bin::routes::get_metrics,
+        shared_bin::routes::get_version,
+        shared_bin::routes::get_electrum_status,
+        shared_bin::routes::get_ledger,
+        shared_bin::routes::get_events,
+        shared_bin::routes::get_cfd_timeline,
+        shared_bin::routes::get_telemetry,
+        shared_bin::routes::get_daily_marks,
+        shared_bin::routes::get_jobs,
+        shared_bin::routes::get_janitor_report,
+        shared_bin::routes::get_supervision_tree,
+        shared_bin::routes::get_cfd_backup,
+        shared_bin::routes::put_transcript,
+        shared_bin::routes::delete_transcript,
+        shared_bin::routes::get_transcripts,
+        shared_bin::routes::change_password,
+        shared_bin::routes::logout,
+        shared_bin::routes::is_authenticated,
+        shared_bin::routes::post_login,
+        shared_bin::openapi::get_openapi,
+    ]
+}
@@ -0,0 +1,737 @@
+//! Running the maker.
+
+use crate::aggregator;
+use crate::balance_target;
+use crate::close_all;
+use crate::collateral_forecast;
+use crate::deposit_watch_list;
+use crate::load_blocked_peers;
+use crate::public_api;
+use crate::routes;
+use crate::shadow_pricing;
+use crate::volatility_spread;
+use crate::wind_down;
+use crate::withdrawal;
+use crate::ActorSystem;
+use crate::Opts;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use daemon::backup;
+use daemon::bdk::FeeRate;
+use daemon::cet_broadcast;
+use daemon::chain_consistency;
+use daemon::daily_marks;
+use daemon::electrum_health;
+use daemon::event_feed;
+use daemon::health;
+use daemon::janitor;
+use daemon::ledger;
+use daemon::metrics_persistence;
+use daemon::missing_attestation;
+use daemon::monitor;
+use daemon::notifications;
+use daemon::oracle;
+use daemon::order::pending_timeout::PendingOrderTimeouts;
+use daemon::projection;
+use daemon::projection::explorer;
+use daemon::projection::rounding;
+use daemon::seed;
+use daemon::seed::RandomSeed;
+use daemon::seed::Seed;
+use daemon::settlement_proposal;
+use daemon::telemetry;
+use daemon::transcript;
+use daemon::wallet;
+use daemon::wallet::MAKER_WALLET_ID;
+use daemon::N_PAYOUTS;
+use model::olivia;
+use model::payout_curve::test_vectors;
+use model::simulate;
+use model::simulate::Scenario;
+use model::symbols;
+use model::symbols::SymbolRegistry;
+use model::Contracts;
+use model::Role;
+use model::SETTLEMENT_INTERVAL;
+use rocket_cookie_auth::users::Users;
+use shared_bin::catchers::default_catchers;
+use shared_bin::cli::Command;
+use shared_bin::cli::DlcCommand;
+use shared_bin::decommission::Decommission;
+use shared_bin::decommission::Recipients;
+use shared_bin::fairings;
+use shared_bin::logger;
+use shared_bin::openapi;
+use sqlite_db::schema_flags;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio_extras::Tasks;
+use xtra::Actor as _;
+use xtras::supervisor::always_restart;
+use xtras::supervisor::Supervisor;
+
+/// Run the maker until its HTTP API shuts down.
+pub async fn run(opts: Opts) -> Result<()> {
+    let data_dir = data_dir(&opts).await?;
+
+    let _guard = logger::init(
+        opts.log_level,
+        opts.json,
+        opts.json_span_list,
+        opts.instrumentation,
+        opts.tokio_console,
+        opts.verbose_spans,
+        &opts.service_name,
+        &opts.collector_endpoint,
+        opts.log_to_file,
+        data_dir.to_str().expect("missing data dir"),
+    )
+    .context("initialize logger")?;
+
+    serve(opts).await
+}
+
+/// Run the maker like [`run`], but leave logging to the caller.
+///
+/// This allows to run the maker in the same process as a taker.
+pub async fn serve(opts: Opts) -> Result<()> {
+    let data_dir = data_dir(&opts).await?;
+
+    tracing::info!("Running version: {}", daemon::version());
+    let settlement_interval_hours = SETTLEMENT_INTERVAL.whole_hours();
+
+    tracing::info!(
+        "CFDs created with this release will settle after {settlement_interval_hours} hours"
+    );
+
+    if let Some(path) = &opts.symbol_config {
+        symbols::install(SymbolRegistry::from_file(path)?)?;
+        tracing::info!("Loaded symbol configuration from {}", path.display());
+    }
+
+    if let Some(policy) = rounding::Policy::load(&data_dir).await? {
+        rounding::init(policy)?;
+        tracing::info!(
+            "Rounding figures as configured in {}",
+            rounding::CONFIG_FILE
+        );
+    }
+
+    if let Some(config) = explorer::Explorer::load(&data_dir).await? {
+        explorer::init(config)?;
+        tracing::info!(
+            "Linking transactions as configured in {}",
+            explorer::CONFIG_FILE
+        );
+    }
+
+    if let Some(Command::Simulate { scenario }) = opts.network.command() {
+        let report = simulate::simulate(&Scenario::from_file(scenario)?)?;
+        println!("{}", serde_json::to_string_pretty(&report)?);
+
+        return Ok(());
+    }
+
+    if let Some(Command::PayoutTestVectors { inputs }) = opts.network.command() {
+        let inputs = match inputs {
+            Some(path) => test_vectors::Inputs::from_file(path)?,
+            None => test_vectors::Inputs::canonical(),
+        };
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&test_vectors::generate(inputs)?)?
+        );
+
+        return Ok(());
+    }
+
+    if let Some(Command::FinalizeMigration { name }) = opts.network.command() {
+        let migration = schema_flags::flagged_migration(name)
+            .with_context(|| format!("Unknown migration {name}"))?;
+
+        let db = sqlite_db::connect(data_dir.join("maker.sqlite"), false).await?;
+        db.finalize_migration(migration).await?;
+
+        tracing::info!(%name, "Finalized migration");
+
+        return Ok(());
+    }
+
+    if let Some(Command::CheckIntegrity) = opts.network.command() {
+        let db = sqlite_db::connect(data_dir.join("maker.sqlite"), false).await?;
+        let report = db.check_integrity().await?;
+        println!("{}", serde_json::to_string_pretty(&report)?);
+
+        if !report.is_consistent() {
+            tracing::warn!("Database is not consistent, see the report for details");
+        }
+
+        return Ok(());
+    }
+
+    if let Some(Command::Dlc {
+        command: DlcCommand::Show { order_id },
+    }) = opts.network.command()
+    {
+        let db = sqlite_db::connect(data_dir.join("maker.sqlite"), false).await?;
+
+        return shared_bin::dlc::show(&db, (*order_id).into(), opts.network.bitcoin_network())
+            .await;
+    }
+
+    let wallet_seed_file = &data_dir.join(seed::MAKER_WALLET_SEED_FILE);
+    let wallet_seed = RandomSeed::initialize(wallet_seed_file).await?;
+
+    let bitcoin_network = opts.network.bitcoin_network();
+
+    let ext_priv_key = match opts.wallet_xprv {
+        Some(wallet_xprv) => {
+            if wallet_xprv.network != bitcoin_network {
+                let network = wallet_xprv.network;
+                bail!("Invalid private key provided. Was '{network}' but should have been '{bitcoin_network}'");
+            }
+            wallet_xprv
+        }
+        None => wallet_seed.derive_extended_priv_key(bitcoin_network)?,
+    };
+
+    let mut tasks = Tasks::default();
+
+    let mut wallet_dir = data_dir.clone();
+
+    wallet_dir.push(MAKER_WALLET_ID);
+
+    let (electrum_health, electrum_status_receiver) =
+        electrum_health::Actor::start(opts.network.electrum_endpoints()).await?;
+    electrum_health.create(None).spawn(&mut tasks);
+
+    let (wallet, wallet_feed_receiver) = wallet::Actor::spawn(
+        electrum_status_receiver.clone(),
+        ext_priv_key,
+        wallet_dir,
+        wallet_seed.is_managed(),
+        opts.wallet_address_type,
+        opts.sweep_previous_wallet,
+    )?;
+
+    if let Some(Command::Withdraw {
+        amount,
+        address,
+        fee,
+    }) = opts.network.command()
+    {
+        wallet
+            .send(wallet::Withdraw {
+                amount: *amount,
+                address: address.clone(),
+                fee: fee.map(FeeRate::from_sat_per_vb),
+            })
+            .await??;
+
+        return Ok(());
+    }
+
+    if let Some(Command::Decommission {
+        addresses,
+        xpub,
+        num_addresses,
+        fee,
+        confirmations,
+    }) = opts.network.command()
+    {
+        let db_path = data_dir.join("maker.sqlite");
+        let db = sqlite_db::connect(db_path.clone(), false).await?;
+
+        return Decommission {
+            db,
+            db_path: &db_path,
+            seed_files: vec![
+                wallet_seed_file.clone(),
+                data_dir.join(seed::MAKER_IDENTITY_SEED_FILE),
+            ],
+            recipients: match xpub {
+                Some(xpub) => Recipients::Xpub {
+                    xpub: *xpub,
+                    num_addresses: *num_addresses,
+                },
+                None => Recipients::Addresses(addresses.clone()),
+            },
+            fee: fee.map(FeeRate::from_sat_per_vb),
+            network: bitcoin_network,
+            confirmations: *confirmations,
+        }
+        .run(wallet)
+        .await;
+    }
+
+    let identity_seed_file = &data_dir.join(seed::MAKER_IDENTITY_SEED_FILE);
+    if !identity_seed_file.exists() {
+        tracing::info!("Copying wallet seed file for identity seed file");
+        // copy wallet seed file for backwards compatibility.
+        tokio::fs::copy(&wallet_seed_file, &identity_seed_file).await?;
+    }
+
+    // generate a new seed for the libp2p identity.
+    let identity_seed = RandomSeed::initialize(identity_seed_file).await?;
+    let identities = identity_seed.derive_identities();
+
+    let peer_id = identities.peer_id();
+    let aggregator_keypair = identities.libp2p.clone();
+    let hex_pk = hex::encode(identities.identity_pk.to_bytes());
+    tracing::info!("Connection details: maker_id='{hex_pk}', peer_id='{peer_id}'");
+
+    let figment = rocket::Config::figment()
+        .merge(("address", opts.http_address.ip()))
+        .merge(("port", opts.http_address.port()))
+        .merge(("cli_colors", false))
+        .merge(("secret_key", RandomSeed::default().seed()));
+
+    let p2p_port = opts.p2p_port;
+    let p2p_socket = format!("0.0.0.0:{p2p_port}").parse::<SocketAddr>().unwrap();
+
+    let db = sqlite_db::connect(data_dir.join("maker.sqlite"), opts.ignore_migration_errors)
+        .await?
+        .with_slow_query_threshold(Duration::from_millis(opts.slow_query_threshold_ms));
+
+    let blocked_peers = load_blocked_peers(&data_dir)
+        .await
+        .context("Failed to load blocked peers")?;
+
+    // Create actors
+    let endpoint_listen = match opts.p2p_listen_address.clone() {
+        Some(address) => address,
+        None => {
+            daemon::libp2p_utils::create_listen_tcp_multiaddr(&p2p_socket.ip(), p2p_socket.port())
+                .expect("to parse properly")
+        }
+    };
+
+    let (supervisor, price_feed) = Supervisor::with_policy(
+        {
+            let network = opts.network.bitmex_network();
+            move || xtra_bitmex_price_feed::Actor::new(network)
+        },
+        always_restart::<xtra_bitmex_price_feed::Error>(),
+    );
+    tasks.add(supervisor.run_log_summary());
+
+    let (feed_senders, feed_receivers) = projection::feeds();
+    let feed_senders = std::sync::Arc::new(feed_senders);
+
+    let missing_attestation_policy = opts.missing_attestation_policy;
+    let cet_broadcast = cet_broadcast::Config {
+        strategy: opts.cet_broadcast,
+        delay: Duration::from_secs(opts.cet_broadcast_delay_mins * 60),
+    };
+    let (supervisor, projection_actor) = Supervisor::with_capacity(
+        {
+            let db = db.clone();
+            let price_feed = price_feed.clone();
+            move || {
+                projection::Actor::new(
+                    db.clone(),
+                    bitcoin_network,
+                    price_feed.clone().into(),
+                    Role::Maker,
+                    feed_senders.clone(),
+                )
+                .with_missing_attestation_policy(missing_attestation_policy)
+            }
+        },
+        projection::MAILBOX_CAPACITY,
+    );
+    tasks.add(supervisor.run_log_summary());
+
+    let (chain_consistency, chain_consistency_status) = chain_consistency::Actor::new(
+        wallet_feed_receiver.clone(),
+        feed_receivers.chain_tip.clone(),
+    );
+    chain_consistency.create(None).spawn(&mut tasks);
+
+    let (volatility_spread, volatility_spreads) = volatility_spread::Actor::new(
+        volatility_spread::Config {
+            window: Duration::from_secs(opts.volatility_window_mins * 60),
+            multiplier: opts.volatility_spread_multiplier.unwrap_or_default(),
+            max_spread: opts.max_volatility_spread,
+        },
+        feed_receivers.quote.clone(),
+    );
+    volatility_spread.create(None).spawn(&mut tasks);
+
+    let shadow_pricing = opts.shadow_volatility_spread_multiplier.map(|multiplier| {
+        let config = volatility_spread::Config {
+            window: Duration::from_secs(
+                opts.shadow_volatility_window_mins
+                    .unwrap_or(opts.volatility_window_mins)
+                    * 60,
+            ),
+            multiplier,
+            max_spread: opts
+                .shadow_max_volatility_spread
+                .unwrap_or(opts.max_volatility_spread),
+        };
+        tracing::info!(
+            ?config,
+            "Running candidate pricing configuration in shadow mode"
+        );
+
+        let (shadow_volatility_spread, shadow_volatility_spreads) =
+            volatility_spread::Actor::new(config, feed_receivers.quote.clone());
+        shadow_volatility_spread.create(None).spawn(&mut tasks);
+
+        shadow_pricing::Actor::new(
+            config,
+            shadow_volatility_spreads,
+            feed_receivers.cfds.clone(),
+        )
+        .create(None)
+        .spawn(&mut tasks)
+    });
+
+    let (collateral_forecast, collateral_forecast_receiver) = collateral_forecast::Actor::new(
+        collateral_forecast::Thresholds {
+            warning: opts.collateral_utilization_warning,
+            critical: opts.collateral_utilization_critical,
+        },
+        feed_receivers.offers.clone(),
+        wallet_feed_receiver.clone(),
+    );
+    collateral_forecast.create(None).spawn(&mut tasks);
+
+    let balance_target = match opts.target_balance {
+        Some(target) => {
+            let auto_withdraw = match opts.auto_withdraw_address.clone() {
+                Some(address) => {
+                    if address.network != bitcoin_network {
+                        bail!("Auto-withdraw address {address} is not a {bitcoin_network} address");
+                    }
+
+                    Some(balance_target::AutoWithdraw {
+                        address,
+                        max_fee_rate: opts.auto_withdraw_max_fee_rate,
+                    })
+                }
+                None => None,
+            };
+
+            let (balance_target, balance_notifications) = balance_target::Actor::new(
+                balance_target::Config {
+                    target,
+                    margin: opts.target_balance_margin,
+                    auto_withdraw,
+                },
+                db.clone(),
+                wallet.clone().into(),
+                wallet_feed_receiver.clone(),
+                collateral_forecast_receiver.clone(),
+            );
+            let balance_target = balance_target.create(None).spawn(&mut tasks);
+
+            if let Some(config) = notifications::email::Config::load(&data_dir).await? {
+                notifications::email::Actor::new(
+                    config,
+                    feed_receivers.cfds.clone(),
+                    wallet_feed_receiver.clone(),
+                    balance_notifications,
+                )?
+                .create(None)
+                .spawn(&mut tasks);
+
+                tracing::info!(
+                    "Sending balance notifications by email as configured in {}",
+                    notifications::email::CONFIG_FILE
+                );
+            }
+
+            Some(balance_target)
+        }
+        None => None,
+    };
+
+    let telemetry = opts.telemetry.then(|| {
+        telemetry::Telemetry::new(
+            db.clone(),
+            Role::Maker,
+            Duration::from_secs(opts.telemetry_retention_days * 24 * 60 * 60),
+            opts.telemetry_collector.clone(),
+        )
+    });
+
+    let daily_marks =
+        daily_marks::DailyMarks::new(db.clone(), &feed_receivers, opts.daily_mark_time);
+    let janitor = janitor::Janitor::new(
+        db.clone(),
+        feed_receivers.cfds.clone(),
+        opts.janitor_dry_run,
+    );
+
+    let maker = ActorSystem::new(
+        db.clone(),
+        wallet.clone(),
+        *olivia::PUBLIC_KEY,
+        |executor| oracle::Actor::new(db.clone(), executor),
+        |executor| {
+            monitor::Actor::new(
+                db.clone(),
+                electrum_status_receiver.clone(),
+                executor,
+                projection_actor.clone().into(),
+                projection_actor.clone().into(),
+            )
+            .map(|monitor| {
+                monitor
+                    .with_chain_consistency(chain_consistency_status.clone())
+                    .with_cet_broadcast(cet_broadcast)
+            })
+        },
+        SETTLEMENT_INTERVAL,
+        N_PAYOUTS,
+        projection_actor.clone(),
+        identities,
+        endpoint_listen,
+        blocked_peers,
+        opts.announce_address.clone(),
+        opts.max_contracts_per_taker.map(Contracts::new),
+        opts.deprecated_offer_protocol_cutoff,
+        opts.offer_history_retention_days
+            .map(|days| time::Duration::days(days.into())),
+        opts.job_intervals.clone(),
+        telemetry.clone(),
+        Some(daily_marks.clone()),
+        Some(janitor.clone()),
+        opts.max_concurrent_setups,
+        PendingOrderTimeouts::new(&opts.pending_order_timeouts),
+        Some(Duration::from_secs(opts.max_ping_interval_secs)),
+        Some(volatility_spreads.clone()),
+        opts.allow_overcommit,
+        shadow_pricing.clone(),
+        opts.offer_time_to_live_mins
+            .map(|mins| time::Duration::minutes(mins.into())),
+    )?;
+
+    if opts.verify_state_on_start {
+        daemon::verify_state(&db).await?;
+    }
+
+    missing_attestation::Actor::new(
+        db.clone(),
+        maker.executor.clone(),
+        missing_attestation_policy,
+    )
+    .create(None)
+    .spawn(&mut tasks);
+
+    let withdrawal = withdrawal::Actor::new(
+        db.clone(),
+        wallet.clone().into(),
+        opts.withdrawal_approval_threshold,
+    )
+    .create(None)
+    .spawn(&mut tasks);
+
+    let (deposit_watch_list, expected_deposits) = deposit_watch_list::Actor::new(
+        db.clone(),
+        wallet.clone().into(),
+        wallet_feed_receiver.clone(),
+    );
+    let deposit_watch_list = deposit_watch_list.create(None).spawn(&mut tasks);
+
+    metrics_persistence::Actor::new(
+        db.clone(),
+        Duration::from_secs(opts.metrics_persistence_interval_secs),
+    )
+    .create(None)
+    .spawn(&mut tasks);
+
+    let settlement_proposal = settlement_proposal::maker::Actor::new(
+        maker.endpoint.clone(),
+        price_feed.clone().into(),
+        db.clone(),
+    )
+    .create(None)
+    .spawn(&mut tasks);
+
+    let (wind_down, wind_down_status) = wind_down::Actor::new(
+        db.clone(),
+        maker.cfd_actor.clone(),
+        (
+            maker.rollover_actor.clone().into(),
+            maker.rollover_actor_deprecated.clone().into(),
+        ),
+        maker.executor.clone(),
+        settlement_proposal.clone().into(),
+        feed_receivers.cfds.clone(),
+    );
+    let wind_down = wind_down.create(None).spawn(&mut tasks);
+
+    let close_all = close_all::Actor::new(
+        maker.cfd_actor.clone(),
+        settlement_proposal.clone().into(),
+        feed_receivers.cfds.clone(),
+    )
+    .create(None)
+    .spawn(&mut tasks);
+
+    let ledger_actor = ledger::Actor::new(db.clone(), wallet_feed_receiver.clone())
+        .create(None)
+        .spawn(&mut tasks);
+
+    let health_actor = health::Actor::new(
+        db.clone(),
+        electrum_status_receiver.clone(),
+        wallet_feed_receiver.clone(),
+        price_feed.into(),
+        maker.oracle_actor.clone().into(),
+        maker.endpoint.clone(),
+        Role::Maker,
+    )
+    .create(None)
+    .spawn(&mut tasks);
+
+    if let Some(password) = opts.password {
+        db.clone()
+            .update_password(rocket_cookie_auth::user::create_password(
+                password.to_string().as_str(),
+            )?)
+            .await?;
+    }
+
+    #[cfg(feature = "grpc")]
+    if let (Some(address), Some(auth_token)) = (opts.grpc_address, opts.grpc_auth_token.clone()) {
+        let grpc = hermes_grpc::Service::new(
+            feed_receivers.cfds.clone(),
+            feed_receivers.offers.clone(),
+            feed_receivers.quote.clone(),
+            wallet_feed_receiver.clone(),
+        );
+        tasks.add_fallible(grpc.serve(address, auth_token), |e| async move {
+            tracing::error!("gRPC API stopped: {e:#}");
+        });
+    }
+
+    let aggregator = opts.aggregator_url.clone().map(|url| {
+        tracing::info!(%url, "Publishing offers to market aggregator");
+
+        aggregator::Actor::new(url, aggregator_keypair, feed_receivers.offers.clone())
+            .create(None)
+            .spawn(&mut tasks)
+    });
+
+    let market_stats = public_api::MarketStatsSource::new(
+        opts.public_market_stats
+            .then(|| maker.position_metrics.clone().into()),
+    );
+
+    let rocket_auth_db_connection = RocketAuthDbConnection::new(db.clone());
+    let users = Users::new(Box::new(rocket_auth_db_connection));
+
+    let rocket = rocket::custom(figment)
+        .manage(feed_receivers)
+        .manage(wallet_feed_receiver)
+        .manage(electrum_status_receiver)
+        .manage(volatility_spreads)
+        .manage(maker.scheduler_actor.clone())
+        .manage(maker)
+        .manage(wind_down)
+        .manage(close_all)
+        .manage(settlement_proposal)
+        .manage(wind_down_status)
+        .manage(withdrawal)
+        .manage(deposit_watch_list)
+        .manage(expected_deposits)
+        .manage(ledger_actor)
+        .manage(health_actor)
+        .manage(users)
+        .manage(bitcoin_network)
+        .manage(backup::Exporter::new(
+            db.clone(),
+            identity_seed.derive_backup_key(),
+        ))
+        .manage(transcript::Transcripts::new(data_dir.clone()))
+        .manage(event_feed::EventFeed::new(db.clone(), Role::Maker))
+        .manage(openapi::Spec(crate::openapi::SPEC))
+        .manage(aggregator)
+        .manage(shadow_pricing)
+        .manage(balance_target)
+        .manage(collateral_forecast_receiver)
+        .manage(telemetry)
+        .manage(daily_marks)
+        .manage(janitor)
+        .mount("/api", routes::api_routes())
+        .register("/api", default_catchers())
+        .mount("/", rocket::routes![routes::dist, routes::index])
+        .register("/", default_catchers())
+        .attach(fairings::log_launch())
+        .attach(fairings::log_requests())
+        .attach(fairings::ui_browser_launch(!opts.headless));
+
+    let rocket = if opts.public_api {
+        tracing::info!("Serving public market data at {}", public_api::BASE_PATH);
+
+        rocket
+            .manage(public_api::RateLimiter::new(opts.public_api_rate_limit))
+            .manage(market_stats)
+            .mount(
+                public_api::BASE_PATH,
+                rocket::routes![public_api::get_offers, public_api::options_offers],
+            )
+            .register(public_api::BASE_PATH, default_catchers())
+            .attach(public_api::cors(opts.public_api_cors_origins))
+    } else {
+        rocket
+    };
+
+    let mission_success = rocket.launch().await?;
+
+    tracing::trace!(?mission_success, "Rocket has landed");
+
+    db.close().await;
+
+    Ok(())
+}
+
+/// The data directory for the network, created if it does not exist yet.
+async fn data_dir(opts: &Opts) -> Result<PathBuf> {
+    let data_dir = opts
+        .data_dir
+        .clone()
+        .unwrap_or_else(|| std::env::current_dir().expect("unable to get cwd"));
+
+    let data_dir = opts.network.data_dir(data_dir);
+
+    if !data_dir.exists() {
+        tokio::fs::create_dir_all(&data_dir).await?;
+    }
+
+    Ok(data_dir)
+}
+
+struct RocketAuthDbConnection {
+    inner: sqlite_db::Connection,
+}
+
+impl RocketAuthDbConnection {
+    fn new(db: sqlite_db::Connection) -> Self {
+        Self { inner: db }
+    }
+}
+
+#[rocket::async_trait]
+impl rocket_cookie_auth::Database for RocketAuthDbConnection {
+    async fn load_user(&self) -> Result<Option<rocket_cookie_auth::user::User>> {
+        let users = self.inner.clone().load_user().await?;
+        Ok(users.map(|user| rocket_cookie_auth::user::User {
+            id: user.id,
+            password: user.password,
+            auth_key: rocket_cookie_auth::NO_AUTH_KEY_SET.to_string(),
+            first_login: user.first_login,
+        }))
+    }
+
+    async fn update_password(&self, password: String) -> Result<()> {
+        self.inner.clone().update_password(password).await?;
+        Ok(())
+    }
+}
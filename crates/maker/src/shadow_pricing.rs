@@ -0,0 +1,377 @@
+//! Shadow mode for a candidate pricing configuration.
+//!
+//! Before switching to a new volatility spread configuration, the operator can run it alongside
+//! the live one. Whenever offers are published, the offers the candidate configuration would have
+//! published instead are recorded. Once a taker's order on a live offer is set up, it is recorded
+//! as a hypothetical fill of the corresponding shadow offer, assuming the taker would have taken it
+//! as well. Shadow offers are never published and do not affect the live offers in any way.
+//!
+//! The [`Report`] compares both configurations since the maker started: a positive price
+//! improvement means that the candidate configuration would have filled the orders at prices more
+//! favourable to the maker.
+
+use crate::cfd::OfferParams;
+use crate::volatility_spread;
+use crate::volatility_spread::VolatilitySpread;
+use crate::volatility_spread::VolatilitySpreads;
+use async_trait::async_trait;
+use daemon::projection;
+use daemon::projection::CfdState;
+use model::ContractSymbol;
+use model::Contracts;
+use model::OfferId;
+use model::OrderId;
+use model::Position;
+use model::Price;
+use model::Timestamp;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use tokio::sync::watch;
+use xtra_productivity::xtra_productivity;
+
+/// Number of shadow offers kept to match fills against, the oldest are dropped first
+const MAX_OFFERS: usize = 10_000;
+
+/// Number of hypothetical fills listed per contract symbol in the report
+const MAX_RECENT_FILLS: usize = 100;
+
+/// Record the shadow offers for offers that are published.
+pub struct RecordOffers {
+    /// The offer parameters before the prices were widened by the live volatility spread
+    pub params: OfferParams,
+    /// The offers that were published for `params`
+    pub offers: Vec<model::Offer>,
+}
+
+/// Compare the candidate pricing configuration with the live one.
+#[derive(Clone, Copy)]
+pub struct GetReport;
+
+#[derive(Clone, Copy)]
+struct UpdateFills;
+
+/// The offer the candidate configuration would have published instead of a live offer.
+#[derive(Debug, Clone, Serialize)]
+pub struct ShadowOffer {
+    /// The live offer this shadow offer was computed alongside
+    pub offer_id: OfferId,
+    /// The maker's position
+    pub position: Position,
+    pub live_price: Price,
+    pub shadow_price: Price,
+    pub published_at: Timestamp,
+}
+
+/// An order on a live offer, priced at the corresponding shadow offer.
+#[derive(Debug, Clone, Serialize)]
+pub struct ShadowFill {
+    pub order_id: OrderId,
+    pub offer_id: OfferId,
+    /// The maker's position
+    pub position: Position,
+    pub quantity: Contracts,
+    /// The price the order was actually filled at
+    pub live_price: Price,
+    pub shadow_price: Price,
+    /// Difference per contract between both prices, positive if the shadow price is more
+    /// favourable to the maker
+    pub price_improvement: Decimal,
+}
+
+impl ShadowFill {
+    fn new(offer: &ShadowOffer, cfd: &projection::Cfd) -> Self {
+        Self {
+            order_id: cfd.order_id,
+            offer_id: offer.offer_id,
+            position: offer.position,
+            quantity: cfd.quantity,
+            live_price: cfd.initial_price,
+            shadow_price: offer.shadow_price,
+            price_improvement: price_improvement(
+                offer.position,
+                cfd.initial_price,
+                offer.shadow_price,
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    /// Period in minutes the realized volatility of the candidate configuration is computed over
+    pub window_mins: u64,
+    pub multiplier: f64,
+    pub max_spread: f64,
+    /// Start of the comparison
+    pub since: Timestamp,
+    pub symbols: Vec<SymbolReport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SymbolReport {
+    pub contract_symbol: ContractSymbol,
+    /// The current spread of the candidate configuration, `None` until enough quotes were sampled
+    pub spread: Option<VolatilitySpread>,
+    /// Number of times offers were published
+    pub publications: u64,
+    /// The shadow offers of the latest publication
+    pub latest: Vec<ShadowOffer>,
+    pub fills: u64,
+    pub filled_contracts: Decimal,
+    /// Price improvement per contract over all fills, weighted by their quantity
+    pub mean_price_improvement: Option<Decimal>,
+    /// The latest hypothetical fills, newest first
+    pub recent_fills: Vec<ShadowFill>,
+}
+
+#[derive(Debug, Default)]
+struct Tally {
+    publications: u64,
+    latest: Vec<ShadowOffer>,
+    fills: u64,
+    filled_contracts: Decimal,
+    /// Sum of the price improvements weighted by the quantity of the fills
+    weighted_price_improvement: Decimal,
+    recent_fills: VecDeque<ShadowFill>,
+}
+
+pub struct Actor {
+    config: volatility_spread::Config,
+    spreads: watch::Receiver<VolatilitySpreads>,
+    cfds: watch::Receiver<Option<Vec<projection::Cfd>>>,
+    since: Timestamp,
+    offers: HashMap<OfferId, (ContractSymbol, ShadowOffer)>,
+    /// Ids of the recorded shadow offers, oldest first
+    offer_ids: VecDeque<OfferId>,
+    filled_orders: HashSet<OrderId>,
+    tallies: HashMap<ContractSymbol, Tally>,
+}
+
+impl Actor {
+    /// Run the candidate configuration `config` whose spreads are derived by the volatility spread
+    /// actor feeding `spreads`.
+    pub fn new(
+        config: volatility_spread::Config,
+        spreads: watch::Receiver<VolatilitySpreads>,
+        cfds: watch::Receiver<Option<Vec<projection::Cfd>>>,
+    ) -> Self {
+        Self {
+            config,
+            spreads,
+            cfds,
+            since: Timestamp::now(),
+            offers: HashMap::new(),
+            offer_ids: VecDeque::new(),
+            filled_orders: HashSet::new(),
+            tallies: HashMap::new(),
+        }
+    }
+
+    fn record_offer(&mut self, contract_symbol: ContractSymbol, offer: ShadowOffer) {
+        self.offer_ids.push_back(offer.offer_id);
+        self.offers.insert(offer.offer_id, (contract_symbol, offer));
+
+        while self.offer_ids.len() > MAX_OFFERS {
+            if let Some(offer_id) = self.offer_ids.pop_front() {
+                self.offers.remove(&offer_id);
+            }
+        }
+    }
+}
+
+/// The price the candidate configuration would have published for the maker's `position`.
+fn shadow_price(
+    spread: Option<VolatilitySpread>,
+    params: &OfferParams,
+    position: Position,
+) -> Option<Price> {
+    let price = match position {
+        Position::Long => params.price_long?,
+        Position::Short => params.price_short?,
+    };
+
+    // Like the live offers, the prices are not widened before enough quotes were sampled
+    match spread {
+        Some(spread) if spread.spread > 0.0 => match spread.widen(price, position) {
+            Ok(price) => Some(price),
+            Err(e) => {
+                tracing::warn!(%price, "Failed to widen shadow price: {e:#}");
+                None
+            }
+        },
+        _ => Some(price),
+    }
+}
+
+/// Difference per contract between the live and the shadow price, positive if the shadow price is
+/// more favourable to the maker in `position`.
+fn price_improvement(position: Position, live_price: Price, shadow_price: Price) -> Decimal {
+    let difference = live_price.into_decimal() - shadow_price.into_decimal();
+
+    // The maker buys when going long, hence a lower price is more favourable
+    match position {
+        Position::Long => difference,
+        Position::Short => -difference,
+    }
+}
+
+/// Whether the contract setup of the CFD completed.
+fn is_filled(state: CfdState) -> bool {
+    !matches!(
+        state,
+        CfdState::PendingSetup
+            | CfdState::ContractSetup
+            | CfdState::Rejected
+            | CfdState::SetupFailed
+    )
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: RecordOffers) {
+        let RecordOffers { params, offers } = msg;
+        let contract_symbol = params.contract_symbol;
+        let spread = self.spreads.borrow().get(&contract_symbol).copied();
+        let published_at = Timestamp::now();
+
+        let shadow_offers = offers
+            .iter()
+            .filter_map(|offer| {
+                let shadow_price = shadow_price(spread, &params, offer.position_maker)?;
+
+                Some(ShadowOffer {
+                    offer_id: offer.id,
+                    position: offer.position_maker,
+                    live_price: offer.price,
+                    shadow_price,
+                    published_at,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for offer in shadow_offers.iter() {
+            tracing::debug!(
+                %contract_symbol,
+                position = ?offer.position,
+                live_price = %offer.live_price,
+                shadow_price = %offer.shadow_price,
+                "Recorded shadow offer"
+            );
+
+            self.record_offer(contract_symbol, offer.clone());
+        }
+
+        let tally = self.tallies.entry(contract_symbol).or_default();
+        tally.publications += 1;
+        tally.latest = shadow_offers;
+    }
+
+    async fn handle(&mut self, _: UpdateFills) {
+        let fills = match self.cfds.borrow().as_ref() {
+            None => return,
+            Some(cfds) => cfds
+                .iter()
+                .filter(|cfd| is_filled(cfd.state) && !self.filled_orders.contains(&cfd.order_id))
+                .filter_map(|cfd| {
+                    let (contract_symbol, offer) = self.offers.get(&cfd.offer_id)?;
+
+                    Some((*contract_symbol, ShadowFill::new(offer, cfd)))
+                })
+                .collect::<Vec<_>>(),
+        };
+
+        for (contract_symbol, fill) in fills {
+            tracing::info!(
+                order_id = %fill.order_id,
+                %contract_symbol,
+                live_price = %fill.live_price,
+                shadow_price = %fill.shadow_price,
+                price_improvement = %fill.price_improvement,
+                "Recorded hypothetical fill of shadow offer"
+            );
+
+            self.filled_orders.insert(fill.order_id);
+
+            let tally = self.tallies.entry(contract_symbol).or_default();
+            let quantity = fill.quantity.into_decimal();
+            tally.fills += 1;
+            tally.filled_contracts += quantity;
+            tally.weighted_price_improvement += fill.price_improvement * quantity;
+            tally.recent_fills.push_front(fill);
+            tally.recent_fills.truncate(MAX_RECENT_FILLS);
+        }
+    }
+
+    async fn handle(&mut self, _: GetReport) -> Report {
+        let spreads = self.spreads.borrow();
+
+        let mut symbols = self
+            .tallies
+            .iter()
+            .map(|(contract_symbol, tally)| SymbolReport {
+                contract_symbol: *contract_symbol,
+                spread: spreads.get(contract_symbol).copied(),
+                publications: tally.publications,
+                latest: tally.latest.clone(),
+                fills: tally.fills,
+                filled_contracts: tally.filled_contracts,
+                mean_price_improvement: (!tally.filled_contracts.is_zero())
+                    .then(|| tally.weighted_price_improvement / tally.filled_contracts),
+                recent_fills: tally.recent_fills.iter().cloned().collect(),
+            })
+            .collect::<Vec<_>>();
+        symbols.sort_by_key(|symbol| symbol.contract_symbol.to_string());
+
+        Report {
+            window_mins: self.config.window.as_secs() / 60,
+            multiplier: self.config.multiplier,
+            max_spread: self.config.max_spread,
+            since: self.since,
+            symbols,
+        }
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we just started");
+
+        tokio_extras::spawn(&this.clone(), {
+            let mut cfds = self.cfds.clone();
+
+            async move {
+                while cfds.changed().await.is_ok() {
+                    if this.send(UpdateFills).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn lower_long_and_higher_short_prices_improve_the_maker_price() {
+        let live = Price::new(dec!(20000)).unwrap();
+        let lower = Price::new(dec!(19900)).unwrap();
+        let higher = Price::new(dec!(20100)).unwrap();
+
+        assert_eq!(price_improvement(Position::Long, live, lower), dec!(100));
+        assert_eq!(price_improvement(Position::Long, live, higher), dec!(-100));
+        assert_eq!(price_improvement(Position::Short, live, higher), dec!(100));
+        assert_eq!(price_improvement(Position::Short, live, lower), dec!(-100));
+    }
+}
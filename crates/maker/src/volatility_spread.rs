@@ -0,0 +1,229 @@
+//! Spread derived from the realized volatility of the quotes.
+//!
+//! The mid price of every contract symbol is sampled at a fixed interval. The realized volatility
+//! over the configured window is the standard deviation of the log returns between consecutive
+//! samples. Published offers are widened by a multiple of it: the maker's long price is lowered
+//! and the maker's short price raised.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use daemon::projection::LatestQuotes;
+use model::ContractSymbol;
+use model::Position;
+use model::Price;
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::sync::watch;
+use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+/// Interval at which the mid prices are sampled
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Minimum number of samples before a spread is derived
+const MIN_SAMPLES: usize = 3;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Period the realized volatility is computed over
+    pub window: Duration,
+    /// Factor the realized volatility is multiplied with to derive the spread
+    pub multiplier: f64,
+    /// Upper bound of the spread as a fraction of the price
+    pub max_spread: f64,
+}
+
+impl Config {
+    fn max_samples(&self) -> usize {
+        ((self.window.as_secs() / SAMPLE_INTERVAL.as_secs()) as usize + 1).max(MIN_SAMPLES)
+    }
+
+    fn spread(&self, realized_volatility: f64) -> f64 {
+        (realized_volatility * self.multiplier).clamp(0.0, self.max_spread)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct VolatilitySpread {
+    /// Standard deviation of the log returns per sample interval
+    pub realized_volatility: f64,
+    /// Fraction of the price the offers are widened by
+    pub spread: f64,
+    /// Number of samples the realized volatility is based on
+    pub samples: usize,
+}
+
+impl VolatilitySpread {
+    /// Widen the price of an offer in which the maker takes `position`.
+    pub fn widen(&self, price: Price, position: Position) -> Result<Price> {
+        let spread = Decimal::from_f64(self.spread).unwrap_or_default();
+        let factor = match position {
+            Position::Long => Decimal::ONE - spread,
+            Position::Short => Decimal::ONE + spread,
+        };
+
+        Price::new((price.into_decimal() * factor).round_dp(2))
+    }
+}
+
+pub type VolatilitySpreads = HashMap<ContractSymbol, VolatilitySpread>;
+
+/// Standard deviation of the log returns between consecutive prices.
+///
+/// Returns `None` if there are not enough prices to compute it.
+pub fn realized_volatility<'a>(prices: impl IntoIterator<Item = &'a Decimal>) -> Option<f64> {
+    let prices = prices
+        .into_iter()
+        .filter_map(|price| price.to_f64())
+        .filter(|price| *price > 0.0)
+        .collect::<Vec<_>>();
+
+    let returns = prices
+        .windows(2)
+        .map(|pair| (pair[1] / pair[0]).ln())
+        .collect::<Vec<_>>();
+
+    if returns.len() < MIN_SAMPLES - 1 {
+        return None;
+    }
+
+    let n = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / n;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+
+    Some(variance.sqrt())
+}
+
+pub struct Actor {
+    config: Config,
+    quotes: watch::Receiver<LatestQuotes>,
+    samples: HashMap<ContractSymbol, VecDeque<Decimal>>,
+    sender: watch::Sender<VolatilitySpreads>,
+}
+
+impl Actor {
+    pub fn new(
+        config: Config,
+        quotes: watch::Receiver<LatestQuotes>,
+    ) -> (Self, watch::Receiver<VolatilitySpreads>) {
+        let (sender, receiver) = watch::channel(VolatilitySpreads::new());
+
+        (
+            Self {
+                config,
+                quotes,
+                samples: HashMap::new(),
+                sender,
+            },
+            receiver,
+        )
+    }
+
+    fn sample(&mut self) {
+        let max_samples = self.config.max_samples();
+
+        for (symbol, quote) in self.quotes.borrow().iter() {
+            let samples = self.samples.entry(*symbol).or_default();
+            samples.push_back((quote.bid + quote.ask) / Decimal::TWO);
+
+            while samples.len() > max_samples {
+                samples.pop_front();
+            }
+        }
+
+        let spreads = self
+            .samples
+            .iter()
+            .filter_map(|(symbol, samples)| {
+                let realized_volatility = realized_volatility(samples)?;
+
+                Some((
+                    *symbol,
+                    VolatilitySpread {
+                        realized_volatility,
+                        spread: self.config.spread(realized_volatility),
+                        samples: samples.len(),
+                    },
+                ))
+            })
+            .collect();
+
+        let _ = self.sender.send(spreads);
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we just started");
+
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(SAMPLE_INTERVAL, || Sample, xtras::IncludeSpan::Never),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[derive(Clone, Copy)]
+struct Sample;
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: Sample) {
+        self.sample();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn given_constant_prices_then_zero_volatility() {
+        let prices = [dec!(20000), dec!(20000), dec!(20000), dec!(20000)];
+
+        assert_eq!(realized_volatility(&prices), Some(0.0));
+    }
+
+    #[test]
+    fn given_too_few_prices_then_no_volatility() {
+        assert_eq!(realized_volatility(&[dec!(20000), dec!(20100)]), None);
+    }
+
+    #[test]
+    fn spread_is_capped_and_widens_offers() {
+        let config = Config {
+            window: Duration::from_secs(60 * 60),
+            multiplier: 10.0,
+            max_spread: 0.01,
+        };
+        let volatility = realized_volatility(&[dec!(20000), dec!(21000), dec!(19000)]).unwrap();
+        let spread = VolatilitySpread {
+            realized_volatility: volatility,
+            spread: config.spread(volatility),
+            samples: 3,
+        };
+
+        assert_eq!(spread.spread, 0.01);
+
+        let price = Price::new(dec!(20000)).unwrap();
+        assert_eq!(
+            spread.widen(price, Position::Long).unwrap(),
+            Price::new(dec!(19800)).unwrap()
+        );
+        assert_eq!(
+            spread.widen(price, Position::Short).unwrap(),
+            Price::new(dec!(20200)).unwrap()
+        );
+    }
+}
@@ -0,0 +1,423 @@
+//! Coordinated wind-down of the maker.
+//!
+//! Winding down withdraws all offers, stops accepting rollovers and gives takers a grace period
+//! to settle their CFDs collaboratively. CFDs that are still open once the grace period is over
+//! are force-closed by committing them to the blockchain.
+//!
+//! The maker proposes settlement at the mark price, i.e. the market closing price from the
+//! maker's perspective, to the taker of every open CFD. Takers that cannot be reached are asked
+//! again until the grace period is over. The taker settles by sending a settlement proposal in
+//! return, which is accepted like all settlement proposals that arrive during the grace period.
+//!
+//! The wind-down is persisted once started. After a restart the maker withdraws its offers and
+//! disables rollovers again and continues with the original deadline.
+
+use crate::cfd;
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+use daemon::command;
+use daemon::projection;
+use daemon::projection::CfdAction;
+use daemon::projection::CfdState;
+use daemon::settlement_proposal;
+use model::OrderId;
+use model::Timestamp;
+use serde::Serialize;
+use sqlite_db::wind_down::WindDown;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::sync::watch;
+use xtra::prelude::MessageChannel;
+use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+/// Interval at which the progress of the wind-down is checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// No wind-down was started
+    Idle,
+    /// Waiting for takers to settle their CFDs collaboratively
+    AwaitingSettlement,
+    /// The grace period is over and remaining CFDs are committed to the blockchain
+    ForceCommitting,
+    /// All CFDs are either closed or committed
+    Completed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Failure {
+    pub order_id: OrderId,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Status {
+    pub phase: Phase,
+    #[serde(with = "time::serde::timestamp::option")]
+    pub started_at: Option<OffsetDateTime>,
+    /// End of the grace period for collaborative settlement
+    #[serde(with = "time::serde::timestamp::option")]
+    pub deadline: Option<OffsetDateTime>,
+    /// Number of CFDs that are neither closed nor committed
+    pub open_cfds: usize,
+    /// CFDs whose takers were asked to settle at the mark price
+    pub settlements_proposed: Vec<OrderId>,
+    pub settlements_accepted: Vec<OrderId>,
+    pub force_committed: Vec<OrderId>,
+    pub failures: Vec<Failure>,
+}
+
+impl Default for Status {
+    fn default() -> Self {
+        Self {
+            phase: Phase::Idle,
+            started_at: None,
+            deadline: None,
+            open_cfds: 0,
+            settlements_proposed: Vec::new(),
+            settlements_accepted: Vec::new(),
+            force_committed: Vec::new(),
+            failures: Vec::new(),
+        }
+    }
+}
+
+/// Start winding down the maker.
+#[derive(Clone, Copy)]
+pub struct Start {
+    /// How long takers have to settle collaboratively before their CFDs are committed
+    pub grace_period: time::Duration,
+}
+
+#[derive(Clone, Copy)]
+struct CheckProgress;
+
+/// Actor that orchestrates the wind-down of the maker
+///
+/// Progress is published through a `watch` channel so it can be reported via the API.
+pub struct Actor {
+    db: sqlite_db::Connection,
+    cfd_actor: xtra::Address<cfd::Actor>,
+    rollover_configuration: (
+        MessageChannel<rollover::maker::UpdateConfiguration, ()>,
+        MessageChannel<rollover::deprecated::maker::UpdateConfiguration, ()>,
+    ),
+    executor: command::Executor,
+    settlement_proposals: MessageChannel<settlement_proposal::maker::ProposeSettlement, Result<()>>,
+    cfds: watch::Receiver<Option<Vec<projection::Cfd>>>,
+    status: watch::Sender<Status>,
+}
+
+impl Actor {
+    pub fn new(
+        db: sqlite_db::Connection,
+        cfd_actor: xtra::Address<cfd::Actor>,
+        rollover_configuration: (
+            MessageChannel<rollover::maker::UpdateConfiguration, ()>,
+            MessageChannel<rollover::deprecated::maker::UpdateConfiguration, ()>,
+        ),
+        executor: command::Executor,
+        settlement_proposals: MessageChannel<
+            settlement_proposal::maker::ProposeSettlement,
+            Result<()>,
+        >,
+        cfds: watch::Receiver<Option<Vec<projection::Cfd>>>,
+    ) -> (Self, watch::Receiver<Status>) {
+        let (status, receiver) = watch::channel(Status::default());
+
+        let actor = Self {
+            db,
+            cfd_actor,
+            rollover_configuration,
+            executor,
+            settlement_proposals,
+            cfds,
+            status,
+        };
+
+        (actor, receiver)
+    }
+
+    fn open_cfds(&self) -> Vec<(OrderId, CfdState, bool)> {
+        self.cfds
+            .borrow()
+            .iter()
+            .flatten()
+            .filter(|cfd| is_open(cfd.state))
+            .map(|cfd| {
+                (
+                    cfd.order_id,
+                    cfd.state,
+                    cfd.actions.contains(&CfdAction::AcceptSettlement),
+                )
+            })
+            .collect()
+    }
+
+    /// Withdraw all offers and stop accepting rollovers.
+    async fn withdraw(&self) -> Result<()> {
+        self.cfd_actor.send(cfd::WithdrawOffers).await??;
+
+        let (rollover, rollover_deprecated) = &self.rollover_configuration;
+        rollover
+            .send(rollover::maker::UpdateConfiguration::new(false))
+            .await?;
+        rollover_deprecated
+            .send(rollover::deprecated::maker::UpdateConfiguration::new(false))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Ask the takers of open CFDs that were not asked yet to settle at the mark price.
+    ///
+    /// Failing to reach a taker is not recorded as failure: the taker is asked again on the next
+    /// check and can still settle on its own during the grace period.
+    async fn propose_settlements(&mut self, open_cfds: &[(OrderId, CfdState, bool)]) {
+        let already_proposed = self.status.borrow().settlements_proposed.clone();
+
+        let to_propose = open_cfds
+            .iter()
+            .filter(|(order_id, state, _)| {
+                *state == CfdState::Open && !already_proposed.contains(order_id)
+            })
+            .map(|(order_id, _, _)| *order_id)
+            .collect::<Vec<_>>();
+
+        for order_id in to_propose {
+            let res = match self
+                .settlement_proposals
+                .send(settlement_proposal::maker::ProposeSettlement { order_id })
+                .await
+            {
+                Ok(res) => res,
+                Err(e) => Err(anyhow::Error::new(e)),
+            };
+
+            if let Err(e) = res {
+                tracing::debug!(%order_id, "Failed to propose settlement during wind-down: {e:#}");
+                continue;
+            }
+
+            self.status
+                .send_modify(|status| status.settlements_proposed.push(order_id));
+        }
+    }
+
+    fn enter_grace_period(&self, wind_down: WindDown) {
+        let WindDown {
+            started_at,
+            deadline,
+        } = wind_down;
+
+        self.status.send_modify(|status| {
+            status.phase = Phase::AwaitingSettlement;
+            status.started_at = OffsetDateTime::from_unix_timestamp(started_at.seconds()).ok();
+            status.deadline = OffsetDateTime::from_unix_timestamp(deadline.seconds()).ok();
+        });
+    }
+
+    /// Resume a wind-down that was started before the maker restarted.
+    async fn resume(&self) -> Result<()> {
+        let wind_down = match self.db.load_wind_down().await? {
+            Some(wind_down) => wind_down,
+            None => return Ok(()),
+        };
+
+        self.withdraw().await?;
+        self.enter_grace_period(wind_down);
+
+        tracing::info!(deadline = %wind_down.deadline, "Resumed wind-down");
+
+        Ok(())
+    }
+
+    async fn accept_settlement_proposals(&mut self, open_cfds: &[(OrderId, CfdState, bool)]) {
+        let already_accepted = self.status.borrow().settlements_accepted.clone();
+
+        let proposals = open_cfds
+            .iter()
+            .filter(|(order_id, _, can_accept_settlement)| {
+                *can_accept_settlement && !already_accepted.contains(order_id)
+            })
+            .map(|(order_id, _, _)| *order_id)
+            .collect::<Vec<_>>();
+
+        for order_id in proposals {
+            let res = match self
+                .cfd_actor
+                .send(cfd::AcceptSettlement { order_id })
+                .await
+            {
+                Ok(res) => res,
+                Err(e) => Err(anyhow::Error::new(e)),
+            };
+
+            match res {
+                Ok(()) => {
+                    tracing::info!(%order_id, "Accepted settlement proposal during wind-down");
+                    self.status
+                        .send_modify(|status| status.settlements_accepted.push(order_id));
+                }
+                Err(e) => self.record_failure(order_id, e),
+            }
+        }
+    }
+
+    async fn force_commit(&mut self, open_cfds: &[(OrderId, CfdState, bool)]) {
+        self.status
+            .send_modify(|status| status.phase = Phase::ForceCommitting);
+
+        // CFDs in the middle of a protocol are committed once they are back to open
+        let to_commit = open_cfds
+            .iter()
+            .filter(|(_, state, _)| *state == CfdState::Open)
+            .map(|(order_id, _, _)| *order_id)
+            .collect::<Vec<_>>();
+
+        for order_id in to_commit {
+            match self
+                .executor
+                .execute(order_id, |cfd| cfd.manual_commit_to_blockchain())
+                .await
+            {
+                Ok(_) => {
+                    tracing::info!(%order_id, "Committed CFD to blockchain during wind-down");
+                    self.status
+                        .send_modify(|status| status.force_committed.push(order_id));
+                }
+                Err(e) => self.record_failure(order_id, e),
+            }
+        }
+    }
+
+    fn record_failure(&mut self, order_id: OrderId, e: anyhow::Error) {
+        tracing::warn!(%order_id, "Wind-down action failed: {e:#}");
+
+        // Only keep the latest failure per CFD as failed actions are retried
+        self.status.send_modify(|status| {
+            status
+                .failures
+                .retain(|failure| failure.order_id != order_id);
+            status.failures.push(Failure {
+                order_id,
+                reason: format!("{e:#}"),
+            })
+        });
+    }
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle_start(&mut self, msg: Start) -> Result<()> {
+        if self.status.borrow().phase != Phase::Idle {
+            bail!("Wind-down was already started");
+        }
+
+        let Start { grace_period } = msg;
+
+        let started_at = OffsetDateTime::now_utc();
+        let deadline = started_at + grace_period;
+        let wind_down = WindDown {
+            started_at: Timestamp::new(started_at.unix_timestamp()),
+            deadline: Timestamp::new(deadline.unix_timestamp()),
+        };
+
+        // Persisted first, so that the wind-down is resumed if the maker stops halfway through
+        self.db.insert_wind_down(wind_down).await?;
+        self.withdraw().await?;
+
+        tracing::info!(%deadline, "Started wind-down");
+
+        self.enter_grace_period(wind_down);
+
+        let open_cfds = self.open_cfds();
+        self.propose_settlements(&open_cfds).await;
+
+        let open_cfds = open_cfds.len();
+        self.status
+            .send_modify(|status| status.open_cfds = open_cfds);
+
+        Ok(())
+    }
+
+    async fn handle(&mut self, _: CheckProgress) {
+        let (phase, deadline) = {
+            let status = self.status.borrow();
+            (status.phase, status.deadline)
+        };
+
+        if !matches!(phase, Phase::AwaitingSettlement | Phase::ForceCommitting) {
+            return;
+        }
+
+        // Until the projection loaded the CFDs the wind-down cannot tell whether any are open
+        if self.cfds.borrow().is_none() {
+            return;
+        }
+
+        let open_cfds = self.open_cfds();
+
+        if phase == Phase::AwaitingSettlement {
+            self.propose_settlements(&open_cfds).await;
+            self.accept_settlement_proposals(&open_cfds).await;
+        }
+
+        let grace_period_over =
+            deadline.map_or(false, |deadline| OffsetDateTime::now_utc() >= deadline);
+        if grace_period_over {
+            self.force_commit(&open_cfds).await;
+        }
+
+        let open_cfds = self.open_cfds().len();
+        self.status.send_modify(|status| {
+            status.open_cfds = open_cfds;
+
+            if open_cfds == 0 {
+                status.phase = Phase::Completed;
+            }
+        });
+
+        if open_cfds == 0 {
+            tracing::info!("Wind-down completed");
+        }
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we just started");
+
+        if let Err(e) = self.resume().await {
+            tracing::error!("Failed to resume wind-down: {e:#}");
+        }
+
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(CHECK_INTERVAL, || CheckProgress, xtras::IncludeSpan::Always),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+/// Whether a CFD still has to be settled or committed for the wind-down to complete
+fn is_open(state: CfdState) -> bool {
+    matches!(
+        state,
+        CfdState::PendingSetup
+            | CfdState::ContractSetup
+            | CfdState::PendingOpen
+            | CfdState::Open
+            | CfdState::IncomingSettlementProposal
+            | CfdState::OutgoingSettlementProposal
+            | CfdState::RolloverSetup
+    )
+}
@@ -0,0 +1,523 @@
+//! Withdrawals from the maker's wallet through the API.
+//!
+//! Withdrawals are broadcast right away as long as they, together with all withdrawals of the last
+//! [`THRESHOLD_WINDOW`], stay within the approval threshold. Other withdrawals, as well as
+//! withdrawals of the whole balance, are created as pending requests that have to be approved with
+//! a time-based one-time password (TOTP) within [`APPROVAL_TIMEOUT`]. The TOTP secret is
+//! provisioned through the API and can be added to any authenticator app.
+//!
+//! A request is denied after [`MAX_FAILED_ATTEMPTS_PER_REQUEST`] invalid one-time passwords. After
+//! [`MAX_FAILED_ATTEMPTS`] invalid one-time passwords within [`FAILED_ATTEMPTS_WINDOW`], no
+//! one-time password is checked until the window passed.
+//!
+//! All requests, approvals and failed approval attempts are recorded in an audit log.
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use bdk::bitcoin::Address;
+use bdk::bitcoin::Amount;
+use bdk::bitcoin::Txid;
+use bdk::FeeRate;
+use daemon::wallet;
+use model::Timestamp;
+use serde::Serialize;
+use sqlite_db::withdrawals::WithdrawalAction;
+use sqlite_db::withdrawals::WithdrawalAuditEntry;
+use sqlite_db::withdrawals::WithdrawalRequest;
+use sqlite_db::withdrawals::WithdrawalStatus;
+use std::collections::VecDeque;
+use std::time::Duration;
+use xtra::message_channel::MessageChannel;
+use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+/// Time after which a pending withdrawal request can no longer be approved
+pub const APPROVAL_TIMEOUT: time::Duration = time::Duration::minutes(15);
+
+/// Period over which withdrawals are summed up to compare them with the approval threshold
+pub const THRESHOLD_WINDOW: time::Duration = time::Duration::hours(24);
+
+/// Number of invalid one-time passwords after which a withdrawal request is denied
+pub const MAX_FAILED_ATTEMPTS_PER_REQUEST: usize = 3;
+
+/// Number of invalid one-time passwords within [`FAILED_ATTEMPTS_WINDOW`] after which no further
+/// one-time password is checked
+pub const MAX_FAILED_ATTEMPTS: usize = 10;
+
+pub const FAILED_ATTEMPTS_WINDOW: time::Duration = time::Duration::minutes(15);
+
+/// Interval at which pending withdrawal requests are checked for expiry
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+pub struct Actor {
+    db: sqlite_db::Connection,
+    wallet: MessageChannel<wallet::Withdraw, Result<Txid>>,
+    /// Withdrawals above this amount within [`THRESHOLD_WINDOW`] need approval, `None` if no
+    /// withdrawal needs approval
+    approval_threshold: Option<Amount>,
+    /// Times of the invalid one-time passwords within [`FAILED_ATTEMPTS_WINDOW`], oldest first
+    failed_attempts: VecDeque<Timestamp>,
+}
+
+impl Actor {
+    pub fn new(
+        db: sqlite_db::Connection,
+        wallet: MessageChannel<wallet::Withdraw, Result<Txid>>,
+        approval_threshold: Option<Amount>,
+    ) -> Self {
+        Self {
+            db,
+            wallet,
+            approval_threshold,
+            failed_attempts: VecDeque::new(),
+        }
+    }
+
+    /// Whether the withdrawal, together with the withdrawals of the last [`THRESHOLD_WINDOW`],
+    /// exceeds the approval threshold.
+    async fn needs_approval(&self, amount: Option<Amount>) -> Result<bool> {
+        let (threshold, amount) = match (self.approval_threshold, amount) {
+            (None, _) => return Ok(false),
+            (Some(_), None) => return Ok(true),
+            (Some(threshold), Some(amount)) => (threshold, amount),
+        };
+
+        let window_start =
+            Timestamp::new(Timestamp::now().seconds() - THRESHOLD_WINDOW.whole_seconds());
+
+        let mut withdrawn = Amount::ZERO;
+        for request in self.db.load_withdrawal_requests().await? {
+            if request.status != WithdrawalStatus::Completed || request.created_at < window_start {
+                continue;
+            }
+
+            // Withdrawals of the whole balance exceed any threshold
+            match request.amount {
+                Some(amount) => withdrawn += amount,
+                None => return Ok(true),
+            }
+        }
+
+        Ok(withdrawn + amount > threshold)
+    }
+
+    /// Check `code` against the provisioned secret, every code is only accepted once.
+    ///
+    /// Fails without checking the code if there were too many invalid codes recently.
+    async fn verify_code(&mut self, code: &str) -> Result<bool> {
+        let now = Timestamp::now();
+        let window_start = now.seconds() - FAILED_ATTEMPTS_WINDOW.whole_seconds();
+        while matches!(self.failed_attempts.front(), Some(attempt) if attempt.seconds() < window_start)
+        {
+            self.failed_attempts.pop_front();
+        }
+
+        if self.failed_attempts.len() >= MAX_FAILED_ATTEMPTS {
+            bail!(
+                "Too many invalid one-time passwords, try again in {} minutes",
+                FAILED_ATTEMPTS_WINDOW.whole_minutes()
+            );
+        }
+
+        let secret = self
+            .db
+            .load_totp_secret()
+            .await?
+            .context("No TOTP secret provisioned")?;
+
+        let is_valid = match totp::verify(&secret.secret, code, now) {
+            Some(step) => self.db.use_totp_step(step).await?,
+            None => false,
+        };
+
+        if !is_valid {
+            self.failed_attempts.push_back(now);
+        }
+
+        Ok(is_valid)
+    }
+
+    async fn execute(&self, request: &WithdrawalRequest) -> Result<Txid> {
+        let result = self
+            .wallet
+            .send(wallet::Withdraw {
+                amount: request.amount,
+                address: request.address.clone(),
+                fee: Some(FeeRate::from_sat_per_vb(request.fee_rate)),
+            })
+            .await
+            .context("Wallet actor disconnected")
+            .and_then(|result| result);
+
+        let (status, txid, entry) = match &result {
+            Ok(txid) => (
+                WithdrawalStatus::Completed,
+                Some(*txid),
+                WithdrawalAuditEntry {
+                    request_id: request.id,
+                    action: WithdrawalAction::Broadcast,
+                    detail: Some(txid.to_string()),
+                    timestamp: Timestamp::now(),
+                },
+            ),
+            Err(e) => (
+                WithdrawalStatus::Failed,
+                None,
+                WithdrawalAuditEntry {
+                    request_id: request.id,
+                    action: WithdrawalAction::Failed,
+                    detail: Some(format!("{e:#}")),
+                    timestamp: Timestamp::now(),
+                },
+            ),
+        };
+        self.db
+            .update_withdrawal_request(status, txid, &entry)
+            .await?;
+
+        result
+    }
+
+    async fn expire_requests(&self) -> Result<()> {
+        let now = Timestamp::now();
+
+        for request in self.db.load_withdrawal_requests().await? {
+            if request.status != WithdrawalStatus::Pending || request.expires_at > now {
+                continue;
+            }
+
+            tracing::info!(id = request.id, "Withdrawal request expired");
+
+            self.db
+                .update_withdrawal_request(
+                    WithdrawalStatus::Expired,
+                    None,
+                    &WithdrawalAuditEntry {
+                        request_id: request.id,
+                        action: WithdrawalAction::Expired,
+                        detail: None,
+                        timestamp: now,
+                    },
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we just started");
+
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(
+                EXPIRY_CHECK_INTERVAL,
+                || ExpireRequests,
+                xtras::IncludeSpan::Never,
+            ),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+/// Withdraw from the wallet, pending approval if above the threshold.
+pub struct RequestWithdrawal {
+    /// `None` to withdraw the whole balance
+    pub amount: Option<Amount>,
+    pub address: Address,
+    /// Fee rate in satoshis per vbyte
+    pub fee_rate: f32,
+}
+
+/// Approve a pending withdrawal request with a one-time password.
+pub struct ApproveWithdrawal {
+    pub id: i64,
+    pub code: String,
+}
+
+/// Generate a new TOTP secret.
+///
+/// If a secret was provisioned already, replacing it requires a one-time password of the current
+/// secret.
+pub struct ProvisionTotp {
+    pub code: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+pub struct GetWithdrawals;
+
+#[derive(Clone, Copy)]
+struct ExpireRequests;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum WithdrawalOutcome {
+    Broadcast { txid: Txid },
+    PendingApproval { id: i64, expires_at: Timestamp },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TotpProvisioning {
+    /// The base32 encoded secret
+    pub secret: String,
+    /// The `otpauth` URI, usually rendered as QR code
+    pub uri: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Withdrawals {
+    pub requests: Vec<WithdrawalRequest>,
+    pub audit_log: Vec<WithdrawalAuditEntry>,
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: RequestWithdrawal) -> Result<WithdrawalOutcome> {
+        let RequestWithdrawal {
+            amount,
+            address,
+            fee_rate,
+        } = msg;
+
+        let needs_approval = self.needs_approval(amount).await?;
+        if needs_approval && self.db.load_totp_secret().await?.is_none() {
+            bail!("Withdrawals above the approval threshold require a provisioned TOTP secret");
+        }
+
+        let created_at = Timestamp::now();
+        let expires_at = Timestamp::new(created_at.seconds() + APPROVAL_TIMEOUT.whole_seconds());
+        let id = self
+            .db
+            .insert_withdrawal_request(amount, &address, fee_rate, created_at, expires_at)
+            .await?;
+
+        if needs_approval {
+            tracing::info!(id, ?amount, %address, "Withdrawal request pending approval");
+            return Ok(WithdrawalOutcome::PendingApproval { id, expires_at });
+        }
+
+        let request = self.db.load_withdrawal_request(id).await?;
+        let txid = self.execute(&request).await?;
+
+        Ok(WithdrawalOutcome::Broadcast { txid })
+    }
+
+    async fn handle(&mut self, msg: ApproveWithdrawal) -> Result<Txid> {
+        let ApproveWithdrawal { id, code } = msg;
+
+        let request = self.db.load_withdrawal_request(id).await?;
+        if request.status != WithdrawalStatus::Pending {
+            bail!("Withdrawal request {id} is {}", request.status);
+        }
+        if request.expires_at <= Timestamp::now() {
+            self.expire_requests().await?;
+            bail!("Withdrawal request {id} expired");
+        }
+
+        if !self.verify_code(&code).await? {
+            tracing::warn!(id, "Withdrawal approval with invalid one-time password");
+
+            self.db
+                .insert_withdrawal_audit_entry(&WithdrawalAuditEntry {
+                    request_id: id,
+                    action: WithdrawalAction::ApprovalDenied,
+                    detail: Some("Invalid one-time password".to_string()),
+                    timestamp: Timestamp::now(),
+                })
+                .await?;
+
+            let failed_attempts = self
+                .db
+                .load_withdrawal_audit_log()
+                .await?
+                .into_iter()
+                .filter(|entry| {
+                    entry.request_id == id && entry.action == WithdrawalAction::ApprovalDenied
+                })
+                .count();
+
+            if failed_attempts >= MAX_FAILED_ATTEMPTS_PER_REQUEST {
+                tracing::warn!(
+                    id,
+                    "Withdrawal request denied after {failed_attempts} invalid one-time passwords"
+                );
+
+                self.db
+                    .update_withdrawal_request(
+                        WithdrawalStatus::Denied,
+                        None,
+                        &WithdrawalAuditEntry {
+                            request_id: id,
+                            action: WithdrawalAction::Denied,
+                            detail: Some(format!("{failed_attempts} invalid one-time passwords")),
+                            timestamp: Timestamp::now(),
+                        },
+                    )
+                    .await?;
+                bail!("Invalid one-time password, withdrawal request {id} denied");
+            }
+
+            bail!("Invalid one-time password");
+        }
+
+        tracing::info!(id, "Withdrawal request approved");
+
+        self.db
+            .insert_withdrawal_audit_entry(&WithdrawalAuditEntry {
+                request_id: id,
+                action: WithdrawalAction::Approved,
+                detail: None,
+                timestamp: Timestamp::now(),
+            })
+            .await?;
+
+        self.execute(&request).await
+    }
+
+    async fn handle(&mut self, msg: ProvisionTotp) -> Result<TotpProvisioning> {
+        if self.db.load_totp_secret().await?.is_some() {
+            let code = msg
+                .code
+                .context("Replacing the TOTP secret requires a one-time password")?;
+
+            if !self.verify_code(&code).await? {
+                bail!("Invalid one-time password");
+            }
+        }
+
+        let secret = totp::generate_secret();
+        self.db
+            .insert_totp_secret(&secret, Timestamp::now())
+            .await?;
+
+        tracing::info!("Provisioned new TOTP secret for withdrawal approvals");
+
+        Ok(TotpProvisioning {
+            secret: totp::encode_secret(&secret),
+            uri: totp::uri(&secret),
+        })
+    }
+
+    async fn handle(&mut self, _: GetWithdrawals) -> Result<Withdrawals> {
+        Ok(Withdrawals {
+            requests: self.db.load_withdrawal_requests().await?,
+            audit_log: self.db.load_withdrawal_audit_log().await?,
+        })
+    }
+
+    async fn handle(&mut self, _: ExpireRequests) {
+        if let Err(e) = self.expire_requests().await {
+            tracing::warn!("Failed to expire withdrawal requests: {e:#}");
+        }
+    }
+}
+
+/// Time-based one-time passwords as specified in RFC 6238, compatible with common authenticator
+/// apps.
+mod totp {
+    use hmac::Hmac;
+    use hmac::Mac;
+    use model::Timestamp;
+    use rand::Rng;
+    use sha1::Sha1;
+    use subtle::ConstantTimeEq;
+
+    const SECRET_LEN: usize = 20;
+    const STEP_SECS: i64 = 30;
+    const DIGITS: u32 = 6;
+    /// Number of steps a code may be off to account for clock drift
+    const ALLOWED_DRIFT: i64 = 1;
+
+    pub fn generate_secret() -> Vec<u8> {
+        let mut secret = [0u8; SECRET_LEN];
+        rand::thread_rng().fill(&mut secret);
+
+        secret.to_vec()
+    }
+
+    pub fn encode_secret(secret: &[u8]) -> String {
+        base32::encode(base32::Alphabet::RFC4648 { padding: false }, secret)
+    }
+
+    pub fn uri(secret: &[u8]) -> String {
+        format!(
+            "otpauth://totp/ItchySats:maker?secret={}&issuer=ItchySats&digits={DIGITS}&period={STEP_SECS}",
+            encode_secret(secret)
+        )
+    }
+
+    /// Returns the time step the code belongs to if it is valid at `now`.
+    ///
+    /// The code is compared with the codes of all steps in constant time, so that the time it
+    /// takes does not reveal how much of the code was right.
+    pub fn verify(secret: &[u8], code: &str, now: Timestamp) -> Option<u64> {
+        let current = now.seconds() / STEP_SECS;
+
+        (current - ALLOWED_DRIFT..=current + ALLOWED_DRIFT)
+            .filter_map(|step| u64::try_from(step).ok())
+            .fold(None, |valid_step, step| {
+                let is_match = bool::from(code_at(secret, step).as_bytes().ct_eq(code.as_bytes()));
+
+                valid_step.or(is_match.then_some(step))
+            })
+    }
+
+    fn code_at(secret: &[u8], step: u64) -> String {
+        let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any size");
+        mac.update(&step.to_be_bytes());
+        let hash = mac.finalize().into_bytes();
+
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes([
+            hash[offset] & 0x7f,
+            hash[offset + 1],
+            hash[offset + 2],
+            hash[offset + 3],
+        ]);
+
+        format!(
+            "{:0width$}",
+            binary % 10u32.pow(DIGITS),
+            width = DIGITS as usize
+        )
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// Test vectors for SHA1 from RFC 6238, truncated to six digits
+        const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+        #[test]
+        fn codes_match_rfc_test_vectors() {
+            assert_eq!(code_at(RFC_SECRET, 59 / 30), "287082");
+            assert_eq!(code_at(RFC_SECRET, 1111111109 / 30), "081804");
+            assert_eq!(code_at(RFC_SECRET, 1234567890 / 30), "005924");
+            assert_eq!(code_at(RFC_SECRET, 2000000000 / 30), "279037");
+        }
+
+        #[test]
+        fn code_of_neighbouring_step_is_accepted_to_allow_for_drift() {
+            let now = Timestamp::new(1234567890);
+
+            assert_eq!(verify(RFC_SECRET, "005924", now), Some(1234567890 / 30));
+            assert_eq!(
+                verify(RFC_SECRET, "005924", Timestamp::new(1234567890 + 30)),
+                Some(1234567890 / 30)
+            );
+            assert_eq!(
+                verify(RFC_SECRET, "005924", Timestamp::new(1234567890 + 90)),
+                None
+            );
+        }
+    }
+}
@@ -7,19 +7,24 @@ use crate::payout_curve::inverse;
 use crate::payout_curve::quanto;
 use crate::payout_curve::InverseMaxPrice;
 use crate::payout_curve::Payouts;
-use crate::payout_curve::ETHUSD_MULTIPLIER;
 use crate::rollover::BaseDlcParams;
+use crate::rollover::FundingRateCheck;
 use crate::rollover::RolloverParams;
+use crate::symbols;
+use crate::symbols::PayoutCurve;
+use crate::trading_calendar::TradingCalendar;
 use crate::CompleteFee;
 use crate::ContractSymbol;
 use crate::Contracts;
 use crate::FeeAccount;
 use crate::FundingFee;
+use crate::FundingPeriod;
 use crate::FundingRate;
 use crate::Identity;
 use crate::Leverage;
 use crate::LotSize;
 use crate::OpeningFee;
+use crate::OpeningFeeTiers;
 use crate::Percent;
 use crate::Position;
 use crate::Price;
@@ -56,8 +61,11 @@ use rust_decimal_macros::dec;
 use serde::de::Error as _;
 use serde::Deserialize;
 use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::fmt;
+use std::hash::Hash;
+use std::hash::Hasher;
 use std::ops::RangeInclusive;
 use std::str;
 use time::Duration;
@@ -146,6 +154,13 @@ pub struct Offer {
     pub id: OfferId,
 
     pub contract_symbol: ContractSymbol,
+    /// Multiplier in BTC per unit of the quote currency the payout of quanto contracts is scaled
+    /// with
+    ///
+    /// `None` for inverse contracts and for offers of makers that do not send the multiplier yet,
+    /// in which case the multiplier from the symbol registry applies.
+    #[serde(default)]
+    pub quanto_multiplier: Option<Decimal>,
 
     /// The maker's position
     pub position_maker: Position,
@@ -154,6 +169,9 @@ pub struct Offer {
 
     pub min_quantity: Contracts,
     pub max_quantity: Contracts,
+    /// Largest quantity a single order may have, regardless of `max_quantity`
+    #[serde(default)]
+    pub max_contracts_per_order: Option<Contracts>,
 
     /// A selection of leverages that the maker allows for the taker
     pub leverage_choices: Vec<Leverage>,
@@ -161,6 +179,13 @@ pub struct Offer {
     /// The creation timestamp as set by the maker
     pub creation_timestamp_maker: Timestamp,
 
+    /// Time after `creation_timestamp_maker` at which takers drop the offer, never if `None`
+    ///
+    /// Makers re-issue such offers before they expire, see [`Offer::reissue`], hence they only
+    /// expire if the maker stops sending them.
+    #[serde(default)]
+    pub time_to_live: Option<Duration>,
+
     /// The duration that will be used for calculating the settlement timestamp
     pub settlement_interval: Duration,
 
@@ -171,8 +196,57 @@ pub struct Offer {
 
     pub tx_fee_rate: TxFeeRate,
     pub funding_rate: FundingRate,
+    pub funding_period: FundingPeriod,
+    /// Opening fee charged for orders below the smallest of the `opening_fee_tiers`
     pub opening_fee: OpeningFee,
+    #[serde(default)]
+    pub opening_fee_tiers: OpeningFeeTiers,
     pub lot_size: LotSize,
+
+    /// Number of times the offer was revised in place, see [`Offer::revise`]
+    ///
+    /// Orders referring to an earlier revision of the offer are rejected by the maker.
+    #[serde(default)]
+    pub revision: u32,
+
+    /// Weekly hours during which the maker accepts orders for the offer, always if `None`
+    #[serde(default)]
+    pub trading_calendar: Option<TradingCalendar>,
+}
+
+/// Partial update of the parameters of an offer that keeps the id of the offer.
+///
+/// Fields that are `None` are left untouched.
+#[cfg(any(test, feature = "test-utils"))]
+impl Offer {
+    /// BTCUSD offer of a short maker, for tests
+    pub fn dummy() -> Self {
+        Self::dummy_short(ContractSymbol::BtcUsd)
+    }
+
+    /// Offer of a short maker on the given contract, for tests
+    pub fn dummy_short(contract_symbol: ContractSymbol) -> Self {
+        Offer::new(
+            Position::Short,
+            Price::new(dec!(1000)).unwrap(),
+            Contracts::new(100),
+            Contracts::new(1000),
+            Duration::hours(24),
+            TxFeeRate::default(),
+            FundingRate::default(),
+            OpeningFee::default(),
+            vec![Leverage::TWO],
+            contract_symbol,
+            LotSize::new(100),
+            FundingPeriod::Hourly,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OfferUpdate {
+    pub leverage_choices: Option<Vec<Leverage>>,
+    pub lot_size: Option<LotSize>,
 }
 
 impl Offer {
@@ -189,6 +263,7 @@ impl Offer {
         leverage_choices: Vec<Leverage>,
         contract_symbol: ContractSymbol,
         lot_size: LotSize,
+        funding_period: FundingPeriod,
     ) -> Self {
         let oracle_event_id = olivia::next_announcement_after(
             time::OffsetDateTime::now_utc() + settlement_interval,
@@ -200,24 +275,117 @@ impl Offer {
             price,
             min_quantity,
             max_quantity,
+            max_contracts_per_order: None,
             leverage_choices,
             contract_symbol,
+            quanto_multiplier: symbols::config(contract_symbol)
+                .payout_curve
+                .quanto_multiplier(),
             position_maker,
             creation_timestamp_maker: Timestamp::now(),
+            time_to_live: None,
             settlement_interval,
             oracle_event_id,
             tx_fee_rate,
             funding_rate,
+            funding_period,
             opening_fee,
+            opening_fee_tiers: OpeningFeeTiers::default(),
+            lot_size,
+            revision: 0,
+            trading_calendar: None,
+        }
+    }
+
+    pub fn with_opening_fee_tiers(self, opening_fee_tiers: OpeningFeeTiers) -> Self {
+        Self {
+            opening_fee_tiers,
+            ..self
+        }
+    }
+
+    pub fn with_max_contracts_per_order(self, max_contracts_per_order: Option<Contracts>) -> Self {
+        Self {
+            max_contracts_per_order,
+            ..self
+        }
+    }
+
+    pub fn with_trading_calendar(self, trading_calendar: Option<TradingCalendar>) -> Self {
+        Self {
+            trading_calendar,
+            ..self
+        }
+    }
+
+    pub fn with_time_to_live(self, time_to_live: Option<Duration>) -> Self {
+        Self {
+            time_to_live,
+            ..self
+        }
+    }
+
+    /// Whether the time-to-live of the offer elapsed at `now`.
+    pub fn is_expired(&self, now: Timestamp) -> bool {
+        self.time_to_live.map_or(false, |time_to_live| {
+            self.creation_timestamp_maker.seconds() + time_to_live.whole_seconds() <= now.seconds()
+        })
+    }
+
+    /// Issue the offer again at `now`, keeping its id.
+    ///
+    /// Like for a new offer, the oracle event follows from the settlement interval. If that moves
+    /// the offer to a new oracle event, the offer moves to the next revision so that the maker
+    /// rejects orders that still refer to the previous event.
+    pub fn reissue(self, now: OffsetDateTime) -> Self {
+        let oracle_event_id =
+            olivia::next_announcement_after(now + self.settlement_interval, self.contract_symbol);
+        let revision = if oracle_event_id == self.oracle_event_id {
+            self.revision
+        } else {
+            self.revision + 1
+        };
+
+        Self {
+            creation_timestamp_maker: Timestamp::new(now.unix_timestamp()),
+            oracle_event_id,
+            revision,
+            ..self
+        }
+    }
+
+    /// Apply `update` to the offer and move it to the next revision.
+    pub fn revise(self, update: &OfferUpdate) -> Self {
+        let OfferUpdate {
+            leverage_choices,
             lot_size,
+        } = update;
+
+        Self {
+            leverage_choices: leverage_choices.clone().unwrap_or(self.leverage_choices),
+            lot_size: lot_size.unwrap_or(self.lot_size),
+            revision: self.revision + 1,
+            ..self
         }
     }
 
+    /// The payout curve of CFDs opened from this offer.
+    pub fn payout_curve(&self) -> PayoutCurve {
+        symbols::payout_curve(self.contract_symbol, self.quanto_multiplier)
+    }
+
+    /// The opening fee of an order of `quantity` contracts.
+    pub fn opening_fee_for(&self, quantity: Contracts) -> OpeningFee {
+        self.opening_fee_tiers
+            .fee(self.payout_curve(), self.price, quantity)
+            .unwrap_or(self.opening_fee)
+    }
+
     /// Defines when we consider an order to be outdated
     ///
     /// If the maker's offer creation timestamp is older than `OUTDATED_AFTER_MINS` minutes then we
     /// consider an order to be outdated.
-    const OUTDATED_AFTER_MINS: i64 = 10;
+    pub const OUTDATED_AFTER_MINS: i64 = 10;
 
     /// Defines when we consider the order to be outdated.
     ///
@@ -260,6 +428,36 @@ pub struct SettlementProposal {
     pub price: Price,
 }
 
+/// Comparison of the price the maker proposes to settle a CFD at with the price the taker closes
+/// the position at according to its latest quote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SettlementPriceCheck {
+    pub proposed: Price,
+    pub quoted: Price,
+    /// Maximum deviation of the proposed from the quoted price, relative to the quoted price.
+    ///
+    /// `None` if proposals of the maker are never accepted automatically.
+    pub tolerance: Option<Decimal>,
+}
+
+impl SettlementPriceCheck {
+    pub fn new(proposed: Price, quoted: Price, tolerance: Option<Decimal>) -> Self {
+        Self {
+            proposed,
+            quoted,
+            tolerance,
+        }
+    }
+
+    pub fn is_within_tolerance(&self) -> bool {
+        let quoted = self.quoted.into_decimal();
+        let deviation = (self.proposed.into_decimal() - quoted).abs();
+
+        self.tolerance
+            .map_or(false, |tolerance| deviation <= tolerance * quoted)
+    }
+}
+
 /// Reasons why we cannot rollover a CFD.
 #[derive(thiserror::Error, Debug, PartialEq, Eq, Clone, Copy)]
 pub enum CannotRollover {
@@ -279,6 +477,14 @@ pub enum CannotRollover {
     NoEvents,
 }
 
+/// Price level at which the taker settles a CFD automatically
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceLevel {
+    TakeProfit,
+    StopLoss,
+}
+
 /// Reasons why we cannot collab close a CFD
 #[derive(thiserror::Error, Debug, PartialEq, Eq, Clone, Copy)]
 pub enum CannotSettleCollaboratively {
@@ -340,9 +546,23 @@ pub enum EventKind {
         complete_fee: Option<CompleteFee>,
     },
     RolloverFailed,
+    /// The taker rejected the rollover because the funding rate proposed by the maker is outside
+    /// of the tolerance band around the funding rate of the maker's latest offer
+    RolloverFundingRateRejected {
+        check: FundingRateCheck,
+    },
 
+    /// The maker proposed to settle the CFD collaboratively. The taker settles automatically if
+    /// the proposed price is within the tolerance of its latest quote, otherwise the proposal is
+    /// left for the user to decide on
+    SettlementProposedByMaker {
+        check: SettlementPriceCheck,
+    },
     CollaborativeSettlementStarted {
         proposal: SettlementProposal,
+        /// External address the taker's payout is sent to instead of the taker's wallet
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        payout_address: Option<Address>,
     },
     CollaborativeSettlementProposalAccepted,
     CollaborativeSettlementCompleted {
@@ -401,6 +621,23 @@ pub enum EventKind {
         #[serde(with = "hex_transaction")]
         tx: Transaction,
     },
+    /// A CET of the DLC we did not publish was found on chain
+    ///
+    /// The counterparty published it, possibly while we were offline. The CET pays out according
+    /// to the price interval `range` of the oracle event `event_id`. The attested price is only
+    /// known if the interval consists of a single price.
+    CetPublishedByCounterparty {
+        #[serde(with = "hex_transaction")]
+        cet: Transaction,
+        event_id: BitMexPriceEventId,
+        range: RangeInclusive<u64>,
+        price: Option<Price>,
+    },
+    /// The taker set the price levels at which the CFD is settled automatically
+    PriceLevelsSet {
+        take_profit: Option<Price>,
+        stop_loss: Option<Price>,
+    },
 }
 
 impl fmt::Display for EventKind {
@@ -416,6 +653,8 @@ impl fmt::Display for EventKind {
             RolloverRejected => "RolloverRejected",
             RolloverCompleted { .. } => "RolloverCompleted",
             RolloverFailed => "RolloverFailed",
+            RolloverFundingRateRejected { .. } => "RolloverFundingRateRejected",
+            SettlementProposedByMaker { .. } => "SettlementProposedByMaker",
             CollaborativeSettlementStarted { .. } => "CollaborativeSettlementStarted",
             CollaborativeSettlementProposalAccepted => "CollaborativeSettlementProposalAccepted",
             CollaborativeSettlementCompleted { .. } => "CollaborativeSettlementCompleted",
@@ -436,6 +675,8 @@ impl fmt::Display for EventKind {
             OracleAttestedPriorCetTimelock { .. } => "OracleAttestedPriorCetTimelock",
             OracleAttestedPostCetTimelock { .. } => "OracleAttestedPostCetTimelock",
             ManualCommit { .. } => "ManualCommit",
+            CetPublishedByCounterparty { .. } => "CetPublishedByCounterparty",
+            PriceLevelsSet { .. } => "PriceLevelsSet",
         };
 
         s.fmt(f)
@@ -508,6 +749,8 @@ pub struct Cfd {
     opening_fee: OpeningFee,
     initial_tx_fee_rate: TxFeeRate,
     contract_symbol: ContractSymbol,
+    payout_curve: PayoutCurve,
+    funding_period: FundingPeriod,
     // dynamic (based on events)
     fee_account: FeeAccount,
 
@@ -542,6 +785,11 @@ pub struct Cfd {
     during_contract_setup: bool,
     during_rollover: bool,
     settlement_proposal: Option<SettlementProposal>,
+    /// External address the taker's payout of the pending settlement is sent to
+    settlement_payout_address: Option<Address>,
+
+    take_profit: Option<Price>,
+    stop_loss: Option<Price>,
 }
 
 impl Cfd {
@@ -563,9 +811,12 @@ impl Cfd {
         initial_funding_rate: FundingRate,
         initial_tx_fee_rate: TxFeeRate,
         contract_symbol: ContractSymbol,
+        funding_period: FundingPeriod,
+        quanto_multiplier: Option<Decimal>,
     ) -> Self {
         let (long_leverage, short_leverage) =
             long_and_short_leverage(taker_leverage, role, position);
+        let payout_curve = symbols::payout_curve(contract_symbol, quanto_multiplier);
 
         let initial_funding_fee = FundingFee::calculate(
             initial_price,
@@ -574,7 +825,7 @@ impl Cfd {
             short_leverage,
             initial_funding_rate,
             SETTLEMENT_INTERVAL.whole_hours(),
-            contract_symbol,
+            payout_curve,
         )
         .expect("values from db to be sane");
 
@@ -595,6 +846,8 @@ impl Cfd {
             opening_fee,
             initial_tx_fee_rate,
             contract_symbol,
+            payout_curve,
+            funding_period,
             dlc: None,
             cet: None,
             commit_tx: None,
@@ -610,6 +863,9 @@ impl Cfd {
             during_contract_setup: false,
             during_rollover: false,
             settlement_proposal: None,
+            settlement_payout_address: None,
+            take_profit: None,
+            stop_loss: None,
             fee_account: FeeAccount::new(position, role)
                 .add_opening_fee(opening_fee)
                 .add_funding_fee(initial_funding_fee),
@@ -642,23 +898,25 @@ impl Cfd {
             quantity,
             counterparty_network_identity,
             counterparty_peer_id,
-            offer.opening_fee,
+            offer.opening_fee_for(quantity),
             offer.funding_rate,
             offer.tx_fee_rate,
             offer.contract_symbol,
+            offer.funding_period,
+            offer.quanto_multiplier,
         )
     }
 
     fn margin(&self) -> Amount {
         match self.position {
             Position::Long => calculate_margin(
-                self.contract_symbol,
+                self.payout_curve,
                 self.initial_price,
                 self.quantity,
                 self.long_leverage,
             ),
             Position::Short => calculate_margin(
-                self.contract_symbol,
+                self.payout_curve,
                 self.initial_price,
                 self.quantity,
                 self.short_leverage,
@@ -669,13 +927,13 @@ impl Cfd {
     fn counterparty_margin(&self) -> Amount {
         match self.position {
             Position::Long => calculate_margin(
-                self.contract_symbol,
+                self.payout_curve,
                 self.initial_price,
                 self.quantity,
                 self.short_leverage,
             ),
             Position::Short => calculate_margin(
-                self.contract_symbol,
+                self.payout_curve,
                 self.initial_price,
                 self.quantity,
                 self.long_leverage,
@@ -683,28 +941,53 @@ impl Cfd {
         }
     }
 
-    fn is_in_collaborative_settlement(&self) -> bool {
+    pub fn is_in_collaborative_settlement(&self) -> bool {
         self.settlement_proposal.is_some()
     }
 
-    fn is_in_force_close(&self) -> bool {
+    pub fn is_in_force_close(&self) -> bool {
         self.commit_tx.is_some()
     }
 
+    /// Since when the attestation of the settlement event is considered missing.
+    ///
+    /// The attestation is considered missing once `grace_period` passed after the settlement
+    /// event. Returns `None` if the attestation is not overdue or the CFD does not depend on it.
+    pub fn attestation_missing_since(
+        &self,
+        now: OffsetDateTime,
+        grace_period: Duration,
+    ) -> Option<OffsetDateTime> {
+        if self.is_closed() || self.cet.is_some() || self.is_in_collaborative_settlement() {
+            return None;
+        }
+
+        let dlc = self.dlc.as_ref()?;
+        let missing_since = dlc.settlement_event_id.timestamp() + grace_period;
+
+        (now >= missing_since).then_some(missing_since)
+    }
+
     pub fn can_auto_rollover_taker(
         &self,
         now: OffsetDateTime,
     ) -> Result<(Txid, BitMexPriceEventId), CannotRollover> {
-        self.can_rollover()?;
+        let (commit_txid, settlement_event_id) = self.can_rollover_taker()?;
 
-        let dlc = self.dlc.as_ref().ok_or(CannotRollover::NoDlc)?;
-
-        let expiry_timestamp = dlc.settlement_event_id.timestamp();
-        let time_until_expiry = expiry_timestamp - now;
+        let time_until_expiry = settlement_event_id.timestamp() - now;
         if time_until_expiry > SETTLEMENT_INTERVAL - Duration::HOUR {
             return Err(CannotRollover::TooRecent);
         }
 
+        Ok((commit_txid, settlement_event_id))
+    }
+
+    /// Whether the taker can propose a rollover, regardless of when the CFD expires.
+    pub fn can_rollover_taker(&self) -> Result<(Txid, BitMexPriceEventId), CannotRollover> {
+        self.can_rollover()?;
+
+        let dlc = self.dlc.as_ref().ok_or(CannotRollover::NoDlc)?;
+
         Ok((dlc.commit.0.txid(), dlc.settlement_event_id))
     }
 
@@ -762,6 +1045,73 @@ impl Cfd {
         self.cet.is_some()
     }
 
+    /// Set the price levels at which the taker settles the CFD automatically.
+    ///
+    /// Passing `None` for a level removes it.
+    pub fn set_price_levels(
+        &self,
+        take_profit: Option<Price>,
+        stop_loss: Option<Price>,
+    ) -> Result<CfdEvent> {
+        ensure!(
+            self.role == Role::Taker,
+            "Only the taker can set price levels"
+        );
+        self.can_settle_collaboratively()
+            .context("Cannot set price levels")?;
+
+        if let (Some(take_profit), Some(stop_loss)) = (take_profit, stop_loss) {
+            match self.position {
+                Position::Long => ensure!(
+                    stop_loss < take_profit,
+                    "Stop-loss {stop_loss} of a long position has to be below take-profit {take_profit}"
+                ),
+                Position::Short => ensure!(
+                    stop_loss > take_profit,
+                    "Stop-loss {stop_loss} of a short position has to be above take-profit {take_profit}"
+                ),
+            }
+        }
+
+        Ok(CfdEvent::new(
+            self.id,
+            EventKind::PriceLevelsSet {
+                take_profit,
+                stop_loss,
+            },
+        ))
+    }
+
+    /// The price level reached if the CFD was closed at `closing_price`, if any.
+    ///
+    /// Price levels are not considered anymore once the CFD cannot be settled collaboratively.
+    pub fn price_level_reached(&self, closing_price: Price) -> Option<PriceLevel> {
+        if self.can_settle_collaboratively().is_err() {
+            return None;
+        }
+
+        let (take_profit_reached, stop_loss_reached) = match self.position {
+            Position::Long => (
+                self.take_profit
+                    .map_or(false, |price| closing_price >= price),
+                self.stop_loss.map_or(false, |price| closing_price <= price),
+            ),
+            Position::Short => (
+                self.take_profit
+                    .map_or(false, |price| closing_price <= price),
+                self.stop_loss.map_or(false, |price| closing_price >= price),
+            ),
+        };
+
+        if stop_loss_reached {
+            Some(PriceLevel::StopLoss)
+        } else if take_profit_reached {
+            Some(PriceLevel::TakeProfit)
+        } else {
+            None
+        }
+    }
+
     /// Any transaction spending from lock has reached finality on the blockchain
     fn is_final(&self) -> bool {
         self.collaborative_settlement_finality || self.cet_finality || self.refund_finality
@@ -800,6 +1150,7 @@ impl Cfd {
             CfdEvent::new(self.id(), EventKind::ContractSetupStarted),
             SetupParams::new(
                 self.contract_symbol,
+                self.payout_curve,
                 self.margin(),
                 self.counterparty_margin(),
                 self.counterparty_network_identity,
@@ -920,7 +1271,7 @@ impl Cfd {
             self.short_leverage,
             funding_rate,
             hours_to_charge as i64,
-            self.contract_symbol,
+            self.payout_curve,
         )?;
 
         tracing::debug!(
@@ -941,6 +1292,7 @@ impl Cfd {
                 tx_fee_rate,
                 rollover_fee_account,
                 funding_fee,
+                self.payout_curve,
             ),
             self.dlc.clone().context("No DLC present")?,
             self.position,
@@ -992,7 +1344,7 @@ impl Cfd {
             self.short_leverage,
             funding_rate,
             hours_to_charge as i64,
-            self.contract_symbol,
+            self.payout_curve,
         )?;
 
         Ok((
@@ -1006,29 +1358,41 @@ impl Cfd {
                 tx_fee_rate,
                 self.fee_account,
                 funding_fee,
+                self.payout_curve,
             ),
             self.dlc.clone().context("No DLC present")?,
             self.position,
         ))
     }
 
+    /// Start a collaborative settlement as the taker.
+    ///
+    /// The taker's payout is sent to `payout_address` if given, otherwise to the taker's wallet.
     pub fn start_collab_settlement_taker(
         self,
         current_price: Price,
         n_payouts: usize,
+        payout_address: Option<Address>,
     ) -> Result<(CfdEvent, SettlementTransaction, SettlementProposal)> {
         ensure!(!self.is_in_collaborative_settlement());
         ensure!(self.role == Role::Taker);
         self.can_settle_collaboratively()
             .context("Cannot collaboratively settle")?;
 
-        let (collab_settlement_tx, proposal) =
-            self.make_proposal(current_price, n_payouts, InverseMaxPrice::OliviaMax)?;
+        let (collab_settlement_tx, proposal) = self.make_proposal(
+            current_price,
+            n_payouts,
+            InverseMaxPrice::OliviaMax,
+            payout_address.as_ref(),
+        )?;
 
         Ok((
             CfdEvent::new(
                 proposal.order_id,
-                EventKind::CollaborativeSettlementStarted { proposal },
+                EventKind::CollaborativeSettlementStarted {
+                    proposal,
+                    payout_address,
+                },
             ),
             collab_settlement_tx,
             proposal,
@@ -1040,17 +1404,21 @@ impl Cfd {
     /// It generates a local [`SettlementProposal`] setting the maximum payout price to Olivia's
     /// maximum attestation price. This assumes that the counterparty has also used the same
     /// configuration.
+    ///
+    /// The taker's payout is sent to the `payout_address` requested by the taker, if any.
     pub fn start_collab_settlement_maker_olivia_max(
         self,
         current_price: Price,
         n_payouts: usize,
         proposed_settlement_transaction: &Transaction,
+        payout_address: Option<Address>,
     ) -> Result<(CfdEvent, SettlementTransaction, SettlementProposal)> {
         self.start_collab_settlement_maker(
             current_price,
             n_payouts,
             proposed_settlement_transaction,
             InverseMaxPrice::OliviaMax,
+            payout_address,
         )
     }
 
@@ -1070,6 +1438,7 @@ impl Cfd {
             n_payouts,
             proposed_settlement_transaction,
             InverseMaxPrice::DoubleOfInitial,
+            None,
         )
     }
 
@@ -1079,14 +1448,19 @@ impl Cfd {
         n_payouts: usize,
         proposed_settlement_transaction: &Transaction,
         inverse_max_price_config: InverseMaxPrice,
+        payout_address: Option<Address>,
     ) -> Result<(CfdEvent, SettlementTransaction, SettlementProposal)> {
         ensure!(!self.is_in_collaborative_settlement());
         ensure!(self.role == Role::Maker);
         self.can_settle_collaboratively()
             .context("Cannot collaboratively settle")?;
 
-        let (settlement_tx, proposal) =
-            self.make_proposal(current_price, n_payouts, inverse_max_price_config)?;
+        let (settlement_tx, proposal) = self.make_proposal(
+            current_price,
+            n_payouts,
+            inverse_max_price_config,
+            payout_address.as_ref(),
+        )?;
 
         let local_settlement_transaction = settlement_tx.unsigned_transaction();
 
@@ -1098,7 +1472,10 @@ impl Cfd {
         Ok((
             CfdEvent::new(
                 proposal.order_id,
-                EventKind::CollaborativeSettlementStarted { proposal },
+                EventKind::CollaborativeSettlementStarted {
+                    proposal,
+                    payout_address,
+                },
             ),
             settlement_tx,
             proposal,
@@ -1110,9 +1487,10 @@ impl Cfd {
         current_price: Price,
         n_payouts: usize,
         inverse_max_price_config: InverseMaxPrice,
+        payout_address: Option<&Address>,
     ) -> Result<(SettlementTransaction, SettlementProposal)> {
-        let payouts = match self.contract_symbol {
-            ContractSymbol::BtcUsd => Payouts::new_inverse(
+        let payouts = match self.payout_curve {
+            PayoutCurve::Inverse => Payouts::new_inverse(
                 (self.position, self.role),
                 self.initial_price,
                 self.quantity,
@@ -1121,13 +1499,13 @@ impl Cfd {
                 self.fee_account.settle(),
                 inverse_max_price_config,
             )?,
-            ContractSymbol::EthUsd => Payouts::new_quanto(
+            PayoutCurve::Quanto { multiplier } => Payouts::new_quanto(
                 (self.position, self.role),
                 self.initial_price.to_u64(),
                 self.quantity.to_u64(),
                 (self.long_leverage, self.short_leverage),
                 n_payouts,
-                ETHUSD_MULTIPLIER,
+                multiplier,
                 self.fee_account.settle(),
             )?,
         }
@@ -1148,6 +1526,7 @@ impl Cfd {
             *payout.taker_amount(),
             current_price,
             self.role,
+            payout_address,
         )?;
 
         let proposal = SettlementProposal {
@@ -1227,6 +1606,34 @@ impl Cfd {
         self.event_with_error(EventKind::RolloverFailed, error)
     }
 
+    pub fn reject_rollover_funding_rate(self, check: FundingRateCheck) -> CfdEvent {
+        let reason = anyhow!(
+            "Proposed funding rate {} deviates from published funding rate {} by more than {}",
+            check.proposed,
+            check.published,
+            check.tolerance
+        );
+
+        self.event_with_error(EventKind::RolloverFundingRateRejected { check }, reason)
+    }
+
+    /// Record a settlement proposal of the maker together with the check that decides whether it
+    /// is accepted automatically.
+    pub fn receive_settlement_proposal(self, check: SettlementPriceCheck) -> Result<CfdEvent> {
+        ensure!(
+            self.role == Role::Taker,
+            "Only the taker receives settlement proposals"
+        );
+        ensure!(
+            !self.is_in_collaborative_settlement(),
+            "Collaborative settlement already in progress"
+        );
+        self.can_settle_collaboratively()
+            .context("Cannot collaboratively settle")?;
+
+        Ok(self.event(EventKind::SettlementProposedByMaker { check }))
+    }
+
     pub fn complete_collaborative_settlement(
         self,
         settlement: CollaborativeSettlement,
@@ -1241,6 +1648,48 @@ impl Cfd {
         }
     }
 
+    /// Complete a collaborative settlement with the transaction finalized by the counterparty.
+    ///
+    /// If the protocol got interrupted after we sent our signature, the counterparty may still have
+    /// finalized and published the settlement transaction. Returns `None` if the CFD is no longer
+    /// in collaborative settlement, e.g. because the settlement was completed in the meantime.
+    pub fn complete_collaborative_settlement_by_counterparty(
+        self,
+        spend_tx: Transaction,
+    ) -> Result<Option<CfdEvent>> {
+        let proposal = match self.settlement_proposal {
+            Some(proposal) => proposal,
+            None => return Ok(None),
+        };
+
+        let dlc = self
+            .dlc
+            .as_ref()
+            .context("Collaborative settlement without DLC")?;
+
+        let (lock_tx, lock_desc) = &dlc.lock;
+        let lock_outpoint = lock_tx
+            .outpoint(&lock_desc.script_pubkey())
+            .expect("lock script to be in lock tx");
+        ensure!(
+            spend_tx
+                .input
+                .iter()
+                .any(|input| input.previous_output == lock_outpoint),
+            "Settlement transaction {} does not spend the lock output",
+            spend_tx.txid()
+        );
+
+        let own_script_pubkey = match (self.role, &self.settlement_payout_address) {
+            (Role::Taker, Some(payout_address)) => payout_address.script_pubkey(),
+            (Role::Taker | Role::Maker, _) => dlc.script_pubkey_for(self.role),
+        };
+
+        let settlement = CollaborativeSettlement::new(spend_tx, own_script_pubkey, proposal.price)?;
+
+        Ok(Some(self.complete_collaborative_settlement(settlement)))
+    }
+
     pub fn reject_collaborative_settlement(self, reason: anyhow::Error) -> CfdEvent {
         self.event_with_error(EventKind::CollaborativeSettlementRejected, reason)
     }
@@ -1301,6 +1750,40 @@ impl Cfd {
         )))
     }
 
+    /// Identify a CET of the DLC that was published by the counterparty.
+    ///
+    /// Returns `Ok(None)` if we already know about the CET, e.g. because we published it ourselves.
+    pub fn handle_counterparty_cet(self, cet: Transaction) -> Result<Option<CfdEvent>> {
+        if self.is_final() {
+            return Ok(None);
+        }
+
+        let txid = cet.txid();
+        if self.cet.as_ref().map(|cet| cet.txid()) == Some(txid) {
+            return Ok(None);
+        }
+
+        let dlc = self.dlc.as_ref().context("CET found without DLC")?;
+        let (event_id, range) = dlc
+            .cets
+            .iter()
+            .flat_map(|(event_id, cets)| cets.iter().map(move |cet| (event_id, cet)))
+            .find(|(_, cet)| cet.txid == txid)
+            .map(|(event_id, cet)| (*event_id, cet.range.clone()))
+            .with_context(|| format!("Transaction {txid} is not a CET of the DLC"))?;
+
+        let price = (range.start() == range.end())
+            .then(|| Price::new(Decimal::from(*range.start())).ok())
+            .flatten();
+
+        Ok(Some(self.event(EventKind::CetPublishedByCounterparty {
+            cet,
+            event_id,
+            range,
+            price,
+        })))
+    }
+
     pub fn handle_cet_timelock_expired(self) -> Result<CfdEvent> {
         ensure!(!self.is_final());
 
@@ -1390,6 +1873,7 @@ impl Cfd {
             | EventKind::CollaborativeSettlementFailed
             | EventKind::OfferRejected
             | EventKind::RolloverRejected
+            | EventKind::RolloverFundingRateRejected { .. }
             | EventKind::CollaborativeSettlementRejected
             | EventKind::CetConfirmed
             | EventKind::RefundConfirmed
@@ -1467,6 +1951,10 @@ impl Cfd {
         self.role
     }
 
+    pub fn dlc(&self) -> Option<&Dlc> {
+        self.dlc.as_ref()
+    }
+
     pub fn initial_funding_rate(&self) -> FundingRate {
         self.initial_funding_rate
     }
@@ -1479,10 +1967,35 @@ impl Cfd {
         self.contract_symbol
     }
 
+    pub fn payout_curve(&self) -> PayoutCurve {
+        self.payout_curve
+    }
+
+    /// The quanto multiplier agreed with the counterparty, `None` for inverse contracts.
+    pub fn quanto_multiplier(&self) -> Option<Decimal> {
+        self.payout_curve.quanto_multiplier()
+    }
+
+    pub fn take_profit(&self) -> Option<Price> {
+        self.take_profit
+    }
+
+    pub fn stop_loss(&self) -> Option<Price> {
+        self.stop_loss
+    }
+
+    pub fn funding_period(&self) -> FundingPeriod {
+        self.funding_period
+    }
+
     pub fn opening_fee(&self) -> OpeningFee {
         self.opening_fee
     }
 
+    pub fn collaborative_settlement_spend_tx(&self) -> Option<&Transaction> {
+        self.collaborative_settlement_spend_tx.as_ref()
+    }
+
     /// Check whether PeerId matches the one the CFD got created with
     pub fn verify_counterparty_peer_id(&self, peer_id: &PeerId) -> Result<()> {
         match self.counterparty_peer_id() {
@@ -1500,6 +2013,22 @@ impl Cfd {
         Ok(())
     }
 
+    /// Check whether the quanto multiplier the counterparty scales the payout with matches ours
+    ///
+    /// Counterparties that do not send the multiplier yet use the one from the symbol registry.
+    pub fn verify_quanto_multiplier(&self, quanto_multiplier: Option<Decimal>) -> Result<()> {
+        if let Some(quanto_multiplier) = quanto_multiplier {
+            ensure!(
+                Some(quanto_multiplier) == self.quanto_multiplier(),
+                "Quanto multiplier mismatch. CFD was created with {:?}, but counterparty uses \
+                 {quanto_multiplier}",
+                self.quanto_multiplier()
+            );
+        }
+
+        Ok(())
+    }
+
     fn hours_to_extend_in_rollover_based_on_event(
         &self,
         to_event_id: BitMexPriceEventId,
@@ -1521,22 +2050,83 @@ impl Cfd {
         let to_settlement_time = to_event_id.timestamp();
         let time_to_extend = to_settlement_time - from_settlement_time;
 
-        let hours_to_extend = time_to_extend.whole_hours();
-
-        if !hours_to_extend.is_positive() {
+        if !time_to_extend.whole_hours().is_positive() {
             bail!(
                 "Cannot rollover if to event ID is not later than from event ID:
                  {to_settlement_time} <= {from_settlement_time}",
             );
         }
 
-        Ok(hours_to_extend as u64)
+        let hours_to_charge = self
+            .funding_period
+            .hours_to_charge(from_settlement_time, to_settlement_time);
+
+        Ok(hours_to_charge as u64)
     }
 
     pub fn version(&self) -> u32 {
         self.version
     }
 
+    /// Hash of the state derived from the events applied to the CFD.
+    ///
+    /// Two instances of the same CFD with different hashes did not end up in the same state, e.g.
+    /// because one of them was built from a stale cache entry.
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+
+        self.version.hash(&mut hasher);
+        self.fee_account.balance().as_sat().hash(&mut hasher);
+
+        if let Some(dlc) = &self.dlc {
+            dlc.lock.0.txid().hash(&mut hasher);
+            dlc.commit.0.txid().hash(&mut hasher);
+            dlc.refund.0.txid().hash(&mut hasher);
+            dlc.settlement_event_id.to_string().hash(&mut hasher);
+            dlc.revoked_commit.len().hash(&mut hasher);
+        }
+
+        for tx in [
+            &self.cet,
+            &self.commit_tx,
+            &self.collaborative_settlement_spend_tx,
+            &self.refund_tx,
+        ] {
+            tx.as_ref().map(|tx| tx.txid()).hash(&mut hasher);
+        }
+
+        [
+            self.lock_finality,
+            self.commit_finality,
+            self.refund_finality,
+            self.cet_finality,
+            self.collaborative_settlement_finality,
+            self.cet_timelock_expired,
+            self.refund_timelock_expired,
+            self.during_contract_setup,
+            self.during_rollover,
+        ]
+        .hash(&mut hasher);
+
+        self.settlement_proposal
+            .map(|proposal| {
+                (
+                    proposal.taker.as_sat(),
+                    proposal.maker.as_sat(),
+                    proposal.price.into_decimal(),
+                )
+            })
+            .hash(&mut hasher);
+        self.settlement_payout_address
+            .as_ref()
+            .map(|address| address.to_string())
+            .hash(&mut hasher);
+        self.take_profit.map(Price::into_decimal).hash(&mut hasher);
+        self.stop_loss.map(Price::into_decimal).hash(&mut hasher);
+
+        hasher.finish()
+    }
+
     pub fn apply(mut self, evt: CfdEvent) -> Cfd {
         use EventKind::*;
 
@@ -1549,6 +2139,11 @@ impl Cfd {
                 self.during_contract_setup = false;
             }
             OracleAttestedPostCetTimelock { cet, .. } => self.cet = Some(cet),
+            CetPublishedByCounterparty { cet, .. } => {
+                self.cet = Some(cet);
+                // The CET can only be published once its timelock expired
+                self.cet_timelock_expired = true;
+            }
             OracleAttestedPriorCetTimelock {
                 timelocked_cet,
                 commit_tx,
@@ -1584,20 +2179,27 @@ impl Cfd {
             RolloverFailed { .. } => {
                 self.during_rollover = false;
             }
-            RolloverRejected => {
+            RolloverRejected | RolloverFundingRateRejected { .. } => {
                 self.during_rollover = false;
             }
 
-            CollaborativeSettlementStarted { proposal } => {
-                self.settlement_proposal = Some(proposal)
+            CollaborativeSettlementStarted {
+                proposal,
+                payout_address,
+            } => {
+                self.settlement_proposal = Some(proposal);
+                self.settlement_payout_address = payout_address;
             }
+            SettlementProposedByMaker { .. } => {}
             CollaborativeSettlementProposalAccepted { .. } => {}
             CollaborativeSettlementCompleted { spend_tx, .. } => {
                 self.settlement_proposal = None;
+                self.settlement_payout_address = None;
                 self.collaborative_settlement_spend_tx = Some(spend_tx);
             }
             CollaborativeSettlementRejected | CollaborativeSettlementFailed => {
                 self.settlement_proposal = None;
+                self.settlement_payout_address = None;
             }
             CetConfirmed => self.cet_finality = true,
             RefundConfirmed => self.refund_finality = true,
@@ -1615,6 +2217,13 @@ impl Cfd {
                 // commands
             }
             ManualCommit { tx } => self.commit_tx = Some(tx),
+            PriceLevelsSet {
+                take_profit,
+                stop_loss,
+            } => {
+                self.take_profit = take_profit;
+                self.stop_loss = stop_loss;
+            }
             RevokeConfirmed => {
                 tracing::error!(order_id = %self.id, "Revoked logic not implemented");
                 // TODO: we should punish the other party instead. For now, we pretend we are in
@@ -1763,18 +2372,18 @@ pub fn market_closing_price(bid: Price, ask: Price, role: Role, position: Positi
 /// The initial margin represents the collateral both parties have to come up with
 /// to satisfy the contract.
 pub fn calculate_margin(
-    contract_symbol: ContractSymbol,
+    payout_curve: impl Into<PayoutCurve>,
     price: Price,
     quantity: Contracts,
     leverage: Leverage,
 ) -> Amount {
-    match contract_symbol {
-        ContractSymbol::BtcUsd => inverse::calculate_margin(price, quantity, leverage),
-        ContractSymbol::EthUsd => quanto::calculate_initial_margin(
+    match payout_curve.into() {
+        PayoutCurve::Inverse => inverse::calculate_margin(price, quantity, leverage),
+        PayoutCurve::Quanto { multiplier } => quanto::calculate_initial_margin(
             price.to_u64(),
             quantity.to_u64(),
             leverage,
-            ETHUSD_MULTIPLIER,
+            multiplier,
         ),
     }
 }
@@ -1787,7 +2396,7 @@ pub fn calculate_margin(
 /// theoretical. There could be slight differences between what we return here and what the payout
 /// curves determine.
 pub fn calculate_payout_at_price(
-    contract_symbol: ContractSymbol,
+    payout_curve: impl Into<PayoutCurve>,
     initial_price: Price,
     closing_price: Price,
     quantity: Contracts,
@@ -1795,8 +2404,8 @@ pub fn calculate_payout_at_price(
     short_leverage: Leverage,
     fee_account: FeeAccount,
 ) -> Result<Amount> {
-    match contract_symbol {
-        ContractSymbol::BtcUsd => inverse::calculate_payout_at_price(
+    match payout_curve.into() {
+        PayoutCurve::Inverse => inverse::calculate_payout_at_price(
             initial_price,
             closing_price,
             quantity,
@@ -1804,9 +2413,7 @@ pub fn calculate_payout_at_price(
             short_leverage,
             fee_account,
         ),
-        ContractSymbol::EthUsd => {
-            let multiplier = ETHUSD_MULTIPLIER;
-
+        PayoutCurve::Quanto { multiplier } => {
             let position = fee_account.position;
             let leverage = match position {
                 Position::Long => long_leverage,
@@ -1861,11 +2468,11 @@ pub fn calculate_long_liquidation_price(
     leverage: Leverage,
     contract_symbol: ContractSymbol,
 ) -> Decimal {
-    match contract_symbol {
-        ContractSymbol::BtcUsd => {
+    match symbols::config(contract_symbol).payout_curve {
+        PayoutCurve::Inverse => {
             inverse::calculate_long_liquidation_price(leverage, initial_price).into_decimal()
         }
-        ContractSymbol::EthUsd => {
+        PayoutCurve::Quanto { .. } => {
             let initial_price = initial_price.to_u64();
 
             let liquidation_price =
@@ -1882,11 +2489,11 @@ pub fn calculate_short_liquidation_price(
     leverage: Leverage,
     contract_symbol: ContractSymbol,
 ) -> Decimal {
-    match contract_symbol {
-        ContractSymbol::BtcUsd => {
+    match symbols::config(contract_symbol).payout_curve {
+        PayoutCurve::Inverse => {
             inverse::calculate_short_liquidation_price(leverage, initial_price).into_decimal()
         }
-        ContractSymbol::EthUsd => {
+        PayoutCurve::Quanto { .. } => {
             let initial_price = initial_price.to_u64();
 
             let liquidation_price =
@@ -2069,13 +2676,30 @@ impl SettlementTransaction {
 }
 
 impl Dlc {
+    /// Build the collaborative settlement transaction.
+    ///
+    /// The taker's payout is sent to `taker_payout_address` if given, otherwise to the taker's
+    /// address of the DLC.
     pub fn collab_settlement_transaction(
         &self,
         payout_maker: Amount,
         payout_taker: Amount,
         current_price: Price,
         role: Role,
+        taker_payout_address: Option<&Address>,
     ) -> Result<SettlementTransaction> {
+        let taker_address = match taker_payout_address {
+            Some(address) => {
+                ensure!(
+                    address.network == self.taker_address.network,
+                    "Payout address {address} is not valid on network {}",
+                    self.taker_address.network
+                );
+                address
+            }
+            None => &self.taker_address,
+        };
+
         let (lock_tx, lock_desc) = &self.lock;
         let (lock_outpoint, lock_amount) = {
             let outpoint = lock_tx
@@ -2091,7 +2715,7 @@ impl Dlc {
             lock_outpoint,
             lock_amount,
             (&self.maker_address, payout_maker),
-            (&self.taker_address, payout_taker),
+            (taker_address, payout_taker),
             1,
         )
         .context("Unable to build collaborative close transaction")?;
@@ -2109,7 +2733,10 @@ impl Dlc {
             price: current_price,
             unsigned_transaction: tx,
             own_pk,
-            own_script_pk: self.script_pubkey_for(role),
+            own_script_pk: match role {
+                Role::Maker => self.maker_address.script_pubkey(),
+                Role::Taker => taker_address.script_pubkey(),
+            },
             own_signature,
             counterparty_pk: self.identity_counterparty,
             counterparty_signature: None,
@@ -3151,6 +3778,89 @@ mod tests {
         );
     }
 
+    #[test]
+    fn given_funding_rate_outside_band_then_rollover_rejected() {
+        let cfd = Cfd::dummy_taker_long()
+            .dummy_open(dummy_event_id())
+            .dummy_start_rollover();
+        let check = FundingRateCheck::new(
+            FundingRate::new(dec!(0.002)).unwrap(),
+            FundingRate::new(dec!(0.0005)).unwrap(),
+            dec!(0.001),
+        );
+
+        let event = cfd.clone().reject_rollover_funding_rate(check);
+        let cfd = cfd.apply(event.clone());
+
+        assert!(!check.is_within_band());
+        assert_eq!(
+            event.event,
+            EventKind::RolloverFundingRateRejected { check }
+        );
+        assert!(!cfd.during_rollover);
+    }
+
+    #[test]
+    fn given_funding_rate_within_band_then_check_passes() {
+        let check = FundingRateCheck::new(
+            FundingRate::new(dec!(-0.001)).unwrap(),
+            FundingRate::new(dec!(0.0005)).unwrap(),
+            dec!(0.0015),
+        );
+
+        assert!(check.is_within_band());
+    }
+
+    #[test]
+    fn given_maker_proposal_within_tolerance_then_check_passes() {
+        let check = SettlementPriceCheck::new(
+            Price::new(dec!(19_980)).unwrap(),
+            Price::new(dec!(20_000)).unwrap(),
+            Some(dec!(0.001)),
+        );
+
+        assert!(check.is_within_tolerance());
+    }
+
+    #[test]
+    fn given_maker_proposal_outside_tolerance_or_without_tolerance_then_check_fails() {
+        let proposed = Price::new(dec!(19_900)).unwrap();
+        let quoted = Price::new(dec!(20_000)).unwrap();
+
+        assert!(
+            !SettlementPriceCheck::new(proposed, quoted, Some(dec!(0.001))).is_within_tolerance()
+        );
+        assert!(!SettlementPriceCheck::new(proposed, proposed, None).is_within_tolerance());
+    }
+
+    #[test]
+    fn given_open_cfd_then_taker_can_receive_settlement_proposal() {
+        let cfd = Cfd::dummy_taker_long().dummy_open(dummy_event_id());
+        let check = SettlementPriceCheck::new(
+            Price::new(dec!(20_000)).unwrap(),
+            Price::new(dec!(20_000)).unwrap(),
+            None,
+        );
+
+        let event = cfd.receive_settlement_proposal(check).unwrap();
+
+        assert_eq!(event.event, EventKind::SettlementProposedByMaker { check });
+    }
+
+    #[test]
+    fn given_collaborative_settlement_in_progress_then_settlement_proposal_rejected() {
+        let cfd = Cfd::dummy_taker_long()
+            .dummy_open(dummy_event_id())
+            .dummy_start_collab_settlement();
+        let check = SettlementPriceCheck::new(
+            Price::new(dec!(20_000)).unwrap(),
+            Price::new(dec!(20_000)).unwrap(),
+            None,
+        );
+
+        assert!(cfd.receive_settlement_proposal(check).is_err());
+    }
+
     #[test]
     fn given_ongoing_rollover_then_can_start_collaborative_settlement() {
         let quantity = Contracts::new(10);
@@ -3191,6 +3901,264 @@ mod tests {
         );
     }
 
+    #[test]
+    fn given_interrupted_collab_settlement_when_maker_finalized_then_taker_completes() {
+        let quantity = Contracts::new(10);
+        let opening_price = Price::new(dec!(10000)).unwrap();
+        let order_id = OrderId::default();
+
+        let taker_keys = new_keypair();
+        let maker_keys = new_keypair();
+
+        let taker_long = Cfd::dummy_taker_long()
+            .with_id(order_id)
+            .with_quantity(quantity)
+            .with_opening_price(opening_price)
+            .dummy_open(dummy_event_id())
+            .with_lock(taker_keys, maker_keys);
+
+        let maker_short = Cfd::dummy_maker_short()
+            .with_id(order_id)
+            .with_quantity(quantity)
+            .with_opening_price(opening_price)
+            .dummy_open(dummy_event_id())
+            .with_lock(taker_keys, maker_keys);
+
+        let (propose, settlement_transaction, proposal) = taker_long
+            .clone()
+            .start_collab_settlement_taker(opening_price, N_PAYOUTS, None)
+            .unwrap();
+        let taker_long = taker_long.apply(propose);
+
+        let (maker_short, _) =
+            maker_short.dummy_collab_settlement_maker(proposal, settlement_transaction);
+        let spend_tx = maker_short
+            .collaborative_settlement_spend_tx()
+            .unwrap()
+            .clone();
+
+        let completed = taker_long
+            .clone()
+            .complete_collaborative_settlement_by_counterparty(spend_tx.clone())
+            .unwrap()
+            .expect("taker to be in collaborative settlement");
+        let taker_long = taker_long.apply(completed);
+
+        assert_eq!(
+            taker_long.collaborative_settlement_spend_tx(),
+            Some(&spend_tx)
+        );
+        assert!(taker_long
+            .complete_collaborative_settlement_by_counterparty(spend_tx)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn given_external_payout_address_then_taker_payout_sent_to_external_address() {
+        let order_id = OrderId::default();
+        let price = Price::new(dec!(10000)).unwrap();
+        let payout_address = Address::from_str("mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn").unwrap();
+
+        let taker_keys = new_keypair();
+        let maker_keys = new_keypair();
+
+        let taker_long = Cfd::dummy_taker_long()
+            .with_id(order_id)
+            .dummy_open(dummy_event_id())
+            .with_lock(taker_keys, maker_keys);
+        let maker_short = Cfd::dummy_maker_short()
+            .with_id(order_id)
+            .dummy_open(dummy_event_id())
+            .with_lock(taker_keys, maker_keys);
+
+        let (propose, settlement_transaction, proposal) = taker_long
+            .clone()
+            .start_collab_settlement_taker(price, N_PAYOUTS, Some(payout_address.clone()))
+            .unwrap();
+        assert_eq!(
+            propose.event,
+            EventKind::CollaborativeSettlementStarted {
+                proposal,
+                payout_address: Some(payout_address.clone()),
+            }
+        );
+        let taker_long = taker_long.apply(propose);
+
+        let unsigned_tx = settlement_transaction.unsigned_transaction();
+        assert!(
+            maker_short
+                .clone()
+                .start_collab_settlement_maker_olivia_max(price, N_PAYOUTS, unsigned_tx, None)
+                .is_err(),
+            "Maker must not accept settlement transaction paying to unknown address"
+        );
+        let (_, maker_transaction, _) = maker_short
+            .start_collab_settlement_maker_olivia_max(
+                price,
+                N_PAYOUTS,
+                unsigned_tx,
+                Some(payout_address.clone()),
+            )
+            .unwrap();
+        let spend_tx = maker_transaction
+            .recv_counterparty_signature(settlement_transaction.own_signature())
+            .unwrap()
+            .finalize()
+            .unwrap()
+            .tx;
+
+        let completed = taker_long
+            .complete_collaborative_settlement_by_counterparty(spend_tx)
+            .unwrap()
+            .expect("taker to be in collaborative settlement");
+
+        match completed.event {
+            EventKind::CollaborativeSettlementCompleted { script, .. } => {
+                assert_eq!(script, payout_address.script_pubkey())
+            }
+            event => panic!("Unexpected event {event:?}"),
+        }
+    }
+
+    #[test]
+    fn given_no_attestation_after_grace_period_then_attestation_missing() {
+        let event_id = dummy_event_id();
+        let settlement_time = event_id.timestamp();
+        let grace_period = Duration::HOUR;
+
+        let cfd = Cfd::dummy_taker_long().dummy_open(event_id);
+        assert_eq!(
+            cfd.attestation_missing_since(settlement_time, grace_period),
+            None
+        );
+        assert_eq!(
+            cfd.attestation_missing_since(settlement_time + Duration::hours(2), grace_period),
+            Some(settlement_time + grace_period)
+        );
+
+        let attested = Cfd::dummy_with_attestation(event_id);
+        assert_eq!(
+            attested.attestation_missing_since(settlement_time + Duration::hours(2), grace_period),
+            None
+        );
+    }
+
+    #[test]
+    fn given_partial_update_then_revised_offer_keeps_id_and_other_fields() {
+        let offer = Offer::dummy();
+
+        let revised = offer.clone().revise(&OfferUpdate {
+            leverage_choices: Some(vec![Leverage::ONE, Leverage::TWO]),
+            lot_size: None,
+        });
+
+        assert_eq!(revised.id, offer.id);
+        assert_eq!(revised.revision, offer.revision + 1);
+        assert_eq!(revised.leverage_choices, vec![Leverage::ONE, Leverage::TWO]);
+        assert_eq!(revised.lot_size, offer.lot_size);
+        assert_eq!(revised.price, offer.price);
+
+        let revised = revised.revise(&OfferUpdate {
+            leverage_choices: None,
+            lot_size: Some(LotSize::new(10)),
+        });
+
+        assert_eq!(revised.revision, offer.revision + 2);
+        assert_eq!(revised.leverage_choices, vec![Leverage::ONE, Leverage::TWO]);
+        assert_eq!(revised.lot_size, LotSize::new(10));
+    }
+
+    #[test]
+    fn given_time_to_live_then_offer_expires_until_reissued() {
+        let offer = dummy_offer_created_at(datetime!(2021-11-19 10:05:00).assume_utc())
+            .with_time_to_live(Some(Duration::minutes(2)));
+        let created_at = offer.creation_timestamp_maker.seconds();
+
+        assert!(!offer.is_expired(Timestamp::new(created_at + 119)));
+        assert!(offer.is_expired(Timestamp::new(created_at + 120)));
+
+        let now = OffsetDateTime::from_unix_timestamp(created_at + 120).unwrap();
+        let reissued = offer.clone().reissue(now);
+
+        assert_eq!(reissued.id, offer.id);
+        assert_eq!(reissued.revision, offer.revision);
+        assert!(!reissued.is_expired(Timestamp::new(created_at + 120)));
+        assert!(reissued.is_safe_to_take(now));
+        assert!(!Offer::dummy().is_expired(Timestamp::new(i64::MAX)));
+    }
+
+    #[test]
+    fn given_oracle_event_rolled_over_when_reissued_then_offer_is_revised() {
+        let offer = dummy_offer_created_at(datetime!(2021-11-19 10:05:00).assume_utc())
+            .with_time_to_live(Some(Duration::minutes(2)));
+
+        let reissued = offer
+            .clone()
+            .reissue(datetime!(2021-11-19 11:05:00).assume_utc());
+
+        assert_eq!(reissued.id, offer.id);
+        assert_ne!(reissued.oracle_event_id, offer.oracle_event_id);
+        assert_eq!(reissued.revision, offer.revision + 1);
+    }
+
+    fn dummy_offer_created_at(created_at: OffsetDateTime) -> Offer {
+        let offer = Offer::dummy();
+        let oracle_event_id = olivia::next_announcement_after(
+            created_at + offer.settlement_interval,
+            offer.contract_symbol,
+        );
+
+        offer
+            .with_creation_timestamp(Timestamp::new(created_at.unix_timestamp()))
+            .with_oracle_event_id(oracle_event_id)
+    }
+
+    #[test]
+    fn given_price_levels_then_level_reached_depends_on_position() {
+        let take_profit = Price::new(dec!(12000)).unwrap();
+        let stop_loss = Price::new(dec!(9000)).unwrap();
+
+        let taker_long = Cfd::dummy_taker_long().dummy_open(dummy_event_id());
+        let event = taker_long
+            .set_price_levels(Some(take_profit), Some(stop_loss))
+            .unwrap();
+        let taker_long = taker_long.apply(event);
+
+        assert_eq!(
+            taker_long.price_level_reached(Price::new(dec!(10000)).unwrap()),
+            None
+        );
+        assert_eq!(
+            taker_long.price_level_reached(Price::new(dec!(12500)).unwrap()),
+            Some(PriceLevel::TakeProfit)
+        );
+        assert_eq!(
+            taker_long.price_level_reached(Price::new(dec!(9000)).unwrap()),
+            Some(PriceLevel::StopLoss)
+        );
+
+        let event = taker_long.set_price_levels(None, None).unwrap();
+        let taker_long = taker_long.apply(event);
+
+        assert_eq!(
+            taker_long.price_level_reached(Price::new(dec!(9000)).unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn given_stop_loss_above_take_profit_of_long_position_then_cannot_set_price_levels() {
+        let taker_long = Cfd::dummy_taker_long().dummy_open(dummy_event_id());
+
+        let result = taker_long.set_price_levels(
+            Some(Price::new(dec!(9000)).unwrap()),
+            Some(Price::new(dec!(12000)).unwrap()),
+        );
+
+        assert!(result.is_err());
+    }
+
     #[test]
     fn given_collab_settlement_then_cannot_force_close() {
         let quantity = Contracts::new(10);
@@ -3226,6 +4194,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn given_open_cfd_when_cet_of_dlc_found_then_cet_published_by_counterparty() {
+        let cfd = Cfd::dummy_taker_long().dummy_open(dummy_event_id());
+
+        let event = cfd
+            .clone()
+            .handle_counterparty_cet(dummy_transaction())
+            .unwrap()
+            .expect("CET to be unknown");
+
+        assert!(matches!(
+            event.event,
+            EventKind::CetPublishedByCounterparty { ref range, price: None, .. } if *range == (0..=1)
+        ));
+        assert!(
+            cfd.apply(event)
+                .handle_counterparty_cet(dummy_transaction())
+                .unwrap()
+                .is_none(),
+            "CET is already known"
+        );
+    }
+
+    #[test]
+    fn given_open_cfd_when_unknown_transaction_found_then_error() {
+        let cfd = Cfd::dummy_taker_long().dummy_open(dummy_event_id());
+        let unknown_tx = Transaction {
+            lock_time: 1,
+            ..dummy_transaction()
+        };
+
+        assert!(cfd.handle_counterparty_cet(unknown_tx).is_err());
+    }
+
     #[test]
     fn given_commit_when_lock_confirmed_then_lock_confirmed_after_finality() {
         let taker_long = Cfd::dummy_taker_long()
@@ -3308,7 +4310,7 @@ mod tests {
         // Extract unsigned tx to be able to trigger collab settlement in the maker
         let unsigned_tx = taker_long
             .clone()
-            .start_collab_settlement_taker(price, N_PAYOUTS)
+            .start_collab_settlement_taker(price, N_PAYOUTS, None)
             .unwrap()
             .1
             .unsigned_transaction()
@@ -3321,12 +4323,13 @@ mod tests {
             .with_lock(taker_keys, maker_keys)
             .dummy_commit();
 
-        let result_taker = taker_long.start_collab_settlement_taker(price, N_PAYOUTS);
+        let result_taker = taker_long.start_collab_settlement_taker(price, N_PAYOUTS, None);
         let result_maker = maker_short.start_collab_settlement_maker(
             Price::dummy(),
             N_PAYOUTS,
             &unsigned_tx,
             InverseMaxPrice::OliviaMax,
+            None,
         );
 
         assert!(result_taker.is_err(), "When having commit tx available we should not be able to trigger collaborative settlement");
@@ -3525,6 +4528,60 @@ mod tests {
         }
     }
 
+    #[test]
+    fn given_eight_hour_funding_period_when_extending_past_one_funding_timestamp_then_8_hours_charged(
+    ) {
+        let now = datetime!(2021-11-19 10:00:00).assume_utc();
+
+        // extending from 22:00 until 05:00 of the next day crosses the funding timestamp at 04:00
+        let from_event_id =
+            BitMexPriceEventId::with_20_digits(now + 12.hours(), ContractSymbol::BtcUsd);
+        let to_event_id =
+            BitMexPriceEventId::with_20_digits(now + 19.hours(), ContractSymbol::BtcUsd);
+
+        let offer = Offer::dummy().with_funding_period(FundingPeriod::EightHours);
+        let taker = Cfd::taker_long_from_order(offer.clone(), Contracts::new(1000), Leverage::TWO)
+            .dummy_open(from_event_id);
+        let maker = Cfd::maker_short_from_order(offer, Contracts::new(1000), Leverage::TWO)
+            .dummy_open(from_event_id);
+
+        assert_eq!(
+            taker
+                .hours_to_extend_in_rollover_based_on_event(to_event_id, now, from_event_id)
+                .unwrap(),
+            8
+        );
+        assert_eq!(
+            maker
+                .hours_to_extend_in_rollover_based_on_event(to_event_id, now, from_event_id)
+                .unwrap(),
+            8
+        );
+    }
+
+    #[test]
+    fn given_eight_hour_funding_period_when_not_extending_past_funding_timestamp_then_nothing_charged(
+    ) {
+        let now = datetime!(2021-11-19 10:00:00).assume_utc();
+
+        // extending from 13:00 until 19:00 does not cross any funding timestamp
+        let from_event_id =
+            BitMexPriceEventId::with_20_digits(now + 3.hours(), ContractSymbol::BtcUsd);
+        let to_event_id =
+            BitMexPriceEventId::with_20_digits(now + 9.hours(), ContractSymbol::BtcUsd);
+
+        let offer = Offer::dummy().with_funding_period(FundingPeriod::EightHours);
+        let taker = Cfd::taker_long_from_order(offer, Contracts::new(1000), Leverage::TWO)
+            .dummy_open(from_event_id);
+
+        assert_eq!(
+            taker
+                .hours_to_extend_in_rollover_based_on_event(to_event_id, now, from_event_id)
+                .unwrap(),
+            0
+        );
+    }
+
     #[test]
     fn given_settlement_within_24_hours_when_calculating_hours_to_extend_based_on_event_then_return_expected_hours(
     ) {
@@ -3648,7 +4705,7 @@ mod tests {
         let order_id = OrderId::default();
 
         let taker_long = Cfd::taker_long_from_order(
-            Offer::dummy_btc_usd_short()
+            Offer::dummy()
                 .with_price(opening_price)
                 .with_funding_rate(FundingRate::new(funding_rate).unwrap()),
             quantity,
@@ -3657,7 +4714,7 @@ mod tests {
         .with_id(order_id);
 
         let maker_short = Cfd::maker_short_from_order(
-            Offer::dummy_btc_usd_short()
+            Offer::dummy()
                 .with_price(opening_price)
                 .with_funding_rate(FundingRate::new(funding_rate).unwrap()),
             quantity,
@@ -3908,6 +4965,7 @@ mod tests {
                         maker: Default::default(),
                         price: Price::new(dec!(10000)).unwrap(),
                     },
+                    payout_address: None,
                 },
             }]
         }
@@ -4007,7 +5065,7 @@ mod tests {
         fn dummy_taker_long() -> Self {
             Cfd::from_order(
                 OrderId::default(),
-                &Offer::dummy_btc_usd_short(),
+                &Offer::dummy(),
                 Contracts::new(1000),
                 dummy_identity(),
                 dummy_peer_id(),
@@ -4019,7 +5077,7 @@ mod tests {
         fn dummy_maker_short() -> Self {
             Cfd::from_order(
                 OrderId::default(),
-                &Offer::dummy_btc_usd_short(),
+                &Offer::dummy(),
                 Contracts::new(1000),
                 dummy_identity(),
                 dummy_peer_id(),
@@ -4031,7 +5089,7 @@ mod tests {
         fn dummy_not_open_yet() -> Self {
             Cfd::from_order(
                 OrderId::default(),
-                &Offer::dummy_btc_usd_short(),
+                &Offer::dummy(),
                 Contracts::new(1000),
                 dummy_identity(),
                 dummy_peer_id(),
@@ -4124,7 +5182,7 @@ mod tests {
 
             let (propose, settlement_transaction, settlement_proposal) = self
                 .clone()
-                .start_collab_settlement_taker(price, N_PAYOUTS)
+                .start_collab_settlement_taker(price, N_PAYOUTS, None)
                 .unwrap();
             events.push(propose);
 
@@ -4134,6 +5192,7 @@ mod tests {
                     N_PAYOUTS,
                     settlement_transaction.unsigned_transaction(),
                     InverseMaxPrice::OliviaMax,
+                    None,
                 )
                 .unwrap();
 
@@ -4172,6 +5231,7 @@ mod tests {
                     N_PAYOUTS,
                     taker_unsigned_tx,
                     InverseMaxPrice::OliviaMax,
+                    None,
                 )
                 .unwrap();
             events.push(incoming_settlement);
@@ -4264,26 +5324,6 @@ mod tests {
     }
 
     impl Offer {
-        fn dummy_short(contract_symbol: ContractSymbol) -> Self {
-            Offer::new(
-                Position::Short,
-                Price::new(dec!(1000)).unwrap(),
-                Contracts::new(100),
-                Contracts::new(1000),
-                time::Duration::hours(24),
-                TxFeeRate::default(),
-                FundingRate::default(),
-                OpeningFee::default(),
-                vec![Leverage::TWO],
-                contract_symbol,
-                LotSize::new(100),
-            )
-        }
-
-        fn dummy_btc_usd_short() -> Self {
-            Self::dummy_short(ContractSymbol::BtcUsd)
-        }
-
         fn with_price(mut self, price: Price) -> Self {
             self.price = price;
             self
@@ -4294,6 +5334,11 @@ mod tests {
             self
         }
 
+        fn with_funding_period(mut self, funding_period: FundingPeriod) -> Self {
+            self.funding_period = funding_period;
+            self
+        }
+
         fn with_creation_timestamp(mut self, creation_timestamp: Timestamp) -> Self {
             self.creation_timestamp_maker = creation_timestamp;
             self
@@ -1,3 +1,4 @@
+use crate::symbols::PayoutCurve;
 use crate::ContractSymbol;
 use crate::Contracts;
 use crate::FeeAccount;
@@ -11,6 +12,7 @@ use bdk::bitcoin::Amount;
 #[derive(Clone, Copy, Debug)]
 pub struct SetupParams {
     pub contract_symbol: ContractSymbol,
+    pub payout_curve: PayoutCurve,
     pub margin: Amount,
     pub counterparty_margin: Amount,
     pub counterparty_identity: Identity,
@@ -27,6 +29,7 @@ impl SetupParams {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         contract_symbol: ContractSymbol,
+        payout_curve: PayoutCurve,
         margin: Amount,
         counterparty_margin: Amount,
         counterparty_identity: Identity,
@@ -40,6 +43,7 @@ impl SetupParams {
     ) -> Result<Self> {
         Ok(Self {
             contract_symbol,
+            payout_curve,
             margin,
             counterparty_margin,
             counterparty_identity,
@@ -0,0 +1,211 @@
+//! Invariants of the fee accounting over arbitrary sequences of fees.
+//!
+//! Maker and taker keep separate [`FeeAccount`]s for the same CFD. The invariants below ensure
+//! that both accounts always agree on who owes how much, no matter which fees were charged, how
+//! often the CFD was rolled over and whether a rollover was accounted for by adding the funding
+//! fee or by adopting the complete fee sent by the maker.
+
+use crate::CompleteFee;
+use crate::FeeAccount;
+use crate::FundingFee;
+use crate::FundingRate;
+use crate::OpeningFee;
+use crate::Position;
+use crate::Role;
+use bdk::bitcoin::Amount;
+use bdk::bitcoin::SignedAmount;
+use proptest::prelude::*;
+use rust_decimal_macros::dec;
+
+/// Upper bound of a single fee, one bitcoin
+const MAX_FEE_SAT: u64 = 100_000_000;
+
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    /// Funding fee charged by both parties individually, e.g. upon contract setup
+    FundingFee(FundingFee),
+    /// Rollover in which the taker adopts the complete fee computed by the maker
+    Rollover(FundingFee),
+}
+
+fn funding_fee() -> impl Strategy<Value = FundingFee> {
+    (0..=MAX_FEE_SAT, any::<bool>()).prop_map(|(fee, long_pays_short)| {
+        let rate = if long_pays_short {
+            dec!(0.001)
+        } else {
+            dec!(-0.001)
+        };
+
+        FundingFee::new(Amount::from_sat(fee), FundingRate::new(rate).unwrap())
+    })
+}
+
+fn step() -> impl Strategy<Value = Step> {
+    prop_oneof![
+        funding_fee().prop_map(Step::FundingFee),
+        funding_fee().prop_map(Step::Rollover),
+    ]
+}
+
+fn position() -> impl Strategy<Value = Position> {
+    prop_oneof![Just(Position::Long), Just(Position::Short)]
+}
+
+/// Apply the steps to the fee accounts of taker and maker.
+///
+/// Returns the accounts after each step.
+fn apply(
+    taker_position: Position,
+    opening_fee: OpeningFee,
+    steps: &[Step],
+) -> Vec<(FeeAccount, FeeAccount)> {
+    let mut taker = FeeAccount::new(taker_position, Role::Taker).add_opening_fee(opening_fee);
+    let mut maker = FeeAccount::new(taker_position.counter_position(), Role::Maker)
+        .add_opening_fee(opening_fee);
+
+    let mut accounts = vec![(taker, maker)];
+
+    for step in steps {
+        match *step {
+            Step::FundingFee(funding_fee) => {
+                taker = taker.add_funding_fee(funding_fee);
+                maker = maker.add_funding_fee(funding_fee);
+            }
+            Step::Rollover(funding_fee) => {
+                maker = maker.add_funding_fee(funding_fee);
+                taker = taker.from_complete_fee(maker.settle());
+            }
+        }
+
+        accounts.push((taker, maker));
+    }
+
+    accounts
+}
+
+/// The balance of the account computed independently of [`FeeAccount`], without risk of overflow.
+fn expected_balance(position: Position, role: Role, opening_fee: u64, steps: &[Step]) -> i128 {
+    let opening_fee = match role {
+        Role::Taker => opening_fee as i128,
+        Role::Maker => -(opening_fee as i128),
+    };
+
+    steps
+        .iter()
+        .map(|step| match step {
+            Step::FundingFee(funding_fee) | Step::Rollover(funding_fee) => funding_fee,
+        })
+        .fold(opening_fee, |balance, funding_fee| {
+            let fee = funding_fee.fee.as_sat() as i128;
+            let long_pays_short = !funding_fee.rate.short_pays_long();
+
+            match (position, long_pays_short) {
+                (Position::Long, true) | (Position::Short, false) => balance + fee,
+                (Position::Long, false) | (Position::Short, true) => balance - fee,
+            }
+        })
+}
+
+proptest! {
+    #[test]
+    fn maker_income_equals_taker_expense(
+        taker_position in position(),
+        opening_fee in 0..=MAX_FEE_SAT,
+        steps in prop::collection::vec(step(), 0..50),
+    ) {
+        let opening_fee = OpeningFee::new(Amount::from_sat(opening_fee));
+
+        for (taker, maker) in apply(taker_position, opening_fee, &steps) {
+            prop_assert_eq!(taker.balance(), maker.balance() * -1);
+            prop_assert_eq!(taker.settle(), maker.settle());
+        }
+    }
+
+    #[test]
+    fn settled_fee_offsets_payouts_by_balance(
+        taker_position in position(),
+        opening_fee in 0..=MAX_FEE_SAT,
+        steps in prop::collection::vec(step(), 0..50),
+    ) {
+        let opening_fee = OpeningFee::new(Amount::from_sat(opening_fee));
+
+        for (taker, maker) in apply(taker_position, opening_fee, &steps) {
+            let taker_offset = taker.settle().as_signed_amount(taker_position);
+            let maker_offset = maker.settle().as_signed_amount(taker_position.counter_position());
+
+            prop_assert_eq!(taker_offset, taker.balance() * -1);
+            prop_assert_eq!(maker_offset, maker.balance() * -1);
+            prop_assert_eq!(taker_offset + maker_offset, SignedAmount::ZERO);
+        }
+    }
+
+    #[test]
+    fn rollover_with_complete_fee_equals_incremental_accounting(
+        taker_position in position(),
+        opening_fee in 0..=MAX_FEE_SAT,
+        steps in prop::collection::vec(step(), 0..50),
+    ) {
+        let opening_fee = OpeningFee::new(Amount::from_sat(opening_fee));
+        let incremental_steps = steps
+            .iter()
+            .map(|step| match *step {
+                Step::FundingFee(funding_fee) | Step::Rollover(funding_fee) => {
+                    Step::FundingFee(funding_fee)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let (taker, maker) = *apply(taker_position, opening_fee, &steps).last().unwrap();
+        let (incremental_taker, incremental_maker) =
+            *apply(taker_position, opening_fee, &incremental_steps).last().unwrap();
+
+        prop_assert_eq!(taker, incremental_taker);
+        prop_assert_eq!(maker, incremental_maker);
+    }
+
+    #[test]
+    fn balance_matches_sum_of_signed_fees(
+        taker_position in position(),
+        opening_fee in 0..=MAX_FEE_SAT,
+        steps in prop::collection::vec(step(), 0..50),
+    ) {
+        let (taker, maker) = *apply(
+            taker_position,
+            OpeningFee::new(Amount::from_sat(opening_fee)),
+            &steps,
+        )
+        .last()
+        .unwrap();
+
+        prop_assert_eq!(
+            taker.balance().as_sat() as i128,
+            expected_balance(taker_position, Role::Taker, opening_fee, &steps)
+        );
+        prop_assert_eq!(
+            maker.balance().as_sat() as i128,
+            expected_balance(taker_position.counter_position(), Role::Maker, opening_fee, &steps)
+        );
+    }
+
+    #[test]
+    fn taker_owes_opening_fee_to_maker(
+        taker_position in position(),
+        opening_fee in 0..=MAX_FEE_SAT,
+    ) {
+        let (taker, maker) = apply(
+            taker_position,
+            OpeningFee::new(Amount::from_sat(opening_fee)),
+            &[],
+        )[0];
+
+        prop_assert!(!taker.balance().is_negative());
+        prop_assert!(!maker.balance().is_positive());
+
+        let expected = match (opening_fee, taker_position) {
+            (0, _) => CompleteFee::None,
+            (fee, Position::Long) => CompleteFee::LongPaysShort(Amount::from_sat(fee)),
+            (fee, Position::Short) => CompleteFee::ShortPaysLong(Amount::from_sat(fee)),
+        };
+        prop_assert_eq!(taker.settle(), expected);
+    }
+}
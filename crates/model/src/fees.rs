@@ -0,0 +1,187 @@
+//! Fee math of maker and taker for external tooling.
+//!
+//! The functions and types exported here are the stable way to reproduce the fees of a CFD
+//! exactly as the daemons compute them. Their signatures and results only change in a breaking
+//! release of this crate; rounding in particular is part of the contract.
+//!
+//! Fees are accumulated over the lifetime of a CFD: the taker pays the opening fee to the maker
+//! upon contract setup and funding fees are exchanged between long and short for every funding
+//! period the CFD is held, starting with the first one upon contract setup. Upon closing, the
+//! accumulated fees are settled as a single [`CompleteFee`] flowing from one position to the
+//! other.
+
+use crate::calculate_margin;
+use anyhow::Result;
+use bdk::bitcoin::SignedAmount;
+
+pub use crate::CompleteFee;
+pub use crate::ContractSymbol;
+pub use crate::Contracts;
+pub use crate::FeeAccount;
+pub use crate::FundingFee;
+pub use crate::FundingRate;
+pub use crate::Leverage;
+pub use crate::OpeningFee;
+pub use crate::Position;
+pub use crate::Price;
+pub use crate::Role;
+pub use crate::SETTLEMENT_INTERVAL;
+
+/// Funding fee for holding a CFD for `hours` at the given `funding_rate`.
+///
+/// The `funding_rate` applies to a full [`SETTLEMENT_INTERVAL`], fees for shorter periods are
+/// charged proportionally. The fee is computed on the margin of the paying position, i.e. the long
+/// margin if the rate is positive and the short margin if it is negative, and rounded away from
+/// zero to whole satoshis.
+pub fn funding_fee(
+    price: Price,
+    quantity: Contracts,
+    long_leverage: Leverage,
+    short_leverage: Leverage,
+    funding_rate: FundingRate,
+    hours: i64,
+    contract_symbol: ContractSymbol,
+) -> Result<FundingFee> {
+    FundingFee::calculate(
+        price,
+        quantity,
+        long_leverage,
+        short_leverage,
+        funding_rate,
+        hours,
+        contract_symbol,
+    )
+}
+
+/// Funding fee from the point of view of the party in `position`.
+///
+/// Positive if the party pays the fee, negative if it earns it.
+pub fn relative_funding_fee(funding_fee: FundingFee, position: Position) -> SignedAmount {
+    funding_fee.compute_relative(position)
+}
+
+/// Fees accumulated over the lifetime of a CFD, settled as a single fee flow.
+///
+/// Maker and taker arrive at the same result from their respective `position` and `role`.
+pub fn complete_fee(
+    position: Position,
+    role: Role,
+    opening_fee: OpeningFee,
+    funding_fees: impl IntoIterator<Item = FundingFee>,
+) -> CompleteFee {
+    funding_fees
+        .into_iter()
+        .fold(
+            FeeAccount::new(position, role).add_opening_fee(opening_fee),
+            FeeAccount::add_funding_fee,
+        )
+        .settle()
+}
+
+/// Amount by which the payout of the party in `position` changes due to the `complete_fee`.
+///
+/// Negative for the paying position, positive for the receiving one.
+pub fn payout_offset(complete_fee: CompleteFee, position: Position) -> SignedAmount {
+    complete_fee.as_signed_amount(position)
+}
+
+/// Margin a party in the CFD has to lock up.
+pub fn margin(
+    contract_symbol: ContractSymbol,
+    price: Price,
+    quantity: Contracts,
+    leverage: Leverage,
+) -> bdk::bitcoin::Amount {
+    calculate_margin(contract_symbol, price, quantity, leverage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bdk::bitcoin::Amount;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn funding_fee_for_full_and_partial_settlement_interval() {
+        let price = Price::new(dec!(20000)).unwrap();
+        let quantity = Contracts::new(100);
+        let rate = FundingRate::new(dec!(0.001)).unwrap();
+
+        let full = funding_fee(
+            price,
+            quantity,
+            Leverage::TWO,
+            Leverage::TWO,
+            rate,
+            SETTLEMENT_INTERVAL.whole_hours(),
+            ContractSymbol::BtcUsd,
+        )
+        .unwrap();
+        let half = funding_fee(
+            price,
+            quantity,
+            Leverage::TWO,
+            Leverage::TWO,
+            rate,
+            SETTLEMENT_INTERVAL.whole_hours() / 2,
+            ContractSymbol::BtcUsd,
+        )
+        .unwrap();
+
+        assert_eq!(full.fee, Amount::from_sat(250));
+        assert_eq!(half.fee, Amount::from_sat(125));
+    }
+
+    #[test]
+    fn positive_rate_is_paid_by_long() {
+        let fee = funding_fee(
+            Price::new(dec!(20000)).unwrap(),
+            Contracts::new(100),
+            Leverage::TWO,
+            Leverage::TWO,
+            FundingRate::new(dec!(0.001)).unwrap(),
+            SETTLEMENT_INTERVAL.whole_hours(),
+            ContractSymbol::BtcUsd,
+        )
+        .unwrap();
+
+        assert_eq!(
+            relative_funding_fee(fee, Position::Long),
+            SignedAmount::from_sat(250)
+        );
+        assert_eq!(
+            relative_funding_fee(fee, Position::Short),
+            SignedAmount::from_sat(-250)
+        );
+    }
+
+    #[test]
+    fn maker_and_taker_agree_on_complete_fee() {
+        let opening_fee = OpeningFee::new(Amount::from_sat(500));
+        let funding_fees = [
+            FundingFee::new(
+                Amount::from_sat(300),
+                FundingRate::new(dec!(0.001)).unwrap(),
+            ),
+            FundingFee::new(
+                Amount::from_sat(1000),
+                FundingRate::new(dec!(-0.001)).unwrap(),
+            ),
+        ];
+
+        let taker = complete_fee(Position::Long, Role::Taker, opening_fee, funding_fees);
+        let maker = complete_fee(Position::Short, Role::Maker, opening_fee, funding_fees);
+
+        // The long taker pays 500 + 300 and earns 1000
+        assert_eq!(taker, CompleteFee::ShortPaysLong(Amount::from_sat(200)));
+        assert_eq!(maker, taker);
+        assert_eq!(
+            payout_offset(taker, Position::Long),
+            SignedAmount::from_sat(200)
+        );
+        assert_eq!(
+            payout_offset(maker, Position::Short),
+            SignedAmount::from_sat(-200)
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! Fixed-point representation of prices on their way through the payout curve.
+//!
+//! Both parties generate the CETs of a contract independently, so every conversion a price goes
+//! through has to round the same way on both sides. Otherwise a payout boundary can end up one
+//! dollar apart or a payout one satoshi apart, and the parties disagree on the signatures they
+//! exchange.
+//!
+//! The inverse payout curve is fitted with floats, while prices are decimals and the oracle attests
+//! to whole numbers. [`FixedPrice`] is the only place these representations meet:
+//!
+//! - [`FixedPrice::try_from`] rounds a [`Price`] to [`FixedPrice::DECIMALS`] decimal places.
+//! - [`FixedPrice::to_curve`] is the float the payout curve is fitted with.
+//! - [`FixedPrice::to_oracle`] is the whole price the oracle attests to, rounded towards zero.
+//! - [`FixedPrice::bound_from_curve`] is the whole price of a payout boundary the payout curve
+//!   produced. Boundaries are truncated like takers and makers of all protocol versions do, even if
+//!   float noise leaves a boundary just below a whole price: rounding the noise away would move the
+//!   boundaries of the CETs and needs a new protocol version.
+
+use crate::Price;
+use anyhow::ensure;
+use anyhow::Context;
+use anyhow::Result;
+use num::ToPrimitive;
+use rust_decimal::Decimal;
+use rust_decimal::RoundingStrategy;
+use std::fmt;
+
+/// A non-negative price with [`FixedPrice::DECIMALS`] decimal places.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FixedPrice(u64);
+
+impl FixedPrice {
+    /// Number of decimal places a fixed price keeps
+    pub const DECIMALS: u32 = 8;
+
+    const SCALE: u64 = 10u64.pow(Self::DECIMALS);
+
+    fn from_decimal(value: Decimal) -> Result<Self> {
+        ensure!(!value.is_sign_negative(), "Negative price not supported");
+
+        let units = value
+            .round_dp_with_strategy(Self::DECIMALS, RoundingStrategy::MidpointAwayFromZero)
+            .checked_mul(Decimal::from(Self::SCALE))
+            .and_then(|units| units.to_u64())
+            .with_context(|| format!("Price {value} does not fit into a fixed price"))?;
+
+        Ok(Self(units))
+    }
+
+    /// The whole price of a payout boundary the payout curve produced, rounded towards zero.
+    pub fn bound_from_curve(value: f64) -> Result<u64> {
+        ensure!(value.is_finite(), "Price {value} is not finite");
+        ensure!(value >= 0.0, "Negative price not supported");
+        ensure!(
+            value < u64::MAX as f64,
+            "Price {value} does not fit into u64"
+        );
+
+        Ok(value as u64)
+    }
+
+    /// The price to fit the payout curve with.
+    ///
+    /// Goes through [`Decimal`] so that the float is the same as the one of [`Price::to_f64`].
+    pub fn to_curve(self) -> f64 {
+        self.into_decimal()
+            .to_f64()
+            .expect("fixed price to fit into f64")
+    }
+
+    /// The whole price the oracle attests to, rounded towards zero like [`Price::to_u64`].
+    pub fn to_oracle(self) -> u64 {
+        self.0 / Self::SCALE
+    }
+
+    pub fn into_decimal(self) -> Decimal {
+        Decimal::from_i128_with_scale(self.0.into(), Self::DECIMALS).normalize()
+    }
+}
+
+impl TryFrom<Price> for FixedPrice {
+    type Error = anyhow::Error;
+
+    fn try_from(price: Price) -> Result<Self> {
+        Self::from_decimal(price.into_decimal())
+    }
+}
+
+impl fmt::Display for FixedPrice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.into_decimal().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn fixed(price: Decimal) -> FixedPrice {
+        FixedPrice::try_from(Price::new(price).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn price_is_rounded_half_away_from_zero() {
+        assert_eq!(fixed(dec!(20_000.000000005)), fixed(dec!(20_000.00000001)));
+        assert_eq!(fixed(dec!(20_000.000000004)), fixed(dec!(20_000)));
+        assert_eq!(fixed(dec!(20_000.5)).to_string(), "20000.5");
+    }
+
+    #[test]
+    fn conversions_agree_with_price_around_payout_boundaries() {
+        // Every cent within a few dollars of a boundary, on either side of a whole dollar
+        for cents in 2_000_000 - 500..=2_000_000 + 500 {
+            let price = Price::new(Decimal::new(cents, 2)).unwrap();
+            let fixed = FixedPrice::try_from(price).unwrap();
+
+            assert_eq!(fixed.to_oracle(), price.to_u64(), "oracle price of {price}");
+            assert_eq!(fixed.to_curve(), price.to_f64(), "curve price of {price}");
+            assert_eq!(
+                fixed.into_decimal(),
+                price.into_decimal(),
+                "decimal of {price}"
+            );
+        }
+    }
+
+    #[test]
+    fn curve_bounds_are_truncated_like_cets_of_all_protocol_versions() {
+        for bound in [
+            49_999.999_999_999,
+            50_000.0,
+            50_000.000_000_001,
+            49_999.5,
+            0.5,
+        ] {
+            assert_eq!(
+                FixedPrice::bound_from_curve(bound).unwrap(),
+                bound as u64,
+                "bound {bound}"
+            );
+        }
+
+        assert_eq!(
+            FixedPrice::bound_from_curve(49_999.999_999_999).unwrap(),
+            49_999
+        );
+    }
+
+    #[test]
+    fn whole_curve_prices_round_trip() {
+        for price in (1..100_000).step_by(7) {
+            let fixed = FixedPrice::try_from(Price::new(Decimal::from(price)).unwrap()).unwrap();
+
+            assert_eq!(
+                FixedPrice::bound_from_curve(fixed.to_curve()).unwrap(),
+                price
+            );
+            assert_eq!(fixed.to_oracle(), price);
+        }
+    }
+
+    #[test]
+    fn rejects_curve_values_which_are_no_price() {
+        assert!(FixedPrice::bound_from_curve(-1.0).is_err());
+        assert!(FixedPrice::bound_from_curve(f64::NAN).is_err());
+        assert!(FixedPrice::bound_from_curve(f64::INFINITY).is_err());
+    }
+
+    #[test]
+    fn rejects_prices_which_do_not_fit() {
+        let price = Price::new(Decimal::from(u64::MAX)).unwrap();
+
+        assert!(FixedPrice::try_from(price).is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::libp2p::PeerId;
+use crate::symbols::PayoutCurve;
 use anyhow::ensure;
 use anyhow::Context;
 use anyhow::Result;
@@ -31,19 +32,29 @@ use time::OffsetDateTime;
 
 mod cfd;
 mod contract_setup;
+#[cfg(test)]
+mod fee_account_proptests;
+pub mod fees;
+mod fixed_price;
 pub mod hex_transaction;
 pub mod libp2p;
 pub mod olivia;
 pub mod payout_curve;
+pub mod protocol_timeout;
 mod rollover;
 pub mod shared_protocol;
+pub mod simulate;
+pub mod symbols;
+pub mod trading_calendar;
 pub mod transaction_ext;
 
 pub use cfd::*;
 pub use contract_setup::SetupParams;
+pub use fixed_price::FixedPrice;
 pub use payout_curve::OraclePayouts;
 pub use payout_curve::Payouts;
 pub use rollover::BaseDlcParams;
+pub use rollover::FundingRateCheck;
 pub use rollover::RolloverParams;
 pub use transaction_ext::TransactionExt;
 
@@ -103,10 +114,12 @@ impl Price {
         Ok(Self(value))
     }
 
+    /// The whole price, rounded towards zero.
     pub fn to_u64(&self) -> u64 {
         self.0.to_u64().expect("price to fit into u64")
     }
 
+    /// Prefer [`FixedPrice::to_curve`] when generating payouts.
     pub fn to_f64(&self) -> f64 {
         self.0.to_f64().expect("price to fit into f64")
     }
@@ -239,10 +252,37 @@ impl Div<Price> for Contracts {
     fn div(self, rhs: Price) -> Self::Output {
         let mut btc = self.0 / rhs.0;
         btc.rescale(8);
-        Amount::from_btc(btc.to_f64().expect("to fit in f64")).expect("Parse the BTC amount")
+        signed_amount_from_btc(btc)
+            .ok()
+            .and_then(|amount| amount.to_unsigned().ok())
+            .expect("Parse the BTC amount")
     }
 }
 
+/// Convert BTC to a [`SignedAmount`] without going through a float.
+///
+/// Fails instead of rounding if `btc` has more than 8 decimal places.
+pub(crate) fn signed_amount_from_btc(btc: Decimal) -> Result<SignedAmount> {
+    let sats = btc
+        .checked_mul(Decimal::from(Amount::ONE_BTC.as_sat()))
+        .with_context(|| format!("{btc} BTC does not fit into a decimal"))?;
+    ensure!(
+        sats.fract().is_zero(),
+        "{btc} BTC has more than 8 decimal places"
+    );
+
+    let sats = sats
+        .to_i64()
+        .with_context(|| format!("{btc} BTC does not fit into an amount"))?;
+
+    Ok(SignedAmount::from_sat(sats))
+}
+
+/// Convert an [`Amount`] to BTC without going through a float.
+pub(crate) fn btc_from_amount(amount: Amount) -> Decimal {
+    Decimal::from(amount.as_sat()) / Decimal::from(Amount::ONE_BTC.as_sat())
+}
+
 impl Mul<Leverage> for Price {
     type Output = Price;
 
@@ -455,10 +495,56 @@ pub struct WalletInfo {
     pub balance: Amount,
     pub address: Address,
     pub last_updated_at: Timestamp,
+    /// Height of the chain tip as seen by the wallet's Electrum server
+    pub block_height: u32,
     pub transactions: Vec<TransactionDetails>,
+    /// Incoming transactions that are not confirmed yet
+    pub pending_deposits: Vec<PendingDeposit>,
     pub managed_wallet: bool,
 }
 
+/// An unconfirmed transaction that increases the wallet balance once confirmed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PendingDeposit {
+    pub txid: Txid,
+    /// Net amount the transaction adds to the wallet
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub amount: Amount,
+    /// Estimated number of blocks until the transaction confirms, based on its fee rate
+    ///
+    /// `None` if the fee rate is below the estimate for the largest confirmation target or the
+    /// estimate is not available.
+    pub estimated_confirmation_blocks: Option<u32>,
+}
+
+/// The reason for a change of the wallet balance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerReason {
+    Deposit,
+    /// Collateral and our share of the fees locked up in the lock transaction of a CFD
+    LockUp,
+    /// Payout of a CFD via collaborative settlement, CET or refund transaction
+    SettlementPayout,
+    Withdrawal,
+    /// Fee of a transaction funded only by the wallet, e.g. a withdrawal
+    ChainFee,
+}
+
+/// A change of the wallet balance caused by a confirmed transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LedgerEntry {
+    pub txid: Txid,
+    pub reason: LedgerReason,
+    pub order_id: Option<OrderId>,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub amount: SignedAmount,
+    /// Confirmation time of the transaction
+    pub timestamp: Timestamp,
+    /// Label of the expected deposit the transaction was matched to
+    pub label: Option<String>,
+}
+
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Timestamp(i64);
 
@@ -533,6 +619,51 @@ impl str::FromStr for FundingRate {
     }
 }
 
+/// Determines which part of the time a contract is extended by is charged with funding fees
+///
+/// Independent of the funding period, the [`FundingRate`] is always given per
+/// [`SETTLEMENT_INTERVAL`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
+pub enum FundingPeriod {
+    /// Every hour the contract is extended by is charged
+    Hourly,
+    /// Eight hours are charged for every BitMEX funding timestamp (04:00, 12:00 and 20:00 UTC)
+    /// that the contract is extended past
+    EightHours,
+}
+
+impl FundingPeriod {
+    const EIGHT_HOURS_IN_SECONDS: i64 = 8 * 60 * 60;
+
+    /// Offset of the first BitMEX funding timestamp of a day from midnight UTC
+    const EIGHT_HOURS_OFFSET_IN_SECONDS: i64 = 4 * 60 * 60;
+
+    /// Number of hours to charge when extending a contract from `from` until `to`
+    pub fn hours_to_charge(&self, from: OffsetDateTime, to: OffsetDateTime) -> i64 {
+        match self {
+            FundingPeriod::Hourly => (to - from).whole_hours(),
+            FundingPeriod::EightHours => {
+                let elapsed_funding_periods =
+                    Self::eight_hour_periods_until(to) - Self::eight_hour_periods_until(from);
+
+                elapsed_funding_periods * 8
+            }
+        }
+    }
+
+    /// Number of BitMEX funding timestamps between the unix epoch and `time`, inclusive
+    fn eight_hour_periods_until(time: OffsetDateTime) -> i64 {
+        (time.unix_timestamp() - Self::EIGHT_HOURS_OFFSET_IN_SECONDS)
+            .div_euclid(Self::EIGHT_HOURS_IN_SECONDS)
+    }
+}
+
+impl Default for FundingPeriod {
+    fn default() -> Self {
+        Self::Hourly
+    }
+}
+
 #[derive(thiserror::Error, Debug, Clone, Copy)]
 pub enum ConversionError {
     #[error("Underflow")]
@@ -578,6 +709,102 @@ impl Default for OpeningFee {
     }
 }
 
+/// Maximum fee rate of an [`OpeningFeeTier`], equal to 100%
+const MAX_OPENING_FEE_BPS: u16 = 10_000;
+
+/// Opening fee charged for orders of at least `min_quantity` contracts.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OpeningFeeTier {
+    /// Smallest quantity the tier applies to
+    pub min_quantity: Contracts,
+    /// Fee charged regardless of the quantity
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub flat: Amount,
+    /// Fee in basis points of the notional value of the quantity exceeding `min_quantity`
+    pub bps: u16,
+}
+
+/// Opening fees as a function of the quantity of an order.
+///
+/// The fee of an order is determined by the tier with the largest `min_quantity` not exceeding
+/// the order's quantity. Orders below the smallest tier are charged the flat opening fee of the
+/// offer.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "Vec<OpeningFeeTier>", into = "Vec<OpeningFeeTier>")]
+pub struct OpeningFeeTiers(Vec<OpeningFeeTier>);
+
+impl OpeningFeeTiers {
+    pub fn new(mut tiers: Vec<OpeningFeeTier>) -> Result<Self> {
+        tiers.sort_by_key(|tier| tier.min_quantity.into_decimal());
+
+        for tier in tiers.iter() {
+            ensure!(
+                tier.bps <= MAX_OPENING_FEE_BPS,
+                "Opening fee of {} bps exceeds {MAX_OPENING_FEE_BPS} bps",
+                tier.bps
+            );
+        }
+
+        ensure!(
+            tiers
+                .windows(2)
+                .all(|pair| pair[0].min_quantity != pair[1].min_quantity),
+            "Opening fee tiers must have distinct minimum quantities"
+        );
+
+        Ok(Self(tiers))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The tiers, ordered by their minimum quantity
+    pub fn tiers(&self) -> &[OpeningFeeTier] {
+        &self.0
+    }
+
+    /// The opening fee of an order of `quantity` contracts at `price`.
+    ///
+    /// Returns `None` if the quantity is below the smallest tier.
+    pub fn fee(
+        &self,
+        payout_curve: impl Into<PayoutCurve>,
+        price: Price,
+        quantity: Contracts,
+    ) -> Option<OpeningFee> {
+        let tier = self
+            .0
+            .iter()
+            .rev()
+            .find(|tier| tier.min_quantity <= quantity)?;
+
+        let notional = calculate_margin(
+            payout_curve,
+            price,
+            quantity - tier.min_quantity,
+            Leverage::ONE,
+        );
+        let variable = notional.as_sat() * u64::from(tier.bps) / u64::from(MAX_OPENING_FEE_BPS);
+
+        Some(OpeningFee::new(tier.flat + Amount::from_sat(variable)))
+    }
+}
+
+impl TryFrom<Vec<OpeningFeeTier>> for OpeningFeeTiers {
+    type Error = anyhow::Error;
+
+    fn try_from(tiers: Vec<OpeningFeeTier>) -> Result<Self> {
+        Self::new(tiers)
+    }
+}
+
+impl From<OpeningFeeTiers> for Vec<OpeningFeeTier> {
+    fn from(tiers: OpeningFeeTiers) -> Self {
+        tiers.0
+    }
+}
+
 /// Fee paid between takers and makers periodically.
 ///
 /// The `fee` field represents the absolute value of this fee.
@@ -605,7 +832,7 @@ impl FundingFee {
         short_leverage: Leverage,
         funding_rate: FundingRate,
         hours_to_charge: i64,
-        contract_symbol: ContractSymbol,
+        payout_curve: impl Into<PayoutCurve>,
     ) -> Result<Self> {
         if funding_rate.0.is_zero() {
             return Ok(Self {
@@ -614,10 +841,11 @@ impl FundingFee {
             });
         }
 
+        let payout_curve = payout_curve.into();
         let margin = if funding_rate.short_pays_long() {
-            calculate_margin(contract_symbol, price, quantity, long_leverage)
+            calculate_margin(payout_curve, price, quantity, long_leverage)
         } else {
-            calculate_margin(contract_symbol, price, quantity, short_leverage)
+            calculate_margin(payout_curve, price, quantity, short_leverage)
         };
 
         let fraction_of_funding_period =
@@ -1054,6 +1282,7 @@ pub struct Lock {
 mod tests {
     use super::*;
     use rust_decimal_macros::dec;
+    use time::macros::datetime;
 
     #[test]
     fn algebra_with_quantities() {
@@ -1080,6 +1309,18 @@ mod tests {
         assert_eq!(res.0, quantity.0);
     }
 
+    #[test]
+    fn btc_is_converted_to_amount_without_rounding() {
+        let amount = signed_amount_from_btc(dec!(-0.12345678)).unwrap();
+
+        assert_eq!(amount, SignedAmount::from_sat(-12_345_678));
+        assert_eq!(
+            btc_from_amount(Amount::from_sat(12_345_678)),
+            dec!(0.12345678)
+        );
+        assert!(signed_amount_from_btc(dec!(0.123456785)).is_err());
+    }
+
     #[test]
     fn roundtrip_identity_serde() {
         let id = Identity::new(x25519_dalek::PublicKey::from([42u8; 32]));
@@ -1430,6 +1671,65 @@ mod tests {
         assert_eq!(fee.fee, Amount::ZERO)
     }
 
+    #[test]
+    fn given_hourly_funding_period_then_every_hour_is_charged() {
+        let from = datetime!(2022-10-17 10:00:00).assume_utc();
+        let to = datetime!(2022-10-18 03:00:00).assume_utc();
+
+        assert_eq!(FundingPeriod::Hourly.hours_to_charge(from, to), 17);
+    }
+
+    #[test]
+    fn given_eight_hour_funding_period_then_only_elapsed_funding_timestamps_are_charged() {
+        let eight_hours = FundingPeriod::EightHours;
+
+        // 12:00 and 20:00 are crossed
+        let from = datetime!(2022-10-17 10:00:00).assume_utc();
+        let to = datetime!(2022-10-18 03:00:00).assume_utc();
+        assert_eq!(eight_hours.hours_to_charge(from, to), 16);
+
+        // no funding timestamp is crossed
+        let from = datetime!(2022-10-17 13:00:00).assume_utc();
+        let to = datetime!(2022-10-17 19:00:00).assume_utc();
+        assert_eq!(eight_hours.hours_to_charge(from, to), 0);
+
+        // the funding timestamp at the end of the interval is charged, the one at the start is not
+        let from = datetime!(2022-10-17 04:00:00).assume_utc();
+        let to = datetime!(2022-10-17 12:00:00).assume_utc();
+        assert_eq!(eight_hours.hours_to_charge(from, to), 8);
+    }
+
+    #[test]
+    fn given_eight_hour_funding_period_then_full_settlement_interval_charged_once() {
+        let eight_hours = FundingPeriod::EightHours;
+
+        for hour in 0..24 {
+            let from = datetime!(2022-10-17 00:00:00).assume_utc() + time::Duration::hours(hour);
+            let to = from + SETTLEMENT_INTERVAL;
+
+            assert_eq!(
+                eight_hours.hours_to_charge(from, to),
+                SETTLEMENT_INTERVAL.whole_hours()
+            );
+        }
+    }
+
+    #[test]
+    fn given_eight_hour_funding_period_then_consecutive_rollovers_charge_same_as_one() {
+        let eight_hours = FundingPeriod::EightHours;
+
+        let start = datetime!(2022-10-17 09:00:00).assume_utc();
+        let first_rollover = datetime!(2022-10-17 11:00:00).assume_utc();
+        let second_rollover = datetime!(2022-10-17 21:00:00).assume_utc();
+        let end = datetime!(2022-10-18 06:00:00).assume_utc();
+
+        let charged_in_steps = eight_hours.hours_to_charge(start, first_rollover)
+            + eight_hours.hours_to_charge(first_rollover, second_rollover)
+            + eight_hours.hours_to_charge(second_rollover, end);
+
+        assert_eq!(charged_in_steps, eight_hours.hours_to_charge(start, end));
+    }
+
     #[test]
     fn given_positive_funding_rate_when_position_long_then_relative_fee_is_positive() {
         let positive_funding_rate = FundingRate::new(dec!(0.001)).unwrap();
@@ -1535,6 +1835,43 @@ mod tests {
         assert_eq!(complete_fee, expected_complete_fee)
     }
 
+    #[test]
+    fn opening_fee_is_determined_by_largest_applicable_tier() {
+        let tiers = OpeningFeeTiers::new(vec![
+            OpeningFeeTier {
+                min_quantity: Contracts::new(1000),
+                flat: Amount::from_sat(2000),
+                bps: 10,
+            },
+            OpeningFeeTier {
+                min_quantity: Contracts::new(100),
+                flat: Amount::from_sat(1000),
+                bps: 0,
+            },
+        ])
+        .unwrap();
+        let price = Price::new(dec!(20_000)).unwrap();
+
+        let fee = |quantity| tiers.fee(ContractSymbol::BtcUsd, price, Contracts::new(quantity));
+
+        assert_eq!(fee(50), None);
+        assert_eq!(fee(500), Some(OpeningFee::new(Amount::from_sat(1000))));
+        assert_eq!(fee(1000), Some(OpeningFee::new(Amount::from_sat(2000))));
+        // 10 bps of the notional value of 2000 contracts, i.e. 0.1 BTC
+        assert_eq!(fee(3000), Some(OpeningFee::new(Amount::from_sat(12_000))));
+    }
+
+    #[test]
+    fn opening_fee_tiers_with_same_minimum_quantity_are_rejected() {
+        let tier = OpeningFeeTier {
+            min_quantity: Contracts::new(100),
+            flat: Amount::from_sat(1000),
+            bps: 0,
+        };
+
+        assert!(OpeningFeeTiers::new(vec![tier, tier]).is_err());
+    }
+
     fn dummy_amount() -> Amount {
         Amount::from_sat(500)
     }
@@ -22,6 +22,7 @@ use time::PrimitiveDateTime;
 use time::Time;
 use url::Url;
 
+use crate::symbols;
 use crate::ContractSymbol;
 
 pub const EVENT_TIME_FORMAT: &[FormatItem] =
@@ -72,10 +73,7 @@ pub enum IndexPrice {
 
 impl From<ContractSymbol> for IndexPrice {
     fn from(contract_symbol: ContractSymbol) -> Self {
-        match contract_symbol {
-            ContractSymbol::BtcUsd => Self::Bxbt,
-            ContractSymbol::EthUsd => Self::Beth,
-        }
+        symbols::config(contract_symbol).index
     }
 }
 
@@ -98,6 +96,13 @@ impl BitMexPriceEventId {
         Self::new(timestamp, 20, index)
     }
 
+    /// The price event of `symbol` at `timestamp` as configured in the symbol registry.
+    pub fn for_symbol(timestamp: OffsetDateTime, symbol: ContractSymbol) -> Self {
+        let config = symbols::config(symbol);
+
+        Self::new(timestamp, config.oracle_digits, config.index)
+    }
+
     /// Checks whether this event has likely already occurred.
     ///
     /// We can't be sure about it because our local clock might be off from the oracle's clock.
@@ -128,10 +133,12 @@ impl BitMexPriceEventId {
     }
 
     pub fn contract_symbol(&self) -> ContractSymbol {
-        match self.index {
-            IndexPrice::Bxbt => ContractSymbol::BtcUsd,
-            IndexPrice::Beth => ContractSymbol::EthUsd,
-        }
+        symbols::registry()
+            .symbol_of(self.index)
+            .unwrap_or(match self.index {
+                IndexPrice::Bxbt => ContractSymbol::BtcUsd,
+                IndexPrice::Beth => ContractSymbol::EthUsd,
+            })
     }
 }
 
@@ -192,11 +199,11 @@ impl From<Announcement> for maia_core::Announcement {
 pub fn hourly_events(
     start: OffsetDateTime,
     end: OffsetDateTime,
-    index: impl Into<IndexPrice>,
+    symbol: ContractSymbol,
 ) -> Result<Vec<BitMexPriceEventId>> {
     let start_adjusted = ceil_to_next_hour(start);
     let end_adjusted = ceil_to_next_hour(end);
-    let announcements = spaced_events(start_adjusted, end_adjusted, Duration::HOUR, index)?;
+    let announcements = spaced_events(start_adjusted, end_adjusted, Duration::HOUR, symbol)?;
 
     Ok(announcements)
 }
@@ -208,26 +215,25 @@ pub fn spaced_events(
     start: OffsetDateTime,
     end: OffsetDateTime,
     interval: Duration,
-    index: impl Into<IndexPrice>,
+    symbol: ContractSymbol,
 ) -> Result<Vec<BitMexPriceEventId>> {
     ensure!(end > start, "end must be later than start");
 
-    let index = index.into();
     Ok((start.unix_timestamp()..=end.unix_timestamp())
         .step_by(interval.whole_seconds() as usize)
         .map(OffsetDateTime::from_unix_timestamp)
         .map(Result::unwrap) // roundtrip should work
-        .map(|timestamp| BitMexPriceEventId::with_20_digits(timestamp, index))
+        .map(|timestamp| BitMexPriceEventId::for_symbol(timestamp, symbol))
         .collect())
 }
 
 pub fn next_announcement_after(
     timestamp: OffsetDateTime,
-    index: impl Into<IndexPrice>,
+    symbol: ContractSymbol,
 ) -> BitMexPriceEventId {
     let adjusted = ceil_to_next_hour(timestamp);
 
-    BitMexPriceEventId::with_20_digits(adjusted, index)
+    BitMexPriceEventId::for_symbol(adjusted, symbol)
 }
 
 fn ceil_to_next_hour(original: OffsetDateTime) -> OffsetDateTime {
@@ -567,7 +573,7 @@ mod tests {
     fn next_event_id_after_timestamp() {
         let event_id = next_announcement_after(
             datetime!(2021-09-23 10:40:00).assume_utc(),
-            IndexPrice::Bxbt,
+            ContractSymbol::BtcUsd,
         );
 
         assert_eq!(
@@ -580,7 +586,7 @@ mod tests {
     fn next_event_id_is_midnight_next_day() {
         let event_id = next_announcement_after(
             datetime!(2021-09-23 23:40:00).assume_utc(),
-            IndexPrice::Bxbt,
+            ContractSymbol::BtcUsd,
         );
 
         assert_eq!(
@@ -594,7 +600,7 @@ mod tests {
         let actual = hourly_events(
             datetime!(2022-07-05 23:40:00).assume_utc(),
             datetime!(2022-07-06 23:40:00).assume_utc(),
-            IndexPrice::Bxbt,
+            ContractSymbol::BtcUsd,
         )
         .unwrap()
         .iter()
@@ -638,7 +644,7 @@ mod tests {
             datetime!(2022-07-05 00:00:00).assume_utc(),
             datetime!(2022-07-05 00:30:00).assume_utc(),
             Duration::MINUTE,
-            IndexPrice::Bxbt,
+            ContractSymbol::BtcUsd,
         )
         .unwrap()
         .iter()
@@ -20,6 +20,7 @@ pub(crate) mod inverse;
 #[cfg(test)]
 mod prop_compose;
 pub(crate) mod quanto;
+pub mod test_vectors;
 
 pub const ETHUSD_MULTIPLIER: Decimal = dec!(0.000001);
 
@@ -120,22 +121,15 @@ impl Payouts {
         fee: CompleteFee,
         inverse_max_price_config: InverseMaxPrice,
     ) -> Result<Self> {
-        let mut payouts = payout_curve::inverse::calculate(
+        let payouts = inverse_intervals(
             price,
             quantity,
-            leverage_long,
-            leverage_short,
+            (leverage_long, leverage_short),
             n_payouts,
             fee,
+            inverse_max_price_config,
         )?;
 
-        if let InverseMaxPrice::OliviaMax = inverse_max_price_config {
-            let n_payouts = payouts.len() - 1;
-            let short_liquidation = payouts.get_mut(n_payouts).expect("several payouts");
-            short_liquidation.range =
-                *short_liquidation.range.start()..=maia_core::interval::MAX_PRICE_DEC;
-        }
-
         let settlement: Vec<_> = match (position, role) {
             (Position::Long, Role::Taker) | (Position::Short, Role::Maker) => payouts
                 .into_iter()
@@ -217,6 +211,35 @@ impl Payouts {
     }
 }
 
+/// The intervals of the inverse payout curve, before they are decomposed into the digits the oracle
+/// attests to.
+fn inverse_intervals(
+    price: Price,
+    quantity: Contracts,
+    (leverage_long, leverage_short): (Leverage, Leverage),
+    n_payouts: usize,
+    fee: CompleteFee,
+    inverse_max_price_config: InverseMaxPrice,
+) -> Result<Vec<inverse::Payout>> {
+    let mut payouts = payout_curve::inverse::calculate(
+        price,
+        quantity,
+        leverage_long,
+        leverage_short,
+        n_payouts,
+        fee,
+    )?;
+
+    if let InverseMaxPrice::OliviaMax = inverse_max_price_config {
+        let n_payouts = payouts.len() - 1;
+        let short_liquidation = payouts.get_mut(n_payouts).expect("several payouts");
+        short_liquidation.range =
+            *short_liquidation.range.start()..=maia_core::interval::MAX_PRICE_DEC;
+    }
+
+    Ok(payouts)
+}
+
 /// Configure the maximum price supported by the inverse payout curve.
 #[derive(Debug, Copy, Clone)]
 pub(crate) enum InverseMaxPrice {
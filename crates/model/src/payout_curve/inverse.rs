@@ -1,3 +1,4 @@
+use crate::signed_amount_from_btc;
 use crate::Contracts;
 use crate::FeeAccount;
 use crate::Leverage;
@@ -7,11 +8,11 @@ use anyhow::Context;
 use anyhow::Result;
 use bdk::bitcoin::Amount;
 use bdk::bitcoin::SignedAmount;
-use num::ToPrimitive;
 
 mod implementation;
 
 pub use implementation::calculate;
+pub use implementation::Payout;
 
 /// Calculates the margin in BTC
 ///
@@ -51,11 +52,8 @@ pub fn calculate_payout_at_price(
         let uncapped_pnl = (quantity / opening_price) - (quantity / closing_price);
         let uncapped_pnl = uncapped_pnl
             .round_dp_with_strategy(8, rust_decimal::RoundingStrategy::MidpointAwayFromZero);
-        let uncapped_pnl = uncapped_pnl
-            .to_f64()
-            .context("Could not convert Decimal to f64")?;
 
-        SignedAmount::from_btc(uncapped_pnl)?
+        signed_amount_from_btc(uncapped_pnl)?
     };
 
     let position = fee_account.position;
@@ -1,5 +1,6 @@
 use crate::CompleteFee;
 use crate::Contracts;
+use crate::FixedPrice;
 use crate::Leverage;
 use crate::Price;
 use anyhow::Context;
@@ -90,7 +91,7 @@ fn calculate_payout_parameters(
     n_payouts: usize,
     fee: CompleteFee,
 ) -> Result<Vec<PayoutParameter>> {
-    let initial_rate = price.to_f64();
+    let initial_rate = FixedPrice::try_from(price)?.to_curve();
     let quantity = quantity.to_u64() as usize;
 
     let payout_curve = PayoutCurve::new(
@@ -107,8 +108,8 @@ fn calculate_payout_parameters(
         .rows()
         .into_iter()
         .map(|row| {
-            let left_bound = row[0] as u64;
-            let right_bound = row[1] as u64;
+            let left_bound = FixedPrice::bound_from_curve(row[0])?;
+            let right_bound = FixedPrice::bound_from_curve(row[1])?;
             let long_amount_btc = row[2];
 
             let long_amount = to_sats(long_amount_btc)?;
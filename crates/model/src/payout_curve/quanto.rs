@@ -1,3 +1,5 @@
+use crate::btc_from_amount;
+use crate::signed_amount_from_btc;
 use crate::CompleteFee;
 use crate::Leverage;
 use crate::Position;
@@ -6,7 +8,6 @@ use anyhow::Result;
 use bdk::bitcoin::Amount;
 use bdk::bitcoin::SignedAmount;
 use num::ToPrimitive;
-use rust_decimal::prelude::FromPrimitive;
 use rust_decimal::Decimal;
 use rust_decimal::RoundingStrategy;
 use std::ops::RangeInclusive;
@@ -318,9 +319,11 @@ pub fn calculate_initial_margin(
 
     let margin = (n_contracts * initial_price * multiplier) / leverage;
     let margin = margin.round_dp_with_strategy(8, RoundingStrategy::MidpointAwayFromZero);
-    let margin = margin.to_f64().expect("margin to fit into f64");
 
-    Amount::from_btc(margin).expect("margin to fit into bitcoin::Amount")
+    signed_amount_from_btc(margin)
+        .ok()
+        .and_then(|margin| margin.to_unsigned().ok())
+        .expect("margin to fit into bitcoin::Amount")
 }
 
 /// Compute the payout in BTC.
@@ -363,8 +366,7 @@ impl Pnl {
         let n_contracts = Decimal::from(n_contracts);
 
         let pnl = (closing_price - initial_price) * multiplier * n_contracts;
-        let pnl = pnl.to_f64().context("Could not convert PNL to f64")?;
-        let pnl = SignedAmount::from_btc(pnl)
+        let pnl = signed_amount_from_btc(pnl)
             .context("Could not convert PNL to bitcoin::SignedAmount")?;
 
         Ok(Self(pnl))
@@ -416,8 +418,7 @@ fn leverage(
 ) -> Result<Decimal> {
     let initial_price = Decimal::from(initial_price);
     let n_contracts = Decimal::from(n_contracts);
-    let margin =
-        Decimal::from_f64(margin.as_btc()).context("Could not convert margin to Decimal")?;
+    let margin = btc_from_amount(margin);
 
     Ok((n_contracts * initial_price * multiplier) / margin)
 }
@@ -0,0 +1,155 @@
+//! Canonical test vectors of the payout curves.
+//!
+//! A test vector pins the intervals of a payout curve, and the amounts paid to either party within
+//! each interval, for one set of contract parameters. Alternative implementations of the protocol
+//! can check their payout curves against them: the transactions the counterparties sign are only
+//! compatible if the curves match exactly.
+//!
+//! The intervals are the ones before they are decomposed into the digits the oracle attests to;
+//! the decomposition is deterministic.
+
+use crate::payout_curve::inverse_intervals;
+use crate::payout_curve::quanto;
+use crate::payout_curve::InverseMaxPrice;
+use crate::payout_curve::ETHUSD_MULTIPLIER;
+use crate::symbols::PayoutCurve;
+use crate::CompleteFee;
+use crate::Contracts;
+use crate::Leverage;
+use crate::Price;
+use anyhow::Context;
+use anyhow::Result;
+use bdk::bitcoin::Amount;
+use rust_decimal_macros::dec;
+use serde::Deserialize;
+use serde::Serialize;
+use std::path::Path;
+
+/// The contract parameters a payout curve is generated for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Inputs {
+    pub payout_curve: PayoutCurve,
+    pub price: Price,
+    pub quantity: Contracts,
+    pub leverage_long: Leverage,
+    pub leverage_short: Leverage,
+    pub n_payouts: usize,
+    pub fee: CompleteFee,
+}
+
+impl Inputs {
+    /// Read a list of inputs from a JSON file.
+    pub fn from_file(path: &Path) -> Result<Vec<Self>> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read test vector inputs {}", path.display()))?;
+
+        serde_json::from_str(&json).context("Failed to parse test vector inputs")
+    }
+
+    /// The inputs the published test vectors are generated for.
+    pub fn canonical() -> Vec<Self> {
+        vec![
+            Inputs {
+                payout_curve: PayoutCurve::Inverse,
+                price: Price::new(dec!(54000)).expect("valid price"),
+                quantity: Contracts::new(3500),
+                leverage_long: Leverage::new(5).expect("valid leverage"),
+                leverage_short: Leverage::ONE,
+                n_payouts: 200,
+                fee: CompleteFee::None,
+            },
+            Inputs {
+                payout_curve: PayoutCurve::Quanto {
+                    multiplier: ETHUSD_MULTIPLIER,
+                },
+                price: Price::new(dec!(1000)).expect("valid price"),
+                quantity: Contracts::new(100),
+                leverage_long: Leverage::TWO,
+                leverage_short: Leverage::ONE,
+                n_payouts: 20,
+                fee: CompleteFee::None,
+            },
+        ]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestVector {
+    pub inputs: Inputs,
+    pub payouts: Vec<PayoutInterval>,
+}
+
+/// The amounts paid to the parties if the attested price is within `[start, end]`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayoutInterval {
+    pub start: u64,
+    pub end: u64,
+    #[serde(with = "::bdk::bitcoin::util::amount::serde::as_sat")]
+    pub long: Amount,
+    #[serde(with = "::bdk::bitcoin::util::amount::serde::as_sat")]
+    pub short: Amount,
+}
+
+impl TestVector {
+    /// Generate the payout curve for `inputs` the way the current protocol does.
+    pub fn generate(inputs: Inputs) -> Result<Self> {
+        let payouts = match inputs.payout_curve {
+            PayoutCurve::Inverse => inverse_intervals(
+                inputs.price,
+                inputs.quantity,
+                (inputs.leverage_long, inputs.leverage_short),
+                inputs.n_payouts,
+                inputs.fee,
+                InverseMaxPrice::OliviaMax,
+            )?
+            .into_iter()
+            .map(|payout| PayoutInterval {
+                start: *payout.range.start(),
+                end: *payout.range.end(),
+                long: payout.long,
+                short: payout.short,
+            })
+            .collect(),
+            PayoutCurve::Quanto { multiplier } => quanto::Payouts::new(
+                inputs.price.to_u64(),
+                inputs.quantity.to_u64(),
+                inputs.leverage_long,
+                inputs.leverage_short,
+                inputs.n_payouts,
+                multiplier,
+                inputs.fee,
+            )?
+            .into_inner()
+            .into_iter()
+            .map(|payout| PayoutInterval {
+                start: *payout.interval.start(),
+                end: *payout.interval.end(),
+                long: payout.long,
+                short: payout.short,
+            })
+            .collect(),
+        };
+
+        Ok(Self { inputs, payouts })
+    }
+}
+
+/// Generate the test vectors for all `inputs`, in order.
+pub fn generate(inputs: Vec<Inputs>) -> Result<Vec<TestVector>> {
+    inputs.into_iter().map(TestVector::generate).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_test_vectors_are_pinned() {
+        let pinned: Vec<TestVector> =
+            serde_json::from_str(include_str!("../../test_vectors/payout_curves.json")).unwrap();
+
+        let generated = generate(Inputs::canonical()).unwrap();
+
+        pretty_assertions::assert_eq!(generated, pinned);
+    }
+}
@@ -0,0 +1,113 @@
+//! Negotiation of how long a protocol waits for the next message of the counterparty.
+//!
+//! If maker and taker wait for different periods, the impatient side aborts the protocol while
+//! the other one still continues, e.g. it goes on to publish a lock transaction. Therefore both
+//! parties propose a timeout in the handshake of a protocol and both use the smaller proposal,
+//! clamped to the bounds of the [`TimeoutPolicy`]. Counterparties that do not negotiate yet use
+//! the timeout that was hard-coded before.
+
+use anyhow::ensure;
+use anyhow::Result;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeoutPolicy {
+    /// The timeout we propose to the counterparty
+    pub proposal: Duration,
+    /// Lower bound of the negotiated timeout
+    pub min: Duration,
+    /// Upper bound of the negotiated timeout
+    pub max: Duration,
+    /// The timeout used if the counterparty does not negotiate
+    pub legacy: Duration,
+}
+
+impl TimeoutPolicy {
+    /// Timeout of the messages of the contract setup protocol
+    ///
+    /// Failed contract setups are annoying compared to failed rollovers so we allow more time to
+    /// see them less often.
+    pub const CONTRACT_SETUP: Self = Self {
+        proposal: Duration::from_secs(120),
+        min: Duration::from_secs(30),
+        max: Duration::from_secs(300),
+        legacy: Duration::from_secs(120),
+    };
+
+    /// Timeout of the messages of the rollover protocol
+    ///
+    /// Rollovers are automatically retried, a few failed rollovers are not a big deal.
+    pub const ROLLOVER: Self = Self {
+        proposal: Duration::from_secs(60),
+        min: Duration::from_secs(15),
+        max: Duration::from_secs(180),
+        legacy: Duration::from_secs(60),
+    };
+
+    /// Our proposal in seconds, as sent to the counterparty.
+    pub fn proposal_secs(&self) -> u64 {
+        self.proposal.as_secs()
+    }
+
+    /// Negotiate the timeout with the proposal of the counterparty in seconds.
+    ///
+    /// Called by the party that decides on the timeout, which then has to send the result to the
+    /// counterparty.
+    pub fn negotiate(&self, counterparty_proposal_secs: Option<u64>) -> Duration {
+        match counterparty_proposal_secs {
+            Some(secs) => self
+                .proposal
+                .min(Duration::from_secs(secs))
+                .clamp(self.min, self.max),
+            None => self.legacy,
+        }
+    }
+
+    /// Verify the timeout in seconds the counterparty negotiated with our proposal.
+    ///
+    /// `None` if the counterparty does not negotiate.
+    pub fn verify(&self, negotiated_secs: Option<u64>) -> Result<Duration> {
+        let negotiated = match negotiated_secs {
+            Some(secs) => Duration::from_secs(secs),
+            None => return Ok(self.legacy),
+        };
+
+        ensure!(
+            negotiated == self.negotiate(Some(negotiated.as_secs())),
+            "Counterparty negotiated a timeout of {}s, which exceeds our proposal of {}s or the bounds of {}s to {}s",
+            negotiated.as_secs(),
+            self.proposal.as_secs(),
+            self.min.as_secs(),
+            self.max.as_secs()
+        );
+
+        Ok(negotiated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: TimeoutPolicy = TimeoutPolicy::CONTRACT_SETUP;
+
+    #[test]
+    fn smaller_proposal_wins_within_bounds() {
+        assert_eq!(POLICY.negotiate(Some(90)), Duration::from_secs(90));
+        assert_eq!(POLICY.negotiate(Some(600)), Duration::from_secs(120));
+        assert_eq!(POLICY.negotiate(Some(1)), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn counterparty_without_negotiation_uses_legacy_timeout() {
+        assert_eq!(POLICY.negotiate(None), POLICY.legacy);
+        assert_eq!(POLICY.verify(None).unwrap(), POLICY.legacy);
+    }
+
+    #[test]
+    fn negotiated_timeout_beyond_proposal_or_bounds_is_rejected() {
+        assert_eq!(POLICY.verify(Some(90)).unwrap(), Duration::from_secs(90));
+        assert!(POLICY.verify(Some(121)).is_err());
+        assert!(POLICY.verify(Some(5)).is_err());
+    }
+}
@@ -1,9 +1,11 @@
 use crate::olivia::BitMexPriceEventId;
+use crate::symbols::PayoutCurve;
 use crate::CompleteFee;
 use crate::Contracts;
 use crate::Dlc;
 use crate::FeeAccount;
 use crate::FundingFee;
+use crate::FundingRate;
 use crate::Leverage;
 use crate::Price;
 use crate::RevokedCommit;
@@ -20,6 +22,9 @@ use bdk_ext::SecretKeyExt;
 use maia_core::secp256k1_zkp;
 use maia_core::secp256k1_zkp::EcdsaAdaptorSignature;
 use maia_core::secp256k1_zkp::SECP256K1;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde::Serialize;
 
 #[derive(Debug, Clone, Copy)]
 pub struct RolloverParams {
@@ -31,6 +36,7 @@ pub struct RolloverParams {
     pub fee_rate: TxFeeRate,
     pub fee_account: FeeAccount,
     pub current_fee: FundingFee,
+    pub payout_curve: PayoutCurve,
 }
 
 impl RolloverParams {
@@ -44,6 +50,7 @@ impl RolloverParams {
         fee_rate: TxFeeRate,
         fee_account: FeeAccount,
         current_fee: FundingFee,
+        payout_curve: PayoutCurve,
     ) -> Self {
         Self {
             price,
@@ -54,6 +61,7 @@ impl RolloverParams {
             fee_rate,
             fee_account,
             current_fee,
+            payout_curve,
         }
     }
 
@@ -66,6 +74,30 @@ impl RolloverParams {
     }
 }
 
+/// Comparison of the funding rate the maker proposes at rollover with the funding rate of the
+/// maker's latest published offer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FundingRateCheck {
+    pub proposed: FundingRate,
+    pub published: FundingRate,
+    /// Maximum absolute difference between the proposed and the published funding rate
+    pub tolerance: Decimal,
+}
+
+impl FundingRateCheck {
+    pub fn new(proposed: FundingRate, published: FundingRate, tolerance: Decimal) -> Self {
+        Self {
+            proposed,
+            published,
+            tolerance,
+        }
+    }
+
+    pub fn is_within_band(&self) -> bool {
+        (self.proposed.to_decimal() - self.published.to_decimal()).abs() <= self.tolerance
+    }
+}
+
 /// Parameters associated with the base DLC involved in a rollover.
 ///
 /// The base DLC is the DLC from which both parties start a rollover.
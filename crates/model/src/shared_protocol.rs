@@ -1,3 +1,4 @@
+use anyhow::ensure;
 use anyhow::Context;
 use anyhow::Result;
 use bdk::bitcoin::secp256k1::ecdsa::Signature;
@@ -15,6 +16,25 @@ use std::ops::RangeInclusive;
 use tracing::instrument;
 use tracing::Span;
 
+/// Maximum number of inputs each party may fund its margin with
+///
+/// The lock transaction combines the inputs of both parties. With this limit it stays far below
+/// the standardness limit of 100 000 vbytes even if both parties spend legacy inputs.
+pub const MAX_LOCK_INPUTS: usize = 200;
+
+/// Verify that the lock PSBT of the counterparty can be combined into the lock transaction.
+pub fn verify_lock_inputs(counterparty: &PartyParams) -> Result<()> {
+    let num_inputs = counterparty.lock_psbt.unsigned_tx.input.len();
+
+    ensure!(num_inputs > 0, "Lock PSBT without inputs");
+    ensure!(
+        num_inputs <= MAX_LOCK_INPUTS,
+        "Lock PSBT with {num_inputs} inputs, at most {MAX_LOCK_INPUTS} are supported"
+    );
+
+    Ok(())
+}
+
 #[instrument(target = "verify_crypto", skip_all)]
 pub fn verify_cets(
     (oracle_pk, nonce_pks): (XOnlyPublicKey, Vec<XOnlyPublicKey>),
@@ -0,0 +1,442 @@
+//! Off-chain simulation of the lifecycle of a CFD.
+//!
+//! Given the parameters of an offer, a price path and a rollover schedule, the CFD is opened,
+//! rolled over and closed using the same fee, payout and liquidation calculations as the real
+//! protocols, but without any transactions. This allows validating parameter changes, e.g. a new
+//! funding rate or opening fee tier, before publishing offers with them.
+//!
+//! Time is expressed in whole hours after the CFD was opened. The CFD closes at the first price of
+//! the path that reaches the liquidation price of either party, or at the first price at or after
+//! the settlement time if it was not rolled over before. If neither happens, the CFD is marked to
+//! market at the last price of the path.
+
+use crate::calculate_long_liquidation_price;
+use crate::calculate_margin;
+use crate::calculate_payout_at_price;
+use crate::calculate_profit;
+use crate::calculate_short_liquidation_price;
+use crate::long_and_short_leverage;
+use crate::ContractSymbol;
+use crate::Contracts;
+use crate::FeeAccount;
+use crate::FundingFee;
+use crate::FundingPeriod;
+use crate::FundingRate;
+use crate::Leverage;
+use crate::OpeningFee;
+use crate::OpeningFeeTiers;
+use crate::Position;
+use crate::Price;
+use crate::Role;
+use crate::SETTLEMENT_INTERVAL;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use bdk::bitcoin::Amount;
+use bdk::bitcoin::SignedAmount;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde::Serialize;
+use std::path::Path;
+use time::Duration;
+use time::OffsetDateTime;
+
+/// The input of a simulation.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Scenario {
+    pub contract_symbol: ContractSymbol,
+    /// The position of the taker
+    pub position: Position,
+    /// The price of the offer the taker takes
+    pub price: Price,
+    pub quantity: Contracts,
+    /// The leverage chosen by the taker
+    pub leverage: Leverage,
+    pub funding_rate: FundingRate,
+    #[serde(default)]
+    pub funding_period: FundingPeriod,
+    pub opening_fee: OpeningFee,
+    #[serde(default)]
+    pub opening_fee_tiers: OpeningFeeTiers,
+    /// Time at which the CFD is opened
+    ///
+    /// Only relevant for funding periods that are aligned to fixed funding timestamps.
+    #[serde(with = "time::serde::timestamp")]
+    pub opened_at: OffsetDateTime,
+    pub price_path: Vec<PricePoint>,
+    #[serde(default)]
+    pub rollovers: Vec<Rollover>,
+}
+
+/// The price observed `hour` hours after the CFD was opened.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct PricePoint {
+    pub hour: i64,
+    pub price: Price,
+}
+
+/// A rollover `hour` hours after the CFD was opened.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Rollover {
+    pub hour: i64,
+    /// The funding rate of the offer at the time of the rollover, defaults to the initial one
+    #[serde(default)]
+    pub funding_rate: Option<FundingRate>,
+}
+
+impl Scenario {
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).context("Failed to parse simulation scenario")
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read simulation scenario {}", path.display()))?;
+
+        Self::from_json(&json)
+    }
+}
+
+/// The outcome of a simulation.
+///
+/// Amounts are in satoshis. The fee balance is the balance of the fee account of the taker, a
+/// positive balance is owed by the taker to the maker.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Report {
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub taker_margin: Amount,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub maker_margin: Amount,
+    pub long_liquidation_price: Decimal,
+    pub short_liquidation_price: Decimal,
+    pub events: Vec<Event>,
+    pub outcome: Outcome,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub taker_fee_balance: SignedAmount,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub taker_payout: Amount,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub maker_payout: Amount,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub taker_profit: SignedAmount,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub maker_profit: SignedAmount,
+}
+
+/// The fees charged over the lifetime of the CFD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    Opened {
+        #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+        opening_fee: Amount,
+        /// The funding fee charged upfront for the first settlement interval
+        #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+        funding_fee: Amount,
+        funding_rate: FundingRate,
+    },
+    RolledOver {
+        hour: i64,
+        hours_charged: i64,
+        #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+        funding_fee: Amount,
+        funding_rate: FundingRate,
+    },
+}
+
+/// How the CFD was closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Outcome {
+    /// The settlement time was reached without a rollover
+    Settled { hour: i64, price: Price },
+    /// The price reached the liquidation price of the party in `position`
+    Liquidated {
+        hour: i64,
+        price: Price,
+        position: Position,
+    },
+    /// The price path ended before the CFD was closed, payouts are marked to market
+    Open { hour: i64, price: Price },
+}
+
+impl Outcome {
+    fn price(&self) -> Price {
+        match self {
+            Outcome::Settled { price, .. }
+            | Outcome::Liquidated { price, .. }
+            | Outcome::Open { price, .. } => *price,
+        }
+    }
+}
+
+/// Simulate the lifecycle of the CFD described by `scenario`.
+///
+/// Rollovers after the CFD was closed are ignored.
+pub fn simulate(scenario: &Scenario) -> Result<Report> {
+    let Scenario {
+        contract_symbol,
+        position,
+        price: initial_price,
+        quantity,
+        leverage,
+        funding_rate,
+        funding_period,
+        opening_fee,
+        ref opening_fee_tiers,
+        opened_at,
+        ref price_path,
+        ref rollovers,
+    } = *scenario;
+
+    let (long_leverage, short_leverage) = long_and_short_leverage(leverage, Role::Taker, position);
+    let (taker_leverage, maker_leverage) = match position {
+        Position::Long => (long_leverage, short_leverage),
+        Position::Short => (short_leverage, long_leverage),
+    };
+
+    let taker_margin = calculate_margin(contract_symbol, initial_price, quantity, taker_leverage);
+    let maker_margin = calculate_margin(contract_symbol, initial_price, quantity, maker_leverage);
+    let long_liquidation_price =
+        calculate_long_liquidation_price(initial_price, long_leverage, contract_symbol);
+    let short_liquidation_price =
+        calculate_short_liquidation_price(initial_price, short_leverage, contract_symbol);
+
+    let mut taker_fees = FeeAccount::new(position, Role::Taker);
+    let mut maker_fees = FeeAccount::new(position.counter_position(), Role::Maker);
+
+    let opening_fee = opening_fee_tiers
+        .fee(contract_symbol, initial_price, quantity)
+        .unwrap_or(opening_fee);
+    let initial_funding_fee = FundingFee::calculate(
+        initial_price,
+        quantity,
+        long_leverage,
+        short_leverage,
+        funding_rate,
+        SETTLEMENT_INTERVAL.whole_hours(),
+        contract_symbol,
+    )?;
+
+    taker_fees = taker_fees
+        .add_opening_fee(opening_fee)
+        .add_funding_fee(initial_funding_fee);
+    maker_fees = maker_fees
+        .add_opening_fee(opening_fee)
+        .add_funding_fee(initial_funding_fee);
+
+    let mut events = vec![Event::Opened {
+        opening_fee: opening_fee.to_inner(),
+        funding_fee: initial_funding_fee.fee,
+        funding_rate,
+    }];
+
+    let mut price_path = price_path.clone();
+    price_path.sort_by_key(|point| point.hour);
+    let mut rollovers = rollovers.clone();
+    rollovers.sort_by_key(|rollover| rollover.hour);
+    let mut rollovers = rollovers.into_iter().peekable();
+
+    let mut settlement_hour = SETTLEMENT_INTERVAL.whole_hours();
+    let mut outcome = None;
+
+    for PricePoint { hour, price } in price_path.iter().copied() {
+        while let Some(rollover) = rollovers.next_if(|rollover| rollover.hour <= hour) {
+            if rollover.hour >= settlement_hour {
+                bail!(
+                    "Rollover at hour {} is not before the settlement at hour {settlement_hour}",
+                    rollover.hour
+                );
+            }
+
+            let next_settlement_hour = rollover.hour + SETTLEMENT_INTERVAL.whole_hours();
+            let hours_charged = funding_period.hours_to_charge(
+                opened_at + Duration::hours(settlement_hour),
+                opened_at + Duration::hours(next_settlement_hour),
+            );
+            let funding_rate = rollover.funding_rate.unwrap_or(funding_rate);
+
+            let funding_fee = FundingFee::calculate(
+                initial_price,
+                quantity,
+                long_leverage,
+                short_leverage,
+                funding_rate,
+                hours_charged,
+                contract_symbol,
+            )?;
+
+            taker_fees = taker_fees.add_funding_fee(funding_fee);
+            maker_fees = maker_fees.add_funding_fee(funding_fee);
+            settlement_hour = next_settlement_hour;
+
+            events.push(Event::RolledOver {
+                hour: rollover.hour,
+                hours_charged,
+                funding_fee: funding_fee.fee,
+                funding_rate,
+            });
+        }
+
+        if price.into_decimal() <= long_liquidation_price {
+            outcome = Some(Outcome::Liquidated {
+                hour,
+                price,
+                position: Position::Long,
+            });
+            break;
+        }
+
+        if price.into_decimal() >= short_liquidation_price {
+            outcome = Some(Outcome::Liquidated {
+                hour,
+                price,
+                position: Position::Short,
+            });
+            break;
+        }
+
+        if hour >= settlement_hour {
+            outcome = Some(Outcome::Settled { hour, price });
+            break;
+        }
+    }
+
+    let outcome = match outcome {
+        Some(outcome) => outcome,
+        None => {
+            let last = price_path.last().context("Price path is empty")?;
+
+            Outcome::Open {
+                hour: last.hour,
+                price: last.price,
+            }
+        }
+    };
+
+    let payout_at = |fee_account: FeeAccount| {
+        calculate_payout_at_price(
+            contract_symbol,
+            initial_price,
+            outcome.price(),
+            quantity,
+            long_leverage,
+            short_leverage,
+            fee_account,
+        )
+    };
+
+    let taker_fee_balance = taker_fees.balance();
+    let taker_payout = payout_at(taker_fees)?;
+    let maker_payout = payout_at(maker_fees)?;
+
+    let (taker_profit, _) = calculate_profit(taker_payout, taker_margin);
+    let (maker_profit, _) = calculate_profit(maker_payout, maker_margin);
+
+    Ok(Report {
+        taker_margin,
+        maker_margin,
+        long_liquidation_price,
+        short_liquidation_price,
+        events,
+        outcome,
+        taker_fee_balance,
+        taker_payout,
+        maker_payout,
+        taker_profit,
+        maker_profit,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn given_price_drop_below_liquidation_price_then_taker_long_liquidated() {
+        let report = simulate(&scenario(
+            vec![point(1, dec!(19_000)), point(5, dec!(13_000))],
+            vec![],
+        ))
+        .unwrap();
+
+        assert_eq!(
+            report.outcome,
+            Outcome::Liquidated {
+                hour: 5,
+                price: Price::new(dec!(13_000)).unwrap(),
+                position: Position::Long,
+            }
+        );
+        assert_eq!(report.taker_payout, Amount::ZERO);
+    }
+
+    #[test]
+    fn given_rollover_then_funding_fee_charged_and_settlement_postponed() {
+        let report = simulate(&scenario(
+            vec![point(24, dec!(21_000)), point(30, dec!(21_000))],
+            vec![Rollover {
+                hour: 20,
+                funding_rate: None,
+            }],
+        ))
+        .unwrap();
+
+        assert!(matches!(
+            report.events.as_slice(),
+            [
+                Event::Opened { .. },
+                Event::RolledOver {
+                    hour: 20,
+                    hours_charged: 20,
+                    ..
+                }
+            ]
+        ));
+        assert!(matches!(report.outcome, Outcome::Open { hour: 30, .. }));
+        assert!(report.taker_profit.is_positive());
+    }
+
+    #[test]
+    fn given_no_rollover_then_settled_at_settlement_time() {
+        let report = simulate(&scenario(
+            vec![point(12, dec!(20_500)), point(24, dec!(19_500))],
+            vec![],
+        ))
+        .unwrap();
+
+        assert_eq!(
+            report.outcome,
+            Outcome::Settled {
+                hour: 24,
+                price: Price::new(dec!(19_500)).unwrap(),
+            }
+        );
+        assert!(report.taker_profit.is_negative());
+    }
+
+    fn scenario(price_path: Vec<PricePoint>, rollovers: Vec<Rollover>) -> Scenario {
+        Scenario {
+            contract_symbol: ContractSymbol::BtcUsd,
+            position: Position::Long,
+            price: Price::new(dec!(20_000)).unwrap(),
+            quantity: Contracts::new(1_000),
+            leverage: Leverage::TWO,
+            funding_rate: FundingRate::new(dec!(0.001)).unwrap(),
+            funding_period: FundingPeriod::Hourly,
+            opening_fee: OpeningFee::new(Amount::from_sat(100)),
+            opening_fee_tiers: OpeningFeeTiers::default(),
+            opened_at: OffsetDateTime::UNIX_EPOCH,
+            price_path,
+            rollovers,
+        }
+    }
+
+    fn point(hour: i64, price: Decimal) -> PricePoint {
+        PricePoint {
+            hour,
+            price: Price::new(price).unwrap(),
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! Registry of the parameters of each contract symbol.
+//!
+//! The oracle event, the number of digits the oracle attests to and the payout curve differ per
+//! contract symbol. They are looked up here instead of being matched on in every crate, so that a
+//! new instrument can be listed by adding it to the configuration. Symbols missing from the
+//! configuration use the parameters of the original listing.
+
+use crate::olivia::IndexPrice;
+use crate::payout_curve::ETHUSD_MULTIPLIER;
+use crate::ContractSymbol;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use conquer_once::OnceCell;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_with::serde_as;
+use serde_with::DisplayFromStr;
+use std::collections::HashMap;
+use std::path::Path;
+use strum::IntoEnumIterator;
+
+/// Number of digits olivia attests to for the BitMEX index prices
+pub const DEFAULT_ORACLE_DIGITS: usize = 20;
+
+static REGISTRY: OnceCell<SymbolRegistry> = OnceCell::uninit();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayoutCurve {
+    /// Margin and payouts in BTC of a contract quoted in USD
+    Inverse,
+    /// Margin and payouts in BTC of a contract quoted in another currency, converted at a fixed
+    /// multiplier in BTC per unit of the quote currency
+    Quanto { multiplier: Decimal },
+}
+
+impl PayoutCurve {
+    /// The multiplier of a quanto payout curve, `None` for inverse contracts.
+    pub fn quanto_multiplier(&self) -> Option<Decimal> {
+        match self {
+            PayoutCurve::Inverse => None,
+            PayoutCurve::Quanto { multiplier } => Some(*multiplier),
+        }
+    }
+}
+
+impl From<ContractSymbol> for PayoutCurve {
+    fn from(symbol: ContractSymbol) -> Self {
+        config(symbol).payout_curve
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SymbolConfig {
+    /// The index price the oracle attests to, e.g. `BXBT`
+    ///
+    /// Oracle events of the symbol are formatted as
+    /// `/x/BitMEX/{index}/{timestamp}.price?n={digits}`.
+    #[serde_as(as = "DisplayFromStr")]
+    pub index: IndexPrice,
+    /// The number of digits of the attested price
+    pub oracle_digits: usize,
+    pub payout_curve: PayoutCurve,
+}
+
+impl SymbolConfig {
+    fn default_for(symbol: ContractSymbol) -> Self {
+        match symbol {
+            ContractSymbol::BtcUsd => Self {
+                index: IndexPrice::Bxbt,
+                oracle_digits: DEFAULT_ORACLE_DIGITS,
+                payout_curve: PayoutCurve::Inverse,
+            },
+            ContractSymbol::EthUsd => Self {
+                index: IndexPrice::Beth,
+                oracle_digits: DEFAULT_ORACLE_DIGITS,
+                payout_curve: PayoutCurve::Quanto {
+                    multiplier: ETHUSD_MULTIPLIER,
+                },
+            },
+        }
+    }
+}
+
+/// The parameters of all contract symbols.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolRegistry {
+    symbols: HashMap<ContractSymbol, SymbolConfig>,
+}
+
+impl Default for SymbolRegistry {
+    fn default() -> Self {
+        Self {
+            symbols: ContractSymbol::iter()
+                .map(|symbol| (symbol, SymbolConfig::default_for(symbol)))
+                .collect(),
+        }
+    }
+}
+
+impl SymbolRegistry {
+    /// Parse the registry from a JSON object keyed by contract symbol, e.g.
+    ///
+    /// `{"EthUsd": {"index": "BETH", "oracle_digits": 20, "payout_curve": {"quanto":
+    /// {"multiplier": "0.000001"}}}}`
+    pub fn from_json(json: &str) -> Result<Self> {
+        let configured = serde_json::from_str::<HashMap<ContractSymbol, SymbolConfig>>(json)
+            .context("Failed to parse symbol configuration")?;
+
+        let mut registry = Self::default();
+        registry.symbols.extend(configured);
+
+        Ok(registry)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read symbol configuration {}", path.display()))?;
+
+        Self::from_json(&json)
+    }
+
+    pub fn get(&self, symbol: ContractSymbol) -> SymbolConfig {
+        self.symbols
+            .get(&symbol)
+            .copied()
+            .unwrap_or_else(|| SymbolConfig::default_for(symbol))
+    }
+
+    /// The contract symbol whose oracle events refer to `index`.
+    pub fn symbol_of(&self, index: IndexPrice) -> Option<ContractSymbol> {
+        ContractSymbol::iter().find(|symbol| self.get(*symbol).index == index)
+    }
+}
+
+/// Install the registry used for the rest of the process.
+///
+/// Must be called before any symbol is looked up, fails if a registry is already in use.
+pub fn install(registry: SymbolRegistry) -> Result<()> {
+    REGISTRY
+        .try_init_once(|| registry)
+        .map_err(|_| anyhow::anyhow!("Symbol registry is already in use"))
+}
+
+/// The registry in use, the default registry if none was installed.
+pub fn registry() -> &'static SymbolRegistry {
+    REGISTRY.get_or_init(SymbolRegistry::default)
+}
+
+/// The parameters of `symbol` from the registry in use.
+pub fn config(symbol: ContractSymbol) -> SymbolConfig {
+    registry().get(symbol)
+}
+
+/// The payout curve of `symbol` scaled by a quanto multiplier that was agreed with the
+/// counterparty.
+///
+/// Offers and CFDs that predate explicit multipliers carry `None` and use the multiplier from the
+/// registry in use. The multiplier is ignored for inverse contracts.
+pub fn payout_curve(symbol: ContractSymbol, quanto_multiplier: Option<Decimal>) -> PayoutCurve {
+    match (config(symbol).payout_curve, quanto_multiplier) {
+        (PayoutCurve::Quanto { .. }, Some(multiplier)) => PayoutCurve::Quanto { multiplier },
+        (payout_curve, _) => payout_curve,
+    }
+}
+
+/// Check that `quanto_multiplier` can be used to scale the payout of `symbol`.
+pub fn check_quanto_multiplier(
+    symbol: ContractSymbol,
+    quanto_multiplier: Option<Decimal>,
+) -> Result<()> {
+    let multiplier = match quanto_multiplier {
+        Some(multiplier) => multiplier,
+        None => return Ok(()),
+    };
+
+    match config(symbol).payout_curve {
+        PayoutCurve::Inverse => {
+            bail!("Inverse contract {symbol} does not take a quanto multiplier")
+        }
+        PayoutCurve::Quanto { .. } if multiplier <= Decimal::ZERO => {
+            bail!("Quanto multiplier of {symbol} must be positive, got {multiplier}")
+        }
+        PayoutCurve::Quanto { .. } => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn default_registry_matches_original_listing() {
+        let registry = SymbolRegistry::default();
+
+        assert_eq!(registry.get(ContractSymbol::BtcUsd).index, IndexPrice::Bxbt);
+        assert_eq!(
+            registry.get(ContractSymbol::EthUsd).payout_curve,
+            PayoutCurve::Quanto {
+                multiplier: ETHUSD_MULTIPLIER
+            }
+        );
+        assert_eq!(
+            registry.symbol_of(IndexPrice::Beth),
+            Some(ContractSymbol::EthUsd)
+        );
+    }
+
+    #[test]
+    fn given_partial_configuration_then_other_symbols_use_defaults() {
+        let registry = SymbolRegistry::from_json(
+            r#"{
+                "EthUsd": {
+                    "index": "BETH",
+                    "oracle_digits": 18,
+                    "payout_curve": { "quanto": { "multiplier": "0.0000005" } }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            registry.get(ContractSymbol::EthUsd),
+            SymbolConfig {
+                index: IndexPrice::Beth,
+                oracle_digits: 18,
+                payout_curve: PayoutCurve::Quanto {
+                    multiplier: dec!(0.0000005)
+                },
+            }
+        );
+        assert_eq!(
+            registry.get(ContractSymbol::BtcUsd),
+            SymbolConfig::default_for(ContractSymbol::BtcUsd)
+        );
+    }
+
+    #[test]
+    fn explicit_quanto_multiplier_only_scales_quanto_contracts() {
+        assert_eq!(
+            payout_curve(ContractSymbol::EthUsd, Some(dec!(0.0000005))),
+            PayoutCurve::Quanto {
+                multiplier: dec!(0.0000005)
+            }
+        );
+        assert_eq!(
+            payout_curve(ContractSymbol::EthUsd, None),
+            PayoutCurve::Quanto {
+                multiplier: ETHUSD_MULTIPLIER
+            }
+        );
+
+        assert!(check_quanto_multiplier(ContractSymbol::EthUsd, Some(dec!(0.0000005))).is_ok());
+        assert!(check_quanto_multiplier(ContractSymbol::EthUsd, Some(dec!(0))).is_err());
+        assert!(check_quanto_multiplier(ContractSymbol::BtcUsd, Some(dec!(0.000001))).is_err());
+        assert!(check_quanto_multiplier(ContractSymbol::BtcUsd, None).is_ok());
+    }
+}
@@ -0,0 +1,225 @@
+//! Weekly trading hours of a contract symbol.
+//!
+//! Outside of the sessions of its calendar the maker rejects new orders for a contract symbol,
+//! CFDs that are already open can still be rolled over and settled. The calendar is part of the
+//! offer so that the taker knows when the market reopens.
+
+use anyhow::ensure;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+use time::Duration;
+use time::OffsetDateTime;
+use time::Weekday;
+
+const MINUTES_PER_DAY: u16 = 24 * 60;
+
+/// A period of a day during which the market is open, in UTC.
+///
+/// Sessions cannot span midnight, such a session has to be split into two sessions on consecutive
+/// days.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    #[serde(with = "weekday")]
+    pub weekday: Weekday,
+    /// Minutes after midnight at which the session opens
+    pub open_minute: u16,
+    /// Minutes after midnight at which the session closes, `1440` for the end of the day
+    pub close_minute: u16,
+}
+
+impl Session {
+    fn contains(&self, weekday: Weekday, minute: u16) -> bool {
+        self.weekday == weekday && self.open_minute <= minute && minute < self.close_minute
+    }
+}
+
+/// The weekly sessions during which the maker accepts new orders.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<Session>", into = "Vec<Session>")]
+pub struct TradingCalendar {
+    sessions: Vec<Session>,
+}
+
+impl TradingCalendar {
+    pub fn new(sessions: Vec<Session>) -> Result<Self> {
+        ensure!(
+            !sessions.is_empty(),
+            "Trading calendar needs at least one session"
+        );
+
+        for session in &sessions {
+            ensure!(
+                session.open_minute < session.close_minute
+                    && session.close_minute <= MINUTES_PER_DAY,
+                "Session on {} from minute {} to {} is not within a single day",
+                session.weekday,
+                session.open_minute,
+                session.close_minute
+            );
+        }
+
+        Ok(Self { sessions })
+    }
+
+    pub fn sessions(&self) -> &[Session] {
+        &self.sessions
+    }
+
+    pub fn is_open(&self, now: OffsetDateTime) -> bool {
+        let now = now.to_offset(time::UtcOffset::UTC);
+        let minute = u16::from(now.hour()) * 60 + u16::from(now.minute());
+
+        self.sessions
+            .iter()
+            .any(|session| session.contains(now.weekday(), minute))
+    }
+
+    /// When the market opens next, `None` if it is open at `now`.
+    pub fn reopens_at(&self, now: OffsetDateTime) -> Option<OffsetDateTime> {
+        if self.is_open(now) {
+            return None;
+        }
+
+        let now = now.to_offset(time::UtcOffset::UTC);
+        let midnight = now.date().midnight().assume_utc();
+
+        // Every weekday is visited once more a week later in case the next session is on the
+        // weekday of today, but earlier than now
+        (0..=7)
+            .map(|days| midnight + Duration::days(days))
+            .flat_map(|day| {
+                let mut opens = self
+                    .sessions
+                    .iter()
+                    .filter(|session| session.weekday == day.weekday())
+                    .map(|session| day + Duration::minutes(session.open_minute.into()))
+                    .collect::<Vec<_>>();
+                opens.sort();
+                opens
+            })
+            .find(|open| *open > now)
+    }
+}
+
+impl TryFrom<Vec<Session>> for TradingCalendar {
+    type Error = anyhow::Error;
+
+    fn try_from(sessions: Vec<Session>) -> Result<Self> {
+        Self::new(sessions)
+    }
+}
+
+impl From<TradingCalendar> for Vec<Session> {
+    fn from(calendar: TradingCalendar) -> Self {
+        calendar.sessions
+    }
+}
+
+/// (De)serialize a weekday by its lowercase name, e.g. `monday`.
+mod weekday {
+    use serde::de::Error;
+    use serde::Deserialize;
+    use serde::Deserializer;
+    use serde::Serializer;
+    use time::Weekday;
+
+    const WEEKDAYS: [Weekday; 7] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
+
+    pub fn serialize<S: Serializer>(weekday: &Weekday, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&weekday.to_string().to_lowercase())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Weekday, D::Error> {
+        let name = String::deserialize(deserializer)?;
+
+        WEEKDAYS
+            .into_iter()
+            .find(|weekday| weekday.to_string().eq_ignore_ascii_case(&name))
+            .ok_or_else(|| D::Error::custom(format!("Unknown weekday: {name}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    /// Open from Monday to Friday between 08:00 and 22:00 UTC
+    fn weekdays() -> TradingCalendar {
+        let sessions = [
+            Weekday::Monday,
+            Weekday::Tuesday,
+            Weekday::Wednesday,
+            Weekday::Thursday,
+            Weekday::Friday,
+        ]
+        .into_iter()
+        .map(|weekday| Session {
+            weekday,
+            open_minute: 8 * 60,
+            close_minute: 22 * 60,
+        })
+        .collect();
+
+        TradingCalendar::new(sessions).unwrap()
+    }
+
+    #[test]
+    fn market_is_only_open_during_sessions() {
+        let calendar = weekdays();
+
+        // 2022-11-07 is a Monday
+        assert!(calendar.is_open(datetime!(2022-11-07 08:00 UTC)));
+        assert!(calendar.is_open(datetime!(2022-11-07 21:59 UTC)));
+        assert!(!calendar.is_open(datetime!(2022-11-07 22:00 UTC)));
+        assert!(!calendar.is_open(datetime!(2022-11-12 12:00 UTC)));
+        assert!(calendar.is_open(datetime!(2022-11-07 10:00 +02:00)));
+    }
+
+    #[test]
+    fn closed_market_reopens_at_next_session() {
+        let calendar = weekdays();
+
+        assert_eq!(calendar.reopens_at(datetime!(2022-11-07 12:00 UTC)), None);
+        assert_eq!(
+            calendar.reopens_at(datetime!(2022-11-07 23:00 UTC)),
+            Some(datetime!(2022-11-08 08:00 UTC))
+        );
+        assert_eq!(
+            calendar.reopens_at(datetime!(2022-11-11 22:30 UTC)),
+            Some(datetime!(2022-11-14 08:00 UTC))
+        );
+    }
+
+    #[test]
+    fn sessions_beyond_a_day_are_rejected() {
+        let session = Session {
+            weekday: Weekday::Monday,
+            open_minute: 60,
+            close_minute: MINUTES_PER_DAY + 1,
+        };
+
+        assert!(TradingCalendar::new(vec![session]).is_err());
+        assert!(TradingCalendar::new(vec![]).is_err());
+    }
+
+    #[test]
+    fn calendar_serializes_as_list_of_sessions() {
+        let json = r#"[{"weekday":"monday","open_minute":0,"close_minute":1440}]"#;
+
+        let calendar = serde_json::from_str::<TradingCalendar>(json).unwrap();
+
+        assert_eq!(calendar.sessions()[0].weekday, Weekday::Monday);
+        assert_eq!(serde_json::to_string(&calendar).unwrap(), json);
+        assert!(serde_json::from_str::<TradingCalendar>("[]").is_err());
+    }
+}
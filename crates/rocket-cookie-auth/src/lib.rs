@@ -11,6 +11,8 @@ mod session;
 pub mod user;
 pub mod users;
 
+pub use crate::session::AUTH_COOKIE;
+
 /// Temporary value if no authentication key is set
 pub const NO_AUTH_KEY_SET: &str = "NONE";
 
@@ -3,9 +3,11 @@ use crate::TESTNET_ELECTRUM;
 use clap::Parser;
 use clap::Subcommand;
 use daemon::bdk::bitcoin;
+use daemon::bdk::bitcoin::util::bip32::ExtendedPubKey;
 use daemon::bdk::bitcoin::Address;
 use daemon::bdk::bitcoin::Amount;
 use std::path::PathBuf;
+use uuid::Uuid;
 
 #[derive(Parser, Clone)]
 pub enum Network {
@@ -15,8 +17,13 @@ pub enum Network {
         #[clap(long, default_value = MAINNET_ELECTRUM)]
         electrum: String,
 
+        /// Backup electrum backend to fail over to if the active one becomes unavailable. Can be
+        /// specified multiple times.
+        #[clap(long)]
+        electrum_backup: Vec<String>,
+
         #[clap(subcommand)]
-        withdraw: Option<Withdraw>,
+        command: Option<Command>,
     },
     /// Run on testnet
     Testnet {
@@ -24,8 +31,13 @@ pub enum Network {
         #[clap(long, default_value = TESTNET_ELECTRUM)]
         electrum: String,
 
+        /// Backup electrum backend to fail over to if the active one becomes unavailable. Can be
+        /// specified multiple times.
+        #[clap(long)]
+        electrum_backup: Vec<String>,
+
         #[clap(subcommand)]
-        withdraw: Option<Withdraw>,
+        command: Option<Command>,
     },
     /// Run on signet
     Signet {
@@ -33,8 +45,13 @@ pub enum Network {
         #[clap(long)]
         electrum: String,
 
+        /// Backup electrum backend to fail over to if the active one becomes unavailable. Can be
+        /// specified multiple times.
+        #[clap(long)]
+        electrum_backup: Vec<String>,
+
         #[clap(subcommand)]
-        withdraw: Option<Withdraw>,
+        command: Option<Command>,
     },
     /// Run on regtest
     Regtest {
@@ -42,8 +59,13 @@ pub enum Network {
         #[clap(long)]
         electrum: String,
 
+        /// Backup electrum backend to fail over to if the active one becomes unavailable. Can be
+        /// specified multiple times.
+        #[clap(long)]
+        electrum_backup: Vec<String>,
+
         #[clap(subcommand)]
-        withdraw: Option<Withdraw>,
+        command: Option<Command>,
     },
 }
 
@@ -51,13 +73,14 @@ impl Default for Network {
     fn default() -> Self {
         Network::Mainnet {
             electrum: MAINNET_ELECTRUM.to_string(),
-            withdraw: None,
+            electrum_backup: Vec::new(),
+            command: None,
         }
     }
 }
 
 #[derive(Subcommand, Clone)]
-pub enum Withdraw {
+pub enum Command {
     Withdraw {
         /// Optionally specify the amount of Bitcoin to be withdrawn. If not specified the wallet
         /// will be drained. Amount is to be specified with denomination, e.g. "0.1 BTC"
@@ -71,6 +94,74 @@ pub enum Withdraw {
         #[clap(long)]
         address: Address,
     },
+    /// Shut down this instance for good: sweep the wallet, archive the database and wipe the seed.
+    ///
+    /// Refuses to run while there are open CFDs. Every step has to be confirmed on the terminal.
+    Decommission {
+        /// Address to receive the Bitcoin. Can be given multiple times to split the balance evenly
+        /// across the addresses.
+        #[clap(long = "address", required_unless_present = "xpub")]
+        addresses: Vec<Address>,
+        /// Extended public key to derive the native segwit addresses `<xpub>/0/<i>` from, instead
+        /// of giving the addresses explicitly.
+        #[clap(long, conflicts_with = "addresses")]
+        xpub: Option<ExtendedPubKey>,
+        /// Number of addresses to derive from `--xpub`.
+        #[clap(long, default_value = "1")]
+        num_addresses: u32,
+        /// Optionally specify the fee-rate for the transaction. The fee-rate is specified as sats
+        /// per vbyte, e.g. 5.0
+        #[clap(long)]
+        fee: Option<f32>,
+        /// Number of confirmations of the sweep transaction to wait for before the seed files are
+        /// wiped.
+        #[clap(long, default_value = "6")]
+        confirmations: u32,
+    },
+    /// Simulate the lifecycle of a CFD without opening it, print the report as JSON and exit.
+    Simulate {
+        /// Path to the JSON file describing the offer params, price path and rollover schedule.
+        #[clap(long)]
+        scenario: PathBuf,
+    },
+    /// Print test vectors of the payout curves as JSON and exit.
+    ///
+    /// Implementations of the protocol can check their payout curves against them.
+    PayoutTestVectors {
+        /// Path to a JSON file with a list of contract parameters to generate the test vectors
+        /// for. Defaults to the parameters of the published test vectors.
+        #[clap(long)]
+        inputs: Option<PathBuf>,
+    },
+    /// Finalize a migration that was rolled out with dual writes and exit.
+    ///
+    /// Verifies that the legacy and the new structures agree and drops the legacy structures.
+    /// Only run this once no instance of an older release accesses the database anymore.
+    FinalizeMigration {
+        /// Name of the migration's flag.
+        #[clap(long)]
+        name: String,
+    },
+    /// Check the referential integrity of the database, print the report as JSON and exit.
+    ///
+    /// Reports rows violating foreign keys and rows referencing CFDs that do not exist, e.g. in
+    /// databases written by older releases. Nothing is changed.
+    CheckIntegrity,
+    /// Inspect the DLC of a CFD and exit.
+    Dlc {
+        #[clap(subcommand)]
+        command: DlcCommand,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum DlcCommand {
+    /// Print a summary of the current DLC of an open CFD: key fingerprints, timelocks, oracle
+    /// event, number of CETs, refund transaction and script addresses.
+    Show {
+        /// Order id of the CFD.
+        order_id: Uuid,
+    },
 }
 
 impl Network {
@@ -83,6 +174,37 @@ impl Network {
         }
     }
 
+    /// All configured electrum backends, starting with the primary one.
+    pub fn electrum_endpoints(&self) -> Vec<String> {
+        let (electrum, electrum_backup) = match self {
+            Network::Mainnet {
+                electrum,
+                electrum_backup,
+                ..
+            } => (electrum, electrum_backup),
+            Network::Testnet {
+                electrum,
+                electrum_backup,
+                ..
+            } => (electrum, electrum_backup),
+            Network::Signet {
+                electrum,
+                electrum_backup,
+                ..
+            } => (electrum, electrum_backup),
+            Network::Regtest {
+                electrum,
+                electrum_backup,
+                ..
+            } => (electrum, electrum_backup),
+        };
+
+        std::iter::once(electrum)
+            .chain(electrum_backup)
+            .cloned()
+            .collect()
+    }
+
     pub fn bitcoin_network(&self) -> bitcoin::Network {
         match self {
             Network::Mainnet { .. } => bitcoin::Network::Bitcoin,
@@ -110,12 +232,12 @@ impl Network {
         }
     }
 
-    pub fn withdraw(&self) -> &Option<Withdraw> {
+    pub fn command(&self) -> &Option<Command> {
         match self {
-            Network::Mainnet { withdraw, .. } => withdraw,
-            Network::Testnet { withdraw, .. } => withdraw,
-            Network::Signet { withdraw, .. } => withdraw,
-            Network::Regtest { withdraw, .. } => withdraw,
+            Network::Mainnet { command, .. } => command,
+            Network::Testnet { command, .. } => command,
+            Network::Signet { command, .. } => command,
+            Network::Regtest { command, .. } => command,
         }
     }
 
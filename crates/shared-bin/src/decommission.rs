@@ -0,0 +1,295 @@
+//! Decommissioning of an instance that is shut down for good.
+//!
+//! Decommissioning sweeps the wallet to the given addresses, archives the database and wipes the
+//! seed files. It refuses to start while there are open CFDs. Every step has to be confirmed by
+//! typing a confirmation word; declining a step aborts the decommissioning without undoing the
+//! steps completed before.
+//!
+//! The seed files are only wiped once the sweep transaction is buried under the configured number
+//! of confirmations, until then the funds are only recoverable with the seed.
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use daemon::bdk::bitcoin;
+use daemon::bdk::bitcoin::secp256k1::Secp256k1;
+use daemon::bdk::bitcoin::util::bip32::ChildNumber;
+use daemon::bdk::bitcoin::util::bip32::ExtendedPubKey;
+use daemon::bdk::bitcoin::Address;
+use daemon::bdk::bitcoin::Txid;
+use daemon::bdk::FeeRate;
+use daemon::wallet;
+use model::Timestamp;
+use rocket::tokio;
+use std::io::BufRead;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Interval at which the confirmations of the sweep transaction are checked
+const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Where the wallet balance is sent to.
+pub enum Recipients {
+    Addresses(Vec<Address>),
+    /// Derive the native segwit addresses `<xpub>/0/<i>` for `i` in `0..num_addresses`
+    Xpub {
+        xpub: ExtendedPubKey,
+        num_addresses: u32,
+    },
+}
+
+impl Recipients {
+    pub fn addresses(&self, network: bitcoin::Network) -> Result<Vec<Address>> {
+        let addresses = match self {
+            Recipients::Addresses(addresses) => addresses.clone(),
+            Recipients::Xpub {
+                xpub,
+                num_addresses,
+            } => {
+                let secp = Secp256k1::verification_only();
+
+                (0..*num_addresses)
+                    .map(|index| {
+                        let path = [
+                            ChildNumber::from_normal_idx(0)?,
+                            ChildNumber::from_normal_idx(index)?,
+                        ];
+                        let public_key = xpub.derive_pub(&secp, &path)?.to_pub();
+
+                        Ok(Address::p2wpkh(&public_key, network)?)
+                    })
+                    .collect::<Result<Vec<_>>>()?
+            }
+        };
+
+        if addresses.is_empty() {
+            bail!("At least one address is required");
+        }
+
+        Ok(addresses)
+    }
+}
+
+pub struct Decommission<'a> {
+    pub db: sqlite_db::Connection,
+    pub db_path: &'a Path,
+    pub seed_files: Vec<PathBuf>,
+    pub recipients: Recipients,
+    pub fee: Option<FeeRate>,
+    pub network: bitcoin::Network,
+    /// Confirmations of the sweep transaction to wait for before wiping the seed files
+    pub confirmations: u32,
+}
+
+impl Decommission<'_> {
+    pub async fn run<W>(self, wallet: xtra::Address<W>) -> Result<()>
+    where
+        W: xtra::Handler<wallet::Sweep, Return = Result<Txid>>
+            + xtra::Handler<wallet::GetConfirmations, Return = Result<Option<u32>>>,
+    {
+        let Decommission {
+            db,
+            db_path,
+            seed_files,
+            recipients,
+            fee,
+            network,
+            confirmations,
+        } = self;
+
+        // CFDs which are closed or failed but not archived yet still count as open
+        db.move_to_closed_cfds().await?;
+        db.move_to_failed_cfds().await?;
+
+        let open_cfds = db.load_open_cfd_ids().await?;
+        if !open_cfds.is_empty() {
+            bail!(
+                "Cannot decommission with {} open CFDs, close them first: {}",
+                open_cfds.len(),
+                open_cfds
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+
+        let addresses = recipients.addresses(network)?;
+
+        println!("Step 1/3: Sweep the entire wallet balance, split evenly across:");
+        for address in addresses.iter() {
+            println!("  {address}");
+        }
+        confirm("sweep").await?;
+
+        let txid = wallet
+            .send(wallet::Sweep { addresses, fee })
+            .await
+            .context("Wallet actor disconnected")??;
+        println!("Published sweep transaction {txid}");
+
+        wait_for_confirmations(&wallet, txid, confirmations).await?;
+
+        let archive_dir = db_path
+            .parent()
+            .context("Database path has no parent directory")?
+            .join(format!("decommissioned-{}", Timestamp::now()));
+
+        println!(
+            "Step 2/3: Move the database {} to {}",
+            db_path.display(),
+            archive_dir.display()
+        );
+        confirm("archive").await?;
+
+        db.close().await;
+        archive(db_path, &archive_dir).await?;
+        println!("Archived the database");
+
+        let seed_files = seed_files
+            .into_iter()
+            .filter(|path| path.exists())
+            .collect::<Vec<_>>();
+
+        println!("Step 3/3: Wipe the seed files:");
+        for path in seed_files.iter() {
+            println!("  {}", path.display());
+        }
+        confirm("wipe").await?;
+
+        for path in seed_files.iter() {
+            wipe(path).await?;
+        }
+        println!("Wiped the seed files, this instance is decommissioned");
+
+        Ok(())
+    }
+}
+
+/// Wait until the transaction has at least `confirmations` confirmations.
+async fn wait_for_confirmations<W>(
+    wallet: &xtra::Address<W>,
+    txid: Txid,
+    confirmations: u32,
+) -> Result<()>
+where
+    W: xtra::Handler<wallet::GetConfirmations, Return = Result<Option<u32>>>,
+{
+    println!("Waiting for {confirmations} confirmations of sweep transaction {txid}");
+
+    loop {
+        let current = wallet
+            .send(wallet::GetConfirmations { txid })
+            .await
+            .context("Wallet actor disconnected")??
+            .unwrap_or_default();
+
+        if current >= confirmations {
+            println!("Sweep transaction {txid} has {current} confirmations");
+            return Ok(());
+        }
+
+        println!("Sweep transaction {txid} has {current}/{confirmations} confirmations");
+        tokio::time::sleep(CONFIRMATION_POLL_INTERVAL).await;
+    }
+}
+
+/// Ask the user to type `word` to continue.
+async fn confirm(word: &'static str) -> Result<()> {
+    let input = tokio::task::spawn_blocking(move || {
+        print!("Type '{word}' to continue: ");
+        std::io::stdout().flush()?;
+
+        let mut input = String::new();
+        std::io::stdin().lock().read_line(&mut input)?;
+
+        anyhow::Ok(input)
+    })
+    .await??;
+
+    if input.trim() != word {
+        bail!("Decommissioning aborted");
+    }
+
+    Ok(())
+}
+
+/// Move the database and its journal files into `archive_dir`.
+async fn archive(db_path: &Path, archive_dir: &Path) -> Result<()> {
+    tokio::fs::create_dir_all(archive_dir).await?;
+
+    let file_name = db_path
+        .file_name()
+        .context("Database path has no file name")?
+        .to_string_lossy()
+        .into_owned();
+
+    for suffix in ["", "-wal", "-shm"] {
+        let source = db_path.with_file_name(format!("{file_name}{suffix}"));
+        if !source.exists() {
+            continue;
+        }
+
+        tokio::fs::rename(&source, archive_dir.join(format!("{file_name}{suffix}")))
+            .await
+            .with_context(|| format!("Failed to move {}", source.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Overwrite the file with zeros before removing it.
+async fn wipe(path: &Path) -> Result<()> {
+    let len = tokio::fs::metadata(path).await?.len();
+
+    tokio::fs::write(path, vec![0u8; len as usize])
+        .await
+        .with_context(|| format!("Failed to overwrite {}", path.display()))?;
+    tokio::fs::remove_file(path)
+        .await
+        .with_context(|| format!("Failed to remove {}", path.display()))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const XPUB: &str = "tpubD6NzVbkrYhZ4XgiXtGrdW5XDAPFCL9h7we1vwNCpn8tGbBcgfVYjXyhWo4E1xkh56hjod1RhGjxbaTLV3X4FyWuejifB9jusQ46QzG87VKp";
+
+    #[test]
+    fn addresses_are_derived_from_xpub() {
+        let xpub = ExtendedPubKey::from_str(XPUB).unwrap();
+
+        let addresses = Recipients::Xpub {
+            xpub,
+            num_addresses: 3,
+        }
+        .addresses(bitcoin::Network::Testnet)
+        .unwrap();
+
+        assert_eq!(addresses.len(), 3);
+        assert!(addresses
+            .iter()
+            .all(|address| address.address_type() == Some(bitcoin::AddressType::P2wpkh)));
+        assert_ne!(addresses[0], addresses[1]);
+        assert_ne!(addresses[1], addresses[2]);
+    }
+
+    #[test]
+    fn given_no_addresses_then_rejected() {
+        assert!(Recipients::Addresses(vec![])
+            .addresses(bitcoin::Network::Testnet)
+            .is_err());
+        assert!(Recipients::Xpub {
+            xpub: ExtendedPubKey::from_str(XPUB).unwrap(),
+            num_addresses: 0,
+        }
+        .addresses(bitcoin::Network::Testnet)
+        .is_err());
+    }
+}
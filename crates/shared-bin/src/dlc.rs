@@ -0,0 +1,164 @@
+//! Human-readable summary of the DLC of an open CFD.
+//!
+//! Allows support to inspect the keys, timelocks, oracle event and transactions of a contract
+//! without querying the database by hand. Secret keys are never printed: keys are identified by
+//! the fingerprint of their public key, the first 4 bytes of its HASH160 as with BIP32.
+
+use anyhow::Context;
+use anyhow::Result;
+use daemon::bdk::bitcoin;
+use daemon::bdk::bitcoin::secp256k1::Secp256k1;
+use daemon::bdk::bitcoin::secp256k1::SecretKey;
+use daemon::bdk::bitcoin::Address;
+use daemon::bdk::bitcoin::PublicKey;
+use daemon::bdk::bitcoin::Script;
+use daemon::bdk::miniscript::DescriptorTrait;
+use model::Dlc;
+use model::OrderId;
+use model::CET_TIMELOCK;
+use std::fmt;
+
+/// Print the summary of the current DLC of the open CFD with `order_id`.
+pub async fn show(
+    db: &sqlite_db::Connection,
+    order_id: OrderId,
+    network: bitcoin::Network,
+) -> Result<()> {
+    let cfd = db
+        .load_open_cfd::<model::Cfd>(order_id, ())
+        .await
+        .with_context(|| format!("Failed to load open CFD {order_id}"))?;
+    let dlc = cfd
+        .dlc()
+        .with_context(|| format!("CFD {order_id} has no DLC, the contract setup is not done"))?;
+
+    print!("{}", Summary::new(order_id, dlc, network));
+
+    Ok(())
+}
+
+pub struct Summary<'a> {
+    order_id: OrderId,
+    dlc: &'a Dlc,
+    network: bitcoin::Network,
+}
+
+impl<'a> Summary<'a> {
+    pub fn new(order_id: OrderId, dlc: &'a Dlc, network: bitcoin::Network) -> Self {
+        Self {
+            order_id,
+            dlc,
+            network,
+        }
+    }
+
+    fn address(&self, script: &Script) -> String {
+        Address::from_script(script, self.network)
+            .map(|address| address.to_string())
+            .unwrap_or_else(|| format!("non-standard script {script}"))
+    }
+}
+
+impl fmt::Display for Summary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dlc = self.dlc;
+        let (lock_tx, lock_descriptor) = &dlc.lock;
+        let (commit_tx, _, commit_descriptor) = &dlc.commit;
+        let (refund_tx, _) = &dlc.refund;
+        let n_cets = dlc.cets.values().map(Vec::len).sum::<usize>();
+
+        writeln!(f, "DLC of CFD {}", self.order_id)?;
+
+        writeln!(f, "\nKeys (ours / theirs)")?;
+        line(
+            f,
+            "identity",
+            keys(&dlc.identity, &dlc.identity_counterparty),
+        )?;
+        line(
+            f,
+            "revocation",
+            keys(&dlc.revocation, &dlc.revocation_pk_counterparty),
+        )?;
+        line(
+            f,
+            "publish",
+            keys(&dlc.publish, &dlc.publish_pk_counterparty),
+        )?;
+
+        writeln!(f, "\nTimelocks (blocks after the commit transaction)")?;
+        line(f, "CETs", CET_TIMELOCK)?;
+        line(f, "refund", dlc.refund_timelock)?;
+
+        writeln!(f, "\nOracle")?;
+        line(f, "settlement event", dlc.settlement_event_id)?;
+        line(f, "events", dlc.cets.len())?;
+        line(f, "CETs", n_cets)?;
+
+        writeln!(f, "\nLock transaction")?;
+        line(f, "txid", lock_tx.txid())?;
+        line(f, "address", self.address(&lock_descriptor.script_pubkey()))?;
+        line(f, "maker amount", dlc.maker_lock_amount)?;
+        line(f, "taker amount", dlc.taker_lock_amount)?;
+
+        writeln!(f, "\nCommit transaction")?;
+        line(f, "txid", commit_tx.txid())?;
+        line(
+            f,
+            "address",
+            self.address(&commit_descriptor.script_pubkey()),
+        )?;
+        line(f, "revoked commits", dlc.revoked_commit.len())?;
+
+        writeln!(f, "\nRefund transaction")?;
+        line(f, "txid", refund_tx.txid())?;
+        for output in refund_tx.output.iter() {
+            line(
+                f,
+                "output",
+                format!(
+                    "{} to {}",
+                    bitcoin::Amount::from_sat(output.value),
+                    self.address(&output.script_pubkey)
+                ),
+            )?;
+        }
+
+        writeln!(f, "\nPayout addresses")?;
+        line(f, "maker", &dlc.maker_address)?;
+        line(f, "taker", &dlc.taker_address)?;
+
+        Ok(())
+    }
+}
+
+fn line(f: &mut fmt::Formatter<'_>, label: &str, value: impl fmt::Display) -> fmt::Result {
+    writeln!(f, "  {label:<18} {value}")
+}
+
+fn keys(ours: &SecretKey, theirs: &PublicKey) -> String {
+    let secp = Secp256k1::signing_only();
+    let ours = PublicKey::new(bitcoin::secp256k1::PublicKey::from_secret_key(&secp, ours));
+
+    format!("{} / {}", fingerprint(&ours), fingerprint(theirs))
+}
+
+fn fingerprint(key: &PublicKey) -> String {
+    key.pubkey_hash().to_string()[..8].to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn fingerprint_is_prefix_of_key_hash() {
+        let key = PublicKey::from_str(
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+        )
+        .unwrap();
+
+        assert_eq!(fingerprint(&key), "751e76e8");
+    }
+}
@@ -1,7 +1,10 @@
 pub mod catchers;
 pub mod cli;
+pub mod decommission;
+pub mod dlc;
 pub mod fairings;
 pub mod logger;
+pub mod openapi;
 pub mod routes;
 mod to_sse_event;
 
@@ -0,0 +1,431 @@
+//! OpenAPI specification of the HTTP API.
+//!
+//! Methods, paths and parameters are taken from the mounted routes, so they cannot drift from the
+//! code. What a route does, what it expects and what it responds with cannot be derived from the
+//! route itself and is maintained by hand as a [`Doc`] next to the routes of each daemon.
+//!
+//! Each daemon commits its generated specification and serves it at `/api/openapi.json`. The
+//! daemons test that the committed specification matches the generated one, which also fails for
+//! routes without a [`Doc`]. Run the tests with `UPDATE_OPENAPI=1` to regenerate the committed
+//! specification after changing the routes.
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use rocket::http::ContentType;
+use rocket::Route;
+use rocket::State;
+use rocket_cookie_auth::AUTH_COOKIE;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+
+/// Environment variable that makes the tests overwrite the committed specification
+pub const UPDATE_ENV_VAR: &str = "UPDATE_OPENAPI";
+
+/// The committed specification of a daemon, served at `/api/openapi.json`
+pub struct Spec(pub &'static str);
+
+#[rocket::get("/openapi.json")]
+pub fn get_openapi(spec: &State<Spec>) -> (ContentType, &'static str) {
+    (ContentType::JSON, spec.0)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Body {
+    Json(&'static str),
+    Form(&'static str),
+    Binary(&'static str),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Response {
+    /// Success without content
+    Empty,
+    Json(&'static str),
+    Text(&'static str),
+    Binary(&'static str),
+    /// Server-sent events with the given event names
+    EventStream(&'static [&'static str]),
+}
+
+/// Hand-maintained documentation of a route.
+#[derive(Debug, Clone, Copy)]
+pub struct Doc {
+    /// Name of the handler function of the route
+    pub name: &'static str,
+    pub summary: &'static str,
+    pub authenticated: bool,
+    pub body: Option<Body>,
+    pub response: Response,
+}
+
+impl Doc {
+    /// Documentation of a route that requires authentication and takes no body.
+    pub const fn new(name: &'static str, summary: &'static str, response: Response) -> Self {
+        Self {
+            name,
+            summary,
+            authenticated: true,
+            body: None,
+            response,
+        }
+    }
+
+    pub const fn body(self, body: Body) -> Self {
+        Self {
+            body: Some(body),
+            ..self
+        }
+    }
+
+    pub const fn public(self) -> Self {
+        Self {
+            authenticated: false,
+            ..self
+        }
+    }
+}
+
+/// Documentation of the routes in [`crate::routes`] and of [`get_openapi`].
+pub const SHARED: &[Doc] = &[
+    Doc::new("get_alive", "Liveness of the daemon", Response::Empty).public(),
+    Doc::new(
+        "get_health_check",
+        "Health of the daemon and its components, `503` if any is unhealthy",
+        Response::Json("Health per component"),
+    )
+    .public(),
+    Doc::new(
+        "get_metrics",
+        "Prometheus metrics",
+        Response::Text("Metrics in the Prometheus text format"),
+    )
+    .public(),
+    Doc::new(
+        "get_version",
+        "Version of the daemon",
+        Response::Json("Daemon version"),
+    )
+    .public(),
+    Doc::new(
+        "get_electrum_status",
+        "Status of the Electrum backends",
+        Response::Json("Electrum status"),
+    ),
+    Doc::new(
+        "get_ledger",
+        "Ledger of all wallet movements",
+        Response::Json("Ledger entries"),
+    ),
+    Doc::new(
+        "get_events",
+        "Page through all CFD events following the sequence number `after_seq`",
+        Response::Json("Page of events and the cursor of the next page"),
+    ),
+    Doc::new(
+        "get_cfd_timeline",
+        "Changes of the state of a CFD with the events that caused them",
+        Response::Json("Timeline"),
+    ),
+    Doc::new(
+        "get_telemetry",
+        "Anonymous usage statistics, `null` if telemetry is not enabled",
+        Response::Json("Telemetry report"),
+    ),
+    Doc::new(
+        "get_daily_marks",
+        "End-of-day marks of the CFDs open on `date` (`YYYY-MM-DD`, UTC), defaults to today",
+        Response::Json("Daily marks"),
+    ),
+    Doc::new(
+        "get_jobs",
+        "Status of the scheduled jobs",
+        Response::Json("Job status"),
+    ),
+    Doc::new(
+        "get_janitor_report",
+        "Orphaned protocol artifacts found by the last run of the janitor, `null` if it did not run yet",
+        Response::Json("Janitor report"),
+    ),
+    Doc::new(
+        "get_supervision_tree",
+        "Supervised actors and their restarts",
+        Response::Json("Supervision tree"),
+    ),
+    Doc::new(
+        "get_cfd_backup",
+        "Encrypted backup of a CFD",
+        Response::Binary("Backup file"),
+    ),
+    Doc::new(
+        "put_transcript",
+        "Start recording the protocol messages of a CFD",
+        Response::Json("Transcript"),
+    ),
+    Doc::new(
+        "delete_transcript",
+        "Stop recording and delete the transcript of a CFD",
+        Response::Empty,
+    ),
+    Doc::new(
+        "get_transcripts",
+        "Recorded transcripts",
+        Response::Json("Transcripts"),
+    ),
+    Doc::new("change_password", "Change the password", Response::Empty)
+        .body(Body::Form("Current and new password")),
+    Doc::new("post_login", "Log in", Response::Empty)
+        .body(Body::Form("Password"))
+        .public(),
+    Doc::new("logout", "Log out", Response::Empty).public(),
+    Doc::new(
+        "is_authenticated",
+        "Whether the session is authenticated and the password was never changed",
+        Response::Json("Authentication status"),
+    )
+    .public(),
+    Doc::new(
+        "get_openapi",
+        "This specification",
+        Response::Json("OpenAPI specification"),
+    )
+    .public(),
+];
+
+/// Generate the specification of `routes`, as mounted at `/api`.
+///
+/// Fails if a route is not documented in any of `docs` or a documented route is not mounted.
+pub fn generate(title: &str, version: &str, routes: &[Route], docs: &[&[Doc]]) -> Result<Value> {
+    let docs = docs.iter().flat_map(|docs| docs.iter()).collect::<Vec<_>>();
+
+    let mut paths = BTreeMap::<String, Map<String, Value>>::new();
+    let mut documented = HashSet::new();
+
+    for route in routes {
+        let name = route.name.as_deref().context("Route without name")?;
+        let doc = docs
+            .iter()
+            .find(|doc| doc.name == name)
+            .with_context(|| format!("Route {name} is not documented"))?;
+        documented.insert(name);
+
+        let (path, parameters) = parse_uri(route.uri.path(), route.uri.query());
+        let method = route.method.as_str().to_lowercase();
+
+        if paths
+            .entry(path.clone())
+            .or_default()
+            .insert(method, operation(doc, parameters))
+            .is_some()
+        {
+            bail!("Route {} {path} is mounted twice", route.method);
+        }
+    }
+
+    if let Some(doc) = docs.iter().find(|doc| !documented.contains(doc.name)) {
+        bail!("Documented route {} is not mounted", doc.name);
+    }
+
+    Ok(json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": title,
+            "version": version,
+        },
+        "servers": [{ "url": "/api" }],
+        "security": [{ "cookie": [] }],
+        "components": {
+            "securitySchemes": {
+                "cookie": {
+                    "type": "apiKey",
+                    "in": "cookie",
+                    "name": AUTH_COOKIE,
+                },
+            },
+            "responses": {
+                "Problem": {
+                    "description": "Problem details as defined in RFC 7807",
+                    "content": {
+                        "application/problem+json": {
+                            "schema": {
+                                "type": "object",
+                                "properties": {
+                                    "type": { "type": "string" },
+                                    "title": { "type": "string" },
+                                    "status": { "type": "integer" },
+                                    "detail": { "type": "string" },
+                                },
+                            },
+                        },
+                    },
+                },
+            },
+        },
+        "paths": paths,
+    }))
+}
+
+/// Translate the path of a route to OpenAPI, e.g. `/cfd/<order_id>` to `/cfd/{order_id}`.
+fn parse_uri(path: &str, query: Option<&str>) -> (String, Vec<Value>) {
+    let mut parameters = Vec::new();
+
+    let path = path
+        .split('/')
+        .map(|segment| match dynamic(segment) {
+            Some(name) => {
+                parameters.push(json!({
+                    "name": name,
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string" },
+                }));
+                format!("{{{name}}}")
+            }
+            None => segment.to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+
+    for name in query
+        .into_iter()
+        .flat_map(|q| q.split('&'))
+        .filter_map(dynamic)
+    {
+        parameters.push(json!({
+            "name": name,
+            "in": "query",
+            "required": false,
+            "schema": { "type": "string" },
+        }));
+    }
+
+    (path, parameters)
+}
+
+/// The name of a dynamic segment, e.g. `order_id` for `<order_id>`.
+fn dynamic(segment: &str) -> Option<&str> {
+    segment
+        .strip_prefix('<')?
+        .strip_suffix('>')
+        .map(|name| name.trim_end_matches(".."))
+}
+
+fn operation(doc: &Doc, parameters: Vec<Value>) -> Value {
+    let mut operation = Map::new();
+    operation.insert("operationId".to_owned(), json!(doc.name));
+    operation.insert("summary".to_owned(), json!(doc.summary));
+
+    if !doc.authenticated {
+        operation.insert("security".to_owned(), json!([]));
+    }
+    if !parameters.is_empty() {
+        operation.insert("parameters".to_owned(), json!(parameters));
+    }
+    if let Some(body) = doc.body {
+        let (description, content_type, schema) = match body {
+            Body::Json(description) => {
+                (description, "application/json", json!({ "type": "object" }))
+            }
+            Body::Form(description) => (
+                description,
+                "application/x-www-form-urlencoded",
+                json!({ "type": "object" }),
+            ),
+            Body::Binary(description) => (
+                description,
+                "application/octet-stream",
+                json!({ "type": "string", "format": "binary" }),
+            ),
+        };
+
+        operation.insert(
+            "requestBody".to_owned(),
+            json!({
+                "description": description,
+                "required": true,
+                "content": { content_type: { "schema": schema } },
+            }),
+        );
+    }
+
+    operation.insert(
+        "responses".to_owned(),
+        json!({
+            "200": response(doc.response),
+            "default": { "$ref": "#/components/responses/Problem" },
+        }),
+    );
+
+    Value::Object(operation)
+}
+
+fn response(response: Response) -> Value {
+    let (description, content_type, schema) = match response {
+        Response::Empty => return json!({ "description": "Success" }),
+        Response::Json(description) => (description.to_owned(), "application/json", json!({})),
+        Response::Text(description) => (
+            description.to_owned(),
+            "text/plain",
+            json!({ "type": "string" }),
+        ),
+        Response::Binary(description) => (
+            description.to_owned(),
+            "application/octet-stream",
+            json!({ "type": "string", "format": "binary" }),
+        ),
+        Response::EventStream(events) => (
+            format!(
+                "Server-sent events, each named after its JSON payload: {}",
+                events
+                    .iter()
+                    .map(|event| format!("`{event}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            "text/event-stream",
+            json!({ "type": "string" }),
+        ),
+    };
+
+    json!({
+        "description": description,
+        "content": { content_type: { "schema": schema } },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dynamic_segments_become_parameters() {
+        let (path, parameters) = parse_uri("/cfd/<order_id>/tags/<tag>", Some("<from>&<to>"));
+
+        assert_eq!(path, "/cfd/{order_id}/tags/{tag}");
+        assert_eq!(
+            parameters
+                .iter()
+                .map(|parameter| (
+                    parameter["name"].as_str().unwrap(),
+                    parameter["in"].as_str().unwrap()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("order_id", "path"),
+                ("tag", "path"),
+                ("from", "query"),
+                ("to", "query"),
+            ]
+        );
+    }
+
+    #[test]
+    fn undocumented_routes_are_rejected() {
+        let result = generate("test", "0.1.0", &rocket::routes![get_openapi], &[]);
+
+        assert!(result.is_err());
+    }
+}
@@ -1,24 +1,67 @@
 #![allow(clippy::let_unit_value)] // see: https://github.com/SergioBenitez/Rocket/issues/2211
 
 use anyhow::Result;
+use daemon::backup;
+use daemon::daily_marks;
+use daemon::electrum_health::ElectrumStatus;
+use daemon::event_feed;
+use daemon::health;
+use daemon::janitor;
+use daemon::ledger;
+use daemon::projection;
+use daemon::scheduler;
+use daemon::supervision;
+use daemon::telemetry;
+use daemon::transcript;
 use http_api_problem::HttpApiProblem;
 use http_api_problem::StatusCode;
 use rocket::form::Form;
+use rocket::http::Status;
 use rocket::serde::json::Json;
+use rocket::tokio::sync::watch;
+use rocket::State;
 use rocket_cookie_auth::auth::Auth;
 use rocket_cookie_auth::forms::ChangePassword;
 use rocket_cookie_auth::forms::Login;
 use rocket_cookie_auth::user::User;
+use rocket_download_response::mime;
+use rocket_download_response::DownloadResponsePro;
 use serde::Serialize;
 use tracing::instrument;
+use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize)]
 pub struct HealthCheck {
     daemon_version: String,
 }
 
+/// Liveness of the daemon, independent of the health of its components.
 #[rocket::get("/alive")]
-pub fn get_health_check() {}
+pub fn get_alive() {}
+
+/// Health of the daemon and the components it depends on.
+///
+/// Responds with `503 Service Unavailable` if any component is unhealthy, which allows using the
+/// endpoint for load balancer health probes.
+#[rocket::get("/health")]
+#[instrument(name = "GET /health", skip_all)]
+pub async fn get_health_check(
+    health: &State<xtra::Address<health::Actor>>,
+) -> Result<(Status, Json<health::Health>), HttpApiProblem> {
+    let health = health.send(health::GetHealth).await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::SERVICE_UNAVAILABLE)
+            .title("Failed to check health")
+            .detail(format!("{e:#}"))
+    })?;
+
+    let status = if health.is_healthy() {
+        Status::Ok
+    } else {
+        Status::ServiceUnavailable
+    };
+
+    Ok((status, Json(health)))
+}
 
 #[rocket::get("/version")]
 #[instrument(name = "GET /version")]
@@ -28,6 +71,247 @@ pub async fn get_version() -> Json<HealthCheck> {
     })
 }
 
+#[rocket::get("/electrum")]
+#[instrument(name = "GET /electrum", skip_all)]
+pub async fn get_electrum_status(
+    rx: &State<watch::Receiver<ElectrumStatus>>,
+    _user: User,
+) -> Json<ElectrumStatus> {
+    Json(rx.borrow().clone())
+}
+
+/// All changes to the wallet balance with their reason, reconcilable against the wallet's
+/// transactions.
+#[rocket::get("/ledger")]
+#[instrument(name = "GET /ledger", skip_all, err)]
+pub async fn get_ledger(
+    ledger: &State<xtra::Address<ledger::Actor>>,
+    _user: User,
+) -> Result<Json<ledger::Ledger>, HttpApiProblem> {
+    let ledger = ledger
+        .send(ledger::GetLedger)
+        .await
+        .map_err(anyhow::Error::new)
+        .and_then(|result| result)
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Failed to load ledger")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(ledger))
+}
+
+/// Events of all CFDs following the event with sequence number `after_seq`, oldest first.
+///
+/// Sequence numbers have no gaps and are stable across restarts, hence consumers can page through
+/// all events by passing the `next_after_seq` of the previous page.
+#[rocket::get("/events?<after_seq>&<limit>")]
+#[instrument(name = "GET /events", skip(feed, _user), err)]
+pub async fn get_events(
+    after_seq: Option<u64>,
+    limit: Option<u32>,
+    feed: &State<event_feed::EventFeed>,
+    _user: User,
+) -> Result<Json<event_feed::Page>, HttpApiProblem> {
+    let page = feed
+        .page(after_seq.unwrap_or_default(), limit)
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Failed to load events")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(page))
+}
+
+/// The changes of the state of a CFD with the events that caused them, oldest first.
+#[rocket::get("/cfds/<order_id>/timeline")]
+#[instrument(name = "GET /cfds/<order_id>/timeline", skip(feed, _user), err)]
+pub async fn get_cfd_timeline(
+    order_id: Uuid,
+    feed: &State<event_feed::EventFeed>,
+    _user: User,
+) -> Result<Json<Vec<projection::TimelineEntry>>, HttpApiProblem> {
+    let timeline = feed
+        .timeline(order_id.into())
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Failed to load CFD timeline")
+                .detail(format!("{e:#}"))
+        })?
+        .ok_or_else(|| {
+            HttpApiProblem::new(StatusCode::NOT_FOUND)
+                .title("Unknown CFD")
+                .detail(format!("No events of CFD {order_id}"))
+        })?;
+
+    Ok(Json(timeline))
+}
+
+/// The anonymous usage statistics, as they would be pushed to the collector.
+#[rocket::get("/telemetry")]
+#[instrument(name = "GET /telemetry", skip_all, err)]
+pub async fn get_telemetry(
+    telemetry: &State<Option<telemetry::Telemetry>>,
+    _user: User,
+) -> Result<Json<Option<telemetry::Report>>, HttpApiProblem> {
+    let telemetry = match telemetry.inner() {
+        Some(telemetry) => telemetry,
+        None => return Ok(Json(None)),
+    };
+
+    let report = telemetry.report().await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Failed to load telemetry")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(Some(report)))
+}
+
+/// The end-of-day marks of all CFDs that were open on `date`, `YYYY-MM-DD` in UTC.
+///
+/// Defaults to the current day.
+#[rocket::get("/reports/marks?<date>")]
+#[instrument(name = "GET /reports/marks", skip(daily_marks, _user), err)]
+pub async fn get_daily_marks(
+    date: Option<String>,
+    daily_marks: &State<daily_marks::DailyMarks>,
+    _user: User,
+) -> Result<Json<Vec<sqlite_db::daily_marks::DailyMark>>, HttpApiProblem> {
+    let date = match date {
+        Some(date) => daily_marks::parse_date(&date).map_err(|e| {
+            HttpApiProblem::new(StatusCode::BAD_REQUEST)
+                .title("Invalid date")
+                .detail(format!("{e:#}"))
+        })?,
+        None => time::OffsetDateTime::now_utc().date(),
+    };
+
+    let marks = daily_marks.load(date).await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Failed to load daily marks")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(marks))
+}
+
+/// Status of all supervised actors, to find out which subsystem keeps restarting.
+#[rocket::get("/debug/actors")]
+#[instrument(name = "GET /debug/actors", skip_all)]
+pub async fn get_supervision_tree(_user: User) -> Json<supervision::SupervisionTree> {
+    Json(supervision::GetSupervisionTree.query())
+}
+
+/// Orphaned protocol artifacts found by the last run of the janitor, `null` if it did not run yet.
+#[rocket::get("/debug/janitor")]
+#[instrument(name = "GET /debug/janitor", skip_all)]
+pub async fn get_janitor_report(
+    janitor: &State<janitor::Janitor>,
+    _user: User,
+) -> Json<Option<janitor::Report>> {
+    Json(janitor.report())
+}
+
+/// Status of the periodic maintenance jobs.
+#[rocket::get("/debug/jobs")]
+#[instrument(name = "GET /debug/jobs", skip_all, err)]
+pub async fn get_jobs(
+    scheduler: &State<xtra::Address<scheduler::Actor>>,
+    _user: User,
+) -> Result<Json<Vec<scheduler::JobStatus>>, HttpApiProblem> {
+    let statuses = scheduler
+        .send(scheduler::GetJobStatuses)
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Failed to get job statuses")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(statuses))
+}
+
+/// Encrypted backup of an open CFD, restorable with `hermes-recover` and the identity seed.
+#[rocket::get("/cfds/<order_id>/backup")]
+#[instrument(name = "GET /cfds/<order_id>/backup", skip(exporter, _user), err)]
+pub async fn get_cfd_backup(
+    order_id: Uuid,
+    exporter: &State<backup::Exporter>,
+    _user: User,
+) -> Result<DownloadResponsePro, HttpApiProblem> {
+    let order_id = model::OrderId::from(order_id);
+
+    let backup = exporter.export(order_id).await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Failed to export CFD backup")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(DownloadResponsePro::from_vec(
+        backup,
+        Some(format!("cfd-{order_id}.backup")),
+        Some(mime::APPLICATION_OCTET_STREAM),
+    ))
+}
+
+/// Capture the full messages of the next protocol run of an order into a dedicated file.
+#[rocket::put("/debug/transcripts/<order_id>")]
+#[instrument(
+    name = "PUT /debug/transcripts/<order_id>",
+    skip(transcripts, _user),
+    err
+)]
+pub async fn put_transcript(
+    order_id: Uuid,
+    transcripts: &State<transcript::Transcripts>,
+    _user: User,
+) -> Result<Json<transcript::Transcript>, HttpApiProblem> {
+    let transcript = transcripts.enable(order_id.into()).map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Failed to enable protocol transcript")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(transcript))
+}
+
+#[rocket::delete("/debug/transcripts/<order_id>")]
+#[instrument(
+    name = "DELETE /debug/transcripts/<order_id>",
+    skip(transcripts, _user),
+    err
+)]
+pub async fn delete_transcript(
+    order_id: Uuid,
+    transcripts: &State<transcript::Transcripts>,
+    _user: User,
+) -> Result<(), HttpApiProblem> {
+    if !transcripts.disable(order_id.into()) {
+        return Err(HttpApiProblem::new(StatusCode::NOT_FOUND)
+            .title("Protocol transcript not enabled")
+            .detail(format!(
+                "No protocol transcript enabled for order {order_id}"
+            )));
+    }
+
+    Ok(())
+}
+
+/// Transcripts that are waiting for or capturing a protocol run.
+#[rocket::get("/debug/transcripts")]
+#[instrument(name = "GET /debug/transcripts", skip_all)]
+pub async fn get_transcripts(
+    transcripts: &State<transcript::Transcripts>,
+    _user: User,
+) -> Json<Vec<transcript::Transcript>> {
+    Json(transcripts.enabled())
+}
+
 #[rocket::post("/change-password", data = "<form>")]
 pub async fn change_password(
     mut user: User,
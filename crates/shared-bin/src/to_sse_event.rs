@@ -6,7 +6,9 @@ use daemon::identify;
 use daemon::listen_protocols::does_maker_satisfy_taker_needs;
 use daemon::listen_protocols::REQUIRED_MAKER_LISTEN_PROTOCOLS;
 use daemon::online_status;
+use daemon::projection::explorer;
 use daemon::projection::Cfd;
+use daemon::projection::ChainTip;
 use model::Timestamp;
 use rocket::response::stream::Event;
 use serde::Serialize;
@@ -22,6 +24,12 @@ impl ToSseEvent for Vec<Cfd> {
     }
 }
 
+impl ToSseEvent for Option<ChainTip> {
+    fn to_sse_event(&self) -> Event {
+        Event::json(&self).event("chain_tip")
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct WalletInfo {
     #[serde(with = "daemon::bdk::bitcoin::util::amount::serde::as_btc")]
@@ -29,6 +37,7 @@ pub struct WalletInfo {
     address: String,
     last_updated_at: Timestamp,
     transactions: Vec<TransactionDetails>,
+    pending_deposits: Vec<model::PendingDeposit>,
     managed_wallet: bool,
 }
 
@@ -46,18 +55,12 @@ pub struct TransactionDetails {
 impl From<(Network, &daemon::bdk::TransactionDetails)> for TransactionDetails {
     fn from((network, tx): (Network, &daemon::bdk::TransactionDetails)) -> Self {
         let txid = tx.txid;
-        let link = match network {
-            Network::Bitcoin => Some(format!("https://mempool.space/tx/{txid}")),
-            Network::Testnet => Some(format!("https://mempool.space/testnet/tx/{txid}")),
-            Network::Signet => Some(format!("https://mempool.space/signet/tx/{txid}")),
-            Network::Regtest => None,
-        };
         Self {
             txid,
             received: Amount::from_sat(tx.received),
             sent: Amount::from_sat(tx.sent),
             confirmation_time: tx.confirmation_time.clone(),
-            link,
+            link: explorer::tx_url(txid, network),
         }
     }
 }
@@ -76,6 +79,7 @@ impl ToSseEvent for Option<model::WalletInfo> {
                 address: wallet_info.address.to_string(),
                 last_updated_at: wallet_info.last_updated_at,
                 transactions: transaction_details,
+                pending_deposits: wallet_info.pending_deposits.clone(),
                 managed_wallet: wallet_info.managed_wallet,
             }
         });
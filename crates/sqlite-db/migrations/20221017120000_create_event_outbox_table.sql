@@ -0,0 +1,6 @@
+-- Events whose side effects (e.g. broadcasting transactions) have not been acknowledged yet
+CREATE TABLE IF NOT EXISTS event_outbox (
+    id integer PRIMARY KEY autoincrement,
+    event_id integer UNIQUE NOT NULL,
+    FOREIGN KEY (event_id) REFERENCES EVENTS (id) ON DELETE CASCADE
+);
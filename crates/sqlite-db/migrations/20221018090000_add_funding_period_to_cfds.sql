@@ -0,0 +1,8 @@
+-- Introduce funding_period field for open cfds.
+--
+-- Default to Hourly for all already opened CFDs, as this is how funding
+-- fees were charged so far.
+ALTER TABLE
+    cfds
+ADD
+    COLUMN funding_period NOT NULL DEFAULT 'Hourly';
@@ -0,0 +1,10 @@
+-- Funding rates the maker offered over time, one row per change of the funding rate
+CREATE TABLE IF NOT EXISTS funding_rate_history (
+    id integer PRIMARY KEY autoincrement,
+    contract_symbol text NOT NULL,
+    position_maker text NOT NULL,
+    funding_rate text NOT NULL,
+    timestamp integer NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS funding_rate_history_timestamp ON funding_rate_history (timestamp);
@@ -0,0 +1,12 @@
+-- Anonymized failure reports of protocols sent by the counterparty
+CREATE TABLE IF NOT EXISTS protocol_failures (
+    id integer PRIMARY KEY autoincrement,
+    peer_id text NOT NULL,
+    order_id text NOT NULL,
+    step text NOT NULL,
+    category text NOT NULL,
+    daemon_version text NOT NULL,
+    timestamp integer NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS protocol_failures_timestamp ON protocol_failures (timestamp);
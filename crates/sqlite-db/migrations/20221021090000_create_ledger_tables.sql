@@ -0,0 +1,19 @@
+-- Transactions of CFDs that move funds of the wallet, used to classify wallet transactions
+CREATE TABLE IF NOT EXISTS ledger_cfd_transactions (
+    txid text PRIMARY KEY NOT NULL,
+    order_id text NOT NULL,
+    reason text NOT NULL
+);
+
+-- Changes of the wallet balance, derived from confirmed wallet transactions
+CREATE TABLE IF NOT EXISTS ledger_entries (
+    id integer PRIMARY KEY autoincrement,
+    txid text NOT NULL,
+    reason text NOT NULL,
+    order_id text,
+    amount integer NOT NULL,
+    timestamp integer NOT NULL,
+    UNIQUE (txid, reason)
+);
+
+CREATE INDEX IF NOT EXISTS ledger_entries_timestamp ON ledger_entries (timestamp);
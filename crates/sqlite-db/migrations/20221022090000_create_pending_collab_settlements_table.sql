@@ -0,0 +1,6 @@
+-- Collaborative settlements in which the taker sent its signature but did not receive the maker's
+CREATE TABLE IF NOT EXISTS pending_collab_settlements (
+    order_id text PRIMARY KEY NOT NULL,
+    unsigned_tx text NOT NULL,
+    timestamp integer NOT NULL
+);
@@ -0,0 +1,22 @@
+-- Every revision of an offer the maker published, with the exact parameters of the offer
+CREATE TABLE IF NOT EXISTS offer_history (
+    id integer PRIMARY KEY autoincrement,
+    offer_id text NOT NULL,
+    revision integer NOT NULL,
+    contract_symbol text NOT NULL,
+    position_maker text NOT NULL,
+    price text NOT NULL,
+    -- The complete offer encoded as JSON
+    offer text NOT NULL,
+    published_at integer NOT NULL,
+    UNIQUE (offer_id, revision)
+);
+
+CREATE INDEX IF NOT EXISTS offer_history_published_at ON offer_history (published_at);
+
+-- Entries are only ever deleted once they are past the retention period
+CREATE TRIGGER IF NOT EXISTS offer_history_append_only
+    BEFORE UPDATE ON offer_history
+BEGIN
+    SELECT RAISE(ABORT, 'offer_history is append-only');
+END;
@@ -0,0 +1,8 @@
+-- Actions the taker requested while the maker was offline, executed once the maker is back online
+CREATE TABLE IF NOT EXISTS intents (
+    order_id text PRIMARY KEY NOT NULL,
+    -- The requested action encoded as JSON
+    action text NOT NULL,
+    created_at integer NOT NULL,
+    expires_at integer NOT NULL
+);
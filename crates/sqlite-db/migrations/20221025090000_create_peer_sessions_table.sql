@@ -0,0 +1,15 @@
+-- Connections of takers to the maker, used to derive business metrics
+CREATE TABLE IF NOT EXISTS peer_sessions (
+    id integer PRIMARY KEY autoincrement,
+    peer_id text NOT NULL,
+    -- The address the taker connected from
+    address text NOT NULL,
+    -- Only known once the taker sent its identify message
+    daemon_version text,
+    connected_at integer NOT NULL,
+    -- Not set while the session is ongoing
+    disconnected_at integer
+);
+
+CREATE INDEX IF NOT EXISTS peer_sessions_peer_id ON peer_sessions (peer_id);
+CREATE INDEX IF NOT EXISTS peer_sessions_disconnected_at ON peer_sessions (disconnected_at);
@@ -0,0 +1,9 @@
+-- Latest values of monotonic counters, restored at startup so that they survive restarts
+CREATE TABLE IF NOT EXISTS metric_snapshots (
+    name text NOT NULL,
+    -- The label values of the counter, opaque to the database
+    labels text NOT NULL,
+    value real NOT NULL,
+    updated_at integer NOT NULL,
+    PRIMARY KEY (name, labels)
+);
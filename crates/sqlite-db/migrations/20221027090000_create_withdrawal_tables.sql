@@ -0,0 +1,33 @@
+-- Withdrawals requested through the maker's API
+CREATE TABLE IF NOT EXISTS withdrawal_requests (
+    id integer PRIMARY KEY autoincrement,
+    -- Not set if the whole balance is withdrawn
+    amount integer,
+    address text NOT NULL,
+    fee_rate real NOT NULL,
+    status text NOT NULL,
+    created_at integer NOT NULL,
+    -- Pending requests can no longer be approved after this time
+    expires_at integer NOT NULL,
+    -- Only set once the withdrawal transaction was broadcast
+    txid text
+);
+
+-- Every change of a withdrawal request, including failed approval attempts
+CREATE TABLE IF NOT EXISTS withdrawal_audit_log (
+    id integer PRIMARY KEY autoincrement,
+    request_id integer NOT NULL,
+    action text NOT NULL,
+    detail text,
+    timestamp integer NOT NULL,
+    FOREIGN KEY (request_id) REFERENCES withdrawal_requests (id)
+);
+
+CREATE INDEX IF NOT EXISTS withdrawal_audit_log_request_id ON withdrawal_audit_log (request_id);
+
+-- The secret for the time-based one-time passwords approving withdrawals, there is at most one
+CREATE TABLE IF NOT EXISTS totp_secret (
+    id integer PRIMARY KEY CHECK (id = 1),
+    secret blob NOT NULL,
+    created_at integer NOT NULL
+);
@@ -0,0 +1,7 @@
+-- User-defined labels attached to CFDs, e.g. the strategy or client a position belongs to
+CREATE TABLE IF NOT EXISTS cfd_tags (
+    order_id text NOT NULL,
+    tag text NOT NULL,
+    created_at integer NOT NULL,
+    PRIMARY KEY (order_id, tag)
+);
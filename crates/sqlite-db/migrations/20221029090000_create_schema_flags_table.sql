@@ -0,0 +1,9 @@
+-- State of migrations that are rolled out without downtime
+--
+-- While a migration is in state `dual_write`, data is written to both its legacy and its new
+-- structures. Finalizing the migration drops the legacy structures.
+CREATE TABLE IF NOT EXISTS schema_flags (
+    name text PRIMARY KEY NOT NULL,
+    state text NOT NULL CHECK (state IN ('dual_write', 'finalized')),
+    updated_at integer NOT NULL
+);
@@ -0,0 +1,14 @@
+-- Deposits the maker expects from clients, matched against incoming wallet transactions
+CREATE TABLE IF NOT EXISTS expected_deposits (
+    label text PRIMARY KEY NOT NULL,
+    address text NOT NULL UNIQUE,
+    -- Not set if any amount is expected
+    amount integer,
+    created_at integer NOT NULL,
+    -- Only set once a transaction paying to the address confirmed
+    txid text,
+    received_amount integer,
+    received_at integer
+);
+
+CREATE INDEX IF NOT EXISTS expected_deposits_txid ON expected_deposits (txid);
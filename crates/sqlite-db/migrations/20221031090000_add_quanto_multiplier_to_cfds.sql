@@ -0,0 +1,8 @@
+-- Persist the quanto multiplier agreed for a cfd.
+--
+-- NULL for inverse contracts and for cfds opened before the multiplier was
+-- agreed explicitly. Those use the multiplier of the symbol registry.
+ALTER TABLE
+    cfds
+ADD
+    COLUMN quanto_multiplier TEXT;
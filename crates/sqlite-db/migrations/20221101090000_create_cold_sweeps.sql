@@ -0,0 +1,20 @@
+-- Policy for forwarding funds of the taker's wallet to an external cold wallet, there is at most one
+CREATE TABLE IF NOT EXISTS cold_sweep_policy (
+    id integer PRIMARY KEY CHECK (id = 1),
+    enabled boolean NOT NULL,
+    address text NOT NULL,
+    -- Balance in satoshis that stays in the wallet
+    threshold integer NOT NULL,
+    -- Highest fee rate in satoshis per vbyte at which funds are forwarded
+    max_fee_rate real NOT NULL,
+    updated_at integer NOT NULL
+);
+
+-- Transactions that forwarded funds to the cold wallet
+CREATE TABLE IF NOT EXISTS cold_sweeps (
+    txid text PRIMARY KEY NOT NULL,
+    address text NOT NULL,
+    amount integer NOT NULL,
+    fee_rate real NOT NULL,
+    created_at integer NOT NULL
+);
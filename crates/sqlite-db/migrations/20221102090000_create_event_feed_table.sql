@@ -0,0 +1,28 @@
+-- Append-only copy of all cfd events for consumers outside of the daemon, e.g. accounting.
+--
+-- Unlike the rows of `events`, which are deleted once a cfd is closed, feed entries are never
+-- deleted. `seq` is assigned in the transaction that inserts the event, hence the sequence has no
+-- gaps and is never reused.
+CREATE TABLE IF NOT EXISTS event_feed (
+    seq integer PRIMARY KEY NOT NULL,
+    order_id text NOT NULL,
+    name text NOT NULL,
+    data text NOT NULL,
+    created_at integer NOT NULL
+);
+
+-- Events of cfds that were closed before the feed existed are gone
+INSERT INTO
+    event_feed (seq, order_id, name, data, created_at)
+SELECT
+    ROW_NUMBER() OVER (
+        ORDER BY
+            events.id
+    ),
+    cfds.order_id,
+    events.name,
+    events.data,
+    CAST(events.created_at AS integer)
+FROM
+    events
+    JOIN cfds ON cfds.id = events.cfd_id;
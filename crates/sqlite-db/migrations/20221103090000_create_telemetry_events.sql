@@ -0,0 +1,9 @@
+-- Coarse anonymous usage events of daemons that opted into telemetry, without any reference to a
+-- cfd. Rows are deleted once they are older than the telemetry retention period.
+CREATE TABLE IF NOT EXISTS telemetry_events (
+    id integer PRIMARY KEY AUTOINCREMENT,
+    category text NOT NULL,
+    created_at integer NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS telemetry_events_created_at ON telemetry_events (created_at);
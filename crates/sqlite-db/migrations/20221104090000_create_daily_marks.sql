@@ -0,0 +1,12 @@
+-- End-of-day marks of open cfds, one per cfd and UTC date. The mark itself is stored as JSON.
+-- Marks are kept after the cfd was closed, hence there is no foreign key to the cfds table.
+CREATE TABLE IF NOT EXISTS daily_marks (
+    id integer PRIMARY KEY AUTOINCREMENT,
+    order_id text NOT NULL,
+    date text NOT NULL,
+    mark text NOT NULL,
+    marked_at integer NOT NULL,
+    UNIQUE (order_id, date)
+);
+
+CREATE INDEX IF NOT EXISTS daily_marks_date ON daily_marks (date);
@@ -0,0 +1,21 @@
+-- API tokens delegating actions on individual CFDs to third-party services. Only the SHA-256 hash
+-- of a token is stored, the token itself is shown once when it is created.
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id integer PRIMARY KEY AUTOINCREMENT,
+    token_hash text NOT NULL UNIQUE,
+    label text,
+    -- Comma-separated actions the token authorizes
+    actions text NOT NULL,
+    created_at integer NOT NULL,
+    expires_at integer NOT NULL,
+    revoked_at integer
+);
+
+-- The CFDs a token authorizes actions on. Tokens are kept after the CFD was closed, hence there is
+-- no foreign key to the cfds table.
+CREATE TABLE IF NOT EXISTS api_token_orders (
+    token_id integer NOT NULL,
+    order_id text NOT NULL,
+    PRIMARY KEY (token_id, order_id),
+    FOREIGN KEY (token_id) REFERENCES api_tokens (id)
+);
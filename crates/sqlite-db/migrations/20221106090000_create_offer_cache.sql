@@ -0,0 +1,10 @@
+-- The latest offers the taker received, one per contract symbol and position of the maker. They are
+-- shown on startup until fresh offers arrive.
+CREATE TABLE IF NOT EXISTS offer_cache (
+    contract_symbol text NOT NULL,
+    position_maker text NOT NULL,
+    -- The complete offer encoded as JSON
+    offer text NOT NULL,
+    received_at integer NOT NULL,
+    PRIMARY KEY (contract_symbol, position_maker)
+);
@@ -0,0 +1,10 @@
+-- Who published the CET of a CFD after we deferred publishing it ourselves. Used to decide whether
+-- it is worth waiting for the counterparty to publish. Kept after the CFD was closed, hence keyed
+-- by the txid of the CET without a foreign key to the cfds table.
+CREATE TABLE IF NOT EXISTS cet_publications (
+    id integer PRIMARY KEY AUTOINCREMENT,
+    txid text NOT NULL UNIQUE,
+    -- `us` or `counterparty`
+    published_by text NOT NULL,
+    created_at integer NOT NULL
+);
@@ -0,0 +1,185 @@
+-- Delete the rows that belong to an open CFD together with the CFD instead of relying on the
+-- application to delete them first.
+--
+-- SQLite cannot alter the constraints of a table, hence the tables are rebuilt. Dropping a table
+-- deletes its rows first, which would cascade to the tables referencing it. The tables referencing
+-- `events` are therefore rebuilt as well and dropped before `events`. Renaming the new tables
+-- updates the references to them.
+--
+-- Rows that do not belong to any open CFD are not copied, the daemon could not load them anyway.
+-- The `check-integrity` command reports the orphaned rows of tables without foreign keys.
+CREATE TABLE events_new (
+    id integer PRIMARY KEY autoincrement,
+    cfd_id integer NOT NULL,
+    name text NOT NULL,
+    data text NOT NULL,
+    created_at text NOT NULL,
+    FOREIGN KEY (cfd_id) REFERENCES cfds (id) ON DELETE CASCADE
+);
+
+INSERT INTO
+    events_new (id, cfd_id, name, data, created_at)
+SELECT
+    events.id,
+    events.cfd_id,
+    events.name,
+    events.data,
+    events.created_at
+FROM
+    events
+    JOIN cfds ON cfds.id = events.cfd_id;
+
+-- Ids of events of closed CFDs must not be reused
+UPDATE
+    sqlite_sequence
+SET
+    seq = (
+        SELECT
+            seq
+        FROM
+            sqlite_sequence
+        WHERE
+            lower(name) = 'events'
+    )
+WHERE
+    name = 'events_new'
+    AND EXISTS (
+        SELECT
+            seq
+        FROM
+            sqlite_sequence
+        WHERE
+            lower(name) = 'events'
+    );
+
+CREATE TABLE event_outbox_new (
+    id integer PRIMARY KEY autoincrement,
+    event_id integer UNIQUE NOT NULL,
+    FOREIGN KEY (event_id) REFERENCES events_new (id) ON DELETE CASCADE
+);
+
+INSERT INTO
+    event_outbox_new (id, event_id)
+SELECT
+    id,
+    event_id
+FROM
+    event_outbox
+WHERE
+    event_id IN (
+        SELECT
+            id
+        FROM
+            events_new
+    );
+
+CREATE TABLE rollover_completed_event_data_new (
+    id integer PRIMARY KEY autoincrement,
+    cfd_id integer UNIQUE NOT NULL,
+    event_id integer NOT NULL,
+    settlement_event_id text NOT NULL,
+    refund_timelock text NOT NULL,
+    funding_fee number NOT NULL,
+    rate text NOT NULL,
+    identity text NOT NULL,
+    identity_counterparty text NOT NULL,
+    maker_address text NOT NULL,
+    taker_address text NOT NULL,
+    maker_lock_amount number NOT NULL,
+    taker_lock_amount number NOT NULL,
+    publish_sk text NOT NULL,
+    publish_pk_counterparty text NOT NULL,
+    revocation_secret text NOT NULL,
+    revocation_pk_counterparty text NOT NULL,
+    lock_tx text NOT NULL,
+    lock_tx_descriptor text NOT NULL,
+    commit_tx text NOT NULL,
+    commit_adaptor_signature text NOT NULL,
+    commit_descriptor text NOT NULL,
+    refund_tx text NOT NULL,
+    refund_signature text NOT NULL,
+    complete_fee INTEGER NULL,
+    complete_fee_flow text NULL,
+    FOREIGN KEY (cfd_id) REFERENCES cfds (id) ON DELETE CASCADE,
+    FOREIGN KEY (event_id) REFERENCES events_new (id) ON DELETE CASCADE
+);
+
+INSERT INTO
+    rollover_completed_event_data_new
+SELECT
+    *
+FROM
+    rollover_completed_event_data
+WHERE
+    event_id IN (
+        SELECT
+            id
+        FROM
+            events_new
+    );
+
+DROP TABLE event_outbox;
+DROP TABLE rollover_completed_event_data;
+DROP TABLE events;
+
+ALTER TABLE
+    events_new RENAME TO events;
+ALTER TABLE
+    event_outbox_new RENAME TO event_outbox;
+ALTER TABLE
+    rollover_completed_event_data_new RENAME TO rollover_completed_event_data;
+
+-- Intents and pending collaborative settlements only concern open CFDs
+CREATE TABLE intents_new (
+    order_id text PRIMARY KEY NOT NULL,
+    -- The requested action encoded as JSON
+    action text NOT NULL,
+    created_at integer NOT NULL,
+    expires_at integer NOT NULL,
+    FOREIGN KEY (order_id) REFERENCES cfds (order_id) ON DELETE CASCADE
+);
+
+INSERT INTO
+    intents_new
+SELECT
+    *
+FROM
+    intents
+WHERE
+    order_id IN (
+        SELECT
+            order_id
+        FROM
+            cfds
+    );
+
+DROP TABLE intents;
+
+ALTER TABLE
+    intents_new RENAME TO intents;
+
+CREATE TABLE pending_collab_settlements_new (
+    order_id text PRIMARY KEY NOT NULL,
+    unsigned_tx text NOT NULL,
+    timestamp integer NOT NULL,
+    FOREIGN KEY (order_id) REFERENCES cfds (order_id) ON DELETE CASCADE
+);
+
+INSERT INTO
+    pending_collab_settlements_new
+SELECT
+    *
+FROM
+    pending_collab_settlements
+WHERE
+    order_id IN (
+        SELECT
+            order_id
+        FROM
+            cfds
+    );
+
+DROP TABLE pending_collab_settlements;
+
+ALTER TABLE
+    pending_collab_settlements_new RENAME TO pending_collab_settlements;
@@ -0,0 +1,17 @@
+-- Side effects that keep failing are retried with a growing delay and given up eventually
+ALTER TABLE
+    event_outbox
+ADD
+    COLUMN attempts integer NOT NULL DEFAULT 0;
+
+-- Unix timestamp in seconds before which the side effects are not retried
+ALTER TABLE
+    event_outbox
+ADD
+    COLUMN next_attempt_at integer NOT NULL DEFAULT 0;
+
+-- Unix timestamp in seconds at which the side effects were given up, NULL while still retried
+ALTER TABLE
+    event_outbox
+ADD
+    COLUMN given_up_at integer;
@@ -0,0 +1,3 @@
+-- The time step of the last accepted one-time password, so that it is not accepted again after a
+-- restart
+ALTER TABLE totp_secret ADD COLUMN last_used_step integer;
@@ -0,0 +1,8 @@
+-- The wind-down of the maker, there is at most one. Once started, the maker keeps its offers
+-- withdrawn and rejects rollovers, also after a restart.
+CREATE TABLE IF NOT EXISTS wind_down (
+    id integer PRIMARY KEY CHECK (id = 1),
+    started_at integer NOT NULL,
+    -- End of the grace period for collaborative settlement
+    deadline integer NOT NULL
+);
@@ -0,0 +1,356 @@
+//! API tokens scoped to individual CFDs.
+//!
+//! A token authorizes a limited set of actions on a limited set of CFDs, which allows to delegate
+//! the management of a position to a third-party service without handing over full API access.
+//! Tokens expire and can be revoked; only the hash of a token is stored.
+
+use crate::models;
+use crate::Connection;
+use anyhow::bail;
+use anyhow::Result;
+use model::OrderId;
+use model::Timestamp;
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::Acquire;
+use std::fmt;
+use std::str::FromStr;
+use tracing::field::Empty;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApiToken {
+    pub id: i64,
+    pub label: Option<String>,
+    /// The CFDs the token authorizes actions on
+    pub order_ids: Vec<OrderId>,
+    pub actions: Vec<TokenAction>,
+    pub created_at: Timestamp,
+    pub expires_at: Timestamp,
+    pub revoked_at: Option<Timestamp>,
+}
+
+/// An action on a CFD that can be delegated with an API token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenAction {
+    /// Propose collaborative settlement, paying out to the wallet
+    Settle,
+    RollOver,
+    /// Publish the commit transaction
+    Commit,
+    /// Set the take-profit and stop-loss price levels
+    PriceLevels,
+}
+
+impl ApiToken {
+    /// Whether the token authorizes `action` on the CFD with `order_id` at `now`.
+    pub fn allows(&self, order_id: OrderId, action: TokenAction, now: Timestamp) -> bool {
+        self.revoked_at.is_none()
+            && now < self.expires_at
+            && self.order_ids.contains(&order_id)
+            && self.actions.contains(&action)
+    }
+}
+
+impl fmt::Display for TokenAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            TokenAction::Settle => "settle",
+            TokenAction::RollOver => "roll_over",
+            TokenAction::Commit => "commit",
+            TokenAction::PriceLevels => "price_levels",
+        };
+
+        s.fmt(f)
+    }
+}
+
+impl FromStr for TokenAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let action = match s {
+            "settle" => TokenAction::Settle,
+            "roll_over" => TokenAction::RollOver,
+            "commit" => TokenAction::Commit,
+            "price_levels" => TokenAction::PriceLevels,
+            other => bail!("Not a token action: {other}"),
+        };
+
+        Ok(action)
+    }
+}
+
+impl Connection {
+    /// Store a new API token by the hash of the token.
+    ///
+    /// Returns the id of the token.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "insert_api_token", duration_ms = Empty)
+    )]
+    pub async fn insert_api_token(
+        &self,
+        token_hash: &str,
+        label: Option<&str>,
+        order_ids: &[OrderId],
+        actions: &[TokenAction],
+        created_at: Timestamp,
+        expires_at: Timestamp,
+    ) -> Result<i64> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+        let mut db_tx = conn.begin().await?;
+
+        let actions = actions
+            .iter()
+            .map(TokenAction::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        let created_at = models::Timestamp::from(created_at);
+        let expires_at = models::Timestamp::from(expires_at);
+
+        let query_result = sqlx::query!(
+            r#"
+            INSERT INTO api_tokens
+            (
+                token_hash,
+                label,
+                actions,
+                created_at,
+                expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            token_hash,
+            label,
+            actions,
+            created_at,
+            expires_at,
+        )
+        .execute(&mut *db_tx)
+        .await?;
+        let id = query_result.last_insert_rowid();
+
+        for order_id in order_ids {
+            let order_id = models::OrderId::from(*order_id);
+
+            sqlx::query!(
+                r#"
+                INSERT OR IGNORE INTO api_token_orders
+                (
+                    token_id,
+                    order_id
+                )
+                VALUES ($1, $2)
+                "#,
+                id,
+                order_id,
+            )
+            .execute(&mut *db_tx)
+            .await?;
+        }
+
+        db_tx.commit().await?;
+
+        Ok(id)
+    }
+
+    /// Load all API tokens, including expired and revoked ones, oldest first.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_api_tokens", duration_ms = Empty)
+    )]
+    pub async fn load_api_tokens(&self) -> Result<Vec<ApiToken>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                id as "id!",
+                label,
+                actions,
+                created_at as "created_at: models::Timestamp",
+                expires_at as "expires_at: models::Timestamp",
+                revoked_at as "revoked_at: models::Timestamp"
+            FROM
+                api_tokens
+            ORDER BY
+                id ASC
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut tokens = Vec::with_capacity(rows.len());
+        for row in rows {
+            tokens.push(ApiToken {
+                id: row.id,
+                label: row.label,
+                order_ids: load_token_orders(&mut conn, row.id).await?,
+                actions: parse_actions(&row.actions)?,
+                created_at: row.created_at.into(),
+                expires_at: row.expires_at.into(),
+                revoked_at: row.revoked_at.map(Into::into),
+            });
+        }
+
+        Ok(tokens)
+    }
+
+    /// Load the API token with the hash `token_hash`, `None` if there is no such token.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_api_token_by_hash", duration_ms = Empty)
+    )]
+    pub async fn load_api_token_by_hash(&self, token_hash: &str) -> Result<Option<ApiToken>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                id as "id!",
+                label,
+                actions,
+                created_at as "created_at: models::Timestamp",
+                expires_at as "expires_at: models::Timestamp",
+                revoked_at as "revoked_at: models::Timestamp"
+            FROM
+                api_tokens
+            WHERE
+                token_hash = $1
+            "#,
+            token_hash
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        let row = match row {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+
+        Ok(Some(ApiToken {
+            id: row.id,
+            label: row.label,
+            order_ids: load_token_orders(&mut conn, row.id).await?,
+            actions: parse_actions(&row.actions)?,
+            created_at: row.created_at.into(),
+            expires_at: row.expires_at.into(),
+            revoked_at: row.revoked_at.map(Into::into),
+        }))
+    }
+
+    /// Revoke the API token with `id`, fails if there is no such token or it was revoked already.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "revoke_api_token", %id, duration_ms = Empty)
+    )]
+    pub async fn revoke_api_token(&self, id: i64, revoked_at: Timestamp) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let revoked_at = models::Timestamp::from(revoked_at);
+
+        let query_result = sqlx::query!(
+            r#"
+            UPDATE api_tokens
+            SET
+                revoked_at = $1
+            WHERE
+                id = $2 AND revoked_at IS NULL
+            "#,
+            revoked_at,
+            id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        if query_result.rows_affected() < 1 {
+            bail!("No API token {id} that is not revoked yet");
+        }
+
+        Ok(())
+    }
+}
+
+async fn load_token_orders(
+    conn: &mut sqlx::SqliteConnection,
+    token_id: i64,
+) -> Result<Vec<OrderId>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            order_id as "order_id: models::OrderId"
+        FROM
+            api_token_orders
+        WHERE
+            token_id = $1
+        "#,
+        token_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(rows.into_iter().map(|row| row.order_id.into()).collect())
+}
+
+fn parse_actions(actions: &str) -> Result<Vec<TokenAction>> {
+    actions
+        .split(',')
+        .filter(|action| !action.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+
+    #[tokio::test]
+    async fn given_revoked_token_then_it_no_longer_allows_actions() {
+        let db = memory().await.unwrap();
+
+        let order_id = OrderId::default();
+        let now = Timestamp::now();
+        let expires_at = Timestamp::new(now.seconds() + 3600);
+
+        let id = db
+            .insert_api_token(
+                "hash",
+                Some("settlement service"),
+                &[order_id],
+                &[TokenAction::Settle, TokenAction::PriceLevels],
+                now,
+                expires_at,
+            )
+            .await
+            .unwrap();
+
+        let token = db.load_api_token_by_hash("hash").await.unwrap().unwrap();
+        assert_eq!(token.id, id);
+        assert!(token.allows(order_id, TokenAction::Settle, now));
+        assert!(!token.allows(order_id, TokenAction::Commit, now));
+        assert!(!token.allows(OrderId::default(), TokenAction::Settle, now));
+        assert!(!token.allows(order_id, TokenAction::Settle, expires_at));
+
+        db.revoke_api_token(id, now).await.unwrap();
+        assert!(db.revoke_api_token(id, now).await.is_err());
+
+        let token = db.load_api_token_by_hash("hash").await.unwrap().unwrap();
+        assert!(!token.allows(order_id, TokenAction::Settle, now));
+        assert_eq!(db.load_api_tokens().await.unwrap(), vec![token]);
+        assert!(db.load_api_token_by_hash("other").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,157 @@
+//! Outcomes of deferring the publication of CETs.
+//!
+//! If we wait for the counterparty to publish the CET, we record whether it did or whether we had
+//! to publish the CET ourselves after all. Only deferred CETs are recorded: if we publish right
+//! away we cannot tell whether the counterparty would have published.
+
+use crate::models;
+use crate::Connection;
+use anyhow::bail;
+use anyhow::Result;
+use bdk::bitcoin::Txid;
+use model::Timestamp;
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+use tracing::field::Empty;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PublishedBy {
+    Us,
+    Counterparty,
+}
+
+impl fmt::Display for PublishedBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            PublishedBy::Us => "us",
+            PublishedBy::Counterparty => "counterparty",
+        };
+
+        s.fmt(f)
+    }
+}
+
+impl FromStr for PublishedBy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let published_by = match s {
+            "us" => PublishedBy::Us,
+            "counterparty" => PublishedBy::Counterparty,
+            other => bail!("Unknown publisher of CET: {other}"),
+        };
+
+        Ok(published_by)
+    }
+}
+
+impl Connection {
+    /// Record who published a deferred CET, the first record of a CET wins.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "insert_cet_publication", %txid, duration_ms = Empty)
+    )]
+    pub async fn insert_cet_publication(
+        &self,
+        txid: Txid,
+        published_by: PublishedBy,
+        created_at: Timestamp,
+    ) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let txid = models::Txid::from(txid);
+        let published_by = published_by.to_string();
+        let created_at = models::Timestamp::from(created_at);
+
+        sqlx::query!(
+            r#"
+            INSERT OR IGNORE INTO cet_publications
+            (
+                txid,
+                published_by,
+                created_at
+            )
+            VALUES ($1, $2, $3)
+            "#,
+            txid,
+            published_by,
+            created_at,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Load who published the `limit` most recently deferred CETs, newest first.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_recent_cet_publications", duration_ms = Empty)
+    )]
+    pub async fn load_recent_cet_publications(&self, limit: u32) -> Result<Vec<PublishedBy>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                published_by
+            FROM
+                cet_publications
+            ORDER BY
+                id DESC
+            LIMIT
+                $1
+            "#,
+            limit
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| row.published_by.parse())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+    use bdk::bitcoin::hashes::Hash;
+
+    #[tokio::test]
+    async fn first_publication_of_cet_is_kept() {
+        let db = memory().await.unwrap();
+
+        let first = Txid::from_inner([1; 32]);
+        let second = Txid::from_inner([2; 32]);
+        let now = Timestamp::now();
+
+        db.insert_cet_publication(first, PublishedBy::Counterparty, now)
+            .await
+            .unwrap();
+        db.insert_cet_publication(first, PublishedBy::Us, now)
+            .await
+            .unwrap();
+        db.insert_cet_publication(second, PublishedBy::Us, now)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            db.load_recent_cet_publications(10).await.unwrap(),
+            vec![PublishedBy::Us, PublishedBy::Counterparty]
+        );
+        assert_eq!(
+            db.load_recent_cet_publications(1).await.unwrap(),
+            vec![PublishedBy::Us]
+        );
+    }
+}
@@ -0,0 +1,143 @@
+//! User-defined tags attached to CFDs.
+//!
+//! Tags are not part of the event log because they do not affect the contract; they are kept
+//! independently of whether the CFD is open, closed or failed.
+
+use crate::models;
+use crate::Connection;
+use anyhow::Result;
+use model::OrderId;
+use model::Timestamp;
+use tracing::field::Empty;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CfdTag {
+    pub order_id: OrderId,
+    pub tag: String,
+}
+
+impl Connection {
+    /// Attach a tag to a CFD, attaching the same tag again has no effect.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "insert_cfd_tag", %order_id, duration_ms = Empty)
+    )]
+    pub async fn insert_cfd_tag(&self, order_id: OrderId, tag: &str) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let order_id = models::OrderId::from(order_id);
+        let created_at = models::Timestamp::from(Timestamp::now());
+
+        sqlx::query!(
+            r#"
+            INSERT OR IGNORE INTO cfd_tags
+            (
+                order_id,
+                tag,
+                created_at
+            )
+            VALUES ($1, $2, $3)
+            "#,
+            order_id,
+            tag,
+            created_at,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Remove a tag from a CFD.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "delete_cfd_tag", %order_id, duration_ms = Empty)
+    )]
+    pub async fn delete_cfd_tag(&self, order_id: OrderId, tag: &str) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let order_id = models::OrderId::from(order_id);
+
+        sqlx::query!(
+            r#"
+            DELETE FROM cfd_tags
+            WHERE
+                order_id = $1 AND tag = $2
+            "#,
+            order_id,
+            tag,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Load the tags of all CFDs, in the order they were attached.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_cfd_tags", duration_ms = Empty)
+    )]
+    pub async fn load_cfd_tags(&self) -> Result<Vec<CfdTag>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                order_id as "order_id: models::OrderId",
+                tag
+            FROM
+                cfd_tags
+            ORDER BY
+                created_at ASC, tag ASC
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let tags = rows
+            .into_iter()
+            .map(|row| CfdTag {
+                order_id: row.order_id.into(),
+                tag: row.tag,
+            })
+            .collect();
+
+        Ok(tags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+
+    #[tokio::test]
+    async fn given_tag_attached_twice_and_removed_then_only_other_tags_left() {
+        let db = memory().await.unwrap();
+
+        let order_id = OrderId::default();
+        db.insert_cfd_tag(order_id, "hedge").await.unwrap();
+        db.insert_cfd_tag(order_id, "hedge").await.unwrap();
+        db.insert_cfd_tag(order_id, "client-a").await.unwrap();
+
+        db.delete_cfd_tag(order_id, "hedge").await.unwrap();
+
+        assert_eq!(
+            db.load_cfd_tags().await.unwrap(),
+            vec![CfdTag {
+                order_id,
+                tag: "client-a".to_owned()
+            }]
+        );
+    }
+}
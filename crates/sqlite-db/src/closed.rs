@@ -8,7 +8,6 @@
 //! of CFD.
 
 use crate::delete_from_cfds_table;
-use crate::delete_from_events_table;
 use crate::derive_known_peer_id;
 use crate::event_log::EventLog;
 use crate::event_log::EventLogEntry;
@@ -29,11 +28,13 @@ use bdk::miniscript::DescriptorTrait;
 use maia_core::TransactionExt;
 use model::libp2p::PeerId;
 use model::long_and_short_leverage;
+use model::symbols;
 use model::CfdEvent;
 use model::ClosedCfd;
 use model::ContractSymbol;
 use model::Contracts;
 use model::Dlc;
+use model::EventKind;
 use model::FeeAccount;
 use model::Fees;
 use model::FundingFee;
@@ -50,9 +51,11 @@ use model::Timestamp;
 use model::SETTLEMENT_INTERVAL;
 use models::Payout;
 use models::Vout;
+use rust_decimal::Decimal;
 use sqlx::Acquire;
 use sqlx::SqliteConnection;
 use time::OffsetDateTime;
+use tracing::field::Empty;
 
 /// A trait for building an aggregate based on a `ClosedCfd`.
 pub trait ClosedCfdAggregate: CfdAggregate {
@@ -60,7 +63,14 @@ pub trait ClosedCfdAggregate: CfdAggregate {
 }
 
 impl Connection {
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "move_to_closed_cfds", duration_ms = Empty)
+    )]
     pub async fn move_to_closed_cfds(&self) -> Result<()> {
+        let _timer = self.query_timer();
+
         let ids = self.closed_cfd_ids_according_to_the_blockchain().await?;
 
         if !ids.is_empty() {
@@ -88,7 +98,6 @@ impl Connection {
 
                 insert_settlement(&mut db_tx, id, closed_cfd.settlement).await?;
 
-                delete_from_events_table(&mut db_tx, id).await?;
                 delete_from_cfds_table(&mut db_tx, id).await?;
 
                 db_tx.commit().await?;
@@ -106,10 +115,17 @@ impl Connection {
     }
 
     /// Load a closed CFD from the database.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_closed_cfd", order_id = %id, duration_ms = Empty)
+    )]
     pub async fn load_closed_cfd<C>(&self, id: OrderId, args: C::CtorArgs) -> Result<C>
     where
         C: ClosedCfdAggregate,
     {
+        let _timer = self.query_timer();
+
         let mut conn = self.inner.acquire().await?;
 
         let inner_id = models::OrderId::from(id);
@@ -186,7 +202,14 @@ impl Connection {
         Ok(C::new_closed(args, cfd))
     }
 
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_closed_cfd_ids", duration_ms = Empty)
+    )]
     pub(crate) async fn load_closed_cfd_ids(&self) -> Result<Vec<OrderId>> {
+        let _timer = self.query_timer();
+
         let mut conn = self.inner.acquire().await?;
 
         let ids = sqlx::query!(
@@ -205,6 +228,59 @@ impl Connection {
 
         Ok(ids)
     }
+
+    /// Load the IDs of all CFDs whose settlement transaction was confirmed at or after `since`.
+    ///
+    /// Considers both CFDs that were already moved to the `closed_cfds` table and CFDs that are
+    /// closed but were not moved yet.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_cfd_ids_closed_since", since = %since, duration_ms = Empty)
+    )]
+    pub async fn load_cfd_ids_closed_since(&self, since: Timestamp) -> Result<Vec<OrderId>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let since = since.seconds();
+        let collaborative_settlement_confirmed = EventKind::COLLABORATIVE_SETTLEMENT_CONFIRMED;
+        let cet_confirmed = EventKind::CET_CONFIRMED;
+        let refund_confirmed = EventKind::REFUND_CONFIRMED;
+
+        let ids = sqlx::query!(
+            r#"
+            SELECT
+                cfds.order_id as "order_id: models::OrderId"
+            FROM
+                events
+            JOIN
+                cfds on cfds.id = events.cfd_id
+            WHERE
+                events.name IN ($2, $3, $4) AND CAST(events.created_at AS INTEGER) >= $1
+            UNION
+            SELECT
+                closed_cfds.order_id as "order_id: models::OrderId"
+            FROM
+                event_log
+            JOIN
+                closed_cfds on closed_cfds.id = event_log.cfd_id
+            WHERE
+                event_log.name IN ($2, $3, $4) AND event_log.created_at >= $1
+            "#,
+            since,
+            collaborative_settlement_confirmed,
+            cet_confirmed,
+            refund_confirmed,
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|r| r.order_id.into())
+        .collect();
+
+        Ok(ids)
+    }
 }
 
 /// Auxiliary type used to gradually combine a `Cfd` with its list of
@@ -250,6 +326,7 @@ impl ClosedCfdInputAggregate {
             opening_fee,
             initial_funding_rate,
             contract_symbol,
+            quanto_multiplier,
             ..
         } = cfd;
         let n_contracts = quantity.to_u64();
@@ -266,7 +343,7 @@ impl ClosedCfdInputAggregate {
                 short_leverage,
                 initial_funding_rate,
                 SETTLEMENT_INTERVAL.whole_hours(),
-                contract_symbol,
+                symbols::payout_curve(contract_symbol, quanto_multiplier),
             )
             .expect("values from db to be sane")
         };
@@ -318,6 +395,8 @@ impl ClosedCfdInputAggregate {
                 self.latest_dlc = dlc;
             }
             RolloverFailed => {}
+            RolloverFundingRateRejected { .. } => {}
+            SettlementProposedByMaker { .. } => {}
             CollaborativeSettlementStarted { .. } => {}
             CollaborativeSettlementProposalAccepted => {}
             CollaborativeSettlementCompleted {
@@ -363,7 +442,27 @@ impl ClosedCfdInputAggregate {
             OracleAttestedPostCetTimelock { cet, price } => {
                 self.cet = Some((cet, price));
             }
+            CetPublishedByCounterparty {
+                cet, range, price, ..
+            } => {
+                // Keep the attested price if we learned about it from the oracle already.
+                // Otherwise the exact price is unknown and a bound of the price interval of the CET
+                // is recorded instead, all prices of the interval result in the same payout.
+                let price = match (price, self.cet.take()) {
+                    (Some(price), _) => price,
+                    (None, Some((known_cet, price))) if known_cet.txid() == cet.txid() => price,
+                    (None, _) => {
+                        let bound = match *range.start() {
+                            0 => *range.end(),
+                            start => start,
+                        };
+                        Price::new(Decimal::from(bound)).context("Invalid price interval of CET")?
+                    }
+                };
+                self.cet = Some((cet, price));
+            }
             ManualCommit { .. } => {}
+            PriceLevelsSet { .. } => {}
         }
 
         Ok(self)
@@ -1000,6 +1099,7 @@ mod tests {
     use model::ContractSymbol;
     use model::Contracts;
     use model::EventKind;
+    use model::FundingPeriod;
     use model::FundingRate;
     use model::OfferId;
     use model::OpeningFee;
@@ -1072,6 +1172,53 @@ mod tests {
         assert!(load_from_closed.is_err());
     }
 
+    #[tokio::test]
+    async fn given_confirmed_settlement_then_cfd_id_closed_since_before_and_after_archival() {
+        let db = memory().await.unwrap();
+
+        let (cfd, contract_setup_completed, collaborative_settlement_completed) =
+            cfd_collaboratively_settled();
+        let order_id = cfd.id();
+
+        db.insert_cfd(&cfd).await.unwrap();
+        db.append_event(contract_setup_completed).await.unwrap();
+        db.append_event(collaborative_settlement_completed)
+            .await
+            .unwrap();
+
+        let before_confirmation = Timestamp::now();
+        let closed = db
+            .load_cfd_ids_closed_since(before_confirmation)
+            .await
+            .unwrap();
+        assert!(closed.is_empty());
+
+        db.append_event(collab_settlement_confirmed(&cfd))
+            .await
+            .unwrap();
+
+        let closed = db
+            .load_cfd_ids_closed_since(before_confirmation)
+            .await
+            .unwrap();
+        assert_eq!(closed, vec![order_id]);
+
+        db.move_to_closed_cfds().await.unwrap();
+
+        let closed = db
+            .load_cfd_ids_closed_since(before_confirmation)
+            .await
+            .unwrap();
+        assert_eq!(closed, vec![order_id]);
+
+        let after_confirmation = Timestamp::new(before_confirmation.seconds() + 60);
+        let closed = db
+            .load_cfd_ids_closed_since(after_confirmation)
+            .await
+            .unwrap();
+        assert!(closed.is_empty());
+    }
+
     #[tokio::test]
     async fn insert_cet_roundtrip() {
         let db = memory().await.unwrap();
@@ -1310,6 +1457,8 @@ mod tests {
             FundingRate::default(),
             TxFeeRate::default(),
             ContractSymbol::BtcUsd,
+            FundingPeriod::Hourly,
+            None,
         );
 
         let contract_setup_completed =
@@ -0,0 +1,249 @@
+//! Forwarding of funds to an external cold wallet.
+//!
+//! The policy of the taker defines the address of the cold wallet and the balance that stays in
+//! the wallet, the maker is configured on the command line. Every transaction that forwarded funds
+//! is recorded in the history.
+
+use crate::models;
+use crate::Connection;
+use anyhow::Context;
+use anyhow::Result;
+use bdk::bitcoin::Address;
+use bdk::bitcoin::Amount;
+use bdk::bitcoin::Txid;
+use model::Timestamp;
+use serde::Serialize;
+use tracing::field::Empty;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ColdSweepPolicy {
+    pub enabled: bool,
+    pub address: Address,
+    /// Balance that stays in the wallet, only funds above it are forwarded
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub threshold: Amount,
+    /// Highest fee rate in satoshis per vbyte at which funds are forwarded
+    pub max_fee_rate: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ColdSweep {
+    pub txid: Txid,
+    pub address: Address,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub amount: Amount,
+    /// Fee rate in satoshis per vbyte
+    pub fee_rate: f32,
+    pub created_at: Timestamp,
+}
+
+impl Connection {
+    /// Store the cold sweep policy, replacing the previous one.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "upsert_cold_sweep_policy", duration_ms = Empty)
+    )]
+    pub async fn upsert_cold_sweep_policy(&self, policy: &ColdSweepPolicy) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let address = policy.address.to_string();
+        let threshold = policy.threshold.as_sat() as i64;
+        let updated_at = models::Timestamp::from(Timestamp::now());
+
+        sqlx::query!(
+            r#"
+            INSERT OR REPLACE INTO cold_sweep_policy
+            (
+                id,
+                enabled,
+                address,
+                threshold,
+                max_fee_rate,
+                updated_at
+            )
+            VALUES (1, $1, $2, $3, $4, $5)
+            "#,
+            policy.enabled,
+            address,
+            threshold,
+            policy.max_fee_rate,
+            updated_at,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Load the cold sweep policy, `None` if none was configured.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_cold_sweep_policy", duration_ms = Empty)
+    )]
+    pub async fn load_cold_sweep_policy(&self) -> Result<Option<ColdSweepPolicy>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                enabled,
+                address,
+                threshold,
+                max_fee_rate
+            FROM
+                cold_sweep_policy
+            WHERE
+                id = 1
+            "#
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        row.map(|row| {
+            Ok(ColdSweepPolicy {
+                enabled: row.enabled,
+                address: row
+                    .address
+                    .parse()
+                    .context("Invalid address of cold sweep policy")?,
+                threshold: Amount::from_sat(row.threshold as u64),
+                max_fee_rate: row.max_fee_rate as f32,
+            })
+        })
+        .transpose()
+    }
+
+    /// Record a transaction that forwarded funds to the cold wallet.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "insert_cold_sweep", txid = %sweep.txid, duration_ms = Empty)
+    )]
+    pub async fn insert_cold_sweep(&self, sweep: &ColdSweep) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let txid = models::Txid::from(sweep.txid);
+        let address = sweep.address.to_string();
+        let amount = sweep.amount.as_sat() as i64;
+        let created_at = models::Timestamp::from(sweep.created_at);
+
+        sqlx::query!(
+            r#"
+            INSERT INTO cold_sweeps
+            (
+                txid,
+                address,
+                amount,
+                fee_rate,
+                created_at
+            )
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            txid,
+            address,
+            amount,
+            sweep.fee_rate,
+            created_at,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Load all transactions that forwarded funds to the cold wallet, newest first.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_cold_sweeps", duration_ms = Empty)
+    )]
+    pub async fn load_cold_sweeps(&self) -> Result<Vec<ColdSweep>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                txid as "txid: models::Txid",
+                address,
+                amount,
+                fee_rate,
+                created_at as "created_at: models::Timestamp"
+            FROM
+                cold_sweeps
+            ORDER BY
+                created_at DESC
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(ColdSweep {
+                    txid: row.txid.into(),
+                    address: row
+                        .address
+                        .parse()
+                        .context("Invalid address of cold sweep")?,
+                    amount: Amount::from_sat(row.amount as u64),
+                    fee_rate: row.fee_rate as f32,
+                    created_at: row.created_at.into(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn given_new_policy_then_it_replaces_the_previous_one() {
+        let db = memory().await.unwrap();
+        let address = "bcrt1qxw9v2aes9dx3dlan3p8ggts0jl2fpvcwrnyn0z"
+            .parse::<Address>()
+            .unwrap();
+
+        assert_eq!(db.load_cold_sweep_policy().await.unwrap(), None);
+
+        let mut policy = ColdSweepPolicy {
+            enabled: true,
+            address: address.clone(),
+            threshold: Amount::from_sat(1_000_000),
+            max_fee_rate: 10.0,
+        };
+        db.upsert_cold_sweep_policy(&policy).await.unwrap();
+
+        policy.enabled = false;
+        db.upsert_cold_sweep_policy(&policy).await.unwrap();
+
+        assert_eq!(db.load_cold_sweep_policy().await.unwrap(), Some(policy));
+
+        let sweep = ColdSweep {
+            txid: Txid::from_str(
+                "6b1f9a5b1e1a4b07e2d8b1b4a8e6bb2d4f8f6bc7e7bfdb5cb1fd1cb7f1f2f3f4",
+            )
+            .unwrap(),
+            address,
+            amount: Amount::from_sat(500_000),
+            fee_rate: 2.0,
+            created_at: Timestamp::new(1),
+        };
+        db.insert_cold_sweep(&sweep).await.unwrap();
+
+        assert_eq!(db.load_cold_sweeps().await.unwrap(), vec![sweep]);
+    }
+}
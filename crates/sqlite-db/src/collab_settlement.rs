@@ -0,0 +1,178 @@
+//! Collaborative settlements interrupted after the taker sent its signature.
+//!
+//! Once the taker sent its signature the maker is able to publish the settlement transaction, so
+//! the taker cannot simply consider the settlement failed. The unsigned settlement transaction is
+//! kept until the taker learned whether the maker completed the settlement or aborted it.
+
+use crate::models;
+use crate::Connection;
+use anyhow::Result;
+use bdk::bitcoin::Transaction;
+use model::OrderId;
+use model::Timestamp;
+use tracing::field::Empty;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingCollabSettlement {
+    pub order_id: OrderId,
+    pub unsigned_tx: Transaction,
+    pub timestamp: Timestamp,
+}
+
+impl Connection {
+    /// Store a pending collaborative settlement, replacing an earlier one of the same CFD.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(
+            query = "insert_pending_collab_settlement",
+            order_id = %settlement.order_id,
+            duration_ms = Empty
+        )
+    )]
+    pub async fn insert_pending_collab_settlement(
+        &self,
+        settlement: &PendingCollabSettlement,
+    ) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let order_id = models::OrderId::from(settlement.order_id);
+        let unsigned_tx = models::Transaction::from(settlement.unsigned_tx.clone());
+        let timestamp = models::Timestamp::from(settlement.timestamp);
+
+        sqlx::query!(
+            r#"
+            INSERT OR REPLACE INTO pending_collab_settlements
+            (
+                order_id,
+                unsigned_tx,
+                timestamp
+            )
+            VALUES ($1, $2, $3)
+            "#,
+            order_id,
+            unsigned_tx,
+            timestamp,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Load all pending collaborative settlements, oldest first.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_pending_collab_settlements", duration_ms = Empty)
+    )]
+    pub async fn load_pending_collab_settlements(&self) -> Result<Vec<PendingCollabSettlement>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                order_id as "order_id: models::OrderId",
+                unsigned_tx as "unsigned_tx: models::Transaction",
+                timestamp as "timestamp: models::Timestamp"
+            FROM
+                pending_collab_settlements
+            ORDER BY
+                timestamp ASC
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let settlements = rows
+            .into_iter()
+            .map(|row| PendingCollabSettlement {
+                order_id: row.order_id.into(),
+                unsigned_tx: row.unsigned_tx.into(),
+                timestamp: row.timestamp.into(),
+            })
+            .collect();
+
+        Ok(settlements)
+    }
+
+    /// Remove the pending collaborative settlement of a CFD once it was completed or aborted.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "delete_pending_collab_settlement", %order_id, duration_ms = Empty)
+    )]
+    pub async fn delete_pending_collab_settlement(&self, order_id: OrderId) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let order_id = models::OrderId::from(order_id);
+
+        sqlx::query!(
+            r#"
+            DELETE FROM pending_collab_settlements
+            WHERE
+                order_id = $1
+            "#,
+            order_id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+    use crate::tests::dummy_cfd;
+    use bdk::bitcoin::TxIn;
+
+    #[tokio::test]
+    async fn given_pending_settlement_replaced_and_deleted_then_none_left() {
+        let db = memory().await.unwrap();
+
+        let cfd = dummy_cfd();
+        db.insert_cfd(&cfd).await.unwrap();
+
+        let order_id = cfd.id();
+        let first = dummy_settlement(order_id, 0);
+        let second = dummy_settlement(order_id, 1);
+
+        db.insert_pending_collab_settlement(&first).await.unwrap();
+        db.insert_pending_collab_settlement(&second).await.unwrap();
+
+        assert_eq!(
+            db.load_pending_collab_settlements().await.unwrap(),
+            vec![second]
+        );
+
+        db.delete_pending_collab_settlement(order_id).await.unwrap();
+
+        assert!(db
+            .load_pending_collab_settlements()
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    fn dummy_settlement(order_id: OrderId, lock_time: u32) -> PendingCollabSettlement {
+        PendingCollabSettlement {
+            order_id,
+            unsigned_tx: Transaction {
+                version: 2,
+                lock_time,
+                input: vec![TxIn::default()],
+                output: vec![],
+            },
+            timestamp: Timestamp::new(1_000 + lock_time as i64),
+        }
+    }
+}
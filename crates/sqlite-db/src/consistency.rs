@@ -0,0 +1,222 @@
+//! Verification of the cached aggregates against the event log.
+//!
+//! Aggregates are cached after loading so that only new events have to be applied upon the next
+//! load. A cache entry that diverged from the event log, e.g. because it was built from events
+//! that were loaded out of order, would go unnoticed until the CFD is closed. Rebuilding the open
+//! CFDs from their events and comparing the result against the cache surfaces such problems.
+
+use crate::load_cfd_events;
+use crate::load_cfd_row;
+use crate::Connection;
+use crate::Error;
+use crate::VerifiableAggregate;
+use anyhow::Context;
+use anyhow::Result;
+use model::OrderId;
+use std::any::TypeId;
+use std::collections::HashSet;
+use tracing::field::Empty;
+
+/// Outcome of verifying the cached aggregates of all open CFDs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    /// Number of open CFDs whose cache entry matches the event log
+    pub verified: usize,
+    /// Number of open CFDs without cache entry, there is nothing to verify for them
+    pub uncached: usize,
+    /// Open CFDs whose cache entry does not match the event log
+    ///
+    /// Their cache entries were invalidated.
+    pub mismatched: Vec<OrderId>,
+}
+
+impl Report {
+    pub fn is_consistent(&self) -> bool {
+        self.mismatched.is_empty()
+    }
+}
+
+impl Connection {
+    /// Verify the cached aggregates of all open CFDs against a rebuild from their events.
+    ///
+    /// The rebuild applies as many events as the cached aggregate, so that a cache entry which
+    /// merely lags behind the event log is not reported. Cache entries that do not match are
+    /// invalidated so that the next load rebuilds the aggregate from all events.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "verify_cached_aggregates", duration_ms = Empty)
+    )]
+    pub async fn verify_cached_aggregates<C>(&self, args: C::CtorArgs) -> Result<Report>
+    where
+        C: VerifiableAggregate,
+        C::CtorArgs: Clone,
+    {
+        let _timer = self.query_timer();
+
+        let aggregate = std::any::type_name::<C>();
+        let mut report = Report::default();
+
+        for id in self.load_open_cfd_ids().await? {
+            let cache_key = (TypeId::of::<C>(), id);
+
+            let (cached_version, cached_hash) = match self.aggregate_cache.get(&cache_key) {
+                Some(entry) => {
+                    let cfd = entry
+                        .downcast_ref::<C>()
+                        .expect("we index by type id, must be able to downcast");
+
+                    (cfd.version(), cfd.state_hash())
+                }
+                None => {
+                    report.uncached += 1;
+                    continue;
+                }
+            };
+
+            let mut conn = self.inner.acquire().await?;
+
+            let cfd = match load_cfd_row(&mut *conn, id).await {
+                Ok(cfd) => cfd,
+                Err(Error::OpenCfdNotFound) => continue,
+                Err(e) => return Err(e).with_context(|| format!("Could not load CFD {id}")),
+            };
+            let events = load_cfd_events(&mut *conn, id, 0)
+                .await
+                .with_context(|| format!("Could not load events for CFD {id}"))?;
+
+            let rebuilt = events
+                .into_iter()
+                .take(cached_version as usize)
+                .fold(C::new(args.clone(), cfd), C::apply);
+
+            if rebuilt.version() == cached_version && rebuilt.state_hash() == cached_hash {
+                report.verified += 1;
+                continue;
+            }
+
+            tracing::warn!(
+                order_id = %id,
+                %aggregate,
+                %cached_version,
+                rebuilt_version = %rebuilt.version(),
+                "Cached CFD does not match event log, invalidating cache entry"
+            );
+
+            self.aggregate_cache.remove(&cache_key);
+            report.mismatched.push(id);
+        }
+
+        Ok(report)
+    }
+
+    /// Remove the cached aggregates of CFDs that are not open anymore.
+    ///
+    /// Closed and failed CFDs are never loaded through the cache again, so their entries would be
+    /// kept for the lifetime of the process. Returns the number of such entries, which are only
+    /// removed unless `dry_run`.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "prune_aggregate_cache", duration_ms = Empty)
+    )]
+    pub async fn prune_aggregate_cache(&self, dry_run: bool) -> Result<usize> {
+        let _timer = self.query_timer();
+
+        let open = self
+            .load_open_cfd_ids()
+            .await?
+            .into_iter()
+            .collect::<HashSet<_>>();
+
+        let stale = self
+            .aggregate_cache
+            .iter()
+            .map(|entry| *entry.key())
+            .filter(|(_, id)| !open.contains(id))
+            .collect::<Vec<_>>();
+
+        if !dry_run {
+            for key in stale.iter() {
+                self.aggregate_cache.remove(key);
+            }
+        }
+
+        Ok(stale.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+    use crate::tests::dummy_cfd;
+    use crate::tests::lock_confirmed;
+    use model::CfdEvent;
+    use model::EventKind;
+    use model::Timestamp;
+
+    #[tokio::test]
+    async fn given_diverged_cache_entry_then_mismatch_reported_and_entry_invalidated() {
+        let db = memory().await.unwrap();
+
+        let cfd = dummy_cfd();
+        db.insert_cfd(&cfd).await.unwrap();
+        db.append_event(lock_confirmed(&cfd)).await.unwrap();
+
+        db.load_open_cfd::<model::Cfd>(cfd.id(), ()).await.unwrap();
+
+        let report = db.verify_cached_aggregates::<model::Cfd>(()).await.unwrap();
+        assert_eq!(
+            report,
+            Report {
+                verified: 1,
+                uncached: 0,
+                mismatched: vec![]
+            }
+        );
+
+        let diverged = cfd.clone().apply(CfdEvent {
+            timestamp: Timestamp::now(),
+            id: cfd.id(),
+            event: EventKind::CommitConfirmed,
+        });
+        db.aggregate_cache
+            .insert((TypeId::of::<model::Cfd>(), cfd.id()), Box::new(diverged));
+
+        let report = db.verify_cached_aggregates::<model::Cfd>(()).await.unwrap();
+        assert_eq!(report.mismatched, vec![cfd.id()]);
+
+        let report = db.verify_cached_aggregates::<model::Cfd>(()).await.unwrap();
+        assert_eq!(
+            report,
+            Report {
+                verified: 0,
+                uncached: 1,
+                mismatched: vec![]
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn only_entries_of_cfds_that_are_not_open_are_pruned() {
+        let db = memory().await.unwrap();
+
+        let open = dummy_cfd();
+        db.insert_cfd(&open).await.unwrap();
+        db.load_open_cfd::<model::Cfd>(open.id(), ()).await.unwrap();
+
+        let closed = dummy_cfd();
+        db.aggregate_cache
+            .insert((TypeId::of::<model::Cfd>(), closed.id()), Box::new(closed));
+
+        assert_eq!(db.prune_aggregate_cache(true).await.unwrap(), 1);
+        assert_eq!(db.aggregate_cache.len(), 2);
+
+        assert_eq!(db.prune_aggregate_cache(false).await.unwrap(), 1);
+        assert_eq!(db.aggregate_cache.len(), 1);
+        assert!(db
+            .aggregate_cache
+            .contains_key(&(TypeId::of::<model::Cfd>(), open.id())));
+    }
+}
@@ -0,0 +1,162 @@
+//! End-of-day marks of open CFDs.
+//!
+//! Once a day every open CFD is marked to the market. The marks are kept after the CFD was
+//! closed, which allows to reconstruct the value of the portfolio on any past day.
+
+use crate::models;
+use crate::Connection;
+use anyhow::Context;
+use anyhow::Result;
+use bdk::bitcoin::Amount;
+use bdk::bitcoin::SignedAmount;
+use model::ContractSymbol;
+use model::Contracts;
+use model::OrderId;
+use model::Position;
+use model::Price;
+use model::Role;
+use model::Timestamp;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde::Serialize;
+use time::Date;
+use tracing::field::Empty;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailyMark {
+    pub order_id: OrderId,
+    pub contract_symbol: ContractSymbol,
+    pub position: Position,
+    pub role: Role,
+    pub quantity: Contracts,
+    /// The price the CFD would be closed at
+    pub mark_price: Price,
+    #[serde(with = "::bdk::bitcoin::util::amount::serde::as_sat")]
+    pub unrealized_pnl: SignedAmount,
+    #[serde(with = "::bdk::bitcoin::util::amount::serde::as_sat")]
+    pub accumulated_fees: SignedAmount,
+    #[serde(with = "::bdk::bitcoin::util::amount::serde::as_sat")]
+    pub margin: Amount,
+    /// The share of the margin left at the mark price, `1` at the initial price and `0` at the
+    /// liquidation price
+    pub margin_health: Decimal,
+    pub marked_at: Timestamp,
+}
+
+impl Connection {
+    /// Record the mark of a CFD for `date`.
+    ///
+    /// Returns `false` if the CFD was already marked for `date`, in which case nothing is recorded.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "insert_daily_mark", order_id = %mark.order_id, date = %date, duration_ms = Empty)
+    )]
+    pub async fn insert_daily_mark(&self, date: Date, mark: &DailyMark) -> Result<bool> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let order_id = models::OrderId::from(mark.order_id);
+        let date = date.to_string();
+        let json = serde_json::to_string(mark).context("Failed to encode mark")?;
+        let marked_at = models::Timestamp::from(mark.marked_at);
+
+        let query_result = sqlx::query!(
+            r#"
+            INSERT OR IGNORE INTO daily_marks
+            (
+                order_id,
+                date,
+                mark,
+                marked_at
+            )
+            VALUES ($1, $2, $3, $4)
+            "#,
+            order_id,
+            date,
+            json,
+            marked_at,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(query_result.rows_affected() == 1)
+    }
+
+    /// Load the marks of all CFDs for `date`, in the order they were recorded.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_daily_marks", date = %date, duration_ms = Empty)
+    )]
+    pub async fn load_daily_marks(&self, date: Date) -> Result<Vec<DailyMark>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let date = date.to_string();
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                mark
+            FROM
+                daily_marks
+            WHERE
+                date = $1
+            ORDER BY
+                id
+            "#,
+            date,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| serde_json::from_str(&row.mark).context("Failed to decode mark"))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+    use rust_decimal_macros::dec;
+    use time::Month;
+
+    #[tokio::test]
+    async fn cfd_is_marked_once_per_date() {
+        let db = memory().await.unwrap();
+
+        let mark = dummy_mark();
+
+        assert!(db.insert_daily_mark(day(7), &mark).await.unwrap());
+        assert!(!db.insert_daily_mark(day(7), &mark).await.unwrap());
+        assert!(db.insert_daily_mark(day(8), &mark).await.unwrap());
+
+        assert_eq!(db.load_daily_marks(day(7)).await.unwrap(), vec![mark]);
+        assert!(db.load_daily_marks(day(9)).await.unwrap().is_empty());
+    }
+
+    fn day(day: u8) -> Date {
+        Date::from_calendar_date(2022, Month::November, day).unwrap()
+    }
+
+    fn dummy_mark() -> DailyMark {
+        DailyMark {
+            order_id: OrderId::default(),
+            contract_symbol: ContractSymbol::BtcUsd,
+            position: Position::Long,
+            role: Role::Taker,
+            quantity: Contracts::new(100),
+            mark_price: Price::new(dec!(20_000)).unwrap(),
+            unrealized_pnl: SignedAmount::from_sat(-1_000),
+            accumulated_fees: SignedAmount::from_sat(500),
+            margin: Amount::from_sat(250_000),
+            margin_health: dec!(0.996),
+            marked_at: Timestamp::new(1_667_779_200),
+        }
+    }
+}
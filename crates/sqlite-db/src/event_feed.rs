@@ -0,0 +1,217 @@
+//! Append-only feed of all CFD events for consumers outside of the daemon.
+//!
+//! Every event appended to the `events` table is also appended to the feed, in the same database
+//! transaction. Feed entries are numbered by a sequence without gaps and are never deleted, even
+//! after the CFD was closed. Consumers page through the feed by remembering the sequence number of
+//! the last entry they processed.
+
+use crate::models;
+use anyhow::Context;
+use anyhow::Result;
+use model::OrderId;
+use model::Timestamp;
+use serde::Serialize;
+use sqlx::SqliteConnection;
+use tracing::field::Empty;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeedEvent {
+    /// Position of the event within the feed, starting at 1
+    pub seq: u64,
+    pub order_id: OrderId,
+    pub name: String,
+    pub data: serde_json::Value,
+    pub created_at: Timestamp,
+}
+
+impl crate::Connection {
+    /// Load up to `limit` events of the feed following the event with sequence number `after_seq`.
+    ///
+    /// Events are sorted by their sequence number, pass `0` to start at the beginning of the feed.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_event_feed", after_seq, limit, duration_ms = Empty)
+    )]
+    pub async fn load_event_feed(&self, after_seq: u64, limit: u32) -> Result<Vec<FeedEvent>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let after_seq = after_seq as i64;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                seq,
+                order_id as "order_id: models::OrderId",
+                name,
+                data,
+                created_at as "created_at: models::Timestamp"
+            FROM
+                event_feed
+            WHERE
+                seq > $1
+            ORDER BY
+                seq
+            LIMIT $2
+            "#,
+            after_seq,
+            limit,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(FeedEvent {
+                    seq: row.seq as u64,
+                    order_id: row.order_id.into(),
+                    data: serde_json::from_str(&row.data)
+                        .with_context(|| format!("Invalid data of event {}", row.seq))?,
+                    name: row.name,
+                    created_at: row.created_at.into(),
+                })
+            })
+            .collect()
+    }
+
+    /// Load all events of a CFD from the feed, sorted by their sequence number.
+    ///
+    /// Unlike the `events` table, the feed still contains the events of closed CFDs.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_event_feed_of_cfd", %order_id, duration_ms = Empty)
+    )]
+    pub async fn load_event_feed_of_cfd(&self, order_id: OrderId) -> Result<Vec<FeedEvent>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let order_id = models::OrderId::from(order_id);
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                seq,
+                order_id as "order_id: models::OrderId",
+                name,
+                data,
+                created_at as "created_at: models::Timestamp"
+            FROM
+                event_feed
+            WHERE
+                order_id = $1
+            ORDER BY
+                seq
+            "#,
+            order_id,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(FeedEvent {
+                    seq: row.seq as u64,
+                    order_id: row.order_id.into(),
+                    data: serde_json::from_str(&row.data)
+                        .with_context(|| format!("Invalid data of event {}", row.seq))?,
+                    name: row.name,
+                    created_at: row.created_at.into(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Append an event to the feed, assigning the next sequence number.
+///
+/// Has to be called within the transaction that inserts the event into the `events` table, so that
+/// the sequence number is only taken if the event is persisted.
+pub(crate) async fn append(
+    conn: &mut SqliteConnection,
+    order_id: &models::OrderId,
+    name: &str,
+    data: &str,
+    created_at: &models::Timestamp,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO event_feed
+        (
+            seq,
+            order_id,
+            name,
+            data,
+            created_at
+        )
+        VALUES ((SELECT COALESCE(MAX(seq), 0) + 1 FROM event_feed), $1, $2, $3, $4)
+        "#,
+        order_id,
+        name,
+        data,
+        created_at,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::memory;
+    use crate::tests::dummy_cfd;
+    use crate::tests::lock_confirmed;
+    use crate::tests::setup_failed;
+
+    #[tokio::test]
+    async fn events_are_paged_by_sequence_without_gaps() {
+        let db = memory().await.unwrap();
+
+        let cfd = dummy_cfd();
+        db.insert_cfd(&cfd).await.unwrap();
+        db.append_event(lock_confirmed(&cfd)).await.unwrap();
+        db.append_event(setup_failed(&cfd)).await.unwrap();
+
+        let first_page = db.load_event_feed(0, 1).await.unwrap();
+        let second_page = db.load_event_feed(1, 10).await.unwrap();
+
+        assert_eq!(first_page.len(), 1);
+        assert_eq!(first_page[0].seq, 1);
+        assert_eq!(first_page[0].order_id, cfd.id());
+        assert_eq!(
+            second_page
+                .iter()
+                .map(|event| event.seq)
+                .collect::<Vec<_>>(),
+            vec![2]
+        );
+        assert!(db.load_event_feed(2, 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn events_of_cfd_are_loaded_in_order() {
+        let db = memory().await.unwrap();
+
+        let cfd = dummy_cfd();
+        let other_cfd = dummy_cfd();
+        db.insert_cfd(&cfd).await.unwrap();
+        db.insert_cfd(&other_cfd).await.unwrap();
+        db.append_event(lock_confirmed(&cfd)).await.unwrap();
+        db.append_event(setup_failed(&other_cfd)).await.unwrap();
+        db.append_event(setup_failed(&cfd)).await.unwrap();
+
+        let events = db.load_event_feed_of_cfd(cfd.id()).await.unwrap();
+
+        assert_eq!(
+            events
+                .iter()
+                .map(|event| (event.seq, event.order_id))
+                .collect::<Vec<_>>(),
+            vec![(1, cfd.id()), (3, cfd.id())]
+        );
+    }
+}
@@ -0,0 +1,265 @@
+//! Deposits the maker expects from clients.
+//!
+//! Expected deposits are registered with a label and the address the client pays to. Once a
+//! transaction paying to the address confirmed, it is recorded with the expected deposit and the
+//! label shows up in the ledger entry of the transaction.
+
+use crate::models;
+use crate::Connection;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use bdk::bitcoin::Address;
+use bdk::bitcoin::Amount;
+use bdk::bitcoin::Txid;
+use model::Timestamp;
+use serde::Serialize;
+use tracing::field::Empty;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExpectedDeposit {
+    pub label: String,
+    pub address: Address,
+    /// `None` if any amount is expected
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat::opt")]
+    pub amount: Option<Amount>,
+    pub created_at: Timestamp,
+    pub received: Option<ReceivedDeposit>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ReceivedDeposit {
+    pub txid: Txid,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub amount: Amount,
+    /// Confirmation time of the transaction
+    pub received_at: Timestamp,
+}
+
+impl ExpectedDeposit {
+    /// Whether the received amount differs from the expected one.
+    pub fn is_amount_mismatch(&self) -> bool {
+        matches!(
+            (self.amount, self.received),
+            (Some(expected), Some(received)) if expected != received.amount
+        )
+    }
+}
+
+impl Connection {
+    /// Register a deposit that is expected to arrive at `address`.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "insert_expected_deposit", %label, duration_ms = Empty)
+    )]
+    pub async fn insert_expected_deposit(
+        &self,
+        label: &str,
+        address: &Address,
+        amount: Option<Amount>,
+    ) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let address = address.to_string();
+        let amount = amount.map(|amount| amount.as_sat() as i64);
+        let created_at = models::Timestamp::from(Timestamp::now());
+
+        sqlx::query!(
+            r#"
+            INSERT INTO expected_deposits
+            (
+                label,
+                address,
+                amount,
+                created_at
+            )
+            VALUES ($1, $2, $3, $4)
+            "#,
+            label,
+            address,
+            amount,
+            created_at,
+        )
+        .execute(&mut *conn)
+        .await
+        .context("Label or address already registered")?;
+
+        Ok(())
+    }
+
+    /// Remove an expected deposit that did not arrive yet.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "delete_expected_deposit", %label, duration_ms = Empty)
+    )]
+    pub async fn delete_expected_deposit(&self, label: &str) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM expected_deposits
+            WHERE
+                label = $1 AND txid IS NULL
+            "#,
+            label,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            bail!("No pending expected deposit with label {label}");
+        }
+
+        Ok(())
+    }
+
+    /// Record the transaction with which an expected deposit arrived.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "record_received_deposit", %label, %txid, duration_ms = Empty)
+    )]
+    pub async fn record_received_deposit(
+        &self,
+        label: &str,
+        txid: Txid,
+        amount: Amount,
+        received_at: Timestamp,
+    ) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let txid = models::Txid::from(txid);
+        let amount = amount.as_sat() as i64;
+        let received_at = models::Timestamp::from(received_at);
+
+        sqlx::query!(
+            r#"
+            UPDATE expected_deposits
+            SET
+                txid = $2,
+                received_amount = $3,
+                received_at = $4
+            WHERE
+                label = $1 AND txid IS NULL
+            "#,
+            label,
+            txid,
+            amount,
+            received_at,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Load all expected deposits, oldest first.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_expected_deposits", duration_ms = Empty)
+    )]
+    pub async fn load_expected_deposits(&self) -> Result<Vec<ExpectedDeposit>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                label as "label!",
+                address,
+                amount,
+                created_at as "created_at: models::Timestamp",
+                txid as "txid: models::Txid",
+                received_amount,
+                received_at as "received_at: models::Timestamp"
+            FROM
+                expected_deposits
+            ORDER BY
+                created_at ASC, label ASC
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let received = match (row.txid, row.received_amount, row.received_at) {
+                    (Some(txid), Some(amount), Some(received_at)) => Some(ReceivedDeposit {
+                        txid: txid.into(),
+                        amount: Amount::from_sat(amount as u64),
+                        received_at: received_at.into(),
+                    }),
+                    _ => None,
+                };
+
+                Ok(ExpectedDeposit {
+                    label: row.label,
+                    address: row
+                        .address
+                        .parse()
+                        .context("Invalid address of expected deposit")?,
+                    amount: row.amount.map(|amount| Amount::from_sat(amount as u64)),
+                    created_at: row.created_at.into(),
+                    received,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn received_deposit_cannot_be_deleted() {
+        let db = memory().await.unwrap();
+        let address = "bcrt1qxw9v2aes9dx3dlan3p8ggts0jl2fpvcwrnyn0z"
+            .parse::<Address>()
+            .unwrap();
+        let txid =
+            Txid::from_str("6b1f9a5b1e1a4b07e2d8b1b4a8e6bb2d4f8f6bc7e7bfdb5cb1fd1cb7f1f2f3f4")
+                .unwrap();
+
+        db.insert_expected_deposit("client-a", &address, Some(Amount::from_sat(100_000)))
+            .await
+            .unwrap();
+        assert!(db
+            .insert_expected_deposit("client-b", &address, None)
+            .await
+            .is_err());
+
+        db.record_received_deposit(
+            "client-a",
+            txid,
+            Amount::from_sat(90_000),
+            Timestamp::new(1),
+        )
+        .await
+        .unwrap();
+
+        let deposits = db.load_expected_deposits().await.unwrap();
+        assert_eq!(
+            deposits[0].received,
+            Some(ReceivedDeposit {
+                txid,
+                amount: Amount::from_sat(90_000),
+                received_at: Timestamp::new(1),
+            })
+        );
+        assert!(deposits[0].is_amount_mismatch());
+        assert!(db.delete_expected_deposit("client-a").await.is_err());
+    }
+}
@@ -12,7 +12,6 @@
 //! of CFD.
 
 use crate::delete_from_cfds_table;
-use crate::delete_from_events_table;
 use crate::derive_known_peer_id;
 use crate::event_log::EventLog;
 use crate::event_log::EventLogEntry;
@@ -26,6 +25,7 @@ use anyhow::bail;
 use anyhow::Result;
 use model::libp2p::PeerId;
 use model::long_and_short_leverage;
+use model::symbols;
 use model::EventKind;
 use model::FailedCfd;
 use model::FeeAccount;
@@ -35,6 +35,7 @@ use model::Timestamp;
 use models::FailedKind;
 use sqlx::Acquire;
 use sqlx::SqliteConnection;
+use tracing::field::Empty;
 
 /// A trait for building an aggregate based on a `FailedCfd`.
 pub trait FailedCfdAggregate: CfdAggregate {
@@ -42,7 +43,14 @@ pub trait FailedCfdAggregate: CfdAggregate {
 }
 
 impl Connection {
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "move_to_failed_cfds", duration_ms = Empty)
+    )]
     pub async fn move_to_failed_cfds(&self) -> Result<()> {
+        let _timer = self.query_timer();
+
         let ids = self.failed_cfd_ids_according_to_events().await?;
 
         if !ids.is_empty() {
@@ -64,7 +72,6 @@ impl Connection {
                 insert_failed_cfd(&mut db_tx, cfd, &event_log).await?;
                 insert_event_log(&mut db_tx, id, event_log).await?;
 
-                delete_from_events_table(&mut db_tx, id).await?;
                 delete_from_cfds_table(&mut db_tx, id).await?;
 
                 db_tx.commit().await?;
@@ -82,10 +89,17 @@ impl Connection {
     }
 
     /// Load a failed CFD from the database.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_failed_cfd", order_id = %id, duration_ms = Empty)
+    )]
     pub async fn load_failed_cfd<C>(&self, id: OrderId, args: C::CtorArgs) -> Result<C>
     where
         C: FailedCfdAggregate,
     {
+        let _timer = self.query_timer();
+
         let mut conn = self.inner.acquire().await?;
 
         let inner_id = models::OrderId::from(id);
@@ -135,7 +149,14 @@ impl Connection {
         Ok(C::new_failed(args, cfd))
     }
 
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_failed_cfd_ids", duration_ms = Empty)
+    )]
     pub(crate) async fn load_failed_cfd_ids(&self) -> Result<Vec<OrderId>> {
+        let _timer = self.query_timer();
+
         let mut conn = self.inner.acquire().await?;
 
         let ids = sqlx::query!(
@@ -182,7 +203,7 @@ async fn insert_failed_cfd(
             short_leverage,
             cfd.initial_funding_rate,
             cfd.settlement_interval.whole_hours(),
-            cfd.contract_symbol,
+            symbols::payout_curve(cfd.contract_symbol, cfd.quanto_multiplier),
         )
         .expect("values from db to be sane");
 
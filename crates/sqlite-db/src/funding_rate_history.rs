@@ -0,0 +1,214 @@
+//! History of the funding rates offered by the maker.
+//!
+//! A new entry is only recorded if the funding rate for a contract symbol and maker position
+//! changed, hence the funding rate of an entry applies until the timestamp of the next entry.
+
+use crate::models;
+use crate::Connection;
+use anyhow::Result;
+use model::ContractSymbol;
+use model::FundingRate;
+use model::Position;
+use model::Timestamp;
+use sqlx::Acquire;
+use tracing::field::Empty;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FundingRateEntry {
+    pub contract_symbol: ContractSymbol,
+    pub position_maker: Position,
+    pub funding_rate: FundingRate,
+    pub timestamp: Timestamp,
+}
+
+impl Connection {
+    /// Record the funding rate offered for a contract symbol and maker position.
+    ///
+    /// Returns `false` if the funding rate did not change since the latest entry, in which case
+    /// nothing is recorded.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(
+            query = "record_funding_rate",
+            contract_symbol = %entry.contract_symbol,
+            position_maker = ?entry.position_maker,
+            duration_ms = Empty
+        )
+    )]
+    pub async fn record_funding_rate(&self, entry: FundingRateEntry) -> Result<bool> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+        let mut db_tx = conn.begin().await?;
+
+        let contract_symbol = models::ContractSymbol::from(entry.contract_symbol);
+        let position_maker = models::Position::from(entry.position_maker);
+        let funding_rate = models::FundingRate::from(entry.funding_rate);
+        let timestamp = models::Timestamp::from(entry.timestamp);
+
+        let latest = sqlx::query!(
+            r#"
+            SELECT
+                funding_rate as "funding_rate: models::FundingRate"
+            FROM
+                funding_rate_history
+            WHERE
+                contract_symbol = $1 AND position_maker = $2
+            ORDER BY
+                id DESC
+            LIMIT 1
+            "#,
+            contract_symbol,
+            position_maker,
+        )
+        .fetch_optional(&mut *db_tx)
+        .await?;
+
+        if latest.map(|row| row.funding_rate) == Some(funding_rate) {
+            return Ok(false);
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO funding_rate_history
+            (
+                contract_symbol,
+                position_maker,
+                funding_rate,
+                timestamp
+            )
+            VALUES ($1, $2, $3, $4)
+            "#,
+            contract_symbol,
+            position_maker,
+            funding_rate,
+            timestamp,
+        )
+        .execute(&mut *db_tx)
+        .await?;
+
+        db_tx.commit().await?;
+
+        Ok(true)
+    }
+
+    /// Load all funding rate entries recorded since `since`, oldest first.
+    ///
+    /// The latest entry before `since` is included for every contract symbol and maker position,
+    /// because its funding rate still applied at `since`.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_funding_rate_history", since = %since, duration_ms = Empty)
+    )]
+    pub async fn load_funding_rate_history(
+        &self,
+        since: Timestamp,
+    ) -> Result<Vec<FundingRateEntry>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let since = models::Timestamp::from(since);
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                contract_symbol as "contract_symbol: models::ContractSymbol",
+                position_maker as "position_maker: models::Position",
+                funding_rate as "funding_rate: models::FundingRate",
+                timestamp as "timestamp: models::Timestamp"
+            FROM
+                funding_rate_history
+            WHERE
+                timestamp >= $1 OR id IN (
+                    SELECT
+                        MAX(id)
+                    FROM
+                        funding_rate_history
+                    WHERE
+                        timestamp < $1
+                    GROUP BY
+                        contract_symbol, position_maker
+                )
+            ORDER BY
+                id
+            "#,
+            since,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let entries = rows
+            .into_iter()
+            .map(|row| FundingRateEntry {
+                contract_symbol: row.contract_symbol.into(),
+                position_maker: row.position_maker.into(),
+                funding_rate: row.funding_rate.into(),
+                timestamp: row.timestamp.into(),
+            })
+            .collect();
+
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+    use rust_decimal_macros::dec;
+
+    #[tokio::test]
+    async fn given_unchanged_funding_rate_then_not_recorded_again() {
+        let db = memory().await.unwrap();
+
+        let entry = dummy_entry(dec!(0.0005), 1_000);
+
+        assert!(db.record_funding_rate(entry).await.unwrap());
+        assert!(!db
+            .record_funding_rate(FundingRateEntry {
+                timestamp: Timestamp::new(2_000),
+                ..entry
+            })
+            .await
+            .unwrap());
+
+        let history = db
+            .load_funding_rate_history(Timestamp::new(0))
+            .await
+            .unwrap();
+
+        assert_eq!(history, vec![entry]);
+    }
+
+    #[tokio::test]
+    async fn history_includes_funding_rate_that_applied_at_start() {
+        let db = memory().await.unwrap();
+
+        let outdated = dummy_entry(dec!(0.0001), 1_000);
+        let applied_at_start = dummy_entry(dec!(0.0002), 2_000);
+        let changed = dummy_entry(dec!(0.0003), 4_000);
+
+        for entry in [outdated, applied_at_start, changed] {
+            db.record_funding_rate(entry).await.unwrap();
+        }
+
+        let history = db
+            .load_funding_rate_history(Timestamp::new(3_000))
+            .await
+            .unwrap();
+
+        assert_eq!(history, vec![applied_at_start, changed]);
+    }
+
+    fn dummy_entry(funding_rate: rust_decimal::Decimal, timestamp: i64) -> FundingRateEntry {
+        FundingRateEntry {
+            contract_symbol: ContractSymbol::BtcUsd,
+            position_maker: Position::Short,
+            funding_rate: FundingRate::new(funding_rate).unwrap(),
+            timestamp: Timestamp::new(timestamp),
+        }
+    }
+}
@@ -20,6 +20,8 @@ impl crate::CfdAggregate for model::Cfd {
             initial_funding_rate,
             initial_tx_fee_rate,
             contract_symbol,
+            funding_period,
+            quanto_multiplier,
         }: crate::Cfd,
     ) -> Self {
         model::Cfd::new(
@@ -37,6 +39,8 @@ impl crate::CfdAggregate for model::Cfd {
             initial_funding_rate,
             initial_tx_fee_rate,
             contract_symbol,
+            funding_period,
+            quanto_multiplier,
         )
     }
 
@@ -48,3 +52,9 @@ impl crate::CfdAggregate for model::Cfd {
         self.version()
     }
 }
+
+impl crate::VerifiableAggregate for model::Cfd {
+    fn state_hash(&self) -> u64 {
+        self.state_hash()
+    }
+}
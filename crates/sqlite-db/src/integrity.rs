@@ -0,0 +1,139 @@
+//! Audit of the referential integrity of the database.
+//!
+//! Foreign keys are enforced on every connection, but databases that were written before may
+//! contain rows referencing rows that do not exist. Some tables are keyed by the order id of a CFD
+//! without a foreign key, because their rows are kept after the CFD moved from `cfds` to
+//! `closed_cfds` or `failed_cfds`. Their rows are orphaned if none of these tables has the CFD.
+
+use crate::Connection;
+use anyhow::Context;
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::Row;
+use tracing::field::Empty;
+
+/// Tables referencing a CFD by its order id without a foreign key
+const ORDER_ID_TABLES: &[&str] = &[
+    "api_token_orders",
+    "cfd_tags",
+    "daily_marks",
+    "event_feed",
+    "ledger_cfd_transactions",
+    "ledger_entries",
+];
+
+/// Outcome of checking the referential integrity of the database.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Report {
+    /// Rows violating a foreign key, as reported by `PRAGMA foreign_key_check`
+    pub foreign_key_violations: Vec<ForeignKeyViolation>,
+    /// Tables with rows referencing an order id that none of the CFD tables has
+    pub orphaned_rows: Vec<OrphanedRows>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ForeignKeyViolation {
+    pub table: String,
+    /// `None` for tables without rowid
+    pub rowid: Option<i64>,
+    /// The table the row references
+    pub parent: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OrphanedRows {
+    pub table: &'static str,
+    pub count: i64,
+}
+
+impl Report {
+    pub fn is_consistent(&self) -> bool {
+        self.foreign_key_violations.is_empty() && self.orphaned_rows.is_empty()
+    }
+}
+
+impl Connection {
+    /// Check the referential integrity of the database, nothing is changed.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "check_integrity", duration_ms = Empty)
+    )]
+    pub async fn check_integrity(&self) -> Result<Report> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let foreign_key_violations = sqlx::query("PRAGMA foreign_key_check")
+            .fetch_all(&mut *conn)
+            .await
+            .context("Failed to check foreign keys")?
+            .into_iter()
+            .map(|row| {
+                Ok(ForeignKeyViolation {
+                    table: row.try_get("table")?,
+                    rowid: row.try_get("rowid")?,
+                    parent: row.try_get("parent")?,
+                })
+            })
+            .collect::<Result<Vec<_>, sqlx::Error>>()?;
+
+        let mut orphaned_rows = Vec::new();
+        for &table in ORDER_ID_TABLES {
+            let count = sqlx::query_scalar::<_, i64>(&format!(
+                "SELECT COUNT(*) FROM {table} WHERE order_id NOT IN \
+                 (SELECT order_id FROM cfds \
+                 UNION SELECT order_id FROM closed_cfds \
+                 UNION SELECT order_id FROM failed_cfds)"
+            ))
+            .fetch_one(&mut *conn)
+            .await
+            .with_context(|| format!("Failed to check {table} for orphaned rows"))?;
+
+            if count > 0 {
+                orphaned_rows.push(OrphanedRows { table, count });
+            }
+        }
+
+        Ok(Report {
+            foreign_key_violations,
+            orphaned_rows,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+    use crate::tests::dummy_cfd;
+    use crate::tests::lock_confirmed;
+    use model::OrderId;
+
+    #[tokio::test]
+    async fn given_tag_of_unknown_cfd_then_orphaned_row_reported() {
+        let db = memory().await.unwrap();
+
+        let cfd = dummy_cfd();
+        db.insert_cfd(&cfd).await.unwrap();
+        db.append_event(lock_confirmed(&cfd)).await.unwrap();
+        db.insert_cfd_tag(cfd.id(), "hedge").await.unwrap();
+
+        assert!(db.check_integrity().await.unwrap().is_consistent());
+
+        db.insert_cfd_tag(OrderId::default(), "hedge")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            db.check_integrity().await.unwrap(),
+            Report {
+                foreign_key_violations: vec![],
+                orphaned_rows: vec![OrphanedRows {
+                    table: "cfd_tags",
+                    count: 1
+                }]
+            }
+        );
+    }
+}
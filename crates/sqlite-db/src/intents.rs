@@ -0,0 +1,199 @@
+//! Actions the taker requested while the maker was offline.
+//!
+//! Every CFD has at most one queued intent; queueing another one replaces it. Intents are deleted
+//! once they were executed or expired.
+
+use crate::models;
+use crate::Connection;
+use anyhow::Context;
+use anyhow::Result;
+use bdk::bitcoin::Address;
+use model::OrderId;
+use model::Timestamp;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fmt;
+use tracing::field::Empty;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IntentAction {
+    /// Settle collaboratively at the market price at the time the intent is executed
+    Settle {
+        payout_address: Option<Address>,
+    },
+    Rollover,
+}
+
+impl fmt::Display for IntentAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntentAction::Settle {
+                payout_address: None,
+            } => write!(f, "close at market"),
+            IntentAction::Settle {
+                payout_address: Some(address),
+            } => write!(f, "close at market to {address}"),
+            IntentAction::Rollover => write!(f, "rollover"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Intent {
+    pub order_id: OrderId,
+    pub action: IntentAction,
+    pub created_at: Timestamp,
+    pub expires_at: Timestamp,
+}
+
+impl Connection {
+    /// Queue an intent, replacing an earlier one of the same CFD.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "insert_intent", order_id = %intent.order_id, duration_ms = Empty)
+    )]
+    pub async fn insert_intent(&self, intent: &Intent) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let order_id = models::OrderId::from(intent.order_id);
+        let action = serde_json::to_string(&intent.action).context("Failed to encode intent")?;
+        let created_at = models::Timestamp::from(intent.created_at);
+        let expires_at = models::Timestamp::from(intent.expires_at);
+
+        sqlx::query!(
+            r#"
+            INSERT OR REPLACE INTO intents
+            (
+                order_id,
+                action,
+                created_at,
+                expires_at
+            )
+            VALUES ($1, $2, $3, $4)
+            "#,
+            order_id,
+            action,
+            created_at,
+            expires_at,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Load all queued intents, oldest first.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_intents", duration_ms = Empty)
+    )]
+    pub async fn load_intents(&self) -> Result<Vec<Intent>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                order_id as "order_id: models::OrderId",
+                action,
+                created_at as "created_at: models::Timestamp",
+                expires_at as "expires_at: models::Timestamp"
+            FROM
+                intents
+            ORDER BY
+                created_at ASC
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let action =
+                    serde_json::from_str(&row.action).context("Failed to decode intent")?;
+
+                Ok(Intent {
+                    order_id: row.order_id.into(),
+                    action,
+                    created_at: row.created_at.into(),
+                    expires_at: row.expires_at.into(),
+                })
+            })
+            .collect()
+    }
+
+    /// Remove the intent of a CFD once it was executed or expired.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "delete_intent", %order_id, duration_ms = Empty)
+    )]
+    pub async fn delete_intent(&self, order_id: OrderId) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let order_id = models::OrderId::from(order_id);
+
+        sqlx::query!(
+            r#"
+            DELETE FROM intents
+            WHERE
+                order_id = $1
+            "#,
+            order_id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+    use crate::tests::dummy_cfd;
+
+    #[tokio::test]
+    async fn given_intent_replaced_and_deleted_then_none_left() {
+        let db = memory().await.unwrap();
+
+        let cfd = dummy_cfd();
+        db.insert_cfd(&cfd).await.unwrap();
+
+        let order_id = cfd.id();
+        let first = dummy_intent(order_id, IntentAction::Rollover);
+        let second = dummy_intent(
+            order_id,
+            IntentAction::Settle {
+                payout_address: None,
+            },
+        );
+
+        db.insert_intent(&first).await.unwrap();
+        db.insert_intent(&second).await.unwrap();
+
+        assert_eq!(db.load_intents().await.unwrap(), vec![second]);
+
+        db.delete_intent(order_id).await.unwrap();
+
+        assert!(db.load_intents().await.unwrap().is_empty());
+    }
+
+    fn dummy_intent(order_id: OrderId, action: IntentAction) -> Intent {
+        Intent {
+            order_id,
+            action,
+            created_at: Timestamp::new(1_000),
+            expires_at: Timestamp::new(8_200),
+        }
+    }
+}
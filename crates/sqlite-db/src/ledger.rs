@@ -0,0 +1,323 @@
+//! Ledger of all changes to the wallet balance.
+//!
+//! Every confirmed wallet transaction results in one or more ledger entries with the reason of
+//! the change. Transactions of CFDs are linked to the CFD beforehand, so that they can be told
+//! apart from deposits and withdrawals once they show up in the wallet.
+
+use crate::models;
+use crate::Connection;
+use anyhow::Result;
+use bdk::bitcoin::SignedAmount;
+use bdk::bitcoin::Txid;
+use model::LedgerEntry;
+use model::LedgerReason;
+use model::OrderId;
+use sqlx::Acquire;
+use std::collections::HashSet;
+use tracing::field::Empty;
+
+impl Connection {
+    /// Link a transaction of a CFD that changes the wallet balance to the CFD.
+    ///
+    /// Linking the same transaction again has no effect.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "link_cfd_transaction", %txid, %order_id, duration_ms = Empty)
+    )]
+    pub async fn link_cfd_transaction(
+        &self,
+        txid: Txid,
+        order_id: OrderId,
+        reason: LedgerReason,
+    ) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let txid = models::Txid::from(txid);
+        let order_id = models::OrderId::from(order_id);
+        let reason = models::LedgerReason::from(reason);
+
+        sqlx::query!(
+            r#"
+            INSERT OR IGNORE INTO ledger_cfd_transactions
+            (
+                txid,
+                order_id,
+                reason
+            )
+            VALUES ($1, $2, $3)
+            "#,
+            txid,
+            order_id,
+            reason,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Load the CFD the transaction belongs to, if any.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_cfd_transaction", %txid, duration_ms = Empty)
+    )]
+    pub async fn load_cfd_transaction(
+        &self,
+        txid: Txid,
+    ) -> Result<Option<(OrderId, LedgerReason)>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let txid = models::Txid::from(txid);
+
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                order_id as "order_id: models::OrderId",
+                reason as "reason: models::LedgerReason"
+            FROM
+                ledger_cfd_transactions
+            WHERE
+                txid = $1
+            "#,
+            txid,
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(row.map(|row| (row.order_id.into(), row.reason.into())))
+    }
+
+    /// Insert the ledger entries of a transaction atomically.
+    ///
+    /// Entries already recorded for the same transaction and reason are ignored.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "insert_ledger_entries", duration_ms = Empty)
+    )]
+    pub async fn insert_ledger_entries(&self, entries: &[LedgerEntry]) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+        let mut db_tx = conn.begin().await?;
+
+        for entry in entries {
+            let txid = models::Txid::from(entry.txid);
+            let reason = models::LedgerReason::from(entry.reason);
+            let order_id = entry.order_id.map(models::OrderId::from);
+            let amount = entry.amount.as_sat();
+            let timestamp = models::Timestamp::from(entry.timestamp);
+
+            sqlx::query!(
+                r#"
+                INSERT OR IGNORE INTO ledger_entries
+                (
+                    txid,
+                    reason,
+                    order_id,
+                    amount,
+                    timestamp
+                )
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+                txid,
+                reason,
+                order_id,
+                amount,
+                timestamp,
+            )
+            .execute(&mut *db_tx)
+            .await?;
+        }
+
+        db_tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Load all ledger entries, oldest first.
+    ///
+    /// Deposits matched to an expected deposit carry the label of the expected deposit, withdrawals
+    /// to the cold wallet the address they were sent to.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_ledger_entries", duration_ms = Empty)
+    )]
+    pub async fn load_ledger_entries(&self) -> Result<Vec<LedgerEntry>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                ledger_entries.txid as "txid!: models::Txid",
+                ledger_entries.reason as "reason!: models::LedgerReason",
+                ledger_entries.order_id as "order_id: models::OrderId",
+                ledger_entries.amount as "amount!",
+                ledger_entries.timestamp as "timestamp!: models::Timestamp",
+                COALESCE(
+                    expected_deposits.label,
+                    'Cold sweep to ' || cold_sweeps.address
+                ) as "label?"
+            FROM
+                ledger_entries
+            LEFT JOIN
+                expected_deposits
+            ON
+                expected_deposits.txid = ledger_entries.txid
+                AND ledger_entries.reason = 'Deposit'
+            LEFT JOIN
+                cold_sweeps
+            ON
+                cold_sweeps.txid = ledger_entries.txid
+                AND ledger_entries.reason = 'Withdrawal'
+            ORDER BY
+                ledger_entries.timestamp ASC, ledger_entries.id ASC
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let entries = rows
+            .into_iter()
+            .map(|row| LedgerEntry {
+                txid: row.txid.into(),
+                reason: row.reason.into(),
+                order_id: row.order_id.map(Into::into),
+                amount: SignedAmount::from_sat(row.amount),
+                timestamp: row.timestamp.into(),
+                label: row.label,
+            })
+            .collect();
+
+        Ok(entries)
+    }
+
+    /// Load the ids of all transactions that were recorded in the ledger.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_ledger_txids", duration_ms = Empty)
+    )]
+    pub async fn load_ledger_txids(&self) -> Result<HashSet<Txid>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT DISTINCT
+                txid as "txid: models::Txid"
+            FROM
+                ledger_entries
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| row.txid.into()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cold_sweeps::ColdSweep;
+    use crate::memory;
+    use bdk::bitcoin::Address;
+    use bdk::bitcoin::Amount;
+    use model::Timestamp;
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn given_entries_inserted_twice_then_recorded_once() {
+        let db = memory().await.unwrap();
+
+        let txid =
+            Txid::from_str("8a1b0d0f0c9c8c1e5f0f2d4c3b2a19181716151413121110090807060504f3f2")
+                .unwrap();
+        let order_id = OrderId::default();
+        let entries = [
+            LedgerEntry {
+                txid,
+                reason: LedgerReason::Withdrawal,
+                order_id: None,
+                amount: SignedAmount::from_sat(-100_000),
+                timestamp: Timestamp::new(1_000),
+                label: None,
+            },
+            LedgerEntry {
+                txid,
+                reason: LedgerReason::ChainFee,
+                order_id: None,
+                amount: SignedAmount::from_sat(-200),
+                timestamp: Timestamp::new(1_000),
+                label: None,
+            },
+        ];
+
+        db.link_cfd_transaction(txid, order_id, LedgerReason::LockUp)
+            .await
+            .unwrap();
+        db.insert_ledger_entries(&entries).await.unwrap();
+        db.insert_ledger_entries(&entries).await.unwrap();
+
+        assert_eq!(db.load_ledger_entries().await.unwrap(), entries.to_vec());
+        assert_eq!(db.load_ledger_txids().await.unwrap(), HashSet::from([txid]));
+        assert_eq!(
+            db.load_cfd_transaction(txid).await.unwrap(),
+            Some((order_id, LedgerReason::LockUp))
+        );
+    }
+
+    #[tokio::test]
+    async fn given_cold_sweep_then_withdrawal_is_labelled_with_address() {
+        let db = memory().await.unwrap();
+
+        let txid =
+            Txid::from_str("8a1b0d0f0c9c8c1e5f0f2d4c3b2a19181716151413121110090807060504f3f2")
+                .unwrap();
+        let address = Address::from_str("tb1qwannruxxe8qneqkge6qnwjrzhrrff4pjaqfxch").unwrap();
+        let entry = |reason, amount| LedgerEntry {
+            txid,
+            reason,
+            order_id: None,
+            amount: SignedAmount::from_sat(amount),
+            timestamp: Timestamp::new(1_000),
+            label: None,
+        };
+
+        db.insert_cold_sweep(&ColdSweep {
+            txid,
+            address: address.clone(),
+            amount: Amount::from_sat(100_000),
+            fee_rate: 1.0,
+            created_at: Timestamp::new(900),
+        })
+        .await
+        .unwrap();
+        db.insert_ledger_entries(&[
+            entry(LedgerReason::Withdrawal, -100_000),
+            entry(LedgerReason::ChainFee, -200),
+        ])
+        .await
+        .unwrap();
+
+        let labels = db
+            .load_ledger_entries()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.label)
+            .collect::<Vec<_>>();
+        assert_eq!(labels, vec![Some(format!("Cold sweep to {address}")), None]);
+    }
+}
@@ -4,6 +4,7 @@ use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
 use dashmap::DashMap;
+use futures::future;
 use futures::future::BoxFuture;
 use futures::FutureExt;
 use futures::Stream;
@@ -12,6 +13,7 @@ use model::CfdEvent;
 use model::ContractSymbol;
 use model::Contracts;
 use model::EventKind;
+use model::FundingPeriod;
 use model::FundingRate;
 use model::Identity;
 use model::Leverage;
@@ -22,6 +24,7 @@ use model::Position;
 use model::Price;
 use model::Role;
 use model::TxFeeRate;
+use rust_decimal::Decimal;
 use sqlx::migrate::MigrateError;
 use sqlx::sqlite::SqliteConnectOptions;
 use sqlx::Acquire;
@@ -29,28 +32,57 @@ use sqlx::SqliteConnection;
 use sqlx::SqlitePool;
 use std::any::Any;
 use std::any::TypeId;
+use std::future::Future;
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
 use time::Duration;
+use tracing::field::Empty;
 
 pub use closed::*;
 pub use failed::*;
 use model::EventKind::RolloverCompleted;
+pub use outbox::OutboxEntry;
+pub use query_timer::DEFAULT_SLOW_QUERY_THRESHOLD;
 
+pub mod api_tokens;
+pub mod cet_publications;
+pub mod cfd_tags;
 pub mod closed;
+pub mod cold_sweeps;
+pub mod collab_settlement;
+pub mod consistency;
+pub mod daily_marks;
+pub mod event_feed;
 pub mod event_log;
+pub mod expected_deposits;
 pub mod failed;
+pub mod funding_rate_history;
 mod impls;
+pub mod integrity;
+pub mod intents;
+pub mod ledger;
+pub mod metric_snapshots;
 mod models;
+pub mod offer_cache;
+pub mod offer_history;
+pub mod outbox;
+pub mod peer_sessions;
+pub mod protocol_failures;
+mod query_timer;
 mod rollover;
+pub mod schema_flags;
+pub mod telemetry;
 pub mod time_to_first_position;
 pub mod user;
+pub mod wind_down;
+pub mod withdrawals;
 
 #[derive(Clone)]
 pub struct Connection {
     inner: SqlitePool,
     aggregate_cache: Arc<DashMap<(TypeId, OrderId), Box<dyn Any + Send + Sync + 'static>>>,
+    slow_query_threshold: std::time::Duration,
 }
 
 impl Connection {
@@ -58,12 +90,45 @@ impl Connection {
         Self {
             inner: pool,
             aggregate_cache: Arc::new(DashMap::new()),
+            slow_query_threshold: DEFAULT_SLOW_QUERY_THRESHOLD,
         }
     }
 
     pub async fn close(self) {
         self.inner.close().await;
     }
+
+    /// Check that the database can be queried.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "ping", duration_ms = Empty)
+    )]
+    pub async fn ping(&self) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        sqlx::query("SELECT 1").execute(&mut *conn).await?;
+
+        Ok(())
+    }
+
+    /// Rebuild the database file to reclaim the space of deleted rows.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "compact", duration_ms = Empty)
+    )]
+    pub async fn compact(&self) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        sqlx::query("VACUUM").execute(&mut *conn).await?;
+
+        Ok(())
+    }
 }
 
 /// Connects to the SQLite database at the given path.
@@ -71,6 +136,8 @@ impl Connection {
 /// If the database does not exist, it will be created. If it does exist, we load it and apply all
 /// pending migrations. If applying migrations fails, the old database is backed up next to it and a
 /// new one is created.
+///
+/// Foreign keys are enforced, rows belonging to a CFD are deleted together with it.
 pub fn connect(
     path: PathBuf,
     ignore_migration_errors: bool,
@@ -79,6 +146,7 @@ pub fn connect(
         let pool = SqlitePool::connect_with(
             SqliteConnectOptions::new()
                 .create_if_missing(true)
+                .foreign_keys(true)
                 .filename(&path),
         )
         .await?;
@@ -132,7 +200,9 @@ pub async fn memory() -> Result<Connection> {
     // Note: Every :memory: database is distinct from every other. So, opening two database
     // connections each with the filename ":memory:" will create two independent in-memory
     // databases. see: https://www.sqlite.org/inmemorydb.html
-    let pool = SqlitePool::connect(":memory:").await?;
+    let pool =
+        SqlitePool::connect_with(SqliteConnectOptions::from_str(":memory:")?.foreign_keys(true))
+            .await?;
 
     run_migrations(&pool).await?;
 
@@ -149,7 +219,14 @@ async fn run_migrations(pool: &SqlitePool) -> Result<()> {
 }
 
 impl Connection {
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "insert_cfd", order_id = %cfd.id(), duration_ms = Empty)
+    )]
     pub async fn insert_cfd(&self, cfd: &model::Cfd) -> Result<()> {
+        let _timer = self.query_timer();
+
         let mut conn = self.inner.acquire().await?;
 
         let order_id = models::OrderId::from(cfd.id());
@@ -168,6 +245,8 @@ impl Connection {
         let tx_fee_rate = models::TxFeeRate::from(cfd.initial_tx_fee_rate());
         let counterparty_peer_id = cfd.counterparty_peer_id().map(models::PeerId::from);
         let contract_symbol = models::ContractSymbol::from(cfd.contract_symbol());
+        let funding_period = models::FundingPeriod::from(cfd.funding_period());
+        let quanto_multiplier = cfd.quanto_multiplier().map(models::QuantoMultiplier::from);
 
         let query_result = sqlx::query(
             r#"
@@ -185,8 +264,10 @@ impl Connection {
             opening_fee,
             initial_funding_rate,
             initial_tx_fee_rate,
-            contract_symbol
-        ) values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)"#,
+            contract_symbol,
+            funding_period,
+            quanto_multiplier
+        ) values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)"#,
         )
         .bind(&order_id)
         .bind(&offer_id)
@@ -209,6 +290,8 @@ impl Connection {
         .bind(&initial_funding_rate)
         .bind(&tx_fee_rate)
         .bind(&contract_symbol)
+        .bind(&funding_period)
+        .bind(&quanto_multiplier)
         .execute(&mut conn)
         .await?;
 
@@ -223,7 +306,14 @@ impl Connection {
     ///
     /// To make handling of `None` events more ergonomic, you can pass anything in here that
     /// implements `Into<Option>` event.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "append_event", order_id = Empty, duration_ms = Empty)
+    )]
     pub async fn append_event(&self, event: impl Into<Option<CfdEvent>>) -> Result<()> {
+        let _timer = self.query_timer();
+
         let mut conn = self.inner.acquire().await?;
         let mut db_tx = conn.begin().await?;
 
@@ -232,62 +322,12 @@ impl Connection {
             None => return Ok(()),
         };
 
-        let (event_name, event_data) = event.event.to_json();
-
-        let order_id = models::OrderId::from(event.id);
-        let timestamp = models::Timestamp::from(event.timestamp);
-        let query_result = sqlx::query(
-            r##"
-        insert into events (
-            cfd_id,
-            name,
-            data,
-            created_at
-        ) values (
-            (select id from cfds where cfds.order_id = $1),
-            $2, $3, $4
-        )"##,
-        )
-        .bind(&order_id)
-        .bind(&event_name)
-        .bind(&event_data)
-        .bind(&timestamp)
-        .execute(&mut db_tx)
-        .await?;
-
-        if query_result.rows_affected() != 1 {
-            bail!("failed to insert event");
-        }
+        tracing::Span::current().record("order_id", tracing::field::display(event.id));
 
-        match event.event {
-            // if we have a rollover completed event we store it additionally in its own table
-            RolloverCompleted {
-                dlc: Some(dlc),
-                funding_fee,
-                complete_fee,
-            } => {
-                rollover::overwrite(
-                    &mut db_tx,
-                    query_result.last_insert_rowid(),
-                    order_id,
-                    dlc,
-                    funding_fee,
-                    complete_fee,
-                )
-                .await?;
-            }
-            RolloverCompleted { dlc: None, .. } => {
-                tracing::error!(
-                    "Invalid RolloverCompleted event: Trying to insert a RolloverCompleted event without a DLC"
-                )
-            }
-            _ => {}
-        }
+        insert_event(&mut db_tx, event).await?;
 
         db_tx.commit().await?;
 
-        tracing::info!(event = %event_name, %order_id, "Appended event to database");
-
         Ok(())
     }
 
@@ -296,6 +336,35 @@ impl Connection {
     where
         C: CfdAggregate,
     {
+        self.load_open_cfd_with(id, args, |cfd, events| {
+            future::ready(Ok(events.into_iter().fold(cfd, C::apply)))
+        })
+        .await
+    }
+
+    /// Load a CFD in its latest version from the database, applying the new events with `fold`.
+    ///
+    /// Allows to apply the events elsewhere than on the calling task, e.g. on a thread pool for
+    /// blocking work if there are many of them. The database connection is released before the
+    /// events are applied.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_open_cfd", order_id = %id, duration_ms = Empty)
+    )]
+    pub async fn load_open_cfd_with<C, F, Fut>(
+        &self,
+        id: OrderId,
+        args: C::CtorArgs,
+        fold: F,
+    ) -> Result<C, Error>
+    where
+        C: CfdAggregate,
+        F: FnOnce(C, Vec<CfdEvent>) -> Fut,
+        Fut: Future<Output = Result<C>>,
+    {
+        let _timer = self.query_timer();
+
         let mut conn = self.inner.acquire().await?;
         let mut db_tx = conn.begin().await?;
 
@@ -324,15 +393,16 @@ impl Connection {
             .with_context(|| format!("Could not load events for CFD {id}"))?;
         let num_events = events.len();
 
+        db_tx.commit().await?;
+        drop(conn);
+
         tracing::trace!(target = "aggregate", order_id =  %id, %aggregate, %cfd_version, %num_events, "Applying new events to CFD");
 
-        let cfd = events.into_iter().fold(cfd, C::apply);
+        let cfd = fold(cfd, events).await?;
 
         self.aggregate_cache
             .insert(cache_key, Box::new(cfd.clone()));
 
-        db_tx.commit().await?;
-
         Ok(cfd)
     }
 
@@ -343,11 +413,30 @@ impl Connection {
     where
         C: CfdAggregate + ClosedCfdAggregate + FailedCfdAggregate,
         C::CtorArgs: Clone + Send + Sync,
+    {
+        self.load_all_cfds_with(args, |cfd, events| {
+            future::ready(Ok(events.into_iter().fold(cfd, C::apply)))
+        })
+    }
+
+    /// Load all CFDs, applying the new events of the open CFDs with `fold`.
+    ///
+    /// See [`Connection::load_open_cfd_with`].
+    pub fn load_all_cfds_with<'a, C, F, Fut>(
+        &'a self,
+        args: C::CtorArgs,
+        fold: F,
+    ) -> impl Stream<Item = Result<C>> + Unpin + 'a
+    where
+        C: CfdAggregate + ClosedCfdAggregate + FailedCfdAggregate,
+        C::CtorArgs: Clone + Send + Sync,
+        F: Fn(C, Vec<CfdEvent>) -> Fut + Send + 'a,
+        Fut: Future<Output = Result<C>> + Send,
     {
         let stream = async_stream::stream! {
             let ids = self.load_open_cfd_ids().await?;
             for id in ids {
-                let res = match self.load_open_cfd(id, args.clone()).await {
+                let res = match self.load_open_cfd_with(id, args.clone(), &fold).await {
                     Err(Error::OpenCfdNotFound) => {
                         tracing::trace!(
                             order_id=%id,
@@ -385,9 +474,9 @@ impl Connection {
     /// Open in this context means that the CFD is not final yet, i.e. we can still append events.
     /// In this context a CFD is not open anymore if one of the following happened:
     /// 1. Event of the confirmation of a payout (spend) transaction on the blockchain was recorded
-    ///     Cases: Collaborative settlement, CET, Refund
+    ///    Cases: Collaborative settlement, CET, Refund
     /// 2. Event that fails the CFD early was recorded, meaning it becomes irrelevant for processing
-    ///     Cases: Setup failed, Taker's take order rejected
+    ///    Cases: Setup failed, Taker's take order rejected
     pub fn load_all_open_cfds<'a, C>(
         &'a self,
         args: C::CtorArgs,
@@ -423,7 +512,14 @@ impl Connection {
     ///
     /// Importantly, callers **cannot** rely on the CFD IDs returned
     /// corresponding to open CFDs.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_open_cfd_ids", duration_ms = Empty)
+    )]
     pub async fn load_open_cfd_ids(&self) -> Result<Vec<OrderId>> {
+        let _timer = self.query_timer();
+
         let mut conn = self.inner.acquire().await?;
 
         let ids = sqlx::query!(
@@ -443,7 +539,14 @@ impl Connection {
         Ok(ids)
     }
 
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "closed_cfd_ids_according_to_the_blockchain", duration_ms = Empty)
+    )]
     async fn closed_cfd_ids_according_to_the_blockchain(&self) -> Result<Vec<OrderId>> {
+        let _timer = self.query_timer();
+
         let mut conn = self.inner.acquire().await?;
 
         let ids = sqlx::query!(
@@ -476,7 +579,14 @@ impl Connection {
         Ok(ids)
     }
 
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "failed_cfd_ids_according_to_events", duration_ms = Empty)
+    )]
     async fn failed_cfd_ids_according_to_events(&self) -> Result<Vec<OrderId>> {
+        let _timer = self.query_timer();
+
         let mut conn = self.inner.acquire().await?;
 
         let ids = sqlx::query!(
@@ -526,6 +636,8 @@ pub struct Cfd {
     pub initial_funding_rate: FundingRate,
     pub initial_tx_fee_rate: TxFeeRate,
     pub contract_symbol: ContractSymbol,
+    pub funding_period: FundingPeriod,
+    pub quanto_multiplier: Option<Decimal>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -563,6 +675,12 @@ pub trait CfdAggregate: Clone + Send + Sync + 'static {
     fn version(&self) -> u32;
 }
 
+/// An aggregate whose cached state can be verified against the event log.
+pub trait VerifiableAggregate: CfdAggregate {
+    /// Hash of the fields derived from the events applied to the aggregate.
+    fn state_hash(&self) -> u64;
+}
+
 async fn load_cfd_row(conn: &mut SqliteConnection, id: OrderId) -> Result<Cfd, Error> {
     let id = models::OrderId::from(id);
 
@@ -583,7 +701,9 @@ async fn load_cfd_row(conn: &mut SqliteConnection, id: OrderId) -> Result<Cfd, E
                 opening_fee as "opening_fee: models::OpeningFee",
                 initial_funding_rate as "initial_funding_rate: models::FundingRate",
                 initial_tx_fee_rate as "initial_tx_fee_rate: models::TxFeeRate",
-                contract_symbol as "contract_symbol: models::ContractSymbol"
+                contract_symbol as "contract_symbol: models::ContractSymbol",
+                funding_period as "funding_period: models::FundingPeriod",
+                quanto_multiplier as "quanto_multiplier: models::QuantoMultiplier"
             from
                 cfds
             where
@@ -620,6 +740,8 @@ async fn load_cfd_row(conn: &mut SqliteConnection, id: OrderId) -> Result<Cfd, E
         initial_funding_rate: cfd_row.initial_funding_rate.into(),
         initial_tx_fee_rate: cfd_row.initial_tx_fee_rate.into(),
         contract_symbol: cfd_row.contract_symbol.into(),
+        funding_period: cfd_row.funding_period.into(),
+        quanto_multiplier: cfd_row.quanto_multiplier.map(Decimal::from),
     })
 }
 
@@ -723,44 +845,89 @@ async fn load_cfd_events(
     Ok(events)
 }
 
-async fn delete_from_cfds_table(conn: &mut SqliteConnection, id: OrderId) -> Result<()> {
-    let id = models::OrderId::from(id);
-    let query_result = sqlx::query!(
-        r#"
-        DELETE FROM
-            cfds
-        WHERE
-            cfds.order_id = $1
-        "#,
-        id,
+/// Inserts an event into the `events` table and returns the row id of the new event.
+///
+/// The event is also appended to the [`event_feed`]. `RolloverCompleted` events are additionally
+/// stored in their own table.
+async fn insert_event(conn: &mut SqliteConnection, event: CfdEvent) -> Result<i64> {
+    let (event_name, event_data) = event.event.to_json();
+
+    let order_id = models::OrderId::from(event.id);
+    let timestamp = models::Timestamp::from(event.timestamp);
+    let query_result = sqlx::query(
+        r##"
+        insert into events (
+            cfd_id,
+            name,
+            data,
+            created_at
+        ) values (
+            (select id from cfds where cfds.order_id = $1),
+            $2, $3, $4
+        )"##,
     )
+    .bind(&order_id)
+    .bind(&event_name)
+    .bind(&event_data)
+    .bind(&timestamp)
     .execute(&mut *conn)
     .await?;
 
     if query_result.rows_affected() != 1 {
-        bail!("failed to delete from cfds");
+        bail!("failed to insert event");
     }
 
-    Ok(())
+    let event_row_id = query_result.last_insert_rowid();
+
+    event_feed::append(&mut *conn, &order_id, &event_name, &event_data, &timestamp).await?;
+
+    match event.event {
+        // if we have a rollover completed event we store it additionally in its own table
+        RolloverCompleted {
+            dlc: Some(dlc),
+            funding_fee,
+            complete_fee,
+        } => {
+            rollover::overwrite(
+                &mut *conn,
+                event_row_id,
+                order_id,
+                dlc,
+                funding_fee,
+                complete_fee,
+            )
+            .await?;
+        }
+        RolloverCompleted { dlc: None, .. } => {
+            tracing::error!(
+                "Invalid RolloverCompleted event: Trying to insert a RolloverCompleted event without a DLC"
+            )
+        }
+        _ => {}
+    }
+
+    tracing::info!(event = %event_name, %order_id, "Appended event to database");
+
+    Ok(event_row_id)
 }
 
-async fn delete_from_events_table(conn: &mut SqliteConnection, id: OrderId) -> Result<()> {
+/// Delete an open CFD, the rows that belong to it are deleted through their foreign keys.
+async fn delete_from_cfds_table(conn: &mut SqliteConnection, id: OrderId) -> Result<()> {
     let id = models::OrderId::from(id);
-
     let query_result = sqlx::query!(
         r#"
         DELETE FROM
-            events
-        WHERE events.cfd_id IN
-            (SELECT id FROM cfds WHERE cfds.order_id = $1)
+            cfds
+        WHERE
+            cfds.order_id = $1
         "#,
         id,
     )
     .execute(&mut *conn)
     .await?;
 
-    if query_result.rows_affected() < 1 {
-        bail!("failed to delete from events");
+    if query_result.rows_affected() != 1 {
+        bail!("failed to delete from cfds");
     }
 
     Ok(())
@@ -771,6 +938,7 @@ mod tests {
     use super::*;
     use bdk::bitcoin::Amount;
     use model::Cfd;
+    use model::FundingPeriod;
     use model::Leverage;
     use model::OpeningFee;
     use model::Position;
@@ -804,6 +972,8 @@ mod tests {
             initial_funding_rate,
             initial_tx_fee_rate,
             contract_symbol,
+            funding_period,
+            quanto_multiplier,
         } = load_cfd_row(&mut *conn, cfd.id()).await.unwrap();
 
         assert_eq!(cfd.id(), id);
@@ -823,6 +993,40 @@ mod tests {
         assert_eq!(cfd.initial_funding_rate(), initial_funding_rate);
         assert_eq!(cfd.initial_tx_fee_rate(), initial_tx_fee_rate);
         assert_eq!(cfd.contract_symbol(), contract_symbol);
+        assert_eq!(cfd.funding_period(), funding_period);
+        assert_eq!(cfd.quanto_multiplier(), quanto_multiplier);
+    }
+
+    #[tokio::test]
+    async fn given_quanto_cfd_when_loading_then_agreed_multiplier_is_kept() {
+        let db = memory().await.unwrap();
+        let mut conn = db.inner.acquire().await.unwrap();
+
+        let cfd = Cfd::new(
+            OrderId::default(),
+            OfferId::default(),
+            Position::Long,
+            Price::new(dec!(1_500)).unwrap(),
+            Leverage::TWO,
+            Duration::hours(24),
+            Role::Taker,
+            Contracts::new(100),
+            "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"
+                .parse()
+                .unwrap(),
+            Some(PeerId::random()),
+            OpeningFee::new(Amount::from_sat(2000)),
+            FundingRate::default(),
+            TxFeeRate::default(),
+            ContractSymbol::EthUsd,
+            FundingPeriod::Hourly,
+            Some(dec!(0.0000005)),
+        );
+        db.insert_cfd(&cfd).await.unwrap();
+
+        let loaded = load_cfd_row(&mut *conn, cfd.id()).await.unwrap();
+
+        assert_eq!(loaded.quanto_multiplier, Some(dec!(0.0000005)));
     }
 
     #[tokio::test]
@@ -956,6 +1160,8 @@ mod tests {
             FundingRate::default(),
             TxFeeRate::default(),
             ContractSymbol::BtcUsd,
+            FundingPeriod::Hourly,
+            None,
         )
     }
 
@@ -975,6 +1181,8 @@ mod tests {
             FundingRate::default(),
             TxFeeRate::default(),
             ContractSymbol::BtcUsd,
+            FundingPeriod::Hourly,
+            None,
         )
     }
 
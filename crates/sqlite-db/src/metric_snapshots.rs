@@ -0,0 +1,133 @@
+//! Snapshots of monotonic counters.
+//!
+//! Prometheus counters start from zero whenever the process restarts. Counters tracking
+//! business-level figures over a long horizon are periodically snapshotted and restored at startup
+//! instead.
+
+use crate::models;
+use crate::Connection;
+use anyhow::Result;
+use model::Timestamp;
+use sqlx::Acquire;
+use tracing::field::Empty;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricSnapshot {
+    pub name: String,
+    /// The label values identifying the counter within its family
+    pub labels: String,
+    pub value: f64,
+}
+
+impl Connection {
+    /// Store the latest value of each of the given counters.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "upsert_metric_snapshots", n = snapshots.len(), duration_ms = Empty)
+    )]
+    pub async fn upsert_metric_snapshots(
+        &self,
+        snapshots: &[MetricSnapshot],
+        updated_at: Timestamp,
+    ) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+        let mut db_tx = conn.begin().await?;
+
+        let updated_at = models::Timestamp::from(updated_at);
+
+        for snapshot in snapshots {
+            sqlx::query!(
+                r#"
+                INSERT INTO metric_snapshots
+                (
+                    name,
+                    labels,
+                    value,
+                    updated_at
+                )
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (name, labels) DO UPDATE SET
+                    value = $3,
+                    updated_at = $4
+                "#,
+                snapshot.name,
+                snapshot.labels,
+                snapshot.value,
+                updated_at,
+            )
+            .execute(&mut *db_tx)
+            .await?;
+        }
+
+        db_tx.commit().await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_metric_snapshots", duration_ms = Empty)
+    )]
+    pub async fn load_metric_snapshots(&self) -> Result<Vec<MetricSnapshot>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                name,
+                labels,
+                value
+            FROM
+                metric_snapshots
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let snapshots = rows
+            .into_iter()
+            .map(|row| MetricSnapshot {
+                name: row.name,
+                labels: row.labels,
+                value: row.value,
+            })
+            .collect();
+
+        Ok(snapshots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+
+    #[tokio::test]
+    async fn given_snapshot_of_same_counter_then_latest_value_is_loaded() {
+        let db = memory().await.unwrap();
+
+        let snapshot = |value| MetricSnapshot {
+            name: "cfd_volume_contracts_total".to_string(),
+            labels: r#"{"symbol":"btcusd"}"#.to_string(),
+            value,
+        };
+
+        db.upsert_metric_snapshots(&[snapshot(100.0)], Timestamp::new(1_000))
+            .await
+            .unwrap();
+        db.upsert_metric_snapshots(&[snapshot(250.0)], Timestamp::new(2_000))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            db.load_metric_snapshots().await.unwrap(),
+            vec![snapshot(250.0)]
+        );
+    }
+}
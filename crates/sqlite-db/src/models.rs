@@ -445,6 +445,38 @@ impl From<model::FundingRate> for FundingRate {
 
 impl_sqlx_type_display_from_str!(FundingRate);
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuantoMultiplier(Decimal);
+
+impl fmt::Display for QuantoMultiplier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for QuantoMultiplier {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let dec = Decimal::from_str(s)?;
+        Ok(QuantoMultiplier(dec))
+    }
+}
+
+impl From<QuantoMultiplier> for Decimal {
+    fn from(multiplier: QuantoMultiplier) -> Self {
+        multiplier.0
+    }
+}
+
+impl From<Decimal> for QuantoMultiplier {
+    fn from(multiplier: Decimal) -> Self {
+        Self(multiplier)
+    }
+}
+
+impl_sqlx_type_display_from_str!(QuantoMultiplier);
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct OpeningFee(Amount);
 
@@ -868,6 +900,73 @@ impl From<FailedKind> for model::FailedKind {
 
 impl_sqlx_type_display_from_str!(FailedKind);
 
+/// The reason for a change of the wallet balance.
+#[derive(Debug, Clone, Copy)]
+pub enum LedgerReason {
+    Deposit,
+    LockUp,
+    SettlementPayout,
+    Withdrawal,
+    ChainFee,
+}
+
+impl fmt::Display for LedgerReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            LedgerReason::Deposit => "Deposit",
+            LedgerReason::LockUp => "LockUp",
+            LedgerReason::SettlementPayout => "SettlementPayout",
+            LedgerReason::Withdrawal => "Withdrawal",
+            LedgerReason::ChainFee => "ChainFee",
+        };
+
+        s.fmt(f)
+    }
+}
+
+impl FromStr for LedgerReason {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let reason = match s {
+            "Deposit" => LedgerReason::Deposit,
+            "LockUp" => LedgerReason::LockUp,
+            "SettlementPayout" => LedgerReason::SettlementPayout,
+            "Withdrawal" => LedgerReason::Withdrawal,
+            "ChainFee" => LedgerReason::ChainFee,
+            other => bail!("Not a ledger reason: {other}"),
+        };
+
+        Ok(reason)
+    }
+}
+
+impl From<model::LedgerReason> for LedgerReason {
+    fn from(reason: model::LedgerReason) -> Self {
+        match reason {
+            model::LedgerReason::Deposit => LedgerReason::Deposit,
+            model::LedgerReason::LockUp => LedgerReason::LockUp,
+            model::LedgerReason::SettlementPayout => LedgerReason::SettlementPayout,
+            model::LedgerReason::Withdrawal => LedgerReason::Withdrawal,
+            model::LedgerReason::ChainFee => LedgerReason::ChainFee,
+        }
+    }
+}
+
+impl From<LedgerReason> for model::LedgerReason {
+    fn from(reason: LedgerReason) -> Self {
+        match reason {
+            LedgerReason::Deposit => model::LedgerReason::Deposit,
+            LedgerReason::LockUp => model::LedgerReason::LockUp,
+            LedgerReason::SettlementPayout => model::LedgerReason::SettlementPayout,
+            LedgerReason::Withdrawal => model::LedgerReason::Withdrawal,
+            LedgerReason::ChainFee => model::LedgerReason::ChainFee,
+        }
+    }
+}
+
+impl_sqlx_type_display_from_str!(LedgerReason);
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Settlement {
     Collaborative {
@@ -1008,6 +1107,30 @@ impl From<ContractSymbol> for model::ContractSymbol {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, sqlx::Type)]
+pub enum FundingPeriod {
+    Hourly,
+    EightHours,
+}
+
+impl From<model::FundingPeriod> for FundingPeriod {
+    fn from(funding_period: model::FundingPeriod) -> Self {
+        match funding_period {
+            model::FundingPeriod::Hourly => FundingPeriod::Hourly,
+            model::FundingPeriod::EightHours => FundingPeriod::EightHours,
+        }
+    }
+}
+
+impl From<FundingPeriod> for model::FundingPeriod {
+    fn from(funding_period: FundingPeriod) -> Self {
+        match funding_period {
+            FundingPeriod::Hourly => model::FundingPeriod::Hourly,
+            FundingPeriod::EightHours => model::FundingPeriod::EightHours,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct User {
     pub id: u32,
@@ -0,0 +1,174 @@
+//! Cache of the latest offers the taker received.
+//!
+//! If the maker is unreachable while the taker starts, the cached offers are shown until fresh
+//! offers arrive. There is at most one offer per contract symbol and position of the maker, like
+//! in the offers shown to the user.
+
+use crate::models;
+use crate::Connection;
+use anyhow::Context;
+use anyhow::Result;
+use model::Offer;
+use model::Timestamp;
+use sqlx::Acquire;
+use tracing::field::Empty;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedOffer {
+    pub offer: Offer,
+    pub received_at: Timestamp,
+}
+
+impl Connection {
+    /// Cache offers, replacing the cached offers with the same contract symbol and position.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "cache_offers", duration_ms = Empty)
+    )]
+    pub async fn cache_offers(&self, offers: &[Offer], received_at: Timestamp) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+        let mut db_tx = conn.begin().await?;
+
+        let received_at = models::Timestamp::from(received_at);
+
+        for offer in offers {
+            let contract_symbol = models::ContractSymbol::from(offer.contract_symbol);
+            let position_maker = models::Position::from(offer.position_maker);
+            let json = serde_json::to_string(offer).context("Failed to encode offer")?;
+
+            sqlx::query!(
+                r#"
+                INSERT OR REPLACE INTO offer_cache
+                (
+                    contract_symbol,
+                    position_maker,
+                    offer,
+                    received_at
+                )
+                VALUES ($1, $2, $3, $4)
+                "#,
+                contract_symbol,
+                position_maker,
+                json,
+                received_at,
+            )
+            .execute(&mut *db_tx)
+            .await?;
+        }
+
+        db_tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Load all cached offers.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_cached_offers", duration_ms = Empty)
+    )]
+    pub async fn load_cached_offers(&self) -> Result<Vec<CachedOffer>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                offer,
+                received_at as "received_at: models::Timestamp"
+            FROM
+                offer_cache
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let offer = serde_json::from_str(&row.offer).context("Failed to decode offer")?;
+
+                Ok(CachedOffer {
+                    offer,
+                    received_at: row.received_at.into(),
+                })
+            })
+            .collect()
+    }
+
+    /// Delete all cached offers, e.g. because the maker withdrew its offers.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "clear_offer_cache", duration_ms = Empty)
+    )]
+    pub async fn clear_offer_cache(&self) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM offer_cache
+            "#
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+    use model::Position;
+
+    #[tokio::test]
+    async fn newer_offer_replaces_cached_offer_of_same_position() {
+        let db = memory().await.unwrap();
+
+        let short = dummy_offer(Position::Short);
+        let long = dummy_offer(Position::Long);
+        let newer_short = dummy_offer(Position::Short);
+
+        db.cache_offers(&[short, long.clone()], Timestamp::new(1_000))
+            .await
+            .unwrap();
+        db.cache_offers(&[newer_short.clone()], Timestamp::new(2_000))
+            .await
+            .unwrap();
+
+        let mut cached = db.load_cached_offers().await.unwrap();
+        cached.sort_by_key(|cached| cached.received_at);
+
+        assert_eq!(
+            cached,
+            vec![
+                CachedOffer {
+                    offer: long,
+                    received_at: Timestamp::new(1_000)
+                },
+                CachedOffer {
+                    offer: newer_short,
+                    received_at: Timestamp::new(2_000)
+                }
+            ]
+        );
+
+        db.clear_offer_cache().await.unwrap();
+
+        assert!(db.load_cached_offers().await.unwrap().is_empty());
+    }
+
+    fn dummy_offer(position_maker: Position) -> Offer {
+        Offer {
+            position_maker,
+            ..Offer::dummy()
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! Append-only history of the offers published by the maker.
+//!
+//! Every revision of an offer is recorded with all its parameters and the time it was published,
+//! which allows to prove which prices were quoted at any point in time. Entries are never
+//! updated; they are only deleted once they are older than the retention period of the maker.
+
+use crate::models;
+use crate::Connection;
+use anyhow::Context;
+use anyhow::Result;
+use model::Offer;
+use model::Timestamp;
+use serde::Serialize;
+use tracing::field::Empty;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OfferRecord {
+    pub offer: Offer,
+    pub published_at: Timestamp,
+}
+
+impl Connection {
+    /// Record the publication of an offer revision.
+    ///
+    /// Returns `false` if the revision was recorded before, in which case nothing is recorded.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(
+            query = "record_offer",
+            offer_id = %record.offer.id,
+            revision = %record.offer.revision,
+            duration_ms = Empty
+        )
+    )]
+    pub async fn record_offer(&self, record: &OfferRecord) -> Result<bool> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let offer = &record.offer;
+        let offer_id = models::OfferId::from(offer.id);
+        let revision = i64::from(offer.revision);
+        let contract_symbol = models::ContractSymbol::from(offer.contract_symbol);
+        let position_maker = models::Position::from(offer.position_maker);
+        let price = models::Price::from(offer.price);
+        let json = serde_json::to_string(offer).context("Failed to encode offer")?;
+        let published_at = models::Timestamp::from(record.published_at);
+
+        let query_result = sqlx::query!(
+            r#"
+            INSERT OR IGNORE INTO offer_history
+            (
+                offer_id,
+                revision,
+                contract_symbol,
+                position_maker,
+                price,
+                offer,
+                published_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            offer_id,
+            revision,
+            contract_symbol,
+            position_maker,
+            price,
+            json,
+            published_at,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(query_result.rows_affected() == 1)
+    }
+
+    /// Load all offer revisions published in `[from, to)`, oldest first.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_offer_history", from = %from, to = %to, duration_ms = Empty)
+    )]
+    pub async fn load_offer_history(
+        &self,
+        from: Timestamp,
+        to: Timestamp,
+    ) -> Result<Vec<OfferRecord>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let from = models::Timestamp::from(from);
+        let to = models::Timestamp::from(to);
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                offer,
+                published_at as "published_at: models::Timestamp"
+            FROM
+                offer_history
+            WHERE
+                published_at >= $1 AND published_at < $2
+            ORDER BY
+                id
+            "#,
+            from,
+            to,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let offer = serde_json::from_str(&row.offer).context("Failed to decode offer")?;
+
+                Ok(OfferRecord {
+                    offer,
+                    published_at: row.published_at.into(),
+                })
+            })
+            .collect()
+    }
+
+    /// Delete all offer revisions published before `before`.
+    ///
+    /// Returns the number of deleted revisions.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "prune_offer_history", before = %before, duration_ms = Empty)
+    )]
+    pub async fn prune_offer_history(&self, before: Timestamp) -> Result<u64> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let before = models::Timestamp::from(before);
+
+        let query_result = sqlx::query!(
+            r#"
+            DELETE FROM
+                offer_history
+            WHERE
+                published_at < $1
+            "#,
+            before,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(query_result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+
+    #[tokio::test]
+    async fn every_revision_is_recorded_once() {
+        let db = memory().await.unwrap();
+
+        let offer = Offer::dummy();
+        let mut revised = offer.clone();
+        revised.revision += 1;
+
+        let first = dummy_record(offer, 1_000);
+        let second = dummy_record(revised, 2_000);
+
+        assert!(db.record_offer(&first).await.unwrap());
+        assert!(db.record_offer(&second).await.unwrap());
+        assert!(!db.record_offer(&first).await.unwrap());
+
+        let history = db
+            .load_offer_history(Timestamp::new(0), Timestamp::new(3_000))
+            .await
+            .unwrap();
+
+        assert_eq!(history, vec![first, second]);
+    }
+
+    #[tokio::test]
+    async fn only_revisions_within_range_are_loaded() {
+        let db = memory().await.unwrap();
+
+        let before = dummy_record(Offer::dummy(), 1_000);
+        let within = dummy_record(Offer::dummy(), 2_000);
+        let after = dummy_record(Offer::dummy(), 3_000);
+
+        for record in [&before, &within, &after] {
+            db.record_offer(record).await.unwrap();
+        }
+
+        let history = db
+            .load_offer_history(Timestamp::new(2_000), Timestamp::new(3_000))
+            .await
+            .unwrap();
+
+        assert_eq!(history, vec![within]);
+    }
+
+    #[tokio::test]
+    async fn pruning_deletes_revisions_published_before_cutoff() {
+        let db = memory().await.unwrap();
+
+        let old = dummy_record(Offer::dummy(), 1_000);
+        let recent = dummy_record(Offer::dummy(), 3_000);
+
+        db.record_offer(&old).await.unwrap();
+        db.record_offer(&recent).await.unwrap();
+
+        let deleted = db.prune_offer_history(Timestamp::new(2_000)).await.unwrap();
+
+        let history = db
+            .load_offer_history(Timestamp::new(0), Timestamp::new(4_000))
+            .await
+            .unwrap();
+
+        assert_eq!(deleted, 1);
+        assert_eq!(history, vec![recent]);
+    }
+
+    #[tokio::test]
+    async fn recorded_revisions_cannot_be_updated() {
+        let db = memory().await.unwrap();
+
+        db.record_offer(&dummy_record(Offer::dummy(), 1_000))
+            .await
+            .unwrap();
+
+        let mut conn = db.inner.acquire().await.unwrap();
+        let result = sqlx::query("UPDATE offer_history SET price = '1'")
+            .execute(&mut *conn)
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    fn dummy_record(offer: Offer, published_at: i64) -> OfferRecord {
+        OfferRecord {
+            offer,
+            published_at: Timestamp::new(published_at),
+        }
+    }
+}
@@ -0,0 +1,336 @@
+//! Outbox for side effects that have to be performed after an event was appended.
+//!
+//! An outbox entry is written in the same database transaction as the event it refers to. Once
+//! all side effects of the event have been performed, the entry is acknowledged and removed.
+//! Entries that were not acknowledged (e.g. because the application crashed in between) are
+//! picked up again upon startup, hence performing the side effects must be idempotent.
+//!
+//! Failed attempts are recorded with the time of the next attempt. Once the caller gives up on an
+//! entry, it is kept as failed for manual inspection but not loaded as pending anymore.
+
+use crate::insert_event;
+use crate::models;
+use crate::rollover;
+use crate::Connection;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use model::CfdEvent;
+use model::EventKind;
+use model::Timestamp;
+use sqlx::Acquire;
+use tracing::field::Empty;
+
+/// An event whose side effects have not been acknowledged yet.
+#[derive(Debug, Clone)]
+pub struct OutboxEntry {
+    pub id: i64,
+    pub event: CfdEvent,
+    /// Number of failed attempts to perform the side effects
+    pub attempts: u32,
+}
+
+impl Connection {
+    /// Appends an event to the `events` table and records it in the outbox.
+    ///
+    /// Returns the id of the outbox entry which has to be acknowledged through
+    /// [`Connection::acknowledge_outbox_entry`] once all side effects of the event were performed.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "append_event_with_outbox", order_id = %event.id, duration_ms = Empty)
+    )]
+    pub async fn append_event_with_outbox(&self, event: CfdEvent) -> Result<i64> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+        let mut db_tx = conn.begin().await?;
+
+        let event_row_id = insert_event(&mut db_tx, event).await?;
+
+        let query_result = sqlx::query!(
+            r#"
+            INSERT INTO event_outbox
+            (
+                event_id
+            )
+            VALUES ($1)
+            "#,
+            event_row_id,
+        )
+        .execute(&mut *db_tx)
+        .await?;
+
+        if query_result.rows_affected() != 1 {
+            bail!("failed to insert outbox entry");
+        }
+
+        let outbox_id = query_result.last_insert_rowid();
+
+        db_tx.commit().await?;
+
+        Ok(outbox_id)
+    }
+
+    /// Marks the side effects of an outbox entry as performed.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "acknowledge_outbox_entry", outbox_id = %id, duration_ms = Empty)
+    )]
+    pub async fn acknowledge_outbox_entry(&self, id: i64) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let query_result = sqlx::query!(
+            r#"
+            DELETE FROM
+                event_outbox
+            WHERE
+                id = $1
+            "#,
+            id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        if query_result.rows_affected() != 1 {
+            tracing::debug!(outbox_id = %id, "Outbox entry was already acknowledged");
+        }
+
+        Ok(())
+    }
+
+    /// Records a failed attempt to perform the side effects of an outbox entry.
+    ///
+    /// The entry is retried at `next_attempt_at`, or given up if it is `None`.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "record_outbox_failure", outbox_id = %id, duration_ms = Empty)
+    )]
+    pub async fn record_outbox_failure(
+        &self,
+        id: i64,
+        next_attempt_at: Option<Timestamp>,
+        now: Timestamp,
+    ) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let next_attempt_at = next_attempt_at.map(models::Timestamp::from);
+        let given_up_at = match next_attempt_at {
+            Some(_) => None,
+            None => Some(models::Timestamp::from(now)),
+        };
+
+        sqlx::query!(
+            r#"
+            UPDATE
+                event_outbox
+            SET
+                attempts = attempts + 1,
+                next_attempt_at = COALESCE($2, next_attempt_at),
+                given_up_at = $3
+            WHERE
+                id = $1
+            "#,
+            id,
+            next_attempt_at,
+            given_up_at,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Loads all outbox entries that have not been acknowledged or given up yet and are due at
+    /// `now`, oldest first.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_pending_outbox_entries", duration_ms = Empty)
+    )]
+    pub async fn load_pending_outbox_entries(&self, now: Timestamp) -> Result<Vec<OutboxEntry>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let now = models::Timestamp::from(now);
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                event_outbox.id as outbox_id,
+                event_outbox.attempts,
+                events.id as event_row_id,
+                cfds.id as cfd_row_id,
+                cfds.order_id as "order_id: models::OrderId",
+                events.name,
+                events.data,
+                events.created_at as "created_at: models::Timestamp"
+            FROM
+                event_outbox
+            JOIN
+                events on events.id = event_outbox.event_id
+            JOIN
+                cfds on cfds.id = events.cfd_id
+            WHERE
+                event_outbox.given_up_at IS NULL AND
+                event_outbox.next_attempt_at <= $1
+            ORDER BY
+                event_outbox.id
+            "#,
+            now
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut entries = Vec::with_capacity(rows.len());
+
+        for row in rows {
+            let mut event = CfdEvent {
+                timestamp: row.created_at.into(),
+                id: row.order_id.into(),
+                event: EventKind::from_json(row.name, row.data)
+                    .context("Failed to deserialize event of outbox entry")?,
+            };
+
+            if let EventKind::RolloverCompleted { .. } = event.event {
+                if let Some((dlc, funding_fee, complete_fee)) =
+                    rollover::load(&mut *conn, row.cfd_row_id, row.event_row_id).await?
+                {
+                    event.event = EventKind::RolloverCompleted {
+                        dlc: Some(dlc),
+                        funding_fee,
+                        complete_fee,
+                    }
+                }
+            }
+
+            entries.push(OutboxEntry {
+                id: row.outbox_id,
+                event,
+                attempts: row.attempts as u32,
+            });
+        }
+
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+    use crate::tests::dummy_cfd;
+    use crate::tests::lock_confirmed;
+
+    #[tokio::test]
+    async fn given_event_with_outbox_when_not_acknowledged_then_entry_pending() {
+        let db = memory().await.unwrap();
+
+        let cfd = dummy_cfd();
+        db.insert_cfd(&cfd).await.unwrap();
+
+        let outbox_id = db
+            .append_event_with_outbox(lock_confirmed(&cfd))
+            .await
+            .unwrap();
+
+        let pending = db
+            .load_pending_outbox_entries(Timestamp::now())
+            .await
+            .unwrap();
+
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, outbox_id);
+        assert_eq!(pending[0].event.id, cfd.id());
+        assert_eq!(pending[0].event.event, EventKind::LockConfirmed);
+    }
+
+    #[tokio::test]
+    async fn given_acknowledged_entry_then_no_entry_pending() {
+        let db = memory().await.unwrap();
+
+        let cfd = dummy_cfd();
+        db.insert_cfd(&cfd).await.unwrap();
+
+        let outbox_id = db
+            .append_event_with_outbox(lock_confirmed(&cfd))
+            .await
+            .unwrap();
+        db.acknowledge_outbox_entry(outbox_id).await.unwrap();
+
+        let pending = db
+            .load_pending_outbox_entries(Timestamp::now())
+            .await
+            .unwrap();
+
+        assert!(pending.is_empty());
+    }
+
+    #[tokio::test]
+    async fn given_failed_entry_then_pending_again_when_due_until_given_up() {
+        let db = memory().await.unwrap();
+
+        let cfd = dummy_cfd();
+        db.insert_cfd(&cfd).await.unwrap();
+
+        let outbox_id = db
+            .append_event_with_outbox(lock_confirmed(&cfd))
+            .await
+            .unwrap();
+
+        db.record_outbox_failure(
+            outbox_id,
+            Some(Timestamp::new(2_000)),
+            Timestamp::new(1_000),
+        )
+        .await
+        .unwrap();
+
+        let before_due = db
+            .load_pending_outbox_entries(Timestamp::new(1_999))
+            .await
+            .unwrap();
+        let due = db
+            .load_pending_outbox_entries(Timestamp::new(2_000))
+            .await
+            .unwrap();
+
+        assert!(before_due.is_empty());
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].attempts, 1);
+
+        db.record_outbox_failure(outbox_id, None, Timestamp::new(2_000))
+            .await
+            .unwrap();
+
+        let given_up = db
+            .load_pending_outbox_entries(Timestamp::new(i64::MAX))
+            .await
+            .unwrap();
+
+        assert!(given_up.is_empty());
+    }
+
+    #[tokio::test]
+    async fn given_event_without_outbox_then_no_entry_pending() {
+        let db = memory().await.unwrap();
+
+        let cfd = dummy_cfd();
+        db.insert_cfd(&cfd).await.unwrap();
+        db.append_event(lock_confirmed(&cfd)).await.unwrap();
+
+        let pending = db
+            .load_pending_outbox_entries(Timestamp::now())
+            .await
+            .unwrap();
+
+        assert!(pending.is_empty());
+    }
+}
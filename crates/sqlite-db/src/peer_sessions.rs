@@ -0,0 +1,294 @@
+//! Connection sessions of takers with the maker.
+//!
+//! A session is started when a taker connects and ended when the connection is dropped. The
+//! daemon version is only filled in once the taker identified itself. Sessions are the basis of
+//! the analytics about connected takers the maker exposes.
+
+use crate::models;
+use crate::Connection;
+use anyhow::Result;
+use model::libp2p::PeerId;
+use model::Timestamp;
+use std::collections::HashMap;
+use tracing::field::Empty;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerSession {
+    pub peer_id: PeerId,
+    pub address: String,
+    pub daemon_version: Option<String>,
+    pub connected_at: Timestamp,
+    /// `None` while the session is ongoing
+    pub disconnected_at: Option<Timestamp>,
+}
+
+impl Connection {
+    /// Start a session of a connected peer.
+    ///
+    /// Returns the id of the session to refer to it when the peer identified itself or
+    /// disconnected.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "start_peer_session", %peer_id, duration_ms = Empty)
+    )]
+    pub async fn start_peer_session(
+        &self,
+        peer_id: PeerId,
+        address: &str,
+        connected_at: Timestamp,
+    ) -> Result<i64> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let peer_id = models::PeerId::from(peer_id);
+        let connected_at = models::Timestamp::from(connected_at);
+
+        let query_result = sqlx::query!(
+            r#"
+            INSERT INTO peer_sessions
+            (
+                peer_id,
+                address,
+                connected_at
+            )
+            VALUES ($1, $2, $3)
+            "#,
+            peer_id,
+            address,
+            connected_at,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(query_result.last_insert_rowid())
+    }
+
+    /// Record the daemon version the peer of a session identified itself with.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "set_peer_session_version", %id, duration_ms = Empty)
+    )]
+    pub async fn set_peer_session_version(&self, id: i64, daemon_version: &str) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE peer_sessions
+            SET
+                daemon_version = $1
+            WHERE
+                id = $2
+            "#,
+            daemon_version,
+            id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// End a session because the peer disconnected.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "end_peer_session", %id, duration_ms = Empty)
+    )]
+    pub async fn end_peer_session(&self, id: i64, disconnected_at: Timestamp) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let disconnected_at = models::Timestamp::from(disconnected_at);
+
+        sqlx::query!(
+            r#"
+            UPDATE peer_sessions
+            SET
+                disconnected_at = $1
+            WHERE
+                id = $2 AND disconnected_at IS NULL
+            "#,
+            disconnected_at,
+            id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// End all sessions which are still ongoing, e.g. because the maker was shut down while peers
+    /// were connected.
+    ///
+    /// Returns the number of ended sessions.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "end_open_peer_sessions", duration_ms = Empty)
+    )]
+    pub async fn end_open_peer_sessions(&self, disconnected_at: Timestamp) -> Result<u64> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let disconnected_at = models::Timestamp::from(disconnected_at);
+
+        let query_result = sqlx::query!(
+            r#"
+            UPDATE peer_sessions
+            SET
+                disconnected_at = $1
+            WHERE
+                disconnected_at IS NULL
+            "#,
+            disconnected_at,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(query_result.rows_affected())
+    }
+
+    /// Load all sessions that were ongoing at or after `since`, oldest first.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_peer_sessions", since = %since, duration_ms = Empty)
+    )]
+    pub async fn load_peer_sessions(&self, since: Timestamp) -> Result<Vec<PeerSession>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let since = models::Timestamp::from(since);
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                peer_id as "peer_id: models::PeerId",
+                address,
+                daemon_version,
+                connected_at as "connected_at: models::Timestamp",
+                disconnected_at as "disconnected_at: models::Timestamp"
+            FROM
+                peer_sessions
+            WHERE
+                disconnected_at IS NULL OR disconnected_at >= $1
+            ORDER BY
+                connected_at ASC
+            "#,
+            since,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let sessions = rows
+            .into_iter()
+            .map(|row| PeerSession {
+                peer_id: row.peer_id.into(),
+                address: row.address,
+                daemon_version: row.daemon_version,
+                connected_at: row.connected_at.into(),
+                disconnected_at: row.disconnected_at.map(Into::into),
+            })
+            .collect();
+
+        Ok(sessions)
+    }
+
+    /// The time every peer connected for the first time.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_first_peer_connections", duration_ms = Empty)
+    )]
+    pub async fn load_first_peer_connections(&self) -> Result<HashMap<PeerId, Timestamp>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                peer_id as "peer_id: models::PeerId",
+                MIN(connected_at) as "first_connected_at!: models::Timestamp"
+            FROM
+                peer_sessions
+            GROUP BY
+                peer_id
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let first_connections = rows
+            .into_iter()
+            .map(|row| (row.peer_id.into(), row.first_connected_at.into()))
+            .collect();
+
+        Ok(first_connections)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+
+    #[tokio::test]
+    async fn given_sessions_then_ongoing_and_recent_ones_are_loaded() {
+        let db = memory().await.unwrap();
+
+        let peer_id = PeerId::random();
+        let address = "/ip4/127.0.0.1/tcp/10000";
+
+        let old = db
+            .start_peer_session(peer_id, address, Timestamp::new(1_000))
+            .await
+            .unwrap();
+        db.end_peer_session(old, Timestamp::new(2_000))
+            .await
+            .unwrap();
+
+        let ongoing = db
+            .start_peer_session(peer_id, address, Timestamp::new(5_000))
+            .await
+            .unwrap();
+        db.set_peer_session_version(ongoing, "0.7.0").await.unwrap();
+
+        let sessions = db.load_peer_sessions(Timestamp::new(3_000)).await.unwrap();
+
+        assert_eq!(
+            sessions,
+            vec![PeerSession {
+                peer_id,
+                address: address.to_string(),
+                daemon_version: Some("0.7.0".to_string()),
+                connected_at: Timestamp::new(5_000),
+                disconnected_at: None,
+            }]
+        );
+        assert_eq!(
+            db.load_first_peer_connections().await.unwrap(),
+            HashMap::from([(peer_id, Timestamp::new(1_000))])
+        );
+
+        assert_eq!(
+            db.end_open_peer_sessions(Timestamp::new(6_000))
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(db
+            .load_peer_sessions(Timestamp::new(7_000))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,152 @@
+//! Failure reports of protocols sent by the counterparty.
+//!
+//! Reports are anonymized: they only state which step of which CFD failed, a coarse category of
+//! the error and the version of the reporting daemon.
+
+use crate::models;
+use crate::Connection;
+use anyhow::Result;
+use model::libp2p::PeerId;
+use model::OrderId;
+use model::Timestamp;
+use tracing::field::Empty;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtocolFailure {
+    pub peer_id: PeerId,
+    pub order_id: OrderId,
+    pub step: String,
+    pub category: String,
+    pub daemon_version: String,
+    pub timestamp: Timestamp,
+}
+
+impl Connection {
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(
+            query = "insert_protocol_failure",
+            order_id = %failure.order_id,
+            duration_ms = Empty
+        )
+    )]
+    pub async fn insert_protocol_failure(&self, failure: &ProtocolFailure) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let peer_id = models::PeerId::from(failure.peer_id);
+        let order_id = models::OrderId::from(failure.order_id);
+        let timestamp = models::Timestamp::from(failure.timestamp);
+
+        sqlx::query!(
+            r#"
+            INSERT INTO protocol_failures
+            (
+                peer_id,
+                order_id,
+                step,
+                category,
+                daemon_version,
+                timestamp
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            peer_id,
+            order_id,
+            failure.step,
+            failure.category,
+            failure.daemon_version,
+            timestamp,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Load all failure reports received since `since`, newest first.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_protocol_failures", since = %since, duration_ms = Empty)
+    )]
+    pub async fn load_protocol_failures(&self, since: Timestamp) -> Result<Vec<ProtocolFailure>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let since = models::Timestamp::from(since);
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                peer_id as "peer_id: models::PeerId",
+                order_id as "order_id: models::OrderId",
+                step,
+                category,
+                daemon_version,
+                timestamp as "timestamp: models::Timestamp"
+            FROM
+                protocol_failures
+            WHERE
+                timestamp >= $1
+            ORDER BY
+                id DESC
+            "#,
+            since,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let failures = rows
+            .into_iter()
+            .map(|row| ProtocolFailure {
+                peer_id: row.peer_id.into(),
+                order_id: row.order_id.into(),
+                step: row.step,
+                category: row.category,
+                daemon_version: row.daemon_version,
+                timestamp: row.timestamp.into(),
+            })
+            .collect();
+
+        Ok(failures)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+
+    #[tokio::test]
+    async fn only_failures_since_given_timestamp_are_loaded() {
+        let db = memory().await.unwrap();
+
+        let old = dummy_failure(1_000);
+        let recent = dummy_failure(3_000);
+
+        db.insert_protocol_failure(&old).await.unwrap();
+        db.insert_protocol_failure(&recent).await.unwrap();
+
+        let failures = db
+            .load_protocol_failures(Timestamp::new(2_000))
+            .await
+            .unwrap();
+
+        assert_eq!(failures, vec![recent]);
+    }
+
+    fn dummy_failure(timestamp: i64) -> ProtocolFailure {
+        ProtocolFailure {
+            peer_id: PeerId::random(),
+            order_id: OrderId::default(),
+            step: "rollover".to_owned(),
+            category: "timeout".to_owned(),
+            daemon_version: "0.7.0".to_owned(),
+            timestamp: Timestamp::new(timestamp),
+        }
+    }
+}
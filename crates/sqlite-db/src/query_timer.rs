@@ -0,0 +1,58 @@
+//! Tracing of database queries.
+//!
+//! Every query of a [`Connection`] runs within a `Database query` span that carries the name of
+//! the query and its (non-sensitive) parameters. Once the query finished, its duration is recorded
+//! on the span and queries that took longer than the slow query threshold are logged.
+
+use crate::Connection;
+use std::time::Duration;
+use std::time::Instant;
+use tracing::Span;
+
+/// Queries taking longer than this are logged as slow queries, unless configured otherwise
+pub const DEFAULT_SLOW_QUERY_THRESHOLD: Duration = Duration::from_millis(500);
+
+/// Measures the duration of a query until it is dropped.
+///
+/// Has to be created within the span of the query. The span is expected to declare an empty
+/// `duration_ms` field.
+pub(crate) struct QueryTimer {
+    start: Instant,
+    slow_query_threshold: Duration,
+    span: Span,
+}
+
+impl Drop for QueryTimer {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        let duration_ms = elapsed.as_millis() as u64;
+
+        self.span.record("duration_ms", duration_ms);
+
+        if elapsed > self.slow_query_threshold {
+            let threshold_ms = self.slow_query_threshold.as_millis() as u64;
+
+            self.span.in_scope(|| {
+                tracing::warn!(duration_ms, threshold_ms, "Slow database query");
+            });
+        }
+    }
+}
+
+impl Connection {
+    /// Log queries that take longer than the given threshold.
+    ///
+    /// Defaults to [`DEFAULT_SLOW_QUERY_THRESHOLD`].
+    pub fn with_slow_query_threshold(mut self, slow_query_threshold: Duration) -> Self {
+        self.slow_query_threshold = slow_query_threshold;
+        self
+    }
+
+    pub(crate) fn query_timer(&self) -> QueryTimer {
+        QueryTimer {
+            start: Instant::now(),
+            slow_query_threshold: self.slow_query_threshold,
+            span: Span::current(),
+        }
+    }
+}
@@ -23,6 +23,7 @@ mod tests {
     use model::Dlc;
     use model::EventKind;
     use model::FundingFee;
+    use model::FundingPeriod;
     use model::FundingRate;
     use model::Leverage;
     use model::OfferId;
@@ -57,6 +58,8 @@ mod tests {
             FundingRate::default(),
             TxFeeRate::default(),
             ContractSymbol::BtcUsd,
+            FundingPeriod::Hourly,
+            None,
         )
     }
 
@@ -0,0 +1,245 @@
+//! Feature flags for migrations that are rolled out without downtime.
+//!
+//! A migration that restructures existing data is split in two steps. The regular migration only
+//! adds the new tables or columns and leaves the legacy ones in place. While the migration's flag
+//! is in state [`FlagState::DualWrite`] the code writes both structures and keeps reading the
+//! legacy ones, hence older releases can still run against the database.
+//!
+//! Once all instances run a release that knows about the new structures, the migration is
+//! finalized with the `finalize-migration` command. It verifies that both structures agree, drops
+//! the legacy structures and flips the flag to [`FlagState::Finalized`], after which only the new
+//! structures are written and read.
+
+use crate::Connection;
+use anyhow::bail;
+use anyhow::ensure;
+use anyhow::Context;
+use anyhow::Result;
+use model::Timestamp;
+use sqlx::Acquire;
+use tracing::field::Empty;
+
+/// A migration that is rolled out with dual writes
+#[derive(Debug)]
+pub struct FlaggedMigration {
+    /// Name of the flag, used to finalize the migration
+    pub name: &'static str,
+    /// Query returning the number of rows in which legacy and new structures disagree
+    ///
+    /// The migration is only finalized if this returns zero.
+    pub verify: &'static str,
+    /// Statements that drop the legacy structures
+    pub drop_legacy: &'static [&'static str],
+}
+
+/// All migrations that are rolled out with dual writes
+///
+/// Add an entry when introducing a migration that keeps legacy structures around. Entries can be
+/// removed once the migration was finalized everywhere.
+pub const FLAGGED_MIGRATIONS: &[FlaggedMigration] = &[];
+
+/// Look up a flagged migration by its name.
+pub fn flagged_migration(name: &str) -> Option<&'static FlaggedMigration> {
+    FLAGGED_MIGRATIONS
+        .iter()
+        .find(|migration| migration.name == name)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(rename_all = "snake_case")]
+pub enum FlagState {
+    /// Legacy and new structures are written, the legacy structures are read
+    DualWrite,
+    /// The legacy structures are dropped, only the new structures are written and read
+    Finalized,
+}
+
+impl FlagState {
+    /// Whether the legacy structures still have to be written.
+    pub fn writes_legacy(&self) -> bool {
+        *self == FlagState::DualWrite
+    }
+}
+
+impl Connection {
+    /// Load the state of the flag of a migration.
+    ///
+    /// Migrations that were never finalized are in state [`FlagState::DualWrite`].
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_schema_flag", %name, duration_ms = Empty)
+    )]
+    pub async fn load_schema_flag(&self, name: &str) -> Result<FlagState> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let state = sqlx::query_scalar!(
+            r#"
+            SELECT
+                state as "state: FlagState"
+            FROM
+                schema_flags
+            WHERE
+                name = $1
+            "#,
+            name,
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(state.unwrap_or(FlagState::DualWrite))
+    }
+
+    /// Verify that legacy and new structures of a migration agree, drop the legacy structures and
+    /// flip the flag of the migration.
+    ///
+    /// All of it happens in a single transaction: if verification fails nothing is changed.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "finalize_migration", name = %migration.name, duration_ms = Empty)
+    )]
+    pub async fn finalize_migration(&self, migration: &FlaggedMigration) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+        let mut db_tx = conn.begin().await?;
+
+        let name = migration.name;
+
+        let state = sqlx::query_scalar!(
+            r#"
+            SELECT
+                state as "state: FlagState"
+            FROM
+                schema_flags
+            WHERE
+                name = $1
+            "#,
+            name,
+        )
+        .fetch_optional(&mut *db_tx)
+        .await?;
+
+        if state == Some(FlagState::Finalized) {
+            bail!("Migration {name} was already finalized");
+        }
+
+        let mismatches = sqlx::query_scalar::<_, i64>(migration.verify)
+            .fetch_one(&mut *db_tx)
+            .await
+            .with_context(|| format!("Failed to verify migration {name}"))?;
+        ensure!(
+            mismatches == 0,
+            "Legacy and new structures of migration {name} disagree in {mismatches} rows"
+        );
+
+        for statement in migration.drop_legacy {
+            sqlx::query(statement)
+                .execute(&mut *db_tx)
+                .await
+                .with_context(|| format!("Failed to drop legacy structures of {name}"))?;
+        }
+
+        let finalized = FlagState::Finalized;
+        let updated_at = Timestamp::now().seconds();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO schema_flags
+            (
+                name,
+                state,
+                updated_at
+            )
+            VALUES ($1, $2, $3)
+            ON CONFLICT (name) DO UPDATE SET
+                state = $2,
+                updated_at = $3
+            "#,
+            name,
+            finalized,
+            updated_at,
+        )
+        .execute(&mut *db_tx)
+        .await?;
+
+        db_tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+
+    const MIGRATION: FlaggedMigration = FlaggedMigration {
+        name: "test_dual_write",
+        verify:
+            "SELECT COUNT(*) FROM legacy_values WHERE value NOT IN (SELECT value FROM new_values)",
+        drop_legacy: &["DROP TABLE legacy_values"],
+    };
+
+    #[tokio::test]
+    async fn given_structures_agree_when_finalizing_then_legacy_dropped() {
+        let db = setup(&[1, 2], &[1, 2]).await;
+
+        assert_eq!(
+            db.load_schema_flag(MIGRATION.name).await.unwrap(),
+            FlagState::DualWrite
+        );
+
+        db.finalize_migration(&MIGRATION).await.unwrap();
+
+        assert_eq!(
+            db.load_schema_flag(MIGRATION.name).await.unwrap(),
+            FlagState::Finalized
+        );
+        assert!(sqlx::query("SELECT * FROM legacy_values")
+            .execute(&db.inner)
+            .await
+            .is_err());
+        assert!(db.finalize_migration(&MIGRATION).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn given_structures_disagree_when_finalizing_then_nothing_changes() {
+        let db = setup(&[1, 2], &[1]).await;
+
+        assert!(db.finalize_migration(&MIGRATION).await.is_err());
+
+        assert_eq!(
+            db.load_schema_flag(MIGRATION.name).await.unwrap(),
+            FlagState::DualWrite
+        );
+        assert!(sqlx::query("SELECT * FROM legacy_values")
+            .execute(&db.inner)
+            .await
+            .is_ok());
+    }
+
+    async fn setup(legacy: &[i64], new: &[i64]) -> Connection {
+        let db = memory().await.unwrap();
+
+        for (table, values) in [("legacy_values", legacy), ("new_values", new)] {
+            sqlx::query(&format!("CREATE TABLE {table} (value integer NOT NULL)"))
+                .execute(&db.inner)
+                .await
+                .unwrap();
+
+            for value in values {
+                sqlx::query(&format!("INSERT INTO {table} (value) VALUES ($1)"))
+                    .bind(value)
+                    .execute(&db.inner)
+                    .await
+                    .unwrap();
+            }
+        }
+
+        db
+    }
+}
@@ -0,0 +1,155 @@
+//! Local store of anonymous usage events.
+//!
+//! Only the category of an event and when it happened are stored, never a reference to the CFD it
+//! originates from. Events are pruned once they are older than the telemetry retention period.
+
+use crate::models;
+use crate::Connection;
+use anyhow::Result;
+use model::Timestamp;
+use tracing::field::Empty;
+
+impl Connection {
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "insert_telemetry_event", category = %category, duration_ms = Empty)
+    )]
+    pub async fn insert_telemetry_event(
+        &self,
+        category: &str,
+        created_at: Timestamp,
+    ) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let created_at = models::Timestamp::from(created_at);
+
+        sqlx::query!(
+            r#"
+            INSERT INTO telemetry_events
+            (
+                category,
+                created_at
+            )
+            VALUES ($1, $2)
+            "#,
+            category,
+            created_at,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Number of telemetry events per category since `since`, ordered by category.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_telemetry_counts", duration_ms = Empty)
+    )]
+    pub async fn load_telemetry_counts(&self, since: Timestamp) -> Result<Vec<(String, u64)>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let since = models::Timestamp::from(since);
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                category,
+                COUNT(*) AS "count!: i64"
+            FROM
+                telemetry_events
+            WHERE
+                created_at >= $1
+            GROUP BY
+                category
+            ORDER BY
+                category
+            "#,
+            since,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let counts = rows
+            .into_iter()
+            .map(|row| (row.category, row.count as u64))
+            .collect();
+
+        Ok(counts)
+    }
+
+    /// Delete all telemetry events that happened before `before`.
+    ///
+    /// Returns the number of deleted events.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "prune_telemetry_events", before = %before, duration_ms = Empty)
+    )]
+    pub async fn prune_telemetry_events(&self, before: Timestamp) -> Result<u64> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let before = models::Timestamp::from(before);
+
+        let query_result = sqlx::query!(
+            r#"
+            DELETE FROM
+                telemetry_events
+            WHERE
+                created_at < $1
+            "#,
+            before,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(query_result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+
+    #[tokio::test]
+    async fn counts_only_include_events_since_and_pruned_events_are_gone() {
+        let db = memory().await.unwrap();
+
+        db.insert_telemetry_event("rollover", Timestamp::new(1_000))
+            .await
+            .unwrap();
+        db.insert_telemetry_event("rollover", Timestamp::new(2_000))
+            .await
+            .unwrap();
+        db.insert_telemetry_event("trade_opened", Timestamp::new(2_000))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            db.load_telemetry_counts(Timestamp::new(1_500))
+                .await
+                .unwrap(),
+            vec![("rollover".to_string(), 1), ("trade_opened".to_string(), 1)]
+        );
+
+        assert_eq!(
+            db.prune_telemetry_events(Timestamp::new(1_500))
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            db.load_telemetry_counts(Timestamp::new(0)).await.unwrap(),
+            vec![("rollover".to_string(), 1), ("trade_opened".to_string(), 1)]
+        );
+    }
+}
@@ -3,6 +3,7 @@ use crate::Connection;
 use anyhow::Result;
 use model::Identity;
 use time::OffsetDateTime;
+use tracing::field::Empty;
 
 impl Connection {
     /// Record the time at which we hear about a `taker_id` for the
@@ -10,11 +11,18 @@ impl Connection {
     ///
     /// If we have already heard about the `taker_id`, we keep the
     /// original `timestamp`.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "try_insert_first_seen", taker_id = %taker_id, duration_ms = Empty)
+    )]
     pub async fn try_insert_first_seen(
         &self,
         taker_id: Identity,
         timestamp: OffsetDateTime,
     ) -> Result<()> {
+        let _timer = self.query_timer();
+
         let mut conn = self.inner.acquire().await?;
         let taker_id = models::Identity::from(taker_id);
 
@@ -43,11 +51,18 @@ impl Connection {
     ///
     /// If we have already heard about the taker opening a position
     /// before, we keep the original `timestamp`.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "try_insert_first_position", taker_id = %taker_id, duration_ms = Empty)
+    )]
     pub async fn try_insert_first_position(
         &self,
         taker_id: Identity,
         timestamp: OffsetDateTime,
     ) -> Result<()> {
+        let _timer = self.query_timer();
+
         let mut conn = self.inner.acquire().await?;
 
         let timestamp = timestamp.unix_timestamp();
@@ -1,12 +1,20 @@
 use crate::models::User;
 use crate::Connection;
 use anyhow::Result;
+use tracing::field::Empty;
 
 // we only want to have max 1 user, hence, we hardcode its ID to 1
 const USER_ID: u8 = 1;
 
 impl Connection {
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_user", duration_ms = Empty)
+    )]
     pub async fn load_user(self) -> Result<Option<User>> {
+        let _timer = self.query_timer();
+
         let mut conn = self.inner.acquire().await?;
         let row = sqlx::query!(
             r#"
@@ -27,7 +35,14 @@ impl Connection {
         }
     }
 
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "update_password", duration_ms = Empty)
+    )]
     pub async fn update_password(self, password: String) -> Result<()> {
+        let _timer = self.query_timer();
+
         let mut conn = self.inner.acquire().await?;
         sqlx::query!(
             r#"
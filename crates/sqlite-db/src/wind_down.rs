@@ -0,0 +1,112 @@
+//! The wind-down of the maker.
+//!
+//! A started wind-down is persisted so that the maker resumes it after a restart: offers stay
+//! withdrawn, rollovers stay disabled and the grace period keeps its original deadline.
+
+use crate::models;
+use crate::Connection;
+use anyhow::Result;
+use model::Timestamp;
+use tracing::field::Empty;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindDown {
+    pub started_at: Timestamp,
+    /// End of the grace period for collaborative settlement
+    pub deadline: Timestamp,
+}
+
+impl Connection {
+    /// Record the start of the wind-down.
+    ///
+    /// Fails if a wind-down was already started, it cannot be started twice.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "insert_wind_down", duration_ms = Empty)
+    )]
+    pub async fn insert_wind_down(&self, wind_down: WindDown) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let started_at = models::Timestamp::from(wind_down.started_at);
+        let deadline = models::Timestamp::from(wind_down.deadline);
+
+        sqlx::query!(
+            r#"
+            INSERT INTO wind_down
+            (
+                id,
+                started_at,
+                deadline
+            )
+            VALUES (1, $1, $2)
+            "#,
+            started_at,
+            deadline,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_wind_down", duration_ms = Empty)
+    )]
+    pub async fn load_wind_down(&self) -> Result<Option<WindDown>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                started_at as "started_at: models::Timestamp",
+                deadline as "deadline: models::Timestamp"
+            FROM
+                wind_down
+            WHERE
+                id = 1
+            "#
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(row.map(|row| WindDown {
+            started_at: row.started_at.into(),
+            deadline: row.deadline.into(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+
+    #[tokio::test]
+    async fn given_wind_down_started_then_it_is_loaded_and_cannot_be_started_again() {
+        let db = memory().await.unwrap();
+        assert_eq!(db.load_wind_down().await.unwrap(), None);
+
+        let wind_down = WindDown {
+            started_at: Timestamp::new(1_000),
+            deadline: Timestamp::new(4_600),
+        };
+        db.insert_wind_down(wind_down).await.unwrap();
+
+        assert_eq!(db.load_wind_down().await.unwrap(), Some(wind_down));
+        assert!(db
+            .insert_wind_down(WindDown {
+                started_at: Timestamp::new(2_000),
+                deadline: Timestamp::new(5_600),
+            })
+            .await
+            .is_err());
+        assert_eq!(db.load_wind_down().await.unwrap(), Some(wind_down));
+    }
+}
@@ -0,0 +1,623 @@
+//! Withdrawals requested through the maker's API.
+//!
+//! Withdrawals above a threshold stay pending until they are approved with a time-based one-time
+//! password. Every change of a request, as well as every failed approval attempt, is recorded in
+//! an audit log.
+
+use crate::models;
+use crate::Connection;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use bdk::bitcoin::Address;
+use bdk::bitcoin::Amount;
+use bdk::bitcoin::Txid;
+use model::Timestamp;
+use serde::Serialize;
+use sqlx::Acquire;
+use std::fmt;
+use std::str::FromStr;
+use tracing::field::Empty;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WithdrawalRequest {
+    pub id: i64,
+    /// `None` if the whole balance is withdrawn
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat::opt")]
+    pub amount: Option<Amount>,
+    pub address: Address,
+    /// Fee rate in satoshis per vbyte
+    pub fee_rate: f32,
+    pub status: WithdrawalStatus,
+    pub created_at: Timestamp,
+    pub expires_at: Timestamp,
+    pub txid: Option<Txid>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WithdrawalStatus {
+    /// Waiting for approval
+    Pending,
+    /// The withdrawal transaction was broadcast
+    Completed,
+    /// Not approved in time
+    Expired,
+    /// Approved, but the withdrawal transaction could not be broadcast
+    Failed,
+    /// Too many approval attempts with an invalid one-time password
+    Denied,
+}
+
+/// The secret for approving withdrawals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TotpSecret {
+    pub secret: Vec<u8>,
+    /// The time step of the last accepted one-time password, `None` if none was accepted yet
+    pub last_used_step: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WithdrawalAuditEntry {
+    pub request_id: i64,
+    pub action: WithdrawalAction,
+    pub detail: Option<String>,
+    pub timestamp: Timestamp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WithdrawalAction {
+    Requested,
+    Approved,
+    /// An approval attempt with an invalid one-time password
+    ApprovalDenied,
+    /// The request was denied after too many invalid approval attempts
+    Denied,
+    Expired,
+    Broadcast,
+    Failed,
+}
+
+impl fmt::Display for WithdrawalStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            WithdrawalStatus::Pending => "pending",
+            WithdrawalStatus::Completed => "completed",
+            WithdrawalStatus::Expired => "expired",
+            WithdrawalStatus::Failed => "failed",
+            WithdrawalStatus::Denied => "denied",
+        };
+
+        s.fmt(f)
+    }
+}
+
+impl FromStr for WithdrawalStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let status = match s {
+            "pending" => WithdrawalStatus::Pending,
+            "completed" => WithdrawalStatus::Completed,
+            "expired" => WithdrawalStatus::Expired,
+            "failed" => WithdrawalStatus::Failed,
+            "denied" => WithdrawalStatus::Denied,
+            other => bail!("Not a withdrawal status: {other}"),
+        };
+
+        Ok(status)
+    }
+}
+
+impl fmt::Display for WithdrawalAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            WithdrawalAction::Requested => "requested",
+            WithdrawalAction::Approved => "approved",
+            WithdrawalAction::ApprovalDenied => "approval_denied",
+            WithdrawalAction::Denied => "denied",
+            WithdrawalAction::Expired => "expired",
+            WithdrawalAction::Broadcast => "broadcast",
+            WithdrawalAction::Failed => "failed",
+        };
+
+        s.fmt(f)
+    }
+}
+
+impl FromStr for WithdrawalAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let action = match s {
+            "requested" => WithdrawalAction::Requested,
+            "approved" => WithdrawalAction::Approved,
+            "approval_denied" => WithdrawalAction::ApprovalDenied,
+            "denied" => WithdrawalAction::Denied,
+            "expired" => WithdrawalAction::Expired,
+            "broadcast" => WithdrawalAction::Broadcast,
+            "failed" => WithdrawalAction::Failed,
+            other => bail!("Not a withdrawal action: {other}"),
+        };
+
+        Ok(action)
+    }
+}
+
+impl Connection {
+    /// Record a new withdrawal request.
+    ///
+    /// Returns the id of the request.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "insert_withdrawal_request", duration_ms = Empty)
+    )]
+    pub async fn insert_withdrawal_request(
+        &self,
+        amount: Option<Amount>,
+        address: &Address,
+        fee_rate: f32,
+        created_at: Timestamp,
+        expires_at: Timestamp,
+    ) -> Result<i64> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+        let mut db_tx = conn.begin().await?;
+
+        let amount = amount.map(|amount| amount.to_sat() as i64);
+        let address = address.to_string();
+        let status = WithdrawalStatus::Pending.to_string();
+        let created_at = models::Timestamp::from(created_at);
+        let expires_at = models::Timestamp::from(expires_at);
+
+        let query_result = sqlx::query!(
+            r#"
+            INSERT INTO withdrawal_requests
+            (
+                amount,
+                address,
+                fee_rate,
+                status,
+                created_at,
+                expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            amount,
+            address,
+            fee_rate,
+            status,
+            created_at,
+            expires_at,
+        )
+        .execute(&mut *db_tx)
+        .await?;
+        let id = query_result.last_insert_rowid();
+
+        let action = WithdrawalAction::Requested.to_string();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO withdrawal_audit_log
+            (
+                request_id,
+                action,
+                timestamp
+            )
+            VALUES ($1, $2, $3)
+            "#,
+            id,
+            action,
+            created_at,
+        )
+        .execute(&mut *db_tx)
+        .await?;
+
+        db_tx.commit().await?;
+
+        Ok(id)
+    }
+
+    /// Change the status of a withdrawal request together with recording the reason in the audit
+    /// log.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "update_withdrawal_request", id = entry.request_id, %status, duration_ms = Empty)
+    )]
+    pub async fn update_withdrawal_request(
+        &self,
+        status: WithdrawalStatus,
+        txid: Option<Txid>,
+        entry: &WithdrawalAuditEntry,
+    ) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+        let mut db_tx = conn.begin().await?;
+
+        let status = status.to_string();
+        let txid = txid.map(models::Txid::from);
+
+        sqlx::query!(
+            r#"
+            UPDATE withdrawal_requests
+            SET
+                status = $1,
+                txid = $2
+            WHERE
+                id = $3
+            "#,
+            status,
+            txid,
+            entry.request_id,
+        )
+        .execute(&mut *db_tx)
+        .await?;
+
+        insert_audit_entry(&mut db_tx, entry).await?;
+
+        db_tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Record an event that does not change the status of a withdrawal request.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "insert_withdrawal_audit_entry", id = entry.request_id, duration_ms = Empty)
+    )]
+    pub async fn insert_withdrawal_audit_entry(&self, entry: &WithdrawalAuditEntry) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        insert_audit_entry(&mut conn, entry).await?;
+
+        Ok(())
+    }
+
+    /// Load all withdrawal requests, oldest first.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_withdrawal_requests", duration_ms = Empty)
+    )]
+    pub async fn load_withdrawal_requests(&self) -> Result<Vec<WithdrawalRequest>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                id as "id!",
+                amount,
+                address,
+                fee_rate,
+                status,
+                created_at as "created_at: models::Timestamp",
+                expires_at as "expires_at: models::Timestamp",
+                txid as "txid: models::Txid"
+            FROM
+                withdrawal_requests
+            ORDER BY
+                id ASC
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(WithdrawalRequest {
+                    id: row.id,
+                    amount: row.amount.map(|amount| Amount::from_sat(amount as u64)),
+                    address: row
+                        .address
+                        .parse()
+                        .context("Invalid address of withdrawal request")?,
+                    fee_rate: row.fee_rate as f32,
+                    status: row.status.parse()?,
+                    created_at: row.created_at.into(),
+                    expires_at: row.expires_at.into(),
+                    txid: row.txid.map(Into::into),
+                })
+            })
+            .collect()
+    }
+
+    /// Load a single withdrawal request.
+    pub async fn load_withdrawal_request(&self, id: i64) -> Result<WithdrawalRequest> {
+        self.load_withdrawal_requests()
+            .await?
+            .into_iter()
+            .find(|request| request.id == id)
+            .with_context(|| format!("Withdrawal request {id} not found"))
+    }
+
+    /// Load the entire audit log, oldest first.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_withdrawal_audit_log", duration_ms = Empty)
+    )]
+    pub async fn load_withdrawal_audit_log(&self) -> Result<Vec<WithdrawalAuditEntry>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                request_id,
+                action,
+                detail,
+                timestamp as "timestamp: models::Timestamp"
+            FROM
+                withdrawal_audit_log
+            ORDER BY
+                id ASC
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(WithdrawalAuditEntry {
+                    request_id: row.request_id,
+                    action: row.action.parse()?,
+                    detail: row.detail,
+                    timestamp: row.timestamp.into(),
+                })
+            })
+            .collect()
+    }
+
+    /// Store the secret for approving withdrawals, replacing the previous one.
+    ///
+    /// Steps used with the previous secret say nothing about the new one and are forgotten.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "insert_totp_secret", duration_ms = Empty)
+    )]
+    pub async fn insert_totp_secret(&self, secret: &[u8], created_at: Timestamp) -> Result<()> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let created_at = models::Timestamp::from(created_at);
+
+        sqlx::query!(
+            r#"
+            INSERT OR REPLACE INTO totp_secret
+            (
+                id,
+                secret,
+                created_at
+            )
+            VALUES (1, $1, $2)
+            "#,
+            secret,
+            created_at,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "load_totp_secret", duration_ms = Empty)
+    )]
+    pub async fn load_totp_secret(&self) -> Result<Option<TotpSecret>> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                secret,
+                last_used_step
+            FROM
+                totp_secret
+            WHERE
+                id = 1
+            "#
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(row.map(|row| TotpSecret {
+            secret: row.secret,
+            last_used_step: row.last_used_step.map(|step| step as u64),
+        }))
+    }
+
+    /// Mark `step` as used, unless the same or a later step was used already.
+    ///
+    /// Returns whether the step was marked as used, a one-time password of the step is only
+    /// accepted in that case.
+    #[tracing::instrument(
+        name = "Database query",
+        skip_all,
+        fields(query = "use_totp_step", duration_ms = Empty)
+    )]
+    pub async fn use_totp_step(&self, step: u64) -> Result<bool> {
+        let _timer = self.query_timer();
+
+        let mut conn = self.inner.acquire().await?;
+
+        let step = step as i64;
+
+        let query_result = sqlx::query!(
+            r#"
+            UPDATE totp_secret
+            SET
+                last_used_step = $1
+            WHERE
+                id = 1 AND (last_used_step IS NULL OR last_used_step < $1)
+            "#,
+            step,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(query_result.rows_affected() == 1)
+    }
+}
+
+async fn insert_audit_entry(
+    conn: &mut sqlx::SqliteConnection,
+    entry: &WithdrawalAuditEntry,
+) -> Result<()> {
+    let action = entry.action.to_string();
+    let timestamp = models::Timestamp::from(entry.timestamp);
+
+    sqlx::query!(
+        r#"
+        INSERT INTO withdrawal_audit_log
+        (
+            request_id,
+            action,
+            detail,
+            timestamp
+        )
+        VALUES ($1, $2, $3, $4)
+        "#,
+        entry.request_id,
+        action,
+        entry.detail,
+        timestamp,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+
+    #[tokio::test]
+    async fn given_completed_withdrawal_then_request_and_audit_log_reflect_it() {
+        let db = memory().await.unwrap();
+
+        let address = "bcrt1qxw9v2aes9dx3dlan3p8ggts0jl2fpvcwrnyn0z"
+            .parse::<Address>()
+            .unwrap();
+        let txid = "2f7e4d8a7cd32c8e8bfc9ff88ab5a3b0bc4d3a1a9d5c27e44a2fb8e8b8cdbb55"
+            .parse::<Txid>()
+            .unwrap();
+
+        let id = db
+            .insert_withdrawal_request(
+                Some(Amount::from_sat(1_000_000)),
+                &address,
+                2.0,
+                Timestamp::new(1_000),
+                Timestamp::new(1_900),
+            )
+            .await
+            .unwrap();
+
+        db.insert_withdrawal_audit_entry(&WithdrawalAuditEntry {
+            request_id: id,
+            action: WithdrawalAction::ApprovalDenied,
+            detail: None,
+            timestamp: Timestamp::new(1_100),
+        })
+        .await
+        .unwrap();
+        db.update_withdrawal_request(
+            WithdrawalStatus::Completed,
+            Some(txid),
+            &WithdrawalAuditEntry {
+                request_id: id,
+                action: WithdrawalAction::Broadcast,
+                detail: None,
+                timestamp: Timestamp::new(1_200),
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            db.load_withdrawal_request(id).await.unwrap(),
+            WithdrawalRequest {
+                id,
+                amount: Some(Amount::from_sat(1_000_000)),
+                address,
+                fee_rate: 2.0,
+                status: WithdrawalStatus::Completed,
+                created_at: Timestamp::new(1_000),
+                expires_at: Timestamp::new(1_900),
+                txid: Some(txid),
+            }
+        );
+        assert_eq!(
+            db.load_withdrawal_audit_log()
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|entry| entry.action)
+                .collect::<Vec<_>>(),
+            vec![
+                WithdrawalAction::Requested,
+                WithdrawalAction::ApprovalDenied,
+                WithdrawalAction::Broadcast
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn given_new_totp_secret_then_it_replaces_the_previous_one() {
+        let db = memory().await.unwrap();
+
+        assert_eq!(db.load_totp_secret().await.unwrap(), None);
+
+        db.insert_totp_secret(&[1; 20], Timestamp::new(1_000))
+            .await
+            .unwrap();
+        db.insert_totp_secret(&[2; 20], Timestamp::new(2_000))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            db.load_totp_secret().await.unwrap(),
+            Some(TotpSecret {
+                secret: vec![2; 20],
+                last_used_step: None
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn given_used_totp_step_then_same_and_earlier_steps_are_rejected() {
+        let db = memory().await.unwrap();
+        db.insert_totp_secret(&[1; 20], Timestamp::new(1_000))
+            .await
+            .unwrap();
+
+        assert!(db.use_totp_step(10).await.unwrap());
+        assert!(!db.use_totp_step(10).await.unwrap());
+        assert!(!db.use_totp_step(9).await.unwrap());
+        assert!(db.use_totp_step(11).await.unwrap());
+
+        assert_eq!(
+            db.load_totp_secret().await.unwrap().unwrap().last_used_step,
+            Some(11)
+        );
+    }
+}
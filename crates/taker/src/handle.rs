@@ -0,0 +1,591 @@
+//! Embedding the taker into another application.
+//!
+//! [`TakerHandle`] starts the same actor system as the taker binary and exposes typed methods for
+//! the functionality of the HTTP API. Applications linking to this crate can therefore drive the
+//! taker directly, serving the HTTP API is optional.
+
+use crate::load_secrets;
+use crate::openapi;
+use crate::resolve_maker_multiaddr;
+use crate::routes;
+use crate::routes::IdentityInfo;
+use crate::Opts;
+use crate::RocketAuthDbConnection;
+use crate::Taker;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use daemon::api_tokens::ApiTokens;
+use daemon::backup;
+use daemon::bdk::bitcoin;
+use daemon::bdk::FeeRate;
+use daemon::cet_broadcast;
+use daemon::chain_consistency;
+use daemon::cold_sweep;
+use daemon::daily_marks;
+use daemon::electrum_health;
+use daemon::electrum_health::ElectrumStatus;
+use daemon::event_feed;
+use daemon::health;
+use daemon::janitor;
+use daemon::ledger;
+use daemon::loss_limit;
+use daemon::maker_selection;
+use daemon::missing_attestation;
+use daemon::monitor;
+use daemon::notifications;
+use daemon::notifications::Notification;
+use daemon::online_status::ConnectionStatus;
+use daemon::oracle;
+use daemon::projection;
+use daemon::projection::explorer;
+use daemon::projection::rounding;
+use daemon::projection::FeedReceivers;
+use daemon::projection::MakerOffers;
+use daemon::seed::RandomSeed;
+use daemon::seed::Seed;
+use daemon::seed::ThreadSafeSeed;
+use daemon::telemetry;
+use daemon::transcript;
+use daemon::wallet;
+use daemon::wallet::TAKER_WALLET_ID;
+use daemon::Environment;
+use daemon::TakerActorSystem;
+use daemon::N_PAYOUTS;
+use model::olivia;
+use model::Contracts;
+use model::Identity;
+use model::Leverage;
+use model::Offer;
+use model::OfferId;
+use model::OrderId;
+use model::Price;
+use model::Role;
+use model::WalletInfo;
+use rocket_cookie_auth::users::Users;
+use shared_bin::catchers::default_catchers;
+use shared_bin::cli::Network;
+use shared_bin::fairings;
+use std::collections::HashMap;
+use std::env;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio_extras::Tasks;
+use xtra::Actor as _;
+use xtras::supervisor::always_restart;
+use xtras::supervisor::Supervisor;
+
+/// A running taker.
+///
+/// Dropping the handle shuts down all actors of the taker.
+pub struct TakerHandle {
+    system: Taker,
+    db: sqlite_db::Connection,
+    feed_receivers: FeedReceivers,
+    wallet_feed_receiver: watch::Receiver<Option<WalletInfo>>,
+    electrum_status_receiver: watch::Receiver<ElectrumStatus>,
+    notifications_feed_receiver: watch::Receiver<Vec<Notification>>,
+    identity_info: IdentityInfo,
+    loss_limit_actor: Option<xtra::Address<loss_limit::Actor>>,
+    ledger_actor: xtra::Address<ledger::Actor>,
+    cold_sweep_actor: xtra::Address<cold_sweep::Actor>,
+    health_actor: xtra::Address<health::Actor>,
+    backup_exporter: backup::Exporter,
+    telemetry: Option<telemetry::Telemetry>,
+    daily_marks: daily_marks::DailyMarks,
+    janitor: janitor::Janitor,
+    wallet_seed: Arc<ThreadSafeSeed>,
+    network: Network,
+    data_dir: PathBuf,
+    _tasks: Tasks,
+}
+
+impl TakerHandle {
+    /// Start the taker and connect to the maker.
+    ///
+    /// Logging is left to the embedding application.
+    pub async fn start(opts: &Opts) -> Result<Self> {
+        let (maker_url, maker_id, maker_peer_id) = opts.maker()?;
+        let network = opts.network();
+        let data_dir = opts.data_dir().await?;
+
+        let maker_identity = Identity::new(maker_id);
+        let bitcoin_network = network.bitcoin_network();
+
+        let secrets = load_secrets(opts, &data_dir, bitcoin_network).await?;
+
+        if let Some(policy) = rounding::Policy::load(&data_dir).await? {
+            rounding::init(policy)?;
+            tracing::info!(
+                "Rounding figures as configured in {}",
+                rounding::CONFIG_FILE
+            );
+        }
+
+        if let Some(config) = explorer::Explorer::load(&data_dir).await? {
+            explorer::init(config)?;
+            tracing::info!(
+                "Linking transactions as configured in {}",
+                explorer::CONFIG_FILE
+            );
+        }
+
+        let mut tasks = Tasks::default();
+
+        let mut wallet_dir = data_dir.clone();
+        wallet_dir.push(TAKER_WALLET_ID);
+
+        let (electrum_health, electrum_status_receiver) =
+            electrum_health::Actor::start(network.electrum_endpoints()).await?;
+        electrum_health.create(None).spawn(&mut tasks);
+
+        let (wallet, wallet_feed_receiver) = wallet::Actor::spawn(
+            electrum_status_receiver.clone(),
+            secrets.ext_priv_key,
+            wallet_dir,
+            secrets.wallet_seed.is_managed(),
+            opts.wallet_address_type,
+            opts.sweep_previous_wallet,
+        )?;
+
+        let db = sqlite_db::connect(data_dir.join("taker.sqlite"), true)
+            .await?
+            .with_slow_query_threshold(Duration::from_millis(opts.slow_query_threshold_ms));
+
+        // Create actors
+
+        let maker_multiaddr = resolve_maker_multiaddr(maker_url.as_str(), maker_peer_id).await?;
+
+        let mut additional_makers = Vec::new();
+        for maker in opts.additional_makers.iter() {
+            additional_makers.push((
+                Identity::new(maker.id),
+                resolve_maker_multiaddr(maker.url.as_str(), maker.peer_id).await?,
+            ));
+        }
+        let maker_preferences = maker_selection::Preferences::new(
+            opts.preferred_makers.clone(),
+            opts.maker_price_tolerance,
+        )?;
+
+        let identities = secrets.identities;
+        let hex_pk = hex::encode(identities.identity_pk.to_bytes());
+        let peer_id = identities.libp2p.public().to_peer_id().to_string();
+
+        tracing::info!("Connection details: taker_id='{hex_pk}', peer_id='{peer_id}'");
+
+        let identity_info = IdentityInfo {
+            taker_id: hex_pk,
+            taker_peer_id: peer_id,
+        };
+
+        let environment = match env::var("ITCHYSATS_ENV") {
+            Ok(environment) => Environment::new(environment.as_str()),
+            Err(_) => Environment::new("binary"),
+        };
+
+        let (supervisor, price_feed_actor) =
+            Supervisor::<_, xtra_bitmex_price_feed::Error>::with_policy(
+                {
+                    let network = network.bitmex_network();
+                    move || xtra_bitmex_price_feed::Actor::new(network)
+                },
+                always_restart(),
+            );
+
+        tasks.add(supervisor.run_log_summary());
+
+        let (feed_senders, feed_receivers) = projection::feeds();
+        let feed_senders = Arc::new(feed_senders);
+
+        let missing_attestation_policy = opts.missing_attestation_policy;
+        let cet_broadcast = cet_broadcast::Config {
+            strategy: opts.cet_broadcast,
+            delay: Duration::from_secs(opts.cet_broadcast_delay_mins * 60),
+        };
+        let (supervisor, projection_actor) = Supervisor::with_capacity(
+            {
+                let db = db.clone();
+                let price_feed = price_feed_actor.clone();
+                move || {
+                    projection::Actor::new(
+                        db.clone(),
+                        bitcoin_network,
+                        price_feed.clone().into(),
+                        Role::Taker,
+                        feed_senders.clone(),
+                    )
+                    .with_missing_attestation_policy(missing_attestation_policy)
+                }
+            },
+            projection::MAILBOX_CAPACITY,
+        );
+        tasks.add(supervisor.run_log_summary());
+
+        let (chain_consistency, chain_consistency_status) = chain_consistency::Actor::new(
+            wallet_feed_receiver.clone(),
+            feed_receivers.chain_tip.clone(),
+        );
+        chain_consistency.create(None).spawn(&mut tasks);
+
+        let telemetry = opts.telemetry.then(|| {
+            telemetry::Telemetry::new(
+                db.clone(),
+                Role::Taker,
+                Duration::from_secs(opts.telemetry_retention_days * 24 * 60 * 60),
+                opts.telemetry_collector.clone(),
+            )
+        });
+
+        let daily_marks =
+            daily_marks::DailyMarks::new(db.clone(), &feed_receivers, opts.daily_mark_time);
+        let janitor = janitor::Janitor::new(
+            db.clone(),
+            feed_receivers.cfds.clone(),
+            opts.janitor_dry_run,
+        );
+
+        let system = TakerActorSystem::new(
+            db.clone(),
+            wallet.clone(),
+            *olivia::PUBLIC_KEY,
+            identities,
+            |executor| oracle::Actor::new(db.clone(), executor),
+            |executor| {
+                monitor::Actor::new(
+                    db.clone(),
+                    electrum_status_receiver.clone(),
+                    executor,
+                    projection_actor.clone().into(),
+                    projection_actor.clone().into(),
+                )
+                .map(|monitor| {
+                    monitor
+                        .with_chain_consistency(chain_consistency_status.clone())
+                        .with_cet_broadcast(cet_broadcast)
+                })
+            },
+            price_feed_actor,
+            N_PAYOUTS,
+            Duration::from_secs(10),
+            projection_actor.clone(),
+            maker_identity,
+            maker_multiaddr,
+            additional_makers,
+            maker_preferences,
+            environment,
+            opts.report_protocol_failures,
+            opts.job_intervals.clone(),
+            telemetry.clone(),
+            Some(daily_marks.clone()),
+            Some(janitor.clone()),
+            opts.rollover_funding_rate_tolerance,
+            Duration::from_secs(opts.intent_validity_minutes * 60),
+            opts.settlement_proposal_tolerance,
+        )?;
+
+        if opts.verify_state_on_start {
+            daemon::verify_state(&db).await?;
+        }
+
+        let (notifications_actor, notifications_feed_receiver) = notifications::Actor::new(
+            feed_receivers.cfds.clone(),
+            system.maker_online_status_feed_receiver.clone(),
+        );
+        notifications_actor.create(None).spawn(&mut tasks);
+
+        if let Some(config) = notifications::email::Config::load(&data_dir).await? {
+            notifications::email::Actor::new(
+                config,
+                feed_receivers.cfds.clone(),
+                wallet_feed_receiver.clone(),
+                notifications_feed_receiver.clone(),
+            )?
+            .create(None)
+            .spawn(&mut tasks);
+
+            tracing::info!(
+                "Sending notifications by email as configured in {}",
+                notifications::email::CONFIG_FILE
+            );
+        }
+
+        missing_attestation::Actor::new(
+            db.clone(),
+            system.executor.clone(),
+            missing_attestation_policy,
+        )
+        .create(None)
+        .spawn(&mut tasks);
+
+        let loss_limit_actor = opts.daily_loss_limit.map(|limit| {
+            loss_limit::Actor::new(db.clone(), feed_receivers.cfds.clone(), limit)
+                .create(None)
+                .spawn(&mut tasks)
+        });
+
+        let ledger_actor = ledger::Actor::new(db.clone(), wallet_feed_receiver.clone())
+            .create(None)
+            .spawn(&mut tasks);
+
+        let cold_sweep_actor = cold_sweep::Actor::new(
+            db.clone(),
+            wallet.clone().into(),
+            feed_receivers.cfds.clone(),
+        )
+        .create(None)
+        .spawn(&mut tasks);
+
+        let health_actor = health::Actor::new(
+            db.clone(),
+            electrum_status_receiver.clone(),
+            wallet_feed_receiver.clone(),
+            system.price_feed_actor.clone().into(),
+            system.oracle_actor.clone().into(),
+            system.endpoint.clone(),
+            Role::Taker,
+        )
+        .create(None)
+        .spawn(&mut tasks);
+
+        if let Some(password) = &opts.password {
+            db.clone()
+                .update_password(rocket_cookie_auth::user::create_password(
+                    password.to_string().as_str(),
+                )?)
+                .await?;
+        }
+
+        let backup_exporter = backup::Exporter::new(db.clone(), secrets.backup_key);
+
+        Ok(Self {
+            system,
+            db,
+            feed_receivers,
+            wallet_feed_receiver,
+            electrum_status_receiver,
+            notifications_feed_receiver,
+            identity_info,
+            loss_limit_actor,
+            ledger_actor,
+            cold_sweep_actor,
+            health_actor,
+            backup_exporter,
+            telemetry,
+            daily_marks,
+            janitor,
+            wallet_seed: secrets.wallet_seed,
+            network,
+            data_dir,
+            _tasks: tasks,
+        })
+    }
+
+    /// The latest offers of the maker.
+    pub fn offers(&self) -> MakerOffers {
+        self.feed_receivers.offers.borrow().clone()
+    }
+
+    /// Subscribe to the offers of the maker.
+    pub fn subscribe_offers(&self) -> watch::Receiver<MakerOffers> {
+        self.feed_receivers.offers.clone()
+    }
+
+    /// Subscribe to the CFDs of the taker, `None` until they were loaded from the database.
+    pub fn subscribe_cfds(&self) -> watch::Receiver<Option<Vec<projection::Cfd>>> {
+        self.feed_receivers.cfds.clone()
+    }
+
+    /// Subscribe to the latest price quotes.
+    pub fn subscribe_quotes(&self) -> watch::Receiver<projection::LatestQuotes> {
+        self.feed_receivers.quote.clone()
+    }
+
+    /// Subscribe to the state of the wallet, `None` until the wallet was synced.
+    pub fn subscribe_wallet(&self) -> watch::Receiver<Option<WalletInfo>> {
+        self.wallet_feed_receiver.clone()
+    }
+
+    /// Subscribe to the status of the connection to the maker.
+    pub fn subscribe_maker_status(&self) -> watch::Receiver<ConnectionStatus> {
+        self.system.maker_online_status_feed_receiver.clone()
+    }
+
+    /// Subscribe to the most recent user-facing notifications.
+    pub fn subscribe_notifications(&self) -> watch::Receiver<Vec<Notification>> {
+        self.notifications_feed_receiver.clone()
+    }
+
+    /// The latest offers of every maker that are still safe to take.
+    ///
+    /// Unlike [`subscribe_offers`](Self::subscribe_offers), which only has the preferred offer for
+    /// each contract symbol and position, this has the offers of all makers.
+    pub async fn offers_by_maker(&self) -> Result<HashMap<libp2p_core::PeerId, Vec<Offer>>> {
+        self.system.offers_by_maker().await
+    }
+
+    /// Take an offer of the maker, returning the id of the new order.
+    ///
+    /// Fails without placing an order if the daily loss limit was reached.
+    pub async fn take_offer(
+        &self,
+        offer_id: OfferId,
+        quantity: Contracts,
+        leverage: Leverage,
+    ) -> Result<OrderId> {
+        if let Some(loss_limit) = &self.loss_limit_actor {
+            let status = loss_limit
+                .send(loss_limit::GetStatus)
+                .await
+                .context("Loss limit actor not available")??;
+
+            if status.refuses_orders() {
+                bail!(
+                    "Realized loss of {} today reached the limit of {}, new orders are refused until the next UTC day",
+                    status.realized_loss_today,
+                    status.limit
+                );
+            }
+        }
+
+        self.system.place_order(offer_id, quantity, leverage).await
+    }
+
+    /// Close the position of a CFD by settling collaboratively with the maker.
+    pub async fn close(&self, order_id: OrderId) -> Result<()> {
+        self.system.propose_settlement(order_id).await
+    }
+
+    /// Close the position of a CFD by settling collaboratively with the maker, sending the payout
+    /// to an external address instead of the internal wallet.
+    pub async fn close_to(
+        &self,
+        order_id: OrderId,
+        payout_address: bitcoin::Address,
+    ) -> Result<()> {
+        self.system
+            .propose_settlement_to(order_id, payout_address)
+            .await
+    }
+
+    /// Roll the CFD over, or queue the rollover until the maker is back online.
+    pub async fn rollover(&self, order_id: OrderId) -> Result<()> {
+        self.system.propose_rollover(order_id).await
+    }
+
+    /// Settle the CFD automatically once the price reaches one of the given levels.
+    ///
+    /// `None` removes the respective level.
+    pub async fn set_price_levels(
+        &self,
+        order_id: OrderId,
+        take_profit: Option<Price>,
+        stop_loss: Option<Price>,
+    ) -> Result<()> {
+        self.system
+            .set_price_levels(order_id, take_profit, stop_loss)
+            .await
+    }
+
+    /// Attach a user-defined tag to the CFD, e.g. the strategy it belongs to.
+    pub async fn add_cfd_tag(&self, order_id: OrderId, tag: String) -> Result<()> {
+        self.system.add_cfd_tag(order_id, tag).await
+    }
+
+    /// Remove a tag from the CFD.
+    pub async fn remove_cfd_tag(&self, order_id: OrderId, tag: String) -> Result<()> {
+        self.system.remove_cfd_tag(order_id, tag).await
+    }
+
+    /// Close the position of a CFD unilaterally by publishing the commit transaction.
+    pub async fn force_close(&self, order_id: OrderId) -> Result<()> {
+        self.system.commit(order_id).await
+    }
+
+    /// Withdraw funds from the internal wallet, sweeping the wallet if no amount is given.
+    pub async fn withdraw(
+        &self,
+        amount: Option<bitcoin::Amount>,
+        address: bitcoin::Address,
+        fee_rate: FeeRate,
+    ) -> Result<bitcoin::Txid> {
+        self.system.withdraw(amount, address, fee_rate).await
+    }
+
+    /// The actor system of the taker, for functionality without a dedicated method.
+    pub fn system(&self) -> &Taker {
+        &self.system
+    }
+
+    /// Serve the HTTP API and the web interface until the server is shut down.
+    pub async fn serve_http(self, http_address: SocketAddr, launch_browser: bool) -> Result<()> {
+        let figment = rocket::Config::figment()
+            .merge(("address", http_address.ip()))
+            .merge(("port", http_address.port()))
+            .merge(("cli_colors", false))
+            .merge(("secret_key", RandomSeed::default().seed()));
+
+        let rocket_auth_db_connection = RocketAuthDbConnection::new(self.db.clone());
+        let users = Users::new(Box::new(rocket_auth_db_connection));
+
+        let bitcoin_network = self.network.bitcoin_network();
+
+        let mut rocket = rocket::custom(figment)
+            .manage(self.feed_receivers)
+            .manage(self.wallet_feed_receiver)
+            .manage(self.electrum_status_receiver)
+            .manage(self.notifications_feed_receiver)
+            .manage(self.identity_info)
+            .manage(bitcoin_network)
+            .manage(self.system.maker_online_status_feed_receiver.clone())
+            .manage(self.system.identify_info_feed_receiver.clone())
+            .manage(self.system.instance_status_feed_receiver.clone())
+            .manage(self.system.maker_service_status_feed_receiver.clone())
+            .manage(self.system.scheduler_actor.clone())
+            .manage(self.system)
+            .manage(self.loss_limit_actor)
+            .manage(self.ledger_actor)
+            .manage(self.cold_sweep_actor)
+            .manage(self.health_actor)
+            .manage(self.backup_exporter)
+            .manage(ApiTokens::new(self.db.clone()))
+            .manage(self.telemetry)
+            .manage(self.daily_marks)
+            .manage(self.janitor)
+            .manage(transcript::Transcripts::new(self.data_dir.clone()))
+            .manage(event_feed::EventFeed::new(self.db.clone(), Role::Taker))
+            .manage(shared_bin::openapi::Spec(openapi::SPEC))
+            .mount("/api", routes::api_routes())
+            .register("/api", default_catchers())
+            .manage(users)
+            .manage(self.data_dir)
+            .manage(self.network)
+            .mount("/", rocket::routes![routes::dist, routes::index])
+            .register("/", default_catchers())
+            .attach(fairings::log_launch())
+            .attach(fairings::log_requests())
+            .attach(fairings::ui_browser_launch(launch_browser));
+
+        if self.wallet_seed.is_managed() {
+            rocket = rocket.mount("/api", routes::managed_wallet_routes());
+        }
+
+        let mission_success = rocket.launch().await?;
+        tracing::trace!(?mission_success, "Rocket has landed");
+
+        self.db.close().await;
+
+        Ok(())
+    }
+
+    /// Shut down the taker, waiting for pending database writes.
+    pub async fn shutdown(self) {
+        let db = self.db.clone();
+        drop(self);
+
+        db.close().await;
+    }
+}
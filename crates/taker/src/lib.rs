@@ -1,56 +1,77 @@
 use crate::bitcoin::util::bip32::ExtendedPrivKey;
-use crate::routes::IdentityInfo;
 use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
 use clap::Parser;
+use daemon::backup;
 use daemon::bdk::bitcoin;
+use daemon::bdk::blockchain::ElectrumBlockchain;
+use daemon::bdk::sled;
 use daemon::bdk::FeeRate;
+use daemon::cet_broadcast;
+use daemon::daily_marks;
+use daemon::electrum_health;
+use daemon::libp2p_utils::create_connect_multiaddr;
 use daemon::libp2p_utils::create_connect_tcp_multiaddr;
-use daemon::monitor;
+use daemon::missing_attestation;
 use daemon::oracle;
-use daemon::projection;
+use daemon::scheduler;
 use daemon::seed;
 use daemon::seed::AppSeed;
+use daemon::seed::Identities;
 use daemon::seed::RandomSeed;
 use daemon::seed::Seed;
 use daemon::seed::ThreadSafeSeed;
+use daemon::telemetry;
 use daemon::wallet;
 use daemon::wallet::TAKER_WALLET_ID;
-use daemon::Environment;
 use daemon::TakerActorSystem;
-use daemon::N_PAYOUTS;
+use itertools::Itertools;
+use libp2p_core::Multiaddr;
 use libp2p_core::PeerId;
-use model::olivia;
-use model::Identity;
-use model::Role;
+use model::payout_curve::test_vectors;
+use model::simulate;
+use model::simulate::Scenario;
+use model::symbols;
+use model::symbols::SymbolRegistry;
 use model::SETTLEMENT_INTERVAL;
 use rocket::async_trait;
-use rocket_cookie_auth::users::Users;
-use shared_bin::catchers::default_catchers;
+use rust_decimal::Decimal;
+use shared_bin::cli::Command;
+use shared_bin::cli::DlcCommand;
 use shared_bin::cli::Network;
-use shared_bin::cli::Withdraw;
-use shared_bin::fairings;
+use shared_bin::decommission::Decommission;
+use shared_bin::decommission::Recipients;
 use shared_bin::logger;
 use shared_bin::logger::LevelFilter;
 use shared_bin::logger::LOCAL_COLLECTOR_ENDPOINT;
 use shared_bin::MAINNET_ELECTRUM;
 use shared_bin::TESTNET_ELECTRUM;
+use sqlite_db::schema_flags;
 use std::convert::Infallible;
-use std::env;
 use std::net::IpAddr;
 use std::net::Ipv4Addr;
 use std::net::SocketAddr;
+use std::path::Path;
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
-use std::time::Duration;
 use tokio_extras::Tasks;
-use xtras::supervisor::always_restart;
-use xtras::supervisor::Supervisor;
+use xtra::Actor as _;
 
+mod handle;
+pub mod openapi;
 mod routes;
 
+pub use handle::TakerHandle;
+
+/// The actor system of the taker binary
+pub type Taker = TakerActorSystem<
+    oracle::Actor,
+    wallet::Actor<ElectrumBlockchain, sled::Tree>,
+    xtra_bitmex_price_feed::Actor,
+>;
+
 pub const ANNOUNCEMENT_LOOKAHEAD: time::Duration = time::Duration::hours(24);
 
 const MAINNET_MAKER: &str = "mainnet.itchysats.network:10001";
@@ -88,6 +109,10 @@ impl std::fmt::Display for Password {
 pub struct Opts {
     /// The IP address or hostname of the other party (i.e. the maker).
     ///
+    /// A multiaddr such as `/ip4/127.0.0.1/tcp/10000` is accepted as well, builds with the
+    /// `memory-transport` feature of the daemon also connect to `/memory/<port>` of a maker in the
+    /// same process.
+    ///
     /// If not specified it defaults to the itchysats maker for the mainnet or testnet.
     #[clap(long)]
     maker: Option<String>,
@@ -104,6 +129,26 @@ pub struct Opts {
     #[clap(long)]
     maker_peer_id: Option<PeerId>,
 
+    /// Further maker to connect to, as `<host:port>,<maker-id>,<peer-id>`.
+    ///
+    /// Can be given multiple times. Offers of all makers are received, equivalent offers are
+    /// chosen between according to `--preferred-maker` and `--maker-price-tolerance`.
+    #[clap(long = "additional-maker", value_parser(parse_additional_maker))]
+    additional_makers: Vec<AdditionalMaker>,
+
+    /// Peer id of a preferred maker. Can be given multiple times, the first one is the most
+    /// preferred.
+    ///
+    /// Among equivalent offers of several makers, the offer with the best price is taken unless
+    /// the offer of a more preferred maker is within `--maker-price-tolerance` of it.
+    #[clap(long = "preferred-maker")]
+    preferred_makers: Vec<PeerId>,
+
+    /// Relative price difference to the best offer accepted in favour of a preferred maker, e.g.
+    /// `0.001` for 0.1%.
+    #[clap(long, default_value = "0")]
+    maker_price_tolerance: Decimal,
+
     /// The IP address to listen on for the HTTP API.
     #[clap(long, default_value = "127.0.0.1:8000")]
     http_address: SocketAddr,
@@ -174,6 +219,139 @@ pub struct Opts {
     /// If enabled, the log will be printed to {service_name}.log in the data dir
     #[clap(long)]
     pub log_to_file: bool,
+
+    /// Database queries taking longer than this many milliseconds are logged as slow queries.
+    #[clap(long, default_value = "500")]
+    slow_query_threshold_ms: u64,
+
+    /// Maximum net loss in BTC of CFDs closed within a UTC day.
+    ///
+    /// Once reached, new orders are refused until the next UTC day unless the limit is overridden
+    /// through the API. Disabled if not specified.
+    #[clap(long, value_parser(parse_btc))]
+    daily_loss_limit: Option<bitcoin::Amount>,
+
+    /// Send anonymized reports of failed contract setups and rollovers to the maker.
+    ///
+    /// Reports only contain the failed step, a coarse error category and the daemon version.
+    #[clap(long)]
+    report_protocol_failures: bool,
+
+    /// Path to a JSON file with the oracle event, digits and payout curve per contract symbol.
+    ///
+    /// Symbols not listed in the file use the built-in parameters. Must match the maker's
+    /// configuration.
+    #[clap(long)]
+    symbol_config: Option<PathBuf>,
+
+    /// Type of the addresses the wallet derives, either `wpkh` or `tr` (taproot).
+    ///
+    /// Funds received on addresses of the other type after switching are reported, see
+    /// `--sweep-previous-wallet`.
+    #[clap(long, default_value = "wpkh")]
+    wallet_address_type: wallet::AddressType,
+
+    /// Sweep funds received on addresses of the previously used address type into the wallet.
+    ///
+    /// The wallet of the previous address type is synced every hour. Sweeps pay the estimated fee
+    /// rate for confirmation within 6 blocks.
+    #[clap(long)]
+    sweep_previous_wallet: bool,
+
+    /// If enabled, the cached state of all open CFDs is verified against the event log upon
+    /// startup. Mismatches are reported and the affected cache entries invalidated.
+    #[clap(long)]
+    verify_state_on_start: bool,
+
+    /// What to do if the oracle attestation is missing one hour after the settlement time: `wait`
+    /// for it, `alert` the operator or publish the commit transaction to `refund` the CFD once the
+    /// refund timelock expired.
+    #[clap(long, default_value = "wait")]
+    missing_attestation_policy: missing_attestation::Policy,
+
+    /// When to publish the CET once the oracle attested: `immediate`ly, after
+    /// `--cet-broadcast-delay-mins` if the counterparty usually publishes it (`delayed`), or only
+    /// if the counterparty did not publish it before the `deadline`. The CET is always published
+    /// about one day before the refund timelock expires.
+    #[clap(long, default_value = "immediate")]
+    cet_broadcast: cet_broadcast::Strategy,
+
+    /// Minutes to wait for the counterparty to publish the CET with `--cet-broadcast delayed`.
+    #[clap(long, default_value = "30")]
+    cet_broadcast_delay_mins: u64,
+
+    /// Interval of a maintenance job in the format `<name>=<seconds>`, e.g.
+    /// `db_compaction=3600`. An interval of `0` disables the job.
+    ///
+    /// Can be given multiple times. The jobs are `db_compaction`, `closed_cfd_migration`,
+    /// `failed_cfd_migration`, `utxo_consolidation`, `daily_marks`, `janitor` and, if telemetry is
+    /// enabled, `telemetry_pruning` and `telemetry_push`.
+    #[clap(long = "job-interval")]
+    job_intervals: Vec<scheduler::JobInterval>,
+
+    /// Count anonymous usage statistics, e.g. the number of trades and failed rollovers, in the
+    /// local database.
+    ///
+    /// The statistics contain no order ids, amounts or addresses and can be exported through
+    /// `GET /api/telemetry`. They only leave the machine if `--telemetry-collector` is specified.
+    #[clap(long)]
+    telemetry: bool,
+
+    /// Endpoint the usage statistics are `POST`ed to once a day if `--telemetry` is enabled.
+    #[clap(long, requires = "telemetry")]
+    telemetry_collector: Option<reqwest::Url>,
+
+    /// Number of days the usage statistics are kept for.
+    #[clap(long, default_value_t = telemetry::DEFAULT_RETENTION_DAYS)]
+    telemetry_retention_days: u64,
+
+    /// Time of the day in UTC at which the open CFDs are marked to the market, in the format
+    /// `HH:MM`.
+    ///
+    /// The marks are exported through `GET /api/reports/marks?date=<YYYY-MM-DD>`.
+    #[clap(long, default_value = "00:00")]
+    daily_mark_time: daily_marks::MarkTime,
+
+    /// Only report orphaned protocol artifacts, e.g. UTXOs locked by failed contract setups,
+    /// instead of releasing them.
+    ///
+    /// The report of the last run is exported through `GET /api/debug/janitor`.
+    #[clap(long)]
+    janitor_dry_run: bool,
+
+    /// Maximum absolute difference between the funding rate the maker proposes at rollover and
+    /// the funding rate of the maker's latest offer, e.g. `0.0001`.
+    ///
+    /// Rollovers with a funding rate outside of this band are rejected. Disabled if not
+    /// specified.
+    #[clap(long)]
+    rollover_funding_rate_tolerance: Option<Decimal>,
+
+    /// How many minutes a settlement or rollover requested while the maker is offline is kept
+    /// to be executed once the maker is back online.
+    #[clap(long, default_value = "120")]
+    intent_validity_minutes: u64,
+
+    /// Maximum deviation of the price at which the maker proposes to settle from the price of the
+    /// latest quote, relative to the latter, e.g. `0.002` for 0.2%.
+    ///
+    /// Settlement proposals of the maker within this tolerance are accepted automatically, all
+    /// others are left for manual decision. If not specified, no proposal is accepted
+    /// automatically.
+    #[clap(long)]
+    settlement_proposal_tolerance: Option<Decimal>,
+
+    /// Address to serve the read-only gRPC API on, e.g. `127.0.0.1:8002`.
+    ///
+    /// The API streams the CFDs, offers, quotes and wallet. Not served if not specified.
+    #[cfg(feature = "grpc")]
+    #[clap(long, requires = "grpc_auth_token")]
+    grpc_address: Option<SocketAddr>,
+
+    /// Token gRPC clients have to present as `authorization: Bearer <token>` metadata.
+    #[cfg(feature = "grpc")]
+    #[clap(long)]
+    grpc_auth_token: Option<String>,
 }
 
 impl Opts {
@@ -194,6 +372,9 @@ impl Opts {
             maker: Some(maker),
             maker_id: Some(maker_id),
             maker_peer_id: Some(maker_peer_id),
+            additional_makers: Vec::new(),
+            preferred_makers: Vec::new(),
+            maker_price_tolerance: Decimal::ZERO,
             http_address: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), port),
             data_dir: Some(PathBuf::from(data_dir)),
             json: false,
@@ -210,6 +391,25 @@ impl Opts {
             app_seed: None,
             wallet_xprv: None,
             log_to_file: true,
+            slow_query_threshold_ms: sqlite_db::DEFAULT_SLOW_QUERY_THRESHOLD.as_millis() as u64,
+            daily_loss_limit: None,
+            report_protocol_failures: false,
+            symbol_config: None,
+            wallet_address_type: wallet::AddressType::Wpkh,
+            sweep_previous_wallet: false,
+            verify_state_on_start: false,
+            missing_attestation_policy: missing_attestation::Policy::Wait,
+            cet_broadcast: cet_broadcast::Strategy::Immediate,
+            cet_broadcast_delay_mins: 30,
+            job_intervals: Vec::new(),
+            telemetry: false,
+            telemetry_collector: None,
+            telemetry_retention_days: telemetry::DEFAULT_RETENTION_DAYS,
+            daily_mark_time: daily_marks::MarkTime::default(),
+            janitor_dry_run: false,
+            rollover_funding_rate_tolerance: None,
+            intent_validity_minutes: daemon::intents::DEFAULT_VALIDITY.as_secs() / 60,
+            settlement_proposal_tolerance: None,
         })
     }
 
@@ -217,7 +417,30 @@ impl Opts {
         self.network.clone().unwrap_or_default()
     }
 
+    /// The data directory for the network, created if it does not exist yet.
+    async fn data_dir(&self) -> Result<PathBuf> {
+        let data_dir = self
+            .data_dir
+            .clone()
+            .unwrap_or_else(|| std::env::current_dir().expect("unable to get cwd"));
+
+        let data_dir = self.network().data_dir(data_dir);
+
+        if !data_dir.exists() {
+            tokio::fs::create_dir_all(&data_dir).await?;
+        }
+
+        Ok(data_dir)
+    }
+
     fn maker(&self) -> Result<(String, x25519_dalek::PublicKey, PeerId)> {
+        // There are no defaults for other networks than mainnet and testnet
+        if let (Some(maker_url), Some(maker_id), Some(maker_peer_id)) =
+            (self.maker.clone(), self.maker_id, self.maker_peer_id)
+        {
+            return Ok((maker_url, maker_id, maker_peer_id));
+        }
+
         let network = PublicNetwork::try_from(self.network())?;
 
         let maker_url = self
@@ -278,11 +501,13 @@ impl From<PublicNetwork> for Network {
         match public {
             PublicNetwork::Mainnet => Network::Mainnet {
                 electrum: MAINNET_ELECTRUM.to_string(),
-                withdraw: None,
+                electrum_backup: Vec::new(),
+                command: None,
             },
             PublicNetwork::Testnet => Network::Testnet {
                 electrum: TESTNET_ELECTRUM.to_string(),
-                withdraw: None,
+                electrum_backup: Vec::new(),
+                command: None,
             },
         }
     }
@@ -300,12 +525,38 @@ impl FromStr for PublicNetwork {
     }
 }
 
+/// A maker to connect to in addition to the main maker.
+#[derive(Clone, Debug)]
+struct AdditionalMaker {
+    url: String,
+    id: x25519_dalek::PublicKey,
+    peer_id: PeerId,
+}
+
+fn parse_additional_maker(s: &str) -> Result<AdditionalMaker> {
+    let (url, id, peer_id) = s
+        .split(',')
+        .collect_tuple()
+        .context("Expected `<host:port>,<maker-id>,<peer-id>`")?;
+
+    Ok(AdditionalMaker {
+        url: url.to_string(),
+        id: parse_x25519_pubkey(id)?,
+        peer_id: peer_id.parse()?,
+    })
+}
+
 fn parse_x25519_pubkey(s: &str) -> Result<x25519_dalek::PublicKey> {
     let mut bytes = [0u8; 32];
     hex::decode_to_slice(s, &mut bytes)?;
     Ok(x25519_dalek::PublicKey::from(bytes))
 }
 
+fn parse_btc(s: &str) -> Result<bitcoin::Amount> {
+    let amount = bitcoin::Amount::from_str_in(s, bitcoin::Denomination::Bitcoin)?;
+    Ok(amount)
+}
+
 fn parse_app_seed(s: &str) -> Result<[u8; 32]> {
     let mut bytes = [0u8; 32];
     hex::decode_to_slice(s, &mut bytes)?;
@@ -313,20 +564,7 @@ fn parse_app_seed(s: &str) -> Result<[u8; 32]> {
 }
 
 pub async fn run(opts: Opts) -> Result<()> {
-    let (maker_url, maker_id, maker_peer_id) = opts.maker()?;
-
-    let network = opts.network();
-
-    let data_dir = opts
-        .data_dir
-        .clone()
-        .unwrap_or_else(|| std::env::current_dir().expect("unable to get cwd"));
-
-    let data_dir = network.data_dir(data_dir);
-
-    if !data_dir.exists() {
-        tokio::fs::create_dir_all(&data_dir).await?;
-    }
+    let data_dir = opts.data_dir().await?;
 
     let _guard = logger::init(
         opts.log_level,
@@ -348,55 +586,74 @@ pub async fn run(opts: Opts) -> Result<()> {
         "CFDs created with this release will settle after {settlement_interval_hours} hours"
     );
 
-    let maker_identity = Identity::new(maker_id);
+    if let Some(path) = &opts.symbol_config {
+        symbols::install(SymbolRegistry::from_file(path)?)?;
+        tracing::info!("Loaded symbol configuration from {}", path.display());
+    }
 
-    let bitcoin_network = network.bitcoin_network();
+    let network = opts.network();
+    if let Some(Command::Simulate { scenario }) = network.command() {
+        let report = simulate::simulate(&Scenario::from_file(scenario)?)?;
+        println!("{}", serde_json::to_string_pretty(&report)?);
 
-    let wallet_seed_file = &data_dir.join(seed::TAKER_WALLET_SEED_FILE);
-    let wallet_seed: Arc<ThreadSafeSeed> = match opts.app_seed {
-        Some(seed_bytes) => Arc::new(AppSeed::from(seed_bytes)),
-        None => Arc::new(RandomSeed::initialize(wallet_seed_file).await?),
-    };
+        return Ok(());
+    }
 
-    let identity_seed_file = &data_dir.join(seed::TAKER_IDENTITY_SEED_FILE);
-    if !identity_seed_file.exists() {
-        tracing::info!("Copying wallet seed file for identity seed file");
-        // copy wallet seed file for backwards compatibility.
-        tokio::fs::copy(&wallet_seed_file, &identity_seed_file).await?;
+    if let Some(Command::PayoutTestVectors { inputs }) = network.command() {
+        let inputs = match inputs {
+            Some(path) => test_vectors::Inputs::from_file(path)?,
+            None => test_vectors::Inputs::canonical(),
+        };
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&test_vectors::generate(inputs)?)?
+        );
+
+        return Ok(());
     }
 
-    // use a different seed for the libp2p identity.
-    let identity_seed = RandomSeed::initialize(identity_seed_file).await?;
-    let identities = identity_seed.derive_identities();
+    if let Some(Command::FinalizeMigration { name }) = network.command() {
+        let migration = schema_flags::flagged_migration(name)
+            .with_context(|| format!("Unknown migration {name}"))?;
 
-    let ext_priv_key = match opts.wallet_xprv {
-        Some(wallet_xprv) => {
-            if wallet_xprv.network != bitcoin_network {
-                let network = wallet_xprv.network;
-                bail!("Invalid private key provided. Was '{network}' but should have been '{bitcoin_network}'");
-            }
-            wallet_xprv
+        let db = sqlite_db::connect(data_dir.join("taker.sqlite"), false).await?;
+        db.finalize_migration(migration).await?;
+
+        tracing::info!(%name, "Finalized migration");
+
+        return Ok(());
+    }
+
+    if let Some(Command::CheckIntegrity) = network.command() {
+        let db = sqlite_db::connect(data_dir.join("taker.sqlite"), false).await?;
+        let report = db.check_integrity().await?;
+        println!("{}", serde_json::to_string_pretty(&report)?);
+
+        if !report.is_consistent() {
+            tracing::warn!("Database is not consistent, see the report for details");
         }
-        None => wallet_seed.derive_extended_priv_key(bitcoin_network)?,
-    };
 
-    let mut tasks = Tasks::default();
+        return Ok(());
+    }
 
-    let mut wallet_dir = data_dir.clone();
-    wallet_dir.push(TAKER_WALLET_ID);
-    let (wallet, wallet_feed_receiver) = wallet::Actor::spawn(
-        network.electrum(),
-        ext_priv_key,
-        wallet_dir,
-        wallet_seed.is_managed(),
-    )?;
+    if let Some(Command::Dlc {
+        command: DlcCommand::Show { order_id },
+    }) = network.command()
+    {
+        let db = sqlite_db::connect(data_dir.join("taker.sqlite"), false).await?;
 
-    if let Some(Withdraw::Withdraw {
+        return shared_bin::dlc::show(&db, (*order_id).into(), network.bitcoin_network()).await;
+    }
+
+    if let Some(Command::Withdraw {
         amount,
         address,
         fee,
-    }) = network.withdraw()
+    }) = network.command()
     {
+        let mut tasks = Tasks::default();
+        let wallet = spawn_wallet(&opts, &data_dir, &mut tasks).await?;
+
         wallet
             .send(wallet::Withdraw {
                 amount: *amount,
@@ -408,147 +665,140 @@ pub async fn run(opts: Opts) -> Result<()> {
         return Ok(());
     }
 
-    let figment = rocket::Config::figment()
-        .merge(("address", opts.http_address.ip()))
-        .merge(("port", opts.http_address.port()))
-        .merge(("cli_colors", false))
-        .merge(("secret_key", RandomSeed::default().seed()));
-
-    let db = sqlite_db::connect(data_dir.join("taker.sqlite"), true).await?;
-
-    // Create actors
+    if let Some(Command::Decommission {
+        addresses,
+        xpub,
+        num_addresses,
+        fee,
+        confirmations,
+    }) = network.command()
+    {
+        let mut tasks = Tasks::default();
+        let wallet = spawn_wallet(&opts, &data_dir, &mut tasks).await?;
+
+        let db_path = data_dir.join("taker.sqlite");
+        let db = sqlite_db::connect(db_path.clone(), false).await?;
+
+        return Decommission {
+            db,
+            db_path: &db_path,
+            seed_files: vec![
+                data_dir.join(seed::TAKER_WALLET_SEED_FILE),
+                data_dir.join(seed::TAKER_IDENTITY_SEED_FILE),
+            ],
+            recipients: match xpub {
+                Some(xpub) => Recipients::Xpub {
+                    xpub: *xpub,
+                    num_addresses: *num_addresses,
+                },
+                None => Recipients::Addresses(addresses.clone()),
+            },
+            fee: fee.map(FeeRate::from_sat_per_vb),
+            network: network.bitcoin_network(),
+            confirmations: *confirmations,
+        }
+        .run(wallet)
+        .await;
+    }
 
-    let possible_addresses = resolve_maker_addresses(maker_url.as_str()).await?;
+    let taker = TakerHandle::start(&opts).await?;
+
+    #[cfg(feature = "grpc")]
+    let _grpc_tasks = {
+        let mut tasks = tokio_extras::Tasks::default();
+
+        if let (Some(address), Some(auth_token)) = (opts.grpc_address, opts.grpc_auth_token.clone())
+        {
+            let grpc = hermes_grpc::Service::new(
+                taker.subscribe_cfds(),
+                taker.subscribe_offers(),
+                taker.subscribe_quotes(),
+                taker.subscribe_wallet(),
+            );
+            tasks.add_fallible(grpc.serve(address, auth_token), |e| async move {
+                tracing::error!("gRPC API stopped: {e:#}");
+            });
+        }
 
-    // Assume that the first resolved ipv4 address is good enough for libp2p.
-    let maker_libp2p_address = possible_addresses
-        .iter()
-        .find(|x| x.is_ipv4())
-        .context("Could not resolve maker URL")?;
-    let maker_multiaddr = create_connect_tcp_multiaddr(maker_libp2p_address, maker_peer_id)?;
+        tasks
+    };
 
-    let hex_pk = hex::encode(identities.identity_pk.to_bytes());
-    let peer_id = identities.libp2p.public().to_peer_id().to_string();
+    taker.serve_http(opts.http_address, !opts.headless).await
+}
 
-    tracing::info!("Connection details: taker_id='{hex_pk}', peer_id='{peer_id}'");
+/// Secrets of the taker, loaded from the data directory unless provided through [`Opts`].
+struct Secrets {
+    wallet_seed: Arc<ThreadSafeSeed>,
+    identities: Identities,
+    ext_priv_key: ExtendedPrivKey,
+    backup_key: backup::Key,
+}
 
-    let identity_info = IdentityInfo {
-        taker_id: hex_pk,
-        taker_peer_id: peer_id,
+async fn load_secrets(
+    opts: &Opts,
+    data_dir: &Path,
+    bitcoin_network: bitcoin::Network,
+) -> Result<Secrets> {
+    let wallet_seed_file = &data_dir.join(seed::TAKER_WALLET_SEED_FILE);
+    let wallet_seed: Arc<ThreadSafeSeed> = match opts.app_seed {
+        Some(seed_bytes) => Arc::new(AppSeed::from(seed_bytes)),
+        None => Arc::new(RandomSeed::initialize(wallet_seed_file).await?),
     };
 
-    let environment = match env::var("ITCHYSATS_ENV") {
-        Ok(environment) => Environment::new(environment.as_str()),
-        Err(_) => Environment::new("binary"),
-    };
+    let identity_seed_file = &data_dir.join(seed::TAKER_IDENTITY_SEED_FILE);
+    if !identity_seed_file.exists() {
+        tracing::info!("Copying wallet seed file for identity seed file");
+        // copy wallet seed file for backwards compatibility.
+        tokio::fs::copy(&wallet_seed_file, &identity_seed_file).await?;
+    }
 
-    let (supervisor, price_feed_actor) =
-        Supervisor::<_, xtra_bitmex_price_feed::Error>::with_policy(
-            {
-                let network = network.bitmex_network();
-                move || xtra_bitmex_price_feed::Actor::new(network)
-            },
-            always_restart(),
-        );
+    // use a different seed for the libp2p identity.
+    let identity_seed = RandomSeed::initialize(identity_seed_file).await?;
+    let identities = identity_seed.derive_identities();
+    let backup_key = identity_seed.derive_backup_key();
 
-    tasks.add(supervisor.run_log_summary());
-
-    let (feed_senders, feed_receivers) = projection::feeds();
-    let feed_senders = Arc::new(feed_senders);
-
-    let (supervisor, projection_actor) = Supervisor::new({
-        let db = db.clone();
-        let price_feed = price_feed_actor.clone();
-        move || {
-            projection::Actor::new(
-                db.clone(),
-                bitcoin_network,
-                price_feed.clone().into(),
-                Role::Taker,
-                feed_senders.clone(),
-            )
+    let ext_priv_key = match opts.wallet_xprv {
+        Some(wallet_xprv) => {
+            if wallet_xprv.network != bitcoin_network {
+                let network = wallet_xprv.network;
+                bail!("Invalid private key provided. Was '{network}' but should have been '{bitcoin_network}'");
+            }
+            wallet_xprv
         }
-    });
-    tasks.add(supervisor.run_log_summary());
+        None => wallet_seed.derive_extended_priv_key(bitcoin_network)?,
+    };
 
-    let taker = TakerActorSystem::new(
-        db.clone(),
-        wallet.clone(),
-        *olivia::PUBLIC_KEY,
+    Ok(Secrets {
+        wallet_seed,
         identities,
-        |executor| oracle::Actor::new(db.clone(), executor),
-        |executor| {
-            let electrum = network.electrum().to_string();
-            monitor::Actor::new(db.clone(), electrum, executor)
-        },
-        price_feed_actor,
-        N_PAYOUTS,
-        Duration::from_secs(10),
-        projection_actor.clone(),
-        maker_identity,
-        maker_multiaddr,
-        environment,
-    )?;
-
-    if let Some(password) = opts.password {
-        db.clone()
-            .update_password(rocket_cookie_auth::user::create_password(
-                password.to_string().as_str(),
-            )?)
-            .await?;
-    }
-
-    let rocket_auth_db_connection = RocketAuthDbConnection::new(db.clone());
-    let users = Users::new(Box::new(rocket_auth_db_connection));
-
-    let mut rocket = rocket::custom(figment)
-        .manage(feed_receivers)
-        .manage(wallet_feed_receiver)
-        .manage(identity_info)
-        .manage(bitcoin_network)
-        .manage(taker.maker_online_status_feed_receiver.clone())
-        .manage(taker.identify_info_feed_receiver.clone())
-        .manage(taker)
-        .mount(
-            "/api",
-            rocket::routes![
-                routes::feed,
-                routes::post_order_request,
-                routes::post_cfd_action,
-                routes::post_withdraw_request,
-                routes::put_sync_wallet,
-                shared_bin::routes::get_health_check,
-                shared_bin::routes::get_metrics,
-                shared_bin::routes::get_version,
-                shared_bin::routes::change_password,
-                shared_bin::routes::post_login,
-                shared_bin::routes::logout,
-                shared_bin::routes::is_authenticated,
-            ],
-        )
-        .register("/api", default_catchers())
-        .manage(users)
-        .manage(data_dir)
-        .manage(network)
-        .mount("/", rocket::routes![routes::dist, routes::index])
-        .register("/", default_catchers())
-        .attach(fairings::log_launch())
-        .attach(fairings::log_requests())
-        .attach(fairings::ui_browser_launch(!opts.headless));
-
-    if wallet_seed.is_managed() {
-        rocket = rocket.mount(
-            "/api",
-            rocket::routes![routes::get_export_seed, routes::put_import_seed],
-        );
-    }
-
-    let mission_success = rocket.launch().await?;
-    tracing::trace!(?mission_success, "Rocket has landed");
+        ext_priv_key,
+        backup_key,
+    })
+}
 
-    db.close().await;
+/// Spawn only the wallet, e.g. to withdraw funds without starting the taker.
+async fn spawn_wallet(
+    opts: &Opts,
+    data_dir: &Path,
+    tasks: &mut Tasks,
+) -> Result<xtra::Address<wallet::Actor<ElectrumBlockchain, sled::Tree>>> {
+    let network = opts.network();
+    let secrets = load_secrets(opts, data_dir, network.bitcoin_network()).await?;
+
+    let (electrum_health, electrum_status_receiver) =
+        electrum_health::Actor::start(network.electrum_endpoints()).await?;
+    electrum_health.create(None).spawn(tasks);
+
+    let (wallet, _) = wallet::Actor::spawn(
+        electrum_status_receiver,
+        secrets.ext_priv_key,
+        data_dir.join(TAKER_WALLET_ID),
+        secrets.wallet_seed.is_managed(),
+        opts.wallet_address_type,
+        opts.sweep_previous_wallet,
+    )?;
 
-    Ok(())
+    Ok(wallet)
 }
 
 async fn resolve_maker_addresses(maker_addr: &str) -> Result<Vec<SocketAddr>> {
@@ -564,6 +814,27 @@ async fn resolve_maker_addresses(maker_addr: &str) -> Result<Vec<SocketAddr>> {
     Ok(possible_addresses)
 }
 
+/// The multiaddr to dial the maker at, given either as `<host:port>` or as multiaddr.
+async fn resolve_maker_multiaddr(maker_addr: &str, peer_id: PeerId) -> Result<Multiaddr> {
+    if maker_addr.starts_with('/') {
+        let multiaddr = maker_addr
+            .parse::<Multiaddr>()
+            .with_context(|| format!("Invalid maker multiaddr {maker_addr}"))?;
+
+        return create_connect_multiaddr(&multiaddr, &peer_id);
+    }
+
+    let possible_addresses = resolve_maker_addresses(maker_addr).await?;
+
+    // Assume that the first resolved ipv4 address is good enough for libp2p.
+    let libp2p_address = possible_addresses
+        .iter()
+        .find(|x| x.is_ipv4())
+        .with_context(|| format!("Could not resolve maker URL {maker_addr}"))?;
+
+    create_connect_tcp_multiaddr(libp2p_address, peer_id)
+}
+
 struct RocketAuthDbConnection {
     inner: sqlite_db::Connection,
 }
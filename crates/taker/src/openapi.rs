@@ -0,0 +1,192 @@
+//! OpenAPI specification of the taker's HTTP API, see [`shared_bin::openapi`].
+
+use crate::routes;
+use anyhow::Result;
+use serde_json::Value;
+use shared_bin::openapi::Body;
+use shared_bin::openapi::Doc;
+use shared_bin::openapi::Response;
+
+/// The committed specification, kept in sync with the routes by the tests
+pub const SPEC: &str = include_str!("../openapi.json");
+
+pub const DOCS: &[Doc] = &[
+    Doc::new(
+        "feed",
+        "Stream of the taker's state, starting with the current state",
+        Response::EventStream(&[
+            "wallet",
+            "maker_status",
+            "maker_compatibility",
+            "instance_status",
+            "maker_service_status",
+            "identity",
+            "btcusd_long_offer",
+            "btcusd_short_offer",
+            "ethusd_long_offer",
+            "ethusd_short_offer",
+            "offer_book",
+            "cfds",
+            "chain_tip",
+            "heartbeat",
+        ]),
+    ),
+    Doc::new(
+        "notifications",
+        "Stream of user-facing notifications, starting with the most recent ones",
+        Response::EventStream(&["notification"]),
+    ),
+    Doc::new(
+        "post_order_request",
+        "Place an order, refused once the daily loss limit was reached",
+        Response::Empty,
+    )
+    .body(Body::Json("Order")),
+    Doc::new(
+        "post_preflight",
+        "Check whether an order would be accepted without placing it",
+        Response::Json("Preflight report"),
+    )
+    .body(Body::Json("Order")),
+    Doc::new(
+        "post_calculate_margin",
+        "Margin and fees of an order",
+        Response::Json("Margin and fees"),
+    )
+    .body(Body::Json("Price, quantity and leverage")),
+    Doc::new(
+        "get_funding_rate_history",
+        "Funding rates of the maker over the last `days`",
+        Response::Json("Funding rates"),
+    ),
+    Doc::new(
+        "get_loss_limit",
+        "Status of the daily loss limit, `null` if not configured",
+        Response::Json("Loss limit status"),
+    ),
+    Doc::new(
+        "post_loss_limit_override",
+        "Accept orders for the rest of the UTC day despite the daily loss limit",
+        Response::Empty,
+    ),
+    Doc::new(
+        "post_cfd_action",
+        "Act on a CFD, e.g. `settle`, `commit` or `rollOver`, also with an API token scoped to the CFD",
+        Response::Empty,
+    ),
+    Doc::new(
+        "post_close_all",
+        "Close all CFDs with a maker",
+        Response::Json("Close-all summary"),
+    ),
+    Doc::new(
+        "post_external_settlement",
+        "Settle a CFD collaboratively, paying out to an external address",
+        Response::Empty,
+    )
+    .body(Body::Json("Payout address")),
+    Doc::new(
+        "put_price_levels",
+        "Set the price levels at which a CFD is settled automatically, also with a scoped API token",
+        Response::Empty,
+    )
+    .body(Body::Json("Price levels")),
+    Doc::new(
+        "get_cfds",
+        "All CFDs, optionally only those with a tag",
+        Response::Json("CFDs"),
+    ),
+    Doc::new("put_cfd_tag", "Tag a CFD", Response::Empty),
+    Doc::new("delete_cfd_tag", "Remove a tag from a CFD", Response::Empty),
+    Doc::new(
+        "post_withdraw_request",
+        "Withdraw from the wallet",
+        Response::Text("Transaction id"),
+    )
+    .body(Body::Json("Withdrawal")),
+    Doc::new("put_sync_wallet", "Sync the wallet", Response::Empty),
+    Doc::new(
+        "get_cold_sweep_policy",
+        "Policy of forwarding funds to a cold wallet, `null` if not configured",
+        Response::Json("Cold sweep policy"),
+    ),
+    Doc::new(
+        "put_cold_sweep_policy",
+        "Configure forwarding funds to a cold wallet",
+        Response::Empty,
+    )
+    .body(Body::Json("Cold sweep policy")),
+    Doc::new(
+        "get_cold_sweep_history",
+        "Funds forwarded to the cold wallet",
+        Response::Json("Cold sweeps"),
+    ),
+    Doc::new(
+        "post_api_token",
+        "Issue an API token authorizing only some actions on some CFDs, shown only once",
+        Response::Json("Issued API token"),
+    )
+    .body(Body::Json("Order ids, actions and validity in hours")),
+    Doc::new(
+        "get_api_tokens",
+        "All issued API tokens, including expired and revoked ones",
+        Response::Json("API tokens"),
+    ),
+    Doc::new(
+        "delete_api_token",
+        "Revoke an API token",
+        Response::Empty,
+    ),
+    Doc::new(
+        "get_export_seed",
+        "Export the wallet seed, only if the seed is managed by the taker",
+        Response::Binary("Seed file"),
+    ),
+    Doc::new(
+        "put_import_seed",
+        "Import a wallet seed, only if the seed is managed by the taker",
+        Response::Empty,
+    )
+    .body(Body::Binary("Seed file"))
+    .public(),
+];
+
+/// Generate the specification from the mounted routes.
+pub fn generate() -> Result<Value> {
+    let mut routes = routes::api_routes();
+    routes.extend(routes::managed_wallet_routes());
+
+    shared_bin::openapi::generate(
+        "Hermes taker",
+        env!("CARGO_PKG_VERSION"),
+        &routes,
+        &[DOCS, shared_bin::openapi::SHARED],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn committed_specification_is_up_to_date() {
+        let generated = generate().unwrap();
+
+        if std::env::var_os(shared_bin::openapi::UPDATE_ENV_VAR).is_some() {
+            let spec = serde_json::to_string_pretty(&generated).unwrap();
+            std::fs::write(
+                concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json"),
+                spec + "\n",
+            )
+            .unwrap();
+            return;
+        }
+
+        let committed = serde_json::from_str::<Value>(SPEC).unwrap();
+        assert!(
+            generated == committed,
+            "openapi.json is outdated, run the tests with {}=1 to regenerate it",
+            shared_bin::openapi::UPDATE_ENV_VAR
+        );
+    }
+}
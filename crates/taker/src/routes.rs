@@ -1,37 +1,50 @@
 #![allow(clippy::let_unit_value)]
 // see: https://github.com/SergioBenitez/Rocket/issues/2211
+use crate::Taker;
+use daemon::api_tokens;
+use daemon::api_tokens::ApiTokens;
 use daemon::bdk;
 use daemon::bdk::bitcoin::Amount;
 use daemon::bdk::bitcoin::Network;
-use daemon::bdk::blockchain::ElectrumBlockchain;
-use daemon::bdk::sled;
+use daemon::close_all;
+use daemon::cold_sweep;
+use daemon::funding_rate_history;
 use daemon::identify;
+use daemon::instance_fence;
+use daemon::loss_limit;
+use daemon::notifications::Notification;
 use daemon::online_status::ConnectionStatus;
-use daemon::oracle;
+use daemon::preflight;
 use daemon::projection;
 use daemon::projection::CfdAction;
 use daemon::projection::FeedReceivers;
 use daemon::seed;
 use daemon::seed::RANDOM_SEED_SIZE;
-use daemon::wallet;
-use daemon::TakerActorSystem;
+use daemon::service_status::MakerServiceStatus;
 use http_api_problem::HttpApiProblem;
 use http_api_problem::StatusCode;
+use model::libp2p::PeerId;
+use model::ContractSymbol;
 use model::Contracts;
+use model::FundingRate;
 use model::Leverage;
 use model::OrderId;
+use model::Position;
 use model::Price;
 use model::Timestamp;
 use model::WalletInfo;
 use rocket::data::ToByteUnit;
 use rocket::http::ContentType;
 use rocket::http::Status;
+use rocket::request::FromRequest;
+use rocket::request::Outcome;
 use rocket::response::stream::Event;
 use rocket::response::stream::EventStream;
 use rocket::response::Responder;
 use rocket::serde::json::Json;
 use rocket::serde::uuid::Uuid;
 use rocket::Data;
+use rocket::Request;
 use rocket::State;
 use rocket_cookie_auth::user::User;
 use rocket_download_response::mime;
@@ -41,18 +54,17 @@ use rust_embed_rocket::EmbeddedFileExt;
 use serde::Deserialize;
 use serde::Serialize;
 use shared_bin::ToSseEvent;
+use sqlite_db::api_tokens::ApiToken;
+use sqlite_db::api_tokens::TokenAction;
+use sqlite_db::cold_sweeps::ColdSweep;
+use sqlite_db::cold_sweeps::ColdSweepPolicy;
 use std::borrow::Cow;
 use std::path::PathBuf;
+use std::time::Duration;
 use tokio::select;
 use tokio::sync::watch;
 use tracing::instrument;
 
-type Taker = TakerActorSystem<
-    oracle::Actor,
-    wallet::Actor<ElectrumBlockchain, sled::Tree>,
-    xtra_bitmex_price_feed::Actor,
->;
-
 const HEARTBEAT_INTERVAL_SECS: u64 = 5;
 
 #[derive(Debug, Clone, Serialize)]
@@ -63,22 +75,95 @@ pub struct IdentityInfo {
     pub(crate) taker_peer_id: String,
 }
 
+/// Request guard of endpoints whose actions can be delegated with an API token scoped to CFDs.
+///
+/// Requests with an `Authorization: Bearer <token>` header are authenticated by the token, all
+/// others need a logged-in user.
+pub enum Caller {
+    User,
+    Token(ApiToken),
+}
+
+impl Caller {
+    /// Fails with `403 Forbidden` unless the caller may perform `action` on the CFD.
+    fn authorize(&self, order_id: OrderId, action: TokenAction) -> Result<(), HttpApiProblem> {
+        let token = match self {
+            Caller::User => return Ok(()),
+            Caller::Token(token) => token,
+        };
+
+        if !token.allows(order_id, action, Timestamp::now()) {
+            tracing::warn!(id = %token.id, %order_id, %action, "API token does not authorize action");
+
+            return Err(HttpApiProblem::new(StatusCode::FORBIDDEN)
+                .title("Action not authorized")
+                .detail(format!(
+                    "API token does not authorize {action} on CFD {order_id}, or it expired or was revoked"
+                )));
+        }
+
+        Ok(())
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Caller {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let token = match request
+            .headers()
+            .get_one("Authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+        {
+            Some(token) => token,
+            None => {
+                return request
+                    .guard::<User>()
+                    .await
+                    .map(|_| Caller::User)
+                    .map_failure(|(status, _)| (status, ()))
+            }
+        };
+
+        let api_tokens = match request.rocket().state::<ApiTokens>() {
+            Some(api_tokens) => api_tokens,
+            None => return Outcome::Failure((Status::InternalServerError, ())),
+        };
+
+        match api_tokens.authenticate(token).await {
+            Ok(Some(token)) => Outcome::Success(Caller::Token(token)),
+            Ok(None) => Outcome::Failure((Status::Unauthorized, ())),
+            Err(e) => {
+                tracing::error!("Failed to authenticate API token: {e:#}");
+                Outcome::Failure((Status::InternalServerError, ()))
+            }
+        }
+    }
+}
+
 #[rocket::get("/feed")]
 pub async fn feed(
     rx: &State<FeedReceivers>,
     rx_wallet: &State<watch::Receiver<Option<WalletInfo>>>,
     rx_maker_status: &State<watch::Receiver<ConnectionStatus>>,
     rx_maker_identity: &State<watch::Receiver<Option<identify::PeerInfo>>>,
+    rx_instance_status: &State<watch::Receiver<instance_fence::Status>>,
+    rx_maker_service_status: &State<watch::Receiver<Option<MakerServiceStatus>>>,
     identity_info: &State<IdentityInfo>,
     _user: User,
 ) -> EventStream![] {
     let rx = rx.inner();
     let mut rx_cfds = rx.cfds.clone();
     let mut rx_offers = rx.offers.clone();
+    let mut rx_offer_book = rx.offer_book.clone();
+    let mut rx_chain_tip = rx.chain_tip.clone();
 
     let mut rx_wallet = rx_wallet.inner().clone();
     let mut rx_maker_status = rx_maker_status.inner().clone();
     let mut rx_maker_identity = rx_maker_identity.inner().clone();
+    let mut rx_instance_status = rx_instance_status.inner().clone();
+    let mut rx_maker_service_status = rx_maker_service_status.inner().clone();
     let identity = identity_info.inner().clone();
     let mut heartbeat =
         tokio::time::interval(std::time::Duration::from_secs(HEARTBEAT_INTERVAL_SECS));
@@ -94,6 +179,12 @@ pub async fn feed(
         let maker_identity = rx_maker_identity.borrow().clone();
         yield maker_identity.to_sse_event();
 
+        let instance_status = *rx_instance_status.borrow();
+        yield Event::json(&instance_status).event("instance_status");
+
+        let maker_service_status = rx_maker_service_status.borrow().clone();
+        yield Event::json(&maker_service_status).event("maker_service_status");
+
         yield Event::json(&identity).event("identity");
 
         let offers = rx_offers.borrow().clone();
@@ -102,11 +193,17 @@ pub async fn feed(
         yield Event::json(&offers.ethusd_long).event("ethusd_long_offer");
         yield Event::json(&offers.ethusd_short).event("ethusd_short_offer");
 
+        let offer_book = rx_offer_book.borrow().clone();
+        yield Event::json(&offer_book).event("offer_book");
+
         let cfds = rx_cfds.borrow().clone();
         if let Some(cfds) = cfds {
             yield cfds.to_sse_event()
         }
 
+        let chain_tip = *rx_chain_tip.borrow();
+        yield chain_tip.to_sse_event();
+
         loop{
             select! {
                 Ok(()) = rx_wallet.changed() => {
@@ -121,6 +218,14 @@ pub async fn feed(
                     let maker_identity = rx_maker_identity.borrow().clone();
                     yield maker_identity.to_sse_event();
                 },
+                Ok(()) = rx_instance_status.changed() => {
+                    let instance_status = *rx_instance_status.borrow();
+                    yield Event::json(&instance_status).event("instance_status");
+                },
+                Ok(()) = rx_maker_service_status.changed() => {
+                    let maker_service_status = rx_maker_service_status.borrow().clone();
+                    yield Event::json(&maker_service_status).event("maker_service_status");
+                },
                 Ok(()) = rx_offers.changed() => {
                     let offers = rx_offers.borrow().clone();
                     yield Event::json(&offers.btcusd_long).event("btcusd_long_offer");
@@ -128,12 +233,20 @@ pub async fn feed(
                     yield Event::json(&offers.ethusd_long).event("ethusd_long_offer");
                     yield Event::json(&offers.ethusd_short).event("ethusd_short_offer");
                 }
+                Ok(()) = rx_offer_book.changed() => {
+                    let offer_book = rx_offer_book.borrow().clone();
+                    yield Event::json(&offer_book).event("offer_book");
+                }
                 Ok(()) = rx_cfds.changed() => {
                     let cfds = rx_cfds.borrow().clone();
                     if let Some(cfds) = cfds {
                         yield cfds.to_sse_event()
                     }
                 }
+                Ok(()) = rx_chain_tip.changed() => {
+                    let chain_tip = *rx_chain_tip.borrow();
+                    yield chain_tip.to_sse_event();
+                }
                 _ = heartbeat.tick() => {
                     yield Event::json(&Heartbeat::new()).event("heartbeat")
                 }
@@ -142,6 +255,40 @@ pub async fn feed(
     }
 }
 
+/// Stream of discrete, user-facing notifications
+///
+/// Upon connecting, the most recent notifications are sent. Clients can use the notification ids
+/// to ignore notifications they have already shown.
+#[rocket::get("/notifications")]
+pub async fn notifications(
+    rx: &State<watch::Receiver<Vec<Notification>>>,
+    _user: User,
+) -> EventStream![] {
+    let mut rx = rx.inner().clone();
+
+    EventStream! {
+        let mut last_sequence = 0;
+
+        loop {
+            let notifications = rx.borrow().clone();
+
+            for notification in notifications
+                .into_iter()
+                .filter(|notification| notification.sequence > last_sequence)
+            {
+                last_sequence = notification.sequence;
+                yield Event::json(&notification)
+                    .id(notification.id.clone())
+                    .event("notification");
+            }
+
+            if rx.changed().await.is_err() {
+                break;
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize)]
 pub struct Heartbeat {
     timestamp: Timestamp,
@@ -162,20 +309,40 @@ pub struct CfdOrderRequest {
     pub order_id: OrderId,
     pub quantity: Contracts,
     pub leverage: Leverage,
+    /// Take the equivalent offer of this maker instead of the preferred one
+    #[serde(default)]
+    pub maker_peer_id: Option<PeerId>,
 }
 
 #[rocket::post("/cfd/order", data = "<cfd_order_request>")]
-#[instrument(name = "POST /cfd/order", skip(taker, _user), err)]
+#[instrument(name = "POST /cfd/order", skip(taker, loss_limit, _user), err)]
 pub async fn post_order_request(
     cfd_order_request: Json<CfdOrderRequest>,
     taker: &State<Taker>,
+    loss_limit: &State<Option<xtra::Address<loss_limit::Actor>>>,
     _user: User,
 ) -> Result<(), HttpApiProblem> {
+    if let Some(loss_limit) = loss_limit.inner() {
+        let status = loss_limit_status(loss_limit).await?;
+
+        if status.refuses_orders() {
+            return Err(HttpApiProblem::new(StatusCode::FORBIDDEN)
+                .title("Daily loss limit reached")
+                .detail(format!(
+                    "Realized loss of {} today reached the limit of {}, new orders are refused until the next UTC day",
+                    status.realized_loss_today, status.limit
+                )));
+        }
+    }
+
     taker
-        .place_order(
+        .place_order_with_maker(
             cfd_order_request.order_id,
             cfd_order_request.quantity,
             cfd_order_request.leverage,
+            cfd_order_request
+                .maker_peer_id
+                .map(|peer_id| peer_id.inner()),
         )
         .await
         .map_err(|e| {
@@ -187,13 +354,129 @@ pub async fn post_order_request(
     Ok(())
 }
 
+/// The status of the daily loss limit, `null` if no limit is configured.
+#[rocket::get("/loss-limit")]
+#[instrument(name = "GET /loss-limit", skip(loss_limit, _user), err)]
+pub async fn get_loss_limit(
+    loss_limit: &State<Option<xtra::Address<loss_limit::Actor>>>,
+    _user: User,
+) -> Result<Json<Option<loss_limit::Status>>, HttpApiProblem> {
+    let status = match loss_limit.inner() {
+        Some(loss_limit) => Some(loss_limit_status(loss_limit).await?),
+        None => None,
+    };
+
+    Ok(Json(status))
+}
+
+/// Accept new orders for the rest of the current UTC day even if the daily loss limit was reached.
+#[rocket::post("/loss-limit/override")]
+#[instrument(name = "POST /loss-limit/override", skip(loss_limit, _user), err)]
+pub async fn post_loss_limit_override(
+    loss_limit: &State<Option<xtra::Address<loss_limit::Actor>>>,
+    _user: User,
+) -> Result<(), HttpApiProblem> {
+    let loss_limit = loss_limit.inner().as_ref().ok_or_else(|| {
+        HttpApiProblem::new(StatusCode::NOT_FOUND).title("No daily loss limit configured")
+    })?;
+
+    loss_limit.send(loss_limit::Override).await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Failed to override daily loss limit")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(())
+}
+
+async fn loss_limit_status(
+    loss_limit: &xtra::Address<loss_limit::Actor>,
+) -> Result<loss_limit::Status, HttpApiProblem> {
+    loss_limit
+        .send(loss_limit::GetStatus)
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|status| status)
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Failed to determine daily loss limit status")
+                .detail(format!("{e:#}"))
+        })
+}
+
+#[rocket::post("/cfds/preflight", data = "<cfd_order_request>")]
+#[instrument(name = "POST /cfds/preflight", skip(taker, _user), err)]
+pub async fn post_preflight(
+    cfd_order_request: Json<CfdOrderRequest>,
+    taker: &State<Taker>,
+    _user: User,
+) -> Result<Json<preflight::Report>, HttpApiProblem> {
+    let report = taker
+        .preflight(
+            cfd_order_request.order_id,
+            cfd_order_request.quantity,
+            cfd_order_request.leverage,
+        )
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Pre-flight checks failed to run")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(report))
+}
+
+/// Number of days of funding rate history returned if not specified otherwise
+const DEFAULT_FUNDING_RATE_HISTORY_DAYS: u32 = 30;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct FundingRateHistoryEntry {
+    pub contract_symbol: ContractSymbol,
+    pub position_maker: Position,
+    pub funding_rate: FundingRate,
+    pub timestamp: Timestamp,
+}
+
+impl From<funding_rate_history::FundingRateEntry> for FundingRateHistoryEntry {
+    fn from(entry: funding_rate_history::FundingRateEntry) -> Self {
+        Self {
+            contract_symbol: entry.contract_symbol,
+            position_maker: entry.position_maker,
+            funding_rate: entry.funding_rate,
+            timestamp: entry.timestamp,
+        }
+    }
+}
+
+/// The funding rates the maker offered during the past `days`, capped at
+/// [`funding_rate_history::MAX_DAYS`].
+#[rocket::get("/funding-rate-history?<days>")]
+#[instrument(name = "GET /funding-rate-history", skip(taker, _user), err)]
+pub async fn get_funding_rate_history(
+    days: Option<u32>,
+    taker: &State<Taker>,
+    _user: User,
+) -> Result<Json<Vec<FundingRateHistoryEntry>>, HttpApiProblem> {
+    let days = days.unwrap_or(DEFAULT_FUNDING_RATE_HISTORY_DAYS);
+
+    let history = taker.funding_rate_history(days).await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::BAD_GATEWAY)
+            .title("Failed to query funding rate history from maker")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(history.into_iter().map(Into::into).collect()))
+}
+
+/// Act on a CFD, also allowed with an API token scoped to the CFD and action.
 #[rocket::post("/cfd/<order_id>/<action>")]
-#[instrument(name = "POST /cfd/<order_id>/<action>", skip(taker, _user), err)]
+#[instrument(name = "POST /cfd/<order_id>/<action>", skip(taker, caller), err)]
 pub async fn post_cfd_action(
     order_id: Uuid,
     action: String,
     taker: &State<Taker>,
-    _user: User,
+    caller: Caller,
 ) -> Result<(), HttpApiProblem> {
     let order_id = OrderId::from(order_id);
     let action = action.parse().map_err(|_| {
@@ -208,8 +491,18 @@ pub async fn post_cfd_action(
             return Err(HttpApiProblem::new(StatusCode::BAD_REQUEST)
                 .detail(format!("taker cannot invoke action {action}")));
         }
-        CfdAction::Commit => taker.commit(order_id).await,
-        CfdAction::Settle => taker.propose_settlement(order_id).await,
+        CfdAction::Commit => {
+            caller.authorize(order_id, TokenAction::Commit)?;
+            taker.commit(order_id).await
+        }
+        CfdAction::Settle => {
+            caller.authorize(order_id, TokenAction::Settle)?;
+            taker.propose_settlement(order_id).await
+        }
+        CfdAction::RollOver => {
+            caller.authorize(order_id, TokenAction::RollOver)?;
+            taker.propose_rollover(order_id).await
+        }
     };
 
     result.map_err(|e| {
@@ -221,14 +514,163 @@ pub async fn post_cfd_action(
     Ok(())
 }
 
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct PriceLevelsRequest {
+    take_profit: Option<Price>,
+    stop_loss: Option<Price>,
+}
+
+/// Set the price levels at which the CFD is settled automatically, omitted levels are removed.
+///
+/// Also allowed with an API token scoped to the CFD.
+#[rocket::put("/cfd/<order_id>/price-levels", data = "<price_levels_request>")]
+#[instrument(name = "PUT /cfd/<order_id>/price-levels", skip(taker, caller), err)]
+pub async fn put_price_levels(
+    order_id: Uuid,
+    price_levels_request: Json<PriceLevelsRequest>,
+    taker: &State<Taker>,
+    caller: Caller,
+) -> Result<(), HttpApiProblem> {
+    let order_id = OrderId::from(order_id);
+    caller.authorize(order_id, TokenAction::PriceLevels)?;
+
+    taker
+        .set_price_levels(
+            order_id,
+            price_levels_request.take_profit,
+            price_levels_request.stop_loss,
+        )
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::BAD_REQUEST)
+                .title("Could not set price levels")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(())
+}
+
+/// All CFDs, only those with the given tag if specified.
+#[rocket::get("/cfds?<tag>")]
+#[instrument(name = "GET /cfds", skip(rx, _user), err)]
+pub async fn get_cfds(
+    tag: Option<String>,
+    rx: &State<FeedReceivers>,
+    _user: User,
+) -> Result<Json<Vec<projection::Cfd>>, HttpApiProblem> {
+    let cfds = rx.cfds.borrow().clone();
+
+    match cfds {
+        Some(cfds) => Ok(Json(
+            cfds.into_iter()
+                .filter(|cfd| tag.as_ref().map_or(true, |tag| cfd.tags.contains(tag)))
+                .collect(),
+        )),
+        None => Err(HttpApiProblem::new(StatusCode::SERVICE_UNAVAILABLE)
+            .title("CFDs not yet available")
+            .detail("CFDs are still being loaded from the database. Please retry later.")),
+    }
+}
+
+/// Attach a user-defined tag to the CFD.
+#[rocket::put("/cfd/<order_id>/tags/<tag>")]
+#[instrument(name = "PUT /cfd/<order_id>/tags/<tag>", skip(taker, _user), err)]
+pub async fn put_cfd_tag(
+    order_id: Uuid,
+    tag: String,
+    taker: &State<Taker>,
+    _user: User,
+) -> Result<(), HttpApiProblem> {
+    taker
+        .add_cfd_tag(OrderId::from(order_id), tag)
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::BAD_REQUEST)
+                .title("Could not tag CFD")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(())
+}
+
+/// Remove a tag from the CFD.
+#[rocket::delete("/cfd/<order_id>/tags/<tag>")]
+#[instrument(name = "DELETE /cfd/<order_id>/tags/<tag>", skip(taker, _user), err)]
+pub async fn delete_cfd_tag(
+    order_id: Uuid,
+    tag: String,
+    taker: &State<Taker>,
+    _user: User,
+) -> Result<(), HttpApiProblem> {
+    taker
+        .remove_cfd_tag(OrderId::from(order_id), tag)
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Could not remove tag from CFD")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExternalSettlementRequest {
+    payout_address: bdk::bitcoin::Address,
+}
+
+/// Settle collaboratively, sending the payout to an external address instead of the wallet.
+#[rocket::post("/cfd/<order_id>/settle/external", data = "<settlement_request>")]
+#[instrument(name = "POST /cfd/<order_id>/settle/external", skip(taker, _user), err)]
+pub async fn post_external_settlement(
+    order_id: Uuid,
+    settlement_request: Json<ExternalSettlementRequest>,
+    taker: &State<Taker>,
+    _user: User,
+) -> Result<(), HttpApiProblem> {
+    taker
+        .propose_settlement_to(
+            OrderId::from(order_id),
+            settlement_request.payout_address.clone(),
+        )
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Settle to external address failed")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(())
+}
+
+/// Propose collaborative settlement of all open CFDs with the maker `peer_id`.
+#[rocket::post("/peers/<peer_id>/close-all")]
+#[instrument(name = "POST /peers/<peer_id>/close-all", skip(taker, _user), err)]
+pub async fn post_close_all(
+    peer_id: &str,
+    taker: &State<Taker>,
+    _user: User,
+) -> Result<Json<close_all::Summary>, HttpApiProblem> {
+    let peer_id = peer_id.parse::<PeerId>().map_err(|e| {
+        HttpApiProblem::new(StatusCode::BAD_REQUEST)
+            .title("Invalid peer id")
+            .detail(format!("{e:#}"))
+    })?;
+
+    let summary = taker.close_all(peer_id).await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Closing all CFDs with maker failed")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(summary))
+}
+
 #[derive(Debug, Clone, Copy, Deserialize)]
 pub struct MarginRequest {
-    pub price: Price,
+    pub offer_id: OrderId,
     pub quantity: Contracts,
     pub leverage: Leverage,
-
-    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
-    pub opening_fee: Amount,
 }
 
 /// Represents the collateral that has to be put up
@@ -237,11 +679,42 @@ pub struct MarginResponse {
     #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
     pub margin: Amount,
 
+    /// Opening fee charged by the maker for the requested quantity
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub opening_fee: Amount,
+
     /// Margin + fees
     #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
     pub complete_initial_costs: Amount,
 }
 
+#[rocket::post("/calculate/margin", data = "<margin_request>")]
+#[instrument(name = "POST /calculate/margin", skip(taker, _user), err)]
+pub async fn post_calculate_margin(
+    margin_request: Json<MarginRequest>,
+    taker: &State<Taker>,
+    _user: User,
+) -> Result<Json<MarginResponse>, HttpApiProblem> {
+    let costs = taker
+        .initial_costs(
+            margin_request.offer_id,
+            margin_request.quantity,
+            margin_request.leverage,
+        )
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::BAD_REQUEST)
+                .title("Failed to calculate margin")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(MarginResponse {
+        margin: costs.margin,
+        opening_fee: costs.opening_fee.to_inner(),
+        complete_initial_costs: costs.total(),
+    }))
+}
+
 #[derive(RustEmbed)]
 #[folder = "../../taker-frontend/dist/taker"]
 struct Asset;
@@ -292,7 +765,7 @@ pub async fn post_withdraw_request(
                 .detail(format!("{e:#}"))
         })?;
 
-    Ok(projection::to_mempool_url(txid, *network.inner()))
+    Ok(projection::tx_url(txid, *network.inner()))
 }
 
 #[rocket::put("/sync")]
@@ -307,6 +780,195 @@ pub async fn put_sync_wallet(taker: &State<Taker>, _user: User) -> Result<(), Ht
     Ok(())
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiTokenRequest {
+    #[serde(default)]
+    label: Option<String>,
+    /// The CFDs the token authorizes actions on
+    order_ids: Vec<OrderId>,
+    actions: Vec<TokenAction>,
+    valid_for_hours: u64,
+}
+
+/// Issue an API token that authorizes only the given actions on the given CFDs.
+///
+/// The token is part of the response and cannot be retrieved again.
+#[rocket::post("/api-tokens", data = "<request>")]
+#[instrument(name = "POST /api-tokens", skip(api_tokens, rx, _user), err)]
+pub async fn post_api_token(
+    request: Json<ApiTokenRequest>,
+    api_tokens: &State<ApiTokens>,
+    rx: &State<FeedReceivers>,
+    _user: User,
+) -> Result<Json<api_tokens::IssuedApiToken>, HttpApiProblem> {
+    let request = request.into_inner();
+
+    let unknown = match rx.cfds.borrow().as_ref() {
+        Some(cfds) => request
+            .order_ids
+            .iter()
+            .filter(|order_id| !cfds.iter().any(|cfd| cfd.order_id == **order_id))
+            .map(OrderId::to_string)
+            .collect::<Vec<_>>(),
+        None => {
+            return Err(HttpApiProblem::new(StatusCode::SERVICE_UNAVAILABLE)
+                .title("CFDs not yet available")
+                .detail("CFDs are still being loaded from the database. Please retry later."))
+        }
+    };
+
+    if !unknown.is_empty() {
+        return Err(HttpApiProblem::new(StatusCode::BAD_REQUEST)
+            .title("Unknown CFDs")
+            .detail(format!("No CFDs with order ids {}", unknown.join(", "))));
+    }
+
+    let issued = api_tokens
+        .issue(api_tokens::NewApiToken {
+            label: request.label,
+            order_ids: request.order_ids,
+            actions: request.actions,
+            validity: Duration::from_secs(request.valid_for_hours.saturating_mul(60 * 60)),
+        })
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::BAD_REQUEST)
+                .title("Could not issue API token")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(issued))
+}
+
+/// All API tokens that were issued, including expired and revoked ones.
+#[rocket::get("/api-tokens")]
+#[instrument(name = "GET /api-tokens", skip_all, err)]
+pub async fn get_api_tokens(
+    api_tokens: &State<ApiTokens>,
+    _user: User,
+) -> Result<Json<Vec<ApiToken>>, HttpApiProblem> {
+    let tokens = api_tokens.list().await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Could not load API tokens")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(tokens))
+}
+
+/// Revoke an API token, it is rejected from then on.
+#[rocket::delete("/api-tokens/<id>")]
+#[instrument(name = "DELETE /api-tokens/<id>", skip(api_tokens, _user), err)]
+pub async fn delete_api_token(
+    id: i64,
+    api_tokens: &State<ApiTokens>,
+    _user: User,
+) -> Result<(), HttpApiProblem> {
+    api_tokens.revoke(id).await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::NOT_FOUND)
+            .title("Could not revoke API token")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(())
+}
+
+#[rocket::get("/cold-sweep")]
+#[instrument(name = "GET /cold-sweep", skip_all, err)]
+pub async fn get_cold_sweep_policy(
+    cold_sweep: &State<xtra::Address<cold_sweep::Actor>>,
+    _user: User,
+) -> Result<Json<Option<ColdSweepPolicy>>, HttpApiProblem> {
+    let policy = cold_sweep
+        .send(cold_sweep::GetPolicy)
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|policy| policy)
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Could not load cold sweep policy")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(policy))
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ColdSweepRequest {
+    enabled: bool,
+    address: bdk::bitcoin::Address,
+    /// Balance that stays in the wallet
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    threshold: Amount,
+    /// Highest fee rate in satoshis per vbyte at which funds are forwarded
+    max_fee_rate: f32,
+}
+
+#[rocket::put("/cold-sweep", data = "<request>")]
+#[instrument(name = "PUT /cold-sweep", skip(cold_sweep, _user), err)]
+pub async fn put_cold_sweep_policy(
+    request: Json<ColdSweepRequest>,
+    cold_sweep: &State<xtra::Address<cold_sweep::Actor>>,
+    network: &State<Network>,
+    _user: User,
+) -> Result<(), HttpApiProblem> {
+    let request = request.into_inner();
+
+    if request.address.network != *network.inner() {
+        return Err(HttpApiProblem::new(StatusCode::BAD_REQUEST)
+            .title("Invalid cold wallet address")
+            .detail(format!(
+                "Address is for {} but the taker runs on {}",
+                request.address.network,
+                network.inner()
+            )));
+    }
+
+    if request.max_fee_rate <= 0.0 {
+        return Err(HttpApiProblem::new(StatusCode::BAD_REQUEST)
+            .title("Invalid maximum fee rate")
+            .detail("Maximum fee rate has to be positive"));
+    }
+
+    cold_sweep
+        .send(cold_sweep::SetPolicy(ColdSweepPolicy {
+            enabled: request.enabled,
+            address: request.address,
+            threshold: request.threshold,
+            max_fee_rate: request.max_fee_rate,
+        }))
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|result| result)
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Could not update cold sweep policy")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(())
+}
+
+#[rocket::get("/cold-sweep/history")]
+#[instrument(name = "GET /cold-sweep/history", skip_all, err)]
+pub async fn get_cold_sweep_history(
+    cold_sweep: &State<xtra::Address<cold_sweep::Actor>>,
+    _user: User,
+) -> Result<Json<Vec<ColdSweep>>, HttpApiProblem> {
+    let history = cold_sweep
+        .send(cold_sweep::GetHistory)
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|history| history)
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Could not load cold sweep history")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(history))
+}
+
 #[rocket::get("/export")]
 #[instrument(name = "GET /export", skip_all)]
 pub async fn get_export_seed(
@@ -369,6 +1031,62 @@ pub async fn put_import_seed(
     Ok(())
 }
 
+/// All routes mounted at `/api`, see [`crate::openapi`] for their documentation.
+pub fn api_routes() -> Vec<rocket::Route> {
+    rocket::routes![
+        feed,
+        notifications,
+        post_order_request,
+        post_preflight,
+        post_calculate_margin,
+        get_funding_rate_history,
+        get_loss_limit,
+        post_loss_limit_override,
+        post_cfd_action,
+        post_close_all,
+        post_external_settlement,
+        put_price_levels,
+        get_cfds,
+        put_cfd_tag,
+        delete_cfd_tag,
+        post_withdraw_request,
+        put_sync_wallet,
+        get_cold_sweep_policy,
+        put_cold_sweep_policy,
+        get_cold_sweep_history,
+        post_api_token,
+        get_api_tokens,
+        delete_api_token,
+        shared_bin::routes::get_alive,
+        shared_bin::routes::get_health_check,
+        shared_bin::routes::get_metrics,
+        shared_bin::routes::get_version,
+        shared_bin::routes::get_electrum_status,
+        shared_bin::routes::get_ledger,
+        shared_bin::routes::get_events,
+        shared_bin::routes::get_cfd_timeline,
+        shared_bin::routes::get_telemetry,
+        shared_bin::routes::get_daily_marks,
+        shared_bin::routes::get_jobs,
+        shared_bin::routes::get_janitor_report,
+        shared_bin::routes::get_supervision_tree,
+        shared_bin::routes::get_cfd_backup,
+        shared_bin::routes::put_transcript,
+        shared_bin::routes::delete_transcript,
+        shared_bin::routes::get_transcripts,
+        shared_bin::routes::change_password,
+        shared_bin::routes::post_login,
+        shared_bin::routes::logout,
+        shared_bin::routes::is_authenticated,
+        shared_bin::openapi::get_openapi,
+    ]
+}
+
+/// Routes mounted at `/api` if the wallet seed is managed by the taker.
+pub fn managed_wallet_routes() -> Vec<rocket::Route> {
+    rocket::routes![get_export_seed, put_import_seed]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
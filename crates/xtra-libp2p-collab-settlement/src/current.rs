@@ -0,0 +1,11 @@
+pub mod maker;
+pub mod resume;
+pub mod taker;
+
+pub const PROTOCOL: &str = "/itchysats/collab-settlement/3.0.0";
+/// Version of [`PROTOCOL`] without sequence numbers, served until all takers upgraded
+pub const UNSEQUENCED_PROTOCOL: &str = "/itchysats/collab-settlement/2.0.0";
+pub const RESUME_PROTOCOL: &str = "/itchysats/collab-settlement/resume/1.0.0";
+
+/// Maximum size of an encoded message in bytes, for both the settlement and the resume protocol
+pub const MAX_FRAME_SIZE: usize = 64 * 1024;
@@ -0,0 +1,38 @@
+use crate::current;
+use crate::listener;
+use crate::protocol::Propose;
+use anyhow::Result;
+use model::Cfd;
+use model::CfdEvent;
+use model::SettlementProposal;
+use model::SettlementTransaction;
+
+pub use crate::listener::Accept;
+pub use crate::listener::Reject;
+
+/// Permanent actor to handle incoming substreams for the `/itchysats/collab-settlement/3.0.0` and
+/// `/itchysats/collab-settlement/2.0.0` protocols.
+pub type Actor<E> = listener::Actor<E, Current>;
+
+pub enum Current {}
+
+impl listener::Version for Current {
+    const PROTOCOL: &'static str = current::PROTOCOL;
+
+    const MAX_FRAME_SIZE: usize = current::MAX_FRAME_SIZE;
+
+    const COMPLETE_IF_SIGNATURE_NOT_SENT: bool = true;
+
+    fn start(
+        cfd: Cfd,
+        propose: &Propose,
+        n_payouts: usize,
+    ) -> Result<(CfdEvent, SettlementTransaction, SettlementProposal)> {
+        cfd.start_collab_settlement_maker_olivia_max(
+            propose.price,
+            n_payouts,
+            &propose.unsigned_tx,
+            propose.payout_address.clone(),
+        )
+    }
+}
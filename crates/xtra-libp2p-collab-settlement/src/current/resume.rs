@@ -0,0 +1,167 @@
+//! Resumption of collaborative settlements interrupted after the taker sent its signature.
+//!
+//! With the taker's signature the maker is able to finalize and publish the settlement
+//! transaction, even if the maker's signature never reached the taker. Upon reconnecting the
+//! taker asks the maker for the outcome of the settlement: either the maker finalized the
+//! transaction and sends it to the taker, or the maker never received the taker's signature and
+//! the settlement can safely be aborted.
+
+use crate::current::MAX_FRAME_SIZE;
+use crate::current::RESUME_PROTOCOL;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use asynchronous_codec::Framed;
+use bdk::bitcoin::Transaction;
+use bdk::bitcoin::Txid;
+use futures::SinkExt;
+use futures::StreamExt;
+use libp2p_core::PeerId;
+use model::hex_transaction;
+use model::OrderId;
+use serde::Deserialize;
+use serde::Serialize;
+use std::time::Duration;
+use tokio_extras::FutureExt;
+use xtra::Address;
+use xtra_libp2p::limited::LimitedJsonCodec;
+use xtra_libp2p::Endpoint;
+use xtra_libp2p::NewInboundSubstream;
+use xtra_libp2p::OpenSubstream;
+use xtra_productivity::xtra_productivity;
+
+/// The duration that the taker waits for the maker to report the outcome of a settlement
+const OUTCOME_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Serialize, Deserialize)]
+pub struct Resume {
+    pub id: OrderId,
+    /// The id of the settlement transaction the taker signed
+    pub txid: Txid,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum Outcome {
+    /// The maker finalized the settlement transaction and published it
+    Completed {
+        #[serde(with = "hex_transaction")]
+        spend_tx: Transaction,
+    },
+    /// The maker did not receive the taker's signature, the settlement can never be completed
+    Aborted,
+    /// The maker is still waiting for the taker's signature
+    Pending,
+}
+
+#[tracing::instrument(skip(endpoint))]
+pub async fn dialer(
+    endpoint: Address<Endpoint>,
+    order_id: OrderId,
+    counterparty: PeerId,
+    txid: Txid,
+) -> Result<Outcome> {
+    let substream = endpoint
+        .send(OpenSubstream::single_protocol(
+            counterparty,
+            RESUME_PROTOCOL,
+        ))
+        .await
+        .context("Endpoint is disconnected")?
+        .context("No connection to peer")?
+        .await
+        .context("Failed to open substream")?;
+    let mut framed = Framed::new(
+        substream,
+        LimitedJsonCodec::<Resume, Outcome>::new(MAX_FRAME_SIZE),
+    );
+
+    framed
+        .send(Resume { id: order_id, txid })
+        .await
+        .context("Failed to send Resume")?;
+
+    let outcome = framed
+        .next()
+        .timeout(OUTCOME_TIMEOUT, || tracing::debug_span!("receive outcome"))
+        .await
+        .with_context(|| {
+            format!(
+                "Maker did not report the outcome within {} seconds.",
+                OUTCOME_TIMEOUT.as_secs()
+            )
+        })?
+        .context("End of stream while receiving Outcome")?
+        .context("Failed to decode Outcome")?;
+
+    Ok(outcome)
+}
+
+/// Permanent actor to handle incoming substreams for the
+/// `/itchysats/collab-settlement/resume/1.0.0` protocol on the maker side.
+///
+/// The outcome is derived from the CFD's event log, no state besides it is needed.
+pub struct Actor {
+    db: sqlite_db::Connection,
+}
+
+impl Actor {
+    pub fn new(db: sqlite_db::Connection) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: NewInboundSubstream, ctx: &mut xtra::Context<Self>) {
+        let NewInboundSubstream { peer_id, stream } = msg;
+        let db = self.db.clone();
+
+        let task = async move {
+            let mut framed = Framed::new(
+                stream,
+                LimitedJsonCodec::<Outcome, Resume>::new(MAX_FRAME_SIZE),
+            );
+
+            let Resume { id, txid } = framed
+                .next()
+                .await
+                .context("End of stream while receiving Resume")?
+                .context("Failed to decode Resume")?;
+
+            let cfd = db
+                .load_open_cfd::<model::Cfd>(id, ())
+                .await
+                .with_context(|| format!("Failed to load CFD {id}"))?;
+            cfd.verify_counterparty_peer_id(&peer_id.into())?;
+
+            let outcome = match cfd.collaborative_settlement_spend_tx() {
+                Some(spend_tx) if spend_tx.txid() == txid => Outcome::Completed {
+                    spend_tx: spend_tx.clone(),
+                },
+                None if cfd.is_in_collaborative_settlement() => Outcome::Pending,
+                Some(_) | None => Outcome::Aborted,
+            };
+
+            framed
+                .send(outcome)
+                .await
+                .context("Failed to send Outcome")?;
+
+            anyhow::Ok(())
+        };
+
+        let err_handler = move |e: anyhow::Error| async move {
+            tracing::warn!(%peer_id, "Failed to resume collab settlement: {e:#}")
+        };
+
+        let this = ctx.address().expect("self to be alive");
+        tokio_extras::spawn_fallible(&this, task, err_handler);
+    }
+}
@@ -0,0 +1,463 @@
+use crate::current::resume;
+use crate::current::MAX_FRAME_SIZE;
+use crate::current::PROTOCOL;
+use crate::current::UNSEQUENCED_PROTOCOL;
+use crate::protocol::*;
+use anyhow::anyhow;
+use anyhow::ensure;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use bdk::bitcoin::Address;
+use bdk::bitcoin::Script;
+use bdk::bitcoin::Transaction;
+use bdk::bitcoin::Txid;
+use futures::SinkExt;
+use futures::StreamExt;
+use model::libp2p::PeerId;
+use model::CollaborativeSettlement;
+use model::ExecuteOnCfd;
+use model::OrderId;
+use model::Price;
+use model::SettlementTransaction;
+use model::Timestamp;
+use sqlite_db::collab_settlement::PendingCollabSettlement;
+use std::time::Duration;
+use tokio_extras::FutureExt;
+use xtra::message_channel::MessageChannel;
+use xtra_libp2p::endpoint;
+use xtra_libp2p::sequenced::Session;
+use xtra_libp2p::sequenced::SessionId;
+use xtra_libp2p::Endpoint;
+use xtra_productivity::xtra_productivity;
+use xtras::SendAsyncSafe;
+use xtras::SendInterval;
+
+/// Interval at which the outcome of interrupted settlements is requested from the maker
+///
+/// The outcome is also requested whenever a connection gets established, the interval only covers
+/// settlements the maker had not yet decided on at the time.
+const RESUME_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+pub struct Actor<E> {
+    endpoint: xtra::Address<Endpoint>,
+    executor: E,
+    n_payouts: usize,
+    db: sqlite_db::Connection,
+    monitor: MessageChannel<MonitorPendingCollaborativeSettlement, ()>,
+}
+
+/// Watch for the settlement transaction of a collaborative settlement that got interrupted after
+/// we sent our signature, in case the counterparty publishes it.
+pub struct MonitorPendingCollaborativeSettlement {
+    pub order_id: OrderId,
+    pub tx: (Txid, Script),
+}
+
+impl<E> Actor<E> {
+    pub fn new(
+        endpoint: xtra::Address<Endpoint>,
+        executor: E,
+        n_payouts: usize,
+        db: sqlite_db::Connection,
+        monitor: MessageChannel<MonitorPendingCollaborativeSettlement, ()>,
+    ) -> Self {
+        Self {
+            endpoint,
+            executor,
+            n_payouts,
+            db,
+            monitor,
+        }
+    }
+}
+
+impl<E> Actor<E>
+where
+    E: ExecuteOnCfd + Clone + Send + Sync + 'static,
+{
+    fn resume_settlements(&self, ctx: &mut xtra::Context<Self>) {
+        let db = self.db.clone();
+        let endpoint = self.endpoint.clone();
+        let executor = self.executor.clone();
+
+        let task = async move {
+            for settlement in db.load_pending_collab_settlements().await? {
+                let order_id = settlement.order_id;
+
+                if let Err(e) = resume_settlement(settlement, &db, &endpoint, &executor).await {
+                    tracing::debug!(%order_id, "Failed to resume collaborative settlement: {e:#}");
+                }
+            }
+
+            anyhow::Ok(())
+        };
+
+        let err_handler = |e: anyhow::Error| async move {
+            tracing::warn!("Failed to load pending collaborative settlements: {e:#}")
+        };
+
+        let this = ctx.address().expect("self to be alive");
+        tokio_extras::spawn_fallible(&this, task, err_handler);
+    }
+}
+
+#[async_trait]
+impl<E> xtra::Actor for Actor<E>
+where
+    E: ExecuteOnCfd + Clone + Send + Sync + 'static,
+{
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we just started");
+
+        // The monitor does not know about pending settlements after a restart
+        match self.db.load_pending_collab_settlements().await {
+            Ok(settlements) => {
+                for settlement in settlements {
+                    if let Err(e) = monitor_settlement(
+                        settlement.order_id,
+                        &settlement.unsigned_tx,
+                        &self.monitor,
+                    )
+                    .await
+                    {
+                        tracing::warn!(order_id = %settlement.order_id, "Failed to monitor pending collaborative settlement: {e:#}");
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load pending collaborative settlements: {e:#}");
+            }
+        }
+
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(
+                RESUME_INTERVAL,
+                || ResumeSettlements,
+                xtras::IncludeSpan::Never,
+            ),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[derive(Clone)]
+pub struct Settle {
+    pub order_id: OrderId,
+    pub price: Price,
+    pub maker_peer_id: PeerId,
+    /// External address to send the payout to instead of the wallet
+    pub payout_address: Option<Address>,
+}
+
+/// Request the outcome of all interrupted settlements from the maker.
+#[derive(Clone, Copy)]
+struct ResumeSettlements;
+
+#[xtra_productivity]
+impl<E> Actor<E>
+where
+    E: ExecuteOnCfd + Clone + Send + Sync + 'static,
+{
+    pub async fn handle(&mut self, msg: Settle, ctx: &mut xtra::Context<Self>) -> Result<()> {
+        let Settle {
+            order_id,
+            price,
+            maker_peer_id,
+            payout_address,
+        } = msg;
+
+        let (collab_settlement_tx, _) = self
+            .executor
+            .execute(order_id, |cfd| {
+                cfd.start_collab_settlement_taker(price, self.n_payouts, payout_address.clone())
+            })
+            .await
+            .context("could not start closing position")?;
+
+        tokio_extras::spawn_fallible(
+            &ctx.address().expect("self to be alive"),
+            {
+                let endpoint = self.endpoint.clone();
+                let executor = self.executor.clone();
+                async move {
+                    let settlement = dialer(
+                        endpoint,
+                        order_id,
+                        maker_peer_id.inner(),
+                        collab_settlement_tx.clone(),
+                        payout_address,
+                    )
+                    .await?;
+
+                    emit_completed(order_id, settlement, &executor).await;
+                    Ok(())
+                }
+            },
+            {
+                let executor = self.executor.clone();
+                let db = self.db.clone();
+                let monitor = self.monitor.clone();
+                move |e| async move {
+                    match e {
+                        DialerFailed::AfterSendingSignature { unsigned_tx, error } => {
+                            // The maker may still publish the transaction, we can only fail the
+                            // settlement once the maker told us that it did not
+                            tracing::warn!(%order_id, "Collaborative settlement interrupted after sending signature: {error:#}");
+
+                            if let Err(e) =
+                                suspend_settlement(order_id, unsigned_tx, &db, &monitor).await
+                            {
+                                emit_failed(
+                                    order_id,
+                                    e.context("Failed to suspend collaborative settlement"),
+                                    &executor,
+                                )
+                                .await;
+                            }
+                        }
+                        e @ DialerFailed::BeforeSendingSignature { .. } => {
+                            emit_failed(order_id, anyhow!(e), &executor).await;
+                        }
+                        DialerFailed::Rejected => {
+                            emit_rejected(order_id, &executor).await;
+                        }
+                    }
+                }
+            },
+        );
+
+        Ok(())
+    }
+
+    async fn handle(&mut self, _: ResumeSettlements, ctx: &mut xtra::Context<Self>) {
+        self.resume_settlements(ctx);
+    }
+
+    async fn handle(&mut self, _: endpoint::ConnectionEstablished, ctx: &mut xtra::Context<Self>) {
+        self.resume_settlements(ctx);
+    }
+}
+
+/// The duration that the taker waits until a decision (accept/reject) is expected from the maker
+///
+/// If the maker does not respond within `DECISION_TIMEOUT` seconds then the taker will fail the
+/// collab settlement.
+const DECISION_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[tracing::instrument(skip(endpoint, collab_settlement_tx))]
+pub async fn dialer(
+    endpoint: xtra::Address<Endpoint>,
+    order_id: OrderId,
+    counterparty: libp2p_core::PeerId,
+    collab_settlement_tx: SettlementTransaction,
+    payout_address: Option<Address>,
+) -> Result<CollaborativeSettlement, DialerFailed> {
+    let mut framed = Session::<DialerMessage, ListenerMessage>::dial(
+        &endpoint,
+        counterparty,
+        (PROTOCOL, UNSEQUENCED_PROTOCOL),
+        SessionId::new(order_id),
+        MAX_FRAME_SIZE,
+    )
+    .await?;
+
+    let unsigned_tx = collab_settlement_tx.unsigned_transaction().clone();
+
+    framed
+        .send(DialerMessage::Propose(Propose {
+            id: order_id,
+            price: collab_settlement_tx.price(),
+            unsigned_tx: unsigned_tx.clone(),
+            payout_address,
+        }))
+        .await
+        .context("Failed to send Propose")?;
+
+    if let Decision::Reject = framed
+        .next()
+        .timeout(DECISION_TIMEOUT, || {
+            tracing::debug_span!("receive decision")
+        })
+        .await
+        .with_context(|| {
+            format!(
+                "Maker did not accept/reject within {} seconds.",
+                DECISION_TIMEOUT.as_secs()
+            )
+        })?
+        .context("End of stream while receiving Decision")?
+        .context("Failed to decode Decision")?
+        .into_decision()?
+    {
+        return Err(DialerFailed::Rejected);
+    }
+
+    framed
+        .send(DialerMessage::DialerSignature(DialerSignature {
+            dialer_signature: collab_settlement_tx.own_signature(),
+        }))
+        .await
+        .context("Failed to send DialerSignature")?;
+
+    let listener_signature = match framed.next().await {
+        Some(Ok(msg)) => msg.into_listener_signature()?,
+        Some(Err(_)) | None => {
+            return Err(DialerFailed::AfterSendingSignature {
+                unsigned_tx: unsigned_tx.clone(),
+                error: anyhow!("failed to receive ListenerSignature"),
+            });
+        }
+    };
+
+    let collab_settlement_tx = match collab_settlement_tx
+        .recv_counterparty_signature(listener_signature.listener_signature)
+    {
+        Ok(collab_settlement_tx) => collab_settlement_tx,
+        Err(error) => {
+            return Err(DialerFailed::AfterSendingSignature {
+                unsigned_tx: unsigned_tx.clone(),
+                error,
+            });
+        }
+    };
+
+    let settlement =
+        collab_settlement_tx
+            .finalize()
+            .map_err(|e| DialerFailed::AfterSendingSignature {
+                unsigned_tx: unsigned_tx.clone(),
+                error: e,
+            })?;
+
+    Ok(settlement)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DialerFailed {
+    #[error("Rejected")]
+    Rejected,
+    #[error("Failed after sending signature")]
+    AfterSendingSignature {
+        unsigned_tx: Transaction,
+        error: anyhow::Error,
+    },
+    #[error("Failed before sending signature")]
+    BeforeSendingSignature { source: anyhow::Error },
+}
+
+impl From<anyhow::Error> for DialerFailed {
+    fn from(source: anyhow::Error) -> Self {
+        Self::BeforeSendingSignature { source }
+    }
+}
+
+/// Keep the settlement pending until the maker reported its outcome.
+async fn suspend_settlement(
+    order_id: OrderId,
+    unsigned_tx: Transaction,
+    db: &sqlite_db::Connection,
+    monitor: &MessageChannel<MonitorPendingCollaborativeSettlement, ()>,
+) -> Result<()> {
+    monitor_settlement(order_id, &unsigned_tx, monitor).await?;
+
+    db.insert_pending_collab_settlement(&PendingCollabSettlement {
+        order_id,
+        unsigned_tx,
+        timestamp: Timestamp::now(),
+    })
+    .await?;
+
+    Ok(())
+}
+
+async fn monitor_settlement(
+    order_id: OrderId,
+    unsigned_tx: &Transaction,
+    monitor: &MessageChannel<MonitorPendingCollaborativeSettlement, ()>,
+) -> Result<()> {
+    // Signatures are not part of the txid, the signed transaction has the same id
+    let txid = unsigned_tx.txid();
+    let script = unsigned_tx
+        .output
+        .first()
+        .context("Settlement transaction without outputs")?
+        .script_pubkey
+        .clone();
+
+    monitor
+        .send_async_safe(MonitorPendingCollaborativeSettlement {
+            order_id,
+            tx: (txid, script),
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Complete or abort an interrupted settlement depending on the outcome reported by the maker.
+async fn resume_settlement<E>(
+    settlement: PendingCollabSettlement,
+    db: &sqlite_db::Connection,
+    endpoint: &xtra::Address<Endpoint>,
+    executor: &E,
+) -> Result<()>
+where
+    E: ExecuteOnCfd,
+{
+    let PendingCollabSettlement {
+        order_id,
+        unsigned_tx,
+        ..
+    } = settlement;
+
+    let cfd = match db.load_open_cfd::<model::Cfd>(order_id, ()).await {
+        Ok(cfd) if cfd.is_in_collaborative_settlement() => cfd,
+        // Resolved in the meantime, e.g. because the monitor found the published transaction
+        Ok(_) | Err(sqlite_db::Error::OpenCfdNotFound) => {
+            db.delete_pending_collab_settlement(order_id).await?;
+            return Ok(());
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let maker = cfd
+        .counterparty_peer_id()
+        .context("CFD without counterparty peer id")?;
+    let txid = unsigned_tx.txid();
+
+    match resume::dialer(endpoint.clone(), order_id, maker.inner(), txid).await? {
+        resume::Outcome::Completed { spend_tx } => {
+            ensure!(
+                spend_tx.txid() == txid,
+                "Maker completed settlement with unexpected transaction {}",
+                spend_tx.txid()
+            );
+
+            executor
+                .execute(order_id, |cfd| {
+                    cfd.complete_collaborative_settlement_by_counterparty(spend_tx)
+                })
+                .await?;
+        }
+        resume::Outcome::Aborted => {
+            emit_failed(
+                order_id,
+                anyhow!("Maker aborted interrupted settlement"),
+                executor,
+            )
+            .await;
+        }
+        resume::Outcome::Pending => {
+            tracing::debug!(%order_id, "Maker did not yet decide on interrupted settlement");
+            return Ok(());
+        }
+    }
+
+    db.delete_pending_collab_settlement(order_id).await?;
+
+    Ok(())
+}
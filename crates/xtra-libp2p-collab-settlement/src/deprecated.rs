@@ -0,0 +1,6 @@
+pub mod maker;
+
+pub const PROTOCOL: &str = "/itchysats/collab-settlement/1.0.0";
+
+/// Maximum size of an encoded message in bytes
+pub const MAX_FRAME_SIZE: usize = 64 * 1024;
@@ -0,0 +1,38 @@
+use crate::deprecated;
+use crate::listener;
+use crate::protocol::Propose;
+use anyhow::Result;
+use model::Cfd;
+use model::CfdEvent;
+use model::SettlementProposal;
+use model::SettlementTransaction;
+
+pub use crate::listener::Accept;
+pub use crate::listener::Reject;
+
+/// Permanent actor to handle incoming substreams for the `/itchysats/collab-settlement/1.0.0`
+/// protocol.
+pub type Actor<E> = listener::Actor<E, Deprecated>;
+
+pub enum Deprecated {}
+
+impl listener::Version for Deprecated {
+    const PROTOCOL: &'static str = deprecated::PROTOCOL;
+
+    const MAX_FRAME_SIZE: usize = deprecated::MAX_FRAME_SIZE;
+
+    // Takers of the deprecated version do not resume interrupted settlements
+    const COMPLETE_IF_SIGNATURE_NOT_SENT: bool = false;
+
+    fn start(
+        cfd: Cfd,
+        propose: &Propose,
+        n_payouts: usize,
+    ) -> Result<(CfdEvent, SettlementTransaction, SettlementProposal)> {
+        cfd.start_collab_settlement_maker_double_initial(
+            propose.price,
+            n_payouts,
+            &propose.unsigned_tx,
+        )
+    }
+}
@@ -1,4 +1,6 @@
 mod current;
 pub mod deprecated;
+mod listener;
+pub mod protocol;
 
 pub use current::*;
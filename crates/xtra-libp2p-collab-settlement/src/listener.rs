@@ -1,71 +1,124 @@
-use crate::collab_settlement::protocol::*;
-use crate::command;
+//! Listener side of the collaborative settlement protocol, shared by all versions of the protocol.
+//!
+//! The versions only differ in the maximum frame size, in how the maker builds the settlement
+//! transaction and in how a failure to send the maker's signature is handled. These differences
+//! are captured by [`Version`]. Whether the taker numbers its messages is detected per session.
+
+use crate::protocol::*;
 use anyhow::anyhow;
 use anyhow::Context;
 use anyhow::Error;
 use anyhow::Result;
 use async_trait::async_trait;
-use asynchronous_codec::Framed;
-use asynchronous_codec::JsonCodec;
 use futures::SinkExt;
 use futures::StreamExt;
 use libp2p_core::PeerId;
+use model::Cfd;
+use model::CfdEvent;
 use model::CollaborativeSettlement;
+use model::ExecuteOnCfd;
 use model::OrderId;
 use model::SettlementProposal;
 use model::SettlementTransaction;
 use std::collections::HashMap;
+use std::marker::PhantomData;
 use tokio_extras::FutureExt;
+use xtra_libp2p::sequenced::Session;
+use xtra_libp2p::sequenced::Sessions;
 use xtra_libp2p::NewInboundSubstream;
-use xtra_libp2p::Substream;
 use xtra_productivity::xtra_productivity;
 
+/// A version of the collaborative settlement protocol as seen by the listener.
+pub trait Version: Send + 'static {
+    const PROTOCOL: &'static str;
+
+    /// Maximum size of an encoded message in bytes
+    const MAX_FRAME_SIZE: usize;
+
+    /// Whether the settlement is completed even if the maker's signature could not be sent.
+    ///
+    /// This is only safe if the taker asks for the outcome of interrupted settlements.
+    const COMPLETE_IF_SIGNATURE_NOT_SENT: bool;
+
+    /// Verify the proposal of the taker and build the settlement transaction.
+    fn start(
+        cfd: Cfd,
+        propose: &Propose,
+        n_payouts: usize,
+    ) -> Result<(CfdEvent, SettlementTransaction, SettlementProposal)>;
+}
+
 type ListenerConnection = (
-    Framed<Substream, JsonCodec<ListenerMessage, DialerMessage>>,
+    Session<ListenerMessage, DialerMessage>,
     SettlementTransaction,
     SettlementProposal,
     PeerId,
 );
 
-/// Permanent actor to handle incoming substreams for the `/itchysats/collab-settlement/1.0.0`
+/// Permanent actor to handle incoming substreams for a version of the collaborative settlement
 /// protocol.
 ///
 /// There is only one instance of this actor for all connections, meaning we must always spawn a
 /// task whenever we interact with a substream to not block the execution of other connections.
-pub struct Actor {
+pub struct Actor<E, V: Version> {
     pending_protocols: HashMap<OrderId, ListenerConnection>,
-    executor: command::Executor,
+    executor: E,
     n_payouts: usize,
+    sessions: Sessions,
+    version: PhantomData<V>,
 }
 
-impl Actor {
-    pub fn new(executor: command::Executor, n_payouts: usize) -> Self {
+impl<E, V: Version> Actor<E, V> {
+    pub fn new(executor: E, n_payouts: usize) -> Self {
         Self {
             pending_protocols: HashMap::default(),
             executor,
             n_payouts,
+            sessions: Sessions::default(),
+            version: PhantomData,
         }
     }
 }
 
 #[async_trait]
-impl xtra::Actor for Actor {
+impl<E, V> xtra::Actor for Actor<E, V>
+where
+    E: Send + Sync + 'static,
+    V: Version,
+{
     type Stop = ();
 
     async fn stopped(self) -> Self::Stop {}
 }
 
 #[xtra_productivity]
-impl Actor {
+impl<E, V> Actor<E, V>
+where
+    E: ExecuteOnCfd + Clone + Send + Sync + 'static,
+    V: Version,
+{
     async fn handle(&mut self, msg: NewInboundSubstream, ctx: &mut xtra::Context<Self>) {
         let NewInboundSubstream { peer_id, stream } = msg;
         let address = ctx.address().expect("we are alive");
+        let sessions = self.sessions.clone();
 
         tokio_extras::spawn_fallible(
             &address.clone(),
             async move {
-                let mut framed =
-                    Framed::new(stream, JsonCodec::<ListenerMessage, DialerMessage>::new());
+                let mut framed = match sessions
+                    .accept::<ListenerMessage, DialerMessage>(
+                        peer_id,
+                        stream,
+                        V::PROTOCOL,
+                        V::MAX_FRAME_SIZE,
+                    )
+                    .await
+                    .context("Failed to decode Propose")?
+                {
+                    Some(framed) => framed,
+                    // The taker resumed a settlement that is already running
+                    None => return Ok(()),
+                };
 
                 let propose = framed
                     .next()
@@ -89,10 +142,7 @@ impl Actor {
             },
         );
     }
-}
 
-#[xtra_productivity]
-impl Actor {
     async fn handle(&mut self, msg: ProposeReceived) {
         let ProposeReceived {
             propose,
@@ -100,16 +150,13 @@ impl Actor {
             peer_id,
         } = msg;
         let order_id = propose.id;
+        let n_payouts = self.n_payouts;
 
         let result = self
             .executor
             .execute(order_id, |cfd| {
                 cfd.verify_counterparty_peer_id(&peer_id.into())?;
-                cfd.start_collab_settlement_maker_olivia_max(
-                    propose.price,
-                    self.n_payouts,
-                    &propose.unsigned_tx,
-                )
+                V::start(cfd, &propose, n_payouts)
             })
             .await
             .context("Failed to start collab settlement protocol");
@@ -198,12 +245,14 @@ impl Actor {
                 let executor = self.executor.clone();
                 move |failed| async move {
                     match failed {
-                        e @ Failed::BeforeReceiving { .. } => {
-                            emit_failed(order_id, anyhow!(e), &executor).await;
+                        Failed::AfterReceiving { settlement, source }
+                            if V::COMPLETE_IF_SIGNATURE_NOT_SENT =>
+                        {
+                            // The taker learns about the outcome when resuming the settlement
+                            tracing::warn!(%order_id, "Failed to send signature to taker, completing settlement regardless: {source:#}");
+                            emit_completed(order_id, settlement, &executor).await;
                         }
-                        e @ Failed::AfterReceiving { .. } => {
-                            // TODO: proceed with the transaction when taker will be able to handle
-                            // that case.
+                        e => {
                             emit_failed(order_id, anyhow!(e), &executor).await;
                         }
                     }
@@ -242,7 +291,7 @@ impl Actor {
 
 struct ProposeReceived {
     propose: Propose,
-    framed: Framed<Substream, JsonCodec<ListenerMessage, DialerMessage>>,
+    framed: Session<ListenerMessage, DialerMessage>,
     peer_id: PeerId,
 }
 
@@ -0,0 +1,215 @@
+//! Messages of the collaborative settlement protocol and the CFD events emitted by both roles.
+//!
+//! The messages are the same for all versions of the protocol, only the framing differs. The
+//! `payout_address` of [`Propose`] is omitted if not set, hence messages of the current version
+//! are understood by the deprecated one.
+
+use anyhow::anyhow;
+use anyhow::Result;
+use bdk::bitcoin::secp256k1::ecdsa::Signature;
+use bdk::bitcoin::Address;
+use bdk::bitcoin::Transaction;
+use model::hex_transaction;
+use model::CollaborativeSettlement;
+use model::ExecuteOnCfd;
+use model::OrderId;
+use model::Price;
+use serde::Deserialize;
+use serde::Serialize;
+use std::time::Duration;
+
+pub const SETTLEMENT_MSG_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Serialize, Deserialize)]
+pub enum DialerMessage {
+    Propose(Propose),
+    DialerSignature(DialerSignature),
+}
+
+impl DialerMessage {
+    pub fn into_propose(self) -> Result<Propose> {
+        match self {
+            DialerMessage::Propose(propose) => Ok(propose),
+            DialerMessage::DialerSignature(_) => {
+                Err(anyhow!("Expected Propose but got DialerSignature"))
+            }
+        }
+    }
+
+    pub fn into_dialer_signature(self) -> Result<DialerSignature> {
+        match self {
+            DialerMessage::DialerSignature(dialer_signature) => Ok(dialer_signature),
+            DialerMessage::Propose(_) => Err(anyhow!("Expected DialerSignature but got Propose")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum ListenerMessage {
+    Decision(Decision),
+    ListenerSignature(ListenerSignature),
+}
+
+impl ListenerMessage {
+    pub fn into_decision(self) -> Result<Decision> {
+        match self {
+            ListenerMessage::Decision(decision) => Ok(decision),
+            ListenerMessage::ListenerSignature(_) => {
+                Err(anyhow!("Expected Decision but got ListenerSignature"))
+            }
+        }
+    }
+
+    pub fn into_listener_signature(self) -> Result<ListenerSignature> {
+        match self {
+            ListenerMessage::ListenerSignature(listener_signature) => Ok(listener_signature),
+            ListenerMessage::Decision(_) => {
+                Err(anyhow!("Expected ListenerSignature but got Decision"))
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Propose {
+    pub id: OrderId,
+    pub price: Price,
+    /// The transaction that is being proposed to close the protocol.
+    ///
+    /// Sending the full transaction allows the listening side to verify, how exactly the dialing
+    /// side wants to perform collaborative settlement.
+    #[serde(with = "hex_transaction")]
+    pub unsigned_tx: Transaction,
+    /// External address the dialing taker wants its payout to be sent to.
+    ///
+    /// Absent if the payout goes to the taker's address of the DLC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payout_address: Option<Address>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum Decision {
+    Accept,
+    Reject,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct DialerSignature {
+    pub dialer_signature: Signature,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct ListenerSignature {
+    pub listener_signature: Signature,
+}
+
+pub(crate) async fn emit_completed<E>(
+    order_id: OrderId,
+    settlement: CollaborativeSettlement,
+    executor: &E,
+) where
+    E: ExecuteOnCfd,
+{
+    if let Err(e) = executor
+        .execute(order_id, |cfd| {
+            Ok(cfd.complete_collaborative_settlement(settlement))
+        })
+        .await
+    {
+        tracing::error!(%order_id, "Failed to execute `complete_collaborative_settlement` command: {e:#}");
+    }
+}
+
+pub(crate) async fn emit_rejected<E>(order_id: OrderId, executor: &E)
+where
+    E: ExecuteOnCfd,
+{
+    if let Err(e) = executor
+        .execute(order_id, |cfd| {
+            Ok(cfd.reject_collaborative_settlement(anyhow!("maker decision")))
+        })
+        .await
+    {
+        tracing::error!(%order_id, "Failed to execute `reject_collaborative_settlement` command: {e:#}")
+    }
+}
+
+pub(crate) async fn emit_failed<E>(order_id: OrderId, e: anyhow::Error, executor: &E)
+where
+    E: ExecuteOnCfd,
+{
+    if let Err(e) = executor
+        .execute(order_id, |cfd| Ok(cfd.fail_collaborative_settlement(e)))
+        .await
+    {
+        tracing::error!(%order_id, "Failed to execute `fail_collaborative_settlement` command: {e:#}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xtra_libp2p::wire_fixtures::Fixtures;
+
+    /// Messages of the protocol versions without sequence numbers, which do not differ otherwise
+    const FIXTURES: Fixtures = Fixtures::new("./src/wire_fixtures");
+    const SEQUENCED_FIXTURES: Fixtures = Fixtures::sequenced("./src/wire_fixtures/3.0.0");
+
+    #[test]
+    fn propose_matches_recording() {
+        let propose = FIXTURES
+            .round_trip::<DialerMessage>("propose.json")
+            .into_propose()
+            .unwrap();
+
+        assert!(propose.payout_address.is_none());
+        assert_eq!(propose.unsigned_tx.output.len(), 2);
+    }
+
+    #[test]
+    fn propose_with_payout_address_matches_recording() {
+        let propose = FIXTURES
+            .round_trip::<DialerMessage>("propose_with_payout_address.json")
+            .into_propose()
+            .unwrap();
+
+        assert!(propose.payout_address.is_some());
+    }
+
+    #[test]
+    fn sequenced_propose_matches_recording() {
+        let propose = SEQUENCED_FIXTURES
+            .round_trip::<DialerMessage>("propose.json")
+            .into_propose()
+            .unwrap();
+
+        assert!(propose.payout_address.is_some());
+    }
+
+    #[test]
+    fn decisions_match_recording() {
+        let accept = FIXTURES
+            .round_trip::<ListenerMessage>("decision_accept.json")
+            .into_decision()
+            .unwrap();
+        let reject = FIXTURES
+            .round_trip::<ListenerMessage>("decision_reject.json")
+            .into_decision()
+            .unwrap();
+
+        assert!(matches!(accept, Decision::Accept));
+        assert!(matches!(reject, Decision::Reject));
+    }
+
+    #[test]
+    fn signatures_match_recording() {
+        FIXTURES
+            .round_trip::<DialerMessage>("dialer_signature.json")
+            .into_dialer_signature()
+            .unwrap();
+        FIXTURES
+            .round_trip::<ListenerMessage>("listener_signature.json")
+            .into_listener_signature()
+            .unwrap();
+    }
+}
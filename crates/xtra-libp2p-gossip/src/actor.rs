@@ -0,0 +1,308 @@
+use crate::protocol;
+use crate::protocol::Message;
+use crate::protocol::MessageId;
+use crate::MAX_HOPS;
+use crate::MAX_MESSAGES_PER_PEER_PER_MINUTE;
+use crate::MAX_MESSAGE_SIZE;
+use crate::PROTOCOL;
+use crate::SEEN_TTL;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::time::Duration;
+use std::time::Instant;
+use tokio_extras::spawn_fallible;
+use xtra::prelude::MessageChannel;
+use xtra_libp2p::endpoint;
+use xtra_libp2p::libp2p::PeerId;
+use xtra_libp2p::Endpoint;
+use xtra_libp2p::GetConnectionStats;
+use xtra_libp2p::NewInboundSubstream;
+use xtra_libp2p::OpenSubstream;
+use xtra_productivity::xtra_productivity;
+
+/// Length of the window in which the number of messages received from a peer is limited
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Publishes messages to and relays messages between all connected peers.
+///
+/// Messages are only relayed for topics this node is subscribed to, and only after the subscriber
+/// accepted the message. Subscribers therefore decide which messages are spam.
+pub struct Actor {
+    endpoint: xtra::Address<Endpoint>,
+    subscriptions: HashMap<String, MessageChannel<Received, Result<()>>>,
+    connected_peers: HashSet<PeerId>,
+    seen: HashMap<MessageId, Instant>,
+    received_per_peer: HashMap<PeerId, (Instant, u32)>,
+}
+
+impl Actor {
+    /// Construct a gossip node that relays messages of the topics in `subscriptions`.
+    pub fn new(
+        endpoint: xtra::Address<Endpoint>,
+        subscriptions: HashMap<String, MessageChannel<Received, Result<()>>>,
+    ) -> Self {
+        Self {
+            endpoint,
+            subscriptions,
+            connected_peers: HashSet::default(),
+            seen: HashMap::default(),
+            received_per_peer: HashMap::default(),
+        }
+    }
+
+    /// Records the message as seen and returns whether it was seen before.
+    fn already_seen(&mut self, id: MessageId, now: Instant) -> bool {
+        self.seen
+            .retain(|_, seen_at| now.duration_since(*seen_at) < SEEN_TTL);
+
+        self.seen.insert(id, now).is_some()
+    }
+
+    /// Records a message received from `peer` and returns whether it is within the rate limit.
+    fn within_rate_limit(&mut self, peer: PeerId, now: Instant) -> bool {
+        let (window_start, count) = self.received_per_peer.entry(peer).or_insert((now, 0));
+
+        if now.duration_since(*window_start) >= RATE_LIMIT_WINDOW {
+            *window_start = now;
+            *count = 0;
+        }
+        *count += 1;
+
+        *count <= MAX_MESSAGES_PER_PEER_PER_MINUTE
+    }
+
+    fn send_to_peers(
+        &self,
+        message: Message,
+        except: Option<PeerId>,
+        ctx: &mut xtra::Context<Self>,
+    ) {
+        let this = ctx.address().expect("self to be alive");
+
+        for peer_id in self
+            .connected_peers
+            .iter()
+            .copied()
+            .filter(|peer_id| Some(*peer_id) != except)
+        {
+            let endpoint = self.endpoint.clone();
+            let message = message.clone();
+
+            let task = async move {
+                let stream = endpoint
+                    .send(OpenSubstream::single_protocol(peer_id, PROTOCOL))
+                    .await??
+                    .await?;
+
+                protocol::send(stream, message).await?;
+
+                anyhow::Ok(())
+            };
+
+            let err_handler = move |e: anyhow::Error| async move {
+                match e.downcast_ref::<xtra_libp2p::Error>() {
+                    Some(xtra_libp2p::Error::ProtocolNotSupportedByPeer) => {
+                        // Not every peer takes part in gossip
+                    }
+                    _ => tracing::debug!(%peer_id, "Failed to send gossip message: {e:#}"),
+                }
+            };
+
+            spawn_fallible(&this, task, err_handler);
+        }
+    }
+}
+
+/// Publish `data` to all peers subscribed to `topic`.
+#[derive(Debug, Clone)]
+pub struct Publish {
+    pub topic: String,
+    pub data: Vec<u8>,
+}
+
+/// A message received for a subscribed topic.
+///
+/// The message is only relayed to other peers if the subscriber returns `Ok`.
+#[derive(Debug, Clone)]
+pub struct Received {
+    /// The peer the message was received from, not necessarily its publisher
+    pub source: PeerId,
+    pub topic: String,
+    pub data: Vec<u8>,
+}
+
+struct Inbound {
+    source: PeerId,
+    message: Message,
+}
+
+struct Relay {
+    source: PeerId,
+    message: Message,
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: Publish, ctx: &mut xtra::Context<Self>) {
+        let Publish { topic, data } = msg;
+
+        let message = Message {
+            topic,
+            data,
+            hops: 0,
+        };
+
+        // Peers echoing our own messages back to us must not cause them to be relayed again
+        self.already_seen(message.id(), Instant::now());
+
+        self.send_to_peers(message, None, ctx);
+    }
+
+    async fn handle(&mut self, msg: Inbound, ctx: &mut xtra::Context<Self>) {
+        let Inbound { source, message } = msg;
+        let now = Instant::now();
+
+        if !self.within_rate_limit(source, now) {
+            tracing::warn!(%source, "Dropping gossip message of peer exceeding rate limit");
+            return;
+        }
+
+        if message.hops >= MAX_HOPS || message.data.len() > MAX_MESSAGE_SIZE {
+            tracing::debug!(%source, ?message, "Dropping gossip message exceeding limits");
+            return;
+        }
+
+        if self.already_seen(message.id(), now) {
+            return;
+        }
+
+        let subscriber = match self.subscriptions.get(&message.topic) {
+            Some(subscriber) => subscriber.clone(),
+            None => {
+                tracing::trace!(%source, ?message, "Ignoring gossip message of unsubscribed topic");
+                return;
+            }
+        };
+
+        let this = ctx.address().expect("self to be alive");
+        let task = {
+            let this = this.clone();
+            async move {
+                subscriber
+                    .send(Received {
+                        source,
+                        topic: message.topic.clone(),
+                        data: message.data.clone(),
+                    })
+                    .await??;
+
+                this.send(Relay { source, message }).await?;
+
+                anyhow::Ok(())
+            }
+        };
+
+        let err_handler = move |e: anyhow::Error| async move {
+            tracing::debug!(%source, "Rejected gossip message: {e:#}")
+        };
+
+        spawn_fallible(&this, task, err_handler);
+    }
+
+    async fn handle(&mut self, msg: Relay, ctx: &mut xtra::Context<Self>) {
+        let Relay { source, message } = msg;
+
+        let message = Message {
+            hops: message.hops + 1,
+            ..message
+        };
+
+        self.send_to_peers(message, Some(source), ctx);
+    }
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: NewInboundSubstream, ctx: &mut xtra::Context<Self>) {
+        let NewInboundSubstream { peer_id, stream } = msg;
+        let this = ctx.address().expect("self to be alive");
+
+        let task = {
+            let this = this.clone();
+            async move {
+                let message = protocol::recv(stream).await?;
+
+                this.send(Inbound {
+                    source: peer_id,
+                    message,
+                })
+                .await?;
+
+                anyhow::Ok(())
+            }
+        };
+
+        let err_handler = move |e: anyhow::Error| async move {
+            tracing::debug!(%peer_id, "Failed to receive gossip message: {e:#}")
+        };
+
+        spawn_fallible(&this, task, err_handler);
+    }
+
+    async fn handle_connection_established(&mut self, msg: endpoint::ConnectionEstablished) {
+        self.connected_peers.insert(msg.peer_id);
+    }
+
+    async fn handle_connection_dropped(&mut self, msg: endpoint::ConnectionDropped) {
+        self.connected_peers.remove(&msg.peer_id);
+        self.received_per_peer.remove(&msg.peer_id);
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        match self.endpoint.send(GetConnectionStats).await {
+            Ok(connection_stats) => self
+                .connected_peers
+                .extend(connection_stats.connected_peers),
+            Err(e) => {
+                tracing::error!(
+                    "Unable to receive connection stats from the endpoint upon startup: {e:#}"
+                );
+
+                // Prevents a continuous loop of restarts in case the endpoint is gone
+                tokio_extras::time::sleep(Duration::from_secs(2)).await;
+
+                ctx.stop_self();
+            }
+        }
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_too_many_messages_from_peer_then_rate_limited_until_window_expired() {
+        let (endpoint, _) = xtra::Context::<Endpoint>::new(None);
+        let mut actor = Actor::new(endpoint, HashMap::default());
+        let peer = PeerId::random();
+        let now = Instant::now();
+
+        for _ in 0..MAX_MESSAGES_PER_PEER_PER_MINUTE {
+            assert!(actor.within_rate_limit(peer, now));
+        }
+        assert!(!actor.within_rate_limit(peer, now));
+        assert!(actor.within_rate_limit(PeerId::random(), now));
+
+        assert!(actor.within_rate_limit(peer, now + RATE_LIMIT_WINDOW));
+    }
+}
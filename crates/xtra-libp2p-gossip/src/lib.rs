@@ -0,0 +1,220 @@
+//! Topic-based publish-subscribe protocol.
+//!
+//! Messages are flooded through the network: every node relays messages of the topics it is
+//! subscribed to to all of its connected peers, until the message was relayed [`MAX_HOPS`] times.
+//! Duplicates are suppressed by remembering the messages seen within [`SEEN_TTL`], and peers
+//! sending more than [`MAX_MESSAGES_PER_PEER_PER_MINUTE`] messages are ignored for the rest of the
+//! minute. Since any peer can publish on any topic, subscribers have to validate messages before
+//! they are relayed further.
+
+mod actor;
+mod protocol;
+
+pub use actor::*;
+pub use protocol::MessageId;
+
+use std::time::Duration;
+
+pub const PROTOCOL: &str = "/itchysats/gossip/1.0.0";
+
+/// Maximum number of times a message is relayed
+pub const MAX_HOPS: u8 = 5;
+
+/// Maximum size of the data of a message in bytes
+pub const MAX_MESSAGE_SIZE: usize = 64 * 1024;
+
+/// Maximum size of an encoded message in bytes
+///
+/// The data is hex encoded, which doubles its size, the rest leaves room for the topic.
+pub const MAX_FRAME_SIZE: usize = 2 * MAX_MESSAGE_SIZE + 4 * 1024;
+
+/// Duration for which a message is remembered to suppress duplicates
+pub const SEEN_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Maximum number of messages accepted from a single peer per minute
+pub const MAX_MESSAGES_PER_PEER_PER_MINUTE: u32 = 60;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::bail;
+    use anyhow::Result;
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+    use std::collections::HashSet;
+    use std::sync::Arc;
+    use tracing_subscriber::util::SubscriberInitExt;
+    use xtra::spawn::TokioGlobalSpawnExt;
+    use xtra::Actor as _;
+    use xtra::Address;
+    use xtra::Context;
+    use xtra_libp2p::endpoint::Subscribers;
+    use xtra_libp2p::libp2p::identity::Keypair;
+    use xtra_libp2p::libp2p::multiaddr::Protocol;
+    use xtra_libp2p::libp2p::transport::MemoryTransport;
+    use xtra_libp2p::libp2p::Multiaddr;
+    use xtra_libp2p::libp2p::PeerId;
+    use xtra_libp2p::Connect;
+    use xtra_libp2p::Endpoint;
+    use xtra_libp2p::ListenOn;
+    use xtra_productivity::xtra_productivity;
+
+    const TOPIC: &str = "test-topic";
+
+    #[tokio::test]
+    async fn given_peers_only_connected_through_relay_then_valid_messages_are_relayed() {
+        let _g = tracing_subscriber::fmt()
+            .with_env_filter("xtra_libp2p_gossip=trace")
+            .with_test_writer()
+            .set_default();
+
+        let (publisher_peer_id, publisher, publisher_endpoint, _) = create_node();
+        let (relay_peer_id, _, relay_endpoint, relay_subscriber) = create_node();
+        let (_, _, subscriber_endpoint, subscriber) = create_node();
+
+        publisher_endpoint
+            .send(ListenOn(Multiaddr::empty().with(Protocol::Memory(2000))))
+            .await
+            .unwrap();
+        relay_endpoint
+            .send(ListenOn(Multiaddr::empty().with(Protocol::Memory(2001))))
+            .await
+            .unwrap();
+        relay_endpoint
+            .send(Connect(memory_address(2000, publisher_peer_id)))
+            .await
+            .unwrap()
+            .unwrap();
+        subscriber_endpoint
+            .send(Connect(memory_address(2001, relay_peer_id)))
+            .await
+            .unwrap()
+            .unwrap();
+
+        // the gossip actors learn about the connections asynchronously
+        tokio_extras::time::sleep(std::time::Duration::from_millis(500)).await;
+
+        publisher
+            .send(Publish {
+                topic: TOPIC.to_owned(),
+                data: b"spam".to_vec(),
+            })
+            .await
+            .unwrap();
+        publisher
+            .send(Publish {
+                topic: TOPIC.to_owned(),
+                data: b"valid".to_vec(),
+            })
+            .await
+            .unwrap();
+
+        let received = retry_until_some(|| {
+            let subscriber = subscriber.clone();
+            async move { subscriber.send(GetReceived).await.unwrap() }
+        })
+        .await;
+
+        assert_eq!(received, vec![b"valid".to_vec()]);
+        assert_eq!(
+            relay_subscriber.send(GetReceived).await.unwrap(),
+            vec![b"valid".to_vec()]
+        );
+    }
+
+    fn memory_address(port: u64, peer_id: PeerId) -> Multiaddr {
+        Multiaddr::empty()
+            .with(Protocol::Memory(port))
+            .with(Protocol::P2p(peer_id.into()))
+    }
+
+    fn create_node() -> (
+        PeerId,
+        Address<Actor>,
+        Address<Endpoint>,
+        Address<Subscriber>,
+    ) {
+        let (endpoint_addr, endpoint_context) = Context::new(None);
+
+        let id = Keypair::generate_ed25519();
+        let subscriber = Subscriber::default().create(None).spawn_global();
+        let gossip_addr = Actor::new(
+            endpoint_addr.clone(),
+            HashMap::from([(TOPIC.to_owned(), subscriber.clone().into())]),
+        )
+        .create(None)
+        .spawn_global();
+
+        let endpoint = Endpoint::new(
+            Box::new(MemoryTransport::default),
+            id.clone(),
+            std::time::Duration::from_secs(10),
+            [(PROTOCOL, gossip_addr.clone().into())],
+            Subscribers::new(
+                vec![gossip_addr.clone().into()],
+                vec![gossip_addr.clone().into()],
+                vec![],
+                vec![],
+            ),
+            Arc::new(HashSet::default()),
+        );
+
+        #[allow(clippy::disallowed_methods)]
+        tokio::spawn(endpoint_context.run(endpoint));
+
+        (
+            id.public().to_peer_id(),
+            gossip_addr,
+            endpoint_addr,
+            subscriber,
+        )
+    }
+
+    /// Accepts all messages except `spam`
+    #[derive(Default)]
+    struct Subscriber {
+        received: Vec<Vec<u8>>,
+    }
+
+    #[async_trait]
+    impl xtra::Actor for Subscriber {
+        type Stop = ();
+
+        async fn stopped(self) -> Self::Stop {}
+    }
+
+    struct GetReceived;
+
+    #[xtra_productivity]
+    impl Subscriber {
+        async fn handle(&mut self, msg: Received) -> Result<()> {
+            if msg.data == b"spam" {
+                bail!("Spam");
+            }
+
+            self.received.push(msg.data);
+
+            Ok(())
+        }
+
+        async fn handle(&mut self, _: GetReceived) -> Vec<Vec<u8>> {
+            self.received.clone()
+        }
+    }
+
+    async fn retry_until_some<F, FUT>(mut fut: F) -> Vec<Vec<u8>>
+    where
+        F: FnMut() -> FUT,
+        FUT: futures::Future<Output = Vec<Vec<u8>>>,
+    {
+        loop {
+            let received = fut().await;
+
+            if received.is_empty() {
+                tokio_extras::time::sleep(std::time::Duration::from_millis(200)).await;
+            } else {
+                return received;
+            }
+        }
+    }
+}
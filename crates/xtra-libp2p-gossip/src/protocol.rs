@@ -0,0 +1,144 @@
+use crate::MAX_FRAME_SIZE;
+use asynchronous_codec::FramedRead;
+use asynchronous_codec::FramedWrite;
+use asynchronous_codec::JsonCodecError;
+use futures::AsyncReadExt;
+use futures::AsyncWriteExt;
+use futures::SinkExt;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+use std::fmt;
+use xtra_libp2p::limited::LimitedJsonCodec;
+
+pub(crate) async fn send<S>(sink: S, message: Message) -> Result<(), JsonCodecError>
+where
+    S: AsyncWriteExt + Unpin,
+{
+    let mut framed = FramedWrite::new(sink, LimitedJsonCodec::<Message, ()>::new(MAX_FRAME_SIZE));
+    framed.send(message).await?;
+    MESSAGES_SENT.inc();
+
+    Ok(())
+}
+
+pub(crate) async fn recv<S>(stream: S) -> Result<Message, ReceiveError>
+where
+    S: AsyncReadExt + Unpin,
+{
+    let mut framed = FramedRead::new(stream, LimitedJsonCodec::<(), Message>::new(MAX_FRAME_SIZE));
+
+    let message = framed.next().await.ok_or(ReceiveError::Terminated)??;
+
+    MESSAGES_RECEIVED.inc();
+
+    Ok(message)
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub(crate) struct Message {
+    pub(crate) topic: String,
+    #[serde(with = "hex")]
+    pub(crate) data: Vec<u8>,
+    /// Number of times the message was forwarded
+    pub(crate) hops: u8,
+}
+
+impl Message {
+    /// Identifies the message independent of the number of hops it took.
+    pub(crate) fn id(&self) -> MessageId {
+        let mut hasher = Sha256::new();
+        hasher.update(self.topic.as_bytes());
+        hasher.update([0]);
+        hasher.update(&self.data);
+
+        MessageId(hasher.finalize().into())
+    }
+}
+
+impl fmt::Debug for Message {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("Message")
+            .field("topic", &self.topic)
+            .field("id", &self.id())
+            .field("hops", &self.hops)
+            .finish()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MessageId([u8; 32]);
+
+impl fmt::Display for MessageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.0))
+    }
+}
+
+impl fmt::Debug for MessageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum ReceiveError {
+    #[error("The stream has terminated")]
+    Terminated,
+    #[error("Failed to decode gossip message")]
+    Decode(#[from] JsonCodecError),
+}
+
+static MESSAGES_SENT: conquer_once::Lazy<prometheus::IntCounter> = conquer_once::Lazy::new(|| {
+    prometheus::register_int_counter!(
+        "gossip_messages_sent_total",
+        "The number of gossip messages sent over the libp2p connection.",
+    )
+    .unwrap()
+});
+
+static MESSAGES_RECEIVED: conquer_once::Lazy<prometheus::IntCounter> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_int_counter!(
+            "gossip_messages_received_total",
+            "The number of gossip messages received over the libp2p connection.",
+        )
+        .unwrap()
+    });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_id_does_not_depend_on_hops() {
+        let message = Message {
+            topic: "topic".to_owned(),
+            data: b"data".to_vec(),
+            hops: 0,
+        };
+        let forwarded = Message {
+            hops: 3,
+            ..message.clone()
+        };
+
+        assert_eq!(message.id(), forwarded.id());
+    }
+
+    #[test]
+    fn message_id_depends_on_topic() {
+        let message = Message {
+            topic: "topic".to_owned(),
+            data: b"data".to_vec(),
+            hops: 0,
+        };
+        let other_topic = Message {
+            topic: "other".to_owned(),
+            ..message.clone()
+        };
+
+        assert_ne!(message.id(), other_topic.id());
+    }
+}
@@ -1,5 +1,8 @@
 pub mod maker;
-mod protocol;
+pub(crate) mod protocol;
 pub mod taker;
 
-pub const PROTOCOL: &str = "/itchysats/offer/2.0.0";
+pub const PROTOCOL: &str = "/itchysats/offer/3.0.0";
+
+/// Maximum size of the encoded offers in bytes
+pub const MAX_FRAME_SIZE: usize = 256 * 1024;
@@ -1,32 +1,133 @@
 use crate::current::protocol;
 use crate::current::PROTOCOL;
+use crate::relay;
+use crate::usage::GetProtocolUsage;
+use crate::usage::PeersUsingProtocol;
+use crate::usage::ProtocolUsage;
+use crate::usage::ProtocolUsed;
 use async_trait::async_trait;
 use model::ContractSymbol;
+use model::Identity;
+use model::OfferUpdate;
 use model::Position;
+use model::Timestamp;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::time::Duration;
+use time::OffsetDateTime;
 use tokio_extras::spawn_fallible;
 use tracing::Instrument;
+use xtra::prelude::MessageChannel;
 use xtra_libp2p::endpoint;
+use xtra_libp2p::libp2p::identity::Keypair;
+use xtra_libp2p::libp2p::Multiaddr;
 use xtra_libp2p::libp2p::PeerId;
 use xtra_libp2p::Endpoint;
 use xtra_libp2p::GetConnectionStats;
 use xtra_libp2p::OpenSubstream;
+use xtra_libp2p_gossip as gossip;
 use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+/// Interval at which offers are re-announced through the gossip network
+///
+/// Has to be shorter than the maximum age of offer signatures for announced offers to stay valid.
+const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Interval at which offers with a time-to-live are checked for whether they need to be re-issued
+const REISSUE_CHECK_INTERVAL: Duration = Duration::from_secs(10);
 
 pub struct Actor {
     endpoint: xtra::Address<Endpoint>,
+    /// Identity of the maker, used to sign the offers
+    identity: Keypair,
     connected_peers: HashSet<PeerId>,
     current_offers: Offers,
+    relay: Option<Relay>,
+    usage: PeersUsingProtocol,
+    /// Time-to-live of the offers, they do not expire if `None`
+    time_to_live: Option<time::Duration>,
+    /// When the offers were last updated, offers are only re-issued while this is recent
+    updated_at: Timestamp,
+}
+
+struct Relay {
+    gossip: MessageChannel<gossip::Publish, ()>,
+    /// Network identity of the maker, used by takers in contract setup
+    network_identity: Identity,
+    /// Addresses takers can dial the maker at
+    addresses: Vec<Multiaddr>,
 }
 
 impl Actor {
-    pub fn new(endpoint: xtra::Address<Endpoint>) -> Self {
+    pub fn new(endpoint: xtra::Address<Endpoint>, identity: Keypair) -> Self {
         Self {
             endpoint,
+            identity,
             connected_peers: HashSet::default(),
             current_offers: Offers::default(),
+            relay: None,
+            usage: PeersUsingProtocol::new(PROTOCOL),
+            time_to_live: None,
+            updated_at: Timestamp::now(),
+        }
+    }
+
+    /// Let takers drop the offers once `time_to_live` elapsed since they were issued.
+    ///
+    /// Offers are re-issued after half their time-to-live, as long as they were updated within
+    /// [`model::Offer::OUTDATED_AFTER_MINS`]. Hence takers drop the offers if the maker or
+    /// whoever updates its offers goes away, but not in between updates.
+    pub fn with_time_to_live(self, time_to_live: time::Duration) -> Self {
+        Self {
+            time_to_live: Some(time_to_live),
+            ..self
+        }
+    }
+
+    /// Additionally announce the offers through the gossip network, together with the maker's
+    /// `network_identity` and the `addresses` takers can dial the maker at.
+    pub fn with_relay(
+        mut self,
+        gossip: MessageChannel<gossip::Publish, ()>,
+        network_identity: Identity,
+        addresses: Vec<Multiaddr>,
+    ) -> Self {
+        self.relay = Some(Relay {
+            gossip,
+            network_identity,
+            addresses,
+        });
+        self
+    }
+
+    async fn announce_offers(&self) {
+        let Relay {
+            gossip,
+            network_identity,
+            addresses,
+        } = match &self.relay {
+            Some(relay) => relay,
+            None => return,
+        };
+
+        let announcements = match relay::announcements(
+            self.current_offers.to_vec(),
+            &self.identity,
+            *network_identity,
+            addresses.clone(),
+        ) {
+            Ok(announcements) => announcements,
+            Err(e) => {
+                tracing::warn!("Failed to create offer announcements: {e:#}");
+                return;
+            }
+        };
+
+        for announcement in announcements {
+            if let Err(e) = gossip.send(announcement).await {
+                tracing::warn!("Failed to announce offers: {e:#}");
+            }
         }
     }
 
@@ -38,16 +139,25 @@ impl Actor {
         ctx: &mut xtra::Context<Self>,
     ) {
         let endpoint = self.endpoint.clone();
+        let identity = self.identity.clone();
+        let this = ctx.address().expect("self to be alive");
 
-        let task = async move {
-            let stream = endpoint
-                .send(OpenSubstream::single_protocol(peer_id, PROTOCOL))
-                .await??
-                .await?;
+        let task = {
+            let this = this.clone();
+            async move {
+                let offers = protocol::Offers::sign(offers, &identity, Timestamp::now())?;
 
-            protocol::send(stream, offers.into()).await?;
+                let stream = endpoint
+                    .send(OpenSubstream::single_protocol(peer_id, PROTOCOL))
+                    .await??
+                    .await?;
 
-            anyhow::Ok(())
+                protocol::send(stream, offers).await?;
+
+                this.send(ProtocolUsed { peer_id }).await?;
+
+                anyhow::Ok(())
+            }
         };
 
         let err_handler = move |e: anyhow::Error| async move {
@@ -62,7 +172,6 @@ impl Actor {
             }
         };
 
-        let this = ctx.address().expect("self to be alive");
         spawn_fallible(
             &this,
             task.instrument(tracing::Span::current()),
@@ -74,19 +183,104 @@ impl Actor {
 #[xtra_productivity]
 impl Actor {
     async fn handle(&mut self, msg: NewOffers, ctx: &mut xtra::Context<Self>) {
-        self.current_offers.update(msg.0.clone());
+        let offers = msg
+            .0
+            .into_iter()
+            .map(|offer| offer.with_time_to_live(self.time_to_live))
+            .collect::<Vec<_>>();
+
+        self.current_offers.update(offers.clone());
+        self.updated_at = Timestamp::now();
 
         let quiet = quiet_spans::sometimes_quiet_children();
         for peer_id in self.connected_peers.iter().copied() {
-            self.send_offers(peer_id, msg.0.clone(), ctx)
+            self.send_offers(peer_id, offers.clone(), ctx)
                 .instrument(quiet.clone())
                 .await
         }
+
+        self.announce_offers().await;
+    }
+
+    /// Revise the current offers of a contract symbol in place.
+    ///
+    /// Orders are checked against [`GetLatestOffers`], which is handled by this actor as well, so
+    /// an order either refers to the previous revision or to the new one, never to a mix of both.
+    async fn handle(
+        &mut self,
+        msg: ReviseOffers,
+        ctx: &mut xtra::Context<Self>,
+    ) -> anyhow::Result<Vec<model::Offer>> {
+        let ReviseOffers {
+            contract_symbol,
+            update,
+        } = msg;
+
+        let revised = self.current_offers.revise(contract_symbol, &update);
+        if revised.is_empty() {
+            anyhow::bail!("No {contract_symbol} offers to revise");
+        }
+        self.updated_at = Timestamp::now();
+
+        for peer_id in self.connected_peers.iter().copied() {
+            self.send_offers(peer_id, revised.clone(), ctx).await
+        }
+
+        self.announce_offers().await;
+
+        Ok(revised)
+    }
+
+    async fn handle(&mut self, _: WithdrawOffers, ctx: &mut xtra::Context<Self>) {
+        self.current_offers = Offers::default();
+
+        for peer_id in self.connected_peers.iter().copied() {
+            self.send_offers(peer_id, Vec::new(), ctx).await
+        }
+
+        self.announce_offers().await;
+    }
+
+    async fn handle(&mut self, _: AnnounceOffers) {
+        self.announce_offers().await;
+    }
+
+    async fn handle(&mut self, _: ReissueOffers, ctx: &mut xtra::Context<Self>) {
+        let time_to_live = match self.time_to_live {
+            Some(time_to_live) => time_to_live,
+            None => return,
+        };
+
+        let now = OffsetDateTime::now_utc();
+        if self.updated_at.seconds() + model::Offer::OUTDATED_AFTER_MINS * 60 < now.unix_timestamp()
+        {
+            // The offers are left to expire on the takers
+            return;
+        }
+
+        if !self.current_offers.reissue(time_to_live / 2, now) {
+            return;
+        }
+
+        for peer_id in self.connected_peers.iter().copied() {
+            self.send_offers(peer_id, self.current_offers.to_vec(), ctx)
+                .await
+        }
+
+        self.announce_offers().await;
     }
 
     async fn handle(&mut self, _: GetLatestOffers) -> Vec<model::Offer> {
         self.current_offers.to_vec()
     }
+
+    async fn handle(&mut self, msg: ProtocolUsed) {
+        self.usage.insert(msg.peer_id);
+    }
+
+    async fn handle(&mut self, _: GetProtocolUsage) -> ProtocolUsage {
+        self.usage.usage()
+    }
 }
 
 #[xtra_productivity]
@@ -105,6 +299,7 @@ impl Actor {
     async fn handle_connection_dropped(&mut self, msg: endpoint::ConnectionDropped) {
         tracing::trace!("Remove dropped connection: {:?}", msg.peer_id);
         self.connected_peers.remove(&msg.peer_id);
+        self.usage.remove(&msg.peer_id);
     }
 }
 
@@ -118,9 +313,29 @@ impl NewOffers {
     }
 }
 
+/// Instruct the `offer::maker::Actor` to apply a partial update to the current offers of a
+/// contract symbol, keeping their ids and bumping their revision.
+///
+/// Returns the revised offers.
+pub struct ReviseOffers {
+    pub contract_symbol: ContractSymbol,
+    pub update: OfferUpdate,
+}
+
+/// Instruct the `offer::maker::Actor` to drop all current offers
+/// and to broadcast an empty list of offers to all connected peers.
+#[derive(Clone, Copy)]
+pub struct WithdrawOffers;
+
 #[derive(Clone, Copy)]
 pub struct GetLatestOffers;
 
+#[derive(Clone, Copy)]
+struct AnnounceOffers;
+
+#[derive(Clone, Copy)]
+struct ReissueOffers;
+
 #[derive(Clone, Default)]
 struct Offers(HashMap<(ContractSymbol, Position), model::Offer>);
 
@@ -137,6 +352,49 @@ impl Offers {
         }
     }
 
+    fn revise(
+        &mut self,
+        contract_symbol: ContractSymbol,
+        update: &OfferUpdate,
+    ) -> Vec<model::Offer> {
+        let mut revised = Vec::new();
+
+        for ((symbol, _), offer) in self.0.iter_mut() {
+            if *symbol != contract_symbol {
+                continue;
+            }
+
+            *offer = offer.clone().revise(update);
+            tracing::debug!(offer_id = %offer.id, revision = %offer.revision, "Revised offer");
+
+            revised.push(offer.clone());
+        }
+
+        revised
+    }
+
+    /// Re-issue the offers that were issued more than `after` before `now`.
+    ///
+    /// Returns whether any offer was re-issued.
+    fn reissue(&mut self, after: time::Duration, now: OffsetDateTime) -> bool {
+        let mut reissued = false;
+
+        for offer in self.0.values_mut() {
+            if offer.creation_timestamp_maker.seconds() + after.whole_seconds()
+                > now.unix_timestamp()
+            {
+                continue;
+            }
+
+            *offer = offer.clone().reissue(now);
+            tracing::debug!(offer_id = %offer.id, "Re-issued offer");
+
+            reissued = true;
+        }
+
+        reissued
+    }
+
     fn to_vec(&self) -> Vec<model::Offer> {
         self.0.iter().map(|(_, offer)| offer).cloned().collect()
     }
@@ -162,8 +420,35 @@ impl xtra::Actor for Actor {
                 tokio_extras::time::sleep(Duration::from_secs(2)).await;
 
                 ctx.stop_self();
+                return;
             }
         }
+
+        if self.time_to_live.is_some() {
+            let this = ctx.address().expect("we just started");
+
+            tokio_extras::spawn(
+                &this.clone(),
+                this.send_interval(
+                    REISSUE_CHECK_INTERVAL,
+                    || ReissueOffers,
+                    xtras::IncludeSpan::Never,
+                ),
+            );
+        }
+
+        if self.relay.is_some() {
+            let this = ctx.address().expect("we just started");
+
+            tokio_extras::spawn(
+                &this.clone(),
+                this.send_interval(
+                    ANNOUNCE_INTERVAL,
+                    || AnnounceOffers,
+                    xtras::IncludeSpan::Never,
+                ),
+            );
+        }
     }
 
     async fn stopped(self) -> Self::Stop {}
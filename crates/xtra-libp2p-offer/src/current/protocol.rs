@@ -1,33 +1,48 @@
+use crate::current::MAX_FRAME_SIZE;
 use asynchronous_codec::FramedRead;
 use asynchronous_codec::FramedWrite;
-use asynchronous_codec::JsonCodec;
 use asynchronous_codec::JsonCodecError;
 use futures::AsyncReadExt;
 use futures::AsyncWriteExt;
 use futures::SinkExt;
 use futures::StreamExt;
 use model::olivia::BitMexPriceEventId;
+use model::symbols;
+use model::trading_calendar::TradingCalendar;
 use model::ContractSymbol;
 use model::Contracts;
+use model::FundingPeriod;
 use model::FundingRate;
 use model::Leverage;
 use model::LotSize;
 use model::OfferId;
 use model::OpeningFee;
+use model::OpeningFeeTiers;
 use model::Position;
 use model::Price;
 use model::Timestamp;
 use model::TxFeeRate;
+use rust_decimal::Decimal;
 use serde::Deserialize;
 use serde::Serialize;
 use std::fmt;
 use time::Duration;
+use xtra_libp2p::libp2p::identity::Keypair;
+use xtra_libp2p::libp2p::identity::PublicKey;
+use xtra_libp2p::libp2p::PeerId;
+use xtra_libp2p::limited::LimitedJsonCodec;
+
+/// Maximum difference in seconds between the time an offer was signed and the time it is verified
+///
+/// Offers are signed whenever they are sent, so this only has to account for latency and clock
+/// drift. It bounds the time for which stale offers can be replayed.
+pub(crate) const MAX_SIGNATURE_AGE_SECS: i64 = 5 * 60;
 
 pub(crate) async fn send<S>(sink: S, offers: Offers) -> Result<(), JsonCodecError>
 where
     S: AsyncWriteExt + Unpin,
 {
-    let mut framed = FramedWrite::new(sink, JsonCodec::<Offers, ()>::new());
+    let mut framed = FramedWrite::new(sink, LimitedJsonCodec::<Offers, ()>::new(MAX_FRAME_SIZE));
     framed.send(offers).await?;
     MESSAGES_SENT.inc();
 
@@ -38,7 +53,7 @@ pub(crate) async fn recv<S>(stream: S) -> Result<Offers, ReceiveError>
 where
     S: AsyncReadExt + Unpin,
 {
-    let mut framed = FramedRead::new(stream, JsonCodec::<(), Offers>::new());
+    let mut framed = FramedRead::new(stream, LimitedJsonCodec::<(), Offers>::new(MAX_FRAME_SIZE));
 
     let offers = framed.next().await.ok_or(ReceiveError::Terminated)??;
 
@@ -48,24 +63,151 @@ where
 }
 
 #[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
-pub(crate) struct Offers(Vec<Offer>);
+pub(crate) struct Offers(Vec<SignedOffer>);
+
+impl Offers {
+    /// Sign each of the `offers` with the maker's `identity`.
+    pub(crate) fn sign(
+        offers: Vec<model::Offer>,
+        identity: &Keypair,
+        timestamp: Timestamp,
+    ) -> anyhow::Result<Self> {
+        let offers = offers
+            .into_iter()
+            .map(|offer| SignedOffer::sign(Offer::from(offer), identity, timestamp))
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self(offers))
+    }
+
+    /// Verify that all offers were recently signed by the `maker` and can be taken with the
+    /// quanto multiplier they carry.
+    ///
+    /// Offers are only returned if all of them are valid, as a partially forged set of offers
+    /// cannot be trusted.
+    pub(crate) fn verify(
+        self,
+        maker: PeerId,
+        now: Timestamp,
+    ) -> Result<Vec<model::Offer>, VerificationError> {
+        self.0
+            .into_iter()
+            .map(|offer| offer.verify(maker, now).map(model::Offer::from))
+            .collect()
+    }
+}
+
+/// An offer together with the maker's signature over its exact encoding.
+///
+/// The signature covers the bytes of `payload` as sent, instead of a re-encoding of the decoded
+/// offer. Takers therefore verify offers carrying fields they do not know about yet, ignoring
+/// those fields. Fields that change the terms of an offer require a new protocol version.
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct SignedOffer {
+    /// JSON encoding of the offer and the time of signing
+    payload: String,
+    /// Protobuf encoding of the public identity key of the maker
+    #[serde(with = "hex")]
+    public_key: Vec<u8>,
+    #[serde(with = "hex")]
+    signature: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SignedPayload {
+    offer: Offer,
+    /// Time at which the offer was signed
+    timestamp: Timestamp,
+}
+
+impl SignedOffer {
+    fn sign(offer: Offer, identity: &Keypair, timestamp: Timestamp) -> anyhow::Result<Self> {
+        let payload = serde_json::to_string(&SignedPayload { offer, timestamp })?;
+        let signature = identity.sign(payload.as_bytes())?;
+
+        Ok(Self {
+            payload,
+            public_key: identity.public().to_protobuf_encoding(),
+            signature,
+        })
+    }
+
+    fn verify(self, maker: PeerId, now: Timestamp) -> Result<Offer, VerificationError> {
+        let public_key = PublicKey::from_protobuf_encoding(&self.public_key)
+            .map_err(|_| VerificationError::InvalidPublicKey)?;
+
+        let signer = public_key.to_peer_id();
+        if signer != maker {
+            return Err(VerificationError::UnexpectedSigner { signer });
+        }
+
+        if !public_key.verify(self.payload.as_bytes(), &self.signature) {
+            return Err(VerificationError::InvalidSignature);
+        }
+
+        let SignedPayload { offer, timestamp } =
+            serde_json::from_str(&self.payload).map_err(VerificationError::Decode)?;
+        let offer_id = offer.id;
+
+        if (now.seconds() - timestamp.seconds()).abs() > MAX_SIGNATURE_AGE_SECS {
+            return Err(VerificationError::Outdated {
+                offer_id,
+                timestamp,
+            });
+        }
+
+        symbols::check_quanto_multiplier(offer.contract_symbol, offer.quanto_multiplier)
+            .map_err(|source| VerificationError::InvalidQuantoMultiplier { offer_id, source })?;
+
+        Ok(offer)
+    }
+}
+
+impl fmt::Debug for SignedOffer {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("SignedOffer")
+            .field("payload", &self.payload)
+            .finish()
+    }
+}
 
 #[derive(Clone, Serialize, Deserialize, PartialEq)]
 pub(crate) struct Offer {
     id: OfferId,
     contract_symbol: ContractSymbol,
+    /// Not sent for inverse contracts and by makers that do not send the multiplier yet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    quanto_multiplier: Option<Decimal>,
     position_maker: Position,
     price: Price,
     min_quantity: Contracts,
     max_quantity: Contracts,
+    /// Not sent by makers that do not cap the quantity of a single order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_contracts_per_order: Option<Contracts>,
     leverage_choices: Vec<Leverage>,
     creation_timestamp_maker: Timestamp,
+    /// Not sent for offers that do not expire
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    time_to_live: Option<Duration>,
     settlement_interval: Duration,
     oracle_event_id: BitMexPriceEventId,
     tx_fee_rate: TxFeeRate,
     funding_rate: FundingRate,
+    /// Defaults to [`FundingPeriod::Hourly`] for makers that do not send a funding period yet
+    #[serde(default)]
+    funding_period: FundingPeriod,
     opening_fee: OpeningFee,
+    /// Not sent by makers that charge a flat opening fee only
+    #[serde(default, skip_serializing_if = "OpeningFeeTiers::is_empty")]
+    opening_fee_tiers: OpeningFeeTiers,
     lot_size: LotSize,
+    /// Not sent for offers that were not revised since their creation
+    #[serde(default, skip_serializing_if = "is_initial_revision")]
+    revision: u32,
+    /// Not sent for offers that can be taken at any time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    trading_calendar: Option<TradingCalendar>,
 }
 
 impl From<model::Offer> for Offer {
@@ -73,18 +215,25 @@ impl From<model::Offer> for Offer {
         Self {
             id: offer.id,
             contract_symbol: offer.contract_symbol,
+            quanto_multiplier: offer.quanto_multiplier,
             position_maker: offer.position_maker,
             price: offer.price,
             min_quantity: offer.min_quantity,
             max_quantity: offer.max_quantity,
+            max_contracts_per_order: offer.max_contracts_per_order,
             leverage_choices: offer.leverage_choices,
             creation_timestamp_maker: offer.creation_timestamp_maker,
+            time_to_live: offer.time_to_live,
             settlement_interval: offer.settlement_interval,
             oracle_event_id: offer.oracle_event_id,
             tx_fee_rate: offer.tx_fee_rate,
             funding_rate: offer.funding_rate,
+            funding_period: offer.funding_period,
             opening_fee: offer.opening_fee,
+            opening_fee_tiers: offer.opening_fee_tiers,
             lot_size: offer.lot_size,
+            revision: offer.revision,
+            trading_calendar: offer.trading_calendar,
         }
     }
 }
@@ -94,32 +243,31 @@ impl From<Offer> for model::Offer {
         Self {
             id: offer.id,
             contract_symbol: offer.contract_symbol,
+            quanto_multiplier: offer.quanto_multiplier,
             position_maker: offer.position_maker,
             price: offer.price,
             min_quantity: offer.min_quantity,
             max_quantity: offer.max_quantity,
+            max_contracts_per_order: offer.max_contracts_per_order,
             leverage_choices: offer.leverage_choices,
             creation_timestamp_maker: offer.creation_timestamp_maker,
+            time_to_live: offer.time_to_live,
             settlement_interval: offer.settlement_interval,
             oracle_event_id: offer.oracle_event_id,
             tx_fee_rate: offer.tx_fee_rate,
             funding_rate: offer.funding_rate,
+            funding_period: offer.funding_period,
             opening_fee: offer.opening_fee,
+            opening_fee_tiers: offer.opening_fee_tiers,
             lot_size: offer.lot_size,
+            revision: offer.revision,
+            trading_calendar: offer.trading_calendar,
         }
     }
 }
 
-impl From<Vec<model::Offer>> for Offers {
-    fn from(offers: Vec<model::Offer>) -> Self {
-        Offers(offers.into_iter().map(Offer::from).collect())
-    }
-}
-
-impl From<Offers> for Vec<model::Offer> {
-    fn from(offers: Offers) -> Self {
-        offers.0.into_iter().map(model::Offer::from).collect()
-    }
+fn is_initial_revision(revision: &u32) -> bool {
+    *revision == 0
 }
 
 impl fmt::Debug for Offer {
@@ -140,9 +288,31 @@ pub(crate) enum ReceiveError {
     Decode(#[from] JsonCodecError),
 }
 
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum VerificationError {
+    #[error("Offer is signed with an invalid public key")]
+    InvalidPublicKey,
+    #[error("Offer is signed by {signer} instead of the maker")]
+    UnexpectedSigner { signer: PeerId },
+    #[error("Signature of offer is invalid")]
+    InvalidSignature,
+    #[error("Failed to decode signed offer")]
+    Decode(#[source] serde_json::Error),
+    #[error("Signature of offer {offer_id} from {timestamp} is outdated")]
+    Outdated {
+        offer_id: OfferId,
+        timestamp: Timestamp,
+    },
+    #[error("Quanto multiplier of offer {offer_id} is invalid")]
+    InvalidQuantoMultiplier {
+        offer_id: OfferId,
+        source: anyhow::Error,
+    },
+}
+
 static MESSAGES_SENT: conquer_once::Lazy<prometheus::IntCounter> = conquer_once::Lazy::new(|| {
     prometheus::register_int_counter!(
-        "offer_messages_sent_total_v2",
+        "offer_messages_sent_total_v3",
         "The number of offer messages sent over the libp2p connection.",
     )
     .unwrap()
@@ -162,17 +332,151 @@ mod tests {
     use super::*;
     use crate::tests::dummy_offers;
     use sluice::pipe::pipe;
+    use xtra_libp2p::libp2p::identity::ed25519;
+    use xtra_libp2p::wire_fixtures::Fixtures;
+
+    const FIXTURES: Fixtures = Fixtures::new("./src/wire_fixtures/3.0.0");
+
+    /// Secret identity key of the maker that signed the recorded offers
+    const RECORDED_MAKER_SECRET_KEY: &str =
+        "6cc4f7173e2f0861f42ec9e1bc408d8a8f2c1c68496afbeb3d0c811ac22780f4";
+
+    /// Time at which the recorded offers were signed
+    const RECORDED_SIGNATURE_TIMESTAMP: i64 = 1665000300;
+
+    fn recorded_maker() -> Keypair {
+        let secret_key = hex::decode(RECORDED_MAKER_SECRET_KEY).unwrap();
+
+        Keypair::Ed25519(ed25519::Keypair::from(
+            ed25519::SecretKey::from_bytes(secret_key).unwrap(),
+        ))
+    }
 
     #[tokio::test]
     async fn sent_offers_match_received_offers() {
         let (stream, sink) = pipe();
 
+        let identity = Keypair::generate_ed25519();
         let maker_offers = dummy_offers();
+        let signed_offers =
+            Offers::sign(maker_offers.clone(), &identity, Timestamp::now()).unwrap();
 
-        let (send_res, recv_res) =
-            tokio::join!(send(sink, Offers::from(maker_offers.clone())), recv(stream));
+        let (send_res, recv_res) = tokio::join!(send(sink, signed_offers), recv(stream));
 
         assert!(send_res.is_ok());
-        assert_eq!(maker_offers, Vec::<model::Offer>::from(recv_res.unwrap()))
+        let received_offers = recv_res
+            .unwrap()
+            .verify(identity.public().to_peer_id(), Timestamp::now())
+            .unwrap();
+        assert_eq!(maker_offers, received_offers)
+    }
+
+    #[test]
+    fn given_tampered_offer_then_verification_fails() {
+        let identity = Keypair::generate_ed25519();
+        let mut offers = Offers::sign(dummy_offers(), &identity, Timestamp::now()).unwrap();
+
+        let mut payload = serde_json::from_str::<SignedPayload>(&offers.0[0].payload).unwrap();
+        payload.offer.price = Price::new(rust_decimal_macros::dec!(1)).unwrap();
+        offers.0[0].payload = serde_json::to_string(&payload).unwrap();
+
+        let result = offers.verify(identity.public().to_peer_id(), Timestamp::now());
+
+        assert!(matches!(result, Err(VerificationError::InvalidSignature)));
+    }
+
+    #[test]
+    fn given_offer_with_unknown_fields_then_verification_succeeds() {
+        let identity = Keypair::generate_ed25519();
+        let maker_offer = dummy_offers().remove(0);
+
+        let mut payload = serde_json::to_value(SignedPayload {
+            offer: Offer::from(maker_offer.clone()),
+            timestamp: Timestamp::now(),
+        })
+        .unwrap();
+        payload["offer"]["field_of_a_newer_maker"] = serde_json::json!(42);
+        let payload = payload.to_string();
+
+        let offers = Offers(vec![SignedOffer {
+            signature: identity.sign(payload.as_bytes()).unwrap(),
+            payload,
+            public_key: identity.public().to_protobuf_encoding(),
+        }]);
+
+        let received_offers = offers
+            .verify(identity.public().to_peer_id(), Timestamp::now())
+            .unwrap();
+
+        assert_eq!(received_offers, vec![maker_offer]);
+    }
+
+    #[test]
+    fn given_offer_signed_by_other_peer_then_verification_fails() {
+        let maker = Keypair::generate_ed25519();
+        let impostor = Keypair::generate_ed25519();
+        let offers = Offers::sign(dummy_offers(), &impostor, Timestamp::now()).unwrap();
+
+        let result = offers.verify(maker.public().to_peer_id(), Timestamp::now());
+
+        assert!(matches!(
+            result,
+            Err(VerificationError::UnexpectedSigner { .. })
+        ));
+    }
+
+    #[test]
+    fn given_outdated_signature_then_verification_fails() {
+        let identity = Keypair::generate_ed25519();
+        let signed_at = Timestamp::now();
+        let offers = Offers::sign(dummy_offers(), &identity, signed_at).unwrap();
+
+        let result = offers.verify(
+            identity.public().to_peer_id(),
+            Timestamp::new(signed_at.seconds() + MAX_SIGNATURE_AGE_SECS + 1),
+        );
+
+        assert!(matches!(result, Err(VerificationError::Outdated { .. })));
+    }
+
+    #[test]
+    fn given_quanto_multiplier_for_inverse_contract_then_verification_fails() {
+        let identity = Keypair::generate_ed25519();
+        let offers = dummy_offers()
+            .into_iter()
+            .map(|offer| model::Offer {
+                quanto_multiplier: Some(rust_decimal_macros::dec!(0.000001)),
+                ..offer
+            })
+            .collect();
+        let offers = Offers::sign(offers, &identity, Timestamp::now()).unwrap();
+
+        let result = offers.verify(identity.public().to_peer_id(), Timestamp::now());
+
+        assert!(matches!(
+            result,
+            Err(VerificationError::InvalidQuantoMultiplier { .. })
+        ));
+    }
+
+    #[test]
+    fn offers_match_recording() {
+        let signed_at = Timestamp::new(RECORDED_SIGNATURE_TIMESTAMP);
+
+        let offers = FIXTURES
+            .round_trip::<Offers>("offers.json")
+            .verify(recorded_maker().public().to_peer_id(), signed_at)
+            .unwrap();
+
+        assert_eq!(offers.len(), 2);
+        assert_eq!(offers[0].settlement_interval, Duration::hours(24));
+        assert_eq!(offers[0].funding_period, FundingPeriod::EightHours);
+        assert!(offers[1].quanto_multiplier.is_some());
+        assert!(offers[1].max_contracts_per_order.is_some());
+        assert_eq!(offers[1].time_to_live, Some(Duration::hours(1)));
+        assert_eq!(offers[1].revision, 3);
+
+        let resigned = Offers::sign(offers, &recorded_maker(), signed_at).unwrap();
+        assert_eq!(resigned, FIXTURES.decode::<Offers>("offers.json"));
     }
 }
@@ -1,17 +1,68 @@
 use crate::current::protocol;
 use async_trait::async_trait;
+use model::Timestamp;
+use std::collections::HashMap;
+use std::time::Duration;
 use tracing::Instrument;
 use xtra::prelude::MessageChannel;
+use xtra_libp2p::libp2p::PeerId;
 use xtra_libp2p::NewInboundSubstream;
 use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+/// Interval at which the latest offers are checked for offers whose time-to-live elapsed
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(10);
 
 pub struct Actor {
     maker_offers: MessageChannel<LatestOffers, ()>,
+    /// Peer whose signature offers have to carry, regardless of who sent them
+    maker: PeerId,
+    /// Further makers whose offers are accepted if they send them themselves
+    additional_makers: Vec<PeerId>,
+    /// The latest offers of the makers that sent offers with a time-to-live
+    expiring_offers: HashMap<PeerId, Vec<model::Offer>>,
 }
 
 impl Actor {
-    pub fn new(maker_offers: MessageChannel<LatestOffers, ()>) -> Self {
-        Self { maker_offers }
+    pub fn new(maker_offers: MessageChannel<LatestOffers, ()>, maker: PeerId) -> Self {
+        Self {
+            maker_offers,
+            maker,
+            additional_makers: Vec::new(),
+            expiring_offers: HashMap::default(),
+        }
+    }
+
+    /// Accept offers of the given makers in addition to the offers of the main maker.
+    pub fn with_additional_makers(self, additional_makers: Vec<PeerId>) -> Self {
+        Self {
+            additional_makers,
+            ..self
+        }
+    }
+
+    /// The maker whose signature the offers received from `peer_id` have to carry.
+    ///
+    /// Offers of additional makers are only accepted from the maker itself, so that an empty list
+    /// of offers can be attributed to the right maker.
+    fn expected_signer(&self, peer_id: PeerId) -> PeerId {
+        if self.additional_makers.contains(&peer_id) {
+            peer_id
+        } else {
+            self.maker
+        }
+    }
+
+    async fn forward_offers(&self, maker: PeerId, offers: Vec<model::Offer>) {
+        let span = tracing::debug_span!("Received new offers from maker", %maker);
+        if let Err(e) = self
+            .maker_offers
+            .send(LatestOffers { maker, offers })
+            .instrument(span)
+            .await
+        {
+            tracing::warn!(%maker, "Failed to forward maker offers: {e:#}");
+        }
     }
 }
 
@@ -19,22 +70,23 @@ impl Actor {
 impl Actor {
     async fn handle(&mut self, msg: NewInboundSubstream, ctx: &mut xtra::Context<Self>) {
         let NewInboundSubstream { peer_id, stream } = msg;
-        let maker_offers = self.maker_offers.clone();
+        let maker = self.expected_signer(peer_id);
 
         let this = ctx.address().expect("self to be alive");
 
-        let task = async move {
-            let offers = protocol::recv(stream).await?;
+        let task = {
+            let this = this.clone();
+            async move {
+                let offers = protocol::recv(stream).await?;
 
-            tracing::debug!(?offers, "Received offers");
+                tracing::debug!(?offers, "Received offers");
 
-            let span = tracing::debug_span!("Received new offers from maker", %peer_id);
-            maker_offers
-                .send(LatestOffers(offers.into()))
-                .instrument(span)
-                .await?;
+                let offers = offers.verify(maker, Timestamp::now())?;
 
-            anyhow::Ok(())
+                this.send(VerifiedOffers { maker, offers }).await?;
+
+                anyhow::Ok(())
+            }
         };
 
         let err_handler = move |e| async move {
@@ -43,15 +95,84 @@ impl Actor {
 
         tokio_extras::spawn_fallible(&this, task, err_handler);
     }
+
+    async fn handle(&mut self, msg: VerifiedOffers) {
+        let VerifiedOffers { maker, mut offers } = msg;
+
+        let now = Timestamp::now();
+        offers.retain(|offer| {
+            let expired = offer.is_expired(now);
+            if expired {
+                tracing::debug!(%maker, offer_id = %offer.id, "Ignoring expired offer");
+            }
+
+            !expired
+        });
+
+        if offers.iter().any(|offer| offer.time_to_live.is_some()) {
+            self.expiring_offers.insert(maker, offers.clone());
+        } else {
+            self.expiring_offers.remove(&maker);
+        }
+
+        self.forward_offers(maker, offers).await;
+    }
+
+    async fn handle(&mut self, _: DropExpiredOffers) {
+        let now = Timestamp::now();
+
+        let mut changed = Vec::new();
+        for (maker, offers) in self.expiring_offers.iter_mut() {
+            let before = offers.len();
+            offers.retain(|offer| !offer.is_expired(now));
+
+            if offers.len() < before {
+                tracing::info!(%maker, expired = before - offers.len(), "Dropping expired offers");
+                changed.push((*maker, offers.clone()));
+            }
+        }
+
+        self.expiring_offers.retain(|_, offers| !offers.is_empty());
+
+        // A maker whose offers all expired is treated like a maker that withdrew its offers
+        for (maker, offers) in changed {
+            self.forward_offers(maker, offers).await;
+        }
+    }
 }
 
 /// Message used to inform other actors about the maker's latest
 /// offers.
-pub struct LatestOffers(pub Vec<model::Offer>);
+pub struct LatestOffers {
+    pub maker: PeerId,
+    pub offers: Vec<model::Offer>,
+}
+
+/// Offers received from the maker whose signatures were verified.
+struct VerifiedOffers {
+    maker: PeerId,
+    offers: Vec<model::Offer>,
+}
+
+#[derive(Clone, Copy)]
+struct DropExpiredOffers;
 
 #[async_trait]
 impl xtra::Actor for Actor {
     type Stop = ();
 
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we just started");
+
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(
+                EXPIRY_CHECK_INTERVAL,
+                || DropExpiredOffers,
+                xtras::IncludeSpan::Never,
+            ),
+        );
+    }
+
     async fn stopped(self) -> Self::Stop {}
 }
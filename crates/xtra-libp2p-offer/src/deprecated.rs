@@ -2,3 +2,6 @@ pub mod maker;
 pub mod protocol;
 
 pub const PROTOCOL: &str = "/itchysats/offer/1.0.0";
+
+/// Maximum size of the encoded offers in bytes
+pub const MAX_FRAME_SIZE: usize = 256 * 1024;
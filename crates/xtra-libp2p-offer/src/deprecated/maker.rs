@@ -1,10 +1,15 @@
 use crate::deprecated;
 use crate::deprecated::protocol;
 use crate::deprecated::protocol::MakerOffers;
+use crate::usage::GetProtocolUsage;
+use crate::usage::PeersUsingProtocol;
+use crate::usage::ProtocolUsage;
+use crate::usage::ProtocolUsed;
 use async_trait::async_trait;
 use nonempty::NonEmpty;
 use std::collections::HashSet;
 use std::time::Duration;
+use time::OffsetDateTime;
 use tokio_extras::spawn_fallible;
 use tracing::Instrument;
 use xtra_libp2p::endpoint;
@@ -18,6 +23,9 @@ pub struct Actor {
     endpoint: xtra::Address<Endpoint>,
     connected_peers: HashSet<PeerId>,
     latest_offers: Option<MakerOffers>,
+    usage: PeersUsingProtocol,
+    /// Point in time after which no offers are sent over the deprecated protocol anymore
+    cutoff: Option<OffsetDateTime>,
 }
 
 impl Actor {
@@ -26,26 +34,53 @@ impl Actor {
             endpoint,
             connected_peers: HashSet::default(),
             latest_offers: None,
+            usage: PeersUsingProtocol::new(deprecated::PROTOCOL),
+            cutoff: None,
         }
     }
 
+    /// Refuse the deprecated protocol after `cutoff`.
+    ///
+    /// The protocol has no way of conveying an error, peers are told that there are no offers
+    /// instead. This keeps peers that did not upgrade from placing orders on outdated terms.
+    pub fn with_cutoff(self, cutoff: OffsetDateTime) -> Self {
+        Self {
+            cutoff: Some(cutoff),
+            ..self
+        }
+    }
+
+    fn is_past_cutoff(&self) -> bool {
+        self.cutoff
+            .map_or(false, |cutoff| OffsetDateTime::now_utc() >= cutoff)
+    }
+
     #[tracing::instrument(name = "Broadcast offers to taker", skip(self, ctx))]
     async fn send_offers(&self, peer_id: PeerId, ctx: &mut xtra::Context<Self>) {
         let endpoint = self.endpoint.clone();
-        let offers = self.latest_offers.clone();
+        let offers = match self.is_past_cutoff() {
+            true => None,
+            false => self.latest_offers.clone(),
+        };
+        let this = ctx.address().expect("self to be alive");
+
+        let task = {
+            let this = this.clone();
+            async move {
+                let stream = endpoint
+                    .send(OpenSubstream::single_protocol(
+                        peer_id,
+                        deprecated::PROTOCOL,
+                    ))
+                    .await??
+                    .await?;
 
-        let task = async move {
-            let stream = endpoint
-                .send(OpenSubstream::single_protocol(
-                    peer_id,
-                    deprecated::PROTOCOL,
-                ))
-                .await??
-                .await?;
+                protocol::send(stream, offers).await?;
 
-            protocol::send(stream, offers).await?;
+                this.send(ProtocolUsed { peer_id }).await?;
 
-            anyhow::Ok(())
+                anyhow::Ok(())
+            }
         };
 
         let err_handler = move |e: anyhow::Error| async move {
@@ -60,7 +95,6 @@ impl Actor {
             }
         };
 
-        let this = ctx.address().expect("self to be alive");
         spawn_fallible(
             &this,
             task.instrument(tracing::Span::current()),
@@ -74,6 +108,11 @@ impl Actor {
     async fn handle(&mut self, msg: NewOffers, ctx: &mut xtra::Context<Self>) {
         self.latest_offers = MakerOffers::new(msg.0);
 
+        if self.is_past_cutoff() {
+            // Peers were told that there are no offers upon connecting
+            return;
+        }
+
         let quiet = quiet_spans::sometimes_quiet_children();
         for peer_id in self.connected_peers.iter().copied() {
             self.send_offers(peer_id, ctx)
@@ -81,6 +120,33 @@ impl Actor {
                 .await
         }
     }
+
+    async fn handle(&mut self, _: WithdrawOffers, ctx: &mut xtra::Context<Self>) {
+        self.latest_offers = None;
+
+        for peer_id in self.connected_peers.iter().copied() {
+            self.send_offers(peer_id, ctx).await
+        }
+    }
+
+    async fn handle(&mut self, msg: ProtocolUsed) {
+        let ProtocolUsed { peer_id } = msg;
+
+        if !self.usage.insert(peer_id) {
+            return;
+        }
+
+        match self.cutoff {
+            Some(cutoff) if self.is_past_cutoff() => {
+                tracing::warn!(%peer_id, %cutoff, "Peer uses deprecated offer protocol past its cutoff, refusing to send offers")
+            }
+            _ => tracing::info!(%peer_id, "Peer uses deprecated offer protocol"),
+        }
+    }
+
+    async fn handle(&mut self, _: GetProtocolUsage) -> ProtocolUsage {
+        self.usage.usage()
+    }
 }
 
 #[xtra_productivity]
@@ -98,6 +164,7 @@ impl Actor {
     async fn handle_connection_dropped(&mut self, msg: endpoint::ConnectionDropped) {
         tracing::trace!("Remove dropped connection: {:?}", msg.peer_id);
         self.connected_peers.remove(&msg.peer_id);
+        self.usage.remove(&msg.peer_id);
     }
 }
 
@@ -111,6 +178,11 @@ impl NewOffers {
     }
 }
 
+/// Instruct the `offer::maker::Actor` to drop all current offers
+/// and to inform all connected peers that there are no offers.
+#[derive(Clone, Copy)]
+pub struct WithdrawOffers;
+
 #[async_trait]
 impl xtra::Actor for Actor {
     type Stop = ();
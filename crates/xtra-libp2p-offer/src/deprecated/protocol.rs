@@ -1,5 +1,5 @@
+use crate::deprecated::MAX_FRAME_SIZE;
 use asynchronous_codec::FramedWrite;
-use asynchronous_codec::JsonCodec;
 use asynchronous_codec::JsonCodecError;
 use futures::AsyncWriteExt;
 use futures::SinkExt;
@@ -19,12 +19,16 @@ use nonempty::NonEmpty;
 use serde::Deserialize;
 use serde::Serialize;
 use time::Duration;
+use xtra_libp2p::limited::LimitedJsonCodec;
 
 pub(crate) async fn send<S>(sink: S, offers: Option<MakerOffers>) -> Result<(), JsonCodecError>
 where
     S: AsyncWriteExt + Unpin,
 {
-    let mut framed = FramedWrite::new(sink, JsonCodec::<Option<MakerOffers>, ()>::new());
+    let mut framed = FramedWrite::new(
+        sink,
+        LimitedJsonCodec::<Option<MakerOffers>, ()>::new(MAX_FRAME_SIZE),
+    );
     framed.send(offers).await?;
     MESSAGES_SENT.inc();
 
@@ -91,10 +95,14 @@ impl MakerOffers {
         // field is redundant across offers
         let tx_fee_rate = offers.first().tx_fee_rate;
 
-        // This version of the protocol caters to takers that only support BTCUSD CFDs
-        let mut offers = offers
-            .iter()
-            .filter(|offer| offer.contract_symbol == ContractSymbol::BtcUsd);
+        // This version of the protocol caters to takers that only support BTCUSD CFDs with a flat
+        // opening fee, without a cap on the quantity of a single order and without trading hours
+        let mut offers = offers.iter().filter(|offer| {
+            offer.contract_symbol == ContractSymbol::BtcUsd
+                && offer.opening_fee_tiers.is_empty()
+                && offer.max_contracts_per_order.is_none()
+                && offer.trading_calendar.is_none()
+        });
 
         let long = offers.find_map(|offer| {
             (offer.position_maker == Position::Long).then(|| Offer::from(offer.clone()))
@@ -0,0 +1,9 @@
+//! The unsigned `/itchysats/offer/2.0.0` protocol, superseded by signed offers.
+
+pub mod maker;
+pub mod protocol;
+
+pub const PROTOCOL: &str = "/itchysats/offer/2.0.0";
+
+/// Maximum size of the encoded offers in bytes
+pub const MAX_FRAME_SIZE: usize = 256 * 1024;
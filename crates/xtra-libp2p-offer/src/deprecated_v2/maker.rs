@@ -0,0 +1,210 @@
+use crate::deprecated_v2;
+use crate::deprecated_v2::protocol;
+use crate::deprecated_v2::protocol::Offers;
+use crate::usage::GetProtocolUsage;
+use crate::usage::PeersUsingProtocol;
+use crate::usage::ProtocolUsage;
+use crate::usage::ProtocolUsed;
+use async_trait::async_trait;
+use std::collections::HashSet;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio_extras::spawn_fallible;
+use tracing::Instrument;
+use xtra_libp2p::endpoint;
+use xtra_libp2p::libp2p::PeerId;
+use xtra_libp2p::Endpoint;
+use xtra_libp2p::GetConnectionStats;
+use xtra_libp2p::OpenSubstream;
+use xtra_productivity::xtra_productivity;
+
+pub struct Actor {
+    endpoint: xtra::Address<Endpoint>,
+    connected_peers: HashSet<PeerId>,
+    latest_offers: Offers,
+    usage: PeersUsingProtocol,
+    /// Point in time after which no offers are sent over the deprecated protocol anymore
+    cutoff: Option<OffsetDateTime>,
+}
+
+impl Actor {
+    pub fn new(endpoint: xtra::Address<Endpoint>) -> Self {
+        Self {
+            endpoint,
+            connected_peers: HashSet::default(),
+            latest_offers: Offers::default(),
+            usage: PeersUsingProtocol::new(deprecated_v2::PROTOCOL),
+            cutoff: None,
+        }
+    }
+
+    /// Refuse the deprecated protocol after `cutoff`.
+    ///
+    /// The protocol has no way of conveying an error, peers are told that there are no offers
+    /// instead. This keeps peers that did not upgrade from placing orders on outdated terms.
+    pub fn with_cutoff(self, cutoff: OffsetDateTime) -> Self {
+        Self {
+            cutoff: Some(cutoff),
+            ..self
+        }
+    }
+
+    fn is_past_cutoff(&self) -> bool {
+        self.cutoff
+            .map_or(false, |cutoff| OffsetDateTime::now_utc() >= cutoff)
+    }
+
+    #[tracing::instrument(name = "Broadcast offers to taker", skip(self, ctx))]
+    async fn send_offers(&self, peer_id: PeerId, ctx: &mut xtra::Context<Self>) {
+        let endpoint = self.endpoint.clone();
+        let offers = match self.is_past_cutoff() {
+            true => Offers::default(),
+            false => self.latest_offers.clone(),
+        };
+        let this = ctx.address().expect("self to be alive");
+
+        let task = {
+            let this = this.clone();
+            async move {
+                let stream = endpoint
+                    .send(OpenSubstream::single_protocol(
+                        peer_id,
+                        deprecated_v2::PROTOCOL,
+                    ))
+                    .await??
+                    .await?;
+
+                protocol::send(stream, offers).await?;
+
+                this.send(ProtocolUsed { peer_id }).await?;
+
+                anyhow::Ok(())
+            }
+        };
+
+        let err_handler = move |e: anyhow::Error| async move {
+            match e.downcast_ref::<xtra_libp2p::Error>() {
+                Some(xtra_libp2p::Error::ProtocolNotSupportedByPeer) => {
+                    // Some peers may not support this protocol as listeners
+                }
+                Some(xtra_libp2p::Error::NegotiationFailed(_)) => {
+                    tracing::debug!(%peer_id, "Failed to send offers: {e:#}")
+                }
+                _ => tracing::warn!(%peer_id, "Failed to send offers: {e:#}"),
+            }
+        };
+
+        spawn_fallible(
+            &this,
+            task.instrument(tracing::Span::current()),
+            err_handler,
+        );
+    }
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: NewOffers, ctx: &mut xtra::Context<Self>) {
+        self.latest_offers = Offers::new(&msg.0);
+
+        if self.is_past_cutoff() {
+            // Peers were told that there are no offers upon connecting
+            return;
+        }
+
+        let quiet = quiet_spans::sometimes_quiet_children();
+        for peer_id in self.connected_peers.iter().copied() {
+            self.send_offers(peer_id, ctx)
+                .instrument(quiet.clone())
+                .await
+        }
+    }
+
+    async fn handle(&mut self, _: WithdrawOffers, ctx: &mut xtra::Context<Self>) {
+        self.latest_offers = Offers::default();
+
+        for peer_id in self.connected_peers.iter().copied() {
+            self.send_offers(peer_id, ctx).await
+        }
+    }
+
+    async fn handle(&mut self, msg: ProtocolUsed) {
+        let ProtocolUsed { peer_id } = msg;
+
+        if !self.usage.insert(peer_id) {
+            return;
+        }
+
+        match self.cutoff {
+            Some(cutoff) if self.is_past_cutoff() => {
+                tracing::warn!(%peer_id, %cutoff, "Peer uses deprecated offer protocol past its cutoff, refusing to send offers")
+            }
+            _ => tracing::info!(%peer_id, "Peer uses deprecated offer protocol"),
+        }
+    }
+
+    async fn handle(&mut self, _: GetProtocolUsage) -> ProtocolUsage {
+        self.usage.usage()
+    }
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle_connection_established(
+        &mut self,
+        msg: endpoint::ConnectionEstablished,
+        ctx: &mut xtra::Context<Self>,
+    ) {
+        tracing::trace!("Adding newly established connection: {:?}", msg.peer_id);
+        self.connected_peers.insert(msg.peer_id);
+        self.send_offers(msg.peer_id, ctx).await;
+    }
+
+    async fn handle_connection_dropped(&mut self, msg: endpoint::ConnectionDropped) {
+        tracing::trace!("Remove dropped connection: {:?}", msg.peer_id);
+        self.connected_peers.remove(&msg.peer_id);
+        self.usage.remove(&msg.peer_id);
+    }
+}
+
+/// Instruct the `offer::maker::Actor` to broadcast to all
+/// connected peers an update to the current offers.
+pub struct NewOffers(Vec<model::Offer>);
+
+impl NewOffers {
+    pub fn new(offers: Vec<model::Offer>) -> Self {
+        Self(offers)
+    }
+}
+
+/// Instruct the `offer::maker::Actor` to drop all current offers
+/// and to broadcast an empty list of offers to all connected peers.
+#[derive(Clone, Copy)]
+pub struct WithdrawOffers;
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    #[tracing::instrument(name = "xtra_libp2p_offer::maker::Maker started", skip_all)]
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        match self.endpoint.send(GetConnectionStats).await {
+            Ok(connection_stats) => self
+                .connected_peers
+                .extend(connection_stats.connected_peers),
+            Err(e) => {
+                tracing::error!(
+                    "Unable to receive connection stats from the endpoint upon startup: {e:#}"
+                );
+
+                // This code path should not be hit, but in case we run into an error this sleep
+                // prevents a continuous endless loop of restarts.
+                tokio_extras::time::sleep(Duration::from_secs(2)).await;
+
+                ctx.stop_self();
+            }
+        }
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
@@ -0,0 +1,163 @@
+use crate::deprecated_v2::MAX_FRAME_SIZE;
+use asynchronous_codec::FramedWrite;
+use asynchronous_codec::JsonCodecError;
+use futures::AsyncWriteExt;
+use futures::SinkExt;
+use model::olivia::BitMexPriceEventId;
+use model::payout_curve::ETHUSD_MULTIPLIER;
+use model::ContractSymbol;
+use model::Contracts;
+use model::FundingPeriod;
+use model::FundingRate;
+use model::Leverage;
+use model::LotSize;
+use model::OfferId;
+use model::OpeningFee;
+use model::Position;
+use model::Price;
+use model::Timestamp;
+use model::TxFeeRate;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fmt;
+use time::Duration;
+use xtra_libp2p::limited::LimitedJsonCodec;
+
+pub(crate) async fn send<S>(sink: S, offers: Offers) -> Result<(), JsonCodecError>
+where
+    S: AsyncWriteExt + Unpin,
+{
+    let mut framed = FramedWrite::new(sink, LimitedJsonCodec::<Offers, ()>::new(MAX_FRAME_SIZE));
+    framed.send(offers).await?;
+    MESSAGES_SENT.inc();
+
+    Ok(())
+}
+
+#[derive(Clone, Default, Serialize, Deserialize, PartialEq, Debug)]
+pub(crate) struct Offers(Vec<Offer>);
+
+impl Offers {
+    pub(crate) fn new(offers: &[model::Offer]) -> Self {
+        // This version of the protocol caters to takers that do not verify signatures and ignore
+        // unknown fields, hence it only carries offers whose terms these takers fully understand
+        let offers = offers
+            .iter()
+            .filter(|offer| {
+                offer.quanto_multiplier == legacy_quanto_multiplier(offer.contract_symbol)
+                    && offer.max_contracts_per_order.is_none()
+                    && offer.time_to_live.is_none()
+                    && offer.funding_period == FundingPeriod::Hourly
+                    && offer.opening_fee_tiers.is_empty()
+                    && offer.revision == 0
+                    && offer.trading_calendar.is_none()
+            })
+            .cloned()
+            .map(Offer::from)
+            .collect();
+
+        Self(offers)
+    }
+}
+
+/// The quanto multiplier these takers assume, they only know the multiplier ETHUSD started out
+/// with.
+fn legacy_quanto_multiplier(contract_symbol: ContractSymbol) -> Option<Decimal> {
+    match contract_symbol {
+        ContractSymbol::BtcUsd => None,
+        ContractSymbol::EthUsd => Some(ETHUSD_MULTIPLIER),
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct Offer {
+    id: OfferId,
+    contract_symbol: ContractSymbol,
+    position_maker: Position,
+    price: Price,
+    min_quantity: Contracts,
+    max_quantity: Contracts,
+    leverage_choices: Vec<Leverage>,
+    creation_timestamp_maker: Timestamp,
+    settlement_interval: Duration,
+    oracle_event_id: BitMexPriceEventId,
+    tx_fee_rate: TxFeeRate,
+    funding_rate: FundingRate,
+    opening_fee: OpeningFee,
+    lot_size: LotSize,
+}
+
+impl From<model::Offer> for Offer {
+    fn from(offer: model::Offer) -> Self {
+        Self {
+            id: offer.id,
+            contract_symbol: offer.contract_symbol,
+            position_maker: offer.position_maker,
+            price: offer.price,
+            min_quantity: offer.min_quantity,
+            max_quantity: offer.max_quantity,
+            leverage_choices: offer.leverage_choices,
+            creation_timestamp_maker: offer.creation_timestamp_maker,
+            settlement_interval: offer.settlement_interval,
+            oracle_event_id: offer.oracle_event_id,
+            tx_fee_rate: offer.tx_fee_rate,
+            funding_rate: offer.funding_rate,
+            opening_fee: offer.opening_fee,
+            lot_size: offer.lot_size,
+        }
+    }
+}
+
+impl fmt::Debug for Offer {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("Offer")
+            .field("contract_symbol", &self.contract_symbol)
+            .field("position_maker", &self.position_maker)
+            .field("offer_id", &self.id)
+            .finish()
+    }
+}
+
+static MESSAGES_SENT: conquer_once::Lazy<prometheus::IntCounter> = conquer_once::Lazy::new(|| {
+    prometheus::register_int_counter!(
+        "offer_messages_sent_total_v2",
+        "The number of offer messages sent over the libp2p connection.",
+    )
+    .unwrap()
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::dummy_offers;
+    use model::symbols;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn given_offer_with_terms_unknown_to_protocol_then_offer_is_not_sent() {
+        let mut offers = dummy_offers();
+        offers[0].max_contracts_per_order = Some(Contracts::new(500));
+        offers[1].revision = 1;
+
+        assert_eq!(Offers::new(&offers), Offers::default());
+        assert_eq!(Offers::new(&dummy_offers()).0.len(), 2);
+    }
+
+    #[test]
+    fn given_ethusd_offer_with_default_multiplier_then_offer_is_sent() {
+        let mut offers = dummy_offers();
+        for offer in offers.iter_mut() {
+            offer.contract_symbol = ContractSymbol::EthUsd;
+            offer.quanto_multiplier = symbols::config(ContractSymbol::EthUsd)
+                .payout_curve
+                .quanto_multiplier();
+        }
+
+        assert_eq!(Offers::new(&offers).0.len(), 2);
+
+        offers[0].quanto_multiplier = Some(dec!(0.0000005));
+
+        assert_eq!(Offers::new(&offers).0.len(), 1);
+    }
+}
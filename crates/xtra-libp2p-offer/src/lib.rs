@@ -1,5 +1,8 @@
 mod current;
 pub mod deprecated;
+pub mod deprecated_v2;
+pub mod relay;
+pub mod usage;
 
 pub use current::*;
 
@@ -9,22 +12,12 @@ mod tests {
     use crate::taker::LatestOffers;
     use async_trait::async_trait;
     use futures::Future;
-    use model::olivia::BitMexPriceEventId;
     use model::ContractSymbol;
-    use model::Contracts;
-    use model::FundingRate;
-    use model::Leverage;
-    use model::LotSize;
+    use model::Identity;
     use model::Position;
-    use model::Price;
-    use model::Timestamp;
-    use model::TxFeeRate;
-    use rust_decimal::Decimal;
-    use rust_decimal_macros::dec;
     use std::collections::HashSet;
     use std::sync::Arc;
     use std::time::Duration;
-    use time::macros::datetime;
     use tracing_subscriber::util::SubscriberInitExt;
     use xtra::spawn::TokioGlobalSpawnExt;
     use xtra::Actor as _;
@@ -39,6 +32,7 @@ mod tests {
     use xtra_libp2p::Connect;
     use xtra_libp2p::Endpoint;
     use xtra_libp2p::ListenOn;
+    use xtra_libp2p_gossip as gossip;
     use xtra_productivity::xtra_productivity;
 
     #[tokio::test]
@@ -50,7 +44,8 @@ mod tests {
 
         let (maker_peer_id, maker_offer_addr, maker_endpoint_addr) =
             create_endpoint_with_offer_maker();
-        let (offer_receiver_addr, taker_endpoint_addr) = create_endpoint_with_offer_taker();
+        let (offer_receiver_addr, taker_endpoint_addr) =
+            create_endpoint_with_offer_taker(maker_peer_id);
 
         maker_endpoint_addr
             .send(ListenOn(Multiaddr::empty().with(Protocol::Memory(1000))))
@@ -105,7 +100,8 @@ mod tests {
 
         let (maker_peer_id, maker_offer_addr, maker_endpoint_addr) =
             create_endpoint_with_offer_maker();
-        let (offer_receiver_addr, taker_endpoint_addr) = create_endpoint_with_offer_taker();
+        let (offer_receiver_addr, taker_endpoint_addr) =
+            create_endpoint_with_offer_taker(maker_peer_id);
 
         maker_endpoint_addr
             .send(ListenOn(Multiaddr::empty().with(Protocol::Memory(1000))))
@@ -151,12 +147,118 @@ mod tests {
         assert!(received_offers.contains(&offer_eth_usd_short));
     }
 
+    #[tokio::test]
+    async fn given_withdrawn_offers_then_no_latest_offers() {
+        let (_, maker_offer_addr, _) = create_endpoint_with_offer_maker();
+
+        maker_offer_addr
+            .send(crate::maker::NewOffers::new(dummy_offers()))
+            .await
+            .unwrap();
+        maker_offer_addr
+            .send(crate::maker::WithdrawOffers)
+            .await
+            .unwrap();
+
+        let latest_offers = maker_offer_addr
+            .send(crate::maker::GetLatestOffers)
+            .await
+            .unwrap();
+
+        assert!(latest_offers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn given_taker_only_connected_to_relay_then_taker_discovers_maker_offers() {
+        let _g = tracing_subscriber::fmt()
+            .with_env_filter("xtra_libp2p_offer=trace,xtra_libp2p_gossip=trace")
+            .with_test_writer()
+            .set_default();
+
+        let maker_id = Keypair::generate_ed25519();
+        let maker_peer_id = maker_id.public().to_peer_id();
+        let maker_address = memory_address(3000, maker_peer_id);
+        let (maker_gossip, _, maker_endpoint) =
+            create_endpoint_with_gossip(maker_id.clone(), relay::Discovery::new());
+        let maker_offer_addr = crate::maker::Actor::new(maker_endpoint.clone(), maker_id)
+            .with_relay(
+                maker_gossip.into(),
+                dummy_identity(),
+                vec![maker_address.clone()],
+            )
+            .create(None)
+            .spawn_global();
+
+        let relay_id = Keypair::generate_ed25519();
+        let relay_peer_id = relay_id.public().to_peer_id();
+        let (_, _, relay_endpoint) = create_endpoint_with_gossip(relay_id, relay::Discovery::new());
+
+        let offers_receiver_addr = OffersReceiver::new().create(None).spawn_global();
+        let (_, taker_discovery, taker_endpoint) = create_endpoint_with_gossip(
+            Keypair::generate_ed25519(),
+            relay::Discovery::new().with_subscriber(offers_receiver_addr.clone().into(), vec![]),
+        );
+
+        maker_endpoint
+            .send(ListenOn(Multiaddr::empty().with(Protocol::Memory(3000))))
+            .await
+            .unwrap();
+        relay_endpoint
+            .send(ListenOn(Multiaddr::empty().with(Protocol::Memory(3001))))
+            .await
+            .unwrap();
+        relay_endpoint
+            .send(Connect(maker_address.clone()))
+            .await
+            .unwrap()
+            .unwrap();
+        taker_endpoint
+            .send(Connect(memory_address(3001, relay_peer_id)))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let new_offers = dummy_offers();
+
+        // maker keeps announcing the offers until they reach the taker, slow enough to stay
+        // within the rate limit of the relay
+        #[allow(clippy::disallowed_methods)]
+        tokio::spawn({
+            let new_offers = new_offers.clone();
+            async move {
+                loop {
+                    maker_offer_addr
+                        .send(crate::maker::NewOffers::new(new_offers.clone()))
+                        .await
+                        .unwrap();
+
+                    tokio_extras::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        });
+
+        let received_offers = retry_until_some(|| {
+            let offers_receiver_addr = offers_receiver_addr.clone();
+            async move { offers_receiver_addr.send(GetLatestOffers).await.unwrap() }
+        })
+        .await;
+        let discovered_makers = taker_discovery
+            .send(relay::GetDiscoveredMakers)
+            .await
+            .unwrap();
+
+        assert_eq!(received_offers, new_offers);
+        assert_eq!(discovered_makers.len(), 1);
+        assert_eq!(discovered_makers[0].peer_id, maker_peer_id);
+        assert_eq!(discovered_makers[0].addresses, vec![maker_address]);
+    }
+
     fn create_endpoint_with_offer_maker(
     ) -> (PeerId, Address<crate::maker::Actor>, Address<Endpoint>) {
         let (endpoint_addr, endpoint_context) = Context::new(None);
 
         let id = Keypair::generate_ed25519();
-        let offer_maker_addr = crate::maker::Actor::new(endpoint_addr.clone())
+        let offer_maker_addr = crate::maker::Actor::new(endpoint_addr.clone(), id.clone())
             .create(None)
             .spawn_global();
 
@@ -180,12 +282,15 @@ mod tests {
         (id.public().to_peer_id(), offer_maker_addr, endpoint_addr)
     }
 
-    fn create_endpoint_with_offer_taker() -> (Address<OffersReceiver>, Address<Endpoint>) {
+    fn create_endpoint_with_offer_taker(
+        maker_peer_id: PeerId,
+    ) -> (Address<OffersReceiver>, Address<Endpoint>) {
         let offers_receiver_addr = OffersReceiver::new().create(None).spawn_global();
 
-        let offer_taker_addr = crate::taker::Actor::new(offers_receiver_addr.clone().into())
-            .create(None)
-            .spawn_global();
+        let offer_taker_addr =
+            crate::taker::Actor::new(offers_receiver_addr.clone().into(), maker_peer_id)
+                .create(None)
+                .spawn_global();
 
         let endpoint_addr = Endpoint::new(
             Box::new(MemoryTransport::default),
@@ -201,6 +306,52 @@ mod tests {
         (offers_receiver_addr, endpoint_addr)
     }
 
+    fn create_endpoint_with_gossip(
+        id: Keypair,
+        discovery: relay::Discovery,
+    ) -> (
+        Address<gossip::Actor>,
+        Address<relay::Discovery>,
+        Address<Endpoint>,
+    ) {
+        let (endpoint_addr, endpoint_context) = Context::new(None);
+
+        let discovery_addr = discovery.create(None).spawn_global();
+        let gossip_addr = gossip::Actor::new(
+            endpoint_addr.clone(),
+            relay::topics()
+                .map(|topic| (topic, discovery_addr.clone().into()))
+                .collect(),
+        )
+        .create(None)
+        .spawn_global();
+
+        let endpoint = Endpoint::new(
+            Box::new(MemoryTransport::default),
+            id,
+            Duration::from_secs(10),
+            [(gossip::PROTOCOL, gossip_addr.clone().into())],
+            Subscribers::new(
+                vec![gossip_addr.clone().into()],
+                vec![gossip_addr.clone().into()],
+                vec![],
+                vec![],
+            ),
+            Arc::new(HashSet::default()),
+        );
+
+        #[allow(clippy::disallowed_methods)]
+        tokio::spawn(endpoint_context.run(endpoint));
+
+        (gossip_addr, discovery_addr, endpoint_addr)
+    }
+
+    fn memory_address(port: u64, peer_id: PeerId) -> Multiaddr {
+        Multiaddr::empty()
+            .with(Protocol::Memory(port))
+            .with(Protocol::P2p(peer_id.into()))
+    }
+
     struct OffersReceiver {
         offers: Vec<model::Offer>,
     }
@@ -221,7 +372,7 @@ mod tests {
     #[xtra_productivity]
     impl OffersReceiver {
         async fn handle(&mut self, msg: LatestOffers) {
-            self.offers = msg.0;
+            self.offers = msg.offers;
         }
     }
 
@@ -250,6 +401,12 @@ mod tests {
         }
     }
 
+    pub fn dummy_identity() -> Identity {
+        "2cfc6a9be4f0f07e3aa6bfa3f5b1fcb1c8d3eaf1e9a1f2a0b3c4d5e6f7a8b9c0"
+            .parse()
+            .unwrap()
+    }
+
     pub fn dummy_offers() -> Vec<model::Offer> {
         vec![
             dummy_offer(ContractSymbol::BtcUsd, Position::Long),
@@ -259,23 +416,8 @@ mod tests {
 
     fn dummy_offer(contract_symbol: ContractSymbol, position_maker: Position) -> model::Offer {
         model::Offer {
-            id: Default::default(),
-            contract_symbol,
             position_maker,
-            price: Price::new(dec!(1000)).unwrap(),
-            min_quantity: Contracts::new(100),
-            max_quantity: Contracts::new(1000),
-            leverage_choices: vec![Leverage::TWO],
-            creation_timestamp_maker: Timestamp::now(),
-            settlement_interval: time::Duration::hours(24),
-            oracle_event_id: BitMexPriceEventId::with_20_digits(
-                datetime!(2021-10-04 22:00:00).assume_utc(),
-                contract_symbol,
-            ),
-            tx_fee_rate: TxFeeRate::default(),
-            funding_rate: FundingRate::new(Decimal::ONE).unwrap(),
-            opening_fee: Default::default(),
-            lot_size: LotSize::new(100),
+            ..model::Offer::dummy_short(contract_symbol)
         }
     }
 }
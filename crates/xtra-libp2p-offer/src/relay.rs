@@ -0,0 +1,326 @@
+//! Distribution of offers through the gossip network.
+//!
+//! Makers announce their signed offers on one topic per contract symbol, together with their
+//! network identity and the addresses they can be dialed at. Takers (and relays) learn about makers
+//! they are not connected to from these announcements. Taking an offer requires dialing the maker
+//! directly.
+//!
+//! Announcements are only relayed if all offers are signed by the announcing maker, belong to the
+//! contract symbol of the topic and were signed recently. Forging or replaying offers of another
+//! maker is therefore not possible and spamming the network requires a fresh identity per
+//! announcement.
+
+use crate::current::protocol::Offers;
+use crate::current::protocol::MAX_SIGNATURE_AGE_SECS;
+use crate::taker::LatestOffers;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use model::ContractSymbol;
+use model::Identity;
+use model::Timestamp;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use strum::IntoEnumIterator;
+use xtra::prelude::MessageChannel;
+use xtra_libp2p::libp2p::identity::Keypair;
+use xtra_libp2p::libp2p::Multiaddr;
+use xtra_libp2p::libp2p::PeerId;
+use xtra_libp2p_gossip::Publish;
+use xtra_libp2p_gossip::Received;
+use xtra_productivity::xtra_productivity;
+
+const TOPIC_PREFIX: &str = "/itchysats/offers/";
+
+/// The gossip topic offers of the given contract symbol are announced on.
+pub fn topic(contract_symbol: ContractSymbol) -> String {
+    format!(
+        "{TOPIC_PREFIX}{}",
+        contract_symbol.to_string().to_lowercase()
+    )
+}
+
+/// The gossip topics of all contract symbols.
+pub fn topics() -> impl Iterator<Item = String> {
+    ContractSymbol::iter().map(topic)
+}
+
+fn contract_symbol(topic: &str) -> Option<ContractSymbol> {
+    ContractSymbol::iter().find(|symbol| self::topic(*symbol) == topic)
+}
+
+#[derive(Serialize, Deserialize)]
+struct Announcement {
+    maker: model::libp2p::PeerId,
+    identity: Identity,
+    addresses: Vec<Multiaddr>,
+    offers: Offers,
+}
+
+/// Create the announcements of the maker's `offers`, one per contract symbol.
+///
+/// An announcement without offers is created for contract symbols without offers, so that offers
+/// which are no longer available are withdrawn across the network.
+pub fn announcements(
+    offers: Vec<model::Offer>,
+    keypair: &Keypair,
+    identity: Identity,
+    addresses: Vec<Multiaddr>,
+) -> Result<Vec<Publish>> {
+    let maker = keypair.public().to_peer_id();
+    let now = Timestamp::now();
+
+    ContractSymbol::iter()
+        .map(|contract_symbol| {
+            let offers = offers
+                .iter()
+                .filter(|offer| offer.contract_symbol == contract_symbol)
+                .cloned()
+                .collect();
+
+            let announcement = Announcement {
+                maker: maker.into(),
+                identity,
+                addresses: addresses.clone(),
+                offers: Offers::sign(offers, keypair, now)?,
+            };
+
+            Ok(Publish {
+                topic: topic(contract_symbol),
+                data: serde_json::to_vec(&announcement)?,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveredMaker {
+    pub peer_id: PeerId,
+    /// Network identity the maker uses in contract setup
+    pub identity: Identity,
+    /// Addresses to dial the maker at
+    pub addresses: Vec<Multiaddr>,
+    pub offers: Vec<model::Offer>,
+    /// When the maker last announced its offers
+    pub last_seen: Timestamp,
+}
+
+/// Validates announcements received through the gossip network and keeps track of the makers
+/// that announced offers.
+#[derive(Default)]
+pub struct Discovery {
+    makers: HashMap<(PeerId, ContractSymbol), DiscoveredMaker>,
+    subscriber: Option<MessageChannel<LatestOffers, ()>>,
+    /// Makers whose offers are not forwarded to the subscriber
+    known_makers: HashSet<PeerId>,
+}
+
+impl Discovery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forward the offers of discovered makers to `subscriber`, except for the offers of
+    /// `known_makers`, which the subscriber receives from the makers directly.
+    pub fn with_subscriber(
+        self,
+        subscriber: MessageChannel<LatestOffers, ()>,
+        known_makers: Vec<PeerId>,
+    ) -> Self {
+        Self {
+            subscriber: Some(subscriber),
+            known_makers: known_makers.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// The offers the maker announced across all contract symbols.
+    fn offers_of(&self, maker: PeerId) -> Vec<model::Offer> {
+        self.makers
+            .values()
+            .filter(|discovered| discovered.peer_id == maker)
+            .flat_map(|discovered| discovered.offers.iter().cloned())
+            .collect()
+    }
+
+    /// Process an announcement, returning the maker that announced it.
+    fn process(&mut self, topic: &str, data: &[u8], now: Timestamp) -> Result<PeerId> {
+        let contract_symbol = contract_symbol(topic).context("Unknown topic")?;
+
+        let Announcement {
+            maker,
+            identity,
+            addresses,
+            offers,
+        } = serde_json::from_slice(data).context("Failed to decode announcement")?;
+        let maker = maker.inner();
+
+        let offers = offers.verify(maker, now)?;
+
+        if let Some(offer) = offers
+            .iter()
+            .find(|offer| offer.contract_symbol != contract_symbol)
+        {
+            bail!("Announced offer {} does not match topic {topic}", offer.id);
+        }
+
+        if addresses.is_empty() {
+            bail!("Maker {maker} did not announce any address");
+        }
+
+        let key = (maker, contract_symbol);
+        if offers.is_empty() {
+            self.makers.remove(&key);
+            return Ok(maker);
+        }
+
+        self.makers.insert(
+            key,
+            DiscoveredMaker {
+                peer_id: maker,
+                identity,
+                addresses,
+                offers,
+                last_seen: now,
+            },
+        );
+
+        Ok(maker)
+    }
+}
+
+/// Get the makers that recently announced offers, grouped per maker and contract symbol.
+#[derive(Clone, Copy)]
+pub struct GetDiscoveredMakers;
+
+#[xtra_productivity]
+impl Discovery {
+    async fn handle(&mut self, msg: Received) -> Result<()> {
+        let Received {
+            source,
+            topic,
+            data,
+        } = msg;
+
+        let maker = self
+            .process(&topic, &data, Timestamp::now())
+            .with_context(|| format!("Invalid offer announcement relayed by {source}"))?;
+
+        if let Some(subscriber) = &self.subscriber {
+            if !self.known_makers.contains(&maker) {
+                let offers = self.offers_of(maker);
+                if let Err(e) = subscriber.send(LatestOffers { maker, offers }).await {
+                    tracing::warn!(%maker, "Failed to forward discovered offers: {e:#}");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn handle(&mut self, _: GetDiscoveredMakers) -> Vec<DiscoveredMaker> {
+        let now = Timestamp::now();
+
+        // Makers re-announce their offers periodically, stale offers are not valid anymore
+        self.makers
+            .retain(|_, maker| now.seconds() - maker.last_seen.seconds() <= MAX_SIGNATURE_AGE_SECS);
+
+        self.makers.values().cloned().collect()
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Discovery {
+    type Stop = ();
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::dummy_identity;
+    use crate::tests::dummy_offers;
+
+    #[test]
+    fn given_announcement_of_maker_then_maker_is_discovered() {
+        let identity = Keypair::generate_ed25519();
+        let address = "/memory/1000".parse::<Multiaddr>().unwrap();
+        let mut discovery = Discovery::new();
+        let offers = dummy_offers();
+
+        for announcement in announcements(
+            offers.clone(),
+            &identity,
+            dummy_identity(),
+            vec![address.clone()],
+        )
+        .unwrap()
+        {
+            discovery
+                .process(&announcement.topic, &announcement.data, Timestamp::now())
+                .unwrap();
+        }
+
+        let makers = discovery.makers.into_values().collect::<Vec<_>>();
+        assert_eq!(makers.len(), 1);
+        assert_eq!(makers[0].peer_id, identity.public().to_peer_id());
+        assert_eq!(makers[0].identity, dummy_identity());
+        assert_eq!(makers[0].addresses, vec![address]);
+        assert_eq!(makers[0].offers, offers);
+    }
+
+    #[test]
+    fn given_announcement_on_topic_of_other_contract_symbol_then_rejected() {
+        let identity = Keypair::generate_ed25519();
+        let address = "/memory/1000".parse::<Multiaddr>().unwrap();
+        let mut discovery = Discovery::new();
+
+        let btc_usd_announcement =
+            announcements(dummy_offers(), &identity, dummy_identity(), vec![address])
+                .unwrap()
+                .into_iter()
+                .find(|announcement| announcement.topic == topic(ContractSymbol::BtcUsd))
+                .unwrap();
+
+        let result = discovery.process(
+            &topic(ContractSymbol::EthUsd),
+            &btc_usd_announcement.data,
+            Timestamp::now(),
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn given_announcement_without_offers_then_maker_is_forgotten() {
+        let identity = Keypair::generate_ed25519();
+        let address = "/memory/1000".parse::<Multiaddr>().unwrap();
+        let mut discovery = Discovery::new();
+
+        for announcement in announcements(
+            dummy_offers(),
+            &identity,
+            dummy_identity(),
+            vec![address.clone()],
+        )
+        .unwrap()
+        {
+            discovery
+                .process(&announcement.topic, &announcement.data, Timestamp::now())
+                .unwrap();
+        }
+        for announcement in
+            announcements(Vec::new(), &identity, dummy_identity(), vec![address]).unwrap()
+        {
+            discovery
+                .process(&announcement.topic, &announcement.data, Timestamp::now())
+                .unwrap();
+        }
+
+        assert!(discovery.makers.is_empty());
+    }
+}
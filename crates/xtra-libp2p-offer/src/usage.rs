@@ -0,0 +1,87 @@
+//! Tracking of the offer protocol versions used by the connected peers.
+//!
+//! The maker sends its offers over every offer protocol version to every connected peer. A peer
+//! uses a version if it accepted the offers on it, peers that do not listen on a version reject
+//! the substream. Knowing which peers still use the deprecated version tells when it can be
+//! removed.
+
+use serde::Serialize;
+use std::collections::HashSet;
+use xtra_libp2p::libp2p::PeerId;
+
+/// Connected peers that accepted offers on a protocol version.
+pub(crate) struct PeersUsingProtocol {
+    protocol: &'static str,
+    peers: HashSet<PeerId>,
+}
+
+impl PeersUsingProtocol {
+    pub(crate) fn new(protocol: &'static str) -> Self {
+        PEERS_USING_PROTOCOL_GAUGE
+            .with_label_values(&[protocol])
+            .set(0);
+
+        Self {
+            protocol,
+            peers: HashSet::new(),
+        }
+    }
+
+    /// Returns whether the peer was not known to use the protocol yet.
+    pub(crate) fn insert(&mut self, peer_id: PeerId) -> bool {
+        let is_new = self.peers.insert(peer_id);
+        if is_new {
+            self.update_gauge();
+        }
+
+        is_new
+    }
+
+    pub(crate) fn remove(&mut self, peer_id: &PeerId) {
+        if self.peers.remove(peer_id) {
+            self.update_gauge();
+        }
+    }
+
+    pub(crate) fn usage(&self) -> ProtocolUsage {
+        ProtocolUsage {
+            protocol: self.protocol,
+            peers: self.peers.iter().map(PeerId::to_string).collect(),
+        }
+    }
+
+    fn update_gauge(&self) {
+        PEERS_USING_PROTOCOL_GAUGE
+            .with_label_values(&[self.protocol])
+            .set(self.peers.len() as i64);
+    }
+}
+
+/// Record that a peer accepted the offers sent over a protocol version.
+#[derive(Clone, Copy)]
+pub(crate) struct ProtocolUsed {
+    pub(crate) peer_id: PeerId,
+}
+
+/// Query the connected peers which use the protocol version of an offer actor.
+#[derive(Clone, Copy)]
+pub struct GetProtocolUsage;
+
+/// The connected peers which use a protocol version.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProtocolUsage {
+    pub protocol: &'static str,
+    pub peers: Vec<String>,
+}
+
+const PROTOCOL_LABEL: &str = "protocol";
+
+static PEERS_USING_PROTOCOL_GAUGE: conquer_once::Lazy<prometheus::IntGaugeVec> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_int_gauge_vec!(
+            "offer_protocol_peers",
+            "The number of connected peers which accepted offers, broken down by protocol version.",
+            &[PROTOCOL_LABEL]
+        )
+        .unwrap()
+    });
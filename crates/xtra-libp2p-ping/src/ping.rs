@@ -6,6 +6,7 @@ use prometheus::Histogram;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::time::Duration;
+use std::time::Instant;
 use tokio_extras::spawn_fallible;
 use tracing::Instrument;
 use xtra::prelude::async_trait;
@@ -31,6 +32,11 @@ use xtras::SendInterval;
 /// When constructed with a `ping_interval`, the actor will request all connected peers from the
 /// provided [`Endpoint`] and ping all peers.
 ///
+/// The interval can adapt to the activity on a connection: Peers are pinged at the `ping_interval`
+/// while they are marked as active through [`RecordActivity`], and at the longer interval set with
+/// [`Actor::with_idle_interval`] otherwise. [`SetMaxInterval`] bounds the interval per peer, e.g.
+/// to the longest interval a peer tolerates before considering the connection dead.
+///
 /// This actor also implements the listening end of the ping protocol and will correctly handle
 /// incoming pings even without a `ping_interval` set. This is useful if an application wants to
 /// allow other peers in the network to measure their latency but is not interested in measuring
@@ -38,8 +44,12 @@ use xtras::SendInterval;
 pub struct Actor {
     endpoint: Address<Endpoint>,
     ping_interval: Duration,
+    idle_interval: Duration,
     connected_peers: HashSet<PeerId>,
     latencies: HashMap<PeerId, Duration>,
+    last_pinged: HashMap<PeerId, Instant>,
+    active_until: HashMap<PeerId, Instant>,
+    max_intervals: HashMap<PeerId, Duration>,
 }
 
 impl Actor {
@@ -47,10 +57,54 @@ impl Actor {
         Self {
             endpoint,
             ping_interval,
+            idle_interval: ping_interval,
             connected_peers: HashSet::default(),
             latencies: HashMap::default(),
+            last_pinged: HashMap::default(),
+            active_until: HashMap::default(),
+            max_intervals: HashMap::default(),
+        }
+    }
+
+    /// Ping peers without recent activity only every `idle_interval`.
+    ///
+    /// Intervals shorter than the `ping_interval` have no effect.
+    pub fn with_idle_interval(self, idle_interval: Duration) -> Self {
+        Self {
+            idle_interval: idle_interval.max(self.ping_interval),
+            ..self
+        }
+    }
+
+    /// The interval at which the peer is currently pinged.
+    fn interval(&self, peer_id: &PeerId, now: Instant) -> Duration {
+        let is_active = self
+            .active_until
+            .get(peer_id)
+            .map_or(false, |active_until| *active_until > now);
+
+        let interval = if is_active {
+            self.ping_interval
+        } else {
+            self.idle_interval
+        };
+
+        match self.max_intervals.get(peer_id) {
+            Some(max_interval) => interval.min(*max_interval),
+            None => interval,
         }
     }
+
+    fn is_ping_due(&self, peer_id: &PeerId, now: Instant) -> bool {
+        let last_pinged = match self.last_pinged.get(peer_id) {
+            Some(last_pinged) => *last_pinged,
+            None => return true,
+        };
+
+        // Peers are checked every `ping_interval`, allow for some jitter so that a ping is not
+        // delayed by a whole period
+        now.duration_since(last_pinged) + self.ping_interval / 2 >= self.interval(peer_id, now)
+    }
 }
 
 #[async_trait]
@@ -86,9 +140,24 @@ impl xtra::Actor for Actor {
     async fn stopped(self) -> Self::Stop {}
 }
 
-/// Private message to ping all connected peers.
+/// Private message to ping all connected peers that are due.
 struct Ping;
 
+/// Mark the connection to a peer as active for the given duration.
+///
+/// Active peers are pinged at the shorter `ping_interval`, e.g. while a protocol is running or a
+/// settlement window approaches.
+pub struct RecordActivity {
+    pub peer_id: PeerId,
+    pub duration: Duration,
+}
+
+/// Never ping a peer less often than every `max_interval`.
+pub struct SetMaxInterval {
+    pub peer_id: PeerId,
+    pub max_interval: Duration,
+}
+
 /// Private message to record latency of a peer.
 struct RecordLatency {
     peer_id: PeerId,
@@ -103,11 +172,21 @@ pub(crate) struct GetLatency(pub PeerId);
 #[xtra_productivity]
 impl Actor {
     async fn handle(&mut self, _: Ping, ctx: &mut Context<Self>) {
-        self.latencies.clear();
+        let now = Instant::now();
+
+        let due_peers = self
+            .connected_peers
+            .iter()
+            .copied()
+            .filter(|peer_id| self.is_ping_due(peer_id, now))
+            .collect::<Vec<_>>();
 
         let quiet = quiet_spans::sometimes_quiet_children();
 
-        for peer_id in self.connected_peers.iter().copied() {
+        for peer_id in due_peers {
+            self.latencies.remove(&peer_id);
+            self.last_pinged.insert(peer_id, now);
+
             let endpoint = self.endpoint.clone();
             let this = ctx.address().expect("we are alive");
 
@@ -156,6 +235,17 @@ impl Actor {
     async fn handle(&mut self, GetLatency(peer): GetLatency) -> Option<Duration> {
         return self.latencies.get(&peer).copied();
     }
+
+    async fn handle(&mut self, msg: RecordActivity) {
+        let active_until = Instant::now() + msg.duration;
+
+        let entry = self.active_until.entry(msg.peer_id).or_insert(active_until);
+        *entry = (*entry).max(active_until);
+    }
+
+    async fn handle(&mut self, msg: SetMaxInterval) {
+        self.max_intervals.insert(msg.peer_id, msg.max_interval);
+    }
 }
 
 #[xtra_productivity]
@@ -171,6 +261,9 @@ impl Actor {
     async fn handle_connection_dropped(&mut self, msg: endpoint::ConnectionDropped) {
         tracing::trace!("Remove dropped connection from ping: {:?}", msg.peer_id);
         self.connected_peers.remove(&msg.peer_id);
+        self.last_pinged.remove(&msg.peer_id);
+        self.active_until.remove(&msg.peer_id);
+        self.max_intervals.remove(&msg.peer_id);
     }
 }
 
@@ -193,3 +286,61 @@ static PEER_LATENCY_HISTOGRAM: Lazy<Histogram> = Lazy::new(|| {
     )
     .unwrap()
 });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PING_INTERVAL: Duration = Duration::from_secs(30);
+    const IDLE_INTERVAL: Duration = Duration::from_secs(300);
+
+    #[test]
+    fn active_peers_are_pinged_more_often_than_idle_peers() {
+        let mut actor = actor();
+        let now = Instant::now();
+        let active_peer = PeerId::random();
+        let idle_peer = PeerId::random();
+
+        actor
+            .active_until
+            .insert(active_peer, now + Duration::from_secs(60));
+
+        assert_eq!(actor.interval(&active_peer, now), PING_INTERVAL);
+        assert_eq!(actor.interval(&idle_peer, now), IDLE_INTERVAL);
+        assert_eq!(
+            actor.interval(&active_peer, now + Duration::from_secs(61)),
+            IDLE_INTERVAL
+        );
+    }
+
+    #[test]
+    fn idle_interval_is_bounded_by_max_interval() {
+        let mut actor = actor();
+        let now = Instant::now();
+        let peer = PeerId::random();
+
+        actor.max_intervals.insert(peer, Duration::from_secs(120));
+
+        assert_eq!(actor.interval(&peer, now), Duration::from_secs(120));
+    }
+
+    #[test]
+    fn idle_peer_is_due_once_idle_interval_elapsed() {
+        let mut actor = actor();
+        let now = Instant::now();
+        let peer = PeerId::random();
+
+        assert!(actor.is_ping_due(&peer, now));
+
+        actor.last_pinged.insert(peer, now);
+
+        assert!(!actor.is_ping_due(&peer, now + PING_INTERVAL));
+        assert!(actor.is_ping_due(&peer, now + IDLE_INTERVAL));
+    }
+
+    fn actor() -> Actor {
+        let (endpoint, _) = Context::<Endpoint>::new(None);
+
+        Actor::new(endpoint, PING_INTERVAL).with_idle_interval(IDLE_INTERVAL)
+    }
+}
@@ -2,4 +2,12 @@ pub mod maker;
 pub mod protocol;
 pub mod taker;
 
-pub const PROTOCOL: &str = "/itchysats/rollover/3.0.0";
+pub const PROTOCOL: &str = "/itchysats/rollover/4.0.0";
+
+/// Version of [`PROTOCOL`] without sequence numbers, served until all takers upgraded
+pub const UNSEQUENCED_PROTOCOL: &str = "/itchysats/rollover/3.0.0";
+
+/// Maximum size of an encoded message in bytes
+///
+/// Rollover messages carry the CETs of all payouts of the new settlement event.
+pub const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
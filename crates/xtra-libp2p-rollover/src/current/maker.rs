@@ -1,24 +1,26 @@
 use crate::current::protocol::*;
+use crate::current::MAX_FRAME_SIZE;
+use crate::current::PROTOCOL;
 use anyhow::Context;
 use async_trait::async_trait;
-use asynchronous_codec::Framed;
-use asynchronous_codec::JsonCodec;
 use bdk_ext::keypair;
 use futures::SinkExt;
 use futures::StreamExt;
 use libp2p_core::PeerId;
 use maia_core::secp256k1_zkp::XOnlyPublicKey;
+use model::protocol_timeout::TimeoutPolicy;
 use model::Dlc;
 use model::ExecuteOnCfd;
 use model::Position;
 use model::Role;
 use tokio_extras::FutureExt;
+use xtra_libp2p::sequenced::Session;
+use xtra_libp2p::sequenced::Sessions;
 use xtra_libp2p::NewInboundSubstream;
-use xtra_libp2p::Substream;
 use xtra_productivity::xtra_productivity;
 
-/// Permanent actor to handle incoming substreams for the `/itchysats/rollover/2.0.0`
-/// protocol.
+/// Permanent actor to handle incoming substreams for the `/itchysats/rollover/4.0.0` and
+/// `/itchysats/rollover/3.0.0` protocols.
 ///
 /// There is only one instance of this actor for all connections, meaning we must always spawn a
 /// task whenever we interact with a substream to not block the execution of other connections.
@@ -29,6 +31,7 @@ pub struct Actor<E, O, R> {
     executor: E,
     rates: R,
     is_accepting_rollovers: bool,
+    sessions: Sessions,
 }
 
 impl<E, O, R> Actor<E, O, R> {
@@ -46,6 +49,7 @@ impl<E, O, R> Actor<E, O, R> {
             executor,
             rates,
             is_accepting_rollovers: true,
+            sessions: Sessions::default(),
         }
     }
 }
@@ -84,12 +88,25 @@ where
     async fn handle(&mut self, msg: NewInboundSubstream, ctx: &mut xtra::Context<Self>) {
         let NewInboundSubstream { peer_id, stream } = msg;
         let address = ctx.address().expect("we are alive");
+        let sessions = self.sessions.clone();
 
         tokio_extras::spawn_fallible(
             &address.clone(),
             async move {
-                let mut framed =
-                    Framed::new(stream, JsonCodec::<ListenerMessage, DialerMessage>::new());
+                let mut framed = match sessions
+                    .accept::<ListenerMessage, DialerMessage>(
+                        peer_id,
+                        stream,
+                        PROTOCOL,
+                        MAX_FRAME_SIZE,
+                    )
+                    .await
+                    .context("Failed to decode Propose")?
+                {
+                    Some(framed) => framed,
+                    // The taker resumed a rollover that is already running
+                    None => return Ok(()),
+                };
 
                 let propose = framed
                     .next()
@@ -122,16 +139,18 @@ where
         } = msg;
         let order_id = propose.order_id;
 
-        let (base_dlc_params, contract_symbol) = match self
+        let (base_dlc_params, contract_symbol, quanto_multiplier) = match self
             .executor
             .execute(order_id, |cfd| {
                 cfd.verify_counterparty_peer_id(&peer_id.into())?;
+                cfd.verify_quanto_multiplier(propose.quanto_multiplier)?;
 
                 let (event, base_dlc_params) =
                     cfd.start_rollover_maker(propose.from_commit_txid)?;
                 let contract_symbol = cfd.contract_symbol();
+                let quanto_multiplier = cfd.quanto_multiplier();
 
-                Ok((event, base_dlc_params, contract_symbol))
+                Ok((event, base_dlc_params, contract_symbol, quanto_multiplier))
             })
             .await
             .context("Rollover failed after handling taker proposal")
@@ -172,6 +191,8 @@ where
             tracing::debug_span!("next rollover message")
         }
 
+        let msg_timeout = TimeoutPolicy::ROLLOVER.negotiate(propose.msg_timeout_secs);
+
         let task = {
             let executor = self.executor.clone();
             let oracle = self.oracle.clone();
@@ -221,6 +242,8 @@ where
                         tx_fee_rate,
                         funding_rate,
                         complete_fee: complete_fee.into(),
+                        quanto_multiplier,
+                        msg_timeout_secs: Some(msg_timeout.as_secs()),
                     })))
                     .await
                     .context("Failed to send rollover confirmation message")?;
@@ -241,13 +264,10 @@ where
 
                 let msg0 = framed
                     .next()
-                    .timeout(ROLLOVER_MSG_TIMEOUT, next_rollover_span)
+                    .timeout(msg_timeout, next_rollover_span)
                     .await
                     .with_context(|| {
-                        format!(
-                            "Expected Msg0 within {} seconds",
-                            ROLLOVER_MSG_TIMEOUT.as_secs()
-                        )
+                        format!("Expected Msg0 within {} seconds", msg_timeout.as_secs())
                     })?
                     .context("Empty stream instead of Msg0")?
                     .context("Unable to decode dialer Msg0")?
@@ -277,19 +297,15 @@ where
                     complete_fee,
                     punish_params,
                     Role::Maker,
-                    contract_symbol,
                 )
                 .await?;
 
                 let msg1 = framed
                     .next()
-                    .timeout(ROLLOVER_MSG_TIMEOUT, next_rollover_span)
+                    .timeout(msg_timeout, next_rollover_span)
                     .await
                     .with_context(|| {
-                        format!(
-                            "Expected Msg1 within {} seconds",
-                            ROLLOVER_MSG_TIMEOUT.as_secs()
-                        )
+                        format!("Expected Msg1 within {} seconds", msg_timeout.as_secs())
                     })?
                     .context("Empty stream instead of Msg1")?
                     .context("Unable to decode dialer Msg1")?
@@ -321,13 +337,10 @@ where
 
                 let msg2 = framed
                     .next()
-                    .timeout(ROLLOVER_MSG_TIMEOUT, next_rollover_span)
+                    .timeout(msg_timeout, next_rollover_span)
                     .await
                     .with_context(|| {
-                        format!(
-                            "Expected Msg2 within {} seconds",
-                            ROLLOVER_MSG_TIMEOUT.as_secs()
-                        )
+                        format!("Expected Msg2 within {} seconds", msg_timeout.as_secs())
                     })?
                     .context("Empty stream instead of Msg2")?
                     .context("Unable to decode dialer Msg2")?
@@ -405,6 +418,6 @@ impl UpdateConfiguration {
 
 struct ProposeReceived {
     propose: Propose,
-    framed: Framed<Substream, JsonCodec<ListenerMessage, DialerMessage>>,
+    framed: Session<ListenerMessage, DialerMessage>,
     peer_id: PeerId,
 }
@@ -20,16 +20,17 @@ use maia_core::CfdTransactions;
 use maia_core::PartyParams;
 use model::olivia;
 use model::olivia::BitMexPriceEventId;
-use model::payout_curve::ETHUSD_MULTIPLIER;
 use model::shared_protocol::verify_adaptor_signature;
 use model::shared_protocol::verify_cets;
 use model::shared_protocol::verify_signature;
+use model::symbols::PayoutCurve;
 use model::Cet;
 use model::ContractSymbol;
 use model::Dlc;
 use model::ExecuteOnCfd;
 use model::FundingFee;
 use model::FundingRate;
+use model::FundingRateCheck;
 use model::OraclePayouts;
 use model::OrderId;
 use model::Payouts;
@@ -40,17 +41,11 @@ use model::Timestamp;
 use model::TransactionExt;
 use model::TxFeeRate;
 use model::CET_TIMELOCK;
+use rust_decimal::Decimal;
 use serde::Deserialize;
 use serde::Serialize;
 use std::collections::HashMap;
 use std::ops::RangeInclusive;
-use std::time::Duration;
-
-/// How long rollover protocol waits for the next message before giving up
-///
-/// 60s timeout are acceptable here because rollovers are automatically retried; a few failed
-/// rollovers are not a big deal.
-pub(crate) const ROLLOVER_MSG_TIMEOUT: Duration = Duration::from_secs(60);
 
 pub struct RolloverCompletedParams {
     pub dlc: Dlc,
@@ -114,6 +109,17 @@ pub struct Propose {
     pub order_id: OrderId,
     pub timestamp: Timestamp,
     pub from_commit_txid: Txid,
+    /// The quanto multiplier the taker scales the payout with
+    ///
+    /// Not sent by takers that predate explicit multipliers.
+    #[serde(default)]
+    pub quanto_multiplier: Option<Decimal>,
+    /// The taker's proposal of the rollover message timeout in seconds, see
+    /// [`model::protocol_timeout`]
+    ///
+    /// Not sent by takers that predate timeout negotiation.
+    #[serde(default)]
+    pub msg_timeout_secs: Option<u64>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -123,6 +129,16 @@ pub struct Confirm {
     pub tx_fee_rate: TxFeeRate,
     pub funding_rate: FundingRate,
     pub complete_fee: CompleteFee,
+    /// The quanto multiplier the maker scales the payout with
+    ///
+    /// Not sent by makers that predate explicit multipliers.
+    #[serde(default)]
+    pub quanto_multiplier: Option<Decimal>,
+    /// The negotiated rollover message timeout in seconds
+    ///
+    /// Not sent by makers that predate timeout negotiation.
+    #[serde(default)]
+    pub msg_timeout_secs: Option<u64>,
 }
 
 #[derive(Copy, Clone, Serialize, Deserialize)]
@@ -274,6 +290,21 @@ where
     }
 }
 
+pub(crate) async fn emit_funding_rate_rejected<E>(
+    order_id: OrderId,
+    check: FundingRateCheck,
+    executor: &E,
+) where
+    E: ExecuteOnCfd,
+{
+    if let Err(e) = executor
+        .execute(order_id, |cfd| Ok(cfd.reject_rollover_funding_rate(check)))
+        .await
+    {
+        tracing::error!(%order_id, "Failed to execute rollover rejected: {e:#}")
+    }
+}
+
 pub(crate) async fn emit_failed<E>(order_id: OrderId, e: anyhow::Error, executor: &E)
 where
     E: ExecuteOnCfd,
@@ -312,6 +343,12 @@ impl PunishParams {
     }
 }
 
+/// Build and sign the commit transaction, CETs and refund transaction of the rolled over DLC.
+///
+/// All CETs are signed anew, even if the payouts did not change since the last rollover. The
+/// adaptor signatures of the previous DLC cannot be reused: a rollover exchanges fresh revocation
+/// and publish keys, hence every CET spends a different commit transaction, and the CETs are
+/// encrypted to the announcements of a different set of oracle events.
 #[allow(clippy::too_many_arguments)]
 pub(crate) async fn build_own_cfd_transactions(
     dlc: &Dlc,
@@ -323,15 +360,14 @@ pub(crate) async fn build_own_cfd_transactions(
     complete_fee: model::CompleteFee,
     punish_params: PunishParams,
     role: Role,
-    contract_symbol: ContractSymbol,
 ) -> Result<CfdTransactions> {
     let sk = dlc.identity;
 
     let maker_lock_amount = dlc.maker_lock_amount;
     let taker_lock_amount = dlc.taker_lock_amount;
 
-    let payouts = match contract_symbol {
-        ContractSymbol::BtcUsd => Payouts::new_inverse_olivia_max(
+    let payouts = match rollover_params.payout_curve {
+        PayoutCurve::Inverse => Payouts::new_inverse_olivia_max(
             (our_position, role),
             rollover_params.price,
             rollover_params.quantity,
@@ -342,7 +378,7 @@ pub(crate) async fn build_own_cfd_transactions(
             n_payouts,
             complete_fee,
         )?,
-        ContractSymbol::EthUsd => Payouts::new_quanto(
+        PayoutCurve::Quanto { multiplier } => Payouts::new_quanto(
             (our_position, role),
             rollover_params.price.to_u64(),
             rollover_params.quantity.to_u64(),
@@ -351,7 +387,7 @@ pub(crate) async fn build_own_cfd_transactions(
                 rollover_params.short_leverage,
             ),
             n_payouts,
-            ETHUSD_MULTIPLIER,
+            multiplier,
             complete_fee,
         )?,
     };
@@ -586,3 +622,108 @@ impl Rates {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xtra_libp2p::wire_fixtures::Fixtures;
+
+    const FIXTURES: Fixtures = Fixtures::new("./src/wire_fixtures/3.0.0");
+    const SEQUENCED_FIXTURES: Fixtures = Fixtures::sequenced("./src/wire_fixtures/4.0.0");
+
+    #[test]
+    fn propose_matches_recording() {
+        let propose = FIXTURES
+            .round_trip::<DialerMessage>("propose.json")
+            .into_propose()
+            .unwrap();
+
+        assert!(propose.quanto_multiplier.is_some());
+        assert_eq!(propose.msg_timeout_secs, Some(60));
+    }
+
+    #[test]
+    fn sequenced_propose_matches_recording() {
+        let propose = SEQUENCED_FIXTURES
+            .round_trip::<DialerMessage>("propose.json")
+            .into_propose()
+            .unwrap();
+
+        assert_eq!(propose.msg_timeout_secs, Some(60));
+    }
+
+    #[test]
+    fn legacy_propose_defaults_new_fields() {
+        let propose = FIXTURES
+            .decode::<DialerMessage>("propose_legacy.json")
+            .into_propose()
+            .unwrap();
+
+        assert!(propose.quanto_multiplier.is_none());
+        assert!(propose.msg_timeout_secs.is_none());
+    }
+
+    #[test]
+    fn decisions_match_recording() {
+        let confirm = FIXTURES
+            .round_trip::<ListenerMessage>("confirm.json")
+            .into_decision()
+            .unwrap();
+        let reject = FIXTURES
+            .round_trip::<ListenerMessage>("reject.json")
+            .into_decision()
+            .unwrap();
+
+        assert!(matches!(
+            confirm,
+            Decision::Confirm(Confirm {
+                complete_fee: CompleteFee::LongPaysShort(_),
+                msg_timeout_secs: Some(60),
+                ..
+            })
+        ));
+        assert!(matches!(reject, Decision::Reject(_)));
+    }
+
+    #[test]
+    fn legacy_confirm_defaults_new_fields() {
+        let confirm = FIXTURES
+            .decode::<ListenerMessage>("confirm_legacy.json")
+            .into_decision()
+            .unwrap();
+
+        assert!(matches!(
+            confirm,
+            Decision::Confirm(Confirm {
+                complete_fee: CompleteFee::Nein,
+                quanto_multiplier: None,
+                msg_timeout_secs: None,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn rollover_messages_match_recording() {
+        FIXTURES
+            .round_trip::<DialerMessage>("msg0.json")
+            .into_rollover_msg()
+            .unwrap()
+            .try_into_msg0()
+            .unwrap();
+        let msg1 = FIXTURES
+            .round_trip::<ListenerMessage>("msg1.json")
+            .into_rollover_msg()
+            .unwrap()
+            .try_into_msg1()
+            .unwrap();
+        FIXTURES
+            .round_trip::<DialerMessage>("msg2.json")
+            .into_rollover_msg()
+            .unwrap()
+            .try_into_msg2()
+            .unwrap();
+
+        assert_eq!(msg1.cets.values().map(Vec::len).sum::<usize>(), 1);
+    }
+}
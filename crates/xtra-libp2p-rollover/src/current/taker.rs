@@ -1,5 +1,6 @@
 use crate::current;
 use crate::current::protocol::*;
+use crate::current::MAX_FRAME_SIZE;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
@@ -10,17 +11,24 @@ use futures::StreamExt;
 use maia_core::secp256k1_zkp::XOnlyPublicKey;
 use model::libp2p::PeerId;
 use model::olivia::BitMexPriceEventId;
+use model::protocol_timeout::TimeoutPolicy;
+use model::ContractSymbol;
 use model::Dlc;
 use model::ExecuteOnCfd;
+use model::FundingRate;
+use model::FundingRateCheck;
 use model::OrderId;
+use model::Position;
 use model::Role;
 use model::Timestamp;
+use rust_decimal::Decimal;
 use std::time::Duration;
 use tokio_extras::FutureExt;
+use xtra::prelude::MessageChannel;
 use xtra::Address;
+use xtra_libp2p::sequenced::Session;
+use xtra_libp2p::sequenced::SessionId;
 use xtra_libp2p::Endpoint;
-use xtra_libp2p::OpenSubstream;
-use xtra_libp2p::Substream;
 use xtra_productivity::xtra_productivity;
 
 /// The duration that the taker waits until a decision (accept/reject) is expected from the maker
@@ -36,6 +44,8 @@ pub struct Actor<E, O> {
     oracle: O,
     n_payouts: usize,
     executor: E,
+    failure_reports: Option<MessageChannel<RolloverFailed, ()>>,
+    funding_rate_band: Option<FundingRateBand>,
 }
 
 #[async_trait]
@@ -57,6 +67,55 @@ pub struct ProposeRollover {
     pub from_settlement_event_id: BitMexPriceEventId,
 }
 
+/// Ask for the funding rate of the latest offer the maker published for the given contract symbol
+/// and position of the maker, if any.
+#[derive(Clone, Copy)]
+pub struct GetPublishedFundingRate {
+    pub maker_peer_id: PeerId,
+    pub contract_symbol: ContractSymbol,
+    pub position_maker: Position,
+}
+
+/// Rejects rollovers whose proposed funding rate deviates by more than `tolerance` from the
+/// funding rate the maker published.
+#[derive(Clone)]
+struct FundingRateBand {
+    published_funding_rates: MessageChannel<GetPublishedFundingRate, Option<FundingRate>>,
+    tolerance: Decimal,
+}
+
+impl FundingRateBand {
+    /// Compare the proposed funding rate with the published one.
+    ///
+    /// Returns `None` if the maker did not publish a funding rate to compare with.
+    async fn check(
+        &self,
+        maker_peer_id: PeerId,
+        contract_symbol: ContractSymbol,
+        position_maker: Position,
+        proposed: FundingRate,
+    ) -> Result<Option<FundingRateCheck>> {
+        let published = self
+            .published_funding_rates
+            .send(GetPublishedFundingRate {
+                maker_peer_id,
+                contract_symbol,
+                position_maker,
+            })
+            .await
+            .context("Failed to get published funding rate")?;
+
+        Ok(published.map(|published| FundingRateCheck::new(proposed, published, self.tolerance)))
+    }
+}
+
+/// Emitted to the failure reporter if a rollover failed
+pub struct RolloverFailed {
+    pub order_id: OrderId,
+    pub maker_peer_id: PeerId,
+    pub error: anyhow::Error,
+}
+
 impl<E, O> Actor<E, O> {
     pub fn new(
         endpoint: Address<Endpoint>,
@@ -71,26 +130,81 @@ impl<E, O> Actor<E, O> {
             oracle: get_announcement,
             oracle_pk,
             n_payouts,
+            failure_reports: None,
+            funding_rate_band: None,
+        }
+    }
+
+    /// Report failed rollovers to the given channel.
+    pub fn with_failure_reports(self, failure_reports: MessageChannel<RolloverFailed, ()>) -> Self {
+        Self {
+            failure_reports: Some(failure_reports),
+            ..self
+        }
+    }
+
+    /// Reject rollovers if the proposed funding rate deviates by more than `tolerance` from the
+    /// funding rate of the maker's latest offer.
+    pub fn with_funding_rate_band(
+        self,
+        published_funding_rates: MessageChannel<GetPublishedFundingRate, Option<FundingRate>>,
+        tolerance: Decimal,
+    ) -> Self {
+        Self {
+            funding_rate_band: Some(FundingRateBand {
+                published_funding_rates,
+                tolerance,
+            }),
+            ..self
         }
     }
 }
 
 impl<E, O> Actor<E, O> {
-    async fn open_substream(&self, peer_id: PeerId) -> Result<Substream> {
-        let substream = self
-            .endpoint
-            .send(OpenSubstream::single_protocol(
-                peer_id.inner(),
-                current::PROTOCOL,
-            ))
-            .await
-            .context("Endpoint is disconnected")?
-            .context("No connection to peer")?
-            .await
-            .context("Failed to open substream")?;
+    /// Open a session for the rollover of `order_id`, on the protocol version with sequence
+    /// numbers if the maker supports it.
+    async fn open_substream(
+        &self,
+        peer_id: PeerId,
+        order_id: OrderId,
+    ) -> Result<Session<DialerMessage, ListenerMessage>> {
+        Session::dial(
+            &self.endpoint,
+            peer_id.inner(),
+            (current::PROTOCOL, current::UNSEQUENCED_PROTOCOL),
+            SessionId::new(order_id),
+            MAX_FRAME_SIZE,
+        )
+        .await
+    }
+}
 
-        Ok(substream)
+/// Forward the error to the failure reporter, if any, and return an error fit for the CFD's event.
+async fn report_failure(
+    failure_reports: Option<&MessageChannel<RolloverFailed, ()>>,
+    order_id: OrderId,
+    maker_peer_id: PeerId,
+    error: anyhow::Error,
+) -> anyhow::Error {
+    let failure_reports = match failure_reports {
+        Some(failure_reports) => failure_reports,
+        None => return error,
+    };
+
+    let message = anyhow::anyhow!("{error:#}");
+
+    if let Err(e) = failure_reports
+        .send(RolloverFailed {
+            order_id,
+            maker_peer_id,
+            error,
+        })
+        .await
+    {
+        tracing::debug!(%order_id, "Failed to report rollover failure: {e:#}");
     }
+
+    message
 }
 
 #[xtra_productivity]
@@ -107,13 +221,15 @@ where
             from_settlement_event_id,
         } = msg;
 
-        let substream = match self
-            .open_substream(maker_peer_id)
+        let mut framed = match self
+            .open_substream(maker_peer_id, order_id)
             .await
             .context("Failed to start rollover")
         {
-            Ok(substream) => substream,
+            Ok(framed) => framed,
             Err(e) => {
+                let e =
+                    report_failure(self.failure_reports.as_ref(), order_id, maker_peer_id, e).await;
                 emit_failed(order_id, e, &self.executor).await;
                 return;
             }
@@ -126,18 +242,16 @@ where
                 let oracle = self.oracle.clone();
                 let oracle_pk = self.oracle_pk;
                 let n_payouts = self.n_payouts;
+                let funding_rate_band = self.funding_rate_band.clone();
                 async move {
-                    let mut framed = asynchronous_codec::Framed::new(
-                        substream,
-                        asynchronous_codec::JsonCodec::<DialerMessage, ListenerMessage>::new(),
-                    );
-
-                    let contract_symbol = executor
+                    let (contract_symbol, position_maker, quanto_multiplier) = executor
                         .execute(order_id, |cfd| {
                             let event = cfd.start_rollover_taker()?;
                             let contract_symbol = cfd.contract_symbol();
+                            let position_maker = cfd.position().counter_position();
+                            let quanto_multiplier = cfd.quanto_multiplier();
 
-                            Ok((event, contract_symbol))
+                            Ok((event, (contract_symbol, position_maker, quanto_multiplier)))
                         })
                         .await?;
 
@@ -146,6 +260,8 @@ where
                             order_id,
                             timestamp: Timestamp::now(),
                             from_commit_txid,
+                            quanto_multiplier,
+                            msg_timeout_secs: Some(TimeoutPolicy::ROLLOVER.proposal_secs()),
                         }))
                         .await
                         .context("Failed to send Msg0")?;
@@ -172,9 +288,38 @@ where
                             tx_fee_rate,
                             funding_rate,
                             complete_fee,
+                            quanto_multiplier,
+                            msg_timeout_secs,
                         }) => {
+                            let msg_timeout = TimeoutPolicy::ROLLOVER
+                                .verify(msg_timeout_secs)
+                                .context("Maker negotiated an invalid rollover timeout")?;
+
+                            if let Some(funding_rate_band) = funding_rate_band {
+                                match funding_rate_band
+                                    .check(
+                                        maker_peer_id,
+                                        contract_symbol,
+                                        position_maker,
+                                        funding_rate,
+                                    )
+                                    .await?
+                                {
+                                    Some(check) if !check.is_within_band() => {
+                                        emit_funding_rate_rejected(order_id, check, &executor)
+                                            .await;
+                                        return Ok(());
+                                    }
+                                    Some(_) => {}
+                                    None => {
+                                        tracing::debug!(%order_id, %funding_rate, "No published funding rate to compare the proposed funding rate with");
+                                    }
+                                }
+                            }
+
                             let (rollover_params, dlc, position) = executor
                                 .execute(order_id, |cfd| {
+                                    cfd.verify_quanto_multiplier(quanto_multiplier)?;
                                     cfd.handle_rollover_accepted_taker(
                                         tx_fee_rate,
                                         funding_rate,
@@ -218,12 +363,12 @@ where
 
                             let msg0 = framed
                                 .next()
-                                .timeout(ROLLOVER_MSG_TIMEOUT, next_rollover_span)
+                                .timeout(msg_timeout, next_rollover_span)
                                 .await
                                 .with_context(|| {
                                     format!(
                                         "Expected Msg0 within {} seconds",
-                                        ROLLOVER_MSG_TIMEOUT.as_secs()
+                                        msg_timeout.as_secs()
                                     )
                                 })?
                                 .context("Empty stream instead of Msg0")?
@@ -248,7 +393,6 @@ where
                                 complete_fee.into(),
                                 punish_params,
                                 Role::Taker,
-                                contract_symbol,
                             )
                             .await?;
 
@@ -261,12 +405,12 @@ where
 
                             let msg1 = framed
                                 .next()
-                                .timeout(ROLLOVER_MSG_TIMEOUT, next_rollover_span)
+                                .timeout(msg_timeout, next_rollover_span)
                                 .await
                                 .with_context(|| {
                                     format!(
                                         "Expected Msg1 within {} seconds",
-                                        ROLLOVER_MSG_TIMEOUT.as_secs()
+                                        msg_timeout.as_secs()
                                     )
                                 })?
                                 .context("Empty stream instead of Msg1")?
@@ -302,12 +446,12 @@ where
 
                             let msg2 = framed
                                 .next()
-                                .timeout(ROLLOVER_MSG_TIMEOUT, next_rollover_span)
+                                .timeout(msg_timeout, next_rollover_span)
                                 .await
                                 .with_context(|| {
                                     format!(
                                         "Expected Msg2 within {} seconds",
-                                        ROLLOVER_MSG_TIMEOUT.as_secs()
+                                        msg_timeout.as_secs()
                                     )
                                 })?
                                 .context("Empty stream instead of Msg2")?
@@ -359,7 +503,10 @@ where
             },
             {
                 let executor = self.executor.clone();
+                let failure_reports = self.failure_reports.clone();
                 move |e| async move {
+                    let e =
+                        report_failure(failure_reports.as_ref(), order_id, maker_peer_id, e).await;
                     emit_failed(order_id, e, &executor).await;
                 }
             },
@@ -3,3 +3,6 @@ pub mod protocol;
 pub mod taker;
 
 pub const PROTOCOL: &str = "/itchysats/rollover/2.0.0";
+
+/// Maximum size of an encoded message in bytes
+pub const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
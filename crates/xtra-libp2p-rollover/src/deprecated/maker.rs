@@ -1,8 +1,8 @@
 use crate::deprecated::protocol::*;
+use crate::deprecated::MAX_FRAME_SIZE;
 use anyhow::Context;
 use async_trait::async_trait;
 use asynchronous_codec::Framed;
-use asynchronous_codec::JsonCodec;
 use bdk_ext::keypair;
 use futures::SinkExt;
 use futures::StreamExt;
@@ -13,6 +13,7 @@ use model::ExecuteOnCfd;
 use model::Position;
 use model::Role;
 use tokio_extras::FutureExt;
+use xtra_libp2p::limited::LimitedJsonCodec;
 use xtra_libp2p::NewInboundSubstream;
 use xtra_libp2p::Substream;
 use xtra_productivity::xtra_productivity;
@@ -88,8 +89,10 @@ where
         tokio_extras::spawn_fallible(
             &address.clone(),
             async move {
-                let mut framed =
-                    Framed::new(stream, JsonCodec::<ListenerMessage, DialerMessage>::new());
+                let mut framed = Framed::new(
+                    stream,
+                    LimitedJsonCodec::<ListenerMessage, DialerMessage>::new(MAX_FRAME_SIZE),
+                );
 
                 let propose = framed
                     .next()
@@ -277,7 +280,6 @@ where
                     complete_fee,
                     punish_params,
                     Role::Maker,
-                    contract_symbol,
                 )
                 .await?;
 
@@ -405,6 +407,6 @@ impl UpdateConfiguration {
 
 struct ProposeReceived {
     propose: Propose,
-    framed: Framed<Substream, JsonCodec<ListenerMessage, DialerMessage>>,
+    framed: Framed<Substream, LimitedJsonCodec<ListenerMessage, DialerMessage>>,
     peer_id: PeerId,
 }
@@ -20,10 +20,10 @@ use maia_core::CfdTransactions;
 use maia_core::PartyParams;
 use model::olivia;
 use model::olivia::BitMexPriceEventId;
-use model::payout_curve::ETHUSD_MULTIPLIER;
 use model::shared_protocol::verify_adaptor_signature;
 use model::shared_protocol::verify_cets;
 use model::shared_protocol::verify_signature;
+use model::symbols::PayoutCurve;
 use model::Cet;
 use model::ContractSymbol;
 use model::Dlc;
@@ -323,15 +323,14 @@ pub(crate) async fn build_own_cfd_transactions(
     complete_fee: model::CompleteFee,
     punish_params: PunishParams,
     role: Role,
-    contract_symbol: ContractSymbol,
 ) -> Result<CfdTransactions> {
     let sk = dlc.identity;
 
     let maker_lock_amount = dlc.maker_lock_amount;
     let taker_lock_amount = dlc.taker_lock_amount;
 
-    let payouts = match contract_symbol {
-        ContractSymbol::BtcUsd => Payouts::new_inverse_double_initial(
+    let payouts = match rollover_params.payout_curve {
+        PayoutCurve::Inverse => Payouts::new_inverse_double_initial(
             (our_position, role),
             rollover_params.price,
             rollover_params.quantity,
@@ -342,7 +341,7 @@ pub(crate) async fn build_own_cfd_transactions(
             n_payouts,
             complete_fee,
         )?,
-        ContractSymbol::EthUsd => Payouts::new_quanto(
+        PayoutCurve::Quanto { multiplier } => Payouts::new_quanto(
             (our_position, role),
             rollover_params.price.to_u64(),
             rollover_params.quantity.to_u64(),
@@ -351,7 +350,7 @@ pub(crate) async fn build_own_cfd_transactions(
                 rollover_params.short_leverage,
             ),
             n_payouts,
-            ETHUSD_MULTIPLIER,
+            multiplier,
             complete_fee,
         )?,
     };
@@ -586,3 +585,64 @@ impl Rates {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xtra_libp2p::wire_fixtures::Fixtures;
+
+    const FIXTURES: Fixtures = Fixtures::new("./src/wire_fixtures/2.0.0");
+
+    #[test]
+    fn propose_matches_recording() {
+        FIXTURES
+            .round_trip::<DialerMessage>("propose.json")
+            .into_propose()
+            .unwrap();
+    }
+
+    #[test]
+    fn decisions_match_recording() {
+        let confirm = FIXTURES
+            .round_trip::<ListenerMessage>("confirm.json")
+            .into_decision()
+            .unwrap();
+        let reject = FIXTURES
+            .round_trip::<ListenerMessage>("reject.json")
+            .into_decision()
+            .unwrap();
+
+        assert!(matches!(
+            confirm,
+            Decision::Confirm(Confirm {
+                complete_fee: CompleteFee::LongPaysShort(_),
+                ..
+            })
+        ));
+        assert!(matches!(reject, Decision::Reject(_)));
+    }
+
+    #[test]
+    fn rollover_messages_match_recording() {
+        FIXTURES
+            .round_trip::<DialerMessage>("msg0.json")
+            .into_rollover_msg()
+            .unwrap()
+            .try_into_msg0()
+            .unwrap();
+        let msg1 = FIXTURES
+            .round_trip::<ListenerMessage>("msg1.json")
+            .into_rollover_msg()
+            .unwrap()
+            .try_into_msg1()
+            .unwrap();
+        FIXTURES
+            .round_trip::<DialerMessage>("msg2.json")
+            .into_rollover_msg()
+            .unwrap()
+            .try_into_msg2()
+            .unwrap();
+
+        assert_eq!(msg1.cets.values().map(Vec::len).sum::<usize>(), 1);
+    }
+}
@@ -18,6 +18,7 @@ use model::Timestamp;
 use std::time::Duration;
 use tokio_extras::FutureExt;
 use xtra::Address;
+use xtra_libp2p::limited::LimitedJsonCodec;
 use xtra_libp2p::Endpoint;
 use xtra_libp2p::OpenSubstream;
 use xtra_libp2p::Substream;
@@ -129,16 +130,13 @@ where
                 async move {
                     let mut framed = asynchronous_codec::Framed::new(
                         substream,
-                        asynchronous_codec::JsonCodec::<DialerMessage, ListenerMessage>::new(),
+                        LimitedJsonCodec::<DialerMessage, ListenerMessage>::new(
+                            deprecated::MAX_FRAME_SIZE,
+                        ),
                     );
 
-                    let contract_symbol = executor
-                        .execute(order_id, |cfd| {
-                            let event = cfd.start_rollover_taker()?;
-                            let contract_symbol = cfd.contract_symbol();
-
-                            Ok((event, contract_symbol))
-                        })
+                    executor
+                        .execute(order_id, |cfd| cfd.start_rollover_taker())
                         .await?;
 
                     framed
@@ -248,7 +246,6 @@ where
                                 complete_fee.into(),
                                 punish_params,
                                 Role::Taker,
-                                contract_symbol,
                             )
                             .await?;
 
@@ -0,0 +1,177 @@
+//! Fault injection for testing the resilience of protocols.
+//!
+//! Only active if the `chaos` feature is enabled, otherwise all hooks are no-ops. Faults are
+//! configured through the following environment variables or programmatically through
+//! [`set_faults`]:
+//!
+//! - `CHAOS_MAX_DELAY_MS`: upper bound of a random delay before a substream is opened
+//! - `CHAOS_DROP_PROBABILITY`: probability with which opening a substream fails
+//! - `CHAOS_DUPLICATE_PROBABILITY`: probability with which a message of a session with sequence
+//!   numbers is sent twice, see [`crate::sequenced`]
+//! - `CHAOS_PROTOCOLS`: comma-separated protocol prefixes faults are restricted to, applies to all
+//!   protocols if not set
+
+use futures::Sink;
+use std::pin::Pin;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Faults {
+    /// Upper bound of the random delay before a substream is opened
+    pub max_delay: Duration,
+    /// Probability with which opening a substream fails
+    pub drop_probability: f64,
+    /// Probability with which a message of a session with sequence numbers is sent twice
+    pub duplicate_probability: f64,
+    /// Protocol prefixes the faults are restricted to, empty for all protocols
+    pub protocols: Vec<String>,
+}
+
+impl Faults {
+    #[cfg_attr(not(feature = "chaos"), allow(dead_code))]
+    fn applies_to(&self, protocol: &str) -> bool {
+        self.protocols.is_empty()
+            || self
+                .protocols
+                .iter()
+                .any(|prefix| protocol.starts_with(prefix.as_str()))
+    }
+}
+
+/// Replace the faults that are injected from now on.
+///
+/// Has no effect unless the `chaos` feature is enabled.
+pub fn set_faults(faults: Faults) {
+    imp::set_faults(faults)
+}
+
+/// Injects the configured delay and failure before a substream for `protocol` is opened.
+pub(crate) async fn before_open_substream(protocol: &str) -> Result<(), crate::Error> {
+    imp::before_open_substream(protocol).await
+}
+
+/// Starts sending `frame` into `sink`, twice if duplicated messages are injected for `protocol`.
+///
+/// Every frame a [`Session`](crate::sequenced::Session) with sequence numbers sends passes through
+/// here after it was numbered. The duplicate therefore carries the same sequence number, just like
+/// a frame that is replayed after the session was resumed, and the receiver has to drop it.
+pub(crate) fn send<S, F>(mut sink: Pin<&mut S>, protocol: &str, frame: F) -> Result<(), S::Error>
+where
+    S: Sink<F>,
+    F: Clone,
+{
+    if imp::duplicate_message(protocol) {
+        tracing::warn!(%protocol, "Injecting duplicated message");
+        sink.as_mut().start_send(frame.clone())?;
+    }
+
+    sink.start_send(frame)
+}
+
+#[cfg(feature = "chaos")]
+mod imp {
+    use super::Faults;
+    use conquer_once::Lazy;
+    use rand::Rng;
+    use std::sync::RwLock;
+    use std::time::Duration;
+
+    static FAULTS: Lazy<RwLock<Faults>> = Lazy::new(|| RwLock::new(from_env()));
+
+    pub(super) fn set_faults(faults: Faults) {
+        tracing::warn!(?faults, "Configured fault injection");
+
+        *FAULTS.write().expect("lock not to be poisoned") = faults;
+    }
+
+    pub(super) async fn before_open_substream(protocol: &str) -> Result<(), crate::Error> {
+        let faults = FAULTS.read().expect("lock not to be poisoned").clone();
+
+        if !faults.applies_to(protocol) {
+            return Ok(());
+        }
+
+        if !faults.max_delay.is_zero() {
+            let delay = rand::thread_rng().gen_range(Duration::ZERO..=faults.max_delay);
+            tracing::warn!(%protocol, ?delay, "Injecting delay before opening substream");
+
+            tokio_extras::time::sleep(delay).await;
+        }
+
+        if rand::thread_rng().gen_bool(faults.drop_probability.clamp(0.0, 1.0)) {
+            tracing::warn!(%protocol, "Injecting dropped substream");
+
+            return Err(crate::Error::InjectedFault);
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn duplicate_message(protocol: &str) -> bool {
+        let faults = FAULTS.read().expect("lock not to be poisoned");
+
+        faults.applies_to(protocol)
+            && rand::thread_rng().gen_bool(faults.duplicate_probability.clamp(0.0, 1.0))
+    }
+
+    fn from_env() -> Faults {
+        fn var<T: std::str::FromStr>(key: &str) -> Option<T> {
+            let value = std::env::var(key).ok()?;
+
+            match value.parse() {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    tracing::warn!(%key, %value, "Ignoring invalid fault injection parameter");
+                    None
+                }
+            }
+        }
+
+        Faults {
+            max_delay: Duration::from_millis(var("CHAOS_MAX_DELAY_MS").unwrap_or_default()),
+            drop_probability: var("CHAOS_DROP_PROBABILITY").unwrap_or_default(),
+            duplicate_probability: var("CHAOS_DUPLICATE_PROBABILITY").unwrap_or_default(),
+            protocols: std::env::var("CHAOS_PROTOCOLS")
+                .map(|protocols| protocols.split(',').map(str::to_owned).collect())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(not(feature = "chaos"))]
+mod imp {
+    use super::Faults;
+
+    pub(super) fn set_faults(_: Faults) {}
+
+    pub(super) async fn before_open_substream(_: &str) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    pub(super) fn duplicate_message(_: &str) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faults_without_protocols_apply_to_all_protocols() {
+        let faults = Faults::default();
+
+        assert!(faults.applies_to("/itchysats/rollover/3.0.0"));
+    }
+
+    #[test]
+    fn faults_with_protocols_only_apply_to_matching_protocols() {
+        let faults = Faults {
+            protocols: vec!["/itchysats/rollover".to_owned()],
+            ..Faults::default()
+        };
+
+        assert!(faults.applies_to("/itchysats/rollover/3.0.0"));
+        assert!(!faults.applies_to("/itchysats/order/2.0.0"));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::chaos;
 use crate::multiaddress_ext::MultiaddrExt as _;
 use crate::upgrade;
 use crate::Connection;
@@ -178,6 +179,9 @@ pub enum Error {
     AlreadyTryingToConnected(PeerId),
     #[error("Peer does not listen for given protocol(s)")]
     ProtocolNotSupportedByPeer,
+    #[cfg(feature = "chaos")]
+    #[error("Substream dropped by fault injection")]
+    InjectedFault,
 }
 
 /// Subscribers that get notified on connection changes
@@ -316,6 +320,10 @@ impl Endpoint {
         protocols: Vec<&'static str>,
         connection_timeout: Duration,
     ) -> Result<(&'static str, Substream), Error> {
+        if let Some(protocol) = protocols.first() {
+            chaos::before_open_substream(protocol).await?;
+        }
+
         let stream = control
             .open_stream()
             .instrument(tracing::debug_span!("open yamux stream"))
@@ -345,6 +353,7 @@ impl Endpoint {
 
         let NewConnection {
             peer_id,
+            address,
             control,
             mut incoming_substreams,
             worker,
@@ -402,7 +411,7 @@ impl Endpoint {
             TOTAL_PEERS.inc(); // Only increment if peer is new
         }
 
-        self.notify_connection_established(peer_id).await;
+        self.notify_connection_established(peer_id, address).await;
     }
 
     async fn handle(&mut self, msg: ListenerFailed) {
@@ -457,6 +466,7 @@ impl Endpoint {
             {
                 let this = this.clone();
                 let connection_timeout = self.connection_timeout;
+                let address = msg.0.clone();
 
                 let fut = async move {
                     let (peer_id, control, incoming_substreams, worker) =
@@ -470,6 +480,7 @@ impl Endpoint {
 
                     this.send_async_next(NewConnection {
                         peer_id,
+                        address,
                         control,
                         incoming_substreams,
                         worker,
@@ -553,6 +564,7 @@ impl Endpoint {
 
                                         this.send_async_next(NewConnection {
                                             peer_id,
+                                            address: remote_addr,
                                             control,
                                             incoming_substreams,
                                             worker,
@@ -679,12 +691,15 @@ impl Endpoint {
 }
 
 impl Endpoint {
-    async fn notify_connection_established(&mut self, peer_id: PeerId) {
-        tracing::info!(%peer_id, "Connection established");
+    async fn notify_connection_established(&mut self, peer_id: PeerId, address: Multiaddr) {
+        tracing::info!(%peer_id, %address, "Connection established");
 
         for subscriber in &self.subscribers.connection_established {
             subscriber
-                .send_async_next(ConnectionEstablished { peer_id })
+                .send_async_next(ConnectionEstablished {
+                    peer_id,
+                    address: address.clone(),
+                })
                 .await;
         }
     }
@@ -772,6 +787,8 @@ struct NewListenAddress {
 
 struct NewConnection {
     peer_id: PeerId,
+    /// The address we dialed or, for inbound connections, the address of the remote
+    address: Multiaddr,
     control: yamux::Control,
     #[allow(clippy::type_complexity)]
     incoming_substreams: BoxStream<
@@ -784,9 +801,11 @@ struct NewConnection {
     worker: BoxFuture<'static, ()>,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct ConnectionEstablished {
     pub peer_id: PeerId,
+    /// The address we dialed or, for inbound connections, the address of the remote
+    pub address: Multiaddr,
 }
 
 #[derive(Clone, Copy)]
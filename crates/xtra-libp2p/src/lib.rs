@@ -18,13 +18,19 @@ use futures::stream::BoxStream;
 use libp2p_core::Negotiated;
 use libp2p_core::PeerId;
 
+pub mod chaos;
 pub mod dialer;
 pub mod endpoint;
+pub mod limited;
 pub mod listener;
 pub mod multiaddress_ext;
+pub mod sequenced;
 mod substream;
+pub mod transcript;
 mod upgrade;
 mod verify_peer_id;
+#[cfg(feature = "wire-fixtures")]
+pub mod wire_fixtures;
 
 type Connection = (
     PeerId,
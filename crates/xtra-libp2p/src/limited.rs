@@ -0,0 +1,200 @@
+//! Size limits for JSON encoded protocol messages.
+//!
+//! [`JsonCodec`] decodes a message once the buffer holds a complete JSON value and keeps buffering
+//! otherwise. A peer that never completes a value, e.g. by sending an endless array, makes us
+//! buffer without bound. [`LimitedJsonCodec`] fails as soon as a frame exceeds the maximum size of
+//! the protocol. The error terminates the substream the frame was received on; other substreams
+//! and the connection to the peer are not affected.
+
+use asynchronous_codec::BytesMut;
+use asynchronous_codec::Decoder;
+use asynchronous_codec::Encoder;
+use asynchronous_codec::JsonCodec;
+use asynchronous_codec::JsonCodecError;
+use serde::Deserialize;
+use serde::Serialize;
+use std::io;
+
+/// A frame exceeded the maximum frame size of the protocol.
+///
+/// Surfaced as the source of a [`JsonCodecError::Io`] so that codecs can be swapped without
+/// changing the error type of the protocols.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Frame of at least {size} bytes exceeds the maximum of {max} bytes")]
+pub struct FrameTooLarge {
+    pub size: usize,
+    pub max: usize,
+}
+
+impl FrameTooLarge {
+    /// Extract the [`FrameTooLarge`] error from a codec error, if any.
+    pub fn from_codec_error(error: &JsonCodecError) -> Option<&Self> {
+        match error {
+            JsonCodecError::Io(e) => e.get_ref()?.downcast_ref(),
+            JsonCodecError::Json(_) => None,
+        }
+    }
+}
+
+impl From<FrameTooLarge> for JsonCodecError {
+    fn from(e: FrameTooLarge) -> Self {
+        JsonCodecError::Io(io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// A [`JsonCodec`] that rejects frames larger than `max_frame_size` bytes, both when encoding and
+/// when decoding.
+pub struct LimitedJsonCodec<Enc, Dec> {
+    inner: JsonCodec<Enc, Dec>,
+    max_frame_size: usize,
+}
+
+impl<Enc, Dec> LimitedJsonCodec<Enc, Dec> {
+    pub fn new(max_frame_size: usize) -> Self {
+        Self {
+            inner: JsonCodec::new(),
+            max_frame_size,
+        }
+    }
+}
+
+impl<Enc, Dec> Encoder for LimitedJsonCodec<Enc, Dec>
+where
+    Enc: Serialize + 'static,
+    Dec: for<'de> Deserialize<'de> + 'static,
+{
+    type Item = Enc;
+    type Error = JsonCodecError;
+
+    fn encode(&mut self, item: Self::Item, buf: &mut BytesMut) -> Result<(), Self::Error> {
+        let mut frame = BytesMut::new();
+        self.inner.encode(item, &mut frame)?;
+
+        if frame.len() > self.max_frame_size {
+            return Err(FrameTooLarge {
+                size: frame.len(),
+                max: self.max_frame_size,
+            }
+            .into());
+        }
+
+        buf.extend_from_slice(&frame);
+
+        Ok(())
+    }
+}
+
+impl<Enc, Dec> Decoder for LimitedJsonCodec<Enc, Dec>
+where
+    Enc: Serialize + 'static,
+    Dec: for<'de> Deserialize<'de> + 'static,
+{
+    type Item = Dec;
+    type Error = JsonCodecError;
+
+    fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let available = buf.len();
+        let item = self.inner.decode(buf)?;
+        let consumed = available - buf.len();
+
+        match item {
+            Some(item) if consumed <= self.max_frame_size => Ok(Some(item)),
+            Some(_) => Err(FrameTooLarge {
+                size: consumed,
+                max: self.max_frame_size,
+            }
+            .into()),
+            // The incomplete frame is already too large, no need to wait for the rest of it
+            None if available > self.max_frame_size => Err(FrameTooLarge {
+                size: available,
+                max: self.max_frame_size,
+            }
+            .into()),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_FRAME_SIZE: usize = 64;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Msg {
+        data: Vec<u8>,
+    }
+
+    #[test]
+    fn given_frames_within_limit_then_all_decoded() {
+        let mut codec = LimitedJsonCodec::<Msg, Msg>::new(MAX_FRAME_SIZE);
+
+        let mut wire = BytesMut::new();
+        codec.encode(Msg { data: vec![1; 10] }, &mut wire).unwrap();
+        codec.encode(Msg { data: vec![2; 10] }, &mut wire).unwrap();
+
+        assert_eq!(
+            codec.decode(&mut wire).unwrap(),
+            Some(Msg { data: vec![1; 10] })
+        );
+        assert_eq!(
+            codec.decode(&mut wire).unwrap(),
+            Some(Msg { data: vec![2; 10] })
+        );
+        assert_eq!(codec.decode(&mut wire).unwrap(), None);
+    }
+
+    #[test]
+    fn given_complete_frame_above_limit_then_rejected() {
+        let mut codec = LimitedJsonCodec::<Msg, Msg>::new(MAX_FRAME_SIZE);
+
+        let mut wire = BytesMut::from(
+            serde_json::to_vec(&Msg { data: vec![1; 64] })
+                .unwrap()
+                .as_slice(),
+        );
+
+        let error = codec.decode(&mut wire).unwrap_err();
+        assert!(matches!(
+            FrameTooLarge::from_codec_error(&error),
+            Some(FrameTooLarge {
+                max: MAX_FRAME_SIZE,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn given_endless_frame_then_rejected_before_it_completes() {
+        let mut codec = LimitedJsonCodec::<Msg, Msg>::new(MAX_FRAME_SIZE);
+
+        let mut wire = BytesMut::from(&b"{\"data\":["[..]);
+        while wire.len() <= MAX_FRAME_SIZE {
+            assert_eq!(codec.decode(&mut wire).unwrap(), None);
+            wire.extend_from_slice(b"1,");
+        }
+
+        let error = codec.decode(&mut wire).unwrap_err();
+        assert_eq!(
+            FrameTooLarge::from_codec_error(&error),
+            Some(&FrameTooLarge {
+                size: wire.len(),
+                max: MAX_FRAME_SIZE
+            })
+        );
+    }
+
+    #[test]
+    fn given_message_above_limit_then_not_encoded() {
+        let mut codec = LimitedJsonCodec::<Msg, Msg>::new(MAX_FRAME_SIZE);
+
+        let mut wire = BytesMut::new();
+        let error = codec
+            .encode(Msg { data: vec![1; 64] }, &mut wire)
+            .unwrap_err();
+
+        assert!(FrameTooLarge::from_codec_error(&error).is_some());
+        assert!(wire.is_empty());
+    }
+}
@@ -0,0 +1,776 @@
+//! Exactly-once delivery of protocol messages within a session that can outlive its substream.
+//!
+//! A substream can break in the middle of a protocol, e.g. because the connection to the peer
+//! dropped. Protocols expect every message exactly once, so simply sending a message again on a
+//! new substream either fails the protocol or, worse, applies the message twice. A [`Session`]
+//! numbers the messages it sends and drops received messages whose sequence number was already
+//! seen.
+//!
+//! If the substream of a session breaks, the dialer opens a new substream for the same protocol
+//! and replays all messages of the session on it. The listener hands the new substream over to
+//! the running session through [`Sessions`], replays its own messages and both sides continue
+//! where they left off. The dialer starts a new [`SessionId`] for every run of a protocol and the
+//! listener learns the session id from the dialer's messages.
+//!
+//! The dialer sends sequenced messages if the negotiated protocol version knows about them. The
+//! listener detects from the first message whether the dialer speaks such a version, so that it
+//! can serve the versions of a protocol with and without sequence numbers. Sessions without
+//! sequence numbers cannot be resumed.
+//!
+//! Frames are limited to the maximum frame size of the protocol, see [`crate::limited`]. Messages
+//! can be captured for debugging, see [`crate::transcript`].
+
+use crate::chaos;
+use crate::limited::FrameTooLarge;
+use crate::limited::LimitedJsonCodec;
+use crate::transcript::Capture;
+use crate::transcript::Direction;
+use crate::Endpoint;
+use crate::OpenSubstream;
+use crate::Substream;
+use anyhow::Context as _;
+use asynchronous_codec::Framed;
+use asynchronous_codec::JsonCodecError;
+use futures::channel::mpsc;
+use futures::future::BoxFuture;
+use futures::ready;
+use futures::FutureExt;
+use futures::Sink;
+use futures::SinkExt;
+use futures::Stream;
+use futures::StreamExt;
+use libp2p_core::PeerId;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
+
+/// How often a session is resumed at most.
+///
+/// Bounds the substreams a dialer opens for a session that the listener ended without telling.
+const MAX_RESUMPTIONS: u32 = 3;
+
+/// How often the dialer tries to open a new substream for a session whose substream broke.
+const RESUME_ATTEMPTS: u32 = 3;
+
+/// Delay before the first attempt to resume a session, grows linearly with every attempt.
+const RESUME_BACKOFF: Duration = Duration::from_secs(1);
+
+/// How long the listener waits for the dialer to resume a session whose substream broke.
+///
+/// Covers all attempts of the dialer, including the time it takes to open a substream.
+const RESUME_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Sessions that ended are remembered for this long.
+///
+/// A dialer that resumes a session which the listener already ended replays the first message
+/// of the session, which must not be mistaken for a new session.
+const ENDED_SESSION_RETENTION: Duration = Duration::from_secs(60 * 60);
+
+/// Resumed substreams that were not yet taken over by their session.
+const PENDING_HANDOVERS: usize = 4;
+
+type Transport = Framed<Substream, LimitedJsonCodec<Value, Value>>;
+
+/// A substream on which the dialer resumed a session, with the first frame read from it.
+type Handover = (Transport, Value);
+
+/// Id of one run of a protocol, e.g. one rollover of a CFD.
+///
+/// Sequence numbers are only kept in memory, a peer that restarted numbers its messages from 1
+/// again. Every run therefore needs an id of its own, otherwise the other peer would take the
+/// messages of a new run for a resumption of an earlier run. Only a session that is resumed on a
+/// new substream reuses the id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionId(String);
+
+impl SessionId {
+    /// A new session of the protocol run for `name`, e.g. the order id.
+    pub fn new(name: impl fmt::Display) -> Self {
+        Self(format!("{name}/{:016x}", rand::random::<u64>()))
+    }
+
+    #[cfg(feature = "wire-fixtures")]
+    pub(crate) fn recorded(id: &str) -> Self {
+        Self(id.to_owned())
+    }
+}
+
+impl fmt::Display for SessionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// The sessions that peers dialed on a protocol, keyed by peer and session id.
+///
+/// Routes substreams on which a dialer resumes a session to the running session. Cloning is
+/// cheap, all clones share the same sessions.
+#[derive(Clone, Default)]
+pub struct Sessions {
+    inner: Arc<Mutex<HashMap<(PeerId, String), Entry>>>,
+}
+
+enum Entry {
+    Running(mpsc::Sender<Handover>),
+    Ended(Instant),
+}
+
+impl Sessions {
+    /// Accept a substream that `peer` opened on `protocol`.
+    ///
+    /// Returns `None` if the substream resumes a session that is already running; the session
+    /// continues on the new substream. A substream that resumes a session which already ended is
+    /// dropped.
+    pub async fn accept<Enc, Dec>(
+        &self,
+        peer: PeerId,
+        stream: Substream,
+        protocol: &'static str,
+        max_frame_size: usize,
+    ) -> Result<Option<Session<Enc, Dec>>, JsonCodecError> {
+        let mut transport = Framed::new(stream, LimitedJsonCodec::new(max_frame_size));
+        let first = transport.next().await.ok_or_else(end_of_stream)??;
+
+        if !is_sequenced(&first) {
+            return Ok(Some(Session::new(transport, protocol).with_received(first)));
+        }
+
+        let id = match first
+            .get("session")
+            .and_then(Value::as_str)
+            .map(str::to_owned)
+        {
+            Some(id) => id,
+            // Sessions of dialers that do not send a session id are bound to their substream
+            None => {
+                return Ok(Some(
+                    Session::new(transport, protocol)
+                        .with_sequence(Sequence::new(None))
+                        .with_received(first),
+                ))
+            }
+        };
+
+        let handovers = {
+            let mut sessions = self.inner.lock().expect("lock not to be poisoned");
+            sessions.retain(|_, entry| match entry {
+                Entry::Running(_) => true,
+                Entry::Ended(at) => at.elapsed() < ENDED_SESSION_RETENTION,
+            });
+
+            match sessions.get_mut(&(peer, id.clone())) {
+                Some(Entry::Running(handovers)) => {
+                    if let Err(e) = handovers.try_send((transport, first)) {
+                        tracing::debug!(%peer, session = %id, "Dropping resumed substream: {e}");
+                    }
+
+                    return Ok(None);
+                }
+                Some(Entry::Ended(_)) => {
+                    tracing::debug!(%peer, session = %id, "Ignoring resumption of ended session");
+
+                    return Ok(None);
+                }
+                None => {
+                    let (sender, receiver) = mpsc::channel(PENDING_HANDOVERS);
+                    sessions.insert((peer, id.clone()), Entry::Running(sender));
+
+                    receiver
+                }
+            }
+        };
+
+        let mut session = Session::new(transport, protocol)
+            .with_sequence(Sequence::new(Some(id.clone())))
+            .with_received(first);
+        session.resume = Some(Resume::Listener {
+            handovers,
+            _registration: Registration {
+                sessions: self.clone(),
+                key: (peer, id),
+            },
+        });
+
+        Ok(Some(session))
+    }
+}
+
+/// Marks the session as ended once the listener drops it.
+struct Registration {
+    sessions: Sessions,
+    key: (PeerId, String),
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let mut sessions = self.sessions.inner.lock().expect("lock not to be poisoned");
+        sessions.insert(self.key.clone(), Entry::Ended(Instant::now()));
+    }
+}
+
+/// One run of a protocol with a peer, as a [`Sink`] of the messages we send and a [`Stream`] of
+/// the messages we receive.
+///
+/// Messages of sessions with sequence numbers are delivered exactly once, even if the session is
+/// resumed on a new substream.
+pub struct Session<Enc, Dec> {
+    state: State,
+    sequence: Option<Sequence>,
+    resume: Option<Resume>,
+    resumptions: u32,
+    protocol: &'static str,
+    /// Frames that were read from the substream but not yet processed
+    received: VecDeque<Value>,
+    transcript: Option<Capture>,
+    _marker: PhantomData<fn() -> (Enc, Dec)>,
+}
+
+enum State {
+    Connected(Box<Transport>),
+    /// The listener waits for the dialer to resume the session
+    AwaitingResumption(BoxFuture<'static, ()>),
+    /// The session is replayed on a new substream
+    Resuming(BoxFuture<'static, Result<Transport, JsonCodecError>>),
+    Closed,
+}
+
+enum Resume {
+    Dialer {
+        endpoint: xtra::Address<Endpoint>,
+        peer: PeerId,
+        protocol: &'static str,
+        max_frame_size: usize,
+    },
+    Listener {
+        handovers: mpsc::Receiver<Handover>,
+        _registration: Registration,
+    },
+}
+
+impl<Enc, Dec> Session<Enc, Dec> {
+    /// Open a session with `peer` on `protocol`, falling back to `unsequenced_protocol` if the
+    /// peer does not support it.
+    ///
+    /// Only sessions on `protocol` are sequenced and resumed if their substream breaks.
+    pub async fn dial(
+        endpoint: &xtra::Address<Endpoint>,
+        peer: PeerId,
+        (protocol, unsequenced_protocol): (&'static str, &'static str),
+        id: SessionId,
+        max_frame_size: usize,
+    ) -> anyhow::Result<Self> {
+        let (negotiated, stream) =
+            open_substream(endpoint, peer, vec![protocol, unsequenced_protocol]).await?;
+
+        let mut session = Self::new(
+            Framed::new(stream, LimitedJsonCodec::new(max_frame_size)),
+            negotiated,
+        );
+
+        if negotiated == protocol {
+            session.sequence = Some(Sequence::new(Some(id.0)));
+            session.resume = Some(Resume::Dialer {
+                endpoint: endpoint.clone(),
+                peer,
+                protocol,
+                max_frame_size,
+            });
+        }
+
+        Ok(session)
+    }
+
+    fn new(transport: Transport, protocol: &'static str) -> Self {
+        Self {
+            state: State::Connected(Box::new(transport)),
+            sequence: None,
+            resume: None,
+            resumptions: 0,
+            protocol,
+            received: VecDeque::new(),
+            transcript: None,
+            _marker: PhantomData,
+        }
+    }
+
+    fn with_sequence(mut self, sequence: Sequence) -> Self {
+        self.sequence = Some(sequence);
+        self
+    }
+
+    fn with_received(mut self, frame: Value) -> Self {
+        self.received.push_back(frame);
+        self
+    }
+
+    fn transport(&mut self) -> Result<&mut Transport, JsonCodecError> {
+        match &mut self.state {
+            State::Connected(transport) => Ok(transport.as_mut()),
+            _ => Err(closed()),
+        }
+    }
+
+    /// Drive the resumption of the session, if any, until the session is connected again.
+    fn poll_connected(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), JsonCodecError>> {
+        loop {
+            // The dialer may resume the session before we noticed that the old substream broke
+            if let Some(Resume::Listener { handovers, .. }) = &mut self.resume {
+                if let Poll::Ready(Some((transport, first))) = handovers.poll_next_unpin(cx) {
+                    tracing::debug!(protocol = %self.protocol, "Resuming session on new substream");
+
+                    self.received.push_back(first);
+                    self.state = State::Resuming(replay(transport, self.sent_frames()).boxed());
+                }
+            }
+
+            match &mut self.state {
+                State::Connected(_) => return Poll::Ready(Ok(())),
+                State::AwaitingResumption(timeout) => {
+                    ready!(timeout.poll_unpin(cx));
+
+                    self.state = State::Closed;
+                    return Poll::Ready(Err(JsonCodecError::Io(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!(
+                            "Dialer did not resume the session within {} seconds",
+                            RESUME_TIMEOUT.as_secs()
+                        ),
+                    ))));
+                }
+                State::Resuming(resuming) => match ready!(resuming.poll_unpin(cx)) {
+                    Ok(transport) => self.state = State::Connected(Box::new(transport)),
+                    Err(e) => {
+                        self.state = State::Closed;
+                        return Poll::Ready(Err(e));
+                    }
+                },
+                State::Closed => return Poll::Ready(Err(closed())),
+            }
+        }
+    }
+
+    /// Resume the session after its substream broke with `error`.
+    ///
+    /// Fails with `error` if the session cannot be resumed.
+    fn connection_lost(&mut self, error: JsonCodecError) -> Result<(), JsonCodecError> {
+        let resuming = match &self.resume {
+            Some(_) if self.resumptions == MAX_RESUMPTIONS => None,
+            Some(Resume::Dialer {
+                endpoint,
+                peer,
+                protocol,
+                max_frame_size,
+            }) => {
+                tracing::debug!(%peer, %protocol, "Substream broke, resuming session: {error}");
+
+                Some(State::Resuming(
+                    reopen(
+                        endpoint.clone(),
+                        *peer,
+                        *protocol,
+                        self.sent_frames(),
+                        *max_frame_size,
+                    )
+                    .boxed(),
+                ))
+            }
+            Some(Resume::Listener { .. }) => {
+                tracing::debug!(
+                    protocol = %self.protocol,
+                    "Substream broke, waiting for dialer to resume session: {error}"
+                );
+
+                Some(State::AwaitingResumption(
+                    tokio_extras::time::sleep(RESUME_TIMEOUT).boxed(),
+                ))
+            }
+            None => None,
+        };
+
+        match resuming {
+            Some(state) => {
+                self.state = state;
+                self.resumptions += 1;
+
+                Ok(())
+            }
+            None => {
+                self.state = State::Closed;
+
+                Err(error)
+            }
+        }
+    }
+
+    fn sent_frames(&self) -> Vec<Value> {
+        self.sequence
+            .as_ref()
+            .map(|sequence| sequence.sent.clone())
+            .unwrap_or_default()
+    }
+
+    fn capture(&mut self, direction: Direction, message: &Value) {
+        if self.transcript.is_none() {
+            self.transcript = Capture::start(message);
+        }
+
+        if let Some(transcript) = &mut self.transcript {
+            transcript.record(direction, message);
+        }
+    }
+}
+
+impl<Enc, Dec> Stream for Session<Enc, Dec>
+where
+    Dec: DeserializeOwned,
+{
+    type Item = Result<Dec, JsonCodecError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Some(frame) = this.received.pop_front() {
+                let message = match &mut this.sequence {
+                    Some(sequence) => match sequence.receive(frame) {
+                        Ok(Some(message)) => message,
+                        Ok(None) => continue,
+                        Err(e) => return Poll::Ready(Some(Err(e.into()))),
+                    },
+                    None => frame,
+                };
+
+                this.capture(Direction::Received, &message);
+                return Poll::Ready(Some(serde_json::from_value(message).map_err(Into::into)));
+            }
+
+            if matches!(this.state, State::Closed) {
+                return Poll::Ready(None);
+            }
+
+            if let Err(e) = ready!(this.poll_connected(cx)) {
+                return Poll::Ready(Some(Err(e)));
+            }
+
+            let transport = match this.transport() {
+                Ok(transport) => transport,
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            };
+
+            match ready!(transport.poll_next_unpin(cx)) {
+                Some(Ok(frame)) => this.received.push_back(frame),
+                Some(Err(e)) if is_connection_error(&e) => {
+                    if let Err(e) = this.connection_lost(e) {
+                        return Poll::Ready(Some(Err(e)));
+                    }
+                }
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => {
+                    if this.connection_lost(end_of_stream()).is_err() {
+                        return Poll::Ready(None);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<Enc, Dec> Sink<Enc> for Session<Enc, Dec>
+where
+    Enc: Serialize,
+{
+    type Error = JsonCodecError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+
+        loop {
+            ready!(this.poll_connected(cx))?;
+
+            match ready!(this.transport()?.poll_ready_unpin(cx)) {
+                Ok(()) => return Poll::Ready(Ok(())),
+                Err(e) => this.connection_lost(e)?,
+            }
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, message: Enc) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+
+        let message = serde_json::to_value(&message)?;
+        this.capture(Direction::Sent, &message);
+
+        let frame = match &mut this.sequence {
+            Some(sequence) => sequence.send(message)?,
+            // Without sequence numbers the receiver cannot drop injected duplicates
+            None => return this.transport()?.start_send_unpin(message),
+        };
+
+        let protocol = this.protocol;
+        chaos::send(Pin::new(this.transport()?), protocol, frame)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+
+        loop {
+            ready!(this.poll_connected(cx))?;
+
+            // A resumption replays the frames that were not flushed yet
+            match ready!(this.transport()?.poll_flush_unpin(cx)) {
+                Ok(()) => return Poll::Ready(Ok(())),
+                Err(e) => this.connection_lost(e)?,
+            }
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.get_mut().transport() {
+            Ok(transport) => transport.poll_close_unpin(cx),
+            Err(_) => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+/// Sequence numbers of a session and the frames sent in it.
+pub(crate) struct Sequence {
+    session: Option<String>,
+    last_sent: u64,
+    last_received: Option<u64>,
+    /// Replayed when the session is resumed
+    sent: Vec<Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Sequenced<T> {
+    seq: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    session: Option<String>,
+    message: T,
+}
+
+impl Sequence {
+    pub(crate) fn new(session: Option<String>) -> Self {
+        Self {
+            session,
+            last_sent: 0,
+            last_received: None,
+            sent: Vec::new(),
+        }
+    }
+
+    #[cfg(feature = "wire-fixtures")]
+    pub(crate) fn dialer(session: SessionId) -> Self {
+        Self::new(Some(session.0))
+    }
+
+    /// Number `message` and record it for replay.
+    pub(crate) fn send(&mut self, message: Value) -> Result<Value, serde_json::Error> {
+        self.last_sent += 1;
+
+        let frame = serde_json::to_value(Sequenced {
+            seq: self.last_sent,
+            session: self.session.clone(),
+            message,
+        })?;
+        self.sent.push(frame.clone());
+
+        Ok(frame)
+    }
+
+    /// The message of `frame`, or `None` if the message was already received.
+    pub(crate) fn receive(&mut self, frame: Value) -> Result<Option<Value>, serde_json::Error> {
+        let Sequenced { seq, message, .. } = serde_json::from_value::<Sequenced<Value>>(frame)?;
+
+        if matches!(self.last_received, Some(last_received) if seq <= last_received) {
+            tracing::debug!(%seq, "Dropping message that was already received");
+            return Ok(None);
+        }
+
+        self.last_received = Some(seq);
+
+        Ok(Some(message))
+    }
+}
+
+pub(crate) fn is_sequenced(frame: &Value) -> bool {
+    match frame.as_object() {
+        Some(object) => {
+            let n_keys = if object.contains_key("session") { 3 } else { 2 };
+
+            object.len() == n_keys && object.contains_key("seq") && object.contains_key("message")
+        }
+        None => false,
+    }
+}
+
+async fn open_substream(
+    endpoint: &xtra::Address<Endpoint>,
+    peer: PeerId,
+    protocols: Vec<&'static str>,
+) -> anyhow::Result<(&'static str, Substream)> {
+    endpoint
+        .send(OpenSubstream::multiple_protocols(peer, protocols))
+        .await
+        .context("Endpoint is disconnected")?
+        .context("No connection to peer")?
+        .await
+        .context("Failed to open substream")
+}
+
+/// Open a new substream for the session of the dialer and replay `frames` on it.
+async fn reopen(
+    endpoint: xtra::Address<Endpoint>,
+    peer: PeerId,
+    protocol: &'static str,
+    frames: Vec<Value>,
+    max_frame_size: usize,
+) -> Result<Transport, JsonCodecError> {
+    let mut error = None;
+
+    for attempt in 1..=RESUME_ATTEMPTS {
+        tokio_extras::time::sleep(RESUME_BACKOFF * attempt).await;
+
+        let resumed = async {
+            let (_, stream) = open_substream(&endpoint, peer, vec![protocol]).await?;
+            let transport = Framed::new(stream, LimitedJsonCodec::new(max_frame_size));
+
+            anyhow::Ok(replay(transport, frames.clone()).await?)
+        };
+
+        match resumed.await {
+            Ok(transport) => return Ok(transport),
+            Err(e) => {
+                tracing::debug!(%peer, %protocol, %attempt, "Failed to resume session: {e:#}");
+                error = Some(e);
+            }
+        }
+    }
+
+    Err(JsonCodecError::Io(io::Error::new(
+        io::ErrorKind::ConnectionAborted,
+        format!(
+            "Failed to resume session after {RESUME_ATTEMPTS} attempts: {:#}",
+            error.expect("at least one attempt")
+        ),
+    )))
+}
+
+async fn replay(mut transport: Transport, frames: Vec<Value>) -> Result<Transport, JsonCodecError> {
+    for frame in frames {
+        transport.feed(frame).await?;
+    }
+    transport.flush().await?;
+
+    Ok(transport)
+}
+
+/// Whether `error` means that the substream broke, rather than that the peer sent an invalid frame.
+fn is_connection_error(error: &JsonCodecError) -> bool {
+    matches!(error, JsonCodecError::Io(_)) && FrameTooLarge::from_codec_error(error).is_none()
+}
+
+fn end_of_stream() -> JsonCodecError {
+    JsonCodecError::Io(io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "End of stream",
+    ))
+}
+
+fn closed() -> JsonCodecError {
+    JsonCodecError::Io(io::Error::new(
+        io::ErrorKind::NotConnected,
+        "Session is closed",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn given_resumed_session_replays_messages_then_only_new_messages_are_received() {
+        let mut dialer = Sequence::new(Some(SessionId::new("order").0));
+        let mut listener = Sequence::new(None);
+
+        let first_attempt = vec![
+            dialer.send(json!({ "Propose": 1 })).unwrap(),
+            dialer.send(json!("Confirm")).unwrap(),
+        ];
+        assert_eq!(
+            receive_all(&mut listener, first_attempt),
+            vec![json!({ "Propose": 1 }), json!("Confirm")]
+        );
+
+        // The resumed substream carries all messages of the session, followed by the new one
+        dialer.send(json!({ "Propose": 2 })).unwrap();
+        let resumed = dialer.sent.clone();
+
+        assert_eq!(
+            receive_all(&mut listener, resumed),
+            vec![json!({ "Propose": 2 })]
+        );
+    }
+
+    #[test]
+    fn given_duplicated_frame_then_message_is_received_once() {
+        let mut dialer = Sequence::new(Some(SessionId::new("order").0));
+        let mut listener = Sequence::new(None);
+
+        let frame = dialer.send(json!({ "Propose": 1 })).unwrap();
+
+        assert_eq!(
+            receive_all(&mut listener, vec![frame.clone(), frame]),
+            vec![json!({ "Propose": 1 })]
+        );
+    }
+
+    #[test]
+    fn sequenced_frames_carry_the_session_id() {
+        let id = SessionId::new("order");
+        let mut dialer = Sequence::new(Some(id.0.clone()));
+
+        let frame = dialer.send(json!("Confirm")).unwrap();
+
+        assert!(is_sequenced(&frame));
+        assert_eq!(
+            frame,
+            json!({ "seq": 1, "session": id.0, "message": "Confirm" })
+        );
+    }
+
+    #[test]
+    fn unsequenced_messages_are_not_mistaken_for_frames() {
+        assert!(!is_sequenced(&json!({ "Propose": { "seq": 1 } })));
+        assert!(!is_sequenced(
+            &json!({ "seq": 1, "message": "Confirm", "other": 1 })
+        ));
+        assert!(is_sequenced(&json!({ "seq": 1, "message": "Confirm" })));
+    }
+
+    #[test]
+    fn sessions_of_the_same_run_get_different_ids() {
+        assert_ne!(SessionId::new("order"), SessionId::new("order"));
+    }
+
+    fn receive_all(sequence: &mut Sequence, frames: Vec<Value>) -> Vec<Value> {
+        frames
+            .into_iter()
+            .filter_map(|frame| sequence.receive(frame).unwrap())
+            .collect()
+    }
+}
@@ -0,0 +1,221 @@
+//! Capture of all messages of a single protocol run for debugging.
+//!
+//! Trace logging of all protocols is too noisy to debug a single misbehaving order. Instead, a
+//! transcript is enabled for a key, e.g. an order id, together with the file it is written to. A
+//! [`Session`](crate::sequenced::Session) starts capturing as soon as a message sent or received
+//! in it mentions an enabled key. From then on every message of the session is appended to the
+//! file as a JSON line, with the full payload and secrets redacted.
+//!
+//! The transcript is disabled once the session that captured it is dropped, i.e. after the
+//! protocol run completed.
+
+use conquer_once::Lazy;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+/// Replaces the values of redacted fields
+const REDACTED: &str = "<redacted>";
+
+/// Enabled transcripts by key
+static ENABLED: Lazy<Mutex<HashMap<String, PathBuf>>> = Lazy::new(Default::default);
+
+/// Capture the next protocol run that mentions `key` into the file at `path`.
+///
+/// Replaces the file of a transcript that was already enabled for `key`.
+pub fn enable(key: impl ToString, path: PathBuf) {
+    enabled().insert(key.to_string(), path);
+}
+
+/// Stop capturing protocol runs that mention `key`.
+///
+/// Returns `false` if no transcript was enabled for `key`.
+pub fn disable(key: &str) -> bool {
+    enabled().remove(key).is_some()
+}
+
+/// All enabled transcripts and the files they are written to.
+pub fn list() -> Vec<(String, PathBuf)> {
+    enabled()
+        .iter()
+        .map(|(key, path)| (key.clone(), path.clone()))
+        .collect()
+}
+
+/// Whether a transcript is enabled for any key.
+pub(crate) fn is_any_enabled() -> bool {
+    !enabled().is_empty()
+}
+
+fn enabled() -> std::sync::MutexGuard<'static, HashMap<String, PathBuf>> {
+    ENABLED.lock().expect("transcripts lock not to be poisoned")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Direction {
+    Sent,
+    Received,
+}
+
+/// Captures the messages of one session into the transcript of `key`.
+pub(crate) struct Capture {
+    key: String,
+    path: PathBuf,
+    file: File,
+}
+
+impl Capture {
+    /// Start capturing if `message` mentions the key of an enabled transcript.
+    pub(crate) fn start(message: &Value) -> Option<Self> {
+        let (key, path) = {
+            let enabled = enabled();
+            if enabled.is_empty() {
+                return None;
+            }
+
+            enabled
+                .iter()
+                .find(|(key, _)| mentions(message, key))
+                .map(|(key, path)| (key.clone(), path.clone()))?
+        };
+
+        let file = match OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                tracing::warn!(%key, path = %path.display(), "Failed to open transcript: {e:#}");
+                return None;
+            }
+        };
+
+        tracing::info!(%key, path = %path.display(), "Capturing protocol transcript");
+
+        Some(Self { key, path, file })
+    }
+
+    pub(crate) fn record(&mut self, direction: Direction, message: &Value) {
+        #[derive(Serialize)]
+        struct Entry<'a> {
+            timestamp: u64,
+            direction: Direction,
+            message: &'a Value,
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        let message = redact(message.clone());
+
+        let result = serde_json::to_vec(&Entry {
+            timestamp,
+            direction,
+            message: &message,
+        })
+        .map_err(anyhow::Error::from)
+        .and_then(|mut line| {
+            line.push(b'\n');
+            self.file.write_all(&line)?;
+            Ok(())
+        });
+
+        if let Err(e) = result {
+            tracing::warn!(key = %self.key, "Failed to write protocol transcript: {e:#}");
+        }
+    }
+}
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        // Only disable the transcript if it was not re-enabled with another file in the meantime
+        let mut enabled = enabled();
+        if enabled.get(&self.key) == Some(&self.path) {
+            enabled.remove(&self.key);
+        }
+
+        tracing::info!(key = %self.key, path = %self.path.display(), "Protocol transcript completed");
+    }
+}
+
+/// Whether any string within `value` equals `key`.
+fn mentions(value: &Value, key: &str) -> bool {
+    match value {
+        Value::String(string) => string == key,
+        Value::Array(values) => values.iter().any(|value| mentions(value, key)),
+        Value::Object(object) => object.values().any(|value| mentions(value, key)),
+        Value::Null | Value::Bool(_) | Value::Number(_) => false,
+    }
+}
+
+/// Replace the values of all fields that hold secrets.
+fn redact(value: Value) -> Value {
+    match value {
+        Value::Array(values) => Value::Array(values.into_iter().map(redact).collect()),
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .map(|(name, value)| {
+                    if is_secret(&name) {
+                        (name, Value::String(REDACTED.to_owned()))
+                    } else {
+                        (name, redact(value))
+                    }
+                })
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+fn is_secret(field: &str) -> bool {
+    let field = field.to_lowercase();
+
+    field == "sk" || field.ends_with("_sk") || field.contains("secret")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn secrets_are_redacted_in_nested_messages() {
+        let message = json!({
+            "RolloverMsg": {
+                "Msg2": {
+                    "revocation_sk": "0101",
+                    "publish_pk": "0202",
+                    "nested": [{ "secret": "0303" }]
+                }
+            }
+        });
+
+        assert_eq!(
+            redact(message),
+            json!({
+                "RolloverMsg": {
+                    "Msg2": {
+                        "revocation_sk": REDACTED,
+                        "publish_pk": "0202",
+                        "nested": [{ "secret": REDACTED }]
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn key_is_found_anywhere_in_message() {
+        let message = json!({ "Propose": { "order_id": "abc", "timestamp": 1 } });
+
+        assert!(mentions(&message, "abc"));
+        assert!(!mentions(&message, "ab"));
+    }
+}
@@ -0,0 +1,97 @@
+//! Recorded protocol messages, for tests of the protocols built on top of this crate.
+//!
+//! A directory of fixtures holds what peers running one version of a protocol put on the wire.
+//! Fixtures must not be changed once recorded: a fixture that no longer decodes means that peers
+//! of that version can no longer talk to us. Fixtures named `*_legacy.json` are messages of older
+//! releases of the same protocol version, before optional fields were added.
+//!
+//! Fixtures are decoded and encoded like the frames of a [`Session`](crate::sequenced::Session)
+//! received from and sent to a peer. Fixtures of protocol versions with sequence numbers are
+//! recorded as the first message of the dialer's session [`SESSION`].
+
+use crate::sequenced::is_sequenced;
+use crate::sequenced::Sequence;
+use crate::sequenced::SessionId;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Session id of the recorded messages of protocol versions with sequence numbers.
+///
+/// The listener does not interpret session ids, so the recorded id stays valid although dialers now
+/// add a nonce to the order id.
+pub const SESSION: &str = "7d6b3a52-1c4e-4d3f-9a0b-2f5e8c9d1a47";
+
+/// The recorded messages of one protocol version.
+pub struct Fixtures {
+    dir: &'static str,
+    sequenced: bool,
+}
+
+impl Fixtures {
+    /// Fixtures of a protocol version without sequence numbers in `dir`, relative to the crate.
+    pub const fn new(dir: &'static str) -> Self {
+        Self {
+            dir,
+            sequenced: false,
+        }
+    }
+
+    /// Fixtures of a protocol version with sequence numbers in `dir`, relative to the crate.
+    pub const fn sequenced(dir: &'static str) -> Self {
+        Self {
+            dir,
+            sequenced: true,
+        }
+    }
+
+    /// Decode the recorded message `name`.
+    pub fn decode<T: DeserializeOwned>(&self, name: &str) -> T {
+        let frame = self.read(name);
+
+        let message = if is_sequenced(&frame) {
+            Sequence::new(None)
+                .receive(frame)
+                .unwrap_or_else(|e| panic!("Failed to decode {}/{name}: {e}", self.dir))
+                .unwrap_or_else(|| panic!("{}/{name} is a duplicate", self.dir))
+        } else {
+            frame
+        };
+
+        serde_json::from_value(message)
+            .unwrap_or_else(|e| panic!("Failed to decode {}/{name}: {e}", self.dir))
+    }
+
+    /// Decode the recorded message `name` and check that we encode it exactly as recorded.
+    pub fn round_trip<T: Serialize + DeserializeOwned>(&self, name: &str) -> T {
+        let message = self.decode::<T>(name);
+
+        let encoded = serde_json::to_value(&message)
+            .unwrap_or_else(|e| panic!("Failed to encode {}/{name}: {e}", self.dir));
+        let encoded = if self.sequenced {
+            Sequence::dialer(SessionId::recorded(SESSION))
+                .send(encoded)
+                .unwrap_or_else(|e| panic!("Failed to encode {}/{name}: {e}", self.dir))
+        } else {
+            encoded
+        };
+
+        assert_eq!(
+            encoded,
+            self.read(name),
+            "{}/{name} is not encoded as recorded",
+            self.dir
+        );
+
+        message
+    }
+
+    fn read(&self, name: &str) -> Value {
+        let path = format!("{}/{name}", self.dir);
+        let recorded =
+            std::fs::read(&path).unwrap_or_else(|e| panic!("Failed to read fixture {path}: {e}"));
+
+        serde_json::from_slice(&recorded)
+            .unwrap_or_else(|e| panic!("Fixture {path} is not valid JSON: {e}"))
+    }
+}
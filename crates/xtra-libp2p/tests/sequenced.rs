@@ -0,0 +1,199 @@
+use crate::util::make_node;
+use crate::util::Node;
+use anyhow::Context as _;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::SinkExt;
+use futures::StreamExt;
+use libp2p_core::Multiaddr;
+use serde::Deserialize;
+use serde::Serialize;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio_extras::FutureExt;
+use xtra::message_channel::MessageChannel;
+use xtra::spawn::TokioGlobalSpawnExt;
+use xtra::Actor;
+use xtra::Context;
+use xtra_libp2p::sequenced::Session;
+use xtra_libp2p::sequenced::SessionId;
+use xtra_libp2p::sequenced::Sessions;
+use xtra_libp2p::Connect;
+use xtra_libp2p::Disconnect;
+use xtra_libp2p::ListenOn;
+use xtra_libp2p::NewInboundSubstream;
+use xtra_productivity::xtra_productivity;
+
+mod util;
+
+const PROTOCOL: &str = "/counter/2.0.0";
+const UNSEQUENCED_PROTOCOL: &str = "/counter/1.0.0";
+const MAX_FRAME_SIZE: usize = 1024;
+
+/// Covers the backoff of all attempts to resume a session
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[tokio::test]
+async fn given_connection_dropped_mid_session_then_session_resumes_without_duplicates() {
+    let counter = Counter::default();
+    let received = counter.received.clone();
+    let counter = counter.create(None).spawn_global();
+    let (alice, bob, alice_address) = alice_and_bob([
+        (PROTOCOL, counter.clone().into()),
+        (UNSEQUENCED_PROTOCOL, counter.into()),
+    ])
+    .await;
+
+    let mut session = Session::<Message, Message>::dial(
+        &bob.endpoint,
+        alice.peer_id,
+        (PROTOCOL, UNSEQUENCED_PROTOCOL),
+        SessionId::new("counter"),
+        MAX_FRAME_SIZE,
+    )
+    .await
+    .unwrap();
+
+    session.send(Message::Count(1)).await.unwrap();
+    assert_eq!(receive(&mut session).await.unwrap(), Message::Ack(1));
+
+    bob.endpoint.send(Disconnect(alice.peer_id)).await.unwrap();
+    bob.endpoint
+        .send(Connect(alice_address))
+        .await
+        .unwrap()
+        .unwrap();
+
+    // Both sides replay their messages on the new substream
+    session.send(Message::Count(2)).await.unwrap();
+    assert_eq!(receive(&mut session).await.unwrap(), Message::Ack(2));
+
+    assert_eq!(
+        *received.lock().unwrap(),
+        vec![Message::Count(1), Message::Count(2)]
+    );
+}
+
+#[tokio::test]
+async fn given_listener_without_sequence_numbers_then_session_falls_back() {
+    let counter = Counter::default();
+    let received = counter.received.clone();
+    let (alice, bob, _) = alice_and_bob([(
+        UNSEQUENCED_PROTOCOL,
+        counter.create(None).spawn_global().into(),
+    )])
+    .await;
+
+    let mut session = Session::<Message, Message>::dial(
+        &bob.endpoint,
+        alice.peer_id,
+        (PROTOCOL, UNSEQUENCED_PROTOCOL),
+        SessionId::new("counter"),
+        MAX_FRAME_SIZE,
+    )
+    .await
+    .unwrap();
+
+    session.send(Message::Count(1)).await.unwrap();
+    session.send(Message::Count(1)).await.unwrap();
+
+    assert_eq!(receive(&mut session).await.unwrap(), Message::Ack(1));
+    assert_eq!(receive(&mut session).await.unwrap(), Message::Ack(1));
+    assert_eq!(
+        *received.lock().unwrap(),
+        vec![Message::Count(1), Message::Count(1)]
+    );
+}
+
+async fn receive(session: &mut Session<Message, Message>) -> Result<Message> {
+    let message = session
+        .next()
+        .timeout(RESPONSE_TIMEOUT, || tracing::debug_span!("receive message"))
+        .await
+        .context("No message in time")?
+        .context("End of stream")??;
+
+    Ok(message)
+}
+
+async fn alice_and_bob<const N: usize>(
+    alice_inbound_substream_handlers: [(&'static str, MessageChannel<NewInboundSubstream, ()>); N],
+) -> (Node, Node, Multiaddr) {
+    let port = rand::random::<u16>();
+    let alice = make_node(alice_inbound_substream_handlers);
+    let bob = make_node([]);
+
+    alice
+        .endpoint
+        .send(ListenOn(format!("/memory/{port}").parse().unwrap()))
+        .await
+        .unwrap();
+
+    let alice_address = format!("/memory/{port}/p2p/{}", alice.peer_id)
+        .parse::<Multiaddr>()
+        .unwrap();
+    bob.endpoint
+        .send(Connect(alice_address.clone()))
+        .await
+        .unwrap()
+        .unwrap();
+
+    (alice, bob, alice_address)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum Message {
+    Count(u32),
+    Ack(u32),
+}
+
+/// Acknowledges every count it receives
+#[derive(Default)]
+struct Counter {
+    sessions: Sessions,
+    received: Arc<Mutex<Vec<Message>>>,
+}
+
+#[xtra_productivity]
+impl Counter {
+    async fn handle(&mut self, msg: NewInboundSubstream, ctx: &mut Context<Self>) {
+        let NewInboundSubstream { peer_id, stream } = msg;
+        let sessions = self.sessions.clone();
+        let received = self.received.clone();
+
+        tokio_extras::spawn_fallible(
+            &ctx.address().unwrap(),
+            async move {
+                let mut session = match sessions
+                    .accept::<Message, Message>(peer_id, stream, PROTOCOL, MAX_FRAME_SIZE)
+                    .await?
+                {
+                    Some(session) => session,
+                    None => return Ok(()),
+                };
+
+                while let Some(message) = session.next().await {
+                    let message = message?;
+                    received.lock().unwrap().push(message.clone());
+
+                    if let Message::Count(n) = message {
+                        session.send(Message::Ack(n)).await?;
+                    }
+                }
+
+                anyhow::Ok(())
+            },
+            move |e| async move {
+                tracing::warn!(%peer_id, "Counter session failed: {e:#}");
+            },
+        );
+    }
+}
+
+#[async_trait]
+impl Actor for Counter {
+    type Stop = ();
+
+    async fn stopped(self) -> Self::Stop {}
+}
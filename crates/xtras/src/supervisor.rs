@@ -6,7 +6,11 @@ use std::fmt;
 use std::ops::ControlFlow;
 use std::panic::AssertUnwindSafe;
 use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::Weak;
 use std::time::Duration;
+use std::time::SystemTime;
 use tracing::Instrument;
 use xtra::Address;
 use xtra::Context;
@@ -18,8 +22,15 @@ pub struct Supervisor<T, R> {
     ctor: Box<dyn Fn() -> T + Send + 'static>,
     restart_policy: AsyncClosure<R>,
     metrics: Metrics,
+    status: Arc<Mutex<SupervisorStatus>>,
 }
 
+/// Status of all supervisors in this process that are still running.
+///
+/// Supervisors register themselves upon construction and are dropped from the registry once they
+/// exit.
+static REGISTRY: Mutex<Vec<Weak<Mutex<SupervisorStatus>>>> = Mutex::new(Vec::new());
+
 type AsyncClosure<R> = Box<
     dyn for<'a> FnMut(&'a R) -> Pin<Box<dyn Future<Output = bool> + 'a + Send + Sync>>
         + Send
@@ -53,7 +64,7 @@ where
     })
 }
 
-#[derive(Default, Clone, Copy, Debug)]
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Metrics {
     /// How many times the supervisor spawned an instance of the actor.
     pub num_spawns: u64,
@@ -61,6 +72,48 @@ pub struct Metrics {
     pub num_panics: u64,
 }
 
+/// Status of a supervisor and the actor it is supervising.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SupervisorStatus {
+    /// Name of the supervised actor.
+    pub actor: String,
+    pub metrics: Metrics,
+    /// When the actor was last restarted, `None` if it was never restarted.
+    pub last_restart_at: Option<SystemTime>,
+    /// Whether an instance of the actor is currently running.
+    ///
+    /// The actor is not running while the restart policy decides whether to restart it.
+    pub running: bool,
+}
+
+impl SupervisorStatus {
+    fn register(actor: String) -> Arc<Mutex<Self>> {
+        let status = Arc::new(Mutex::new(Self {
+            actor,
+            metrics: Metrics::default(),
+            last_restart_at: None,
+            running: false,
+        }));
+
+        let mut registry = REGISTRY.lock().expect("registry not to be poisoned");
+        registry.retain(|status| status.strong_count() > 0);
+        registry.push(Arc::downgrade(&status));
+
+        status
+    }
+}
+
+/// Snapshot of the status of all supervisors in this process that did not exit yet.
+pub fn statuses() -> Vec<SupervisorStatus> {
+    REGISTRY
+        .lock()
+        .expect("registry not to be poisoned")
+        .iter()
+        .filter_map(Weak::upgrade)
+        .map(|status| status.lock().expect("status not to be poisoned").clone())
+        .collect()
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct UnitReason {}
 
@@ -89,13 +142,31 @@ where
     /// should be restarted, set [`xtra::Actor::Stop`] to a more descriptive value and use
     /// [`Actor::with_policy`].
     pub fn new(ctor: impl (Fn() -> T) + Send + 'static) -> (Self, Address<T>) {
-        let (address, context) = Context::new(None);
+        Self::with_mailbox(ctor, None)
+    }
+
+    /// Like [`Supervisor::new`], but the mailbox of the actor holds at most `capacity` messages.
+    ///
+    /// Once the mailbox is full, senders wait until the actor made room for their message.
+    pub fn with_capacity(
+        ctor: impl (Fn() -> T) + Send + 'static,
+        capacity: usize,
+    ) -> (Self, Address<T>) {
+        Self::with_mailbox(ctor, Some(capacity))
+    }
+
+    fn with_mailbox(
+        ctor: impl (Fn() -> T) + Send + 'static,
+        capacity: Option<usize>,
+    ) -> (Self, Address<T>) {
+        let (address, context) = Context::new(capacity);
 
         let supervisor = Self {
             context,
             ctor: Box::new(ctor),
             restart_policy: always_restart(),
             metrics: Metrics::default(),
+            status: SupervisorStatus::register(T::name()),
         };
 
         (supervisor, address)
@@ -124,6 +195,7 @@ where
             ctor: Box::new(ctor),
             restart_policy,
             metrics: Metrics::default(),
+            status: SupervisorStatus::register(T::name()),
         };
 
         (supervisor, address)
@@ -147,6 +219,7 @@ where
 
         loop {
             if !self.context.running {
+                self.update_status(|status| status.running = false);
                 let reason = actor.stopped().await.into();
                 let restart = (self.restart_policy)(&reason).await;
                 let err = anyhow::Error::new(reason);
@@ -189,6 +262,7 @@ where
                     tracing::info!(actor = %&actor_name, %reason, restart = true, "Actor panicked");
 
                     self.metrics.num_panics += 1;
+                    self.update_status(|status| status.running = false);
                     actor = self.spawn_new().await;
                 }
             }
@@ -202,17 +276,23 @@ where
         let mut actor = (self.ctor)();
         self.context.running = true;
         actor.started(&mut self.context).await;
+
+        let metrics = self.metrics;
+        self.update_status(|status| {
+            if metrics.num_spawns > 1 {
+                status.last_restart_at = Some(SystemTime::now());
+            }
+            status.metrics = metrics;
+            status.running = true;
+        });
+
         actor
     }
-}
 
-/// Return the metrics tracked by this supervisor.
-///
-/// Currently private because it is a feature only used for testing. If we want to expose metrics
-/// about the supervisor, we should look into creating a [`tracing::Subscriber`] that processes the
-/// events we are emitting.
-#[derive(Debug)]
-struct GetMetrics;
+    fn update_status(&self, update: impl FnOnce(&mut SupervisorStatus)) {
+        update(&mut self.status.lock().expect("status not to be poisoned"));
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -322,6 +402,38 @@ mod tests {
         assert_eq!(metrics.num_panics, 1, "after panic, should have 1 panic");
     }
 
+    #[tokio::test]
+    async fn supervisor_reports_status_until_exit() {
+        let _guard = tracing_subscriber::fmt().with_test_writer().set_default();
+
+        let (supervisor, address) =
+            Supervisor::with_policy(|| StatusActor, always_restart::<io::Error>());
+        let status = || {
+            statuses()
+                .into_iter()
+                .find(|status| status.actor == StatusActor::name())
+        };
+
+        #[allow(clippy::disallowed_methods)]
+        let task = tokio::spawn(supervisor.run());
+
+        address.send(Shutdown).await.unwrap();
+        address.send(SayHello("World".to_owned())).await.unwrap();
+
+        let status = status().expect("supervisor to be registered");
+        assert_eq!(status.metrics.num_spawns, 2);
+        assert!(status.last_restart_at.is_some());
+        assert!(status.running);
+
+        drop(address);
+        task.await.unwrap();
+
+        assert!(
+            status().is_none(),
+            "supervisor should be unregistered after exit"
+        );
+    }
+
     #[tokio::test]
     async fn supervisor_can_supervise_unit_actor() {
         let _guard = tracing_subscriber::fmt().with_test_writer().set_default();
@@ -332,6 +444,25 @@ mod tests {
         task.await;
     }
 
+    #[tokio::test]
+    async fn bounded_mailbox_makes_senders_wait() {
+        let (_supervisor, address) = Supervisor::with_capacity(|| UnitActor, 1);
+
+        assert!(address
+            .send(SayHello("World".to_owned()))
+            .split_receiver()
+            .now_or_never()
+            .is_some());
+        assert!(
+            address
+                .send(SayHello("World".to_owned()))
+                .split_receiver()
+                .now_or_never()
+                .is_none(),
+            "actor did not run, hence the mailbox should be full"
+        );
+    }
+
     /// An actor that can be shutdown remotely.
     struct RemoteShutdown;
 
@@ -360,6 +491,29 @@ mod tests {
         }
     }
 
+    /// Same as [`RemoteShutdown`], used by a single test to have its own entry in the registry.
+    struct StatusActor;
+
+    #[async_trait]
+    impl xtra::Actor for StatusActor {
+        type Stop = io::Error;
+
+        async fn stopped(self) -> Self::Stop {
+            io::Error::new(io::ErrorKind::Other, "unknown")
+        }
+    }
+
+    #[xtra_productivity]
+    impl StatusActor {
+        fn handle(&mut self, _: Shutdown, ctx: &mut Context<Self>) {
+            ctx.stop_self()
+        }
+
+        fn handle(&mut self, msg: SayHello) -> String {
+            format!("Hello {}", msg.0)
+        }
+    }
+
     struct PanickingActor;
 
     #[derive(Debug)]
@@ -389,4 +543,11 @@ mod tests {
 
         async fn stopped(self) -> Self::Stop {}
     }
+
+    #[xtra_productivity]
+    impl UnitActor {
+        fn handle(&mut self, msg: SayHello) -> String {
+            format!("Hello {}", msg.0)
+        }
+    }
 }
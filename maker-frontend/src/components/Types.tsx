@@ -63,6 +63,8 @@ export interface Cfd {
     expiry_timestamp?: number;
 
     counterparty: string;
+
+    pending_age_secs?: number;
 }
 
 export interface CfdDetails {
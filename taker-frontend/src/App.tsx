@@ -18,6 +18,7 @@ import {
     Cfd,
     ConnectionStatus,
     IdentityInfo,
+    InstanceStatus,
     intoCfd,
     intoMakerOffer,
     LeverageDetails,
@@ -177,6 +178,7 @@ export const App = () => {
     let cfds = cfdsOrUndefined ? cfdsOrUndefined! : [];
     const connectedToMakerOrUndefined = useLatestEvent<ConnectionStatus>(source, "maker_status");
     const makerCompatibilityOrUndefined = useLatestEvent<MakerCompatibility>(source, "maker_compatibility");
+    const instanceStatusOrUndefined = useLatestEvent<InstanceStatus>(source, "instance_status");
 
     let incompatible = false;
     if (makerCompatibilityOrUndefined) {
@@ -231,6 +233,22 @@ export const App = () => {
         }
     }, [toast, connectedToMakerOrUndefined]);
 
+    useEffect(() => {
+        const id = "instance-fenced-toast";
+        if (instanceStatusOrUndefined?.status === "fenced" && !toast.isActive(id)) {
+            toast({
+                id,
+                status: "error",
+                isClosable: true,
+                duration: null,
+                position: "bottom",
+                title: "Another taker took over!",
+                description:
+                    "Another taker with the same seed connected to the maker. Make sure only one taker per seed is running, then restart this one.",
+            });
+        }
+    }, [toast, instanceStatusOrUndefined]);
+
     const {
         isOpen: outdatedWarningIsVisible,
         onClose: onCloseOutdatedWarning,
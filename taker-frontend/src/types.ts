@@ -11,9 +11,16 @@ export interface WalletInfo {
     address: string;
     last_updated_at: number;
     transactions: Transaction[];
+    pending_deposits: PendingDeposit[];
     managed_wallet: boolean;
 }
 
+export interface PendingDeposit {
+    txid: string;
+    amount: number;
+    estimated_confirmation_blocks?: number;
+}
+
 export interface Transaction {
     txid: string;
     received: number;
@@ -34,6 +41,7 @@ export function unixTimestampToDate(unixTimestamp: number): Date {
 export interface MakerOffer {
     id: string;
     contract_symbol: string;
+    quanto_multiplier?: string; // BTC per unit of the quote currency, unset for inverse contracts
     // this is the maker's position
     position: Position;
     price: number;
@@ -46,6 +54,7 @@ export interface MakerOffer {
     funding_rate_annualized_percent: number; // e.g. "18.5" (does not include % char)
     funding_rate_hourly_percent: number; // e.g. "0.002345" (does not include % char)
     creation_timestamp: number;
+    cached_at?: number; // set while the offer is shown from the cache because the maker is unreachable
 }
 
 export interface LeverageDetails {
@@ -79,6 +88,7 @@ export interface Cfd {
 
     leverage: number;
     contract_symbol: string;
+    quanto_multiplier?: string;
     position: Position;
     liquidation_price: number;
 
@@ -98,6 +108,22 @@ export interface Cfd {
     counterparty: string;
 
     accumulated_fees: number;
+
+    queued_intent?: QueuedIntent;
+
+    maker_settlement_proposal?: SettlementPriceCheck;
+}
+
+export interface SettlementPriceCheck {
+    proposed: string;
+    quoted: string;
+    tolerance?: string;
+}
+
+export interface QueuedIntent {
+    action: { type: "settle" | "rollover"; payout_address?: string };
+    expires_at: number;
+    status: string;
 }
 
 export function isClosed(cfd: Cfd): boolean {
@@ -305,3 +331,7 @@ export interface IdentityInfo {
 export interface MakerCompatibility {
     unsupported_protocols?: string[];
 }
+
+export interface InstanceStatus {
+    status: "active" | "fenced";
+}